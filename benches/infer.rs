@@ -0,0 +1,115 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use drivel::{infer_schema_from_iter, InferenceOptions};
+use serde_json::json;
+
+/// Telemetry records with `width` metrics each, some of them only reported now and then.
+fn telemetry(n_records: usize, width: usize) -> Vec<serde_json::Value> {
+    (0..n_records)
+        .map(|n| {
+            let mut record = serde_json::Map::new();
+            record.insert("host".to_owned(), json!(format!("host-{}", n % 20)));
+            record.insert("timestamp".to_owned(), json!("2024-03-01T12:00:00Z"));
+            for metric in 0..width {
+                if metric % 10 == 0 && n % 3 == 0 {
+                    continue;
+                }
+                let value = match metric % 3 {
+                    0 => json!(n * metric),
+                    1 => json!((n * metric) as f64 / 7.0),
+                    _ => json!(format!("state-{}", (n + metric) % 5)),
+                };
+                record.insert(format!("metric_{}", metric), value);
+            }
+            serde_json::Value::Object(record)
+        })
+        .collect()
+}
+
+/// Orders with nested customers and line items, whose strings are sampled on every merge.
+fn orders(n_records: usize) -> Vec<serde_json::Value> {
+    (0..n_records)
+        .map(|n| {
+            let tier = ["bronze", "silver", "gold"][n % 3];
+            json!({
+                "id": format!("order-{}", n),
+                "customer": {
+                    "name": format!("customer {}", n % 500),
+                    "email": format!("customer{}@example.com", n % 500),
+                    "tier": tier,
+                },
+                "items": (0..n % 5).map(|item| json!({
+                    "sku": format!("SKU-{:05}", (n + item) % 1000),
+                    "quantity": item + 1,
+                    "price": (n % 97) as f64 + 0.99,
+                    "note": if item % 2 == 0 { json!(null) } else { json!(format!("note {}", n)) },
+                })).collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+/// Events of short strings, most of them of no particular format, like those of a log.
+fn events(n_records: usize) -> Vec<serde_json::Value> {
+    (0..n_records)
+        .map(|n| {
+            let level = ["info", "warn", "error"][n % 3];
+            let region = ["eu-west-1", "us-east-1", "ap-south-1"][n % 3];
+            json!({
+                "level": level,
+                "code": format!("E{:04}", n % 300),
+                "user": format!("user_{}", n % 1000),
+                "region": region,
+                "status": format!("{}", 200 + n % 5),
+                "version": format!("v{}.{}", n % 3, n % 10),
+                "host": format!("web-{}.internal", n % 40),
+                "at": format!("2024-03-{:02}", n % 28 + 1),
+            })
+        })
+        .collect()
+}
+
+fn infer_short_strings(c: &mut Criterion) {
+    let options = InferenceOptions::default();
+    let records = events(50_000);
+    c.bench_function("infer short strings", |b| {
+        b.iter_batched(
+            || records.clone(),
+            |records| black_box(infer_schema_from_iter(records, &options)),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn infer_nested_records(c: &mut Criterion) {
+    let options = InferenceOptions::default();
+    let records = orders(20_000);
+    c.bench_function("infer nested records", |b| {
+        b.iter_batched(
+            || records.clone(),
+            |records| black_box(infer_schema_from_iter(records, &options)),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn infer_wide_records(c: &mut Criterion) {
+    let options = InferenceOptions::default();
+    for width in [10, 500] {
+        let records = telemetry(2_000, width);
+        c.bench_function(&format!("infer {} fields", width), |b| {
+            b.iter_batched(
+                || records.clone(),
+                |records| black_box(infer_schema_from_iter(records, &options)),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+}
+
+criterion_group!(
+    benches,
+    infer_wide_records,
+    infer_nested_records,
+    infer_short_strings
+);
+criterion_main!(benches);
@@ -0,0 +1,29 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use drivel::{infer_schema, produce_to_writer_with_options, InferenceOptions, ProduceOptions};
+use serde_json::json;
+
+fn produce_records(c: &mut Criterion) {
+    let schema = infer_schema(
+        json!([{
+            "id": "0b0e0a86-7d0d-4bd6-9f6c-2f5a8a6d0f43",
+            "customer": { "name": "Alice", "email": "alice@example.com", "age": 34 },
+            "items": [
+                { "sku": "SKU-00042", "quantity": 2, "price": 9.99, "tags": ["a", "b"] },
+                { "sku": "SKU-00105", "quantity": 1, "price": 24.5, "tags": [] }
+            ],
+            "paid": true,
+            "created_at": "2024-03-01T12:00:00Z"
+        }]),
+        &InferenceOptions::default(),
+    );
+    let options = ProduceOptions::new().count(20_000).seed(1);
+    c.bench_function("produce 20000 records", |b| {
+        b.iter(|| {
+            produce_to_writer_with_options(&schema, &options, &mut std::io::sink()).unwrap();
+            black_box(())
+        })
+    });
+}
+
+criterion_group!(benches, produce_records);
+criterion_main!(benches);
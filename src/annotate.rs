@@ -0,0 +1,424 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{
+    infer_string::{format_name, infer_string_type, non_finite_float},
+    path, Detectors, NumberType, SchemaState, StringType,
+};
+
+/// The reason a field was flagged while annotating the input records.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AnnotationKind {
+    /// The field was null in these records, which made it nullable.
+    Nullable,
+    /// The field was missing from these records, which made it optional.
+    Optional,
+    /// The field had a different type in these records than the inferred type, or than the
+    /// majority of records if no definite type could be inferred.
+    TypeConflict {
+        /// The inferred JSON type, or the type seen in the majority of records.
+        expected: &'static str,
+        /// The JSON type seen in the flagged records.
+        found: &'static str,
+    },
+    /// The field held strings that did not match the format inferred for it, few enough to be
+    /// tolerated. See `InferenceOptions::format_tolerance`.
+    FormatOutlier {
+        /// The format inferred for the field, e.g. `uuid`.
+        expected: &'static str,
+        /// The format of the strings in the flagged records, or `string` if none was detected.
+        found: &'static str,
+    },
+}
+
+/// Links a field that became nullable, optional, or type-conflicted during inference, or that
+/// kept its format despite outliers, back to the records that caused it.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct Annotation {
+    /// The path of the field, e.g. `users[].address.city`.
+    pub path: String,
+    #[serde(flatten)]
+    pub kind: AnnotationKind,
+    /// Zero-based indices of the records that caused the annotation.
+    pub records: Vec<usize>,
+}
+
+impl Display for Annotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match &self.kind {
+            AnnotationKind::Nullable => "null".to_owned(),
+            AnnotationKind::Optional => "missing".to_owned(),
+            AnnotationKind::TypeConflict { expected, found }
+            | AnnotationKind::FormatOutlier { expected, found } => {
+                format!("{} instead of {}", found, expected)
+            }
+        };
+        write!(
+            f,
+            "{}: {} in {} record(s)",
+            path::display(&self.path),
+            reason,
+            self.records.len()
+        )
+    }
+}
+
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+fn schema_type(schema: &SchemaState) -> Option<&'static str> {
+    match schema {
+        SchemaState::Null => Some("null"),
+        SchemaState::Boolean => Some("boolean"),
+        SchemaState::Number(_) => Some("number"),
+        SchemaState::String(_) => Some("string"),
+        SchemaState::Array { .. } | SchemaState::Tuple(_) | SchemaState::UnconstrainedArray => {
+            Some("array")
+        }
+        SchemaState::Object { .. } | SchemaState::Map { .. } | SchemaState::UnconstrainedObject => {
+            Some("object")
+        }
+        SchemaState::Const(value) => Some(json_type(value)),
+        _ => None,
+    }
+}
+
+type ConflictKey = (String, &'static str, &'static str);
+
+#[derive(Default)]
+struct Collector {
+    nullable: HashMap<String, Vec<usize>>,
+    optional: HashMap<String, Vec<usize>>,
+    conflicts: HashMap<ConflictKey, Vec<usize>>,
+    format_outliers: HashMap<ConflictKey, Vec<usize>>,
+    types_seen: HashMap<String, Vec<(usize, &'static str)>>,
+}
+
+fn walk(
+    value: &serde_json::Value,
+    schema: &SchemaState,
+    current_path: &str,
+    record: usize,
+    collector: &mut Collector,
+) {
+    // floats that were seen to be non-finite are encoded as strings
+    if let (
+        SchemaState::Number(NumberType::Float {
+            non_finite: true, ..
+        }),
+        serde_json::Value::String(s),
+    ) = (schema, value)
+    {
+        if non_finite_float(s).is_some() {
+            return;
+        }
+    }
+    if let Some(expected) = schema_type(schema) {
+        let found = json_type(value);
+        if found != expected {
+            collector
+                .conflicts
+                .entry((current_path.to_owned(), expected, found))
+                .or_default()
+                .push(record);
+            return;
+        }
+    }
+
+    match schema {
+        SchemaState::Nullable(inner) => {
+            if value.is_null() {
+                collector
+                    .nullable
+                    .entry(current_path.to_owned())
+                    .or_default()
+                    .push(record);
+            } else {
+                walk(value, inner, current_path, record, collector);
+            }
+        }
+        SchemaState::Object { required, optional } => {
+            if let serde_json::Value::Object(map) = value {
+                for (k, v) in required {
+                    if let Some(field) = map.get(k) {
+                        walk(field, v, &path::key(current_path, k), record, collector);
+                    }
+                }
+                for (k, v) in optional {
+                    let field_path = path::key(current_path, k);
+                    match map.get(k) {
+                        Some(field) => walk(field, v, &field_path, record, collector),
+                        None => collector
+                            .optional
+                            .entry(field_path)
+                            .or_default()
+                            .push(record),
+                    }
+                }
+            }
+        }
+        SchemaState::Array { schema, .. } => {
+            if let serde_json::Value::Array(items) = value {
+                let elements_path = path::elements(current_path);
+                for item in items {
+                    walk(item, schema, &elements_path, record, collector);
+                }
+            }
+        }
+        SchemaState::Tuple(elements) => {
+            if let serde_json::Value::Array(items) = value {
+                for (idx, (item, element)) in items.iter().zip(elements).enumerate() {
+                    walk(
+                        item,
+                        element,
+                        &path::index(current_path, idx),
+                        record,
+                        collector,
+                    );
+                }
+            }
+        }
+        // only the formats detected in strings can have been tolerated
+        SchemaState::String(string_type)
+            if !matches!(
+                string_type,
+                StringType::Unknown { .. }
+                    | StringType::Enum { .. }
+                    | StringType::Pattern(_)
+                    | StringType::EmbeddedJson(_)
+                    | StringType::NonFiniteFloat
+            ) =>
+        {
+            if let serde_json::Value::String(s) = value {
+                let expected = format_name(string_type);
+                let found = format_name(&infer_string_type(s, &Detectors::new(), 0, None));
+                if found != expected {
+                    collector
+                        .format_outliers
+                        .entry((current_path.to_owned(), expected, found))
+                        .or_default()
+                        .push(record);
+                }
+            }
+        }
+        SchemaState::Indefinite => collector
+            .types_seen
+            .entry(current_path.to_owned())
+            .or_default()
+            .push((record, json_type(value))),
+        _ => {}
+    }
+}
+
+fn dedup_sorted(mut records: Vec<usize>) -> Vec<usize> {
+    records.sort_unstable();
+    records.dedup();
+    records
+}
+
+/// Traces the fields of an inferred schema that became nullable, optional, or type-conflicted,
+/// or that kept a format despite strings that did not match it, back to the records that
+/// caused them.
+///
+/// `records` are the values the schema was inferred from, and `schema` is the schema of a
+/// single record: when the input is a JSON array, pass its elements and the array's element
+/// schema. Records are flagged as type-conflicted when their value does not match the inferred
+/// type; for fields without a definite type, the records holding the most common type are
+/// considered conforming and all others are flagged.
+///
+/// The returned annotations are sorted by path.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use drivel::{annotate_outliers, infer_schema_from_iter, AnnotationKind, InferenceOptions};
+///
+/// let records = vec![
+///     json!({ "name": "Alice", "age": 30 }),
+///     json!({ "name": "Bob", "age": null }),
+///     json!({ "age": 25 }),
+/// ];
+/// let opts = InferenceOptions::default();
+/// let schema = infer_schema_from_iter(records.clone(), &opts);
+///
+/// let annotations = annotate_outliers(&records, &schema);
+///
+/// assert_eq!(annotations[0].path, "age");
+/// assert_eq!(annotations[0].kind, AnnotationKind::Nullable);
+/// assert_eq!(annotations[0].records, vec![1]);
+/// assert_eq!(annotations[1].path, "name");
+/// assert_eq!(annotations[1].kind, AnnotationKind::Optional);
+/// assert_eq!(annotations[1].records, vec![2]);
+/// ```
+pub fn annotate_outliers(records: &[serde_json::Value], schema: &SchemaState) -> Vec<Annotation> {
+    let mut collector = Collector::default();
+    for (idx, record) in records.iter().enumerate() {
+        walk(record, schema, "", idx, &mut collector);
+    }
+
+    let mut annotations = vec![];
+    for (path, records) in collector.nullable {
+        annotations.push(Annotation {
+            path,
+            kind: AnnotationKind::Nullable,
+            records: dedup_sorted(records),
+        });
+    }
+    for (path, records) in collector.optional {
+        annotations.push(Annotation {
+            path,
+            kind: AnnotationKind::Optional,
+            records: dedup_sorted(records),
+        });
+    }
+    for ((path, expected, found), records) in collector.conflicts {
+        annotations.push(Annotation {
+            path,
+            kind: AnnotationKind::TypeConflict { expected, found },
+            records: dedup_sorted(records),
+        });
+    }
+    for ((path, expected, found), records) in collector.format_outliers {
+        annotations.push(Annotation {
+            path,
+            kind: AnnotationKind::FormatOutlier { expected, found },
+            records: dedup_sorted(records),
+        });
+    }
+    // fields without a definite type are annotated against the type seen most often
+    for (path, seen) in collector.types_seen {
+        let mut counts: HashMap<&'static str, usize> = HashMap::new();
+        for (_, type_name) in &seen {
+            *counts.entry(type_name).or_default() += 1;
+        }
+        // ties are broken alphabetically so that the output is stable across runs
+        let expected = counts
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(type_name, _)| *type_name)
+            .unwrap();
+
+        let mut by_found: HashMap<&'static str, Vec<usize>> = HashMap::new();
+        for (record, type_name) in seen {
+            if type_name != expected {
+                by_found.entry(type_name).or_default().push(record);
+            }
+        }
+        for (found, records) in by_found {
+            annotations.push(Annotation {
+                path: path.clone(),
+                kind: AnnotationKind::TypeConflict { expected, found },
+                records: dedup_sorted(records),
+            });
+        }
+    }
+
+    annotations.sort_by(|a, b| a.path.cmp(&b.path).then(a.kind.cmp(&b.kind)));
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema, infer_schema_from_iter, InferenceOptions};
+
+    #[test]
+    fn annotates_type_conflicts_with_minority_type() {
+        let records = vec![json!({"id": 1}), json!({"id": "two"}), json!({"id": 3})];
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(records.clone(), &options);
+
+        assert_eq!(
+            annotate_outliers(&records, &schema),
+            vec![Annotation {
+                path: "id".to_owned(),
+                kind: AnnotationKind::TypeConflict {
+                    expected: "number",
+                    found: "string"
+                },
+                records: vec![1]
+            }]
+        );
+    }
+
+    #[test]
+    fn annotates_nested_array_elements_by_record() {
+        let input = json!([
+            {"tags": [{"name": "a"}, {"name": null}]},
+            {"tags": [{"name": "b"}, {}]}
+        ]);
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema(input.clone(), &options);
+        let SchemaState::Array { schema, .. } = schema else {
+            panic!("expected array schema");
+        };
+        let records = input.as_array().unwrap();
+
+        assert_eq!(
+            annotate_outliers(records, &schema),
+            vec![
+                Annotation {
+                    path: "tags[].name".to_owned(),
+                    kind: AnnotationKind::Nullable,
+                    records: vec![0]
+                },
+                Annotation {
+                    path: "tags[].name".to_owned(),
+                    kind: AnnotationKind::Optional,
+                    records: vec![1]
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn annotates_strings_that_did_not_match_a_tolerated_format() {
+        let mut records = (0..99)
+            .map(|n| json!({ "email": format!("user{}@example.com", n) }))
+            .collect::<Vec<_>>();
+        records.insert(42, json!({ "email": "unknown" }));
+        let options = InferenceOptions {
+            format_tolerance: Some(0.01),
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(records.clone(), &options);
+
+        let annotations = annotate_outliers(&records, &schema);
+
+        assert_eq!(
+            annotations,
+            vec![Annotation {
+                path: "email".to_owned(),
+                kind: AnnotationKind::FormatOutlier {
+                    expected: "email",
+                    found: "string"
+                },
+                records: vec![42]
+            }]
+        );
+        assert_eq!(
+            annotations[0].to_string(),
+            "email: string instead of email in 1 record(s)"
+        );
+    }
+}
@@ -0,0 +1,1041 @@
+//! Exporting schemas as Avro schemas, writing produced records as Avro object container files,
+//! and parsing Avro schemas.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+use indexmap::IndexMap;
+use serde_json::json;
+
+use crate::codegen::{self, field_name, pascal_case, unique_name, Field};
+use crate::json_schema::{bounds, child_pointer, error, union_of};
+use crate::{
+    infer::integer_value, pattern, DistinctCount, DrivelError, JsonSchemaError, NumberType,
+    ParsedSchema, SchemaDefaults, SchemaState, StringType,
+};
+
+/// The name of the record type describing each record.
+const ROOT_RECORD: &str = "Record";
+
+/// The precision of the decimals that integers beyond the range of longs, and decimals, are
+/// written as: the number of digits that fit in an `i128`.
+const DECIMAL_PRECISION: u32 = 38;
+
+/// The number of records written in each block of a container file, each compressed on its own.
+const RECORDS_PER_BLOCK: usize = 4096;
+
+/// The type of a value in Avro, along with how it is encoded.
+enum AvroType {
+    Null,
+    Boolean,
+    Long,
+    Double,
+    String,
+    Uuid,
+    /// Microseconds since the Unix epoch, from an ISO 8601 or RFC 2822 datetime.
+    TimestampMicros,
+    /// Days since the Unix epoch, from an ISO 8601 date.
+    Date,
+    /// An integer scaled by `10^scale`, as big-endian two's complement bytes.
+    Decimal {
+        scale: u32,
+    },
+    Enum {
+        name: String,
+        symbols: Vec<String>,
+    },
+    /// Values Avro cannot describe, like those of mixed types, as strings holding their JSON.
+    Json,
+    Array(Box<AvroType>),
+    /// A map from strings to values of a type.
+    Map(Box<AvroType>),
+    /// A union of `null` and a type.
+    Nullable(Box<AvroType>),
+    Record {
+        name: String,
+        fields: Vec<AvroField>,
+    },
+}
+
+struct AvroField {
+    /// The key of the field in JSON, which may not be a valid Avro name.
+    key: String,
+    name: String,
+    schema: AvroType,
+    optional: bool,
+}
+
+/// Returns true if `s` is a valid Avro name, of a field or a symbol of an enum.
+fn is_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn nullable(schema: AvroType) -> AvroType {
+    match schema {
+        AvroType::Null | AvroType::Json | AvroType::Nullable(_) => schema,
+        schema => AvroType::Nullable(Box::new(schema)),
+    }
+}
+
+fn integer_type(min: i128, max: i128) -> AvroType {
+    if min >= i64::MIN.into() && max <= i64::MAX.into() {
+        AvroType::Long
+    } else {
+        AvroType::Decimal { scale: 0 }
+    }
+}
+
+fn avro_type(schema: &SchemaState, field: &str, names: &mut HashSet<String>) -> AvroType {
+    match schema {
+        SchemaState::Initial | SchemaState::Null | SchemaState::Const(serde_json::Value::Null) => {
+            AvroType::Null
+        }
+        SchemaState::Indefinite
+        | SchemaState::Union(_)
+        | SchemaState::Tuple(_)
+        | SchemaState::UnconstrainedArray
+        | SchemaState::UnconstrainedObject
+        | SchemaState::Const(serde_json::Value::Array(_) | serde_json::Value::Object(_)) => {
+            AvroType::Json
+        }
+        SchemaState::Nullable(inner) => nullable(avro_type(inner, field, names)),
+        SchemaState::String(StringType::Enum { variants })
+            if variants.iter().all(|variant| is_name(variant)) =>
+        {
+            let mut symbols = variants.iter().cloned().collect::<Vec<_>>();
+            symbols.sort();
+            AvroType::Enum {
+                name: unique_name(pascal_case(&field_name(field)), names),
+                symbols,
+            }
+        }
+        SchemaState::String(StringType::UUID) => AvroType::Uuid,
+        SchemaState::String(
+            StringType::DateTimeISO8601 { .. } | StringType::DateTimeRFC2822 { .. },
+        ) => AvroType::TimestampMicros,
+        SchemaState::String(StringType::IsoDate { .. }) => AvroType::Date,
+        SchemaState::String(_) | SchemaState::Const(serde_json::Value::String(_)) => {
+            AvroType::String
+        }
+        SchemaState::Number(
+            number_type @ (NumberType::Integer { .. }
+            | NumberType::Enum { .. }
+            | NumberType::BigInteger { .. }),
+        ) => {
+            let (min, max) = number_type.integer_range().unwrap_or_default();
+            integer_type(min, max)
+        }
+        SchemaState::Const(serde_json::Value::Number(n)) => match integer_value(n) {
+            Some(n) => integer_type(n, n),
+            None => AvroType::Double,
+        },
+        SchemaState::Number(NumberType::Decimal { scale, .. }) if *scale <= DECIMAL_PRECISION => {
+            AvroType::Decimal { scale: *scale }
+        }
+        SchemaState::Number(NumberType::Float { .. } | NumberType::Decimal { .. }) => {
+            AvroType::Double
+        }
+        SchemaState::Boolean | SchemaState::Const(serde_json::Value::Bool(_)) => AvroType::Boolean,
+        SchemaState::Array { schema, .. } => {
+            AvroType::Array(Box::new(avro_type(schema, field, names)))
+        }
+        SchemaState::Map { schema, .. } => AvroType::Map(Box::new(avro_type(schema, field, names))),
+        // records need at least one field
+        SchemaState::Object { required, optional }
+            if required.is_empty() && optional.is_empty() =>
+        {
+            AvroType::Json
+        }
+        SchemaState::Object { required, optional } => {
+            let name = unique_name(pascal_case(&field_name(field)), names);
+            record(name, codegen::fields(required, optional), names)
+        }
+    }
+}
+
+fn record(name: String, fields: Vec<Field>, names: &mut HashSet<String>) -> AvroType {
+    let mut field_names = HashSet::new();
+    let fields = fields
+        .into_iter()
+        .map(|field| {
+            let name = unique_name(field_name(field.key), &mut field_names);
+            let schema = avro_type(field.schema, &name, names);
+            AvroField {
+                key: field.key.to_owned(),
+                schema: if field.optional {
+                    nullable(schema)
+                } else {
+                    schema
+                },
+                name,
+                optional: field.optional,
+            }
+        })
+        .collect();
+    AvroType::Record { name, fields }
+}
+
+/// Returns the type describing each record: the elements of an array at the root, or the root
+/// itself, held in a `value` field when it is not an object.
+fn record_type(schema: &SchemaState) -> AvroType {
+    let mut names = HashSet::from_iter([ROOT_RECORD.to_owned()]);
+    record(
+        ROOT_RECORD.to_owned(),
+        codegen::record_fields(schema),
+        &mut names,
+    )
+}
+
+impl AvroType {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            AvroType::Null => json!("null"),
+            AvroType::Boolean => json!("boolean"),
+            AvroType::Long => json!("long"),
+            AvroType::Double => json!("double"),
+            AvroType::String | AvroType::Json => json!("string"),
+            AvroType::Uuid => json!({ "type": "string", "logicalType": "uuid" }),
+            AvroType::TimestampMicros => {
+                json!({ "type": "long", "logicalType": "timestamp-micros" })
+            }
+            AvroType::Date => json!({ "type": "int", "logicalType": "date" }),
+            AvroType::Decimal { scale } => json!({
+                "type": "bytes",
+                "logicalType": "decimal",
+                "precision": DECIMAL_PRECISION,
+                "scale": scale
+            }),
+            AvroType::Enum { name, symbols } => {
+                json!({ "type": "enum", "name": name, "symbols": symbols })
+            }
+            AvroType::Array(items) => json!({ "type": "array", "items": items.to_json() }),
+            AvroType::Map(values) => json!({ "type": "map", "values": values.to_json() }),
+            AvroType::Nullable(inner) => json!(["null", inner.to_json()]),
+            AvroType::Record { name, fields } => {
+                let fields = fields
+                    .iter()
+                    .map(|field| {
+                        let mut json =
+                            json!({ "name": field.name, "type": field.schema.to_json() });
+                        // the default of a union is a value of its first type, which is null
+                        if field.optional && matches!(field.schema, AvroType::Nullable(_)) {
+                            json["default"] = serde_json::Value::Null;
+                        }
+                        json
+                    })
+                    .collect::<Vec<_>>();
+                json!({ "type": "record", "name": name, "fields": fields })
+            }
+        }
+    }
+
+    /// Encodes a value of this type in Avro's binary encoding.
+    fn encode(&self, value: &serde_json::Value, out: &mut Vec<u8>) -> Result<(), DrivelError> {
+        let mismatch = |expected: &str| {
+            DrivelError::AvroEncoding(format!("expected {}, found {}", expected, value))
+        };
+        match self {
+            AvroType::Null => match value {
+                serde_json::Value::Null => {}
+                _ => return Err(mismatch("null")),
+            },
+            AvroType::Boolean => match value {
+                serde_json::Value::Bool(b) => out.push(u8::from(*b)),
+                _ => return Err(mismatch("a boolean")),
+            },
+            AvroType::Long => match value.as_i64() {
+                Some(n) => write_long(n, out),
+                None => return Err(mismatch("a long")),
+            },
+            AvroType::Double => match value {
+                serde_json::Value::Number(n) => {
+                    out.extend(n.as_f64().unwrap_or_default().to_le_bytes())
+                }
+                // non-finite floats are written as strings in JSON
+                serde_json::Value::String(s) => match s.parse::<f64>() {
+                    Ok(n) if !n.is_finite() => out.extend(n.to_le_bytes()),
+                    _ => return Err(mismatch("a double")),
+                },
+                _ => return Err(mismatch("a double")),
+            },
+            AvroType::String | AvroType::Uuid => match value {
+                serde_json::Value::String(s) => write_bytes(s.as_bytes(), out),
+                _ => return Err(mismatch("a string")),
+            },
+            AvroType::Json => write_bytes(value.to_string().as_bytes(), out),
+            AvroType::TimestampMicros => {
+                let moment = value.as_str().and_then(|s| {
+                    chrono::DateTime::parse_from_rfc3339(s)
+                        .or_else(|_| chrono::DateTime::parse_from_rfc2822(s))
+                        .ok()
+                });
+                match moment {
+                    Some(moment) => write_long(moment.timestamp_micros(), out),
+                    None => return Err(mismatch("a datetime")),
+                }
+            }
+            AvroType::Date => {
+                let date = value
+                    .as_str()
+                    .and_then(|s| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok());
+                match date {
+                    Some(date) => {
+                        let days = date.signed_duration_since(chrono::NaiveDate::default());
+                        write_long(days.num_days(), out)
+                    }
+                    None => return Err(mismatch("a date")),
+                }
+            }
+            AvroType::Decimal { scale } => {
+                let unscaled = match value {
+                    serde_json::Value::Number(n) => match integer_value(n) {
+                        Some(n) if *scale == 0 => Some(n),
+                        _ => n
+                            .as_f64()
+                            .map(|n| (n * 10f64.powi(*scale as i32)).round() as i128),
+                    },
+                    _ => None,
+                };
+                match unscaled {
+                    Some(unscaled) => write_bytes(&twos_complement(unscaled), out),
+                    None => return Err(mismatch("a number")),
+                }
+            }
+            AvroType::Enum { symbols, .. } => {
+                let index = value
+                    .as_str()
+                    .and_then(|s| symbols.iter().position(|symbol| symbol == s));
+                match index {
+                    Some(index) => write_long(index as i64, out),
+                    None => return Err(mismatch(&format!("one of {}", symbols.join(", ")))),
+                }
+            }
+            AvroType::Array(items) => match value {
+                serde_json::Value::Array(elements) => {
+                    // arrays are written as a single block of elements, ended by an empty one
+                    if !elements.is_empty() {
+                        write_long(elements.len() as i64, out);
+                        for element in elements {
+                            items.encode(element, out)?;
+                        }
+                    }
+                    write_long(0, out);
+                }
+                _ => return Err(mismatch("an array")),
+            },
+            AvroType::Map(values) => match value {
+                serde_json::Value::Object(entries) => {
+                    // maps are written like arrays, of keys each followed by their value
+                    if !entries.is_empty() {
+                        write_long(entries.len() as i64, out);
+                        for (key, value) in entries {
+                            write_bytes(key.as_bytes(), out);
+                            values.encode(value, out)?;
+                        }
+                    }
+                    write_long(0, out);
+                }
+                _ => return Err(mismatch("an object")),
+            },
+            AvroType::Nullable(inner) => match value {
+                serde_json::Value::Null => write_long(0, out),
+                value => {
+                    write_long(1, out);
+                    inner.encode(value, out)?;
+                }
+            },
+            AvroType::Record { fields, .. } => match value {
+                serde_json::Value::Object(object) => {
+                    for field in fields {
+                        let value = object.get(&field.key).unwrap_or(&serde_json::Value::Null);
+                        field.schema.encode(value, out)?;
+                    }
+                }
+                _ => return Err(mismatch("an object")),
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Writes a long as a zig-zag encoded variable-length integer.
+fn write_long(n: i64, out: &mut Vec<u8>) {
+    let mut n = ((n << 1) ^ (n >> 63)) as u64;
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    write_long(bytes.len() as i64, out);
+    out.extend(bytes);
+}
+
+/// Returns an integer as the fewest big-endian two's complement bytes that hold it.
+fn twos_complement(n: i128) -> Vec<u8> {
+    let bytes = n.to_be_bytes();
+    let redundant = bytes
+        .windows(2)
+        .take_while(|pair| {
+            (pair[0] == 0x00 && pair[1] & 0x80 == 0) || (pair[0] == 0xff && pair[1] & 0x80 != 0)
+        })
+        .count();
+    bytes[redundant..].to_vec()
+}
+
+/// Exports a schema as an Avro schema, with a `Record` record type describing each record: the
+/// elements of an array at the root, or the root itself.
+///
+/// Objects become records named after their fields, arrays arrays, and nullable and optional
+/// fields unions with `null`, defaulting to `null` when optional. Integers become longs, or
+/// decimals when they do not fit, decimals `decimal`s, datetimes `timestamp-micros`, dates
+/// `date`s and UUIDs `uuid`s. String enums become enums if their strings are valid names.
+/// Values Avro cannot describe, like those of mixed types, become strings holding their JSON.
+/// Keys that are not valid names are renamed. Records that are not objects are held in the
+/// `value` field of `Record`.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_avro_schema, InferenceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!([{ "id": 1, "tags": ["a"] }, { "id": 2, "tags": [], "note": null }]),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(
+///     to_avro_schema(&schema),
+///     json!({
+///         "type": "record",
+///         "name": "Record",
+///         "fields": [
+///             { "name": "id", "type": "long" },
+///             { "name": "tags", "type": { "type": "array", "items": "string" } },
+///             { "name": "note", "type": "null" }
+///         ]
+///     })
+/// );
+/// ```
+pub fn to_avro_schema(schema: &SchemaState) -> serde_json::Value {
+    record_type(schema).to_json()
+}
+
+/// Writes records as an Avro object container file, whose schema is the one `to_avro_schema`
+/// exports from `schema`. The records are the elements of an array, or the value itself, and
+/// each is encoded as a `Record`; data blocks are compressed with deflate.
+///
+/// Returns an error if a record does not conform to the schema, as records produced with
+/// overrides may not.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, produce_with_options, write_avro, InferenceOptions, ProduceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(json!([{ "id": 1 }, { "id": 2 }]), &InferenceOptions::default());
+/// let records = produce_with_options(&schema, &ProduceOptions::new().count(10)).unwrap();
+///
+/// let mut file = vec![];
+/// write_avro(&records, &schema, &mut file).unwrap();
+/// assert!(file.starts_with(b"Obj\x01"));
+/// ```
+pub fn write_avro(
+    records: &serde_json::Value,
+    schema: &SchemaState,
+    writer: &mut impl Write,
+) -> Result<(), DrivelError> {
+    let record_type = record_type(schema);
+    let record_schema = match schema {
+        SchemaState::Array { schema, .. } => schema,
+        schema => schema,
+    };
+    let is_object = matches!(record_schema, SchemaState::Object { .. });
+    let records = match records {
+        serde_json::Value::Array(records) => records.iter().collect::<Vec<_>>(),
+        record => vec![record],
+    };
+
+    let sync_marker = rand::random::<[u8; 16]>();
+    let mut header = b"Obj\x01".to_vec();
+    write_long(2, &mut header);
+    write_bytes(b"avro.schema", &mut header);
+    write_bytes(record_type.to_json().to_string().as_bytes(), &mut header);
+    write_bytes(b"avro.codec", &mut header);
+    write_bytes(b"deflate", &mut header);
+    write_long(0, &mut header);
+    header.extend(sync_marker);
+    writer.write_all(&header)?;
+
+    for block in records.chunks(RECORDS_PER_BLOCK) {
+        let mut data = vec![];
+        for record in block {
+            if is_object {
+                record_type.encode(record, &mut data)?;
+            } else {
+                record_type.encode(&json!({ "value": record }), &mut data)?;
+            }
+        }
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(&data)?;
+        let data = encoder.finish()?;
+
+        let mut block_header = vec![];
+        write_long(block.len() as i64, &mut block_header);
+        write_long(data.len() as i64, &mut block_header);
+        writer.write_all(&block_header)?;
+        writer.write_all(&data)?;
+        writer.write_all(&sync_marker)?;
+    }
+    Ok(())
+}
+
+struct ParseContext<'a> {
+    /// The named types (records, enums and fixeds) defined so far, by their full names, along
+    /// with the pointers to their definitions.
+    names: HashMap<String, (&'a serde_json::Value, String)>,
+    /// The namespace of the named type being parsed, which names within it are relative to.
+    namespace: Option<String>,
+    defaults: &'a SchemaDefaults,
+    warnings: Vec<String>,
+    /// The named types currently being parsed, used to detect recursive types.
+    name_stack: Vec<String>,
+}
+
+/// Returns the full name of a named type or a reference to one, like `com.shop.User`, given
+/// the namespace it appears in.
+fn full_name(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(namespace) if !name.contains('.') && !namespace.is_empty() => {
+            format!("{}.{}", namespace, name)
+        }
+        _ => name.to_owned(),
+    }
+}
+
+/// Returns the integers of an Avro type within `lower..=upper`, in the default range where it
+/// fits within the type.
+fn parse_integer(lower: i64, upper: i64, ctx: &ParseContext) -> SchemaState {
+    let (min, max) = (
+        (*ctx.defaults.integers.start()).max(lower),
+        (*ctx.defaults.integers.end()).min(upper),
+    );
+    let (min, max) = if min <= max {
+        (min, max)
+    } else {
+        (lower, upper)
+    };
+    SchemaState::Number(NumberType::Integer { min, max })
+}
+
+fn parse_primitive(
+    name: &str,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    match name {
+        "null" => Ok(SchemaState::Null),
+        "boolean" => Ok(SchemaState::Boolean),
+        "int" => Ok(parse_integer(i32::MIN.into(), i32::MAX.into(), ctx)),
+        "long" => Ok(parse_integer(i64::MIN, i64::MAX, ctx)),
+        "float" | "double" => Ok(SchemaState::Number(NumberType::Float {
+            min: *ctx.defaults.floats.start(),
+            max: *ctx.defaults.floats.end(),
+            non_finite: false,
+            exclusive_min: false,
+            exclusive_max: false,
+        })),
+        // bytes are read and written as strings in JSON; they are produced as base64
+        "bytes" => {
+            let (min_length, max_length) = bounds(None, None, &ctx.defaults.string_lengths);
+            Ok(SchemaState::String(StringType::Base64 {
+                min_length,
+                max_length,
+            }))
+        }
+        "string" => {
+            let (min_length, max_length) = bounds(None, None, &ctx.defaults.string_lengths);
+            Ok(SchemaState::String(StringType::Unknown {
+                strings_seen: vec![],
+                chars_seen: HashMap::new(),
+                n_strings_seen: 0,
+                distinct: DistinctCount::new(),
+                n_chars_seen: 0,
+                min_length: Some(min_length),
+                max_length: Some(max_length),
+                prefix: String::new(),
+                suffix: String::new(),
+                structure: None,
+            }))
+        }
+        name => {
+            let full_name = full_name(name, ctx.namespace.as_deref());
+            let (definition, definition_pointer) = ctx
+                .names
+                .get(&full_name)
+                .or_else(|| ctx.names.get(name))
+                .cloned()
+                .ok_or_else(|| error(pointer, format!("unknown type {:?}", name)))?;
+            if ctx.name_stack.contains(&full_name) || ctx.name_stack.iter().any(|n| n == name) {
+                return Err(error(
+                    pointer,
+                    format!("recursive type {:?} is not supported", name),
+                ));
+            }
+            parse_node(definition, &definition_pointer, ctx)
+        }
+    }
+}
+
+/// Parses a type with a logical type, falling back to its underlying type when drivel cannot
+/// represent the logical type, as Avro readers do.
+fn parse_logical(
+    logical_type: &str,
+    node: &serde_json::Map<String, serde_json::Value>,
+    type_name: &str,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<Option<SchemaState>, JsonSchemaError> {
+    let schema = match (logical_type, type_name) {
+        ("uuid", "string" | "fixed") => SchemaState::String(StringType::UUID),
+        ("date", "int") => SchemaState::String(StringType::IsoDate { range: None }),
+        (
+            "timestamp-millis"
+            | "timestamp-micros"
+            | "timestamp-nanos"
+            | "local-timestamp-millis"
+            | "local-timestamp-micros"
+            | "local-timestamp-nanos",
+            "long",
+        ) => SchemaState::String(StringType::DateTimeISO8601 { range: None }),
+        ("decimal", "bytes" | "fixed") => {
+            let digits = |keyword: &str| node.get(keyword).and_then(|n| n.as_u64());
+            let precision =
+                digits("precision").ok_or_else(|| error(pointer, "a decimal needs a precision"))?;
+            let scale = digits("scale").unwrap_or(0);
+            if scale > precision {
+                return Err(error(
+                    pointer,
+                    "the scale of a decimal exceeds its precision",
+                ));
+            }
+            // the default range, within the largest decimal of the precision
+            let largest = 10f64.powi((precision - scale) as i32) - 10f64.powi(-(scale as i32));
+            let min = ctx.defaults.floats.start().clamp(-largest, largest);
+            let max = ctx.defaults.floats.end().clamp(-largest, largest);
+            SchemaState::Number(NumberType::Decimal {
+                scale: scale as u32,
+                min,
+                max,
+            })
+        }
+        _ => {
+            ctx.warnings.push(format!(
+                "{}: logical type {:?} of {} is not supported, and values of {} are produced",
+                pointer, logical_type, type_name, type_name
+            ));
+            return Ok(None);
+        }
+    };
+    Ok(Some(schema))
+}
+
+/// Registers the named type defined by `node` under its full name, returning that name and
+/// the namespace of the types within it.
+fn define<'a>(
+    node: &'a serde_json::Map<String, serde_json::Value>,
+    value: &'a serde_json::Value,
+    pointer: &str,
+    ctx: &mut ParseContext<'a>,
+) -> Result<(String, Option<String>), JsonSchemaError> {
+    let name = node
+        .get("name")
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| error(pointer, "a named type needs a name"))?;
+    let namespace = node
+        .get("namespace")
+        .and_then(|namespace| namespace.as_str())
+        .or(ctx.namespace.as_deref());
+    let full_name = full_name(name, namespace);
+    ctx.names
+        .insert(full_name.clone(), (value, pointer.to_owned()));
+    let namespace = full_name
+        .rsplit_once('.')
+        .map(|(namespace, _)| namespace.to_owned());
+    Ok((full_name, namespace))
+}
+
+fn parse_record<'a>(
+    node: &'a serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext<'a>,
+) -> Result<SchemaState, JsonSchemaError> {
+    let fields = node
+        .get("fields")
+        .and_then(|fields| fields.as_array())
+        .ok_or_else(|| error(pointer, "a record needs an array of fields"))?;
+    let fields_pointer = child_pointer(pointer, "fields");
+    let mut required = IndexMap::new();
+    for (idx, field) in fields.iter().enumerate() {
+        let field_pointer = child_pointer(&fields_pointer, &idx.to_string());
+        let name = field
+            .get("name")
+            .and_then(|name| name.as_str())
+            .ok_or_else(|| error(&field_pointer, "a field needs a name"))?;
+        let schema = field
+            .get("type")
+            .ok_or_else(|| error(&field_pointer, "a field needs a type"))?;
+        let schema = parse_node(schema, &child_pointer(&field_pointer, "type"), ctx)?;
+        required.insert(name.to_owned(), schema);
+    }
+    Ok(SchemaState::Object {
+        required,
+        optional: IndexMap::new(),
+    })
+}
+
+fn parse_node<'a>(
+    value: &'a serde_json::Value,
+    pointer: &str,
+    ctx: &mut ParseContext<'a>,
+) -> Result<SchemaState, JsonSchemaError> {
+    let node = match value {
+        serde_json::Value::String(name) => return parse_primitive(name, pointer, ctx),
+        serde_json::Value::Array(branches) => {
+            let alternatives = branches
+                .iter()
+                .enumerate()
+                .map(|(idx, branch)| {
+                    parse_node(branch, &child_pointer(pointer, &idx.to_string()), ctx)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(union_of(alternatives));
+        }
+        serde_json::Value::Object(node) => node,
+        _ => {
+            return Err(error(
+                pointer,
+                "a schema must be a name, an array or an object",
+            ))
+        }
+    };
+
+    let type_name = node
+        .get("type")
+        .ok_or_else(|| error(pointer, "a schema needs a type"))?;
+    let serde_json::Value::String(type_name) = type_name else {
+        // a type may itself be a schema, like `{ "type": { "type": "array", ... } }`
+        return parse_node(type_name, &child_pointer(pointer, "type"), ctx);
+    };
+    if let Some(logical_type) = node.get("logicalType").and_then(|t| t.as_str()) {
+        if let Some(schema) = parse_logical(logical_type, node, type_name, pointer, ctx)? {
+            if matches!(type_name.as_str(), "fixed") {
+                define(node, value, pointer, ctx)?;
+            }
+            return Ok(schema);
+        }
+    }
+
+    match type_name.as_str() {
+        "record" | "error" | "enum" | "fixed" => {
+            let (full_name, namespace) = define(node, value, pointer, ctx)?;
+            let outer = std::mem::replace(&mut ctx.namespace, namespace);
+            ctx.name_stack.push(full_name);
+            let parsed = match type_name.as_str() {
+                "enum" => {
+                    let variants = node
+                        .get("symbols")
+                        .and_then(|symbols| symbols.as_array())
+                        .and_then(|symbols| {
+                            symbols
+                                .iter()
+                                .map(|symbol| symbol.as_str().map(str::to_owned))
+                                .collect::<Option<HashSet<_>>>()
+                        })
+                        .ok_or_else(|| error(pointer, "symbols must be an array of strings"));
+                    variants.map(|variants| SchemaState::String(StringType::Enum { variants }))
+                }
+                "fixed" => {
+                    let size = node
+                        .get("size")
+                        .and_then(|size| size.as_u64())
+                        .ok_or_else(|| error(pointer, "a fixed needs a size"));
+                    // every 3 bytes are encoded as 4 characters of base64
+                    size.map(|size| {
+                        let length = (size as usize).div_ceil(3) * 4;
+                        SchemaState::String(StringType::Base64 {
+                            min_length: length,
+                            max_length: length,
+                        })
+                    })
+                }
+                _ => parse_record(node, pointer, ctx),
+            };
+            ctx.name_stack.pop();
+            ctx.namespace = outer;
+            parsed
+        }
+        "array" => {
+            let items = node
+                .get("items")
+                .ok_or_else(|| error(pointer, "an array needs items"))?;
+            let schema = parse_node(items, &child_pointer(pointer, "items"), ctx)?;
+            let (min_length, max_length) = bounds(None, None, &ctx.defaults.array_lengths);
+            Ok(SchemaState::Array {
+                min_length,
+                max_length,
+                unique: false,
+                schema: Box::new(schema),
+            })
+        }
+        "map" => {
+            let values = node
+                .get("values")
+                .ok_or_else(|| error(pointer, "a map needs values"))?;
+            let schema = parse_node(values, &child_pointer(pointer, "values"), ctx)?;
+            let (min_length, max_length) = bounds(None, None, &ctx.defaults.map_lengths);
+            Ok(SchemaState::Map {
+                pattern: pattern::ANY_KEY.to_owned(),
+                min_length,
+                max_length,
+                schema: Box::new(schema),
+            })
+        }
+        name => parse_primitive(name, pointer, ctx),
+    }
+}
+
+/// Parses an Avro schema, like those of `.avsc` files, into a SchemaState, so that data can be
+/// described and produced from it.
+///
+/// Records become objects whose fields are all required, enums enums of strings, and unions
+/// unions, with `null` making them nullable. Values are produced as Avro's JSON encoding
+/// reads them, apart from unions, which are produced as their value alone: bytes and fixeds as
+/// base64, and the logical types `uuid`, `date`, `timestamp-*` and `decimal` as UUIDs, ISO 8601
+/// dates and datetimes, and decimals. Numbers, strings and arrays are bounded by `defaults`,
+/// and integers by the range of their type too, and maps have `defaults.map_lengths` keys.
+/// References to named types are resolved.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{parse_avro_schema, NumberType, SchemaDefaults, SchemaState, StringType};
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "type": "record",
+///     "name": "User",
+///     "namespace": "com.shop",
+///     "fields": [
+///         { "name": "id", "type": { "type": "string", "logicalType": "uuid" } },
+///         { "name": "age", "type": ["null", "int"], "default": null }
+///     ]
+/// });
+///
+/// let parsed = parse_avro_schema(&schema, &SchemaDefaults::default()).unwrap();
+///
+/// let SchemaState::Object { required, .. } = parsed.schema else { panic!() };
+/// assert_eq!(required["id"], SchemaState::String(StringType::UUID));
+/// assert_eq!(
+///     required["age"],
+///     SchemaState::Nullable(Box::new(SchemaState::Number(NumberType::Integer {
+///         min: 0,
+///         max: 1000
+///     })))
+/// );
+/// ```
+pub fn parse_avro_schema(
+    schema: &serde_json::Value,
+    defaults: &SchemaDefaults,
+) -> Result<ParsedSchema, DrivelError> {
+    let mut ctx = ParseContext {
+        names: HashMap::new(),
+        namespace: None,
+        defaults,
+        warnings: vec![],
+        name_stack: vec![],
+    };
+    let schema = parse_node(schema, "#", &mut ctx)?;
+    Ok(ParsedSchema {
+        schema,
+        warnings: ctx.warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn encodes_records_in_a_container_file() {
+        let schema = SchemaState::Object {
+            required: IndexMap::from_iter([
+                (
+                    "id".to_owned(),
+                    SchemaState::Number(NumberType::Integer { min: -1, max: 64 }),
+                ),
+                (
+                    "price".to_owned(),
+                    SchemaState::Number(NumberType::Decimal {
+                        scale: 2,
+                        min: 1.0,
+                        max: 2.0,
+                    }),
+                ),
+                (
+                    "day".to_owned(),
+                    SchemaState::String(StringType::IsoDate { range: None }),
+                ),
+                (
+                    "tags".to_owned(),
+                    SchemaState::Array {
+                        min_length: 0,
+                        max_length: 2,
+                        unique: false,
+                        schema: Box::new(SchemaState::Boolean),
+                    },
+                ),
+            ]),
+            optional: IndexMap::from_iter([(
+                "first name".to_owned(),
+                SchemaState::String(StringType::Enum {
+                    variants: HashSet::from_iter(["b".to_owned(), "a".to_owned()]),
+                }),
+            )]),
+        };
+        assert_eq!(
+            to_avro_schema(&schema)["fields"][4],
+            json!({
+                "name": "first_name",
+                "type": ["null", { "type": "enum", "name": "FirstName", "symbols": ["a", "b"] }],
+                "default": null
+            })
+        );
+
+        let records = json!([
+            { "id": -1, "price": 1.5, "day": "1970-01-02", "tags": [true], "first name": "b" },
+            { "id": 64, "price": 2, "day": "1970-01-01", "tags": [] },
+        ]);
+        let mut file = vec![];
+        write_avro(&records, &schema, &mut file).unwrap();
+
+        assert!(file.starts_with(b"Obj\x01"));
+        // the header and each block end with the same sync marker
+        let sync_marker = &file[file.len() - 16..];
+        let block = file
+            .windows(16)
+            .position(|window| window == sync_marker)
+            .unwrap()
+            + 16;
+        // two records, then the length of the compressed data
+        assert_eq!(file[block], 4);
+        let mut data = vec![];
+        flate2::read::DeflateDecoder::new(&file[block + 2..file.len() - 16])
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(
+            data,
+            [
+                // -1, 150 as a decimal, 1 day, a block of one true, then the second enum symbol
+                vec![1, 4, 0, 150, 2, 2, 1, 0, 2, 2],
+                // 64, 200 as a decimal, 0 days, no tags, then null
+                vec![128, 1, 4, 0, 200, 0, 0, 0],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn rejects_records_that_do_not_conform() {
+        let schema = SchemaState::Number(NumberType::Integer { min: 1, max: 2 });
+        assert!(write_avro(&json!("one"), &schema, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn parses_named_types_logical_types_and_unions() {
+        let schema = json!({
+            "type": "record",
+            "name": "Order",
+            "namespace": "com.shop",
+            "fields": [
+                {
+                    "name": "status",
+                    "type": { "type": "enum", "name": "Status", "symbols": ["OPEN", "PAID"] }
+                },
+                { "name": "previous", "type": ["null", "Status"] },
+                { "name": "total", "type": {
+                    "type": "bytes", "logicalType": "decimal", "precision": 4, "scale": 2
+                } },
+                { "name": "placed_at", "type": { "type": "long", "logicalType": "timestamp-millis" } },
+                { "name": "hash", "type": { "type": "fixed", "name": "Md5", "size": 16 } },
+                { "name": "labels", "type": { "type": "map", "values": "string" } },
+                { "name": "id", "type": ["int", "string"] }
+            ]
+        });
+
+        let parsed = parse_avro_schema(&schema, &SchemaDefaults::default()).unwrap();
+
+        let SchemaState::Object { required, .. } = parsed.schema else {
+            panic!()
+        };
+        let status = SchemaState::String(StringType::Enum {
+            variants: HashSet::from_iter(["OPEN".to_owned(), "PAID".to_owned()]),
+        });
+        assert_eq!(required["status"], status);
+        assert_eq!(
+            required["previous"],
+            SchemaState::Nullable(Box::new(status))
+        );
+        assert_eq!(
+            required["total"],
+            SchemaState::Number(NumberType::Decimal {
+                scale: 2,
+                min: 0.0,
+                max: 99.99
+            })
+        );
+        assert_eq!(
+            required["placed_at"],
+            SchemaState::String(StringType::DateTimeISO8601 { range: None })
+        );
+        assert_eq!(
+            required["hash"],
+            SchemaState::String(StringType::Base64 {
+                min_length: 24,
+                max_length: 24
+            })
+        );
+        assert!(matches!(
+            &required["labels"],
+            SchemaState::Map { pattern, min_length: 1, max_length: 4, schema }
+                if pattern == crate::pattern::ANY_KEY
+                    && matches!(**schema, SchemaState::String(StringType::Unknown { .. }))
+        ));
+        assert!(matches!(&required["id"], SchemaState::Union(branches) if branches.len() == 2));
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn rejects_recursive_types() {
+        let schema = json!({
+            "type": "record",
+            "name": "Node",
+            "fields": [{ "name": "next", "type": ["null", "Node"] }]
+        });
+
+        assert_eq!(
+            parse_avro_schema(&schema, &SchemaDefaults::default())
+                .unwrap_err()
+                .to_string(),
+            "#/fields/0/type/1: recursive type \"Node\" is not supported"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Exporting schemas as BigQuery table schemas.
+
+use std::collections::HashSet;
+
+use serde_json::json;
+
+use crate::codegen::{self, field_name, unique_name, Field};
+use crate::{infer::integer_value, NumberType, SchemaState, StringType};
+
+/// Returns the narrowest BigQuery type that holds every integer in `min..=max`.
+fn integer_type(min: i128, max: i128) -> &'static str {
+    let fits = |digits: u32| min.unsigned_abs().max(max.unsigned_abs()) < 10u128.pow(digits);
+    if min >= i64::MIN.into() && max <= i64::MAX.into() {
+        "INT64"
+    } else if fits(29) {
+        "NUMERIC"
+    } else if fits(38) {
+        "BIGNUMERIC"
+    } else {
+        "FLOAT64"
+    }
+}
+
+/// Returns the BigQuery type of a value, and the fields of it if it is a `RECORD`.
+fn column_type(schema: &SchemaState) -> (&'static str, Option<serde_json::Value>) {
+    match schema {
+        // BigQuery has no unions, tuples or maps, nor arrays of arrays; a JSON column holds any
+        // value
+        SchemaState::Initial
+        | SchemaState::Indefinite
+        | SchemaState::Null
+        | SchemaState::Union(_)
+        | SchemaState::Tuple(_)
+        | SchemaState::UnconstrainedArray
+        | SchemaState::UnconstrainedObject
+        | SchemaState::Map { .. }
+        | SchemaState::Const(
+            serde_json::Value::Null | serde_json::Value::Array(_) | serde_json::Value::Object(_),
+        )
+        | SchemaState::Array { .. } => ("JSON", None),
+        SchemaState::Nullable(inner) => column_type(inner),
+        SchemaState::String(StringType::DateTimeISO8601 { .. }) => ("TIMESTAMP", None),
+        SchemaState::String(StringType::IsoDate { .. }) => ("DATE", None),
+        // BigQuery loads bytes from base64 in JSON
+        SchemaState::String(StringType::Base64 { .. }) => ("BYTES", None),
+        SchemaState::String(_) | SchemaState::Const(serde_json::Value::String(_)) => {
+            ("STRING", None)
+        }
+        SchemaState::Number(
+            number_type @ (NumberType::Integer { .. }
+            | NumberType::Enum { .. }
+            | NumberType::BigInteger { .. }),
+        ) => {
+            let (min, max) = number_type.integer_range().unwrap_or_default();
+            (integer_type(min, max), None)
+        }
+        SchemaState::Number(NumberType::Decimal { scale, .. }) => match scale {
+            0..=9 => ("NUMERIC", None),
+            10..=38 => ("BIGNUMERIC", None),
+            _ => ("FLOAT64", None),
+        },
+        SchemaState::Number(NumberType::Float { .. }) => ("FLOAT64", None),
+        SchemaState::Const(serde_json::Value::Number(n)) => match integer_value(n) {
+            Some(n) => (integer_type(n, n), None),
+            None => ("FLOAT64", None),
+        },
+        SchemaState::Boolean | SchemaState::Const(serde_json::Value::Bool(_)) => ("BOOL", None),
+        // records need at least one field
+        SchemaState::Object { required, optional }
+            if required.is_empty() && optional.is_empty() =>
+        {
+            ("JSON", None)
+        }
+        SchemaState::Object { required, optional } => {
+            ("RECORD", Some(columns(codegen::fields(required, optional))))
+        }
+    }
+}
+
+fn columns(fields: Vec<Field>) -> serde_json::Value {
+    let mut names = HashSet::new();
+    fields
+        .into_iter()
+        .map(|field| {
+            let (mode, schema) = match field.schema {
+                // repeated columns load nulls as empty arrays
+                SchemaState::Array { schema, .. } => ("REPEATED", schema.as_ref()),
+                SchemaState::Nullable(inner) => match inner.as_ref() {
+                    SchemaState::Array { schema, .. } => ("REPEATED", schema.as_ref()),
+                    inner => ("NULLABLE", inner),
+                },
+                SchemaState::Null => ("NULLABLE", field.schema),
+                schema if field.optional => ("NULLABLE", schema),
+                schema => ("REQUIRED", schema),
+            };
+            let (column_type, fields) = column_type(schema);
+            let mut column = json!({
+                "name": unique_name(field_name(field.key), &mut names),
+                "type": column_type,
+                "mode": mode,
+            });
+            if let Some(fields) = fields {
+                column["fields"] = fields;
+            }
+            column
+        })
+        .collect()
+}
+
+/// Exports a schema as a BigQuery table schema, as used by `bq load --schema`, with a column
+/// for each field of the records: the elements of an array at the root, or the root itself.
+///
+/// Objects become `RECORD`s, arrays `REPEATED` columns, and optional and nullable fields are
+/// `NULLABLE`. ISO 8601 datetimes and dates become `TIMESTAMP`s and `DATE`s. Values BigQuery
+/// cannot describe, like unions, tuples and arrays of arrays, become `JSON` columns. Records
+/// that are not objects are held in a `value` column.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_bigquery, InferenceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!({ "id": 1, "email": null, "tags": ["a"] }),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(
+///     to_bigquery(&schema),
+///     json!([
+///         { "name": "id", "type": "INT64", "mode": "REQUIRED" },
+///         { "name": "email", "type": "JSON", "mode": "NULLABLE" },
+///         { "name": "tags", "type": "STRING", "mode": "REPEATED" }
+///     ])
+/// );
+/// ```
+pub fn to_bigquery(schema: &SchemaState) -> serde_json::Value {
+    columns(codegen::record_fields(schema))
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+
+    #[test]
+    fn maps_records_repeated_and_nullable_columns() {
+        let schema = SchemaState::Object {
+            required: IndexMap::from_iter([
+                (
+                    "items".to_owned(),
+                    SchemaState::Array {
+                        min_length: 1,
+                        max_length: 3,
+                        unique: false,
+                        schema: Box::new(SchemaState::Object {
+                            required: IndexMap::from_iter([(
+                                "sku-code".to_owned(),
+                                SchemaState::String(StringType::UUID),
+                            )]),
+                            optional: IndexMap::from_iter([(
+                                "price".to_owned(),
+                                SchemaState::Number(NumberType::Decimal {
+                                    scale: 2,
+                                    min: 0.5,
+                                    max: 9.99,
+                                }),
+                            )]),
+                        }),
+                    },
+                ),
+                (
+                    "at".to_owned(),
+                    SchemaState::Nullable(Box::new(SchemaState::String(
+                        StringType::DateTimeISO8601 { range: None },
+                    ))),
+                ),
+                (
+                    "id".to_owned(),
+                    SchemaState::Number(NumberType::BigInteger {
+                        min: 0,
+                        max: u64::MAX.into(),
+                    }),
+                ),
+            ]),
+            optional: IndexMap::new(),
+        };
+
+        assert_eq!(
+            to_bigquery(&schema),
+            json!([
+                {
+                    "name": "items",
+                    "type": "RECORD",
+                    "mode": "REPEATED",
+                    "fields": [
+                        { "name": "sku_code", "type": "STRING", "mode": "REQUIRED" },
+                        { "name": "price", "type": "NUMERIC", "mode": "NULLABLE" }
+                    ]
+                },
+                { "name": "at", "type": "TIMESTAMP", "mode": "NULLABLE" },
+                { "name": "id", "type": "NUMERIC", "mode": "REQUIRED" }
+            ])
+        );
+    }
+}
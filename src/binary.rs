@@ -0,0 +1,263 @@
+//! Reading and writing MessagePack and CBOR, binary formats that hold the same kinds of values
+//! as JSON, as JSON values.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{produce::integer_number, DrivelError};
+
+/// Returns a float as a JSON number, or as the string JSON producers write NaN and infinities
+/// as, which drivel reads back as floats.
+pub(crate) fn float(n: f64) -> serde_json::Value {
+    match serde_json::Number::from_f64(n) {
+        Some(n) => serde_json::Value::Number(n),
+        None if n.is_nan() => serde_json::Value::from("NaN"),
+        None if n > 0.0 => serde_json::Value::from("Infinity"),
+        None => serde_json::Value::from("-Infinity"),
+    }
+}
+
+/// Returns binary data as base64, which drivel recognises as such.
+pub(crate) fn bytes(bytes: &[u8]) -> serde_json::Value {
+    serde_json::Value::String(STANDARD.encode(bytes))
+}
+
+/// Returns a value as the key of an object: strings as they are, and other values as their
+/// JSON.
+fn key(key: serde_json::Value) -> String {
+    match key {
+        serde_json::Value::String(s) => s,
+        key => key.to_string(),
+    }
+}
+
+fn from_msgpack(value: rmpv::Value) -> Result<serde_json::Value, String> {
+    Ok(match value {
+        rmpv::Value::Nil => serde_json::Value::Null,
+        rmpv::Value::Boolean(b) => serde_json::Value::Bool(b),
+        rmpv::Value::Integer(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(n), _) => serde_json::Value::from(n),
+            (None, Some(n)) => serde_json::Value::from(n),
+            (None, None) => return Err(format!("integer {} is out of range", n)),
+        },
+        rmpv::Value::F32(n) => float(n.into()),
+        rmpv::Value::F64(n) => float(n),
+        rmpv::Value::String(s) => match s.into_str() {
+            Some(s) => serde_json::Value::String(s),
+            None => return Err("string is not valid UTF-8".to_owned()),
+        },
+        rmpv::Value::Binary(data) | rmpv::Value::Ext(_, data) => bytes(&data),
+        rmpv::Value::Array(values) => values
+            .into_iter()
+            .map(from_msgpack)
+            .collect::<Result<_, _>>()?,
+        rmpv::Value::Map(entries) => serde_json::Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| Ok((key(from_msgpack(k)?), from_msgpack(v)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+    })
+}
+
+fn to_msgpack(value: &serde_json::Value) -> rmpv::Value {
+    match value {
+        serde_json::Value::Null => rmpv::Value::Nil,
+        serde_json::Value::Bool(b) => rmpv::Value::Boolean(*b),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(n), _) => rmpv::Value::from(n),
+            (None, Some(n)) => rmpv::Value::from(n),
+            (None, None) => rmpv::Value::F64(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => rmpv::Value::from(s.as_str()),
+        serde_json::Value::Array(values) => {
+            rmpv::Value::Array(values.iter().map(to_msgpack).collect())
+        }
+        serde_json::Value::Object(fields) => rmpv::Value::Map(
+            fields
+                .iter()
+                .map(|(k, v)| (rmpv::Value::from(k.as_str()), to_msgpack(v)))
+                .collect(),
+        ),
+    }
+}
+
+fn from_cbor(value: ciborium::Value) -> Result<serde_json::Value, String> {
+    Ok(match value {
+        ciborium::Value::Null => serde_json::Value::Null,
+        ciborium::Value::Bool(b) => serde_json::Value::Bool(b),
+        ciborium::Value::Integer(n) => serde_json::Value::Number(integer_number(n.into())),
+        ciborium::Value::Float(n) => float(n),
+        ciborium::Value::Text(s) => serde_json::Value::String(s),
+        ciborium::Value::Bytes(data) => bytes(&data),
+        // tags, like those of dates, annotate the value they hold
+        ciborium::Value::Tag(_, value) => from_cbor(*value)?,
+        ciborium::Value::Array(values) => values
+            .into_iter()
+            .map(from_cbor)
+            .collect::<Result<_, _>>()?,
+        ciborium::Value::Map(entries) => serde_json::Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| Ok((key(from_cbor(k)?), from_cbor(v)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        value => return Err(format!("{:?} has no JSON equivalent", value)),
+    })
+}
+
+/// Returns a value read at the top level of a stream, which holds records: arrays or maps.
+/// Anything else is more likely to be input in another format, as every ASCII character is a
+/// small integer in MessagePack, than a stream of plain values.
+fn record(value: serde_json::Value) -> Result<serde_json::Value, String> {
+    match value {
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => Ok(value),
+        value => Err(format!(
+            "expected an array or a map at the top level, found {}",
+            value
+        )),
+    }
+}
+
+/// Reads the values of a stream, one after the other, until the input is exhausted.
+fn read_stream<T>(
+    mut input: &[u8],
+    mut read: impl FnMut(&mut &[u8]) -> Result<T, DrivelError>,
+) -> Result<Vec<T>, DrivelError> {
+    let mut values = vec![];
+    while !input.is_empty() {
+        values.push(read(&mut input)?);
+    }
+    Ok(values)
+}
+
+/// Parses MessagePack: one array or map, or a stream of them written one after the other.
+pub(crate) fn parse_msgpack(input: &[u8]) -> Result<Vec<serde_json::Value>, DrivelError> {
+    read_stream(input, |input| {
+        rmpv::decode::read_value(input)
+            .map_err(|err| err.to_string())
+            .and_then(from_msgpack)
+            .and_then(record)
+            .map_err(DrivelError::InvalidMsgpack)
+    })
+}
+
+/// Parses CBOR: one array or map, or a sequence of them (RFC 8742) written one after the
+/// other.
+pub(crate) fn parse_cbor(input: &[u8]) -> Result<Vec<serde_json::Value>, DrivelError> {
+    read_stream(input, |input| {
+        ciborium::de::from_reader::<ciborium::Value, _>(input)
+            .map_err(|err| err.to_string())
+            .and_then(from_cbor)
+            .and_then(record)
+            .map_err(DrivelError::InvalidCbor)
+    })
+}
+
+/// Writes a value as MessagePack.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::write_msgpack;
+/// use serde_json::json;
+///
+/// let mut out = vec![];
+/// write_msgpack(&json!({ "a": 1 }), &mut out).unwrap();
+/// assert_eq!(out, [0x81, 0xa1, b'a', 0x01]);
+/// ```
+pub fn write_msgpack(
+    value: &serde_json::Value,
+    writer: &mut impl std::io::Write,
+) -> Result<(), DrivelError> {
+    rmpv::encode::write_value(writer, &to_msgpack(value)).map_err(std::io::Error::from)?;
+    Ok(())
+}
+
+/// Writes a value as CBOR.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::write_cbor;
+/// use serde_json::json;
+///
+/// let mut out = vec![];
+/// write_cbor(&json!({ "a": 1 }), &mut out).unwrap();
+/// assert_eq!(out, [0xa1, 0x61, b'a', 0x01]);
+/// ```
+pub fn write_cbor(
+    value: &serde_json::Value,
+    writer: &mut impl std::io::Write,
+) -> Result<(), DrivelError> {
+    ciborium::ser::into_writer(value, writer).map_err(|err| match err {
+        ciborium::ser::Error::Io(err) => DrivelError::Io(err),
+        ciborium::ser::Error::Value(message) => DrivelError::InvalidCbor(message),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reads_binary_data_and_non_string_keys() {
+        let value = rmpv::Value::Map(vec![
+            (rmpv::Value::from(1), rmpv::Value::Binary(vec![1, 2, 3])),
+            (rmpv::Value::from("nan"), rmpv::Value::F64(f64::NAN)),
+            (rmpv::Value::from("big"), rmpv::Value::from(u64::MAX)),
+        ]);
+        let mut input = vec![];
+        rmpv::encode::write_value(&mut input, &value).unwrap();
+        rmpv::encode::write_value(&mut input, &rmpv::Value::Array(vec![])).unwrap();
+
+        assert_eq!(
+            parse_msgpack(&input).unwrap(),
+            vec![
+                json!({ "1": "AQID", "nan": "NaN", "big": u64::MAX }),
+                json!([])
+            ]
+        );
+        assert!(parse_msgpack(&input[..input.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn roundtrips_cbor_sequences() {
+        let values = [
+            json!({ "id": -7, "tags": ["a"], "score": 0.5 }),
+            json!([null]),
+        ];
+        let mut input = vec![];
+        for value in &values {
+            write_cbor(value, &mut input).unwrap();
+        }
+        assert_eq!(parse_cbor(&input).unwrap(), values);
+
+        // a date tagged as an epoch timestamp
+        let tagged = ciborium::Value::Tag(1, Box::new(ciborium::Value::Integer(1.into())));
+        let mut input = vec![];
+        ciborium::ser::into_writer(&ciborium::Value::Array(vec![tagged]), &mut input).unwrap();
+        assert_eq!(parse_cbor(&input).unwrap(), vec![json!([1])]);
+    }
+
+    #[test]
+    fn rejects_values_that_are_not_records_or_have_no_json_equivalent() {
+        // JSON text decodes to a stream of small integers
+        assert_eq!(
+            parse_msgpack(br#"[{"id": 1}]"#).unwrap_err().to_string(),
+            "invalid MessagePack: expected an array or a map at the top level, found 91"
+        );
+
+        // an array holding a string of two bytes that are not UTF-8
+        let input = [0x91, 0xa2, 0xff, 0xfe];
+        assert_eq!(
+            parse_msgpack(&input).unwrap_err().to_string(),
+            "invalid MessagePack: string is not valid UTF-8"
+        );
+
+        // an array holding an unassigned simple value
+        let error = parse_cbor(&[0x81, 0xe0]).unwrap_err().to_string();
+        assert!(error.starts_with("invalid CBOR: "), "{}", error);
+    }
+}
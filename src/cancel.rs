@@ -0,0 +1,93 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The error returned by an operation that was cancelled, or that ran past its deadline.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the operation was cancelled or timed out")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// A token for cooperatively cancelling inference or production, e.g. to bound how long
+/// drivel may run on untrusted input.
+///
+/// Clones of a token share their cancellation state, so a token can be handed to an operation
+/// running on another thread and cancelled from the current one. A token can also carry a
+/// deadline, after which it counts as cancelled.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{
+///     infer_schema_with_cancellation, Cancellation, Cancelled, DrivelError, InferenceOptions,
+/// };
+/// use serde_json::json;
+///
+/// let cancellation = Cancellation::new();
+/// cancellation.cancel();
+///
+/// let result = infer_schema_with_cancellation(
+///     json!({ "name": "Alice" }),
+///     &InferenceOptions::default(),
+///     &cancellation,
+/// );
+/// assert!(matches!(result, Err(DrivelError::Cancelled(Cancelled))));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Cancellation {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl Cancellation {
+    /// Returns a token that is only cancelled when `cancel` is called.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a token that is cancelled once `timeout` has elapsed, or when `cancel` is called.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    /// Returns a token that is cancelled at `deadline`, or when `cancel` is called.
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// Cancels the operations using this token (or any of its clones).
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the token was cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub(crate) fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Checks an optional cancellation token.
+pub(crate) fn check(cancellation: Option<&Cancellation>) -> Result<(), Cancelled> {
+    cancellation.map_or(Ok(()), Cancellation::check)
+}
@@ -0,0 +1,121 @@
+//! Helpers shared by the exporters that describe schemas in the languages of other tools,
+//! like protobuf and SQL dialects.
+
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+
+use crate::SchemaState;
+
+/// A field of an object, as described by an exporter.
+pub(crate) struct Field<'a> {
+    pub key: &'a str,
+    pub schema: &'a SchemaState,
+    pub optional: bool,
+}
+
+/// Returns the fields of an object: the required ones, then the optional ones.
+pub(crate) fn fields<'a>(
+    required: &'a IndexMap<String, SchemaState>,
+    optional: &'a IndexMap<String, SchemaState>,
+) -> Vec<Field<'a>> {
+    let field = |optional| {
+        move |(key, schema): (&'a String, &'a SchemaState)| Field {
+            key,
+            schema,
+            optional,
+        }
+    };
+    required
+        .iter()
+        .map(field(false))
+        .chain(optional.iter().map(field(true)))
+        .collect()
+}
+
+/// Returns the fields describing each record: those of the elements of an array at the root,
+/// or of the root itself. Records that are not objects are held in a single `value` field.
+pub(crate) fn record_fields(schema: &SchemaState) -> Vec<Field<'_>> {
+    let record = match schema {
+        SchemaState::Array { schema, .. } => schema,
+        schema => schema,
+    };
+    match record {
+        SchemaState::Object { required, optional } => fields(required, optional),
+        record => vec![Field {
+            key: "value",
+            schema: record,
+            optional: false,
+        }],
+    }
+}
+
+/// Returns `name`, or `name` followed by the first number that makes it unique among `taken`.
+pub(crate) fn unique_name(name: String, taken: &mut HashSet<String>) -> String {
+    let mut candidate = name.clone();
+    let mut n = 2;
+    while !taken.insert(candidate.clone()) {
+        candidate = format!("{}{}", name, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// Turns a key into a field name, which may only hold letters, digits and underscores and
+/// cannot start with a digit.
+pub(crate) fn field_name(key: &str) -> String {
+    let name = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("field_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Turns a field name into the name of a message or enum, e.g. `shipping_address` into
+/// `ShippingAddress`.
+pub(crate) fn pascal_case(name: &str) -> String {
+    let pascal = name
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect::<String>();
+    if pascal.is_empty() {
+        "Field".to_owned()
+    } else {
+        pascal
+    }
+}
+
+/// Turns a name or string into the name of an enum value, e.g. `inReview` into `IN_REVIEW`.
+pub(crate) fn screaming_snake_case(s: &str) -> String {
+    let mut name = String::new();
+    let mut previous: Option<char> = None;
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase()
+                && previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+            {
+                name.push('_');
+            }
+            name.push(c.to_ascii_uppercase());
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+        previous = Some(c);
+    }
+    let name = name.trim_end_matches('_');
+    if name.is_empty() {
+        "EMPTY".to_owned()
+    } else {
+        name.to_owned()
+    }
+}
@@ -0,0 +1,647 @@
+//! Deriving the values produced at some paths from the other fields of the same object, so that
+//! the fields of a record agree with one another, like an `email_domain` with its `email`.
+
+use std::cmp::Ordering;
+
+use serde_json::Value;
+
+use crate::{
+    path::{self, parse_steps, visit_mut, Step},
+    DrivelError,
+};
+
+/// The functions that expressions can call, with the number of arguments they take.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Domain,
+    Lower,
+    Upper,
+    Concat,
+    If,
+    IsNull,
+    Not,
+    Coalesce,
+    Min,
+    Max,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "domain" => Some(Self::Domain),
+            "lower" => Some(Self::Lower),
+            "upper" => Some(Self::Upper),
+            "concat" => Some(Self::Concat),
+            "if" => Some(Self::If),
+            "is_null" => Some(Self::IsNull),
+            "not" => Some(Self::Not),
+            "coalesce" => Some(Self::Coalesce),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the function takes `n` arguments.
+    fn takes(self, n: usize) -> bool {
+        match self {
+            Self::Domain | Self::Lower | Self::Upper | Self::IsNull | Self::Not => n == 1,
+            Self::If => n == 3,
+            Self::Concat | Self::Coalesce | Self::Min | Self::Max => n >= 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// An expression that derives a value from the fields of an object.
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Literal(Value),
+    /// A field of the object, or a field nested within it, like `address.city`.
+    Field(Vec<String>),
+    Call(Function, Vec<Expr>),
+    Compare(Box<Expr>, Comparison, Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Str(String),
+    Number(serde_json::Number),
+    Comparison(Comparison),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = expression.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Comma,
+                });
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => s.push(escaped),
+                            None => return Err("unterminated string".to_owned()),
+                        },
+                        Some((_, quote)) if quote == c => break,
+                        Some((_, other)) => s.push(other),
+                        None => return Err("unterminated string".to_owned()),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let followed_by_eq = chars.next_if(|&(_, next)| next == '=').is_some();
+                let comparison = match (c, followed_by_eq) {
+                    ('=', true) => Comparison::Eq,
+                    ('!', true) => Comparison::Ne,
+                    ('<', false) => Comparison::Lt,
+                    ('<', true) => Comparison::Le,
+                    ('>', false) => Comparison::Gt,
+                    ('>', true) => Comparison::Ge,
+                    _ => return Err(format!("unexpected `{}` at position {}", c, start)),
+                };
+                tokens.push(Token::Comparison(comparison));
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut end = start;
+                while let Some((idx, c)) = chars.next_if(|&(idx, c)| {
+                    c.is_ascii_digit() || c == '.' || (idx == start && c == '-')
+                }) {
+                    end = idx + c.len_utf8();
+                }
+                let number = &expression[start..end];
+                let number = serde_json::from_str(number)
+                    .map_err(|_| format!("invalid number `{}`", number))?;
+                tokens.push(Token::Number(number));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some((idx, c)) =
+                    chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_' || c == '.')
+                {
+                    end = idx + c.len_utf8();
+                }
+                tokens.push(Token::Name(expression[start..end].to_owned()));
+            }
+            c => return Err(format!("unexpected `{}` at position {}", c, start)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let left = self.operand()?;
+        match self.peek() {
+            Some(&Token::Comparison(comparison)) => {
+                self.position += 1;
+                let right = self.operand()?;
+                Ok(Expr::Compare(Box::new(left), comparison, Box::new(right)))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::String(s))),
+            Some(Token::Number(n)) => Ok(Expr::Literal(Value::Number(n))),
+            Some(Token::Open) => {
+                let expr = self.expression()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err("expected `)`".to_owned()),
+                }
+            }
+            Some(Token::Name(name)) if self.peek() == Some(&Token::Open) => {
+                self.position += 1;
+                let function = Function::from_name(&name)
+                    .ok_or_else(|| format!("unknown function `{}`", name))?;
+                let mut args = vec![];
+                if self.peek() == Some(&Token::Close) {
+                    self.position += 1;
+                } else {
+                    loop {
+                        args.push(self.expression()?);
+                        match self.next() {
+                            Some(Token::Comma) => continue,
+                            Some(Token::Close) => break,
+                            _ => return Err(format!("expected `,` or `)` in `{}`", name)),
+                        }
+                    }
+                }
+                if !function.takes(args.len()) {
+                    return Err(format!("`{}` does not take {} arguments", name, args.len()));
+                }
+                Ok(Expr::Call(function, args))
+            }
+            Some(Token::Name(name)) => Ok(match name.as_str() {
+                "null" => Expr::Literal(Value::Null),
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                _ => Expr::Field(name.split('.').map(str::to_owned).collect()),
+            }),
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_owned()),
+        }
+    }
+}
+
+fn parse_expression(expression: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+    };
+    let expr = parser.expression()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {:?}", token)),
+    }
+}
+
+/// Orders two values: numbers by their value, strings holding dates or date-times by the
+/// moment they hold, and other strings alphabetically.
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => left.as_f64()?.partial_cmp(&right.as_f64()?),
+        (Value::String(left), Value::String(right)) => {
+            if let (Ok(left), Ok(right)) = (
+                chrono::DateTime::parse_from_rfc3339(left),
+                chrono::DateTime::parse_from_rfc3339(right),
+            ) {
+                Some(left.cmp(&right))
+            } else if let (Ok(left), Ok(right)) = (
+                left.parse::<chrono::NaiveDate>(),
+                right.parse::<chrono::NaiveDate>(),
+            ) {
+                Some(left.cmp(&right))
+            } else {
+                Some(left.cmp(right))
+            }
+        }
+        (Value::Bool(left), Value::Bool(right)) => Some(left.cmp(right)),
+        _ => None,
+    }
+}
+
+/// Describes a value by its type, for errors.
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn truthy(value: &Value) -> Result<bool, String> {
+    match value {
+        Value::Null => Ok(false),
+        Value::Bool(b) => Ok(*b),
+        value => Err(format!("expected a condition, found {}", type_name(value))),
+    }
+}
+
+impl Expr {
+    /// Evaluates the expression against the fields of `object`. Missing fields are null, and
+    /// so are functions of null, apart from `if`, `is_null`, `not` and `coalesce`.
+    fn eval(&self, object: &serde_json::Map<String, Value>) -> Result<Value, String> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Field(keys) => {
+                let mut value = object.get(&keys[0]);
+                for key in &keys[1..] {
+                    value = value.and_then(|value| value.get(key));
+                }
+                Ok(value.cloned().unwrap_or_default())
+            }
+            Expr::Compare(left, comparison, right) => {
+                let (left, right) = (left.eval(object)?, right.eval(object)?);
+                match comparison {
+                    Comparison::Eq => return Ok(Value::Bool(left == right)),
+                    Comparison::Ne => return Ok(Value::Bool(left != right)),
+                    _ if left.is_null() || right.is_null() => return Ok(Value::Null),
+                    _ => {}
+                }
+                let ordering = compare(&left, &right).ok_or_else(|| {
+                    format!(
+                        "unable to compare {} to {}",
+                        type_name(&left),
+                        type_name(&right)
+                    )
+                })?;
+                Ok(Value::Bool(match comparison {
+                    Comparison::Lt => ordering.is_lt(),
+                    Comparison::Le => ordering.is_le(),
+                    Comparison::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                }))
+            }
+            Expr::Call(Function::If, args) => {
+                if truthy(&args[0].eval(object)?)? {
+                    args[1].eval(object)
+                } else {
+                    args[2].eval(object)
+                }
+            }
+            Expr::Call(Function::Coalesce, args) => {
+                for arg in args {
+                    let value = arg.eval(object)?;
+                    if !value.is_null() {
+                        return Ok(value);
+                    }
+                }
+                Ok(Value::Null)
+            }
+            Expr::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(object))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match function {
+                    Function::IsNull => return Ok(Value::Bool(args[0].is_null())),
+                    Function::Not => return Ok(Value::Bool(!truthy(&args[0])?)),
+                    _ if args.iter().any(Value::is_null) => return Ok(Value::Null),
+                    _ => {}
+                }
+                call(*function, args)
+            }
+        }
+    }
+}
+
+/// Calls one of the functions that are null for null arguments, with arguments that are not.
+fn call(function: Function, mut args: Vec<Value>) -> Result<Value, String> {
+    let string = |value: &Value| match value {
+        Value::String(s) => Ok(s.clone()),
+        value => Err(format!("expected a string, found {}", type_name(value))),
+    };
+    match function {
+        Function::Domain => {
+            let s = string(&args[0])?;
+            Ok(match s.rsplit_once('@') {
+                Some((_, domain)) => Value::String(domain.to_owned()),
+                None => url::Url::parse(&s)
+                    .ok()
+                    .and_then(|url| url.host_str().map(|host| Value::String(host.to_owned())))
+                    .unwrap_or_default(),
+            })
+        }
+        Function::Lower => Ok(Value::String(string(&args[0])?.to_lowercase())),
+        Function::Upper => Ok(Value::String(string(&args[0])?.to_uppercase())),
+        Function::Concat => Ok(Value::String(
+            args.iter()
+                .map(|arg| match arg {
+                    Value::String(s) => s.clone(),
+                    arg => arg.to_string(),
+                })
+                .collect(),
+        )),
+        Function::Min | Function::Max => {
+            let wanted = if function == Function::Min {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            };
+            let mut best = args.remove(0);
+            for arg in args {
+                let ordering = compare(&arg, &best).ok_or_else(|| {
+                    format!(
+                        "unable to compare {} to {}",
+                        type_name(&arg),
+                        type_name(&best)
+                    )
+                })?;
+                if ordering == wanted {
+                    best = arg;
+                }
+            }
+            Ok(best)
+        }
+        Function::If | Function::IsNull | Function::Not | Function::Coalesce => {
+            unreachable!("these functions are evaluated before their arguments are")
+        }
+    }
+}
+
+/// A derivation with its path parsed into the steps to the objects it derives a field of.
+#[derive(Clone, Debug)]
+struct Derivation {
+    /// The path as it was given, to report errors with.
+    path: String,
+    parent: Vec<Step>,
+    field: String,
+    expr: Expr,
+}
+
+/// A set of derivations that compute the values produced at some paths from the other fields of
+/// the same object after it is produced, e.g. `email_domain` from `email`, so that the fields of
+/// each record are consistent with one another.
+///
+/// Expressions refer to the fields of the object by name, nested fields like `address.city`,
+/// and call the functions `domain`, `lower`, `upper`, `concat`, `if`, `is_null`, `not`,
+/// `coalesce`, `min` and `max`. Values compare with `==`, `!=`, `<`, `<=`, `>` and `>=`,
+/// dates and date-times by the moment they hold. Derivations are evaluated in the order they
+/// are added, so later ones see the values derived by earlier ones.
+///
+/// Paths use the same notation as overrides, and are relative to the elements of an array at
+/// the root.
+#[derive(Clone, Debug, Default)]
+pub struct Derivations {
+    derivations: Vec<Derivation>,
+}
+
+impl Derivations {
+    /// Returns an empty set of derivations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if no derivations have been added.
+    pub fn is_empty(&self) -> bool {
+        self.derivations.is_empty()
+    }
+
+    /// Derives the value produced at `path`, a field of an object, from `expression`.
+    pub fn insert(&mut self, path: &str, expression: &str) -> Result<(), DrivelError> {
+        let invalid = |message: String| DrivelError::InvalidDerivation {
+            path: path.to_owned(),
+            message,
+        };
+        let mut parent = parse_steps(path);
+        let field = match parent.pop() {
+            Some(Step::Key(field)) => field,
+            _ => {
+                return Err(invalid(
+                    "expected the path of a field of an object".to_owned(),
+                ))
+            }
+        };
+        let expr = parse_expression(expression).map_err(invalid)?;
+        self.derivations.push(Derivation {
+            path: path.to_owned(),
+            parent,
+            field,
+            expr,
+        });
+        Ok(())
+    }
+
+    /// Parses derivations from a TOML document mapping paths to the expressions their values
+    /// are derived from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::Derivations;
+    ///
+    /// let derivations = Derivations::from_toml(r#"
+    ///     "users[].email_domain" = "domain(email)"
+    ///     "users[].user_name" = "if(is_null(user_id), null, user_name)"
+    ///     "users[].updated_at" = "max(updated_at, created_at)"
+    /// "#).unwrap();
+    ///
+    /// assert!(!derivations.is_empty());
+    /// ```
+    pub fn from_toml(document: &str) -> Result<Self, DrivelError> {
+        let table: serde_json::Map<String, Value> = toml::from_str(document)?;
+
+        let mut derivations = Self::new();
+        for (path, expression) in table {
+            match expression {
+                Value::String(expression) => derivations.insert(&path, &expression)?,
+                expression => {
+                    return Err(DrivelError::InvalidDerivation {
+                        path,
+                        message: format!("expected an expression, found {}", expression),
+                    })
+                }
+            }
+        }
+        Ok(derivations)
+    }
+
+    /// Replaces the values of the fields at the paths of the derivations by the values derived
+    /// for them, within `value`. Fields that were not produced are not added.
+    pub(crate) fn apply(&self, value: &mut Value, root_is_array: bool) -> Result<(), DrivelError> {
+        self.apply_from(value, |steps| path::from_root(steps, root_is_array))
+    }
+
+    /// Derives values like `apply` does, within one of the elements of an array at the root.
+    pub(crate) fn apply_to_record(&self, record: &mut Value) -> Result<(), DrivelError> {
+        self.apply_from(record, path::from_record)
+    }
+
+    fn apply_from(
+        &self,
+        value: &mut Value,
+        steps: impl Fn(Vec<Step>) -> Vec<Step>,
+    ) -> Result<(), DrivelError> {
+        for derivation in &self.derivations {
+            let mut result = Ok(());
+            visit_mut(value, &steps(derivation.parent.clone()), &mut |object| {
+                let Value::Object(object) = object else {
+                    return;
+                };
+                if result.is_err() || !object.contains_key(&derivation.field) {
+                    return;
+                }
+                match derivation.expr.eval(object) {
+                    Ok(derived) => {
+                        object.insert(derivation.field.clone(), derived);
+                    }
+                    Err(message) => {
+                        result = Err(DrivelError::InvalidDerivation {
+                            path: derivation.path.clone(),
+                            message,
+                        })
+                    }
+                }
+            });
+            result?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn derives_fields_from_the_other_fields_of_their_object() {
+        let derivations = Derivations::from_toml(
+            r#"
+            "users[].email_domain" = "domain(email)"
+            "users[].user_name" = "if(is_null(user_id), null, user_name)"
+            "users[].updated_at" = "max(updated_at, created_at)"
+            "users[].label" = "concat(upper(user_name), '-', user_id)"
+            "#,
+        )
+        .unwrap();
+        let mut value = json!([
+            {
+                "users": [
+                    {
+                        "email": "ada@example.com",
+                        "email_domain": "nowhere.org",
+                        "user_id": 7,
+                        "user_name": "ada",
+                        "created_at": "2024-05-01T10:00:00Z",
+                        "updated_at": "2024-04-01T10:00:00+02:00",
+                        "label": "",
+                    },
+                    {
+                        "email": null,
+                        "email_domain": "nowhere.org",
+                        "user_id": null,
+                        "user_name": "bob",
+                        "created_at": "2024-05-01",
+                        "updated_at": "2024-06-01",
+                    },
+                ]
+            }
+        ]);
+
+        derivations.apply(&mut value, true).unwrap();
+        assert_eq!(
+            value[0]["users"][0],
+            json!({
+                "email": "ada@example.com",
+                "email_domain": "example.com",
+                "user_id": 7,
+                "user_name": "ada",
+                "created_at": "2024-05-01T10:00:00Z",
+                "updated_at": "2024-05-01T10:00:00Z",
+                "label": "ADA-7",
+            })
+        );
+        assert_eq!(
+            value[0]["users"][1],
+            json!({
+                "email": null,
+                "email_domain": null,
+                "user_id": null,
+                "user_name": null,
+                "created_at": "2024-05-01",
+                "updated_at": "2024-06-01",
+            })
+        );
+    }
+
+    #[test]
+    fn derives_fields_within_records() {
+        let mut derivations = Derivations::new();
+        derivations.insert("[].is_adult", "age >= 18 ").unwrap();
+        let mut record = json!({ "age": 17, "is_adult": true });
+        derivations.apply_to_record(&mut record).unwrap();
+        assert_eq!(record, json!({ "age": 17, "is_adult": false }));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        let mut derivations = Derivations::new();
+        for expression in ["domain(email", "lower(a, b)", "unknown(a)", "a >", "'open"] {
+            assert!(matches!(
+                derivations.insert("field", expression),
+                Err(DrivelError::InvalidDerivation { .. })
+            ));
+        }
+        assert!(matches!(
+            derivations.insert("tags[]", "lower(tag)"),
+            Err(DrivelError::InvalidDerivation { .. })
+        ));
+
+        derivations.insert("name", "lower(age)").unwrap();
+        assert!(matches!(
+            derivations.apply(&mut json!({ "name": "x", "age": 3 }), false),
+            Err(DrivelError::InvalidDerivation { .. })
+        ));
+    }
+}
@@ -0,0 +1,504 @@
+//! Detecting the formats of strings, like UUIDs and dates, including formats of the user's own.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::{
+    infer_string::{
+        base64, currency_code, date, datetime, duration, email, epoch_seconds, file_path,
+        formatted_date, hostname, identifier, jwt, non_finite_float, phone, semver, storage_uri,
+        text, time, url, uuid, Shape,
+    },
+    pattern, DateFormat, DrivelError, Profile, StringType,
+};
+
+/// Detects a format of strings, e.g. SKUs like `SKU-004211`, so that strings of that format are
+/// inferred as a type of their own rather than as strings of unknown format.
+pub trait StringDetector: Send + Sync {
+    /// The name of the format, e.g. `sku`.
+    fn name(&self) -> &str;
+
+    /// Returns the type of `s` if it is of this format. Strings of the same format should be of
+    /// types that merge with each other, e.g. the same `StringType::Pattern`.
+    fn detect(&self, s: &str) -> Option<StringType>;
+}
+
+/// A format detected by drivel itself.
+struct Builtin {
+    name: &'static str,
+    /// Whether a string of this shape may be of this format, which is checked before the string
+    /// is detected so that most strings are ruled out without parsing them or evaluating a
+    /// regular expression. It must hold for every string of the format.
+    may_match: fn(&Shape) -> bool,
+    detect: fn(&str) -> Option<StringType>,
+}
+
+/// A detector that is tried, either one that was added or a built-in one.
+#[derive(Clone, Copy)]
+enum Detector<'a> {
+    Added(&'a dyn StringDetector),
+    Builtin(&'a Builtin),
+}
+
+impl<'a> Detector<'a> {
+    fn name(self) -> &'a str {
+        match self {
+            Detector::Added(detector) => detector.name(),
+            Detector::Builtin(builtin) => builtin.name,
+        }
+    }
+
+    fn detect(self, s: &str, shape: &Shape) -> Option<StringType> {
+        match self {
+            Detector::Added(detector) => detector.detect(s),
+            Detector::Builtin(builtin) => (builtin.may_match)(shape)
+                .then(|| (builtin.detect)(s))
+                .flatten(),
+        }
+    }
+}
+
+/// The built-in detectors, in the order in which they are tried.
+const BUILTINS: [Builtin; 24] = [
+    Builtin {
+        name: "jwt",
+        may_match: |s| s.dots == 2,
+        detect: jwt,
+    },
+    Builtin {
+        name: "nan",
+        may_match: |s| matches!(s.first, b'N' | b'I' | b'-'),
+        detect: non_finite_float,
+    },
+    Builtin {
+        name: "currency",
+        may_match: |s| s.len == 3 && s.first.is_ascii_uppercase(),
+        detect: currency_code,
+    },
+    Builtin {
+        name: "uuid",
+        may_match: |s| s.len == 36 && s.has_dash,
+        detect: uuid,
+    },
+    Builtin {
+        name: "ulid",
+        may_match: |s| s.len == 26 && s.digits > 0 && s.has_uppercase,
+        detect: |s| identifier(s).filter(|id| *id == StringType::Ulid),
+    },
+    Builtin {
+        name: "ksuid",
+        may_match: |s| s.len == 27 && s.digits > 0 && s.has_uppercase && s.has_lowercase,
+        detect: |s| identifier(s).filter(|id| *id == StringType::Ksuid),
+    },
+    Builtin {
+        name: "objectid",
+        may_match: |s| s.len == 24 && s.digits > 0 && s.has_lowercase,
+        detect: |s| identifier(s).filter(|id| *id == StringType::ObjectId),
+    },
+    Builtin {
+        name: "semver",
+        may_match: |s| s.first.is_ascii_digit() && s.dots >= 2,
+        detect: semver,
+    },
+    Builtin {
+        name: "email",
+        may_match: |s| s.has_at && s.dots > 0,
+        detect: email,
+    },
+    Builtin {
+        name: "storage-uri",
+        may_match: |s| matches!(s.first, b's' | b'g') && s.has_colon && s.has_slash,
+        detect: storage_uri,
+    },
+    Builtin {
+        name: "path",
+        may_match: |s| matches!(s.first, b'/' | b'.' | b'~') || s.has_colon,
+        detect: file_path,
+    },
+    Builtin {
+        name: "url",
+        may_match: |s| s.dots > 0 && s.has_colon,
+        detect: url,
+    },
+    Builtin {
+        name: "hostname",
+        may_match: |s| s.dots > 0 && s.last.is_ascii_alphabetic(),
+        detect: hostname,
+    },
+    Builtin {
+        name: "date",
+        may_match: |s| s.len == 10 && s.first.is_ascii_digit() && s.has_dash,
+        detect: date,
+    },
+    Builtin {
+        name: "datetime",
+        may_match: |s| s.has_colon && s.digits > 0,
+        detect: datetime,
+    },
+    Builtin {
+        name: "date-mdy",
+        may_match: |s| s.len == 10 && s.digits == 8 && s.has_slash,
+        detect: |s| formatted_date(s, DateFormat::MonthDayYear),
+    },
+    Builtin {
+        name: "date-dmy",
+        may_match: |s| s.len == 10 && s.digits == 8 && s.has_dash,
+        detect: |s| formatted_date(s, DateFormat::DayMonthYear),
+    },
+    Builtin {
+        name: "date-compact",
+        may_match: |s| s.len == 8 && s.digits == 8,
+        detect: |s| formatted_date(s, DateFormat::Compact),
+    },
+    Builtin {
+        name: "epoch",
+        may_match: |s| s.len == 10 && s.digits == 10,
+        detect: epoch_seconds,
+    },
+    Builtin {
+        name: "time",
+        may_match: |s| s.has_colon && s.first.is_ascii_digit(),
+        detect: time,
+    },
+    Builtin {
+        name: "duration",
+        may_match: |s| s.first == b'P' && s.len >= 3,
+        detect: duration,
+    },
+    Builtin {
+        name: "phone",
+        may_match: |s| (7..=15).contains(&s.digits),
+        detect: phone,
+    },
+    Builtin {
+        name: "base64",
+        may_match: |s| s.len >= 16 && s.len.is_multiple_of(4) && s.has_uppercase && s.has_lowercase,
+        detect: base64,
+    },
+    Builtin {
+        name: "text",
+        may_match: |s| s.first.is_ascii_uppercase() || !s.first.is_ascii(),
+        detect: text,
+    },
+];
+
+/// Detects strings matching a regular expression, which are produced from it.
+struct PatternDetector {
+    name: String,
+    pattern: String,
+}
+
+impl StringDetector for PatternDetector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn detect(&self, s: &str) -> Option<StringType> {
+        pattern::is_match(&self.pattern, s).then(|| StringType::Pattern(self.pattern.clone()))
+    }
+}
+
+/// The detectors of the formats of strings: those added, tried in the order in which they were
+/// added, and then the built-in ones (`jwt`, `nan`, `currency`, `uuid`, `ulid`, `ksuid`,
+/// `objectid`, `semver`, `email`, `storage-uri`, `path`, `url`, `hostname`, `date`,
+/// `datetime`, `date-mdy`, `date-dmy`, `date-compact`, `epoch`, `time`, `duration`, `phone`,
+/// `base64` and `text`). The first format detected is a string's type, unless its detector is
+/// disabled.
+#[derive(Clone, Default)]
+pub struct Detectors {
+    added: Vec<Arc<dyn StringDetector>>,
+    disabled: HashSet<String>,
+}
+
+impl Detectors {
+    /// Returns the built-in detectors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a detector, tried after those added before it and before the built-in ones.
+    pub fn add(&mut self, detector: impl StringDetector + 'static) {
+        self.added.push(Arc::new(detector));
+    }
+
+    /// Adds a detector of the format `name`: strings that match the regular expression
+    /// `pattern` in their entirety, described and produced as `StringType::Pattern`.
+    pub fn add_pattern(&mut self, name: &str, pattern: &str) -> Result<(), DrivelError> {
+        let invalid = |message| DrivelError::InvalidDetector {
+            name: name.to_owned(),
+            message,
+        };
+        if name.is_empty() {
+            return Err(invalid("expected a name".to_owned()));
+        }
+        // the pattern must match strings in their entirety, not just a part of them
+        let pattern = if pattern.starts_with('^') && pattern.ends_with('$') {
+            pattern.to_owned()
+        } else {
+            format!("^(?:{})$", pattern)
+        };
+        pattern::check(&pattern).map_err(invalid)?;
+        self.add(PatternDetector {
+            name: name.to_owned(),
+            pattern,
+        });
+        Ok(())
+    }
+
+    /// Adds a detector written as `name=pattern`, e.g. `sku=SKU-[0-9]{6}`. See `add_pattern`.
+    pub fn add_spec(&mut self, spec: &str) -> Result<(), DrivelError> {
+        let (name, pattern) = spec
+            .split_once('=')
+            .ok_or_else(|| DrivelError::InvalidDetector {
+                name: spec.to_owned(),
+                message: "expected a detector like `sku=SKU-[0-9]{6}`".to_owned(),
+            })?;
+        self.add_pattern(name.trim(), pattern)
+    }
+
+    /// Parses detectors from a TOML document with a `detectors` table, mapping the names of
+    /// formats to the regular expressions their strings match. They are tried in the order in
+    /// which they are written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema, Detectors, InferenceOptions, SchemaState, StringType};
+    /// use serde_json::json;
+    ///
+    /// let detectors = Detectors::from_toml(r#"
+    ///     [detectors]
+    ///     sku = "SKU-[0-9]{6}"
+    /// "#).unwrap();
+    /// let options = InferenceOptions { detectors, ..Default::default() };
+    ///
+    /// assert_eq!(
+    ///     infer_schema(json!("SKU-004211"), &options),
+    ///     SchemaState::String(StringType::Pattern("^(?:SKU-[0-9]{6})$".to_owned()))
+    /// );
+    /// ```
+    pub fn from_toml(document: &str) -> Result<Self, DrivelError> {
+        let table: serde_json::Map<String, serde_json::Value> = toml::from_str(document)?;
+
+        let mut detectors = Self::new();
+        for (key, value) in table {
+            if key != "detectors" {
+                return Err(DrivelError::InvalidDetector {
+                    name: key,
+                    message: "unknown key, expected a \"detectors\" table".to_owned(),
+                });
+            }
+            let serde_json::Value::Object(formats) = value else {
+                return Err(DrivelError::InvalidDetector {
+                    name: key,
+                    message: "expected a table mapping names to patterns".to_owned(),
+                });
+            };
+            for (name, pattern) in formats {
+                let Some(pattern) = pattern.as_str() else {
+                    return Err(DrivelError::InvalidDetector {
+                        name,
+                        message: "expected a regular expression".to_owned(),
+                    });
+                };
+                detectors.add_pattern(&name, pattern)?;
+            }
+        }
+        Ok(detectors)
+    }
+
+    /// Disables the detector of the format `name`, built-in or added, so that strings are no
+    /// longer detected as that format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema, Detectors, InferenceOptions, SchemaState, StringType};
+    /// use serde_json::json;
+    ///
+    /// let mut detectors = Detectors::new();
+    /// detectors.disable("hostname").unwrap();
+    /// let options = InferenceOptions { detectors, ..Default::default() };
+    ///
+    /// let SchemaState::String(string_type) = infer_schema(json!("file.txt"), &options) else {
+    ///     panic!();
+    /// };
+    /// assert!(matches!(string_type, StringType::Unknown { .. }));
+    /// assert!(Detectors::new().disable("hostnames").is_err());
+    /// ```
+    pub fn disable(&mut self, name: &str) -> Result<(), DrivelError> {
+        if !self.all().any(|detector| detector.name() == name) {
+            let names = self.all().map(Detector::name).collect::<Vec<_>>();
+            return Err(DrivelError::InvalidDetector {
+                name: name.to_owned(),
+                message: format!("unknown format, expected one of {}", names.join(", ")),
+            });
+        }
+        self.disabled.insert(name.to_owned());
+        Ok(())
+    }
+
+    /// Returns the names of the formats detected, in the order in which they are tried.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.detectors().map(Detector::name)
+    }
+
+    /// Returns all detectors, including those that are disabled.
+    fn all(&self) -> impl Iterator<Item = Detector<'_>> {
+        self.added
+            .iter()
+            .map(|detector| Detector::Added(detector.as_ref()))
+            .chain(BUILTINS.iter().map(Detector::Builtin))
+    }
+
+    fn detectors(&self) -> impl Iterator<Item = Detector<'_>> {
+        self.all()
+            .filter(|detector| self.disabled.is_empty() || !self.disabled.contains(detector.name()))
+    }
+
+    /// Returns the name of the first format detected in `s`, if any.
+    pub(crate) fn detect_name(&self, s: &str) -> Option<&str> {
+        let shape = Shape::of(s);
+        self.detectors()
+            .find(|detector| detector.detect(s, &shape).is_some())
+            .map(Detector::name)
+    }
+
+    /// Returns the type of the first format detected in `s`, if any.
+    pub(crate) fn detect(&self, s: &str) -> Option<StringType> {
+        let shape = Shape::of(s);
+        self.detectors()
+            .find_map(|detector| detector.detect(s, &shape))
+    }
+
+    /// Returns the type of the first format detected in `s`, if any, counting the detectors
+    /// tried in `profile`.
+    pub(crate) fn detect_profiled(&self, s: &str, profile: &Profile) -> Option<StringType> {
+        let shape = Shape::of(s);
+        self.detectors()
+            .zip(profile.detector_stats(self))
+            .find_map(|(detector, stats)| stats.tried(|| detector.detect(s, &shape)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema_from_iter, InferenceOptions, SchemaState};
+
+    struct Ticket;
+
+    impl StringDetector for Ticket {
+        fn name(&self) -> &str {
+            "ticket"
+        }
+
+        fn detect(&self, s: &str) -> Option<StringType> {
+            let (project, number) = s.split_once('-')?;
+            (project.chars().all(|c| c.is_ascii_uppercase())
+                && number.chars().all(|c| c.is_ascii_digit()))
+            .then(|| StringType::Pattern("[A-Z]{2,5}-[0-9]{1,5}".to_owned()))
+        }
+    }
+
+    #[test]
+    fn rules_out_no_string_of_a_built_in_format() {
+        let strings = [
+            "eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiIxIn0.c2ln",
+            "eyJhbGciOiJub25lIn0.eyJzdWIiOiIxIn0.",
+            "NaN",
+            "-Infinity",
+            "EUR",
+            "67e55044-10b1-426f-9247-bb680e5fe0c8",
+            "01ARZ3NDEKTSV4RRFFQ69G5FAV",
+            "0ujtsYcgvSTl8PAuAdqWYSMnLOv",
+            "507f1f77bcf86cd799439011",
+            "1.2.3-beta.1+build.5",
+            "ann.lee@example.com",
+            "s3://logs/2024/app.log",
+            "gs://assets/img/logo.png",
+            "/var/log/app.log",
+            "./report.csv",
+            "~/notes.txt",
+            "C:\\Users\\report.pdf",
+            "https://example.com/path?q=1",
+            "  http://example.com",
+            "mailto:ann@example.com",
+            "example.com",
+            "web-3.internal",
+            "2024-03-14",
+            "2024-03-14T09:30:00Z",
+            "2024-03-14T09:30:00.123+02:00",
+            "Thu, 14 Mar 2024 09:30:00 +0000",
+            "03/14/2024",
+            "14-03-2024",
+            "20240314",
+            "1710408600",
+            "09:30",
+            "17:45:00.250",
+            "P3D",
+            "PT1H30M",
+            "+14155550123",
+            "(415) 555-0123",
+            "+44 20 7946 0958",
+            "SGVsbG8sIFdvcmxkIQ==",
+            "Arrived on time.",
+            "Été à Paris cette année",
+            "Übermorgen kommt der Brief",
+        ];
+        for builtin in &BUILTINS {
+            for s in strings {
+                if (builtin.detect)(s).is_some() {
+                    assert!((builtin.may_match)(&Shape::of(s)), "{} {}", builtin.name, s);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tries_added_detectors_before_the_built_in_ones() {
+        let mut detectors = Detectors::new();
+        detectors.add(Ticket);
+        detectors.add_spec("version=v[0-9]+").unwrap();
+        // a detector of the user's own takes precedence over the built-in detector of emails
+        detectors.add_spec("staff=[a-z]+@example\\.com").unwrap();
+        let options = InferenceOptions {
+            detectors,
+            ..Default::default()
+        };
+
+        let schema = infer_schema_from_iter(
+            vec![
+                json!({ "ticket": "OPS-42", "version": "v1", "email": "ann@example.com" }),
+                json!({ "ticket": "WEB-7", "version": "v12", "email": "bob@example.com" }),
+            ],
+            &options,
+        );
+
+        let SchemaState::Object { required, .. } = schema else {
+            panic!("expected an object");
+        };
+        assert_eq!(
+            required["ticket"],
+            SchemaState::String(StringType::Pattern("[A-Z]{2,5}-[0-9]{1,5}".to_owned()))
+        );
+        assert_eq!(
+            required["version"],
+            SchemaState::String(StringType::Pattern("^(?:v[0-9]+)$".to_owned()))
+        );
+        assert_eq!(
+            required["email"],
+            SchemaState::String(StringType::Pattern("^(?:[a-z]+@example\\.com)$".to_owned()))
+        );
+        assert_eq!(
+            options.detectors.names().take(4).collect::<Vec<_>>(),
+            vec!["ticket", "version", "staff", "jwt"]
+        );
+        assert!(matches!(
+            options.detectors.clone().add_spec("sku"),
+            Err(DrivelError::InvalidDetector { .. })
+        ));
+    }
+}
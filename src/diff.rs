@@ -0,0 +1,687 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{path, NumberType, SchemaState, StringType};
+
+/// The way in which a field differs between two schemas.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The field only exists in the new schema.
+    Added,
+    /// The field only exists in the old schema.
+    Removed,
+    /// The value has a different type, or a string has a different format.
+    TypeChanged { from: String, to: String },
+    /// The field is required in the old schema, but optional in the new one.
+    BecameOptional,
+    /// The field is optional in the old schema, but required in the new one.
+    BecameRequired,
+    /// The value may be null in the new schema, but not in the old one.
+    BecameNullable,
+    /// The value may be null in the old schema, but not in the new one.
+    BecameNonNullable,
+    /// The range of a number extends beyond the range in the old schema.
+    RangeWidened { from: String, to: String },
+    /// The length of a string or array extends beyond the lengths in the old schema.
+    LengthWidened { from: String, to: String },
+    /// An enum has variants that the old schema does not have.
+    VariantsAdded { variants: Vec<String> },
+}
+
+impl ChangeKind {
+    /// Returns whether the change may break consumers of data that adhered to the old schema:
+    /// fields that disappear, change type, or may be absent or null, and enums with variants
+    /// they have not seen before. Added fields and widened ranges are not breaking.
+    pub fn is_breaking(&self) -> bool {
+        matches!(
+            self,
+            ChangeKind::Removed
+                | ChangeKind::TypeChanged { .. }
+                | ChangeKind::BecameOptional
+                | ChangeKind::BecameNullable
+                | ChangeKind::VariantsAdded { .. }
+        )
+    }
+}
+
+/// A structural difference between two schemas.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct Change {
+    /// The path of the field, e.g. `users[].address.city`.
+    pub path: String,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match &self.kind {
+            ChangeKind::Added => "added".to_owned(),
+            ChangeKind::Removed => "removed".to_owned(),
+            ChangeKind::TypeChanged { from, to } => format!("changed from {} to {}", from, to),
+            ChangeKind::BecameOptional => "became optional".to_owned(),
+            ChangeKind::BecameRequired => "became required".to_owned(),
+            ChangeKind::BecameNullable => "became nullable".to_owned(),
+            ChangeKind::BecameNonNullable => "is no longer nullable".to_owned(),
+            ChangeKind::RangeWidened { from, to } => {
+                format!("range widened from {} to {}", from, to)
+            }
+            ChangeKind::LengthWidened { from, to } => {
+                format!("length widened from {} to {}", from, to)
+            }
+            ChangeKind::VariantsAdded { variants } => {
+                format!("new enum variants {}", variants.join(", "))
+            }
+        };
+        write!(f, "{}: {}", path::display(&self.path), description)
+    }
+}
+
+/// Names the structure of a schema, leaving out the details (such as ranges and lengths) that
+/// vary between samples of the same data.
+fn structure_name(schema: &SchemaState) -> String {
+    match schema {
+        SchemaState::Initial | SchemaState::Indefinite => "unknown".to_owned(),
+        SchemaState::Null => "null".to_owned(),
+        SchemaState::Nullable(inner) => format!("nullable {}", structure_name(inner)),
+        SchemaState::String(StringType::Unknown { .. } | StringType::Enum { .. }) => {
+            "string".to_owned()
+        }
+        // the range of dates seen varies between samples as well
+        SchemaState::String(StringType::IsoDate { .. }) => "string (date - ISO 8601)".to_owned(),
+        SchemaState::String(StringType::DateTimeISO8601 { .. }) => {
+            "string (datetime - ISO 8601)".to_owned()
+        }
+        SchemaState::String(StringType::DateTimeRFC2822 { .. }) => {
+            "string (datetime - RFC 2822)".to_owned()
+        }
+        SchemaState::String(StringType::FormattedDate { format, .. }) => {
+            format!("string (date - {})", format)
+        }
+        SchemaState::String(StringType::EpochSeconds { .. }) => "string (epoch seconds)".to_owned(),
+        SchemaState::String(StringType::Time { .. }) => "string (time)".to_owned(),
+        SchemaState::String(StringType::Duration { .. }) => "string (duration)".to_owned(),
+        SchemaState::String(StringType::Text { .. }) => "string (text)".to_owned(),
+        SchemaState::String(StringType::FilePath { kind, .. }) => format!("string ({} path)", kind),
+        SchemaState::String(StringType::CurrencyCode { .. }) => "string (currency)".to_owned(),
+        SchemaState::String(StringType::Base64 { .. }) => "string (base64)".to_owned(),
+        SchemaState::String(StringType::EmbeddedJson(inner)) => {
+            format!("string (json: {})", structure_name(inner))
+        }
+        SchemaState::String(StringType::Semver { .. }) => "string (semver)".to_owned(),
+        SchemaState::String(string_type) => string_type.to_string(),
+        SchemaState::Number(
+            NumberType::Integer { .. } | NumberType::Enum { .. } | NumberType::BigInteger { .. },
+        ) => "int".to_owned(),
+        // amounts that happen to be seen without cents are floats, so decimals are as well
+        SchemaState::Number(NumberType::Float { .. } | NumberType::Decimal { .. }) => {
+            "float".to_owned()
+        }
+        SchemaState::Boolean => "boolean".to_owned(),
+        SchemaState::Array { .. } | SchemaState::UnconstrainedArray => "array".to_owned(),
+        SchemaState::Tuple(elements) => format!("tuple ({})", elements.len()),
+        SchemaState::Map {
+            pattern, schema, ..
+        } => {
+            format!("map (/{}/: {})", pattern, structure_name(schema))
+        }
+        SchemaState::Union(branches) => format!(
+            "one of ({})",
+            branches
+                .iter()
+                .map(structure_name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        SchemaState::Object { .. } | SchemaState::UnconstrainedObject => "object".to_owned(),
+        SchemaState::Const(value) => format!("const {}", value),
+    }
+}
+
+fn range<T: PartialEq + Display>(min: T, max: T) -> String {
+    if min == max {
+        min.to_string()
+    } else {
+        format!("{}-{}", min, max)
+    }
+}
+
+/// Returns how the ranges and lengths of `new` extend beyond those of `old`, and the enum
+/// variants that `new` adds, for two schemas of the same structure.
+fn detail_changes(old: &SchemaState, new: &SchemaState) -> Vec<ChangeKind> {
+    fn widens<T: PartialOrd>(old: (T, T), new: (T, T)) -> bool {
+        new.0 < old.0 || new.1 > old.1
+    }
+
+    match (old, new) {
+        (
+            SchemaState::Number(
+                old_type @ (NumberType::Integer { .. } | NumberType::BigInteger { .. }),
+            ),
+            SchemaState::Number(
+                new_type @ (NumberType::Integer { .. } | NumberType::BigInteger { .. }),
+            ),
+        ) => {
+            let (min, max) = old_type.integer_range().unwrap_or_default();
+            let (new_min, new_max) = new_type.integer_range().unwrap_or_default();
+            if widens((min, max), (new_min, new_max)) {
+                vec![ChangeKind::RangeWidened {
+                    from: range(min, max),
+                    to: range(new_min, new_max),
+                }]
+            } else {
+                vec![]
+            }
+        }
+        (
+            SchemaState::Number(
+                NumberType::Float { min, max, .. } | NumberType::Decimal { min, max, .. },
+            ),
+            SchemaState::Number(
+                NumberType::Float {
+                    min: new_min,
+                    max: new_max,
+                    ..
+                }
+                | NumberType::Decimal {
+                    min: new_min,
+                    max: new_max,
+                    ..
+                },
+            ),
+        ) if widens((min, max), (new_min, new_max)) => vec![ChangeKind::RangeWidened {
+            from: range(min, max),
+            to: range(new_min, new_max),
+        }],
+        (
+            SchemaState::String(StringType::Unknown {
+                min_length: Some(min),
+                max_length: Some(max),
+                ..
+            }),
+            SchemaState::String(StringType::Unknown {
+                min_length: Some(new_min),
+                max_length: Some(new_max),
+                ..
+            }),
+        )
+        | (
+            SchemaState::String(StringType::Base64 {
+                min_length: min,
+                max_length: max,
+            }),
+            SchemaState::String(StringType::Base64 {
+                min_length: new_min,
+                max_length: new_max,
+            }),
+        )
+        | (
+            SchemaState::Array {
+                min_length: min,
+                max_length: max,
+                ..
+            },
+            SchemaState::Array {
+                min_length: new_min,
+                max_length: new_max,
+                ..
+            },
+        ) if widens((min, max), (new_min, new_max)) => vec![ChangeKind::LengthWidened {
+            from: range(min, max),
+            to: range(new_min, new_max),
+        }],
+        (
+            SchemaState::String(StringType::Enum { variants }),
+            SchemaState::String(StringType::Enum {
+                variants: new_variants,
+            }),
+        ) => {
+            let mut added = new_variants
+                .difference(variants)
+                .cloned()
+                .collect::<Vec<_>>();
+            added.sort();
+            if added.is_empty() {
+                vec![]
+            } else {
+                vec![ChangeKind::VariantsAdded { variants: added }]
+            }
+        }
+        (
+            SchemaState::Number(NumberType::Enum { variants, .. }),
+            SchemaState::Number(NumberType::Enum {
+                variants: new_variants,
+                ..
+            }),
+        ) => {
+            let mut added = new_variants
+                .difference(variants)
+                .copied()
+                .collect::<Vec<_>>();
+            added.sort();
+            if added.is_empty() {
+                vec![]
+            } else {
+                vec![ChangeKind::VariantsAdded {
+                    variants: added.iter().map(i64::to_string).collect(),
+                }]
+            }
+        }
+        // strings that were not inferred as an enum are checked by the sample of them kept
+        (
+            SchemaState::String(StringType::Enum { variants }),
+            SchemaState::String(StringType::Unknown { strings_seen, .. }),
+        ) => {
+            let mut added = strings_seen
+                .iter()
+                .filter(|s| !variants.contains(*s))
+                .cloned()
+                .collect::<Vec<_>>();
+            added.sort();
+            added.dedup();
+            if added.is_empty() {
+                vec![]
+            } else {
+                vec![ChangeKind::VariantsAdded { variants: added }]
+            }
+        }
+        _ => vec![],
+    }
+}
+
+fn diff_inner(
+    old: &SchemaState,
+    new: &SchemaState,
+    current_path: &str,
+    detailed: bool,
+    changes: &mut Vec<Change>,
+) {
+    let mut change = |kind: ChangeKind| {
+        changes.push(Change {
+            path: current_path.to_owned(),
+            kind,
+        })
+    };
+
+    match (old, new) {
+        // a value without a known type is compatible with any other value
+        (SchemaState::Initial | SchemaState::Indefinite, _)
+        | (_, SchemaState::Initial | SchemaState::Indefinite) => {}
+        (SchemaState::Nullable(old), SchemaState::Nullable(new)) => {
+            diff_inner(old, new, current_path, detailed, changes)
+        }
+        (SchemaState::Nullable(old), new) if new != &SchemaState::Null => {
+            change(ChangeKind::BecameNonNullable);
+            diff_inner(old, new, current_path, detailed, changes);
+        }
+        (old, SchemaState::Nullable(new)) if old != &SchemaState::Null => {
+            change(ChangeKind::BecameNullable);
+            diff_inner(old, new, current_path, detailed, changes);
+        }
+        (
+            SchemaState::Array {
+                schema: old_elements,
+                ..
+            },
+            SchemaState::Array {
+                schema: new_elements,
+                ..
+            },
+        ) => {
+            if detailed {
+                detail_changes(old, new).into_iter().for_each(change);
+            }
+            diff_inner(
+                old_elements,
+                new_elements,
+                &path::elements(current_path),
+                detailed,
+                changes,
+            )
+        }
+        (SchemaState::Tuple(old), SchemaState::Tuple(new)) if old.len() == new.len() => {
+            for (idx, (old, new)) in old.iter().zip(new).enumerate() {
+                diff_inner(old, new, &path::index(current_path, idx), detailed, changes);
+            }
+        }
+        (
+            SchemaState::Object {
+                required: old_required,
+                optional: old_optional,
+            },
+            SchemaState::Object {
+                required: new_required,
+                optional: new_optional,
+            },
+        ) => {
+            let mut keys = old_required
+                .keys()
+                .chain(old_optional.keys())
+                .chain(new_required.keys())
+                .chain(new_optional.keys())
+                .collect::<Vec<_>>();
+            keys.sort();
+            keys.dedup();
+
+            for k in keys {
+                let field_path = path::key(current_path, k);
+                let field_change = |kind| Change {
+                    path: field_path.clone(),
+                    kind,
+                };
+                let old = old_required.get(k).or_else(|| old_optional.get(k));
+                let new = new_required.get(k).or_else(|| new_optional.get(k));
+                match (old, new) {
+                    (Some(old), Some(new)) => {
+                        match (old_required.contains_key(k), new_required.contains_key(k)) {
+                            (true, false) => changes.push(field_change(ChangeKind::BecameOptional)),
+                            (false, true) => changes.push(field_change(ChangeKind::BecameRequired)),
+                            _ => {}
+                        }
+                        diff_inner(old, new, &field_path, detailed, changes);
+                    }
+                    (Some(_), None) => changes.push(field_change(ChangeKind::Removed)),
+                    (None, Some(_)) => changes.push(field_change(ChangeKind::Added)),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        (old, new) => {
+            let (from, to) = (structure_name(old), structure_name(new));
+            if from != to {
+                change(ChangeKind::TypeChanged { from, to });
+            } else if detailed {
+                detail_changes(old, new).into_iter().for_each(change);
+            }
+        }
+    }
+}
+
+/// Compares the structure of two schemas: fields that were added or removed, values whose
+/// type (or string format) changed, and changes to whether fields are optional or nullable.
+///
+/// Details that vary between samples of the same data, like ranges, lengths and enum
+/// variants, are not compared (see `diff_schemas_in_detail`). When both schemas describe an array at their root, paths are
+/// relative to the elements of those arrays.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{diff_schemas, infer_schema, ChangeKind, InferenceOptions};
+/// use serde_json::json;
+///
+/// let opts = InferenceOptions::default();
+/// let old = infer_schema(json!({ "id": 1, "name": "Alice" }), &opts);
+/// let new = infer_schema(json!({ "id": "1", "email": "alice@example.com" }), &opts);
+///
+/// let changes = diff_schemas(&old, &new);
+///
+/// assert_eq!(changes[0].path, "email");
+/// assert_eq!(changes[0].kind, ChangeKind::Added);
+/// assert_eq!(changes[1].to_string(), "id: changed from int to string");
+/// assert_eq!(changes[2].kind, ChangeKind::Removed);
+/// ```
+pub fn diff_schemas(old: &SchemaState, new: &SchemaState) -> Vec<Change> {
+    diff_root(old, new, false)
+}
+
+/// Compares two schemas like `diff_schemas` does, and also reports the ranges of numbers and
+/// the lengths of strings and arrays that extend beyond those of the old schema, and the enum
+/// variants that the new schema adds. Ranges that narrowed are not reported, as a smaller
+/// sample of the same data yields narrower ranges, nor are changes to the range of dates.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{diff_schemas_in_detail, infer_schema, ChangeKind, InferenceOptions};
+/// use serde_json::json;
+///
+/// let opts = InferenceOptions::default();
+/// let old = infer_schema(json!([{ "age": 30 }, { "age": 40 }]), &opts);
+/// let new = infer_schema(json!([{ "age": 18 }, { "age": 35 }]), &opts);
+///
+/// let changes = diff_schemas_in_detail(&old, &new);
+///
+/// assert_eq!(changes[0].to_string(), "age: range widened from 30-40 to 18-35");
+/// assert!(!changes[0].kind.is_breaking());
+/// ```
+pub fn diff_schemas_in_detail(old: &SchemaState, new: &SchemaState) -> Vec<Change> {
+    diff_root(old, new, true)
+}
+
+/// Collects the paths of the fields that a schema requires.
+fn required_paths(schema: &SchemaState, current_path: &str, paths: &mut HashSet<String>) {
+    match schema {
+        SchemaState::Nullable(inner) => required_paths(inner, current_path, paths),
+        SchemaState::Array { schema, .. } => {
+            required_paths(schema, &path::elements(current_path), paths)
+        }
+        SchemaState::Tuple(elements) => {
+            for (idx, element) in elements.iter().enumerate() {
+                required_paths(element, &path::index(current_path, idx), paths);
+            }
+        }
+        SchemaState::Object { required, optional } => {
+            for (k, v) in required {
+                let field_path = path::key(current_path, k);
+                required_paths(v, &field_path, paths);
+                paths.insert(field_path);
+            }
+            for (k, v) in optional {
+                required_paths(v, &path::key(current_path, k), paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Checks that a schema is compatible with a baseline schema that data is expected to adhere
+/// to, returning the changes that make it incompatible: fields the baseline requires that are
+/// missing or may be absent, new required fields, values of another type or that may be null,
+/// and enum values outside of the baseline's variants. Widened ranges are compatible.
+///
+/// Strings that were not inferred as an enum are checked against the variants of the baseline
+/// by the sample of strings kept while inferring them (see `InferenceOptions::max_samples`).
+/// An array at the root of either schema holds the records that are compared.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{check_compatibility, infer_schema, parse_json_schema, SchemaDefaults};
+/// use drivel::InferenceOptions;
+/// use serde_json::json;
+///
+/// let baseline = parse_json_schema(
+///     &json!({
+///         "type": "object",
+///         "properties": { "status": { "enum": ["open", "closed"] } },
+///         "required": ["status"]
+///     }),
+///     &SchemaDefaults::default(),
+/// )
+/// .unwrap()
+/// .schema;
+/// let schema = infer_schema(
+///     json!([{ "status": "open" }, { "status": "pending" }]),
+///     &InferenceOptions::default(),
+/// );
+///
+/// let changes = check_compatibility(&baseline, &schema);
+///
+/// assert_eq!(changes[0].to_string(), "status: new enum variants pending");
+/// ```
+pub fn check_compatibility(baseline: &SchemaState, schema: &SchemaState) -> Vec<Change> {
+    fn records(schema: &SchemaState) -> &SchemaState {
+        match schema {
+            SchemaState::Array { schema, .. } => schema,
+            schema => schema,
+        }
+    }
+    let (baseline, schema) = (records(baseline), records(schema));
+
+    let mut baseline_required = HashSet::new();
+    required_paths(baseline, "", &mut baseline_required);
+    let mut required = HashSet::new();
+    required_paths(schema, "", &mut required);
+
+    diff_schemas_in_detail(baseline, schema)
+        .into_iter()
+        .filter(|change| match &change.kind {
+            ChangeKind::Added => required.contains(&change.path),
+            ChangeKind::Removed => baseline_required.contains(&change.path),
+            ChangeKind::TypeChanged { .. }
+            | ChangeKind::BecameOptional
+            | ChangeKind::BecameNullable
+            | ChangeKind::VariantsAdded { .. } => true,
+            ChangeKind::BecameRequired
+            | ChangeKind::BecameNonNullable
+            | ChangeKind::RangeWidened { .. }
+            | ChangeKind::LengthWidened { .. } => false,
+        })
+        .collect()
+}
+
+fn diff_root(old: &SchemaState, new: &SchemaState, detailed: bool) -> Vec<Change> {
+    let (old, new) = match (old, new) {
+        (SchemaState::Array { schema: old, .. }, SchemaState::Array { schema: new, .. }) => {
+            (old.as_ref(), new.as_ref())
+        }
+        other => other,
+    };
+    let mut changes = vec![];
+    diff_inner(old, new, "", detailed, &mut changes);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema, infer_schema_from_iter, InferenceOptions};
+
+    #[test]
+    fn reports_optionality_and_nullability_changes() {
+        let options = InferenceOptions::default();
+        let old = infer_schema_from_iter(
+            vec![
+                json!({"a": 1, "b": "x", "c": [true]}),
+                json!({"a": 2, "b": "y", "c": []}),
+            ],
+            &options,
+        );
+        let new = infer_schema_from_iter(
+            vec![
+                json!({"a": null, "c": [1]}),
+                json!({"a": 2, "b": "y", "c": [2]}),
+            ],
+            &options,
+        );
+
+        assert_eq!(
+            diff_schemas(&old, &new),
+            vec![
+                Change {
+                    path: "a".to_owned(),
+                    kind: ChangeKind::BecameNullable
+                },
+                Change {
+                    path: "b".to_owned(),
+                    kind: ChangeKind::BecameOptional
+                },
+                Change {
+                    path: "c[]".to_owned(),
+                    kind: ChangeKind::TypeChanged {
+                        from: "boolean".to_owned(),
+                        to: "int".to_owned()
+                    }
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_widened_ranges_and_new_variants_in_detail() {
+        let options = InferenceOptions {
+            enum_inference: Some(crate::EnumInference {
+                max_unique_ratio: 0.8,
+                min_sample_size: 2,
+            }),
+            ..Default::default()
+        };
+        let old = infer_schema(
+            json!([
+                json!({"n": 5, "status": "open", "tags": ["a"]}),
+                json!({"n": 7, "status": "open", "tags": ["b"]}),
+                json!({"n": 9, "status": "closed", "tags": ["c"]}),
+                json!({"n": 6, "status": "open", "tags": ["d"]}),
+            ]),
+            &options,
+        );
+        let new = infer_schema(
+            json!([
+                json!({"n": 6, "status": "open", "tags": ["a", "b"]}),
+                json!({"n": 7, "status": "open", "tags": ["c"]}),
+                json!({"n": 8, "status": "pending", "tags": ["d"]}),
+                json!({"n": 6, "status": "open", "tags": ["e"]}),
+            ]),
+            &options,
+        );
+
+        let changes = diff_schemas_in_detail(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                Change {
+                    path: "status".to_owned(),
+                    kind: ChangeKind::VariantsAdded {
+                        variants: vec!["pending".to_owned()]
+                    }
+                },
+                Change {
+                    path: "tags".to_owned(),
+                    kind: ChangeKind::LengthWidened {
+                        from: "1".to_owned(),
+                        to: "1-2".to_owned()
+                    }
+                },
+            ]
+        );
+        assert!(changes[0].kind.is_breaking());
+        assert!(!changes[1].kind.is_breaking());
+        assert_eq!(diff_schemas(&old, &new), vec![]);
+    }
+
+    #[test]
+    fn checks_required_fields_against_the_baseline() {
+        let options = InferenceOptions::default();
+        let baseline = infer_schema_from_iter(
+            vec![json!({"id": 1, "note": "x"}), json!({"id": 2})],
+            &options,
+        );
+        let schema = infer_schema_from_iter(
+            vec![
+                json!({"id": 300, "code": "a", "extra": true}),
+                json!({"code": "b"}),
+            ],
+            &options,
+        );
+
+        assert_eq!(
+            check_compatibility(&baseline, &schema),
+            vec![
+                Change {
+                    path: "code".to_owned(),
+                    kind: ChangeKind::Added
+                },
+                Change {
+                    path: "id".to_owned(),
+                    kind: ChangeKind::BecameOptional
+                },
+            ]
+        );
+    }
+}
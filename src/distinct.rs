@@ -0,0 +1,161 @@
+//! Estimating the number of distinct values seen in constant memory, with a HyperLogLog sketch.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// The number of bits of a hash that select its register.
+const PRECISION: u32 = 12;
+
+/// The number of registers of a sketch, 4 KiB of them.
+const REGISTERS: usize = 1 << PRECISION;
+
+/// The number of hashes kept exactly before they take up as much memory as the registers.
+const MAX_EXACT: usize = REGISTERS / std::mem::size_of::<u64>();
+
+/// The number of distinct values seen, counted exactly for up to 512 values and estimated
+/// with a HyperLogLog sketch beyond that, to within about 2%. Either way it takes up at most
+/// 4 KiB, however many values are seen.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::DistinctCount;
+///
+/// let mut count = DistinctCount::from_iter(["a", "b", "a"]);
+/// assert_eq!(count.estimate(), 2);
+///
+/// for n in 0..100_000 {
+///     count.insert(&n.to_string());
+/// }
+/// let estimate = count.estimate() as f64;
+/// assert!((estimate - 100_002.0).abs() / 100_002.0 < 0.05);
+/// ```
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct DistinctCount {
+    /// The hashes of the values seen, sorted, while there are few enough to keep.
+    hashes: Vec<u64>,
+    /// The registers of the sketch, once there are too many hashes to keep; empty before.
+    registers: Vec<u8>,
+}
+
+fn hash(value: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl DistinctCount {
+    /// Returns a count of no values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts `value`, unless it was seen before.
+    pub fn insert(&mut self, value: &str) {
+        self.insert_hash(hash(value));
+    }
+
+    fn insert_hash(&mut self, hash: u64) {
+        if !self.registers.is_empty() {
+            let register = (hash >> (u64::BITS - PRECISION)) as usize;
+            // the position of the first set bit among the bits that do not select the register
+            let rank = ((hash << PRECISION) | (1 << (PRECISION - 1))).leading_zeros() as u8 + 1;
+            self.registers[register] = self.registers[register].max(rank);
+        } else if let Err(idx) = self.hashes.binary_search(&hash) {
+            self.hashes.insert(idx, hash);
+            if self.hashes.len() > MAX_EXACT {
+                self.registers = vec![0; REGISTERS];
+                for hash in std::mem::take(&mut self.hashes) {
+                    self.insert_hash(hash);
+                }
+            }
+        }
+    }
+
+    /// Returns the count of the values seen by either this count or `other`.
+    pub fn merge(mut self, mut other: Self) -> Self {
+        if self.registers.is_empty() && !other.registers.is_empty() {
+            std::mem::swap(&mut self, &mut other);
+        }
+        if other.registers.is_empty() {
+            for hash in other.hashes {
+                self.insert_hash(hash);
+            }
+        } else {
+            for (register, other_register) in self.registers.iter_mut().zip(other.registers) {
+                *register = (*register).max(other_register);
+            }
+        }
+        self
+    }
+
+    /// Returns the number of distinct values seen, exactly or as estimated.
+    pub fn estimate(&self) -> usize {
+        if self.registers.is_empty() {
+            return self.hashes.len();
+        }
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum = self
+            .registers
+            .iter()
+            .map(|register| 2f64.powi(-i32::from(*register)))
+            .sum::<f64>();
+        let estimate = alpha * m * m / sum;
+        let zeros = self
+            .registers
+            .iter()
+            .filter(|register| **register == 0)
+            .count();
+        // few values are better estimated by how many registers are still empty
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as usize
+        } else {
+            estimate.round() as usize
+        }
+    }
+}
+
+impl<'a> FromIterator<&'a str> for DistinctCount {
+    fn from_iter<T: IntoIterator<Item = &'a str>>(values: T) -> Self {
+        let mut count = Self::new();
+        for value in values {
+            count.insert(value);
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_exact_counts_and_sketches_alike() {
+        let exact = (0..300).map(|n| n.to_string()).collect::<Vec<_>>();
+        let halves = DistinctCount::from_iter(exact[..200].iter().map(String::as_str)).merge(
+            DistinctCount::from_iter(exact[100..].iter().map(String::as_str)),
+        );
+        assert_eq!(halves.estimate(), 300);
+        assert_eq!(
+            halves,
+            DistinctCount::from_iter(exact.iter().map(String::as_str))
+        );
+
+        let values = (0..50_000)
+            .map(|n| format!("user-{}", n))
+            .collect::<Vec<_>>();
+        let sketch = DistinctCount::from_iter(values[..30_000].iter().map(String::as_str));
+        let merged = sketch.clone().merge(DistinctCount::from_iter(
+            values[20_000..].iter().map(String::as_str),
+        ));
+        // a small count merges into a sketch the same way, whichever side it is on
+        assert_eq!(
+            merged.clone().merge(halves.clone()),
+            halves.merge(merged.clone())
+        );
+        let error = (merged.estimate() as f64 - 50_000.0).abs() / 50_000.0;
+        assert!(error < 0.05, "estimated {}", merged.estimate());
+        assert!(merged.estimate() > sketch.estimate());
+    }
+}
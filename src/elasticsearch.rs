@@ -0,0 +1,269 @@
+//! Exporting schemas as Elasticsearch (and OpenSearch) index mappings.
+
+use serde_json::json;
+
+use crate::codegen::{self, Field};
+use crate::{DateFormat, NumberType, SchemaState, StringType};
+
+/// The length beyond which Elasticsearch does not index keywords by default.
+const IGNORE_ABOVE: usize = 256;
+
+/// The share of the strings seen that must hold whitespace, and be distinct, for them to be
+/// indexed as full text rather than as keywords.
+const TEXT_RATIO: f64 = 0.5;
+
+/// Returns true if strings look like free text, like descriptions and messages, rather than
+/// like identifiers, codes and names: most of them hold whitespace and are distinct, or they
+/// are too long to be indexed as keywords.
+fn is_text(strings_seen: &[String], max_length: Option<usize>) -> bool {
+    if max_length.is_some_and(|max_length| max_length > IGNORE_ABOVE) {
+        return true;
+    }
+    if strings_seen.is_empty() {
+        return false;
+    }
+    let n = strings_seen.len() as f64;
+    let with_whitespace = strings_seen
+        .iter()
+        .filter(|s| s.trim().contains(char::is_whitespace))
+        .count() as f64;
+    let distinct = strings_seen
+        .iter()
+        .collect::<std::collections::HashSet<_>>()
+        .len() as f64;
+    with_whitespace / n > TEXT_RATIO && distinct / n > TEXT_RATIO
+}
+
+fn string_mapping(string_type: &StringType) -> serde_json::Value {
+    match string_type {
+        StringType::Unknown {
+            strings_seen,
+            max_length,
+            ..
+        } if is_text(strings_seen, *max_length) => {
+            if max_length.is_some_and(|max_length| max_length <= IGNORE_ABOVE) {
+                // short texts can also be sorted and aggregated on
+                json!({
+                    "type": "text",
+                    "fields": { "keyword": { "type": "keyword", "ignore_above": IGNORE_ABOVE } }
+                })
+            } else {
+                json!({ "type": "text" })
+            }
+        }
+        // texts too long for keywords are left out of the keyword subfield
+        StringType::Text { .. } => json!({
+            "type": "text",
+            "fields": { "keyword": { "type": "keyword", "ignore_above": IGNORE_ABOVE } }
+        }),
+        StringType::DateTimeISO8601 { .. } => json!({ "type": "date" }),
+        StringType::IsoDate { .. } => json!({ "type": "date", "format": "strict_date" }),
+        StringType::DateTimeRFC2822 { .. } => {
+            json!({ "type": "date", "format": "EEE, d MMM yyyy HH:mm:ss Z" })
+        }
+        StringType::FormattedDate { format, .. } => {
+            let format = match format {
+                DateFormat::MonthDayYear => "MM/dd/yyyy",
+                DateFormat::DayMonthYear => "dd-MM-yyyy",
+                DateFormat::Compact => "basic_date",
+            };
+            json!({ "type": "date", "format": format })
+        }
+        StringType::EpochSeconds { .. } => json!({ "type": "date", "format": "epoch_second" }),
+        // binary fields hold base64
+        StringType::Base64 { .. } => json!({ "type": "binary" }),
+        _ => json!({ "type": "keyword" }),
+    }
+}
+
+fn integer_mapping(min: i128, max: i128) -> serde_json::Value {
+    let fits = |lower: i128, upper: i128| min >= lower && max <= upper;
+    if fits(i32::MIN.into(), i32::MAX.into()) {
+        json!({ "type": "integer" })
+    } else if fits(i64::MIN.into(), i64::MAX.into()) {
+        json!({ "type": "long" })
+    } else if fits(0, u64::MAX.into()) {
+        json!({ "type": "unsigned_long" })
+    } else {
+        json!({ "type": "double" })
+    }
+}
+
+fn mapping(schema: &SchemaState) -> serde_json::Value {
+    match schema {
+        // values of mixed types cannot be mapped, so they are kept in the source without being
+        // indexed
+        SchemaState::Initial
+        | SchemaState::Indefinite
+        | SchemaState::Null
+        | SchemaState::Union(_)
+        | SchemaState::Tuple(_)
+        | SchemaState::UnconstrainedArray
+        | SchemaState::UnconstrainedObject
+        | SchemaState::Const(
+            serde_json::Value::Null | serde_json::Value::Array(_) | serde_json::Value::Object(_),
+        ) => json!({ "type": "object", "enabled": false }),
+        SchemaState::Nullable(inner) => mapping(inner),
+        SchemaState::String(string_type) => string_mapping(string_type),
+        SchemaState::Const(serde_json::Value::String(_)) => json!({ "type": "keyword" }),
+        SchemaState::Number(
+            number_type @ (NumberType::Integer { .. }
+            | NumberType::Enum { .. }
+            | NumberType::BigInteger { .. }),
+        ) => {
+            let (min, max) = number_type.integer_range().unwrap_or_default();
+            integer_mapping(min, max)
+        }
+        SchemaState::Const(serde_json::Value::Number(n)) => match crate::infer::integer_value(n) {
+            Some(n) => integer_mapping(n, n),
+            None => json!({ "type": "double" }),
+        },
+        // amounts with a fixed number of decimals are stored exactly as scaled integers
+        SchemaState::Number(NumberType::Decimal { scale, .. }) if *scale <= 9 => {
+            json!({ "type": "scaled_float", "scaling_factor": 10u64.pow(*scale) })
+        }
+        SchemaState::Number(NumberType::Float { .. } | NumberType::Decimal { .. }) => {
+            json!({ "type": "double" })
+        }
+        SchemaState::Boolean | SchemaState::Const(serde_json::Value::Bool(_)) => {
+            json!({ "type": "boolean" })
+        }
+        // arrays are mapped like their elements, but objects in arrays are `nested`, so that
+        // the fields of each object are queried together
+        SchemaState::Array { schema, .. } => {
+            let mut element = mapping(schema);
+            if element.get("properties").is_some() {
+                element["type"] = json!("nested");
+            }
+            element
+        }
+        SchemaState::Object { required, optional } => {
+            json!({ "properties": properties(codegen::fields(required, optional)) })
+        }
+        // objects with arbitrary keys would map a field for every key; `flattened` maps the
+        // whole object as one field instead
+        SchemaState::Map { .. } => json!({ "type": "flattened" }),
+    }
+}
+
+fn properties(fields: Vec<Field>) -> serde_json::Value {
+    fields
+        .into_iter()
+        .map(|field| (field.key.to_owned(), mapping(field.schema)))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Exports a schema as the mappings of an Elasticsearch (or OpenSearch) index, as the body of
+/// a request creating the index, with a field for each field of the records: the elements of
+/// an array at the root, or the root itself.
+///
+/// Natural language, and strings that look like free text (most hold whitespace and are
+/// distinct, or they are too long for keywords), become `text`, and other strings `keyword`s.
+/// Datetimes and dates become `date`s, decimals `scaled_float`s, and objects in arrays
+/// `nested` objects. Values of mixed types cannot be mapped, so they are kept without being
+/// indexed. Records that are not objects are held in a `value` field.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_es_mapping, InferenceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!([
+///         { "id": "a1", "at": "2024-01-01T00:00:00Z", "body": "Hello there, world" },
+///         { "id": "b2", "at": "2024-01-02T00:00:00Z", "body": "Nice to meet you" },
+///     ]),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(
+///     to_es_mapping(&schema),
+///     json!({
+///         "mappings": {
+///             "properties": {
+///                 "id": { "type": "keyword" },
+///                 "at": { "type": "date" },
+///                 "body": {
+///                     "type": "text",
+///                     "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } }
+///                 }
+///             }
+///         }
+///     })
+/// );
+/// ```
+pub fn to_es_mapping(schema: &SchemaState) -> serde_json::Value {
+    json!({
+        "mappings": {
+            "properties": properties(codegen::record_fields(schema))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+
+    #[test]
+    fn maps_nested_objects_decimals_and_mixed_values() {
+        let schema = SchemaState::Object {
+            required: IndexMap::from_iter([
+                (
+                    "items".to_owned(),
+                    SchemaState::Array {
+                        min_length: 1,
+                        max_length: 3,
+                        unique: false,
+                        schema: Box::new(SchemaState::Object {
+                            required: IndexMap::from_iter([(
+                                "price".to_owned(),
+                                SchemaState::Number(NumberType::Decimal {
+                                    scale: 2,
+                                    min: 0.5,
+                                    max: 9.99,
+                                }),
+                            )]),
+                            optional: IndexMap::new(),
+                        }),
+                    },
+                ),
+                (
+                    "payload".to_owned(),
+                    SchemaState::Union(vec![
+                        SchemaState::Boolean,
+                        SchemaState::UnconstrainedObject,
+                    ]),
+                ),
+            ]),
+            optional: IndexMap::from_iter([(
+                "id".to_owned(),
+                SchemaState::Number(NumberType::BigInteger {
+                    min: 0,
+                    max: u64::MAX.into(),
+                }),
+            )]),
+        };
+
+        assert_eq!(
+            to_es_mapping(&schema),
+            json!({
+                "mappings": {
+                    "properties": {
+                        "items": {
+                            "type": "nested",
+                            "properties": {
+                                "price": { "type": "scaled_float", "scaling_factor": 100 }
+                            }
+                        },
+                        "payload": { "type": "object", "enabled": false },
+                        "id": { "type": "unsigned_long" }
+                    }
+                }
+            })
+        );
+    }
+}
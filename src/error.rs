@@ -0,0 +1,92 @@
+//! The errors returned by the library, for embedders to handle rather than exit on.
+
+use crate::{Cancelled, JsonSchemaError};
+
+/// An error returned by drivel.
+#[derive(Debug, thiserror::Error)]
+pub enum DrivelError {
+    /// The input is neither a JSON document nor JSON lines.
+    #[error("invalid JSON: {0}")]
+    InvalidJson(#[source] serde_json::Error),
+    /// The input is not a valid YAML document or stream of documents.
+    #[error("invalid YAML: {0}")]
+    InvalidYaml(#[source] serde_yaml::Error),
+    /// The input is not valid MessagePack.
+    #[error("invalid MessagePack: {0}")]
+    InvalidMsgpack(String),
+    /// The input is not valid CBOR.
+    #[error("invalid CBOR: {0}")]
+    InvalidCbor(String),
+    /// Text input, like JSON, is not valid UTF-8.
+    #[error("invalid UTF-8: {0}")]
+    InvalidUtf8(#[from] std::str::Utf8Error),
+    /// The input is not a well-formed XML document.
+    #[error("invalid XML: {0}")]
+    InvalidXml(String),
+    /// A line of JSON lines input is not valid JSON. Lines are numbered from 1.
+    #[error("invalid JSON lines: {source}")]
+    InvalidJsonLine {
+        line: usize,
+        source: serde_json::Error,
+    },
+    /// A JSON Schema could not be parsed; the error points to the offending subschema.
+    #[error(transparent)]
+    JsonSchema(#[from] JsonSchemaError),
+    /// A protobuf descriptor set could not be decoded.
+    #[error("invalid descriptor set: {0}")]
+    InvalidDescriptor(String),
+    /// A TOML document, of overrides, type pins or detectors, could not be parsed.
+    #[error("invalid TOML: {0}")]
+    InvalidToml(#[from] toml::de::Error),
+    /// The override for a path is not valid.
+    #[error("invalid override for {path}: {message}")]
+    InvalidOverride { path: String, message: String },
+    /// The type pinned for a path is not valid.
+    #[error("invalid type pin for {path}: {message}")]
+    InvalidTypePin { path: String, message: String },
+    /// The detector of a format of strings is not valid.
+    #[error("invalid detector {name}: {message}")]
+    InvalidDetector { name: String, message: String },
+    /// The transform of a path is not valid, or cannot be applied to the schema.
+    #[error("invalid transform for {path}: {message}")]
+    InvalidTransform { path: String, message: String },
+    /// A value could not be encoded as Avro, as it does not conform to the schema.
+    #[error("unable to encode as Avro: {0}")]
+    AvroEncoding(String),
+    /// Input data could not be fetched from a URL.
+    #[error("unable to fetch {url}: {message}")]
+    Http { url: String, message: String },
+    /// Input data could not be queried from PostgreSQL.
+    #[cfg(feature = "postgres")]
+    #[error("unable to query PostgreSQL: {0}")]
+    Postgres(String),
+    /// Input data could not be sampled from MongoDB.
+    #[cfg(feature = "mongo")]
+    #[error("unable to sample MongoDB: {0}")]
+    Mongo(String),
+    /// Records could not be published to Kafka.
+    #[cfg(feature = "kafka")]
+    #[error("unable to publish to Kafka: {0}")]
+    Kafka(String),
+    /// A relation between the values produced at two paths is not valid.
+    #[error("invalid relation for {path}: {message}")]
+    InvalidRelation { path: String, message: String },
+    /// The derivation of the values produced at a path is not valid, or cannot be evaluated.
+    #[error("invalid derivation for {path}: {message}")]
+    InvalidDerivation { path: String, message: String },
+    /// The time series of the values produced at a path is not valid.
+    #[error("invalid time series for {path}: {message}")]
+    InvalidTimeSeries { path: String, message: String },
+    /// The decimals produced at a path have a range without any decimal of their scale.
+    #[error("invalid decimal for {path}: {message}")]
+    InvalidDecimal { path: String, message: String },
+    /// The values produced at a path that must be unique ran out of values not produced before.
+    #[error("unable to produce unique values for {path}: {message}")]
+    NotUnique { path: String, message: String },
+    /// The operation was cancelled, or ran past its deadline.
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+    /// Output could not be written.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
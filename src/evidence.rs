@@ -0,0 +1,180 @@
+//! Tracing how many of the values found at each path of the input records support the format
+//! inferred for them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{path, Detectors};
+
+/// How many of the values found at one path of the input records match the format most of
+/// them were detected as.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct FieldEvidence {
+    /// The path of the field, e.g. `users[].address.city`.
+    pub path: String,
+    /// The number of strings, numbers and booleans found at the path, not counting nulls.
+    pub samples: usize,
+    /// The name of the format that most samples were detected as (see `Detectors`), e.g.
+    /// `email`, or their type (`string`, `number` or `boolean`) if no format was detected.
+    pub format: String,
+    /// The number of samples detected as `format`.
+    pub matches: usize,
+}
+
+impl FieldEvidence {
+    /// Returns the share of samples detected as `format`.
+    pub fn match_ratio(&self) -> f64 {
+        self.matches as f64 / self.samples as f64
+    }
+}
+
+/// Formats a count with a comma between each group of three digits, e.g. `1,204`.
+pub(crate) fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Formats `part` as a percentage of `whole`, rounded, e.g. `67` for 2 of 3. A share that is
+/// neither none nor all never rounds to 0 or 100, but shows as `<1` or `>99`, so that a single
+/// outlier is not hidden.
+pub(crate) fn percentage(part: usize, whole: usize) -> String {
+    let rounded = ((part as f64 / whole as f64) * 100.0).round() as usize;
+    match rounded {
+        0 if part > 0 => "<1".to_owned(),
+        100 if part < whole => ">99".to_owned(),
+        rounded => rounded.to_string(),
+    }
+}
+
+impl Display for FieldEvidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percentage = percentage(self.matches, self.samples);
+        write!(
+            f,
+            "{}: {} ({}% of {} samples)",
+            path::display(&self.path),
+            self.format,
+            percentage,
+            group_digits(self.samples)
+        )
+    }
+}
+
+fn accumulate(
+    value: &serde_json::Value,
+    current_path: &str,
+    detectors: &Detectors,
+    fields: &mut BTreeMap<String, HashMap<String, usize>>,
+) {
+    let format = match value {
+        serde_json::Value::Null => return,
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(s) => detectors.detect_name(s).unwrap_or("string"),
+        serde_json::Value::Array(items) => {
+            let elements_path = path::elements(current_path);
+            for item in items {
+                accumulate(item, &elements_path, detectors, fields);
+            }
+            return;
+        }
+        serde_json::Value::Object(object) => {
+            for (k, v) in object {
+                accumulate(v, &path::key(current_path, k), detectors, fields);
+            }
+            return;
+        }
+    };
+    *fields
+        .entry(current_path.to_owned())
+        .or_default()
+        .entry(format.to_owned())
+        .or_default() += 1;
+}
+
+/// Detects the format of each string, number and boolean found in `records` with `detectors`,
+/// and reports for
+/// each path the format that most of its values were detected as and how many were. A field
+/// whose format was not inferred because a few of its values did not match, e.g. one malformed
+/// email address among thousands, shows up with a share just short of 100%. The evidence is
+/// sorted by path; paths where only nulls, arrays and objects were found are left out.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{field_evidence, Detectors};
+/// use serde_json::json;
+///
+/// let records = vec![
+///     json!({ "email": "alice@example.com" }),
+///     json!({ "email": "bob@example.com" }),
+///     json!({ "email": "not an email" }),
+/// ];
+///
+/// let evidence = field_evidence(&records, &Detectors::new());
+///
+/// assert_eq!(evidence[0].format, "email");
+/// assert_eq!(evidence[0].matches, 2);
+/// assert_eq!(evidence[0].to_string(), "email: email (67% of 3 samples)");
+/// ```
+pub fn field_evidence(records: &[serde_json::Value], detectors: &Detectors) -> Vec<FieldEvidence> {
+    let mut fields = BTreeMap::new();
+    for record in records {
+        accumulate(record, "", detectors, &mut fields);
+    }
+    fields
+        .into_iter()
+        .map(|(path, formats)| {
+            let samples = formats.values().sum();
+            let (format, matches) = formats
+                .into_iter()
+                .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+                .expect("a path is only recorded with a value");
+            FieldEvidence {
+                path,
+                samples,
+                format,
+                matches,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn counts_the_values_matching_the_most_common_format() {
+        let mut records = (0..1203)
+            .map(|n| json!({ "user": { "email": format!("user{}@example.com", n), "age": null }, "tags": [n % 2 == 0] }))
+            .collect::<Vec<_>>();
+        records.push(json!({ "user": { "email": "unknown", "age": 30 }, "tags": [] }));
+
+        let evidence = field_evidence(&records, &Detectors::new());
+
+        assert_eq!(
+            evidence
+                .iter()
+                .map(|field| field.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "tags[]: boolean (100% of 1,203 samples)",
+                "user.age: number (100% of 1 samples)",
+                "user.email: email (>99% of 1,204 samples)",
+            ]
+        );
+        assert_eq!(evidence[2].matches, 1203);
+    }
+}
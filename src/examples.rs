@@ -0,0 +1,191 @@
+//! Keeping a few of the values found at each path of the input records, to show what the fields
+//! of an inferred schema looked like.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::path;
+
+/// The first distinct strings, numbers and booleans found at one path of the input records.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct FieldExamples {
+    /// The path of the field, e.g. `users[].address.city`.
+    pub path: String,
+    /// The values, in the order in which they were first found.
+    pub values: Vec<serde_json::Value>,
+}
+
+impl Display for FieldExamples {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values = self
+            .values
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}: {}", path::display(&self.path), values.join(", "))
+    }
+}
+
+fn accumulate(
+    value: &serde_json::Value,
+    current_path: &str,
+    max_examples: usize,
+    fields: &mut BTreeMap<String, Vec<serde_json::Value>>,
+) {
+    match value {
+        serde_json::Value::Null => {}
+        serde_json::Value::Array(items) => {
+            let elements_path = path::elements(current_path);
+            for item in items {
+                accumulate(item, &elements_path, max_examples, fields);
+            }
+        }
+        serde_json::Value::Object(object) => {
+            for (k, v) in object {
+                accumulate(v, &path::key(current_path, k), max_examples, fields);
+            }
+        }
+        value => {
+            let examples = fields.entry(current_path.to_owned()).or_default();
+            if examples.len() < max_examples && !examples.contains(value) {
+                examples.push(value.clone());
+            }
+        }
+    }
+}
+
+/// Keeps up to `max_examples` distinct strings, numbers and booleans found at each path of
+/// `records`, the first ones found. The examples are sorted by path; paths where only nulls,
+/// arrays and objects were found are left out.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::field_examples;
+/// use serde_json::json;
+///
+/// let records = vec![
+///     json!({ "status": "active", "tags": ["a", "b"] }),
+///     json!({ "status": "active", "tags": ["c"] }),
+///     json!({ "status": "closed", "tags": [] }),
+/// ];
+///
+/// let examples = field_examples(&records, 2);
+///
+/// assert_eq!(examples[0].to_string(), "status: \"active\", \"closed\"");
+/// assert_eq!(examples[1].path, "tags[]");
+/// assert_eq!(examples[1].values, vec![json!("a"), json!("b")]);
+/// ```
+pub fn field_examples(records: &[serde_json::Value], max_examples: usize) -> Vec<FieldExamples> {
+    let mut fields = BTreeMap::new();
+    for record in records {
+        accumulate(record, "", max_examples, &mut fields);
+    }
+    fields
+        .into_iter()
+        .filter(|(_, values)| !values.is_empty())
+        .map(|(path, values)| FieldExamples { path, values })
+        .collect()
+}
+
+/// Adds the examples of each path to the subschema of `document`, a JSON Schema, that describes
+/// it, as `examples`. Alternatives of a union share the examples of the union.
+pub(crate) fn add_examples(document: &mut serde_json::Value, examples: &[FieldExamples]) {
+    let examples = examples
+        .iter()
+        .map(|field| (field.path.as_str(), &field.values))
+        .collect::<HashMap<_, _>>();
+    add_examples_inner(document, "", &examples, false);
+}
+
+fn add_examples_inner(
+    node: &mut serde_json::Value,
+    current_path: &str,
+    examples: &HashMap<&str, &Vec<serde_json::Value>>,
+    added: bool,
+) {
+    let serde_json::Value::Object(node) = node else {
+        return;
+    };
+    let added = added
+        || match examples.get(current_path) {
+            Some(values) => {
+                node.insert("examples".to_owned(), (*values).clone().into());
+                true
+            }
+            None => false,
+        };
+    if let Some(serde_json::Value::Object(properties)) = node.get_mut("properties") {
+        for (k, v) in properties {
+            add_examples_inner(v, &path::key(current_path, k), examples, false);
+        }
+    }
+    let elements_path = path::elements(current_path);
+    if let Some(items) = node.get_mut("items") {
+        add_examples_inner(items, &elements_path, examples, false);
+    }
+    if let Some(serde_json::Value::Array(elements)) = node.get_mut("prefixItems") {
+        for element in elements {
+            add_examples_inner(element, &elements_path, examples, false);
+        }
+    }
+    if let Some(serde_json::Value::Array(branches)) = node.get_mut("anyOf") {
+        for branch in branches {
+            add_examples_inner(branch, current_path, examples, added);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        infer_schema_from_iter, to_json_schema_with_examples, InferenceOptions, SchemaState,
+        StringType,
+    };
+
+    #[test]
+    fn adds_examples_to_the_json_schema_of_each_field() {
+        let records = vec![
+            json!({ "user": { "email": "ann@example.com", "age": null }, "tags": [] }),
+            json!({ "user": { "email": "bob@example.com", "age": 30 }, "tags": ["a"] }),
+            json!({ "user": { "email": "ann@example.com", "age": 41 }, "tags": ["b", "c"] }),
+        ];
+        let schema = infer_schema_from_iter(records.clone(), &InferenceOptions::default());
+
+        let examples = field_examples(&records, 2);
+        let document = to_json_schema_with_examples(&schema, &examples);
+
+        let user = &document["properties"]["user"]["properties"];
+        assert_eq!(
+            user["email"]["examples"],
+            json!(["ann@example.com", "bob@example.com"])
+        );
+        assert_eq!(user["age"]["examples"], json!([30, 41]));
+        assert_eq!(
+            document["properties"]["tags"]["items"]["examples"],
+            json!(["a", "b"])
+        );
+
+        // the examples of a union are not repeated for each of its alternatives
+        let union = SchemaState::Union(vec![
+            SchemaState::Boolean,
+            SchemaState::String(StringType::Email),
+        ]);
+        let examples = [FieldExamples {
+            path: String::new(),
+            values: vec![json!(true), json!("ann@example.com")],
+        }];
+        let document = to_json_schema_with_examples(&union, &examples);
+        assert_eq!(document["examples"], json!([true, "ann@example.com"]));
+        assert!(document["anyOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|branch| branch.get("examples").is_none()));
+    }
+}
@@ -0,0 +1,229 @@
+//! Fetching input data over HTTP(S), following the links to further pages of paginated APIs.
+
+use std::io::Read;
+
+use crate::{DrivelError, Input};
+
+/// The number of pages fetched at most unless another limit is given.
+pub const DEFAULT_MAX_PAGES: usize = 100;
+
+/// How input data is fetched from a URL.
+#[derive(Clone, Debug)]
+pub struct FetchOptions {
+    /// The headers sent with every request, like `Authorization`, as names and values.
+    pub headers: Vec<(String, String)>,
+    /// A JSON pointer, like `/links/next`, to the URL of the next page in each page fetched.
+    /// Pages are followed until the pointer does not lead to a URL, or `max_pages` were
+    /// fetched. Only the first page is fetched without one.
+    pub next_link: Option<String>,
+    /// The number of pages fetched at most.
+    pub max_pages: usize,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        FetchOptions {
+            headers: vec![],
+            next_link: None,
+            max_pages: DEFAULT_MAX_PAGES,
+        }
+    }
+}
+
+fn fetch_page(url: &url::Url, headers: &[(String, String)]) -> Result<Vec<u8>, DrivelError> {
+    let mut request = ureq::get(url.as_str());
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    let response = request.call().map_err(|err| DrivelError::Http {
+        url: url.to_string(),
+        // the errors of ureq name the URL too
+        message: match err {
+            ureq::Error::Status(code, response) => {
+                format!("status code {} {}", code, response.status_text())
+            }
+            ureq::Error::Transport(transport) => match transport.message() {
+                Some(message) => format!("{}: {}", transport.kind(), message),
+                None => transport.kind().to_string(),
+            },
+        },
+    })?;
+    let mut body = vec![];
+    response.into_reader().read_to_end(&mut body)?;
+    Ok(body)
+}
+
+/// Returns the URL that the next link of a page points to, resolved against the URL of the
+/// page, or `None` if the page does not link to another.
+fn next_page(page: &Input, pointer: &str, url: &url::Url) -> Result<Option<url::Url>, DrivelError> {
+    let link = match page {
+        Input::Document(document) => document.pointer(pointer),
+        Input::Lines(_) => None,
+    };
+    match link {
+        Some(serde_json::Value::String(link)) if !link.is_empty() => {
+            url.join(link).map(Some).map_err(|err| DrivelError::Http {
+                url: link.to_owned(),
+                message: err.to_string(),
+            })
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Fetches input data from an HTTP(S) URL, parsing the body of each page with `parse`.
+///
+/// With a `next_link`, the pages it leads to are fetched too, and the records of all pages are
+/// returned as lines: a page that is an array holds records, and other pages are records
+/// themselves, so that the envelope of a paginated API, like `{ "items": [...], "next": ... }`,
+/// is described as it is seen on every page. Relative links are resolved against the URL of
+/// the page they are on.
+///
+/// # Examples
+///
+/// ```no_run
+/// use drivel::{fetch_input, FetchOptions, Input};
+///
+/// let options = FetchOptions {
+///     headers: vec![("Authorization".to_owned(), "Bearer token".to_owned())],
+///     next_link: Some("/links/next".to_owned()),
+///     ..FetchOptions::default()
+/// };
+/// let input = fetch_input("https://api.example.com/items?limit=500", &options, |body| {
+///     Input::parse(std::str::from_utf8(body)?)
+/// })
+/// .unwrap();
+/// println!("{} records", input.records().len());
+/// ```
+pub fn fetch_input(
+    url: &str,
+    options: &FetchOptions,
+    mut parse: impl FnMut(&[u8]) -> Result<Input, DrivelError>,
+) -> Result<Input, DrivelError> {
+    let mut url = url::Url::parse(url).map_err(|err| DrivelError::Http {
+        url: url.to_owned(),
+        message: err.to_string(),
+    })?;
+    let mut pages = vec![];
+    loop {
+        let page = parse(&fetch_page(&url, &options.headers)?)?;
+        let next = match &options.next_link {
+            Some(pointer) if pages.len() + 1 < options.max_pages => {
+                next_page(&page, pointer, &url)?
+            }
+            _ => None,
+        };
+        pages.push(page);
+        match next {
+            Some(next) => url = next,
+            None => break,
+        }
+    }
+
+    if options.next_link.is_none() {
+        return Ok(pages.remove(0));
+    }
+    Ok(Input::Lines(
+        pages
+            .iter()
+            .flat_map(|page| page.records().iter().cloned())
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    use serde_json::json;
+
+    use super::*;
+
+    /// Serves the pages of a paginated API once each, in order, returning the request lines
+    /// and authorization headers received.
+    fn serve(pages: Vec<String>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut received = vec![];
+            for page in pages {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if line.starts_with("GET") || line.to_lowercase().starts_with("authorization") {
+                        received.push(line.to_owned());
+                    }
+                }
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    page.len(),
+                    page
+                )
+                .unwrap();
+            }
+            received
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn follows_next_links_across_pages() {
+        let (url, server) = serve(vec![
+            json!({ "items": [{ "id": 1 }], "next": "/items?page=2" }).to_string(),
+            json!({ "items": [{ "id": 2 }], "next": null }).to_string(),
+        ]);
+        let options = FetchOptions {
+            headers: vec![("Authorization".to_owned(), "Bearer t".to_owned())],
+            next_link: Some("/next".to_owned()),
+            ..FetchOptions::default()
+        };
+
+        let input = fetch_input(&format!("{}/items", url), &options, |body| {
+            Input::parse(std::str::from_utf8(body)?)
+        })
+        .unwrap();
+        assert_eq!(
+            input,
+            Input::Lines(vec![
+                json!({ "items": [{ "id": 1 }], "next": "/items?page=2" }),
+                json!({ "items": [{ "id": 2 }], "next": null }),
+            ])
+        );
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                "GET /items HTTP/1.1",
+                "Authorization: Bearer t",
+                "GET /items?page=2 HTTP/1.1",
+                "Authorization: Bearer t",
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_after_the_maximum_number_of_pages() {
+        let (url, server) = serve(vec![
+            json!([{ "id": 1, "next": "/again" }, { "id": 2 }]).to_string()
+        ]);
+        let options = FetchOptions {
+            next_link: Some("/0/next".to_owned()),
+            max_pages: 1,
+            ..FetchOptions::default()
+        };
+
+        let input = fetch_input(&url, &options, |body| {
+            Input::parse(std::str::from_utf8(body)?)
+        })
+        .unwrap();
+        assert_eq!(input.records().len(), 2);
+        server.join().unwrap();
+    }
+}
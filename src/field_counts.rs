@@ -0,0 +1,160 @@
+//! Counting how often the values at each path of the records a schema is inferred from were
+//! null, and how often the fields of their objects were present, in a single walk over them.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::{path, FieldNulls, FieldPresence};
+
+#[derive(Default)]
+struct Counts {
+    /// The number of values found at each path, and how many of them were null.
+    values: BTreeMap<String, (usize, usize)>,
+    /// The number of objects found at each path.
+    objects: BTreeMap<String, usize>,
+    /// The number of objects holding each field, by the path of the field.
+    fields: BTreeMap<String, (String, usize)>,
+}
+
+impl Counts {
+    fn accumulate(&mut self, value: &serde_json::Value, current_path: &str) {
+        let (samples, nulls) = self.values.entry(current_path.to_owned()).or_default();
+        *samples += 1;
+        match value {
+            serde_json::Value::Null => *nulls += 1,
+            serde_json::Value::Array(items) => {
+                let elements_path = path::elements(current_path);
+                for item in items {
+                    self.accumulate(item, &elements_path);
+                }
+            }
+            serde_json::Value::Object(object) => {
+                *self.objects.entry(current_path.to_owned()).or_default() += 1;
+                for (k, v) in object {
+                    let field_path = path::key(current_path, k);
+                    self.fields
+                        .entry(field_path.clone())
+                        .or_insert_with(|| (current_path.to_owned(), 0))
+                        .1 += 1;
+                    self.accumulate(v, &field_path);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// How often the values at each path of the records were null, and how often the fields of
+/// their objects were present, counted over the records a schema is inferred from when given as
+/// `InferenceOptions::field_counts`, or over any records with `count`. Records are counted as
+/// they are inferred, so that only those inferred within `Input::infer_schema_within` are.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use drivel::{infer_schema, FieldCounts, InferenceOptions};
+/// use serde_json::json;
+///
+/// let counts = Arc::new(FieldCounts::new());
+/// let options = InferenceOptions {
+///     field_counts: Some(counts.clone()),
+///     ..InferenceOptions::default()
+/// };
+/// infer_schema(
+///     json!([{ "nickname": null }, { "nickname": "bobby" }, {}]),
+///     &options,
+/// );
+///
+/// assert_eq!(counts.nulls()[0].to_string(), "nickname: 50% null (1 of 2 samples)");
+/// assert_eq!(counts.presence()[0].to_string(), "nickname: 67% present (2 of 3 objects)");
+/// ```
+#[derive(Default)]
+pub struct FieldCounts {
+    counts: Mutex<Counts>,
+}
+
+impl FieldCounts {
+    /// Returns counts of no records.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the values and fields of `records`, paths being relative to each record.
+    pub fn count(&self, records: &[serde_json::Value]) {
+        let mut counts = self.counts.lock().unwrap();
+        for record in records {
+            counts.accumulate(record, "");
+        }
+    }
+
+    /// Returns the paths where both nulls and other values were found, as those are the paths
+    /// of nullable values, with how often they were null; sorted by path.
+    pub fn nulls(&self) -> Vec<FieldNulls> {
+        let counts = self.counts.lock().unwrap();
+        counts
+            .values
+            .iter()
+            .filter(|(_, (samples, nulls))| *nulls > 0 && nulls < samples)
+            .map(|(path, (samples, nulls))| FieldNulls {
+                path: path.clone(),
+                samples: *samples,
+                nulls: *nulls,
+            })
+            .collect()
+    }
+
+    /// Returns the fields left out of some of the objects holding them, as those are the
+    /// optional fields, with how often they were present; sorted by path.
+    pub fn presence(&self) -> Vec<FieldPresence> {
+        let counts = self.counts.lock().unwrap();
+        counts
+            .fields
+            .iter()
+            .map(|(path, (parent, present))| FieldPresence {
+                path: path.clone(),
+                objects: counts.objects[parent],
+                present: *present,
+            })
+            .filter(|field| field.present < field.objects)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{InferenceOptions, Input};
+
+    #[test]
+    fn counts_only_the_records_inferred() {
+        let counts = Arc::new(FieldCounts::new());
+        let options = InferenceOptions {
+            field_counts: Some(counts.clone()),
+            ..InferenceOptions::default()
+        };
+        let lines = (0..3000)
+            .map(|n| match n % 2 {
+                0 => json!({ "note": null }),
+                _ => json!({ "note": "seen", "extra": n }),
+            })
+            .collect();
+        let (_, n_inferred) =
+            Input::Lines(lines).infer_schema_within(&options, std::time::Duration::ZERO);
+        assert!(n_inferred < 3000);
+
+        let nulls = counts.nulls();
+        assert_eq!(nulls.len(), 1);
+        assert_eq!(nulls[0].samples, n_inferred);
+        assert_eq!(nulls[0].null_ratio(), 0.5);
+        let presence = counts.presence();
+        assert_eq!(presence.len(), 1);
+        assert_eq!(presence[0].path, "extra");
+        assert_eq!(presence[0].objects, n_inferred);
+    }
+}
@@ -0,0 +1,359 @@
+//! Exporting schemas as GraphQL type definitions, in the schema definition language (SDL).
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::codegen::{self, field_name, pascal_case, screaming_snake_case, unique_name, Field};
+use crate::{NumberType, SchemaState, StringType};
+
+/// The name of the type describing each record.
+const ROOT_TYPE: &str = "Record";
+
+/// The scalars GraphQL defines, whose names types cannot take.
+const BUILT_IN_SCALARS: [&str; 5] = ["Boolean", "Float", "ID", "Int", "String"];
+
+/// The scalars drivel declares for values GraphQL has no type for.
+const DATE_TIME: &str = "DateTime";
+const DATE: &str = "Date";
+const JSON: &str = "JSON";
+
+/// The types defined so far, and the names they have taken.
+struct Document {
+    definitions: Vec<String>,
+    names: HashSet<String>,
+    scalars: BTreeSet<&'static str>,
+}
+
+impl Document {
+    fn scalar(&mut self, name: &'static str) -> String {
+        self.scalars.insert(name);
+        name.to_owned()
+    }
+
+    /// Names the type of the field `name` of the type `parent`, e.g. `Address`, or
+    /// `OrderAddress` when `Address` is taken. The types of the elements of arrays are named in
+    /// the singular, e.g. `Item` for `items`.
+    fn type_name(&mut self, parent: &str, name: &str, element: bool) -> String {
+        let name = pascal_case(name);
+        let name = if element { singular(&name) } else { name };
+        if self.names.contains(&name) {
+            unique_name(format!("{}{}", parent, name), &mut self.names)
+        } else {
+            unique_name(name, &mut self.names)
+        }
+    }
+}
+
+/// Returns the singular of an English noun, e.g. `Category` for `Categories`, or the noun
+/// itself when it does not look plural.
+fn singular(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if ["sses", "ches", "shes", "xes", "zes", "uses"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+    {
+        name[..name.len() - 2].to_owned()
+    } else if ["ss", "us", "is"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+        || name.len() <= 1
+    {
+        name.to_owned()
+    } else if let Some(stem) = name.strip_suffix('s') {
+        stem.to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Returns true if `s` is a valid GraphQL name for an enum value.
+fn is_enum_value(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !matches!(s, "true" | "false" | "null")
+}
+
+fn enum_definition(name: &str, variants: &HashSet<String>) -> String {
+    let mut variants = variants.iter().collect::<Vec<_>>();
+    variants.sort();
+    // enum values are written as their names, so the strings are kept where they are valid ones
+    let values = if variants.iter().all(|variant| is_enum_value(variant)) {
+        variants.into_iter().cloned().collect::<Vec<_>>()
+    } else {
+        let mut taken = HashSet::new();
+        variants
+            .into_iter()
+            .map(|variant| {
+                let value = screaming_snake_case(variant);
+                let value = if is_enum_value(&value) {
+                    value
+                } else {
+                    format!("_{}", value)
+                };
+                unique_name(value, &mut taken)
+            })
+            .collect()
+    };
+    let values = values
+        .iter()
+        .map(|value| format!("  {}\n", value))
+        .collect::<String>();
+    format!("enum {} {{\n{}}}\n", name, values)
+}
+
+/// Returns the GraphQL type of a value, without the `!` marking it as non-null.
+fn graphql_type(
+    schema: &SchemaState,
+    parent: &str,
+    field: &str,
+    element: bool,
+    document: &mut Document,
+) -> String {
+    match schema {
+        // GraphQL has no unions of scalars, nor tuples or maps
+        SchemaState::Initial
+        | SchemaState::Indefinite
+        | SchemaState::Null
+        | SchemaState::Union(_)
+        | SchemaState::Tuple(_)
+        | SchemaState::UnconstrainedArray
+        | SchemaState::UnconstrainedObject
+        | SchemaState::Map { .. }
+        | SchemaState::Const(
+            serde_json::Value::Null | serde_json::Value::Array(_) | serde_json::Value::Object(_),
+        ) => document.scalar(JSON),
+        SchemaState::Nullable(inner) => graphql_type(inner, parent, field, element, document),
+        SchemaState::String(StringType::Enum { variants }) => {
+            let name = document.type_name(parent, field, element);
+            document.definitions.push(enum_definition(&name, variants));
+            name
+        }
+        SchemaState::String(StringType::DateTimeISO8601 { .. }) => document.scalar(DATE_TIME),
+        SchemaState::String(StringType::IsoDate { .. }) => document.scalar(DATE),
+        SchemaState::String(
+            StringType::UUID | StringType::Ulid | StringType::Ksuid | StringType::ObjectId,
+        ) => "ID".to_owned(),
+        SchemaState::String(_) | SchemaState::Const(serde_json::Value::String(_)) => {
+            "String".to_owned()
+        }
+        SchemaState::Number(
+            number_type @ (NumberType::Integer { .. }
+            | NumberType::Enum { .. }
+            | NumberType::BigInteger { .. }),
+        ) => {
+            // GraphQL integers are 32-bit
+            let (min, max) = number_type.integer_range().unwrap_or_default();
+            if min >= i32::MIN.into() && max <= i32::MAX.into() {
+                "Int".to_owned()
+            } else {
+                "Float".to_owned()
+            }
+        }
+        SchemaState::Const(serde_json::Value::Number(n)) => {
+            if n.as_i64().is_some_and(|n| i32::try_from(n).is_ok()) {
+                "Int".to_owned()
+            } else {
+                "Float".to_owned()
+            }
+        }
+        SchemaState::Number(NumberType::Float { .. } | NumberType::Decimal { .. }) => {
+            "Float".to_owned()
+        }
+        SchemaState::Boolean | SchemaState::Const(serde_json::Value::Bool(_)) => {
+            "Boolean".to_owned()
+        }
+        SchemaState::Array { schema, .. } => {
+            format!("[{}]", field_type(schema, parent, field, true, document))
+        }
+        // types need at least one field
+        SchemaState::Object { required, optional }
+            if required.is_empty() && optional.is_empty() =>
+        {
+            document.scalar(JSON)
+        }
+        SchemaState::Object { required, optional } => {
+            let name = document.type_name(parent, field, element);
+            object_type(&name, codegen::fields(required, optional), document);
+            name
+        }
+    }
+}
+
+/// Returns the GraphQL type of a value, with a `!` unless it can be null.
+fn field_type(
+    schema: &SchemaState,
+    parent: &str,
+    field: &str,
+    element: bool,
+    document: &mut Document,
+) -> String {
+    let graphql_type = graphql_type(schema, parent, field, element, document);
+    match schema {
+        SchemaState::Nullable(_)
+        | SchemaState::Null
+        | SchemaState::Const(serde_json::Value::Null) => graphql_type,
+        _ => format!("{}!", graphql_type),
+    }
+}
+
+/// Defines an object type named `name` with `fields`, before the types of its fields.
+fn object_type(name: &str, fields: Vec<Field>, document: &mut Document) {
+    let index = document.definitions.len();
+    document.definitions.push(String::new());
+    let mut field_names = HashSet::new();
+    let mut lines = String::new();
+    for field in fields {
+        let mut field_name = unique_name(field_name(field.key), &mut field_names);
+        // names starting with two underscores are reserved for introspection
+        if field_name.starts_with("__") {
+            field_name = unique_name(format!("field{}", field_name), &mut field_names);
+        }
+        let field_type = if field.optional {
+            graphql_type(field.schema, name, &field_name, false, document)
+        } else {
+            field_type(field.schema, name, &field_name, false, document)
+        };
+        lines.push_str(&format!("  {}: {}\n", field_name, field_type));
+    }
+    document.definitions[index] = format!("type {} {{\n{}}}\n", name, lines);
+}
+
+/// Exports a schema as GraphQL type definitions, with a `Record` type describing each record:
+/// the elements of an array at the root, or the root itself.
+///
+/// Objects become object types, named after the fields holding them (in the singular for the
+/// elements of arrays, and prefixed with the name of the type they are in when that name is
+/// taken). Required fields that are never null are non-null (`!`), arrays become lists, and
+/// string enums become enums. ISO 8601 datetimes and dates become `DateTime` and `Date`
+/// scalars, and values GraphQL cannot describe, like unions, a `JSON` scalar. Records that
+/// are not objects are held in the `value` field of `Record`.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_graphql, InferenceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!({ "id": 1, "email": null, "tags": [{ "name": "a" }] }),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(
+///     to_graphql(&schema),
+///     r#"scalar JSON
+///
+/// type Record {
+///   id: Int!
+///   email: JSON
+///   tags: [Tag!]!
+/// }
+///
+/// type Tag {
+///   name: String!
+/// }
+/// "#
+/// );
+/// ```
+pub fn to_graphql(schema: &SchemaState) -> String {
+    let mut document = Document {
+        definitions: vec![],
+        names: HashSet::from_iter(
+            BUILT_IN_SCALARS
+                .into_iter()
+                .chain([DATE_TIME, DATE, JSON, ROOT_TYPE])
+                .map(str::to_owned),
+        ),
+        scalars: BTreeSet::new(),
+    };
+    object_type(ROOT_TYPE, codegen::record_fields(schema), &mut document);
+    let scalars = document
+        .scalars
+        .iter()
+        .map(|scalar| format!("scalar {}\n", scalar))
+        .collect::<String>();
+    [scalars]
+        .into_iter()
+        .filter(|scalars| !scalars.is_empty())
+        .chain(document.definitions)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+
+    fn object(fields: Vec<(&str, SchemaState)>) -> SchemaState {
+        SchemaState::Object {
+            required: fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect(),
+            optional: IndexMap::new(),
+        }
+    }
+
+    #[test]
+    fn names_nested_types_from_their_paths() {
+        let address = || object(vec![("city", SchemaState::String(StringType::Email))]);
+        let schema = SchemaState::Object {
+            required: IndexMap::from_iter([
+                ("address".to_owned(), address()),
+                (
+                    "orders".to_owned(),
+                    SchemaState::Array {
+                        min_length: 1,
+                        max_length: 2,
+                        unique: false,
+                        schema: Box::new(object(vec![
+                            ("address", SchemaState::Nullable(Box::new(address()))),
+                            (
+                                "status",
+                                SchemaState::String(StringType::Enum {
+                                    variants: HashSet::from_iter([
+                                        "in review".to_owned(),
+                                        "shipped".to_owned(),
+                                    ]),
+                                }),
+                            ),
+                        ])),
+                    },
+                ),
+            ]),
+            optional: IndexMap::from_iter([(
+                "at".to_owned(),
+                SchemaState::String(StringType::DateTimeISO8601 { range: None }),
+            )]),
+        };
+
+        assert_eq!(
+            to_graphql(&schema),
+            r#"scalar DateTime
+
+type Record {
+  address: Address!
+  orders: [Order!]!
+  at: DateTime
+}
+
+type Address {
+  city: String!
+}
+
+type Order {
+  address: OrderAddress
+  status: Status!
+}
+
+type OrderAddress {
+  city: String!
+}
+
+enum Status {
+  IN_REVIEW
+  SHIPPED
+}
+"#
+        );
+    }
+}
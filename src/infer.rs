@@ -1,15 +1,94 @@
-use crate::{infer_string::infer_string_type, NumberType, SchemaState, StringType};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::{
+    cancel,
+    infer_string::{format_name, infer_string_type},
+    path,
+    profile::{timed, Stage},
+    sample::{merge_counts, merge_samples},
+    widen, Cancellation, Cancelled, Detectors, DistinctCount, DrivelError, FieldCounts, NumberType,
+    Profile, SchemaState, Segment, StringType, TypePins,
+};
+use indexmap::IndexMap;
 use rayon::prelude::*;
 
 pub struct EnumInference {
-    /// The maximum ratio of unique values to total values in a collection of strings for it to be considered an enum.
+    /// The maximum ratio of unique values to total values in a collection of strings or integers for it to be considered an enum.
     pub max_unique_ratio: f64,
-    /// The minimum number of values in a collection of strings for enum inference to be applied.
+    /// The minimum number of values in a collection of strings or integers for enum inference to be applied.
     pub min_sample_size: usize,
 }
 
+/// The default value of `InferenceOptions::max_samples`.
+pub const DEFAULT_MAX_SAMPLES: usize = 10_000;
+
+/// The default value of `InferenceOptions::max_depth`, which is as deep as `serde_json` parses.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// The longest nested array that is inferred as a tuple; longer arrays are always inferred as
+/// arrays of their merged elements.
+const MAX_TUPLE_LENGTH: usize = 8;
+
 pub struct InferenceOptions {
     pub enum_inference: Option<EnumInference>,
+    /// The maximum number of strings kept as a sample, and of different characters counted, for
+    /// each string field. Bounds the memory used when inferring large inputs.
+    /// Default = `DEFAULT_MAX_SAMPLES`.
+    pub max_samples: usize,
+    /// Infer empty arrays and objects as unconstrained, rather than as containers that are
+    /// always empty. Unconstrained containers take on the shape of the non-empty containers
+    /// they are merged with, without making their fields optional or their length zero.
+    /// Default = false.
+    pub unconstrained_empty: bool,
+    /// Relax the constraints inferred from values that were observed only once, whose lengths
+    /// and ranges would otherwise be a single value: lengths are widened by 50% and numbers by
+    /// 20%, either way. See `singleton_paths`. Default = false.
+    pub widen_singletons: bool,
+    /// The types pinned at specific paths, which values of other types cannot widen. See
+    /// `TypePins`. Default = no pins.
+    pub type_pins: TypePins,
+    /// Parse strings that hold a JSON object or array, and infer the schema of the document
+    /// they embed as a `StringType::EmbeddedJson`. Default = false.
+    pub embedded_json: bool,
+    /// The maximum number of arrays and objects that are nested within each other, counting the
+    /// records as the first level. Arrays and objects nested any deeper are inferred as
+    /// unconstrained, rather than recursing into them. See `truncated_paths`.
+    /// Default = `DEFAULT_MAX_DEPTH`.
+    pub max_depth: usize,
+    /// The share of the strings at a path, e.g. `0.01`, that may fail to match the format
+    /// detected for the others without the path losing that format. Strings are then counted
+    /// per format while merging, and the format most of them match is kept if few enough did
+    /// not. See `annotate_outliers` for the records holding those that did not. Default = `None`,
+    /// so that a single string that does not match makes the path a string of unknown format.
+    pub format_tolerance: Option<f64>,
+    /// The detectors of the formats of strings, tried in order. See `Detectors`.
+    /// Default = the built-in detectors.
+    pub detectors: Detectors,
+    /// Where the time spent in each stage of inference is added up, if it is to be profiled.
+    /// See `Profile`. Default = `None`.
+    pub profile: Option<Arc<Profile>>,
+    /// Where how often the values of the records inferred were null, and their fields present,
+    /// is counted, if it is to be. See `FieldCounts`. Default = `None`.
+    pub field_counts: Option<Arc<FieldCounts>>,
+}
+
+impl Default for InferenceOptions {
+    fn default() -> Self {
+        Self {
+            enum_inference: None,
+            max_samples: DEFAULT_MAX_SAMPLES,
+            unconstrained_empty: false,
+            widen_singletons: false,
+            type_pins: TypePins::new(),
+            embedded_json: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            format_tolerance: None,
+            detectors: Detectors::new(),
+            profile: None,
+            field_counts: None,
+        }
+    }
 }
 
 fn min<T: PartialOrd>(left: T, right: T) -> T {
@@ -28,59 +107,625 @@ fn max<T: PartialOrd>(left: T, right: T) -> T {
     }
 }
 
-fn merge(initial: SchemaState, new: SchemaState) -> SchemaState {
-    match (initial, new) {
-        (SchemaState::Initial, s)
-        | (s, SchemaState::Initial)
-        | (SchemaState::Indefinite, s)
-        | (s, SchemaState::Indefinite) => s,
+fn common_prefix<'a>(first: &'a str, second: &str) -> &'a str {
+    let length = first
+        .char_indices()
+        .zip(second.chars())
+        .find(|((_, a), b)| a != b)
+        .map(|((idx, _), _)| idx)
+        .unwrap_or_else(|| first.len().min(second.len()));
+    &first[..length]
+}
 
-        // --- String merging ---
+fn common_suffix<'a>(first: &'a str, second: &str) -> &'a str {
+    let length = first
+        .chars()
+        .rev()
+        .zip(second.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+    &first[first.len() - length..]
+}
+
+fn merge_segments(first: Segment, second: Segment) -> Option<Segment> {
+    use crate::CharClass::*;
+    let class = match (first.class, second.class) {
+        (first, second) if first == second => first,
+        (Uppercase | Lowercase | Letter, Uppercase | Lowercase | Letter) => Letter,
+        _ => return None,
+    };
+    let literal = (first.literal == second.literal)
+        .then_some(first.literal)
+        .flatten();
+    if class == Other && literal.is_none() {
+        return None;
+    }
+    Some(Segment {
+        class,
+        literal,
+        min_length: min(first.min_length, second.min_length),
+        max_length: max(first.max_length, second.max_length),
+    })
+}
+
+/// Merges the structures of two sets of strings, which only share a structure if they consist
+/// of the same number of segments and each pair of segments is compatible.
+fn merge_structures(
+    first: Option<Vec<Segment>>,
+    second: Option<Vec<Segment>>,
+) -> Option<Vec<Segment>> {
+    match (first, second) {
+        (Some(first), Some(second)) if first.len() == second.len() => first
+            .into_iter()
+            .zip(second)
+            .map(|(first, second)| merge_segments(first, second))
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Merges the ranges of dates seen. A date whose range is not known makes the merged range
+/// unknown as well.
+fn merge_ranges<T: PartialOrd>(
+    first: Option<std::ops::RangeInclusive<T>>,
+    second: Option<std::ops::RangeInclusive<T>>,
+) -> Option<std::ops::RangeInclusive<T>> {
+    let (first_start, first_end) = first?.into_inner();
+    let (second_start, second_end) = second?.into_inner();
+    Some(min(first_start, second_start)..=max(first_end, second_end))
+}
+
+/// Returns the lower of two bounds of floats, given with whether they are exclusive. Equal
+/// bounds are exclusive only if both are.
+fn lower_bound(first: (f64, bool), second: (f64, bool)) -> (f64, bool) {
+    match first.0.partial_cmp(&second.0) {
+        Some(std::cmp::Ordering::Less) => first,
+        Some(std::cmp::Ordering::Greater) => second,
+        _ => (first.0.min(second.0), first.1 && second.1),
+    }
+}
+
+/// Returns the higher of two bounds of floats, given with whether they are exclusive. Equal
+/// bounds are exclusive only if both are.
+fn upper_bound(first: (f64, bool), second: (f64, bool)) -> (f64, bool) {
+    match first.0.partial_cmp(&second.0) {
+        Some(std::cmp::Ordering::Greater) => first,
+        Some(std::cmp::Ordering::Less) => second,
+        _ => (first.0.max(second.0), first.1 && second.1),
+    }
+}
+
+/// Merges two string types, which always merge into a string type.
+fn merge_string_types(first: StringType, second: StringType, max_samples: usize) -> StringType {
+    match merge(
+        SchemaState::String(first),
+        SchemaState::String(second),
+        max_samples,
+    ) {
+        SchemaState::String(string_type) => string_type,
+        _ => unreachable!("strings merge into strings"),
+    }
+}
+
+/// Returns the number of strings a string type was inferred from, counting those of a format
+/// that is not counted as one.
+fn strings_counted(string_type: &StringType) -> usize {
+    match string_type {
+        StringType::Unknown { n_strings_seen, .. } => *n_strings_seen,
+        StringType::Tally {
+            matches, outliers, ..
+        } => matches + outliers,
+        _ => 1,
+    }
+}
+
+/// Merges the format of a tally with the strings that did not match it, as if no tally had
+/// been kept.
+fn untallied(string_type: StringType, max_samples: usize) -> StringType {
+    match string_type {
+        StringType::Tally {
+            format,
+            unmatched: Some(unmatched),
+            ..
+        } => merge_string_types(*format, *unmatched, max_samples),
+        StringType::Tally { format, .. } => *format,
+        string_type => string_type,
+    }
+}
+
+/// Counts the strings of `other` as outliers of `tally`.
+fn add_outliers(tally: StringType, other: StringType, max_samples: usize) -> StringType {
+    let StringType::Tally {
+        format,
+        matches,
+        outliers,
+        unmatched,
+    } = tally
+    else {
+        unreachable!("outliers are only added to tallies")
+    };
+    let n_outliers = strings_counted(&other);
+    let other = untallied(other, max_samples);
+    StringType::Tally {
+        format,
+        matches,
+        outliers: outliers + n_outliers,
+        unmatched: Some(Box::new(match unmatched {
+            Some(unmatched) => merge_string_types(*unmatched, other, max_samples),
+            None => other,
+        })),
+    }
+}
+
+/// Merges two tallies. Tallies of the same format add up, while of two different formats the
+/// one matched by more strings is kept, and the strings of the other are outliers.
+fn merge_tallies(first: StringType, second: StringType, max_samples: usize) -> StringType {
+    match (first, second) {
+        (
+            StringType::Tally {
+                format,
+                matches,
+                outliers,
+                unmatched,
+            },
+            StringType::Tally {
+                format: second_format,
+                matches: second_matches,
+                outliers: second_outliers,
+                unmatched: second_unmatched,
+            },
+        ) if std::mem::discriminant(format.as_ref())
+            == std::mem::discriminant(second_format.as_ref()) =>
+        {
+            StringType::Tally {
+                format: Box::new(merge_string_types(*format, *second_format, max_samples)),
+                matches: matches + second_matches,
+                outliers: outliers + second_outliers,
+                unmatched: match (unmatched, second_unmatched) {
+                    (Some(first), Some(second)) => {
+                        Some(Box::new(merge_string_types(*first, *second, max_samples)))
+                    }
+                    (unmatched, None) | (None, unmatched) => unmatched,
+                },
+            }
+        }
+        (first, second) => {
+            let votes = |tally: &StringType| match tally {
+                StringType::Tally {
+                    format, matches, ..
+                } => (*matches, format_name(format)),
+                _ => unreachable!("only tallies are merged as tallies"),
+            };
+            // ties are broken alphabetically so that the order of merging does not matter
+            let (first_matches, first_name) = votes(&first);
+            let (second_matches, second_name) = votes(&second);
+            if first_matches
+                .cmp(&second_matches)
+                .then(second_name.cmp(first_name))
+                == std::cmp::Ordering::Less
+            {
+                add_outliers(second, first, max_samples)
+            } else {
+                add_outliers(first, second, max_samples)
+            }
+        }
+    }
+}
+
+/// Counts a string detected as a format towards a tally of that format, if a tolerance of
+/// outliers is set.
+fn tallied(string_type: StringType, options: &InferenceOptions) -> StringType {
+    match string_type {
+        StringType::Unknown { .. } | StringType::NonFiniteFloat => string_type,
+        format if options.format_tolerance.is_some() => StringType::Tally {
+            format: Box::new(format),
+            matches: 1,
+            outliers: 0,
+            unmatched: None,
+        },
+        format => format,
+    }
+}
+
+pub(crate) fn merge(initial: SchemaState, new: SchemaState, max_samples: usize) -> SchemaState {
+    let mut merged = initial;
+    merge_into(&mut merged, new, max_samples);
+    merged
+}
+
+/// Merges `new` into `schema` in place. The schemas that are merged most often, those of
+/// objects, arrays, unknown strings and numbers, reuse the maps, boxes and samples of `schema`
+/// rather than building them anew for every pair of values; the others are merged by
+/// `merge_owned`.
+pub(crate) fn merge_into(schema: &mut SchemaState, new: SchemaState, max_samples: usize) {
+    let rest = match (&mut *schema, new) {
         (
             SchemaState::String(StringType::Unknown {
-                mut strings_seen,
-                mut chars_seen,
+                strings_seen,
+                chars_seen,
+                n_strings_seen,
+                distinct,
+                n_chars_seen,
                 min_length,
                 max_length,
+                prefix,
+                suffix,
+                structure,
             }),
             SchemaState::String(StringType::Unknown {
                 strings_seen: second_strings_seen,
                 chars_seen: second_chars_seen,
+                n_strings_seen: second_n_strings_seen,
+                distinct: second_distinct,
+                n_chars_seen: second_n_chars_seen,
                 min_length: second_min_length,
                 max_length: second_max_length,
+                prefix: second_prefix,
+                suffix: second_suffix,
+                structure: second_structure,
             }),
         ) => {
-            let min_length = match (min_length, second_min_length) {
-                (Some(min_length), Some(second_min_length)) => {
-                    Some(min(min_length, second_min_length))
-                }
-                (Some(min_length), None) => Some(min_length),
-                (None, Some(second_min_length)) => Some(second_min_length),
-                _ => None,
+            *min_length = match (*min_length, second_min_length) {
+                (Some(first), Some(second)) => Some(min(first, second)),
+                (first, second) => first.or(second),
             };
+            *max_length = match (*max_length, second_max_length) {
+                (Some(first), Some(second)) => Some(max(first, second)),
+                (first, second) => first.or(second),
+            };
+            *chars_seen = merge_counts(std::mem::take(chars_seen), second_chars_seen, max_samples);
+            *strings_seen = merge_samples(
+                std::mem::take(strings_seen),
+                *n_strings_seen,
+                second_strings_seen,
+                second_n_strings_seen,
+                max_samples,
+            );
+            *n_strings_seen += second_n_strings_seen;
+            *n_chars_seen += second_n_chars_seen;
+            *distinct = std::mem::take(distinct).merge(second_distinct);
+            let prefix_length = common_prefix(prefix, &second_prefix).len();
+            prefix.truncate(prefix_length);
+            let suffix_length = common_suffix(suffix, &second_suffix).len();
+            suffix.drain(..suffix.len() - suffix_length);
+            *structure = merge_structures(structure.take(), second_structure);
+            None
+        }
+
+        (
+            SchemaState::Number(NumberType::Integer {
+                min: first_min,
+                max: first_max,
+            }),
+            SchemaState::Number(NumberType::Integer {
+                min: second_min,
+                max: second_max,
+            }),
+        ) => {
+            *first_min = min(*first_min, second_min);
+            *first_max = max(*first_max, second_max);
+            None
+        }
+        (
+            SchemaState::Number(NumberType::Float {
+                min: first_min,
+                max: first_max,
+                non_finite,
+                exclusive_min,
+                exclusive_max,
+            }),
+            SchemaState::Number(NumberType::Float {
+                min: second_min,
+                max: second_max,
+                non_finite: second_non_finite,
+                exclusive_min: second_exclusive_min,
+                exclusive_max: second_exclusive_max,
+            }),
+        ) => {
+            (*first_min, *exclusive_min) = lower_bound(
+                (*first_min, *exclusive_min),
+                (second_min, second_exclusive_min),
+            );
+            (*first_max, *exclusive_max) = upper_bound(
+                (*first_max, *exclusive_max),
+                (second_max, second_exclusive_max),
+            );
+            *non_finite |= second_non_finite;
+            None
+        }
+
+        (SchemaState::Boolean, SchemaState::Boolean) | (SchemaState::Null, SchemaState::Null) => {
+            None
+        }
+
+        (
+            SchemaState::Array {
+                min_length,
+                max_length,
+                unique,
+                schema,
+            },
+            SchemaState::Array {
+                min_length: second_min_length,
+                max_length: second_max_length,
+                unique: second_unique,
+                schema: second_schema,
+            },
+        ) => {
+            *min_length = min(*min_length, second_min_length);
+            *max_length = max(*max_length, second_max_length);
+            *unique &= second_unique;
+            merge_into(schema, *second_schema, max_samples);
+            None
+        }
 
-            let max_length = match (max_length, second_max_length) {
-                (Some(max_length), Some(second_max_length)) => {
-                    Some(max(max_length, second_max_length))
+        (
+            SchemaState::Object { required, optional },
+            SchemaState::Object {
+                required: second_required,
+                optional: second_optional,
+            },
+        ) => {
+            // fields keep the order they were first seen in, the first object's before the
+            // second's, and required fields the second object lacks become optional
+            if required.keys().any(|k| !second_required.contains_key(k)) {
+                for (k, v) in std::mem::take(required) {
+                    if second_required.contains_key(&k) {
+                        required.insert(k, v);
+                    } else {
+                        optional.insert(k, v);
+                    }
                 }
-                (Some(max_length), None) => Some(max_length),
-                (None, Some(second_max_length)) => Some(second_max_length),
-                _ => None,
-            };
+            }
+            let new_optional = merge_fields_into(optional, second_optional, max_samples);
+            optional.extend(new_optional);
+            let rest = merge_fields_into(required, second_required, max_samples);
+            let new_optional = merge_fields_into(optional, rest, max_samples);
+            optional.extend(new_optional);
+            None
+        }
 
-            chars_seen.extend(second_chars_seen);
-            strings_seen.extend(second_strings_seen);
+        (SchemaState::Nullable(_), SchemaState::Null) => None,
+        (SchemaState::Nullable(inner), SchemaState::Nullable(second_inner)) => {
+            merge_into(inner, *second_inner, max_samples);
+            None
+        }
+        (SchemaState::Nullable(inner), new)
+            if !matches!(new, SchemaState::Initial | SchemaState::Indefinite) =>
+        {
+            merge_into(inner, new, max_samples);
+            None
+        }
+
+        (_, new) => Some(new),
+    };
+    if let Some(new) = rest {
+        let initial = std::mem::replace(schema, SchemaState::Initial);
+        *schema = merge_owned(initial, new, max_samples);
+    }
+}
+
+/// Merges the schemas that `merge_into` does not merge in place, consuming both.
+fn merge_owned(initial: SchemaState, new: SchemaState, max_samples: usize) -> SchemaState {
+    match (initial, new) {
+        (SchemaState::Initial, s) | (s, SchemaState::Initial) => s,
+        // values of conflicting types stay indefinite, whatever they are merged with, so that
+        // the order in which schemas are merged does not matter
+        (SchemaState::Indefinite, SchemaState::Null | SchemaState::Nullable(_))
+        | (SchemaState::Null | SchemaState::Nullable(_), SchemaState::Indefinite) => {
+            SchemaState::Nullable(Box::new(SchemaState::Indefinite))
+        }
+        (SchemaState::Indefinite, _) | (_, SchemaState::Indefinite) => SchemaState::Indefinite,
+
+        // --- String merging ---
+        // strings counted towards a tally keep their format until they are settled
+        (
+            SchemaState::String(first @ StringType::Tally { .. }),
+            SchemaState::String(second @ StringType::Tally { .. }),
+        ) => SchemaState::String(merge_tallies(first, second, max_samples)),
+        (SchemaState::String(tally @ StringType::Tally { .. }), SchemaState::String(other))
+        | (SchemaState::String(other), SchemaState::String(tally @ StringType::Tally { .. })) => {
+            SchemaState::String(add_outliers(tally, other, max_samples))
+        }
 
+        // strings of a known format are absorbed into unknown strings, which then no longer
+        // share a prefix, suffix or structure
+        (
+            SchemaState::String(StringType::Unknown {
+                strings_seen,
+                chars_seen,
+                n_strings_seen,
+                distinct,
+                n_chars_seen,
+                min_length,
+                max_length,
+                ..
+            }),
+            SchemaState::String(_),
+        )
+        | (
+            SchemaState::String(_),
             SchemaState::String(StringType::Unknown {
                 strings_seen,
                 chars_seen,
+                n_strings_seen,
+                distinct,
+                n_chars_seen,
                 min_length,
                 max_length,
+                ..
+            }),
+        ) => SchemaState::String(StringType::Unknown {
+            strings_seen,
+            chars_seen,
+            n_strings_seen,
+            distinct,
+            n_chars_seen,
+            min_length,
+            max_length,
+            prefix: String::new(),
+            suffix: String::new(),
+            structure: None,
+        }),
+
+        (
+            SchemaState::String(StringType::IsoDate { range: first }),
+            SchemaState::String(StringType::IsoDate { range: second }),
+        ) => SchemaState::String(StringType::IsoDate {
+            range: merge_ranges(first, second),
+        }),
+        (
+            SchemaState::String(StringType::DateTimeISO8601 { range: first }),
+            SchemaState::String(StringType::DateTimeISO8601 { range: second }),
+        ) => SchemaState::String(StringType::DateTimeISO8601 {
+            range: merge_ranges(first, second),
+        }),
+        (
+            SchemaState::String(StringType::DateTimeRFC2822 { range: first }),
+            SchemaState::String(StringType::DateTimeRFC2822 { range: second }),
+        ) => SchemaState::String(StringType::DateTimeRFC2822 {
+            range: merge_ranges(first, second),
+        }),
+        (
+            SchemaState::String(StringType::FormattedDate {
+                format,
+                range: first,
+            }),
+            SchemaState::String(StringType::FormattedDate {
+                format: second_format,
+                range: second,
+            }),
+        ) if format == second_format => SchemaState::String(StringType::FormattedDate {
+            format,
+            range: merge_ranges(first, second),
+        }),
+        (
+            SchemaState::String(StringType::EpochSeconds { range: first }),
+            SchemaState::String(StringType::EpochSeconds { range: second }),
+        ) => SchemaState::String(StringType::EpochSeconds {
+            range: merge_ranges(first, second),
+        }),
+        // times written with and without seconds are produced with them
+        (
+            SchemaState::String(StringType::Time {
+                range: first,
+                seconds,
+            }),
+            SchemaState::String(StringType::Time {
+                range: second,
+                seconds: second_seconds,
+            }),
+        ) => SchemaState::String(StringType::Time {
+            range: merge_ranges(first, second),
+            seconds: seconds || second_seconds,
+        }),
+        (
+            SchemaState::String(StringType::FilePath {
+                kind,
+                roots: mut first_roots,
+                min_depth,
+                max_depth,
+                extensions: mut first_extensions,
+            }),
+            SchemaState::String(StringType::FilePath {
+                kind: second_kind,
+                roots: second_roots,
+                min_depth: second_min_depth,
+                max_depth: second_max_depth,
+                extensions: second_extensions,
+            }),
+        ) if kind == second_kind => {
+            for (root, count) in second_roots {
+                *first_roots.entry(root).or_default() += count;
+            }
+            for (extension, count) in second_extensions {
+                *first_extensions.entry(extension).or_default() += count;
+            }
+            SchemaState::String(StringType::FilePath {
+                kind,
+                roots: first_roots,
+                min_depth: min(min_depth, second_min_depth),
+                max_depth: max(max_depth, second_max_depth),
+                extensions: first_extensions,
             })
         }
+        (
+            SchemaState::String(StringType::Text {
+                min_words,
+                max_words,
+            }),
+            SchemaState::String(StringType::Text {
+                min_words: second_min_words,
+                max_words: second_max_words,
+            }),
+        ) => SchemaState::String(StringType::Text {
+            min_words: min(min_words, second_min_words),
+            max_words: max(max_words, second_max_words),
+        }),
+        (
+            SchemaState::String(StringType::Duration { range: first }),
+            SchemaState::String(StringType::Duration { range: second }),
+        ) => SchemaState::String(StringType::Duration {
+            range: merge_ranges(first, second),
+        }),
 
-        (s @ SchemaState::String(StringType::Unknown { .. }), SchemaState::String(_))
-        | (SchemaState::String(_), s @ SchemaState::String(StringType::Unknown { .. })) => s,
+        (
+            SchemaState::String(StringType::PhoneNumber { formats: mut first }),
+            SchemaState::String(StringType::PhoneNumber { formats: second }),
+        ) => {
+            for (format, count) in second {
+                *first.entry(format).or_default() += count;
+            }
+            SchemaState::String(StringType::PhoneNumber { formats: first })
+        }
+        (
+            SchemaState::String(StringType::Semver {
+                min_major: first_min,
+                max_major: first_max,
+            }),
+            SchemaState::String(StringType::Semver {
+                min_major: second_min,
+                max_major: second_max,
+            }),
+        ) => SchemaState::String(StringType::Semver {
+            min_major: first_min.min(second_min),
+            max_major: first_max.max(second_max),
+        }),
+        (
+            SchemaState::String(StringType::Base64 {
+                min_length: first_min,
+                max_length: first_max,
+            }),
+            SchemaState::String(StringType::Base64 {
+                min_length: second_min,
+                max_length: second_max,
+            }),
+        ) => SchemaState::String(StringType::Base64 {
+            min_length: first_min.min(second_min),
+            max_length: first_max.max(second_max),
+        }),
+        (
+            SchemaState::String(StringType::CurrencyCode { codes: mut first }),
+            SchemaState::String(StringType::CurrencyCode { codes: second }),
+        ) => {
+            first.extend(second);
+            SchemaState::String(StringType::CurrencyCode { codes: first })
+        }
+        (
+            SchemaState::String(StringType::EmbeddedJson(first)),
+            SchemaState::String(StringType::EmbeddedJson(second)),
+        ) => SchemaState::String(StringType::EmbeddedJson(Box::new(merge(
+            *first,
+            *second,
+            max_samples,
+        )))),
+        (
+            SchemaState::String(StringType::Jwt { claims: first }),
+            SchemaState::String(StringType::Jwt { claims: second }),
+        ) => SchemaState::String(StringType::Jwt {
+            claims: Box::new(merge(*first, *second, max_samples)),
+        }),
 
         (SchemaState::String(first_type), SchemaState::String(second_type)) => {
             if first_type == second_type {
@@ -88,30 +733,138 @@ fn merge(initial: SchemaState, new: SchemaState) -> SchemaState {
             } else {
                 SchemaState::String(StringType::Unknown {
                     strings_seen: vec![],
-                    chars_seen: vec![],
+                    chars_seen: HashMap::new(),
+                    n_strings_seen: 0,
+                    distinct: DistinctCount::new(),
+                    n_chars_seen: 0,
                     min_length: None,
                     max_length: None,
+                    prefix: String::new(),
+                    suffix: String::new(),
+                    structure: None,
                 })
             }
         }
 
         // --- Number merging ---
+        (
+            SchemaState::Number(NumberType::Enum {
+                variants: mut first,
+                n_seen: first_n_seen,
+            }),
+            SchemaState::Number(NumberType::Enum {
+                variants: second,
+                n_seen: second_n_seen,
+            }),
+        ) => {
+            first.extend(second);
+            let merged = NumberType::Enum {
+                variants: first,
+                n_seen: first_n_seen + second_n_seen,
+            };
+            // integers with more distinct values than are kept as a sample are no enum
+            match merged {
+                NumberType::Enum { ref variants, .. } if variants.len() > max_samples => {
+                    SchemaState::Number(integers(&merged))
+                }
+                merged => SchemaState::Number(merged),
+            }
+        }
+        // integers merged with other numbers are a range
+        (SchemaState::Number(number_type @ NumberType::Enum { .. }), other)
+        | (other, SchemaState::Number(number_type @ NumberType::Enum { .. }))
+            if matches!(
+                other,
+                SchemaState::Number(_) | SchemaState::String(StringType::NonFiniteFloat)
+            ) =>
+        {
+            merge(
+                SchemaState::Number(integers(&number_type)),
+                other,
+                max_samples,
+            )
+        }
+        // integers beyond the range of i64 widen the integers they are merged with, and are
+        // floats when merged with floats
+        (SchemaState::Number(big @ NumberType::BigInteger { .. }), SchemaState::Number(other))
+        | (SchemaState::Number(other), SchemaState::Number(big @ NumberType::BigInteger { .. })) => {
+            let (big_min, big_max) = big.integer_range().unwrap_or_default();
+            match other.integer_range() {
+                Some((other_min, other_max)) => SchemaState::Number(NumberType::integers(
+                    min(big_min, other_min),
+                    max(big_max, other_max),
+                )),
+                None => merge(
+                    SchemaState::Number(NumberType::Float {
+                        min: big_min as f64,
+                        max: big_max as f64,
+                        non_finite: false,
+                        exclusive_min: false,
+                        exclusive_max: false,
+                    }),
+                    SchemaState::Number(other),
+                    max_samples,
+                ),
+            }
+        }
         (
             SchemaState::Number(NumberType::Float {
                 min: first_min,
                 max: first_max,
+                non_finite,
+                exclusive_min,
+                exclusive_max,
+            }),
+            SchemaState::Number(NumberType::Integer {
+                min: second_min,
+                max: second_max,
             }),
+        )
+        | (
+            SchemaState::Number(NumberType::Integer {
+                min: second_min,
+                max: second_max,
+            }),
+            SchemaState::Number(NumberType::Float {
+                min: first_min,
+                max: first_max,
+                non_finite,
+                exclusive_min,
+                exclusive_max,
+            }),
+        ) => {
+            let (lowest, exclusive_min) =
+                lower_bound((first_min, exclusive_min), (second_min as f64, false));
+            let (highest, exclusive_max) =
+                upper_bound((first_max, exclusive_max), (second_max as f64, false));
             SchemaState::Number(NumberType::Float {
+                min: lowest,
+                max: highest,
+                non_finite,
+                exclusive_min,
+                exclusive_max,
+            })
+        }
+
+        (
+            SchemaState::Number(NumberType::Decimal {
+                scale: first_scale,
+                min: first_min,
+                max: first_max,
+            }),
+            SchemaState::Number(NumberType::Decimal {
+                scale: second_scale,
                 min: second_min,
                 max: second_max,
             }),
-        ) => SchemaState::Number(NumberType::Float {
+        ) => SchemaState::Number(NumberType::Decimal {
+            scale: max(first_scale, second_scale),
             min: min(first_min, second_min),
             max: max(first_max, second_max),
         }),
-
         (
-            SchemaState::Number(NumberType::Float {
+            SchemaState::Number(NumberType::Decimal {
+                scale,
                 min: first_min,
                 max: first_max,
             }),
@@ -119,149 +872,168 @@ fn merge(initial: SchemaState, new: SchemaState) -> SchemaState {
                 min: second_min,
                 max: second_max,
             }),
-        ) => SchemaState::Number(NumberType::Float {
+        )
+        | (
+            SchemaState::Number(NumberType::Integer {
+                min: second_min,
+                max: second_max,
+            }),
+            SchemaState::Number(NumberType::Decimal {
+                scale,
+                min: first_min,
+                max: first_max,
+            }),
+        ) => SchemaState::Number(NumberType::Decimal {
+            scale,
             min: min(first_min, second_min as f64),
             max: max(first_max, second_max as f64),
         }),
-
         (
-            SchemaState::Number(NumberType::Integer {
+            SchemaState::Number(NumberType::Decimal {
                 min: first_min,
                 max: first_max,
+                ..
             }),
             SchemaState::Number(NumberType::Float {
                 min: second_min,
                 max: second_max,
+                non_finite,
+                exclusive_min,
+                exclusive_max,
             }),
-        ) => SchemaState::Number(NumberType::Float {
-            min: min(first_min as f64, second_min),
-            max: max(first_max as f64, second_max),
-        }),
-
-        (
-            SchemaState::Number(NumberType::Integer {
-                min: first_min,
-                max: first_max,
-            }),
-            SchemaState::Number(NumberType::Integer {
+        )
+        | (
+            SchemaState::Number(NumberType::Float {
                 min: second_min,
                 max: second_max,
+                non_finite,
+                exclusive_min,
+                exclusive_max,
+            }),
+            SchemaState::Number(NumberType::Decimal {
+                min: first_min,
+                max: first_max,
+                ..
             }),
-        ) => SchemaState::Number(NumberType::Integer {
-            min: min(first_min, second_min),
-            max: max(first_max, second_max),
-        }),
-
-        // --- Boolean merging ---
-        (SchemaState::Boolean, SchemaState::Boolean) => SchemaState::Boolean,
-
-        // --- Array merging ---
-        (
-            SchemaState::Array {
-                min_length,
-                max_length,
-                schema,
-            },
-            SchemaState::Array {
-                min_length: second_min_length,
-                max_length: second_max_length,
-                schema: second_schema,
-            },
         ) => {
-            let min_length = min(min_length, second_min_length);
-            let max_length = max(max_length, second_max_length);
-            let schema = Box::new(merge(*schema, *second_schema));
-            SchemaState::Array {
-                min_length,
-                max_length,
-                schema,
-            }
+            let (lowest, exclusive_min) =
+                lower_bound((first_min, false), (second_min, exclusive_min));
+            let (highest, exclusive_max) =
+                upper_bound((first_max, false), (second_max, exclusive_max));
+            SchemaState::Number(NumberType::Float {
+                min: lowest,
+                max: highest,
+                non_finite,
+                exclusive_min,
+                exclusive_max,
+            })
         }
 
-        // --- Object merging ---
+        // "NaN" and "Infinity" strings among numbers are floats that JSON cannot represent
+        (SchemaState::Number(number_type), SchemaState::String(StringType::NonFiniteFloat))
+        | (SchemaState::String(StringType::NonFiniteFloat), SchemaState::Number(number_type)) => {
+            SchemaState::Number(match number_type {
+                NumberType::Integer { .. }
+                | NumberType::Enum { .. }
+                | NumberType::BigInteger { .. } => {
+                    let (min, max) = number_type.integer_range().unwrap_or_default();
+                    NumberType::Float {
+                        min: min as f64,
+                        max: max as f64,
+                        non_finite: true,
+                        exclusive_min: false,
+                        exclusive_max: false,
+                    }
+                }
+                NumberType::Float { min, max, .. } | NumberType::Decimal { min, max, .. } => {
+                    NumberType::Float {
+                        min,
+                        max,
+                        non_finite: true,
+                        exclusive_min: false,
+                        exclusive_max: false,
+                    }
+                }
+            })
+        }
+
+        // --- Unconstrained container merging ---
+        // containers that were only seen empty take on the shape of the containers they are
+        // merged with
+        (SchemaState::UnconstrainedArray, SchemaState::UnconstrainedArray) => {
+            SchemaState::UnconstrainedArray
+        }
         (
-            SchemaState::Object {
-                required: mut first_required,
-                optional: mut first_optional,
-            },
-            SchemaState::Object {
-                required: mut second_required,
-                optional: mut second_optional,
-            },
-        ) => {
-            let required_keys: std::collections::HashSet<String> = first_required
-                .keys()
-                .filter(|k| second_required.contains_key(*k))
+            SchemaState::UnconstrainedArray,
+            array @ (SchemaState::Array { .. } | SchemaState::Tuple(_)),
+        )
+        | (
+            array @ (SchemaState::Array { .. } | SchemaState::Tuple(_)),
+            SchemaState::UnconstrainedArray,
+        ) => array,
+        (SchemaState::UnconstrainedObject, SchemaState::UnconstrainedObject) => {
+            SchemaState::UnconstrainedObject
+        }
+        (SchemaState::UnconstrainedObject, object @ SchemaState::Object { .. })
+        | (object @ SchemaState::Object { .. }, SchemaState::UnconstrainedObject) => object,
+
+        // --- Tuple merging ---
+        // tuples stay positional only for as long as every position keeps a single type; once a
+        // position conflicts, the tuple is widened into an array of all its elements instead
+        (SchemaState::Tuple(first), SchemaState::Tuple(second)) if first.len() == second.len() => {
+            let merged = first
+                .iter()
                 .cloned()
-                .collect();
-
-            let optional_keys: std::collections::HashSet<String> = first_optional
-                .keys()
-                .chain(second_optional.keys())
-                .chain(
-                    first_required
-                        .keys()
-                        .chain(second_required.keys())
-                        .filter(|key| !required_keys.contains(*key)),
+                .zip(second.iter().cloned())
+                .map(|(first, second)| merge(first, second, max_samples))
+                .collect::<Vec<_>>();
+            if merged.iter().any(is_conflicting) {
+                merge(
+                    tuple_to_array(first, max_samples),
+                    tuple_to_array(second, max_samples),
+                    max_samples,
                 )
-                .cloned()
-                .collect();
-
-            let required: std::collections::HashMap<String, SchemaState> = required_keys
-                .into_iter()
-                .map(|k| {
-                    let first = first_required.remove(&k);
-                    let second = second_required.remove(&k);
-                    let merged = match (first, second) {
-                        (Some(first), Some(second)) => merge(first, second),
-                        (Some(first), None) => first,
-                        (None, Some(second)) => second,
-                        _ => unreachable!(),
-                    };
-                    (k, merged)
-                })
-                .collect();
-
-            let optional: std::collections::HashMap<String, SchemaState> = optional_keys
-                .into_iter()
-                .map(|k| {
-                    let first = first_required
-                        .remove(&k)
-                        .or_else(|| first_optional.remove(&k));
-                    let second = second_required
-                        .remove(&k)
-                        .or_else(|| second_optional.remove(&k));
-                    let merged = match (first, second) {
-                        (Some(first), Some(second)) => merge(first, second),
-                        (Some(first), None) => first,
-                        (None, Some(second)) => second,
-                        _ => unreachable!(),
-                    };
-                    (k, merged)
-                })
-                .collect();
+            } else {
+                SchemaState::Tuple(merged)
+            }
+        }
 
-            SchemaState::Object { required, optional }
+        // tuples that disagree on their length, or that are merged with a regular array, are no
+        // longer positional; they are widened into an array of all their elements
+        (SchemaState::Tuple(first), SchemaState::Tuple(second)) => merge(
+            tuple_to_array(first, max_samples),
+            tuple_to_array(second, max_samples),
+            max_samples,
+        ),
+        (SchemaState::Tuple(elements), array @ SchemaState::Array { .. })
+        | (array @ SchemaState::Array { .. }, SchemaState::Tuple(elements)) => {
+            merge(tuple_to_array(elements, max_samples), array, max_samples)
         }
 
         // --- Null(able) merging ---
-        (SchemaState::Null, SchemaState::Null) => SchemaState::Null,
-
-        (SchemaState::Null, SchemaState::Nullable(inner))
-        | (SchemaState::Nullable(inner), SchemaState::Null) => SchemaState::Nullable(inner),
+        (SchemaState::Null, SchemaState::Nullable(inner)) => SchemaState::Nullable(inner),
 
         (non_null_type, SchemaState::Null) => SchemaState::Nullable(Box::new(non_null_type)),
         (SchemaState::Null, non_null_type) => SchemaState::Nullable(Box::new(non_null_type)),
 
-        (SchemaState::Nullable(first_inner), SchemaState::Nullable(second_inner)) => {
-            SchemaState::Nullable(Box::new(merge(*first_inner, *second_inner)))
+        (non_nullable_type, SchemaState::Nullable(inner)) => {
+            SchemaState::Nullable(Box::new(merge(non_nullable_type, *inner, max_samples)))
         }
 
-        (SchemaState::Nullable(inner), non_nullable_type) => {
-            SchemaState::Nullable(Box::new(merge(*inner, non_nullable_type)))
+        (SchemaState::Const(first), SchemaState::Const(second)) if first == second => {
+            SchemaState::Const(first)
         }
-        (non_nullable_type, SchemaState::Nullable(inner)) => {
-            SchemaState::Nullable(Box::new(merge(non_nullable_type, *inner)))
+
+        // --- Union merging ---
+        (SchemaState::Union(mut first), SchemaState::Union(second)) => {
+            for branch in second {
+                merge_branch(&mut first, branch, max_samples);
+            }
+            SchemaState::Union(first)
+        }
+        (SchemaState::Union(mut branches), other) | (other, SchemaState::Union(mut branches)) => {
+            merge_branch(&mut branches, other, max_samples);
+            SchemaState::Union(branches)
         }
 
         // --- Fallback ---
@@ -269,55 +1041,658 @@ fn merge(initial: SchemaState, new: SchemaState) -> SchemaState {
     }
 }
 
+/// Returns the kind of values a branch of a union describes, if branches of that kind merge
+/// into one: constants and maps only merge with branches equal to them.
+fn branch_kind(schema: &SchemaState) -> Option<&'static str> {
+    match schema {
+        SchemaState::Nullable(inner) => branch_kind(inner),
+        SchemaState::Null => Some("null"),
+        SchemaState::String(_) => Some("string"),
+        SchemaState::Number(_) => Some("number"),
+        SchemaState::Boolean => Some("boolean"),
+        SchemaState::Array { .. } | SchemaState::Tuple(_) | SchemaState::UnconstrainedArray => {
+            Some("array")
+        }
+        SchemaState::Object { .. } | SchemaState::UnconstrainedObject => Some("object"),
+        SchemaState::Map { .. }
+        | SchemaState::Const(_)
+        | SchemaState::Union(_)
+        | SchemaState::Initial
+        | SchemaState::Indefinite => None,
+    }
+}
+
+/// Adds `branch` to the branches of a union: merged into the branch of the same kind, if any,
+/// so that merging a union with itself leaves it as it is, and appended otherwise.
+fn merge_branch(branches: &mut Vec<SchemaState>, branch: SchemaState, max_samples: usize) {
+    match branch {
+        SchemaState::Initial => {}
+        SchemaState::Union(inner) => {
+            for branch in inner {
+                merge_branch(branches, branch, max_samples);
+            }
+        }
+        branch if branches.contains(&branch) => {}
+        branch => {
+            let kind = branch_kind(&branch);
+            match branches
+                .iter_mut()
+                .find(|existing| kind.is_some() && branch_kind(existing) == kind)
+            {
+                Some(existing) => merge_into(existing, branch, max_samples),
+                None => branches.push(branch),
+            }
+        }
+    }
+}
+
+pub(crate) fn tuple_to_array(elements: Vec<SchemaState>, max_samples: usize) -> SchemaState {
+    let length = elements.len();
+    let schema = elements
+        .into_iter()
+        .fold(SchemaState::Initial, |acc, element| {
+            merge(acc, element, max_samples)
+        });
+    SchemaState::Array {
+        min_length: length,
+        max_length: length,
+        unique: false,
+        schema: Box::new(schema),
+    }
+}
+
+/// Returns whether a tuple position has merged into a conflict, in which case the tuple is no
+/// longer positional.
+fn is_conflicting(schema: &SchemaState) -> bool {
+    match schema {
+        SchemaState::Indefinite => true,
+        SchemaState::Nullable(inner) => is_conflicting(inner),
+        _ => false,
+    }
+}
+
+/// Returns the JSON type of a schema, for the purpose of telling tuples apart from arrays.
+/// Nulls are left out, as they make an element nullable rather than positional.
+fn tuple_element_type(schema: &SchemaState) -> Option<&'static str> {
+    match schema {
+        SchemaState::String(_) => Some("string"),
+        SchemaState::Number(_) => Some("number"),
+        SchemaState::Boolean => Some("boolean"),
+        SchemaState::Array { .. } | SchemaState::Tuple(_) | SchemaState::UnconstrainedArray => {
+            Some("array")
+        }
+        SchemaState::Object { .. } | SchemaState::Map { .. } | SchemaState::UnconstrainedObject => {
+            Some("object")
+        }
+        _ => None,
+    }
+}
+
+/// Infers the schema of an array nested within the input. Arrays of two up to
+/// `MAX_TUPLE_LENGTH` elements are inferred position by position as tuples, e.g. `[52.1, 4.3]`,
+/// unless they hold only objects or only arrays, which are records rather than positions. Tuples
+/// are widened into arrays when later samples disagree on their length or on the type at any
+/// position.
+fn infer_array(
+    array: Vec<serde_json::Value>,
+    options: &InferenceOptions,
+    cancellation: Option<&Cancellation>,
+    depth: usize,
+) -> Result<SchemaState, Cancelled> {
+    let elements = array
+        .into_iter()
+        .map(|value| infer_schema_inner(value, options, cancellation, depth + 1))
+        .collect::<Result<Vec<_>, Cancelled>>()?;
+
+    let types = elements
+        .iter()
+        .filter_map(tuple_element_type)
+        .collect::<std::collections::HashSet<_>>();
+    let records = types.len() == 1 && (types.contains("object") || types.contains("array"));
+    if (2..=MAX_TUPLE_LENGTH).contains(&elements.len()) && !records {
+        return Ok(SchemaState::Tuple(elements));
+    }
+
+    let length = elements.len();
+    let schema = elements
+        .into_iter()
+        .try_fold(SchemaState::Initial, |acc, element| {
+            cancel::check(cancellation)?;
+            Ok(timed(options.profile.as_deref(), Stage::Merging, || {
+                merge(acc, element, options.max_samples)
+            }))
+        })?;
+    Ok(SchemaState::Array {
+        min_length: length,
+        max_length: length,
+        unique: false,
+        schema: Box::new(schema),
+    })
+}
+
 fn apply_enum_inner(s: StringType, opts: &EnumInference) -> StringType {
     match &s {
-        StringType::Unknown { strings_seen, .. } => {
-            if strings_seen.len() < opts.min_sample_size {
+        StringType::Unknown {
+            strings_seen,
+            n_strings_seen,
+            distinct,
+            ..
+        } => {
+            if *n_strings_seen < opts.min_sample_size || strings_seen.is_empty() {
                 return s;
             }
 
+            // the ratio of unique values is that of all strings seen, not just of the sample
+            let unique_ratio = distinct.estimate() as f64 / *n_strings_seen as f64;
+            if unique_ratio > opts.max_unique_ratio {
+                return s;
+            }
+
+            // strings_seen is a uniform sample of all strings seen, which holds every variant
+            // but the rarest
             let variants = strings_seen
                 .iter()
                 .cloned()
                 .collect::<std::collections::HashSet<_>>();
+            StringType::Enum { variants }
+        }
+        _ => s,
+    }
+}
 
-            let unique_ratio = variants.len() as f64 / strings_seen.len() as f64;
-            if unique_ratio > opts.max_unique_ratio {
-                return s;
+fn apply_enum_recursive(s: SchemaState, opts: &EnumInference) -> SchemaState {
+    match s {
+        SchemaState::String(s) => SchemaState::String(apply_enum_inner(s, opts)),
+        SchemaState::Array {
+            min_length,
+            max_length,
+            unique,
+            schema,
+        } => SchemaState::Array {
+            min_length,
+            max_length,
+            unique,
+            schema: Box::new(apply_enum_recursive(*schema, opts)),
+        },
+        SchemaState::Object { required, optional } => SchemaState::Object {
+            required: required
+                .into_iter()
+                .map(|(k, v)| (k, apply_enum_recursive(v, opts)))
+                .collect(),
+            optional: optional
+                .into_iter()
+                .map(|(k, v)| (k, apply_enum_recursive(v, opts)))
+                .collect(),
+        },
+        SchemaState::Tuple(elements) => SchemaState::Tuple(
+            elements
+                .into_iter()
+                .map(|element| apply_enum_recursive(element, opts))
+                .collect(),
+        ),
+        SchemaState::Union(branches) => SchemaState::Union(
+            branches
+                .into_iter()
+                .map(|branch| apply_enum_recursive(branch, opts))
+                .collect(),
+        ),
+        SchemaState::Nullable(inner) => {
+            SchemaState::Nullable(Box::new(apply_enum_recursive(*inner, opts)))
+        }
+        _ => s,
+    }
+}
+
+/// Parses a string that embeds a JSON object or array, if embedded JSON is to be inferred.
+fn embedded_json(s: &str, options: &InferenceOptions) -> Option<serde_json::Value> {
+    if !options.embedded_json || !s.trim_start().starts_with(['{', '[']) {
+        return None;
+    }
+    serde_json::from_str(s).ok()
+}
+
+/// Drops a value using a stack of its own, rather than recursing into it as dropping does, so
+/// that values nested too deeply to infer cannot overflow the stack when they are dropped.
+fn drop_iteratively(value: serde_json::Value) {
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        match value {
+            serde_json::Value::Array(array) => stack.extend(array),
+            serde_json::Value::Object(object) => stack.extend(object.into_iter().map(|(_, v)| v)),
+            _ => {}
+        }
+    }
+}
+
+/// Returns whether `value` is an array or object that is nested too deeply to infer, being at
+/// `depth` (where the records are at depth 1).
+fn is_truncated(value: &serde_json::Value, depth: usize, max_depth: usize) -> bool {
+    depth > max_depth
+        && match value {
+            serde_json::Value::Array(array) => !array.is_empty(),
+            serde_json::Value::Object(object) => !object.is_empty(),
+            _ => false,
+        }
+}
+
+/// Infers the schema of `json`, found at `depth` within the input: the root is at depth 0 and
+/// the records at depth 1.
+fn infer_schema_inner(
+    json: serde_json::Value,
+    options: &InferenceOptions,
+    cancellation: Option<&Cancellation>,
+    depth: usize,
+) -> Result<SchemaState, Cancelled> {
+    cancel::check(cancellation)?;
+
+    if is_truncated(&json, depth, options.max_depth) {
+        let truncated = if json.is_array() {
+            SchemaState::UnconstrainedArray
+        } else {
+            SchemaState::UnconstrainedObject
+        };
+        drop_iteratively(json);
+        return Ok(truncated);
+    }
+
+    let inferred = match json {
+        serde_json::Value::Null => SchemaState::Null,
+        serde_json::Value::String(value) => match embedded_json(&value, options) {
+            Some(document) => SchemaState::String(StringType::EmbeddedJson(Box::new(
+                infer_schema_inner(document, options, cancellation, depth + 1)?,
+            ))),
+            None => {
+                SchemaState::String(timed(options.profile.as_deref(), Stage::Detection, || {
+                    tallied(
+                        infer_string_type(
+                            &value,
+                            &options.detectors,
+                            options.max_samples,
+                            options.profile.as_deref(),
+                        ),
+                        options,
+                    )
+                }))
+            }
+        },
+        serde_json::Value::Number(n) => SchemaState::Number(if let Some(value) = n.as_i64() {
+            if options.enum_inference.is_some() {
+                // whether integers are an enum can only be told once all of them were seen
+                NumberType::Enum {
+                    variants: HashSet::from([value]),
+                    n_seen: 1,
+                }
+            } else {
+                NumberType::Integer {
+                    min: value,
+                    max: value,
+                }
+            }
+        } else if let Some(value) = integer_value(&n) {
+            NumberType::BigInteger {
+                min: value,
+                max: value,
+            }
+        } else {
+            let n_f64 = n.as_f64().unwrap_or(f64::NAN);
+            match decimal_places(&n) {
+                Some(scale) if scale <= MONETARY_SCALE => NumberType::Decimal {
+                    scale,
+                    min: n_f64,
+                    max: n_f64,
+                },
+                _ => NumberType::Float {
+                    min: n_f64,
+                    max: n_f64,
+                    non_finite: false,
+                    exclusive_min: false,
+                    exclusive_max: false,
+                },
+            }
+        }),
+        serde_json::Value::Bool(_) => SchemaState::Boolean,
+        // an array at the root holds the records, which are never positional
+        serde_json::Value::Array(array) if depth == 0 => SchemaState::Array {
+            min_length: array.len(),
+            max_length: array.len(),
+            unique: false,
+            schema: Box::new(infer_records(array, options, cancellation)?),
+        },
+        serde_json::Value::Array(array) if array.is_empty() && options.unconstrained_empty => {
+            SchemaState::UnconstrainedArray
+        }
+        serde_json::Value::Array(array) => infer_array(array, options, cancellation, depth)?,
+        serde_json::Value::Object(object) if object.is_empty() && options.unconstrained_empty => {
+            SchemaState::UnconstrainedObject
+        }
+        serde_json::Value::Object(object) => SchemaState::Object {
+            required: object
+                .into_iter()
+                .map(|(k, v)| Ok((k, infer_schema_inner(v, options, cancellation, depth + 1)?)))
+                .collect::<Result<_, Cancelled>>()?,
+            optional: IndexMap::new(),
+        },
+    };
+
+    if let Some(enum_opts) = &options.enum_inference {
+        Ok(timed(options.profile.as_deref(), Stage::Enums, || {
+            apply_enum_recursive(inferred, enum_opts)
+        }))
+    } else {
+        Ok(inferred)
+    }
+}
+
+/// The number of fields of an object from which they are merged in parallel, as merging fewer
+/// costs less than spreading them across threads.
+const MIN_PARALLEL_FIELDS: usize = 64;
+
+/// Merges the fields of another object into the fields of the same name in `fields`, in
+/// parallel if there are many, and returns the fields `fields` lacks in their original order.
+fn merge_fields_into(
+    fields: &mut IndexMap<String, SchemaState>,
+    other: impl IntoIterator<Item = (String, SchemaState)>,
+    max_samples: usize,
+) -> Vec<(String, SchemaState)> {
+    let mut missing = vec![];
+    if fields.len() < MIN_PARALLEL_FIELDS {
+        for (k, v) in other {
+            match fields.get_mut(&k) {
+                Some(field) => merge_into(field, v, max_samples),
+                None => missing.push((k, v)),
             }
+        }
+        return missing;
+    }
 
-            StringType::Enum { variants }
+    let mut merged = std::iter::repeat_with(|| None)
+        .take(fields.len())
+        .collect::<Vec<_>>();
+    for (k, v) in other {
+        match fields.get_index_of(&k) {
+            Some(idx) => merged[idx] = Some(v),
+            None => missing.push((k, v)),
         }
-        _ => s,
     }
+    fields
+        .values_mut()
+        .zip(merged)
+        .filter_map(|(field, v)| Some((field, v?)))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .for_each(|(field, v)| merge_into(field, v, max_samples));
+    missing
+}
+
+fn infer_schema_from_iter_inner(
+    values: Vec<serde_json::Value>,
+    options: &InferenceOptions,
+    cancellation: Option<&Cancellation>,
+) -> Result<SchemaState, Cancelled> {
+    values
+        .into_par_iter()
+        .map(|value| infer_schema_inner(value, options, cancellation, 1))
+        .try_reduce(
+            || SchemaState::Initial,
+            |left, right| {
+                cancel::check(cancellation)?;
+                Ok(timed(options.profile.as_deref(), Stage::Merging, || {
+                    merge(left, right, options.max_samples)
+                }))
+            },
+        )
+}
+
+/// What is observed across a set of records before inferring their schema: the values seen only
+/// once, and the values at pinned paths.
+struct Observations {
+    singletons: Option<HashSet<String>>,
+    pinned: HashMap<String, Vec<serde_json::Value>>,
+}
+
+fn observe(records: &[serde_json::Value], options: &InferenceOptions) -> Observations {
+    Observations {
+        singletons: options.widen_singletons.then(|| widen::singletons(records)),
+        // the records are only walked when there are pins, as they may be nested deeper than
+        // the max depth
+        pinned: if options.type_pins.is_empty() {
+            HashMap::new()
+        } else {
+            options.type_pins.conforming_values(records)
+        },
+    }
+}
+
+/// Counts how often the values of the records inferred were null, and their fields present, if
+/// they are to be counted.
+fn count_fields(records: &[serde_json::Value], options: &InferenceOptions) {
+    if let Some(field_counts) = &options.field_counts {
+        field_counts.count(records);
+    }
+}
+
+/// The number of decimal places of monetary amounts; numbers with fewer decimals are floats.
+const MONETARY_SCALE: u32 = 2;
+
+/// Returns the number of decimal places with which a number is written, unless it is written
+/// in exponent notation.
+pub(crate) fn decimal_places(n: &serde_json::Number) -> Option<u32> {
+    let written = n.to_string();
+    if written.contains(['e', 'E']) {
+        return None;
+    }
+    Some(
+        written
+            .split_once('.')
+            .map_or(0, |(_, decimals)| decimals.len() as u32),
+    )
+}
+
+/// Returns the value of an integer, including integers beyond the range of `i64`: unsigned
+/// 64-bit integers, and bigger ones when serde_json parses numbers with arbitrary precision.
+pub(crate) fn integer_value(n: &serde_json::Number) -> Option<i128> {
+    n.as_i64()
+        .map(i128::from)
+        .or_else(|| n.as_u64().map(i128::from))
+        .or_else(|| n.to_string().parse().ok())
+}
+
+/// Returns the range of integers that an enum of integers takes.
+fn integers(number_type: &NumberType) -> NumberType {
+    let (min, max) = number_type.integer_range().unwrap_or_default();
+    NumberType::integers(min, max)
+}
+
+/// Settles the types that can only be told once all values were seen: decimals become floats
+/// unless some of them were written with exactly the number of decimal places of monetary
+/// amounts, integers are an enum only if they have few enough distinct values, and tallied
+/// strings keep their format only if few enough of them were outliers.
+fn settle_types(schema: SchemaState, options: &InferenceOptions) -> SchemaState {
+    let enum_inference = options.enum_inference.as_ref();
+    let settle = |schema| settle_types(schema, options);
+    match schema {
+        SchemaState::Number(NumberType::Decimal { scale, min, max }) if scale != MONETARY_SCALE => {
+            SchemaState::Number(NumberType::Float {
+                min,
+                max,
+                non_finite: false,
+                exclusive_min: false,
+                exclusive_max: false,
+            })
+        }
+        SchemaState::Number(NumberType::Enum { variants, n_seen }) => {
+            let is_enum = enum_inference.is_some_and(|opts| {
+                n_seen >= opts.min_sample_size
+                    && variants.len() as f64 / n_seen as f64 <= opts.max_unique_ratio
+            });
+            let number_type = NumberType::Enum { variants, n_seen };
+            SchemaState::Number(if is_enum {
+                number_type
+            } else {
+                integers(&number_type)
+            })
+        }
+        SchemaState::String(StringType::Tally {
+            format,
+            matches,
+            outliers,
+            unmatched,
+        }) => {
+            let tolerance = options.format_tolerance.unwrap_or_default();
+            if outliers as f64 <= tolerance * (matches + outliers) as f64 {
+                return SchemaState::String(*format);
+            }
+            let tally = StringType::Tally {
+                format,
+                matches,
+                outliers,
+                unmatched,
+            };
+            let string_type = untallied(tally, options.max_samples);
+            SchemaState::String(match enum_inference {
+                Some(enum_opts) => apply_enum_inner(string_type, enum_opts),
+                None => string_type,
+            })
+        }
+        SchemaState::Nullable(inner) => SchemaState::Nullable(Box::new(settle(*inner))),
+        SchemaState::String(StringType::EmbeddedJson(inner)) => {
+            SchemaState::String(StringType::EmbeddedJson(Box::new(settle(*inner))))
+        }
+        SchemaState::Array {
+            min_length,
+            max_length,
+            unique,
+            schema,
+        } => SchemaState::Array {
+            min_length,
+            max_length,
+            unique,
+            schema: Box::new(settle(*schema)),
+        },
+        SchemaState::Tuple(elements) => {
+            SchemaState::Tuple(elements.into_iter().map(settle).collect())
+        }
+        SchemaState::Union(branches) => {
+            SchemaState::Union(branches.into_iter().map(settle).collect())
+        }
+        SchemaState::Object { required, optional } => SchemaState::Object {
+            required: required.into_iter().map(|(k, v)| (k, settle(v))).collect(),
+            optional: optional.into_iter().map(|(k, v)| (k, settle(v))).collect(),
+        },
+        schema => schema,
+    }
+}
+
+/// Refines the schema inferred from a set of records with what was observed across them, pinning
+/// the types of the pinned paths and widening the values observed only once.
+fn refine(
+    schema: SchemaState,
+    observations: Observations,
+    options: &InferenceOptions,
+    cancellation: Option<&Cancellation>,
+) -> Result<SchemaState, Cancelled> {
+    let schema = if options.type_pins.is_empty() {
+        schema
+    } else {
+        let pinned = observations
+            .pinned
+            .into_iter()
+            .map(|(path, values)| {
+                Ok((
+                    path,
+                    infer_schema_from_iter_inner(values, options, cancellation)?,
+                ))
+            })
+            .collect::<Result<_, Cancelled>>()?;
+        options.type_pins.apply(schema, pinned)
+    };
+    let schema = timed(options.profile.as_deref(), Stage::Enums, || {
+        settle_types(schema, options)
+    });
+    Ok(match observations.singletons {
+        Some(singletons) => widen::widen_singletons(schema, &singletons, options.max_samples),
+        None => schema,
+    })
+}
+
+fn collect_truncated_paths(
+    value: &serde_json::Value,
+    current_path: &str,
+    depth: usize,
+    max_depth: usize,
+    paths: &mut std::collections::BTreeSet<String>,
+) {
+    if is_truncated(value, depth, max_depth) {
+        paths.insert(current_path.to_owned());
+        return;
+    }
+    match value {
+        serde_json::Value::Array(items) => {
+            let elements_path = path::elements(current_path);
+            for item in items {
+                collect_truncated_paths(item, &elements_path, depth + 1, max_depth, paths);
+            }
+        }
+        serde_json::Value::Object(object) => {
+            for (k, v) in object {
+                let field_path = path::key(current_path, k);
+                collect_truncated_paths(v, &field_path, depth + 1, max_depth, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the paths of the arrays and objects across `records` that are nested more than
+/// `max_depth` levels deep, sorted by path. These are inferred as unconstrained, rather than
+/// described, when `InferenceOptions::max_depth` is `max_depth`.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::truncated_paths;
+/// use serde_json::json;
+///
+/// let records = vec![json!({ "a": { "b": { "c": 1 } }, "tags": ["x"] })];
+///
+/// assert_eq!(truncated_paths(&records, 2), vec!["a.b"]);
+/// ```
+pub fn truncated_paths(records: &[serde_json::Value], max_depth: usize) -> Vec<String> {
+    let mut paths = std::collections::BTreeSet::new();
+    for record in records {
+        collect_truncated_paths(record, "", 1, max_depth, &mut paths);
+    }
+    paths.into_iter().collect()
 }
 
-fn apply_enum_recursive(s: SchemaState, opts: &EnumInference) -> SchemaState {
-    match s {
-        SchemaState::String(s) => SchemaState::String(apply_enum_inner(s, opts)),
-        SchemaState::Array {
-            min_length,
-            max_length,
-            schema,
-        } => SchemaState::Array {
-            min_length,
-            max_length,
-            schema: Box::new(apply_enum_recursive(*schema, opts)),
-        },
-        SchemaState::Object { required, optional } => SchemaState::Object {
-            required: required
-                .into_iter()
-                .map(|(k, v)| (k, apply_enum_recursive(v, opts)))
-                .collect(),
-            optional: optional
-                .into_iter()
-                .map(|(k, v)| (k, apply_enum_recursive(v, opts)))
-                .collect(),
-        },
-        SchemaState::Nullable(inner) => {
-            SchemaState::Nullable(Box::new(apply_enum_recursive(*inner, opts)))
-        }
-        _ => s,
+/// Infers the schema of a set of records, refined with what was observed across them.
+fn infer_records(
+    records: Vec<serde_json::Value>,
+    options: &InferenceOptions,
+    cancellation: Option<&Cancellation>,
+) -> Result<SchemaState, Cancelled> {
+    count_fields(&records, options);
+    let observations = observe(&records, options);
+    let schema = infer_schema_from_iter_inner(records, options, cancellation)?;
+    refine(schema, observations, options, cancellation)
+}
+
+fn infer_root(
+    json: serde_json::Value,
+    options: &InferenceOptions,
+    cancellation: Option<&Cancellation>,
+) -> Result<SchemaState, Cancelled> {
+    // the elements of an array at the root are records, which are refined when inferring them
+    let observations = (!json.is_array()).then(|| {
+        count_fields(std::slice::from_ref(&json), options);
+        observe(std::slice::from_ref(&json), options)
+    });
+    // any other value at the root is the one record, at the depth of the records
+    let depth = if json.is_array() { 0 } else { 1 };
+    let schema = infer_schema_inner(json, options, cancellation, depth)?;
+    match observations {
+        Some(observations) => refine(schema, observations, options, cancellation),
+        None => Ok(schema),
     }
 }
 
@@ -327,12 +1702,15 @@ fn apply_enum_recursive(s: SchemaState, opts: &EnumInference) -> SchemaState {
 /// # Example
 ///
 /// ```
+/// use std::collections::HashMap;
+///
 /// use serde_json::json;
-/// use std::collections::{HashMap, HashSet};
-/// use drivel::{infer_schema, SchemaState, StringType, NumberType, InferenceOptions};
+/// use indexmap::IndexMap;
+/// use drivel::{infer_schema, CharClass, DistinctCount, Segment, SchemaState, StringType, NumberType, InferenceOptions};
 ///
 /// let opts = InferenceOptions {
-///     enum_inference: None
+///     enum_inference: None,
+///     ..Default::default()
 /// };
 ///
 /// // Define a JSON value
@@ -346,60 +1724,57 @@ fn apply_enum_recursive(s: SchemaState, opts: &EnumInference) -> SchemaState {
 /// assert_eq!(
 ///     infer_schema(input, &opts),
 ///     SchemaState::Object {
-///         required: HashMap::from_iter([
+///         required: IndexMap::from_iter([
 ///             ("name".to_string(), SchemaState::String(StringType::Unknown {
 ///                 strings_seen: vec!["John".to_string()],
-///                 chars_seen: vec!['J', 'o', 'h', 'n'],
+///                 chars_seen: HashMap::from([('J', 1), ('o', 1), ('h', 1), ('n', 1)]),
+///                 n_strings_seen: 1,
+///                 distinct: DistinctCount::from_iter(["John"]),
+///                 n_chars_seen: 4,
 ///                 min_length: Some(4),
-///                 max_length: Some(4)
+///                 max_length: Some(4),
+///                 prefix: "John".to_owned(),
+///                 suffix: "John".to_owned(),
+///                 structure: Some(vec![
+///                     Segment {
+///                         class: CharClass::Uppercase,
+///                         literal: Some("J".to_owned()),
+///                         min_length: 1,
+///                         max_length: 1,
+///                     },
+///                     Segment {
+///                         class: CharClass::Lowercase,
+///                         literal: Some("ohn".to_owned()),
+///                         min_length: 3,
+///                         max_length: 3,
+///                     },
+///                 ]),
 ///             })),
 ///             ("age".to_string(), SchemaState::Number(NumberType::Integer { min: 30, max: 30 })),
 ///             ("is_student".to_string(), SchemaState::Boolean),
-///             ("grades".to_string(), SchemaState::Array {
-///                 min_length: 3,
-///                 max_length: 3,
-///                 schema: Box::new(SchemaState::Number(NumberType::Integer { min: 78, max: 92 }))
-///             }),
+///             // short arrays are inferred position by position, until a later sample differs
+///             ("grades".to_string(), SchemaState::Tuple(vec![
+///                 SchemaState::Number(NumberType::Integer { min: 85, max: 85 }),
+///                 SchemaState::Number(NumberType::Integer { min: 92, max: 92 }),
+///                 SchemaState::Number(NumberType::Integer { min: 78, max: 78 }),
+///             ])),
 ///         ]),
-///         optional: HashMap::new()
+///         optional: IndexMap::new()
 ///     }
 /// );
 /// ```
 pub fn infer_schema(json: serde_json::Value, options: &InferenceOptions) -> SchemaState {
-    let inferred = match json {
-        serde_json::Value::Null => SchemaState::Null,
-        serde_json::Value::String(value) => SchemaState::String(infer_string_type(&value)),
-        serde_json::Value::Number(n) => SchemaState::Number(if n.is_f64() {
-            NumberType::Float {
-                min: n.as_f64().unwrap(),
-                max: n.as_f64().unwrap(),
-            }
-        } else {
-            NumberType::Integer {
-                min: n.as_i64().unwrap(),
-                max: n.as_i64().unwrap(),
-            }
-        }),
-        serde_json::Value::Bool(_) => SchemaState::Boolean,
-        serde_json::Value::Array(array) => SchemaState::Array {
-            min_length: array.len(),
-            max_length: array.len(),
-            schema: Box::new(infer_schema_from_iter(array, options)),
-        },
-        serde_json::Value::Object(object) => SchemaState::Object {
-            required: object
-                .into_iter()
-                .map(|(k, v)| (k, infer_schema(v, options)))
-                .collect(),
-            optional: std::collections::HashMap::new(),
-        },
-    };
+    infer_root(json, options, None).expect("inference without cancellation cannot fail")
+}
 
-    if let Some(enum_opts) = &options.enum_inference {
-        apply_enum_recursive(inferred, enum_opts)
-    } else {
-        inferred
-    }
+/// Infer a schema like `infer_schema` does, giving up as soon as `cancellation` is cancelled
+/// or its deadline passes.
+pub fn infer_schema_with_cancellation(
+    json: serde_json::Value,
+    options: &InferenceOptions,
+    cancellation: &Cancellation,
+) -> Result<SchemaState, DrivelError> {
+    Ok(infer_root(json, options, Some(cancellation))?)
 }
 
 /// Infer a schema, encoded as a SchemaState struct, from an iterator of JSON values.
@@ -411,9 +1786,13 @@ pub fn infer_schema(json: serde_json::Value, options: &InferenceOptions) -> Sche
 /// # Example
 ///
 /// ```
+/// use std::collections::HashMap;
+///
 /// use serde_json::json;
-/// use std::collections::{HashMap, HashSet};
-/// use drivel::{infer_schema_from_iter, SchemaState, StringType, NumberType, InferenceOptions};
+/// use indexmap::IndexMap;
+/// use drivel::{
+///     infer_schema_from_iter, CharClass, DistinctCount, Segment, SchemaState, StringType, NumberType, InferenceOptions,
+/// };
 ///
 /// // Define a collection of JSON values
 /// let values = vec![
@@ -430,7 +1809,8 @@ pub fn infer_schema(json: serde_json::Value, options: &InferenceOptions) -> Sche
 /// ];
 ///
 /// let opts = InferenceOptions {
-///     enum_inference: None
+///     enum_inference: None,
+///     ..Default::default()
 /// };
 ///
 /// // Infer the schema from the iterator of JSON values
@@ -439,17 +1819,38 @@ pub fn infer_schema(json: serde_json::Value, options: &InferenceOptions) -> Sche
 /// assert_eq!(
 ///     schema,
 ///     SchemaState::Object {
-///         required: HashMap::from_iter([
+///         required: IndexMap::from_iter([
 ///             ("name".to_string(), SchemaState::String(StringType::Unknown {
 ///                 strings_seen: vec!["Alice".to_string(), "Bob".to_string()],
-///                 chars_seen: vec!['A', 'l', 'i', 'c', 'e', 'B', 'o', 'b'],
+///                 chars_seen: HashMap::from([
+///                     ('A', 1), ('l', 1), ('i', 1), ('c', 1), ('e', 1), ('B', 1), ('o', 1), ('b', 1),
+///                 ]),
+///                 n_strings_seen: 2,
+///                 distinct: DistinctCount::from_iter(["Alice", "Bob"]),
+///                 n_chars_seen: 8,
 ///                 min_length: Some(3),
-///                 max_length: Some(5)
+///                 max_length: Some(5),
+///                 prefix: String::new(),
+///                 suffix: String::new(),
+///                 structure: Some(vec![
+///                     Segment {
+///                         class: CharClass::Uppercase,
+///                         literal: None,
+///                         min_length: 1,
+///                         max_length: 1,
+///                     },
+///                     Segment {
+///                         class: CharClass::Lowercase,
+///                         literal: None,
+///                         min_length: 2,
+///                         max_length: 4,
+///                     },
+///                 ]),
 ///             })),
 ///             ("age".to_string(), SchemaState::Number(NumberType::Integer { min: 25, max: 30 })),
 ///             ("is_student".to_string(), SchemaState::Boolean),
 ///         ]),
-///         optional: HashMap::new()
+///         optional: IndexMap::new()
 ///     }
 /// );
 /// ```
@@ -457,10 +1858,92 @@ pub fn infer_schema_from_iter(
     values: Vec<serde_json::Value>,
     options: &InferenceOptions,
 ) -> SchemaState {
-    values
-        .into_par_iter()
-        .map(|value| infer_schema(value, options))
-        .reduce(|| SchemaState::Initial, merge)
+    infer_records(values, options, None).expect("inference without cancellation cannot fail")
+}
+
+/// Infer a schema like `infer_schema_from_iter` does, giving up as soon as `cancellation` is
+/// cancelled or its deadline passes.
+pub fn infer_schema_from_iter_with_cancellation(
+    values: Vec<serde_json::Value>,
+    options: &InferenceOptions,
+    cancellation: &Cancellation,
+) -> Result<SchemaState, DrivelError> {
+    Ok(infer_records(values, options, Some(cancellation))?)
+}
+
+/// Infers the schema of a single record, without refining it. See `settle`.
+pub(crate) fn infer_record(record: serde_json::Value, options: &InferenceOptions) -> SchemaState {
+    infer_schema_inner(record, options, None, 1)
+        .expect("inference without cancellation cannot fail")
+}
+
+/// Settles the schema of records merged with `infer_record`, as inference does once all of
+/// them were seen.
+pub(crate) fn settle(schema: SchemaState, options: &InferenceOptions) -> SchemaState {
+    timed(options.profile.as_deref(), Stage::Enums, || {
+        settle_types(schema, options)
+    })
+}
+
+/// The number of records inferred between checks of the deadline of `infer_records_until`.
+const BUDGET_CHUNK_SIZE: usize = 1024;
+
+/// Infers the schema of records like `infer_schema_from_iter` does, leaving out the records
+/// that are left once `deadline` has passed. The records are inferred in chunks, of which the
+/// first is always inferred. Returns the schema with the number of records inferred.
+pub(crate) fn infer_records_until(
+    records: Vec<serde_json::Value>,
+    options: &InferenceOptions,
+    deadline: std::time::Instant,
+) -> (SchemaState, usize) {
+    // the records inferred are only kept around if they are observed for refining the schema
+    let observed = options.widen_singletons || !options.type_pins.is_empty();
+    let mut inferred = vec![];
+    let mut schema = SchemaState::Initial;
+    let mut n_inferred = 0;
+    let mut records = records.into_iter();
+    loop {
+        let chunk = records.by_ref().take(BUDGET_CHUNK_SIZE).collect::<Vec<_>>();
+        if chunk.is_empty() {
+            break;
+        }
+        n_inferred += chunk.len();
+        count_fields(&chunk, options);
+        if observed {
+            inferred.extend(chunk.iter().cloned());
+        }
+        let chunk = infer_schema_from_iter_inner(chunk, options, None)
+            .expect("inference without cancellation cannot fail");
+        timed(options.profile.as_deref(), Stage::Merging, || {
+            merge_into(&mut schema, chunk, options.max_samples)
+        });
+        if std::time::Instant::now() >= deadline {
+            break;
+        }
+    }
+    let schema = refine(schema, observe(&inferred, options), options, None)
+        .expect("inference without cancellation cannot fail");
+    (schema, n_inferred)
+}
+
+/// Returns the schema of an array at the root of a document, of `length` records of `schema`.
+pub(crate) fn root_array(
+    length: usize,
+    schema: SchemaState,
+    options: &InferenceOptions,
+) -> SchemaState {
+    let array = SchemaState::Array {
+        min_length: length,
+        max_length: length,
+        unique: false,
+        schema: Box::new(schema),
+    };
+    match &options.enum_inference {
+        Some(enum_opts) => timed(options.profile.as_deref(), Stage::Enums, || {
+            apply_enum_recursive(array, enum_opts)
+        }),
+        None => array,
+    }
 }
 
 #[cfg(test)]
@@ -468,12 +1951,23 @@ mod tests {
     use serde_json::json;
 
     use super::*;
+    use crate::CharClass;
+
+    fn lowercase(literal: Option<&str>, min_length: usize, max_length: usize) -> Segment {
+        Segment {
+            class: CharClass::Lowercase,
+            literal: literal.map(str::to_owned),
+            min_length,
+            max_length,
+        }
+    }
 
     #[test]
     fn infers_null() {
         let input = json!(null);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -485,6 +1979,7 @@ mod tests {
         let input = json!("foo");
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -492,9 +1987,15 @@ mod tests {
             schema,
             SchemaState::String(StringType::Unknown {
                 strings_seen: vec!["foo".to_owned()],
-                chars_seen: vec!['f', 'o', 'o'],
+                chars_seen: HashMap::from([('f', 1), ('o', 2)]),
+                n_strings_seen: 1,
+                distinct: DistinctCount::from_iter(["foo"]),
+                n_chars_seen: 3,
                 min_length: Some(3),
-                max_length: Some(3)
+                max_length: Some(3),
+                prefix: "foo".to_owned(),
+                suffix: "foo".to_owned(),
+                structure: Some(vec![lowercase(Some("foo"), 3, 3)])
             })
         )
     }
@@ -504,10 +2005,17 @@ mod tests {
         let input = json!("2013-01-12");
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
-        assert_eq!(schema, SchemaState::String(StringType::IsoDate))
+        let date = chrono::NaiveDate::from_ymd_opt(2013, 1, 12).unwrap();
+        assert_eq!(
+            schema,
+            SchemaState::String(StringType::IsoDate {
+                range: Some(date..=date)
+            })
+        )
     }
 
     #[test]
@@ -515,10 +2023,19 @@ mod tests {
         let input = json!("Thu, 18 Mar 2021 10:37:31 +0000");
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
-        assert_eq!(schema, SchemaState::String(StringType::DateTimeISO8601))
+        let moment = chrono::DateTime::parse_from_rfc3339("2021-03-18T10:37:31Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(
+            schema,
+            SchemaState::String(StringType::DateTimeISO8601 {
+                range: Some(moment..=moment)
+            })
+        )
     }
 
     #[test]
@@ -526,10 +2043,19 @@ mod tests {
         let input = json!("2013-01-12T00:00:00.000+00:00");
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
-        assert_eq!(schema, SchemaState::String(StringType::DateTimeISO8601))
+        let moment = chrono::DateTime::parse_from_rfc3339("2013-01-12T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(
+            schema,
+            SchemaState::String(StringType::DateTimeISO8601 {
+                range: Some(moment..=moment)
+            })
+        )
     }
 
     #[test]
@@ -537,10 +2063,19 @@ mod tests {
         let input = json!("2013-01-12T00:00:00.000Z");
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
-        assert_eq!(schema, SchemaState::String(StringType::DateTimeISO8601))
+        let moment = chrono::DateTime::parse_from_rfc3339("2013-01-12T00:00:00Z")
+            .unwrap()
+            .to_utc();
+        assert_eq!(
+            schema,
+            SchemaState::String(StringType::DateTimeISO8601 {
+                range: Some(moment..=moment)
+            })
+        )
     }
 
     #[test]
@@ -548,17 +2083,245 @@ mod tests {
         let input = json!("988c2c6d-df1b-4bb9-b837-6ba706c0b4ad");
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
         assert_eq!(schema, SchemaState::String(StringType::UUID))
     }
 
+    #[test]
+    fn infers_and_produces_identifiers() {
+        let options = InferenceOptions::default();
+        for (input, expected) in [
+            ("01ARZ3NDEKTSV4RRFFQ69G5FAV", StringType::Ulid),
+            ("0ujtsYcgvSTl8PAuAdqWYSMnLOv", StringType::Ksuid),
+            ("507f1f77bcf86cd799439011", StringType::ObjectId),
+        ] {
+            let schema = infer_schema(json!(input), &options);
+            assert_eq!(schema, SchemaState::String(expected));
+
+            let produced =
+                crate::produce_with_options(&schema, &crate::ProduceOptions::new()).unwrap();
+            assert_eq!(infer_schema(produced, &options), schema);
+        }
+
+        // long runs of digits are numbers rather than identifiers
+        let schema = infer_schema(json!("12345678901234567890123456"), &options);
+        assert!(matches!(
+            schema,
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+    }
+
+    #[test]
+    fn infers_semantic_versions_and_their_major_versions() {
+        let options = InferenceOptions::default();
+        let input = json!(["1.2.3", "2.0.0-rc.1+build5", "3.10.0"]);
+
+        let SchemaState::Array { schema, .. } = infer_schema(input, &options) else {
+            panic!("expected an array");
+        };
+        assert_eq!(schema.to_string_pretty(), "string (semver, major 1-3)");
+        for _ in 0..20 {
+            let produced =
+                crate::produce_with_options(&schema, &crate::ProduceOptions::new()).unwrap();
+            assert_eq!(crate::validate(&produced, &schema), vec![]);
+        }
+
+        let exported = crate::to_json_schema(&schema);
+        let parsed = crate::parse_json_schema(&exported, &Default::default()).unwrap();
+        assert_eq!(&parsed.schema, schema.as_ref());
+
+        // leading zeros and missing patch numbers are not allowed
+        for input in ["01.2.3", "1.2"] {
+            assert!(!matches!(
+                infer_schema(json!(input), &options),
+                SchemaState::String(StringType::Semver { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn infers_and_produces_base64() {
+        use base64::Engine;
+        let options = InferenceOptions::default();
+        let input = json!(["aGVsbG8sIHdvcmxkIQ==", "U29tZSBiaW5hcnkgcGF5bG9hZA=="]);
+
+        let schema = infer_schema(input, &options);
+        let SchemaState::Array { schema: blob, .. } = &schema else {
+            panic!("expected an array");
+        };
+        assert_eq!(blob.to_string_pretty(), "string (base64, 20-28)");
+
+        for _ in 0..20 {
+            let produced =
+                crate::produce_with_options(blob, &crate::ProduceOptions::new()).unwrap();
+            let blob = produced.as_str().unwrap();
+            assert!((20..=28).contains(&blob.len()));
+            assert!(base64::engine::general_purpose::STANDARD
+                .decode(blob)
+                .is_ok());
+        }
+
+        // words and hex digests are valid base64 too, but are not recognised as such
+        for input in ["abcdefghijklmnop", "d41d8cd98f00b204e9800998ecf8427e"] {
+            let schema = infer_schema(json!(input), &options);
+            assert!(matches!(
+                schema,
+                SchemaState::String(StringType::Unknown { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn infers_the_schema_of_embedded_json_when_asked_to() {
+        let input = json!(["{\"a\": 1}", "{\"a\": 2, \"b\": [true]}"]);
+        let options = InferenceOptions {
+            embedded_json: true,
+            ..Default::default()
+        };
+
+        let SchemaState::Array { schema, .. } = infer_schema(input.clone(), &options) else {
+            panic!("expected an array");
+        };
+        let SchemaState::String(StringType::EmbeddedJson(document)) = schema.as_ref() else {
+            panic!("expected embedded JSON, found {:?}", schema);
+        };
+        let SchemaState::Object { required, optional } = document.as_ref() else {
+            panic!("expected an object, found {:?}", document);
+        };
+        assert_eq!(
+            required["a"],
+            SchemaState::Number(NumberType::Integer { min: 1, max: 2 })
+        );
+        assert_eq!(optional["b"].to_string_pretty(), "[\n  boolean\n] (1)");
+
+        for _ in 0..10 {
+            let produced =
+                crate::produce_with_options(&schema, &crate::ProduceOptions::new()).unwrap();
+            assert!(serde_json::from_str::<serde_json::Value>(produced.as_str().unwrap()).is_ok());
+            assert_eq!(crate::validate(&produced, &schema), vec![]);
+        }
+
+        // strings of JSON are only parsed when asked to
+        let SchemaState::Array { schema, .. } = infer_schema(input, &Default::default()) else {
+            panic!("expected an array");
+        };
+        assert!(matches!(
+            *schema,
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+    }
+
+    #[test]
+    fn infers_the_claims_of_jwts() {
+        // {"alg":"HS256"}, with claims {"sub":"a","admin":true} and {"sub":"bc"}
+        let tokens = json!([
+            "eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiJhIiwiYWRtaW4iOnRydWV9.c2ln",
+            "eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiJiYyJ9.c2ln"
+        ]);
+        let options = InferenceOptions::default();
+
+        let SchemaState::Array { schema, .. } = infer_schema(tokens, &options) else {
+            panic!("expected an array");
+        };
+        let SchemaState::String(StringType::Jwt { claims }) = *schema else {
+            panic!("expected a JWT, found {:?}", schema);
+        };
+        let SchemaState::Object { required, optional } = *claims else {
+            panic!("expected object claims");
+        };
+        assert!(required.contains_key("sub"));
+        assert_eq!(optional.get("admin"), Some(&SchemaState::Boolean));
+
+        // tokens with more or fewer parts, or without a header, are not JWTs
+        let schema = infer_schema(json!("eyJzdWIiOiJhIn0.eyJzdWIiOiJhIn0.c2ln"), &options);
+        assert!(matches!(
+            schema,
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+    }
+
+    #[test]
+    fn infers_phone_numbers_in_their_dominant_format() {
+        let input = json!([
+            "(415) 555-2671",
+            "+44 20 7946 0958",
+            "(212) 555-0199",
+            "+14155552671"
+        ]);
+        let options = InferenceOptions::default();
+
+        let SchemaState::Array { schema, .. } = infer_schema(input, &options) else {
+            panic!("expected an array");
+        };
+        let SchemaState::String(string_type) = schema.as_ref() else {
+            panic!("expected a string, found {:?}", schema);
+        };
+        assert_eq!(string_type.phone_format(), Some("(###) ###-####"));
+        assert_eq!(
+            infer_schema(json!("+44 20 7946 0958"), &options).to_string_pretty(),
+            "string (phone: +44 ## #### ####)"
+        );
+
+        // dates and plain runs of digits are not phone numbers
+        for input in ["2024-01-31", "4155552671"] {
+            let schema = infer_schema(json!(input), &options);
+            assert!(!matches!(
+                schema,
+                SchemaState::String(StringType::PhoneNumber { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn infers_monetary_amounts_and_currency_codes() {
+        let options = InferenceOptions::default();
+        let input = json!([
+            { "amount": 12.50, "currency": "EUR" },
+            { "amount": 3.99, "currency": "USD" },
+            { "amount": 10, "currency": "USD" }
+        ]);
+
+        let schema = infer_schema(input, &options);
+        let SchemaState::Array { schema: record, .. } = &schema else {
+            panic!("expected an array");
+        };
+        let SchemaState::Object { required, .. } = record.as_ref() else {
+            panic!("expected an object, found {:?}", record);
+        };
+        assert_eq!(
+            required["amount"].to_string_pretty(),
+            "decimal (3.99-12.50)"
+        );
+        assert_eq!(
+            required["currency"].to_string_pretty(),
+            "string (currency: EUR, USD)"
+        );
+
+        let produced =
+            crate::produce_with_options(&schema, &crate::ProduceOptions::new().count(20)).unwrap();
+        assert_eq!(crate::validate(&produced, &schema), vec![]);
+        for record in produced.as_array().unwrap() {
+            let amount = record["amount"].as_f64().unwrap();
+            assert_eq!((amount * 100.0).round() / 100.0, amount);
+        }
+
+        // amounts are written with two decimals, so other decimals are floats
+        assert!(matches!(
+            infer_schema(json!([1.5, 2.25, 3.125]), &options),
+            SchemaState::Array { schema, .. }
+                if matches!(*schema, SchemaState::Number(NumberType::Float { .. }))
+        ));
+    }
+
     #[test]
     fn infers_string_email() {
         let input = json!("test@example.com");
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -570,6 +2333,7 @@ mod tests {
         let input = json!("https://somedomain.somehost.nl/somepage");
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -581,6 +2345,7 @@ mod tests {
         let input = json!("somehost.com");
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -592,6 +2357,7 @@ mod tests {
         let input = json!(42);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -606,6 +2372,7 @@ mod tests {
         let input = json!(42.0);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -613,7 +2380,10 @@ mod tests {
             schema,
             SchemaState::Number(NumberType::Float {
                 min: 42.0,
-                max: 42.0
+                max: 42.0,
+                non_finite: false,
+                exclusive_min: false,
+                exclusive_max: false,
             })
         )
     }
@@ -623,6 +2393,7 @@ mod tests {
         let input = json!(true);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -634,6 +2405,7 @@ mod tests {
         let input = json!(false);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -655,20 +2427,27 @@ mod tests {
         });
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
         assert_eq!(
             schema,
             SchemaState::Object {
-                required: std::collections::HashMap::from_iter([
+                required: IndexMap::from_iter([
                     (
                         "string".to_string(),
                         SchemaState::String(StringType::Unknown {
                             strings_seen: vec!["foo".to_owned()],
-                            chars_seen: vec!['f', 'o', 'o'],
+                            chars_seen: HashMap::from([('f', 1), ('o', 2)]),
+                            n_strings_seen: 1,
+                            distinct: DistinctCount::from_iter(["foo"]),
+                            n_chars_seen: 3,
                             min_length: Some(3),
-                            max_length: Some(3)
+                            max_length: Some(3),
+                            prefix: "foo".to_owned(),
+                            suffix: "foo".to_owned(),
+                            structure: Some(vec![lowercase(Some("foo"), 3, 3)])
                         })
                     ),
                     (
@@ -679,7 +2458,10 @@ mod tests {
                         "float".to_string(),
                         SchemaState::Number(NumberType::Float {
                             min: 10.4,
-                            max: 10.4
+                            max: 10.4,
+                            non_finite: false,
+                            exclusive_min: false,
+                            exclusive_max: false,
                         })
                     ),
                     ("bool".to_string(), SchemaState::Boolean),
@@ -688,11 +2470,18 @@ mod tests {
                         SchemaState::Array {
                             min_length: 1,
                             max_length: 1,
+                            unique: false,
                             schema: Box::new(SchemaState::String(StringType::Unknown {
                                 strings_seen: vec!["baz".to_owned()],
-                                chars_seen: vec!['b', 'a', 'z'],
+                                chars_seen: HashMap::from([('b', 1), ('a', 1), ('z', 1)]),
+                                n_strings_seen: 1,
+                                distinct: DistinctCount::from_iter(["baz"]),
+                                n_chars_seen: 3,
                                 min_length: Some(3),
-                                max_length: Some(3)
+                                max_length: Some(3),
+                                prefix: "baz".to_owned(),
+                                suffix: "baz".to_owned(),
+                                structure: Some(vec![lowercase(Some("baz"), 3, 3)])
                             }))
                         }
                     ),
@@ -700,20 +2489,26 @@ mod tests {
                     (
                         "object".to_string(),
                         SchemaState::Object {
-                            required: std::collections::HashMap::from_iter([(
+                            required: IndexMap::from_iter([(
                                 "string".to_owned(),
                                 SchemaState::String(StringType::Unknown {
                                     strings_seen: vec!["foo".to_owned()],
-                                    chars_seen: vec!['f', 'o', 'o'],
+                                    chars_seen: HashMap::from([('f', 1), ('o', 2)]),
+                                    n_strings_seen: 1,
+                                    distinct: DistinctCount::from_iter(["foo"]),
+                                    n_chars_seen: 3,
                                     min_length: Some(3),
-                                    max_length: Some(3)
+                                    max_length: Some(3),
+                                    prefix: "foo".to_owned(),
+                                    suffix: "foo".to_owned(),
+                                    structure: Some(vec![lowercase(Some("foo"), 3, 3)])
                                 })
                             )]),
-                            optional: std::collections::HashMap::new(),
+                            optional: IndexMap::new(),
                         }
                     ),
                 ]),
-                optional: std::collections::HashMap::new()
+                optional: IndexMap::new()
             }
         )
     }
@@ -723,6 +2518,7 @@ mod tests {
         let input = json!([null, null]);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -731,6 +2527,7 @@ mod tests {
             SchemaState::Array {
                 min_length: 2,
                 max_length: 2,
+                unique: false,
                 schema: Box::new(SchemaState::Null)
             }
         );
@@ -741,6 +2538,7 @@ mod tests {
         let input = json!(["foo", "barbar"]);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -749,11 +2547,18 @@ mod tests {
             SchemaState::Array {
                 min_length: 2,
                 max_length: 2,
+                unique: false,
                 schema: Box::new(SchemaState::String(StringType::Unknown {
                     strings_seen: vec!["foo".to_owned(), "barbar".to_owned()],
-                    chars_seen: vec!['f', 'o', 'o', 'b', 'a', 'r', 'b', 'a', 'r'],
+                    chars_seen: HashMap::from([('f', 1), ('o', 2), ('b', 2), ('a', 2), ('r', 2)]),
+                    n_strings_seen: 2,
+                    distinct: DistinctCount::from_iter(["foo", "barbar"]),
+                    n_chars_seen: 9,
                     min_length: Some(3),
-                    max_length: Some(6)
+                    max_length: Some(6),
+                    prefix: String::new(),
+                    suffix: String::new(),
+                    structure: Some(vec![lowercase(None, 3, 6)])
                 }))
             }
         );
@@ -769,22 +2574,188 @@ mod tests {
         };
         let options = InferenceOptions {
             enum_inference: Some(enum_opts),
+            ..Default::default()
+        };
+
+        let schema = infer_schema(input, &options);
+
+        assert_eq!(
+            schema,
+            SchemaState::Array {
+                min_length: 4,
+                max_length: 4,
+                unique: false,
+                schema: Box::new(SchemaState::String(StringType::Enum {
+                    variants: vec!["foo".to_owned(), "barbar".to_owned()]
+                        .into_iter()
+                        .collect()
+                }))
+            }
+        );
+    }
+
+    #[test]
+    fn bounds_string_samples() {
+        let input = json!((0..1000).map(|i| format!("s{}", i % 3)).collect::<Vec<_>>());
+        let options = InferenceOptions {
+            enum_inference: None,
+            max_samples: 50,
+            ..Default::default()
+        };
+
+        let schema = infer_schema(input, &options);
+
+        let SchemaState::Array { schema, .. } = schema else {
+            panic!("expected array schema");
+        };
+        let SchemaState::String(StringType::Unknown {
+            strings_seen,
+            chars_seen,
+            n_strings_seen,
+            n_chars_seen,
+            ..
+        }) = *schema
+        else {
+            panic!("expected unknown string schema");
+        };
+        assert_eq!(strings_seen.len(), 50);
+        assert_eq!(
+            chars_seen,
+            HashMap::from([('s', 1000), ('0', 334), ('1', 333), ('2', 333)])
+        );
+        assert_eq!(n_strings_seen, 1000);
+        assert_eq!(n_chars_seen, 2000);
+    }
+
+    #[test]
+    fn infers_enum_from_bounded_sample() {
+        let input = json!((0..1000).map(|i| format!("s{}", i % 3)).collect::<Vec<_>>());
+        let options = InferenceOptions {
+            enum_inference: Some(EnumInference {
+                max_unique_ratio: 0.1,
+                min_sample_size: 500,
+            }),
+            max_samples: 100,
+            ..Default::default()
         };
 
         let schema = infer_schema(input, &options);
 
         assert_eq!(
-            schema,
-            SchemaState::Array {
-                min_length: 4,
-                max_length: 4,
-                schema: Box::new(SchemaState::String(StringType::Enum {
-                    variants: vec!["foo".to_owned(), "barbar".to_owned()]
-                        .into_iter()
-                        .collect()
-                }))
-            }
+            schema,
+            SchemaState::Array {
+                min_length: 1000,
+                max_length: 1000,
+                unique: false,
+                schema: Box::new(SchemaState::String(StringType::Enum {
+                    variants: ["s0", "s1", "s2"].iter().map(|s| s.to_string()).collect()
+                }))
+            }
+        );
+    }
+
+    #[test]
+    fn decides_on_enums_by_the_distinct_strings_of_all_records() {
+        // a sample of 50 holds most of the 40 statuses, but all records hold few of them
+        let input = json!((0..2000)
+            .map(|i| format!("status-{}", i % 40))
+            .collect::<Vec<_>>());
+        let options = InferenceOptions {
+            enum_inference: Some(EnumInference {
+                max_unique_ratio: 0.05,
+                min_sample_size: 100,
+            }),
+            max_samples: 50,
+            ..Default::default()
+        };
+
+        let SchemaState::Array { schema, .. } = infer_schema(input, &options) else {
+            panic!("expected an array");
+        };
+        assert!(matches!(
+            *schema,
+            SchemaState::String(StringType::Enum { .. })
+        ));
+    }
+
+    #[test]
+    fn infers_integer_enums_from_records() {
+        let records = (0..30)
+            .map(|i| json!({ "status": 200 + 150 * (i % 3), "id": i }))
+            .collect::<Vec<_>>();
+        let options = InferenceOptions {
+            enum_inference: Some(EnumInference {
+                max_unique_ratio: 0.2,
+                min_sample_size: 10,
+            }),
+            ..Default::default()
+        };
+
+        let SchemaState::Object { required, .. } = infer_schema_from_iter(records, &options) else {
+            panic!("expected an object");
+        };
+        assert_eq!(
+            required["status"],
+            SchemaState::Number(NumberType::Enum {
+                variants: HashSet::from([200, 350, 500]),
+                n_seen: 30
+            })
+        );
+        assert_eq!(
+            required["id"],
+            SchemaState::Number(NumberType::Integer { min: 0, max: 29 })
+        );
+
+        for _ in 0..10 {
+            let produced =
+                crate::produce_with_options(&required["status"], &crate::ProduceOptions::new())
+                    .unwrap();
+            assert!([200, 350, 500].contains(&produced.as_i64().unwrap()));
+        }
+    }
+
+    #[test]
+    fn infers_integers_beyond_the_range_of_i64() {
+        let records = vec![
+            json!({ "id": 18_446_744_073_709_551_615u64, "n": 1 }),
+            json!({ "id": 12, "n": 9_223_372_036_854_775_808u64 }),
+            json!({ "id": 40, "n": 0.5 }),
+        ];
+        // enum inference must not give up on integers it cannot hold as an i64 either
+        let options = InferenceOptions {
+            enum_inference: Some(EnumInference {
+                max_unique_ratio: 1.0,
+                min_sample_size: 1,
+            }),
+            ..Default::default()
+        };
+
+        let SchemaState::Object { required, .. } = infer_schema_from_iter(records, &options) else {
+            panic!("expected an object");
+        };
+        assert_eq!(
+            required["id"],
+            SchemaState::Number(NumberType::BigInteger {
+                min: 12,
+                max: u64::MAX.into()
+            })
+        );
+        assert_eq!(
+            required["id"].to_string_pretty(),
+            "int (12-18446744073709551615)"
         );
+        assert!(matches!(
+            required["n"],
+            SchemaState::Number(NumberType::Float { .. })
+        ));
+
+        for _ in 0..10 {
+            let produced =
+                crate::produce_with_options(&required["id"], &crate::ProduceOptions::new())
+                    .unwrap();
+            assert!(produced.as_u64().is_some_and(|id| id >= 12));
+            assert!(crate::validate(&produced, &required["id"]).is_empty());
+        }
     }
 
     #[test]
@@ -797,6 +2768,7 @@ mod tests {
         };
         let options = InferenceOptions {
             enum_inference: Some(enum_opts),
+            ..Default::default()
         };
 
         let schema = infer_schema(input, &options);
@@ -806,6 +2778,7 @@ mod tests {
             SchemaState::Array {
                 min_length: 4,
                 max_length: 4,
+                unique: false,
                 schema: Box::new(SchemaState::String(StringType::Unknown {
                     strings_seen: vec![
                         "foo".to_owned(),
@@ -813,12 +2786,15 @@ mod tests {
                         "foo".to_owned(),
                         "barbar".to_owned()
                     ],
-                    chars_seen: vec![
-                        'f', 'o', 'o', 'b', 'a', 'r', 'b', 'a', 'r', 'f', 'o', 'o', 'b', 'a', 'r',
-                        'b', 'a', 'r'
-                    ],
+                    chars_seen: HashMap::from([('f', 2), ('o', 4), ('b', 4), ('a', 4), ('r', 4)]),
+                    n_strings_seen: 4,
+                    distinct: DistinctCount::from_iter(["foo", "barbar"]),
+                    n_chars_seen: 18,
                     min_length: Some(3),
-                    max_length: Some(6)
+                    max_length: Some(6),
+                    prefix: String::new(),
+                    suffix: String::new(),
+                    structure: Some(vec![lowercase(None, 3, 6)])
                 }))
             }
         );
@@ -834,6 +2810,7 @@ mod tests {
         };
         let options = InferenceOptions {
             enum_inference: Some(enum_opts),
+            ..Default::default()
         };
 
         let schema = infer_schema(input, &options);
@@ -843,6 +2820,7 @@ mod tests {
             SchemaState::Array {
                 min_length: 4,
                 max_length: 4,
+                unique: false,
                 schema: Box::new(SchemaState::String(StringType::Unknown {
                     strings_seen: vec![
                         "foo".to_owned(),
@@ -850,12 +2828,15 @@ mod tests {
                         "foo".to_owned(),
                         "barbar".to_owned()
                     ],
-                    chars_seen: vec![
-                        'f', 'o', 'o', 'b', 'a', 'r', 'b', 'a', 'r', 'f', 'o', 'o', 'b', 'a', 'r',
-                        'b', 'a', 'r'
-                    ],
+                    chars_seen: HashMap::from([('f', 2), ('o', 4), ('b', 4), ('a', 4), ('r', 4)]),
+                    n_strings_seen: 4,
+                    distinct: DistinctCount::from_iter(["foo", "barbar"]),
+                    n_chars_seen: 18,
                     min_length: Some(3),
-                    max_length: Some(6)
+                    max_length: Some(6),
+                    prefix: String::new(),
+                    suffix: String::new(),
+                    structure: Some(vec![lowercase(None, 3, 6)])
                 }))
             }
         );
@@ -866,6 +2847,7 @@ mod tests {
         let input = json!(["48f41410-2d97-4d54-8bfa-aa4e22acca01", "barbar"]);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -874,11 +2856,18 @@ mod tests {
             SchemaState::Array {
                 min_length: 2,
                 max_length: 2,
+                unique: false,
                 schema: Box::new(SchemaState::String(StringType::Unknown {
                     strings_seen: vec!["barbar".to_owned()],
-                    chars_seen: vec!['b', 'a', 'r', 'b', 'a', 'r'],
+                    chars_seen: HashMap::from([('b', 2), ('a', 2), ('r', 2)]),
+                    n_strings_seen: 1,
+                    distinct: DistinctCount::from_iter(["barbar"]),
+                    n_chars_seen: 6,
                     min_length: Some(6),
                     max_length: Some(6),
+                    prefix: String::new(),
+                    suffix: String::new(),
+                    structure: None
                 }))
             }
         );
@@ -889,6 +2878,7 @@ mod tests {
         let input = json!([100, 104]);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -897,6 +2887,7 @@ mod tests {
             SchemaState::Array {
                 min_length: 2,
                 max_length: 2,
+                unique: false,
                 schema: Box::new(SchemaState::Number(NumberType::Integer {
                     min: 100,
                     max: 104
@@ -910,6 +2901,7 @@ mod tests {
         let input = json!([100, 104.5]);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -918,9 +2910,13 @@ mod tests {
             SchemaState::Array {
                 min_length: 2,
                 max_length: 2,
+                unique: false,
                 schema: Box::new(SchemaState::Number(NumberType::Float {
                     min: 100.0,
-                    max: 104.5
+                    max: 104.5,
+                    non_finite: false,
+                    exclusive_min: false,
+                    exclusive_max: false,
                 }))
             }
         );
@@ -931,6 +2927,7 @@ mod tests {
         let input = json!([true, false]);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -939,6 +2936,7 @@ mod tests {
             SchemaState::Array {
                 min_length: 2,
                 max_length: 2,
+                unique: false,
                 schema: Box::new(SchemaState::Boolean)
             }
         );
@@ -964,6 +2962,7 @@ mod tests {
         ]);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -972,8 +2971,9 @@ mod tests {
             SchemaState::Array {
                 min_length: 3,
                 max_length: 3,
+                unique: false,
                 schema: Box::new(SchemaState::Object {
-                    required: std::collections::HashMap::from_iter([
+                    required: IndexMap::from_iter([
                         (
                             "baz".to_owned(),
                             SchemaState::Nullable(Box::new(SchemaState::Number(
@@ -982,13 +2982,19 @@ mod tests {
                         ),
                         ("qux".to_owned(), SchemaState::Boolean),
                     ]),
-                    optional: std::collections::HashMap::from_iter([(
+                    optional: IndexMap::from_iter([(
                         "foo".to_owned(),
                         SchemaState::String(StringType::Unknown {
                             strings_seen: vec!["bar".to_owned(), "barbar".to_owned()],
-                            chars_seen: vec!['b', 'a', 'r', 'b', 'a', 'r', 'b', 'a', 'r'],
+                            chars_seen: HashMap::from([('b', 3), ('a', 3), ('r', 3)]),
+                            n_strings_seen: 2,
+                            distinct: DistinctCount::from_iter(["bar", "barbar"]),
+                            n_chars_seen: 9,
                             min_length: Some(3),
-                            max_length: Some(6)
+                            max_length: Some(6),
+                            prefix: "bar".to_owned(),
+                            suffix: "bar".to_owned(),
+                            structure: Some(vec![lowercase(None, 3, 6)])
                         })
                     )])
                 })
@@ -996,6 +3002,51 @@ mod tests {
         )
     }
 
+    #[test]
+    fn merges_wide_objects_keeping_the_order_of_their_fields() {
+        let record = |skip: usize| {
+            let mut record = serde_json::Map::new();
+            for n in (0..200).filter(|n| n % 7 != skip) {
+                record.insert(format!("field_{}", n), json!(n));
+            }
+            serde_json::Value::Object(record)
+        };
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema(json!([record(0), record(1)]), &options);
+
+        let SchemaState::Array { schema, .. } = schema else {
+            panic!("expected an array, got {:?}", schema);
+        };
+        let SchemaState::Object { required, optional } = *schema else {
+            panic!("expected an object, got {:?}", schema);
+        };
+        let expected_required = (0..200)
+            .filter(|n| n % 7 > 1)
+            .map(|n| format!("field_{}", n))
+            .collect::<Vec<_>>();
+        // the fields only the first record had come before those only the second had
+        let expected_optional = (0..200)
+            .filter(|n| n % 7 == 1)
+            .chain((0..200).filter(|n| n % 7 == 0))
+            .map(|n| format!("field_{}", n))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            required.keys().cloned().collect::<Vec<_>>(),
+            expected_required
+        );
+        assert_eq!(
+            optional.keys().cloned().collect::<Vec<_>>(),
+            expected_optional
+        );
+        assert_eq!(
+            required["field_100"],
+            SchemaState::Number(NumberType::Integer { min: 100, max: 100 })
+        );
+    }
+
     #[test]
     fn infers_array_object_enum() {
         let input = json!([
@@ -1018,6 +3069,7 @@ mod tests {
         };
         let options = InferenceOptions {
             enum_inference: Some(enun_opts),
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -1026,8 +3078,9 @@ mod tests {
             SchemaState::Array {
                 min_length: 4,
                 max_length: 4,
+                unique: false,
                 schema: Box::new(SchemaState::Object {
-                    required: std::collections::HashMap::from_iter([(
+                    required: IndexMap::from_iter([(
                         "foo".to_owned(),
                         SchemaState::String(StringType::Enum {
                             variants: vec!["bar".to_owned(), "baz".to_owned()]
@@ -1035,7 +3088,7 @@ mod tests {
                                 .collect()
                         })
                     )]),
-                    optional: std::collections::HashMap::new()
+                    optional: IndexMap::new()
                 })
             }
         )
@@ -1046,6 +3099,7 @@ mod tests {
         let input = json!([[true, false], [false]]);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema(input, &options);
 
@@ -1054,9 +3108,11 @@ mod tests {
             SchemaState::Array {
                 min_length: 2,
                 max_length: 2,
+                unique: false,
                 schema: Box::new(SchemaState::Array {
                     min_length: 1,
                     max_length: 2,
+                    unique: false,
                     schema: Box::new(SchemaState::Boolean)
                 })
             }
@@ -1068,6 +3124,7 @@ mod tests {
         let input_1 = json!(["foo", null]);
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema_1 = infer_schema(input_1, &options);
 
@@ -1079,12 +3136,19 @@ mod tests {
             SchemaState::Array {
                 min_length: 2,
                 max_length: 2,
+                unique: false,
                 schema: Box::new(SchemaState::Nullable(Box::new(SchemaState::String(
                     StringType::Unknown {
                         strings_seen: vec!["foo".to_owned()],
-                        chars_seen: vec!['f', 'o', 'o'],
+                        chars_seen: HashMap::from([('f', 1), ('o', 2)]),
+                        n_strings_seen: 1,
+                        distinct: DistinctCount::from_iter(["foo"]),
+                        n_chars_seen: 3,
                         min_length: Some(3),
-                        max_length: Some(3)
+                        max_length: Some(3),
+                        prefix: "foo".to_owned(),
+                        suffix: "foo".to_owned(),
+                        structure: Some(vec![lowercase(Some("foo"), 3, 3)])
                     }
                 ))))
             }
@@ -1093,6 +3157,467 @@ mod tests {
         assert_eq!(schema_1, schema_2)
     }
 
+    #[test]
+    fn infers_tuples_and_widens_them_on_length_mismatch() {
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(
+            vec![json!({"point": [true, 1]}), json!({"point": [false, 5]})],
+            &options,
+        );
+        assert_eq!(
+            schema,
+            SchemaState::Object {
+                required: IndexMap::from_iter([(
+                    "point".to_owned(),
+                    SchemaState::Tuple(vec![
+                        SchemaState::Boolean,
+                        SchemaState::Number(NumberType::Integer { min: 1, max: 5 })
+                    ])
+                )]),
+                optional: IndexMap::new()
+            }
+        );
+
+        let schema = infer_schema_from_iter(vec![json!([true, 1]), json!([1, 2, 3])], &options);
+        assert!(matches!(
+            schema,
+            SchemaState::Array {
+                min_length: 2,
+                max_length: 3,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn infers_tuples_only_while_positions_keep_their_types() {
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(
+            vec![json!({"at": [52.1, 4.3]}), json!({"at": [51.9, 5.1]})],
+            &options,
+        );
+        let SchemaState::Object { required, .. } = schema else {
+            panic!("expected object schema");
+        };
+        assert_eq!(
+            required["at"].to_string_pretty(),
+            "tuple [\n  float (51.9-52.1),\n  float (4.3-5.1)\n]"
+        );
+
+        let schema = infer_schema_from_iter(
+            vec![json!({"p": [1, "a"]}), json!({"p": ["b", 2]})],
+            &options,
+        );
+        let SchemaState::Object { required, .. } = schema else {
+            panic!("expected object schema");
+        };
+        assert!(matches!(
+            required["p"],
+            SchemaState::Array {
+                min_length: 2,
+                max_length: 2,
+                ..
+            }
+        ));
+
+        let schema = infer_schema(json!({"p": [1, 2, 3, 4, 5, 6, 7, 8, 9]}), &options);
+        let SchemaState::Object { required, .. } = schema else {
+            panic!("expected object schema");
+        };
+        assert!(matches!(required["p"], SchemaState::Array { .. }));
+    }
+
+    #[test]
+    fn infers_shared_prefix_and_suffix() {
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(
+            vec![
+                json!("#ab-x"),
+                json!("#abcd-x"),
+                json!("#z-x"),
+                json!("#ø-x"),
+            ],
+            &options,
+        );
+        let SchemaState::String(string_type) = &schema else {
+            panic!("expected string schema");
+        };
+        assert_eq!(string_type.affixes(), Some(("#", "-x")));
+
+        // a single string is not enough to tell a convention apart from a coincidence
+        let schema = infer_schema(json!("#ab-x"), &options);
+        let SchemaState::String(string_type) = &schema else {
+            panic!("expected string schema");
+        };
+        assert_eq!(string_type.affixes(), None);
+    }
+
+    #[test]
+    fn infers_shared_structure() {
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(
+            vec![
+                json!("ORD-2024-00123.a"),
+                json!("ORD-2023-9.B"),
+                json!("ORD-2024-04711.c"),
+            ],
+            &options,
+        );
+        let SchemaState::String(string_type) = &schema else {
+            panic!("expected string schema");
+        };
+        assert_eq!(
+            string_type.structure_pattern().as_deref(),
+            Some(r"ORD-[0-9]{4}-[0-9]{1,5}\.[A-Za-z]")
+        );
+
+        // strings made up of different runs do not share a structure
+        let schema = infer_schema_from_iter(vec![json!("AB-12"), json!("AB-1C")], &options);
+        let SchemaState::String(string_type) = &schema else {
+            panic!("expected string schema");
+        };
+        assert_eq!(string_type.structure_pattern(), None);
+    }
+
+    #[test]
+    fn infers_non_finite_floats() {
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(
+            vec![json!(1), json!("NaN"), json!(2.5), json!("-Infinity")],
+            &options,
+        );
+        assert_eq!(
+            schema,
+            SchemaState::Number(NumberType::Float {
+                min: 1.0,
+                max: 2.5,
+                non_finite: true,
+                exclusive_min: false,
+                exclusive_max: false,
+            })
+        );
+        assert_eq!(schema.to_string_pretty(), "float (1-2.5) or NaN/Infinity");
+
+        // among other strings, the sentinels are plain strings
+        let schema = infer_schema_from_iter(vec![json!("NaN"), json!("foo")], &options);
+        assert!(matches!(
+            schema,
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+    }
+
+    #[test]
+    fn tracks_the_range_of_dates_seen() {
+        let options = InferenceOptions::default();
+        let schema = infer_schema_from_iter(
+            vec![
+                json!({"day": "2021-06-01", "at": "2021-06-01T12:00:00+02:00"}),
+                json!({"day": "2020-01-15", "at": "2021-06-01T09:30:00Z"}),
+                json!({"day": "2021-02-28", "at": "2021-06-01T10:15:00Z"}),
+            ],
+            &options,
+        );
+        let SchemaState::Object { required, .. } = &schema else {
+            panic!("expected object schema");
+        };
+
+        assert_eq!(
+            required["day"].to_string_pretty(),
+            "string (date - ISO 8601, 2020-01-15 to 2021-06-01)"
+        );
+        assert_eq!(
+            required["at"].to_string_pretty(),
+            "string (datetime - ISO 8601, 2021-06-01T09:30:00+00:00 to 2021-06-01T10:15:00+00:00)"
+        );
+    }
+
+    #[test]
+    fn infers_dates_in_other_formats_and_produces_them_alike() {
+        let options = InferenceOptions::default();
+        let schema = infer_schema_from_iter(
+            vec![
+                json!({"us": "03/14/2024", "eu": "14-03-2024", "compact": "20240314", "at": "1710428400", "mixed": "03/14/2024"}),
+                json!({"us": "12/01/2023", "eu": "01-12-2023", "compact": "20231201", "at": "1700000000", "mixed": "14-03-2024"}),
+            ],
+            &options,
+        );
+        let SchemaState::Object { required, .. } = &schema else {
+            panic!("expected object schema");
+        };
+
+        assert_eq!(
+            required["us"].to_string_pretty(),
+            "string (date - MM/DD/YYYY, 12/01/2023 to 03/14/2024)"
+        );
+        assert_eq!(
+            required["eu"].to_string_pretty(),
+            "string (date - DD-MM-YYYY, 01-12-2023 to 14-03-2024)"
+        );
+        assert_eq!(
+            required["compact"].to_string_pretty(),
+            "string (date - YYYYMMDD, 20231201 to 20240314)"
+        );
+        assert_eq!(
+            required["at"].to_string_pretty(),
+            "string (epoch seconds, 2023-11-14T22:13:20+00:00 to 2024-03-14T15:00:00+00:00)"
+        );
+        // dates written in different formats are not of one format
+        assert!(matches!(
+            required["mixed"],
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+
+        for _ in 0..20 {
+            let record =
+                crate::produce_with_options(&schema, &crate::ProduceOptions::new()).unwrap();
+            assert!(crate::validate(&record, &schema).is_empty());
+            let us = record["us"].as_str().unwrap();
+            assert_eq!((us.len(), &us[2..3], &us[5..6]), (10, "/", "/"));
+        }
+    }
+
+    #[test]
+    fn infers_times_of_day_and_durations() {
+        let options = InferenceOptions::default();
+        let schema = infer_schema_from_iter(
+            vec![
+                json!({"opens": "09:30", "at": "17:45:00.250", "every": "PT1H30M", "for": "P1D"}),
+                json!({"opens": "08:00", "at": "06:05:09", "every": "PT15M", "for": "P1W"}),
+            ],
+            &options,
+        );
+        let SchemaState::Object { required, .. } = &schema else {
+            panic!("expected object schema");
+        };
+
+        assert_eq!(
+            required["opens"].to_string_pretty(),
+            "string (time, 08:00 to 09:30)"
+        );
+        assert_eq!(
+            required["at"].to_string_pretty(),
+            "string (time, 06:05:09 to 17:45:00)"
+        );
+        assert_eq!(
+            required["every"].to_string_pretty(),
+            "string (duration, PT15M to PT1H30M)"
+        );
+        assert_eq!(
+            required["for"].to_string_pretty(),
+            "string (duration, P1D to P7D)"
+        );
+
+        for _ in 0..20 {
+            let record =
+                crate::produce_with_options(&schema, &crate::ProduceOptions::new()).unwrap();
+            assert!(crate::validate(&record, &schema).is_empty());
+        }
+    }
+
+    #[test]
+    fn infers_file_paths_and_produces_them_alike() {
+        let options = InferenceOptions::default();
+        let schema = infer_schema_from_iter(
+            vec![
+                json!({"log": "/var/log/app.log", "doc": "C:\\Users\\ann\\report.pdf", "object": "s3://exports/2024/03/users.csv"}),
+                json!({"log": "/var/log/nginx/access.log", "doc": "C:\\notes.txt", "object": "s3://exports/2024/users.parquet"}),
+                json!({"log": "/tmp/build.log", "doc": "D:\\data\\scan.pdf", "object": "s3://exports/2024/04/orders.csv"}),
+            ],
+            &options,
+        );
+        let SchemaState::Object { required, .. } = &schema else {
+            panic!("expected object schema");
+        };
+
+        assert_eq!(
+            required["log"].to_string_pretty(),
+            "string (POSIX path under /, 1-3 directories, extensions: log)"
+        );
+        assert_eq!(
+            required["doc"].to_string_pretty(),
+            "string (Windows path under C:\\ D:\\, 0-2 directories, extensions: pdf, txt)"
+        );
+        assert_eq!(
+            required["object"].to_string_pretty(),
+            "string (S3 path under s3://exports/, 1-2 directories, extensions: csv, parquet)"
+        );
+
+        for _ in 0..20 {
+            let record =
+                crate::produce_with_options(&schema, &crate::ProduceOptions::new()).unwrap();
+            assert!(crate::validate(&record, &schema).is_empty());
+            assert!(record["object"]
+                .as_str()
+                .unwrap()
+                .starts_with("s3://exports/"));
+        }
+    }
+
+    #[test]
+    fn infers_natural_language_and_produces_sentences_alike() {
+        let options = InferenceOptions::default();
+        let schema = infer_schema_from_iter(
+            vec![
+                json!({"comment": "Arrived on time, well packaged.", "name": "Ann Smith"}),
+                json!({"comment": "The box was damaged but the contents were fine!", "name": "Bob Jones"}),
+            ],
+            &options,
+        );
+        let SchemaState::Object { required, .. } = &schema else {
+            panic!("expected object schema");
+        };
+
+        assert_eq!(
+            required["comment"].to_string_pretty(),
+            "string (text, 5-9 words)"
+        );
+        // names are too short to be taken for natural language
+        assert!(matches!(
+            required["name"],
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+
+        for _ in 0..20 {
+            let record =
+                crate::produce_with_options(&schema, &crate::ProduceOptions::new()).unwrap();
+            let comment = record["comment"].as_str().unwrap();
+            assert!(comment.starts_with(char::is_uppercase) && comment.ends_with('.'));
+            assert!((5..=9).contains(&comment.split_whitespace().count()));
+        }
+    }
+
+    #[test]
+    fn merges_unconstrained_empty_containers() {
+        let options = InferenceOptions {
+            enum_inference: None,
+            unconstrained_empty: true,
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(
+            vec![
+                json!({"tags": [], "meta": {}, "seen_empty": []}),
+                json!({"tags": [true], "meta": {"a": true}, "seen_empty": []}),
+            ],
+            &options,
+        );
+
+        assert_eq!(
+            schema,
+            SchemaState::Object {
+                required: IndexMap::from_iter([
+                    (
+                        "tags".to_owned(),
+                        SchemaState::Array {
+                            min_length: 1,
+                            max_length: 1,
+                            unique: false,
+                            schema: Box::new(SchemaState::Boolean)
+                        }
+                    ),
+                    (
+                        "meta".to_owned(),
+                        SchemaState::Object {
+                            required: IndexMap::from_iter([("a".to_owned(), SchemaState::Boolean)]),
+                            optional: IndexMap::new()
+                        }
+                    ),
+                    ("seen_empty".to_owned(), SchemaState::UnconstrainedArray),
+                ]),
+                optional: IndexMap::new()
+            }
+        );
+    }
+
+    #[test]
+    fn truncates_values_nested_deeper_than_the_max_depth() {
+        let records = vec![json!({ "a": { "b": { "c": 1 } }, "n": 1 })];
+        let options = InferenceOptions {
+            max_depth: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(truncated_paths(&records, 2), vec!["a.b"]);
+        let schema = infer_schema_from_iter(records, &options);
+        let SchemaState::Object { required, .. } = schema else {
+            panic!("expected an object, got {:?}", schema);
+        };
+        assert_eq!(
+            required["a"],
+            SchemaState::Object {
+                required: IndexMap::from([("b".to_owned(), SchemaState::UnconstrainedObject)]),
+                optional: IndexMap::new(),
+            }
+        );
+
+        // nesting far deeper than the stack allows to recurse is truncated all the same
+        let mut deep = json!(1);
+        for _ in 0..100_000 {
+            deep = serde_json::Value::Array(vec![deep]);
+        }
+        let record =
+            serde_json::Value::Object(serde_json::Map::from_iter([("deep".to_owned(), deep)]));
+        let schema = infer_schema(record, &InferenceOptions::default());
+        assert!(matches!(schema, SchemaState::Object { .. }));
+    }
+
+    #[test]
+    fn keeps_fields_in_the_order_they_were_first_seen() {
+        let records = vec![
+            json!({ "z": 1, "a": { "y": 1, "b": 1 }, "m": 1 }),
+            json!({ "z": 2, "c": 2, "a": { "y": 2, "b": 2 } }),
+        ];
+
+        let mut schema = infer_schema_from_iter(records, &InferenceOptions::default());
+        let SchemaState::Object { required, optional } = &schema else {
+            panic!("expected an object, got {:?}", schema);
+        };
+        assert_eq!(required.keys().collect::<Vec<_>>(), vec!["z", "a"]);
+        assert_eq!(optional.keys().collect::<Vec<_>>(), vec!["m", "c"]);
+        let produced = crate::produce_with_options(&schema, &crate::ProduceOptions::new()).unwrap();
+        let keys = produced["a"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec!["y", "b"]);
+
+        schema.sort_fields();
+        let SchemaState::Object { required, optional } = &schema else {
+            panic!("expected an object, got {:?}", schema);
+        };
+        assert_eq!(required.keys().collect::<Vec<_>>(), vec!["a", "z"]);
+        assert_eq!(optional.keys().collect::<Vec<_>>(), vec!["c", "m"]);
+    }
+
+    #[test]
+    fn stops_inference_when_cancelled() {
+        let input = vec![json!({"foo": "bar"}), json!({"foo": "baz"})];
+        let cancellation = Cancellation::with_timeout(std::time::Duration::ZERO);
+
+        let result = infer_schema_from_iter_with_cancellation(
+            input,
+            &InferenceOptions::default(),
+            &cancellation,
+        );
+
+        assert!(matches!(result, Err(DrivelError::Cancelled(Cancelled))));
+    }
+
     #[test]
     fn infers_from_iter() {
         let input = vec![
@@ -1113,12 +3638,13 @@ mod tests {
         ];
         let options = InferenceOptions {
             enum_inference: None,
+            ..Default::default()
         };
         let schema = infer_schema_from_iter(input, &options);
         assert_eq!(
             schema,
             SchemaState::Object {
-                required: std::collections::HashMap::from_iter([
+                required: IndexMap::from_iter([
                     (
                         "baz".to_owned(),
                         SchemaState::Nullable(Box::new(SchemaState::Number(NumberType::Integer {
@@ -1128,16 +3654,178 @@ mod tests {
                     ),
                     ("qux".to_owned(), SchemaState::Boolean),
                 ]),
-                optional: std::collections::HashMap::from_iter([(
+                optional: IndexMap::from_iter([(
                     "foo".to_owned(),
                     SchemaState::String(StringType::Unknown {
                         strings_seen: vec!["bar".to_owned(), "barbar".to_owned()],
-                        chars_seen: vec!['b', 'a', 'r', 'b', 'a', 'r', 'b', 'a', 'r'],
+                        chars_seen: HashMap::from([('b', 3), ('a', 3), ('r', 3)]),
+                        n_strings_seen: 2,
+                        distinct: DistinctCount::from_iter(["bar", "barbar"]),
+                        n_chars_seen: 9,
                         min_length: Some(3),
-                        max_length: Some(6)
+                        max_length: Some(6),
+                        prefix: "bar".to_owned(),
+                        suffix: "bar".to_owned(),
+                        structure: Some(vec![lowercase(None, 3, 6)])
                     })
                 )])
             }
         );
     }
+
+    #[test]
+    fn infers_the_first_chunk_of_records_past_the_deadline() {
+        let records = (0..3000).map(|n| json!(n)).collect::<Vec<_>>();
+        let (schema, n_inferred) = infer_records_until(
+            records,
+            &InferenceOptions::default(),
+            std::time::Instant::now(),
+        );
+
+        assert_eq!(n_inferred, BUDGET_CHUNK_SIZE);
+        assert_eq!(
+            schema,
+            SchemaState::Number(NumberType::Integer {
+                min: 0,
+                max: BUDGET_CHUNK_SIZE as i64 - 1
+            })
+        );
+    }
+
+    /// Returns a schema in a form that does not depend on the order in which schemas were
+    /// merged: with its fields sorted, and its samples left out.
+    fn normalized(mut schema: SchemaState) -> String {
+        schema.sort_fields();
+        // the branches of unions follow the order of merging, like the fields of objects
+        let union = match &mut schema {
+            SchemaState::Nullable(inner) => inner.as_mut(),
+            schema => schema,
+        };
+        if let SchemaState::Union(branches) = union {
+            branches.sort_by_key(SchemaState::to_string_pretty);
+        }
+        schema.to_string_pretty()
+    }
+
+    #[test]
+    fn merging_is_commutative_and_associative() {
+        let values = [
+            json!(1),
+            json!(-2.5),
+            json!(12.25),
+            json!(u64::MAX),
+            json!("NaN"),
+            json!("alice"),
+            json!("Bob Smith"),
+            json!("2024-01-01"),
+            json!("2024-01-01T10:00:00Z"),
+            json!("0e3a99a5-0201-4444-9ab1-8343fac56233"),
+            json!(true),
+            json!(null),
+            json!([]),
+            json!([1, 2]),
+            json!(["a", 1]),
+            json!({}),
+            json!({ "a": 1, "b": "x" }),
+            json!({ "b": null, "c": [true] }),
+        ];
+        let options = InferenceOptions {
+            unconstrained_empty: true,
+            ..InferenceOptions::default()
+        };
+        let mut schemas = values
+            .iter()
+            .map(|value| infer_schema(value.clone(), &options))
+            .collect::<Vec<_>>();
+        // unions are only parsed from schemas, and merge their branches by kind
+        schemas.push(SchemaState::Union(vec![
+            SchemaState::String(StringType::Email),
+            SchemaState::Number(NumberType::Integer { min: 1, max: 5 }),
+        ]));
+        schemas.push(SchemaState::Union(vec![
+            infer_schema(json!({ "a": 1 }), &options),
+            SchemaState::Boolean,
+            SchemaState::Const(json!("none")),
+        ]));
+        let schema = |n: usize| schemas[n].clone();
+
+        for a in 0..schemas.len() {
+            assert_eq!(
+                normalized(schema(a).merge(schema(a))),
+                normalized(schema(a)),
+                "merging {} with itself",
+                normalized(schema(a))
+            );
+            for b in 0..schemas.len() {
+                assert_eq!(
+                    normalized(schema(a).merge(schema(b))),
+                    normalized(schema(b).merge(schema(a))),
+                    "merging {} and {}",
+                    normalized(schema(a)),
+                    normalized(schema(b))
+                );
+                // a conflict between two values is indefinite, whatever union it is merged
+                // into afterwards, so associativity only holds among the schemas of values
+                if a >= values.len() || b >= values.len() {
+                    continue;
+                }
+                for c in 0..values.len() {
+                    assert_eq!(
+                        normalized(schema(a).merge(schema(b)).merge(schema(c))),
+                        normalized(schema(a).merge(schema(b).merge(schema(c)))),
+                        "merging {}, {} and {}",
+                        values[a],
+                        values[b],
+                        values[c]
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_formats_matched_by_all_but_a_tolerated_share_of_strings() {
+        let uuid = |n: usize| format!("0e3a99a5-0201-4444-9ab1-{:012}", n);
+        let records = |outliers: usize| {
+            (0..200)
+                .map(|n| {
+                    json!({
+                        "id": if n < outliers { "n/a".to_owned() } else { uuid(n) },
+                        "contact": if n == 0 { uuid(n) } else { format!("user{}@example.com", n) },
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let tolerant = InferenceOptions {
+            format_tolerance: Some(0.01),
+            ..InferenceOptions::default()
+        };
+        let field = |schema: &SchemaState, name: &str| match schema {
+            SchemaState::Object { required, .. } => required[name].clone(),
+            _ => panic!("expected an object"),
+        };
+
+        let schema = infer_schema_from_iter(records(2), &tolerant);
+        assert_eq!(field(&schema, "id"), SchemaState::String(StringType::UUID));
+        assert_eq!(
+            field(&schema, "contact"),
+            SchemaState::String(StringType::Email)
+        );
+
+        let schema = infer_schema_from_iter(records(3), &tolerant);
+        assert!(matches!(
+            field(&schema, "id"),
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+
+        let schema = infer_schema_from_iter(records(1), &InferenceOptions::default());
+        assert!(matches!(
+            field(&schema, "id"),
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+        assert!(matches!(
+            field(&schema, "contact"),
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+    }
 }
@@ -1,7 +1,19 @@
-use crate::StringType;
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+use crate::{
+    sample::count_into, CharClass, DateFormat, Detectors, DistinctCount, PathKind, Profile,
+    Segment, StringType,
+};
 
 lazy_static! {
     static ref ISO_DATE_REGEX: regex::Regex = regex::Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
+    static ref TIME_REGEX: regex::Regex =
+        regex::Regex::new(r"^\d{2}:\d{2}(?::\d{2}(?:\.\d+)?)?$").unwrap();
+    static ref DURATION_REGEX: regex::Regex = regex::Regex::new(
+        r"^P(?:(\d+)Y)?(?:(\d+)M)?(?:(\d+)W)?(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+)(?:[.,]\d+)?S)?)?$"
+    )
+    .unwrap();
     static ref UUIDREGEX: regex::Regex =
         regex::Regex::new(r"^[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$")
             .unwrap();
@@ -9,9 +21,98 @@ lazy_static! {
         regex::Regex::new(r"^[a-zA-Z0-9\-]+\.[a-zA-Z]{2,}$").unwrap();
     static ref EMAIL_REGEX: regex::Regex =
         regex::Regex::new(r"[a-zA-Z0-9]+@[a-zA-Z0-9]+\.[a-zA-Z]{2,}$").unwrap();
+    // E.164, or a national number with separators or an area code in parentheses, optionally
+    // preceded by a country code
+    static ref PHONE_REGEX: regex::Regex = regex::Regex::new(
+        r"^(?:\+[1-9]\d{7,14}|(?:\+\d{1,3}[ .-]?)?(?:\(\d{2,4}\)[ .-]?|\d{2,4}[ .-])\d{3,4}[ .-]?\d{3,4})$"
+    )
+    .unwrap();
+    static ref ULID_REGEX: regex::Regex = regex::Regex::new(ULID_PATTERN).unwrap();
+    static ref KSUID_REGEX: regex::Regex = regex::Regex::new(KSUID_PATTERN).unwrap();
+    static ref OBJECT_ID_REGEX: regex::Regex = regex::Regex::new(OBJECT_ID_PATTERN).unwrap();
+    static ref SEMVER_REGEX: regex::Regex = regex::Regex::new(SEMVER_PATTERN).unwrap();
+}
+
+/// ULIDs: a 48-bit timestamp and 80 random bits in 26 characters of Crockford's base32.
+pub(crate) const ULID_PATTERN: &str = "^[0-7][0-9A-HJKMNP-TV-Z]{25}$";
+/// KSUIDs: a 32-bit timestamp and 128 random bits in 27 base62 characters.
+pub(crate) const KSUID_PATTERN: &str = "^[0-9A-Za-z]{27}$";
+/// MongoDB ObjectIds: a 32-bit timestamp, 5 random bytes and a 3-byte counter in hex.
+pub(crate) const OBJECT_ID_PATTERN: &str = "^[0-9a-f]{24}$";
+/// JWTs: a header, claims and a signature, which is empty for unsigned tokens, in base64url.
+pub(crate) const JWT_PATTERN: &str = "^[A-Za-z0-9_-]+\\.[A-Za-z0-9_-]+\\.[A-Za-z0-9_-]*$";
+/// Semantic versions: major, minor and patch numbers without leading zeros, optionally
+/// followed by a pre-release and build metadata, as given by the semver specification.
+pub(crate) const SEMVER_PATTERN: &str = r"^(0|[1-9]\d*)\.(0|[1-9]\d*)\.(0|[1-9]\d*)(?:-(?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*)(?:\.(?:0|[1-9]\d*|\d*[a-zA-Z-][0-9a-zA-Z-]*))*)?(?:\+[0-9a-zA-Z-]+(?:\.[0-9a-zA-Z-]+)*)?$";
+/// The largest KSUID; base62 digits sort like their ASCII characters.
+const MAX_KSUID: &str = "aWgEPTl1tmebfsQzFP4bxwgy80V";
+
+/// What can be told about a string in a single pass over its bytes, from which the built-in
+/// detectors rule out most strings before parsing them or evaluating a regular expression.
+/// Bytes other than ASCII are only ever part of a multi-byte character, so they are never
+/// taken for the ASCII characters looked for.
+pub(crate) struct Shape {
+    pub(crate) len: usize,
+    /// The first byte, or 0 if the string is empty.
+    pub(crate) first: u8,
+    /// The last byte, or 0 if the string is empty.
+    pub(crate) last: u8,
+    /// The number of ASCII digits.
+    pub(crate) digits: usize,
+    pub(crate) dots: usize,
+    pub(crate) has_uppercase: bool,
+    pub(crate) has_lowercase: bool,
+    pub(crate) has_at: bool,
+    pub(crate) has_colon: bool,
+    pub(crate) has_dash: bool,
+    pub(crate) has_slash: bool,
+}
+
+impl Shape {
+    pub(crate) fn of(s: &str) -> Self {
+        let bytes = s.as_bytes();
+        let mut shape = Shape {
+            len: bytes.len(),
+            first: bytes.first().copied().unwrap_or_default(),
+            last: bytes.last().copied().unwrap_or_default(),
+            digits: 0,
+            dots: 0,
+            has_uppercase: false,
+            has_lowercase: false,
+            has_at: false,
+            has_colon: false,
+            has_dash: false,
+            has_slash: false,
+        };
+        for &b in bytes {
+            match b {
+                b'0'..=b'9' => shape.digits += 1,
+                b'A'..=b'Z' => shape.has_uppercase = true,
+                b'a'..=b'z' => shape.has_lowercase = true,
+                b'.' => shape.dots += 1,
+                b'@' => shape.has_at = true,
+                b':' => shape.has_colon = true,
+                b'-' => shape.has_dash = true,
+                b'/' => shape.has_slash = true,
+                _ => {}
+            }
+        }
+        shape
+    }
 }
 
-fn uuid(s: &str) -> Option<StringType> {
+/// The strings with which NaN and infinite floats are commonly encoded in JSON.
+pub(crate) const NON_FINITE_FLOATS: [&str; 3] = ["NaN", "Infinity", "-Infinity"];
+
+pub(crate) fn non_finite_float(s: &str) -> Option<StringType> {
+    if NON_FINITE_FLOATS.contains(&s) {
+        Some(StringType::NonFiniteFloat)
+    } else {
+        None
+    }
+}
+
+pub(crate) fn uuid(s: &str) -> Option<StringType> {
     if s.len() == 36 && UUIDREGEX.is_match(s) {
         Some(StringType::UUID)
     } else {
@@ -19,7 +120,163 @@ fn uuid(s: &str) -> Option<StringType> {
     }
 }
 
-fn email(s: &str) -> Option<StringType> {
+/// Recognises ULIDs, KSUIDs and ObjectIds. Strings of digits alone are more likely to be
+/// numbers than identifiers, so identifiers must mix digits and letters (and KSUIDs both
+/// cases of letters) to be recognised.
+pub(crate) fn identifier(s: &str) -> Option<StringType> {
+    let has = |predicate: fn(&char) -> bool| s.chars().any(|c| predicate(&c));
+    let has_digit = has(char::is_ascii_digit);
+    match s.len() {
+        26 if has_digit && has(char::is_ascii_uppercase) && ULID_REGEX.is_match(s) => {
+            Some(StringType::Ulid)
+        }
+        27 if has_digit
+            && has(char::is_ascii_uppercase)
+            && has(char::is_ascii_lowercase)
+            && s <= MAX_KSUID
+            && KSUID_REGEX.is_match(s) =>
+        {
+            Some(StringType::Ksuid)
+        }
+        24 if has_digit && has(char::is_ascii_lowercase) && OBJECT_ID_REGEX.is_match(s) => {
+            Some(StringType::ObjectId)
+        }
+        _ => None,
+    }
+}
+
+/// Decodes the header or payload of a JWT: unpadded base64url-encoded JSON.
+fn jwt_part(part: &str) -> Option<serde_json::Value> {
+    use base64::Engine;
+    let decoded = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(part)
+        .ok()?;
+    serde_json::from_slice(&decoded).ok()
+}
+
+/// Returns the claims of a JWT: three base64url parts separated by dots, of which the first
+/// is a header naming the signing algorithm, the second the claims, and the last the
+/// signature, which is empty for unsigned tokens.
+pub(crate) fn jwt_claims(s: &str) -> Option<serde_json::Value> {
+    let mut parts = s.split('.');
+    let (header, claims, signature) = (parts.next()?, parts.next()?, parts.next()?);
+    if parts.next().is_some()
+        || !signature
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+    {
+        return None;
+    }
+    jwt_part(header)?.as_object()?.get("alg")?;
+    Some(jwt_part(claims)?).filter(|claims| claims.is_object())
+}
+
+/// Recognises JSON Web Tokens, whose claims are inferred like any other JSON.
+pub(crate) fn jwt(s: &str) -> Option<StringType> {
+    let options = crate::InferenceOptions {
+        enum_inference: None,
+        ..Default::default()
+    };
+    Some(StringType::Jwt {
+        claims: Box::new(crate::infer_schema(jwt_claims(s)?, &options)),
+    })
+}
+
+pub(crate) fn semver(s: &str) -> Option<StringType> {
+    let major = SEMVER_REGEX.captures(s)?[1].parse().ok()?;
+    Some(StringType::Semver {
+        min_major: major,
+        max_major: major,
+    })
+}
+
+/// The length of the shortest strings that are recognised as base64.
+const MIN_BASE64_LENGTH: usize = 16;
+
+/// Recognises padded base64. Many words and numbers are valid base64 as well, so only longer
+/// strings that mix both cases of letters with digits or symbols are recognised.
+pub(crate) fn base64(s: &str) -> Option<StringType> {
+    use base64::Engine;
+    let has = |predicate: fn(&char) -> bool| s.chars().any(|c| predicate(&c));
+    if s.len() < MIN_BASE64_LENGTH
+        || !s.len().is_multiple_of(4)
+        || !has(char::is_ascii_uppercase)
+        || !has(char::is_ascii_lowercase)
+        || !(has(char::is_ascii_digit) || has(|c| matches!(c, '+' | '/' | '=')))
+    {
+        return None;
+    }
+    base64::engine::general_purpose::STANDARD
+        .decode(s)
+        .ok()
+        .map(|_| StringType::Base64 {
+            min_length: s.len(),
+            max_length: s.len(),
+        })
+}
+
+/// The ISO 4217 codes of currencies, funds and precious metals, in alphabetical order.
+const CURRENCY_CODES: [&str; 178] = [
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BOV", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD",
+    "CAD", "CDF", "CHE", "CHF", "CHW", "CLF", "CLP", "CNY", "COP", "COU", "CRC", "CUC", "CUP",
+    "CVE", "CZK", "DJF", "DKK", "DOP", "DZD", "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP",
+    "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ", "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS",
+    "INR", "IQD", "IRR", "ISK", "JMD", "JOD", "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW",
+    "KWD", "KYD", "KZT", "LAK", "LBP", "LKR", "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD",
+    "MMK", "MNT", "MOP", "MRU", "MUR", "MVR", "MWK", "MXN", "MXV", "MYR", "MZN", "NAD", "NGN",
+    "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN", "PGK", "PHP", "PKR", "PLN", "PYG", "QAR",
+    "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR", "SDG", "SEK", "SGD", "SHP", "SLE", "SLL",
+    "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB", "TJS", "TMT", "TND", "TOP", "TRY",
+    "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "USN", "UYI", "UYU", "UYW", "UZS", "VED", "VES",
+    "VND", "VUV", "WST", "XAF", "XAG", "XAU", "XBA", "XBB", "XBC", "XBD", "XCD", "XDR", "XOF",
+    "XPD", "XPF", "XPT", "XSU", "XUA", "YER", "ZAR", "ZMW", "ZWL",
+];
+
+pub(crate) fn currency_code(s: &str) -> Option<StringType> {
+    if s.len() == 3 && CURRENCY_CODES.binary_search(&s).is_ok() {
+        Some(StringType::CurrencyCode {
+            codes: HashSet::from_iter([s.to_owned()]),
+        })
+    } else {
+        None
+    }
+}
+
+/// Returns the format of a phone number: the number with each digit replaced by `#`, e.g.
+/// `(###) ###-####`. A country code set apart from the number is kept, e.g. `+44 ## #### ####`,
+/// so that numbers produced in the format belong to the same country.
+pub(crate) fn phone_format(s: &str) -> Option<String> {
+    let digits = s.chars().filter(char::is_ascii_digit).count();
+    if !(7..=15).contains(&digits) || !PHONE_REGEX.is_match(s) {
+        return None;
+    }
+    let country_code = match s.strip_prefix('+') {
+        Some(rest) => rest
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(0, |end| end + 1),
+        None => 0,
+    };
+    Some(
+        s.char_indices()
+            .map(|(idx, c)| {
+                if c.is_ascii_digit() && idx >= country_code {
+                    '#'
+                } else {
+                    c
+                }
+            })
+            .collect(),
+    )
+}
+
+pub(crate) fn phone(s: &str) -> Option<StringType> {
+    phone_format(s).map(|format| StringType::PhoneNumber {
+        formats: HashMap::from_iter([(format, 1)]),
+    })
+}
+
+pub(crate) fn email(s: &str) -> Option<StringType> {
     if s.contains('@') && EMAIL_REGEX.is_match(s) {
         Some(StringType::Email)
     } else {
@@ -27,46 +284,372 @@ fn email(s: &str) -> Option<StringType> {
     }
 }
 
-fn url_host(s: &str) -> Option<StringType> {
-    if s.contains('.') {
-        if url::Url::parse(s).is_ok() {
-            return Some(StringType::Url);
+pub(crate) fn url(s: &str) -> Option<StringType> {
+    if s.contains('.') && url::Url::parse(s).is_ok() {
+        Some(StringType::Url)
+    } else {
+        None
+    }
+}
+
+pub(crate) fn hostname(s: &str) -> Option<StringType> {
+    if s.contains('.') && HOSTNAME_REGEX.is_match(s) {
+        Some(StringType::Hostname)
+    } else {
+        None
+    }
+}
+
+/// Splits a file path or a URI of an object in cloud storage into its kind, its root (e.g. `/`,
+/// `C:\` or `s3://logs/`) and the names of its directories and file.
+pub(crate) fn split_path(s: &str) -> Option<(PathKind, &str, Vec<&str>)> {
+    let (kind, root_len) = if let Some(rest) = s.strip_prefix("s3://").or(s.strip_prefix("gs://")) {
+        let bucket = &rest[..rest.find('/')?];
+        let valid_bucket = (3..=63).contains(&bucket.len())
+            && bucket
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-._".contains(c));
+        if !valid_bucket {
+            return None;
         }
-        if HOSTNAME_REGEX.is_match(s) {
-            return Some(StringType::Hostname);
+        (PathKind::of_root(s), "s3://".len() + bucket.len() + 1)
+    } else if s.get(1..3) == Some(":\\") && s.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        (PathKind::Windows, 3)
+    } else {
+        let root = ["/", "./", "../", "~/"]
+            .into_iter()
+            .find(|root| s.starts_with(root))?;
+        (PathKind::Posix, root.len())
+    };
+    let (root, rest) = s.split_at(root_len);
+    let names = rest.split(kind.separator()).collect::<Vec<_>>();
+    names
+        .iter()
+        .all(|name| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || "._-~@+ ".contains(c))
+        })
+        .then_some((kind, root, names))
+}
+
+/// Returns the extension of a file name, e.g. `csv`, or an empty string if it has none.
+pub(crate) fn extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(idx) if idx > 0 => &name[idx + 1..],
+        _ => "",
+    }
+}
+
+fn path_type(s: &str, cloud: bool) -> Option<StringType> {
+    let (kind, root, names) = split_path(s)?;
+    if matches!(kind, PathKind::S3 | PathKind::Gcs) != cloud {
+        return None;
+    }
+    let file_name = names.last().expect("a path has at least one name");
+    Some(StringType::FilePath {
+        kind,
+        roots: HashMap::from_iter([(root.to_owned(), 1)]),
+        min_depth: names.len() - 1,
+        max_depth: names.len() - 1,
+        extensions: HashMap::from_iter([(extension(file_name).to_owned(), 1)]),
+    })
+}
+
+/// Recognises POSIX and Windows file paths, e.g. `/var/log/app.log` or `C:\Users\report.pdf`.
+pub(crate) fn file_path(s: &str) -> Option<StringType> {
+    path_type(s, false)
+}
+
+/// Recognises URIs of objects in S3 and Google Cloud Storage, e.g. `s3://logs/2024/app.log`.
+pub(crate) fn storage_uri(s: &str) -> Option<StringType> {
+    path_type(s, true)
+}
+
+/// Recognises URLs, and hostnames that are not URLs.
+pub(crate) fn url_host(s: &str) -> Option<StringType> {
+    url(s).or_else(|| hostname(s))
+}
+
+pub(crate) fn date(s: &str) -> Option<StringType> {
+    if s.chars().take(1).all(|char| char.is_numeric()) && ISO_DATE_REGEX.is_match(s) {
+        if let Ok(date) = s.parse::<chrono::NaiveDate>() {
+            return Some(StringType::IsoDate {
+                range: Some(date..=date),
+            });
         }
     }
     None
 }
 
-fn dates(s: &str) -> Option<StringType> {
+/// Recognises datetimes in RFC 3339 (the profile of ISO 8601 used on the internet) or
+/// RFC 2822, which are both described as ISO 8601.
+pub(crate) fn datetime(s: &str) -> Option<StringType> {
     if s.chars().take(1).all(|char| char.is_numeric()) {
-        if ISO_DATE_REGEX.is_match(s) {
-            return Some(StringType::IsoDate);
-        }
-        if chrono::DateTime::parse_from_rfc3339(s).is_ok() {
-            return Some(StringType::DateTimeISO8601);
+        if let Ok(date_time) = chrono::DateTime::parse_from_rfc3339(s) {
+            let date_time = date_time.to_utc();
+            return Some(StringType::DateTimeISO8601 {
+                range: Some(date_time..=date_time),
+            });
         }
     }
 
-    if chrono::DateTime::parse_from_rfc2822(s).is_ok() {
-        return Some(StringType::DateTimeISO8601);
+    if let Ok(date_time) = chrono::DateTime::parse_from_rfc2822(s) {
+        let date_time = date_time.to_utc();
+        return Some(StringType::DateTimeISO8601 {
+            range: Some(date_time..=date_time),
+        });
     }
 
     None
 }
 
-pub(crate) fn infer_string_type(s: &str) -> StringType {
-    for matcher in [uuid, email, url_host, dates] {
-        if let Some(string_type) = matcher(s) {
-            return string_type;
+/// The years of dates written as `YYYYMMDD`, so that other eight-digit numbers are not taken
+/// for dates.
+const COMPACT_DATE_YEARS: RangeInclusive<i32> = 1900..=2100;
+
+/// The seconds since the epoch taken for moments: ten-digit numbers up to 2100-01-01.
+const EPOCH_SECONDS: RangeInclusive<i64> = 1_000_000_000..=4_102_444_800;
+
+/// Recognises dates written in `format`, with every digit present, e.g. `03/14/2024` but not
+/// `3/14/2024`, so that dates are produced the way they were seen.
+pub(crate) fn formatted_date(s: &str, format: DateFormat) -> Option<StringType> {
+    let template = match format {
+        DateFormat::MonthDayYear => "##/##/####",
+        DateFormat::DayMonthYear => "##-##-####",
+        DateFormat::Compact => "########",
+    };
+    let fits = s.len() == template.len()
+        && s.chars().zip(template.chars()).all(|(c, t)| match t {
+            '#' => c.is_ascii_digit(),
+            _ => c == t,
+        });
+    if !fits {
+        return None;
+    }
+    let date = chrono::NaiveDate::parse_from_str(s, format.chrono_format()).ok()?;
+    if format == DateFormat::Compact && !COMPACT_DATE_YEARS.contains(&chrono::Datelike::year(&date))
+    {
+        return None;
+    }
+    Some(StringType::FormattedDate {
+        format,
+        range: Some(date..=date),
+    })
+}
+
+/// Recognises moments written as the number of seconds since the Unix epoch, e.g. `1710428400`.
+pub(crate) fn epoch_seconds(s: &str) -> Option<StringType> {
+    if s.len() != 10 || !s.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let seconds = s
+        .parse::<i64>()
+        .ok()
+        .filter(|n| EPOCH_SECONDS.contains(n))?;
+    let date_time = chrono::DateTime::from_timestamp(seconds, 0)?;
+    Some(StringType::EpochSeconds {
+        range: Some(date_time..=date_time),
+    })
+}
+
+/// Recognises times of day, e.g. `09:30` or `17:45:00.250`, leaving out fractions of seconds.
+pub(crate) fn time(s: &str) -> Option<StringType> {
+    if !TIME_REGEX.is_match(s) {
+        return None;
+    }
+    let seconds = s.len() > "HH:MM".len();
+    let format = if seconds { "%H:%M:%S%.f" } else { "%H:%M" };
+    let time = chrono::NaiveTime::parse_from_str(s, format).ok()?;
+    let time = chrono::Timelike::with_nanosecond(&time, 0)?;
+    Some(StringType::Time {
+        range: Some(time..=time),
+        seconds,
+    })
+}
+
+/// The seconds in each unit of an ISO 8601 duration, in the order in which they are written.
+const DURATION_UNITS: [u64; 7] = [365 * 86_400, 30 * 86_400, 7 * 86_400, 86_400, 3600, 60, 1];
+
+/// Recognises ISO 8601 durations, e.g. `P3D` or `PT1H30M`, leaving out fractions of seconds.
+pub(crate) fn duration(s: &str) -> Option<StringType> {
+    // at least one amount must be given, and a time part must not be empty
+    if s.len() < 3 || s.ends_with('T') {
+        return None;
+    }
+    let captures = DURATION_REGEX.captures(s)?;
+    let mut seconds = 0u64;
+    for (idx, unit) in DURATION_UNITS.into_iter().enumerate() {
+        if let Some(amount) = captures.get(idx + 1) {
+            let amount = amount.as_str().parse::<u64>().ok()?;
+            seconds = seconds.checked_add(amount.checked_mul(unit)?)?;
+        }
+    }
+    Some(StringType::Duration {
+        range: Some(seconds..=seconds),
+    })
+}
+
+/// Recognises dates and datetimes.
+pub(crate) fn dates(s: &str) -> Option<StringType> {
+    date(s).or_else(|| datetime(s))
+}
+
+/// The fewest words of natural language, so that names like `Ann Smith` are not taken for it.
+const MIN_TEXT_WORDS: usize = 3;
+
+/// The least share of letters among the characters of natural language, other than whitespace.
+const MIN_TEXT_LETTERS: f64 = 0.8;
+
+/// Recognises natural language: words starting with a capital letter, like a sentence, and
+/// made up of letters, give or take some digits and punctuation, e.g. `Arrived on time.`
+pub(crate) fn text(s: &str) -> Option<StringType> {
+    if !s.starts_with(char::is_uppercase) {
+        return None;
+    }
+    let words = s.split_whitespace().count();
+    let (letters, others) =
+        s.chars()
+            .filter(|c| !c.is_whitespace())
+            .fold((0, 0), |(letters, others), c| {
+                if c.is_alphabetic() {
+                    (letters + 1, others)
+                } else {
+                    (letters, others + 1)
+                }
+            });
+    let letter_ratio = letters as f64 / (letters + others) as f64;
+    (words >= MIN_TEXT_WORDS && letter_ratio >= MIN_TEXT_LETTERS).then_some(StringType::Text {
+        min_words: words,
+        max_words: words,
+    })
+}
+
+/// Strings made up of more runs than this are free text rather than identifiers or codes,
+/// and are not given a structure.
+const MAX_SEGMENTS: usize = 32;
+
+fn char_class(c: char) -> CharClass {
+    if c.is_ascii_digit() {
+        CharClass::Digit
+    } else if c.is_ascii_uppercase() {
+        CharClass::Uppercase
+    } else if c.is_ascii_lowercase() {
+        CharClass::Lowercase
+    } else {
+        CharClass::Other
+    }
+}
+
+/// Splits a string into runs of digits, uppercase and lowercase letters; every other character
+/// forms a segment of its own.
+pub(crate) fn structure(s: &str) -> Option<Vec<Segment>> {
+    let mut segments: Vec<Segment> = vec![];
+    for c in s.chars() {
+        let class = char_class(c);
+        match segments.last_mut() {
+            Some(Segment {
+                class: last_class,
+                literal: Some(literal),
+                min_length,
+                max_length,
+            }) if *last_class == class && class != CharClass::Other => {
+                literal.push(c);
+                *min_length += 1;
+                *max_length += 1;
+            }
+            _ => {
+                if segments.len() == MAX_SEGMENTS {
+                    return None;
+                }
+                segments.push(Segment {
+                    class,
+                    literal: Some(c.to_string()),
+                    min_length: 1,
+                    max_length: 1,
+                });
+            }
         }
     }
+    Some(segments)
+}
+
+/// Returns the name of the format a single string was detected as, or `string` if none was.
+pub(crate) fn format_name(string_type: &StringType) -> &'static str {
+    match string_type {
+        StringType::Unknown { .. } => "string",
+        StringType::IsoDate { .. } => "date",
+        StringType::DateTimeRFC2822 { .. } => "datetime (RFC 2822)",
+        StringType::DateTimeISO8601 { .. } => "datetime",
+        StringType::FormattedDate { format, .. } => format.name(),
+        StringType::EpochSeconds { .. } => "epoch",
+        StringType::Time { .. } => "time",
+        StringType::Duration { .. } => "duration",
+        StringType::Text { .. } => "text",
+        StringType::FilePath {
+            kind: PathKind::S3 | PathKind::Gcs,
+            ..
+        } => "storage-uri",
+        StringType::FilePath { .. } => "path",
+        StringType::UUID => "uuid",
+        StringType::Ulid => "ulid",
+        StringType::Ksuid => "ksuid",
+        StringType::ObjectId => "ObjectId",
+        StringType::CurrencyCode { .. } => "currency",
+        StringType::PhoneNumber { .. } => "phone",
+        StringType::Semver { .. } => "semver",
+        StringType::Base64 { .. } => "base64",
+        StringType::EmbeddedJson(_) => "json",
+        StringType::Jwt { .. } => "jwt",
+        StringType::Email => "email",
+        StringType::Url => "url",
+        StringType::Hostname => "hostname",
+        StringType::Enum { .. } => "enum",
+        StringType::Pattern(_) => "pattern",
+        StringType::NonFiniteFloat => "NaN/Infinity",
+        StringType::Tally { format, .. } => format_name(format),
+    }
+}
+
+/// The number of different characters of a string for which their counts are sized up front.
+const MAX_PRESIZED_CHARS: usize = 32;
+
+/// Infers the type of a string: the format of the first of `detectors` that detects one, or
+/// else a string of unknown format, whose characters are counted for up to `max_samples`
+/// different characters. The detectors tried are counted in `profile`, if any.
+pub(crate) fn infer_string_type(
+    s: &str,
+    detectors: &Detectors,
+    max_samples: usize,
+    profile: Option<&Profile>,
+) -> StringType {
+    let detected = match profile {
+        Some(profile) => detectors.detect_profiled(s, profile),
+        None => detectors.detect(s),
+    };
+    if let Some(string_type) = detected {
+        return string_type;
+    }
 
-    return StringType::Unknown {
-        strings_seen: vec![s.to_owned()],
-        chars_seen: s.chars().collect(),
+    // sized up front for the characters of most strings, rather than growing as they are counted
+    let mut chars_seen = HashMap::with_capacity(s.len().min(MAX_PRESIZED_CHARS));
+    let n_chars_seen = count_into(&mut chars_seen, s.chars().map(|c| (c, 1)), max_samples);
+
+    StringType::Unknown {
+        strings_seen: if max_samples > 0 {
+            vec![s.to_owned()]
+        } else {
+            vec![]
+        },
+        chars_seen,
+        n_strings_seen: 1,
+        distinct: DistinctCount::from_iter([s]),
+        n_chars_seen,
         min_length: Some(s.len()),
         max_length: Some(s.len()),
-    };
+        prefix: s.to_owned(),
+        suffix: s.to_owned(),
+        structure: structure(s),
+    }
 }
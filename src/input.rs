@@ -0,0 +1,377 @@
+//! Reading the JSON documents and JSON lines, YAML or XML documents, or MessagePack or CBOR
+//! values, that schemas are inferred from.
+
+use rayon::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    infer_schema, infer_schema_from_iter, DrivelError, InferenceOptions, SchemaState, XmlOptions,
+};
+
+/// The size of the chunks of JSON lines that are parsed in parallel, each of which is extended
+/// to the end of the line it would end in.
+const LINES_CHUNK_SIZE: usize = 1 << 20;
+
+/// Parses JSON lines in chunks across threads, without copying them out of `input`. Returns
+/// `None` if a line is not a single JSON value.
+fn parse_lines_in_parallel(input: &str) -> Option<Vec<serde_json::Value>> {
+    let mut chunks = vec![];
+    let mut rest = input;
+    while !rest.is_empty() {
+        let end = match rest.as_bytes().get(LINES_CHUNK_SIZE..) {
+            Some(tail) => tail
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(rest.len(), |idx| LINES_CHUNK_SIZE + idx + 1),
+            None => rest.len(),
+        };
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    let chunks = chunks
+        .into_par_iter()
+        .map(|chunk| {
+            chunk
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| serde_json::from_str(line).ok())
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(chunks.into_iter().flatten().collect())
+}
+
+/// The data a schema is inferred from.
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    /// A single JSON document. The elements of an array at its root are the records.
+    Document(serde_json::Value),
+    /// JSON lines, or a stream of YAML documents or of MessagePack or CBOR values, each of which
+    /// is a record.
+    Lines(Vec<serde_json::Value>),
+}
+
+impl Input {
+    /// Parses input as a JSON document, or as JSON lines if it is not one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{DrivelError, Input};
+    ///
+    /// let input = Input::parse("{\"id\": 1}\n{\"id\": 2}\n").unwrap();
+    /// assert_eq!(input.records().len(), 2);
+    ///
+    /// let err = Input::parse("{\"id\": 1}\n{\"id\": }\n").unwrap_err();
+    /// assert!(matches!(err, DrivelError::InvalidJsonLine { line: 2, .. }));
+    /// ```
+    pub fn parse(input: &str) -> Result<Self, DrivelError> {
+        let document_err = match serde_json::from_str(input) {
+            Ok(document) => return Ok(Self::Document(document)),
+            Err(err) => err,
+        };
+        if let Some(lines) = parse_lines_in_parallel(input) {
+            return Ok(Self::Lines(lines));
+        }
+        // values that span lines, or share one, are read one after the other, which also tells
+        // on which line a value is invalid
+        let mut lines = vec![];
+        for line in serde_json::Deserializer::from_str(input).into_iter() {
+            match line {
+                Ok(line) => lines.push(line),
+                // input that fails before its first value was most likely meant as one document
+                Err(_) if lines.is_empty() => return Err(DrivelError::InvalidJson(document_err)),
+                Err(source) => {
+                    return Err(DrivelError::InvalidJsonLine {
+                        line: source.line(),
+                        source,
+                    })
+                }
+            }
+        }
+        Ok(Self::Lines(lines))
+    }
+
+    /// Parses input as YAML: a single document, or a stream of documents separated by `---`,
+    /// each of which is a record like a line of JSON lines is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::Input;
+    /// use serde_json::json;
+    ///
+    /// let input = Input::parse_yaml("name: Alice\ntags: [a, b]\n").unwrap();
+    /// assert_eq!(input, Input::Document(json!({ "name": "Alice", "tags": ["a", "b"] })));
+    ///
+    /// let input = Input::parse_yaml("id: 1\n---\nid: 2\n").unwrap();
+    /// assert_eq!(input.records().len(), 2);
+    /// ```
+    pub fn parse_yaml(input: &str) -> Result<Self, DrivelError> {
+        let documents = serde_yaml::Deserializer::from_str(input)
+            .map(serde_json::Value::deserialize)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DrivelError::InvalidYaml)?;
+        Ok(Self::from_values(documents))
+    }
+
+    /// Parses input as an XML document, into an object holding its root element. Attributes
+    /// become fields named with `options.attribute_prefix`, and elements that are repeated
+    /// anywhere in the document become arrays.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{Input, XmlOptions};
+    /// use serde_json::json;
+    ///
+    /// let input = Input::parse_xml(
+    ///     "<users><user id=\"1\">Alice</user><user id=\"2\">Bob</user></users>",
+    ///     &XmlOptions::default(),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(
+    ///     input,
+    ///     Input::Document(json!({
+    ///         "users": {
+    ///             "user": [
+    ///                 { "@id": 1, "#text": "Alice" },
+    ///                 { "@id": 2, "#text": "Bob" }
+    ///             ]
+    ///         }
+    ///     }))
+    /// );
+    /// ```
+    pub fn parse_xml(input: &str, options: &XmlOptions) -> Result<Self, DrivelError> {
+        crate::xml::parse_xml(input, options).map(Self::Document)
+    }
+
+    /// Parses input as MessagePack: a single array or map, or a stream of them written one after
+    /// the other, each of which is a record. Binary data is read as base64, and the keys of maps
+    /// that are not strings as their JSON. Strings that are not valid UTF-8 are rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{write_msgpack, Input};
+    /// use serde_json::json;
+    ///
+    /// let mut input = vec![];
+    /// write_msgpack(&json!({ "id": 1 }), &mut input).unwrap();
+    /// write_msgpack(&json!({ "id": 2 }), &mut input).unwrap();
+    /// assert_eq!(Input::parse_msgpack(&input).unwrap().records().len(), 2);
+    /// ```
+    pub fn parse_msgpack(input: &[u8]) -> Result<Self, DrivelError> {
+        crate::binary::parse_msgpack(input).map(Self::from_values)
+    }
+
+    /// Parses input as CBOR: a single array or map, or a sequence of them (RFC 8742) written one
+    /// after the other, each of which is a record. Binary data is read as base64, tagged values
+    /// as the values they hold, and the keys of maps that are not strings as their JSON. Values
+    /// JSON has no equivalent of, like unassigned simple values, are rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{write_cbor, Input};
+    /// use serde_json::json;
+    ///
+    /// let mut input = vec![];
+    /// write_cbor(&json!({ "id": 1 }), &mut input).unwrap();
+    /// assert_eq!(Input::parse_cbor(&input).unwrap(), Input::Document(json!({ "id": 1 })));
+    /// ```
+    pub fn parse_cbor(input: &[u8]) -> Result<Self, DrivelError> {
+        crate::binary::parse_cbor(input).map(Self::from_values)
+    }
+
+    /// Returns a single value as a document, and a stream of values as lines.
+    fn from_values(mut values: Vec<serde_json::Value>) -> Self {
+        if values.len() == 1 {
+            Self::Document(values.remove(0))
+        } else {
+            Self::Lines(values)
+        }
+    }
+
+    /// Returns the records of the input: the lines of JSON lines, the elements of an array at
+    /// the root of a document, or else the document itself.
+    pub fn records(&self) -> &[serde_json::Value] {
+        match self {
+            Self::Document(serde_json::Value::Array(items)) => items,
+            Self::Document(document) => std::slice::from_ref(document),
+            Self::Lines(lines) => lines,
+        }
+    }
+
+    /// Returns the input with at most `max_records` of its records: the first ones, or, if
+    /// `random`, a uniform random sample of them. The sample of an array at the root of a
+    /// document is an array of the records sampled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::Input;
+    /// use serde_json::json;
+    ///
+    /// let input = Input::Document(json!([1, 2, 3, 4]));
+    /// assert_eq!(input.sample(2, false), Input::Document(json!([1, 2])));
+    ///
+    /// let input = Input::Lines((0..100).map(|n| json!(n)).collect());
+    /// assert_eq!(input.sample(10, true).records().len(), 10);
+    /// ```
+    pub fn sample(self, max_records: usize, random: bool) -> Self {
+        let sample = |records: Vec<serde_json::Value>| {
+            if random {
+                let mut sample = vec![];
+                crate::sample::sample_into(&mut sample, 0, records, max_records);
+                sample
+            } else {
+                records.into_iter().take(max_records).collect()
+            }
+        };
+        match self {
+            Self::Document(serde_json::Value::Array(items)) => {
+                Self::Document(serde_json::Value::Array(sample(items)))
+            }
+            Self::Document(document) => Self::Document(document),
+            Self::Lines(lines) => Self::Lines(sample(lines)),
+        }
+    }
+
+    /// Infers the schema of the input like `infer_schema` does, but leaves out the records that
+    /// are left once `budget` has elapsed, so that the schema of a large input is inferred
+    /// from as many of its records as time allows. Records are inferred in chunks, of which
+    /// the first is always inferred. Returns the schema with the number of records inferred.
+    ///
+    /// An array at the root of a document is described with the length it has, however many
+    /// of its records were inferred.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use drivel::{InferenceOptions, Input, SchemaState};
+    /// use serde_json::json;
+    ///
+    /// let input = Input::Document(json!([{ "id": 1 }, { "id": 2 }]));
+    /// let (schema, n_inferred) =
+    ///     input.infer_schema_within(&InferenceOptions::default(), Duration::from_secs(1));
+    /// assert_eq!(n_inferred, 2);
+    /// assert!(matches!(schema, SchemaState::Array { min_length: 2, max_length: 2, .. }));
+    /// ```
+    pub fn infer_schema_within(
+        self,
+        options: &InferenceOptions,
+        budget: std::time::Duration,
+    ) -> (SchemaState, usize) {
+        let deadline = std::time::Instant::now() + budget;
+        match self {
+            Self::Document(serde_json::Value::Array(items)) => {
+                let length = items.len();
+                let (schema, n_inferred) =
+                    crate::infer::infer_records_until(items, options, deadline);
+                (
+                    crate::infer::root_array(length, schema, options),
+                    n_inferred,
+                )
+            }
+            Self::Document(document) => (infer_schema(document, options), 1),
+            Self::Lines(lines) => crate::infer::infer_records_until(lines, options, deadline),
+        }
+    }
+
+    /// Infers the schema of the input, like `infer_schema` does for a document and
+    /// `infer_schema_from_iter` for JSON lines.
+    pub fn infer_schema(self, options: &InferenceOptions) -> SchemaState {
+        match self {
+            Self::Document(document) => infer_schema(document, options),
+            Self::Lines(lines) => infer_schema_from_iter(lines, options),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_lines_across_chunks_in_order() {
+        let input = (0..100_000)
+            .map(|n| format!("{{\"id\": {}, \"name\": \"record {}\"}}\n", n, n))
+            .collect::<String>();
+        assert!(input.len() > LINES_CHUNK_SIZE);
+
+        let Input::Lines(lines) = Input::parse(&input).unwrap() else {
+            panic!("expected lines");
+        };
+        assert_eq!(lines.len(), 100_000);
+        assert!(lines
+            .iter()
+            .enumerate()
+            .all(|(n, line)| line["id"] == json!(n)));
+    }
+
+    #[test]
+    fn parses_values_that_span_lines_one_after_the_other() {
+        let input = "{\n  \"id\": 1\n}\n{\"id\": 2} {\"id\": 3}\n";
+        assert_eq!(
+            Input::parse(input).unwrap(),
+            Input::Lines(vec![
+                json!({ "id": 1 }),
+                json!({ "id": 2 }),
+                json!({ "id": 3 })
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_each_document_of_a_yaml_stream_as_a_record() {
+        let input = "---\nid: 1\nname: Alice\n---\nid: 2\nname: Bob\n...\n---\n- 3\n";
+        assert_eq!(
+            Input::parse_yaml(input).unwrap(),
+            Input::Lines(vec![
+                json!({ "id": 1, "name": "Alice" }),
+                json!({ "id": 2, "name": "Bob" }),
+                json!([3])
+            ])
+        );
+        assert_eq!(
+            Input::parse_yaml("- id: 1\n- id: 2\n").unwrap().records(),
+            &[json!({ "id": 1 }), json!({ "id": 2 })]
+        );
+    }
+
+    #[test]
+    fn parses_yaml_scalars_by_the_core_schema() {
+        let input = "a: yes\nb: ~\nc: null\nd: true\ne: 0x1f\ng: '12'\nh: 1.5\n";
+        assert_eq!(
+            Input::parse_yaml(input).unwrap(),
+            Input::Document(json!({
+                "a": "yes",
+                "b": null,
+                "c": null,
+                "d": true,
+                "e": 31,
+                "g": "12",
+                "h": 1.5
+            }))
+        );
+    }
+
+    #[test]
+    fn reads_back_the_yaml_written_of_values() {
+        let value = json!({
+            "answer": "yes",
+            "nothing": "~",
+            "number": "12",
+            "missing": null,
+            "tags": ["on", "off", ""],
+            "nested": { "multiline": "first\nsecond", "float": 0.5 }
+        });
+        let written = serde_yaml::to_string(&value).unwrap();
+        assert_eq!(Input::parse_yaml(&written).unwrap(), Input::Document(value));
+    }
+}
@@ -0,0 +1,2749 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+use indexmap::IndexMap;
+use serde_json::json;
+
+use crate::{
+    examples::add_examples,
+    infer::integer_value,
+    infer_string::{
+        JWT_PATTERN, KSUID_PATTERN, NON_FINITE_FLOATS, OBJECT_ID_PATTERN, SEMVER_PATTERN,
+        ULID_PATTERN,
+    },
+    produce::integer_number,
+    DateFormat, DistinctCount, DrivelError, FieldExamples, NumberType, PathKind, SchemaState,
+    StringType,
+};
+
+/// The bounds used when parsing a JSON Schema that does not constrain a value, and how its
+/// `default`s are used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaDefaults {
+    /// The range of integers, when neither `minimum` nor `maximum` are set. Default = 0..=1000.
+    pub integers: RangeInclusive<i64>,
+    /// The range of numbers, when neither `minimum` nor `maximum` are set. Default = 0..=1000.
+    pub floats: RangeInclusive<f64>,
+    /// The range of string lengths, when neither `minLength` nor `maxLength` are set. Default = 0..=32.
+    pub string_lengths: RangeInclusive<usize>,
+    /// The range of array lengths, when neither `minItems` nor `maxItems` are set. Default = 0..=16.
+    pub array_lengths: RangeInclusive<usize>,
+    /// The range of the number of keys of objects of `patternProperties`, when neither
+    /// `minProperties` nor `maxProperties` are set, and of maps, like Avro maps and JTD
+    /// `values`. Default = 1..=4.
+    pub map_lengths: RangeInclusive<usize>,
+    /// Produce optional properties that have a `default` with that value, rather than with
+    /// values adhering to their schema. Default = false.
+    pub use_defaults: bool,
+}
+
+impl Default for SchemaDefaults {
+    fn default() -> Self {
+        Self {
+            integers: 0..=1000,
+            floats: 0.0..=1000.0,
+            string_lengths: 0..=32,
+            array_lengths: 0..=16,
+            map_lengths: 1..=4,
+            use_defaults: false,
+        }
+    }
+}
+
+/// The draft of the JSON Schema specification that a schema is written against, which decides
+/// how some of its keywords are read.
+///
+/// Keywords whose meaning changed in a way that their value tells apart are read as either
+/// draft writes them, whatever the draft: a boolean `exclusiveMinimum` of Draft-04, an array of
+/// `items` rather than `prefixItems`, and the `dependencies` that became `dependentRequired`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SchemaDraft {
+    /// Draft-04, whose subschemas are identified by `id`.
+    Draft4,
+    /// Draft-06, whose subschemas are identified by `$id`.
+    Draft6,
+    /// Draft-07, whose `$ref`s replace the rest of the subschema they are in.
+    Draft7,
+    /// Draft 2019-09, whose `$ref`s apply along with the rest of the subschema they are in.
+    Draft2019_09,
+    /// Draft 2020-12, the latest draft.
+    Draft2020_12,
+}
+
+impl SchemaDraft {
+    /// Returns the draft named by the `$schema` of a schema, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::SchemaDraft;
+    /// use serde_json::json;
+    ///
+    /// let schema = json!({ "$schema": "http://json-schema.org/draft-07/schema#" });
+    /// assert_eq!(SchemaDraft::detect(&schema), Some(SchemaDraft::Draft7));
+    /// ```
+    pub fn detect(schema: &serde_json::Value) -> Option<Self> {
+        let uri = schema.get("$schema")?.as_str()?;
+        [
+            ("draft-04", SchemaDraft::Draft4),
+            ("draft-06", SchemaDraft::Draft6),
+            ("draft-07", SchemaDraft::Draft7),
+            ("2019-09", SchemaDraft::Draft2019_09),
+            ("2020-12", SchemaDraft::Draft2020_12),
+        ]
+        .into_iter()
+        .find_map(|(name, draft)| uri.contains(name).then_some(draft))
+    }
+
+    /// The keyword that identifies a subschema, so that `$ref`s can refer to it.
+    fn id_keyword(self) -> &'static str {
+        match self {
+            SchemaDraft::Draft4 => "id",
+            _ => "$id",
+        }
+    }
+}
+
+/// Keywords that describe a subschema rather than constrain its values, which do not count as
+/// the rest of a subschema with a `$ref`.
+const ANNOTATIONS: [&str; 13] = [
+    "$schema",
+    "$id",
+    "id",
+    "$anchor",
+    "$comment",
+    "$defs",
+    "definitions",
+    "title",
+    "description",
+    "examples",
+    "deprecated",
+    "readOnly",
+    "writeOnly",
+];
+
+/// An error encountered while parsing a JSON Schema, or a JSON Type Definition.
+#[derive(Debug, PartialEq)]
+pub struct JsonSchemaError {
+    /// A JSON pointer (as a URI fragment) to the offending subschema, e.g. `#/properties/age`.
+    pub pointer: String,
+    /// A description of the error.
+    pub message: String,
+}
+
+impl Display for JsonSchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.pointer, self.message)
+    }
+}
+
+impl std::error::Error for JsonSchemaError {}
+
+/// The result of parsing a JSON Schema.
+#[derive(Debug, PartialEq)]
+pub struct ParsedSchema {
+    /// The parsed schema.
+    pub schema: SchemaState,
+    /// Keywords that were ignored or only partially honoured, prefixed with the JSON pointer of
+    /// the subschema they appear in.
+    pub warnings: Vec<String>,
+}
+
+struct ParseContext<'a> {
+    root: &'a serde_json::Value,
+    defaults: &'a SchemaDefaults,
+    warnings: Vec<String>,
+    /// The `$ref`s currently being resolved, used to detect cycles.
+    ref_stack: Vec<String>,
+    /// Whether the schema is part of an OpenAPI document, whose schemas are marked `nullable`
+    /// rather than allowing `null` by their `type`.
+    openapi: bool,
+    draft: SchemaDraft,
+    /// The file that the document was read from, if `$ref`s to other files are followed.
+    file: Option<&'a std::path::Path>,
+    /// The documents of other files that `$ref`s were followed to, by their path.
+    documents: HashMap<std::path::PathBuf, std::rc::Rc<serde_json::Value>>,
+}
+
+impl ParseContext<'_> {
+    fn warn(&mut self, pointer: &str, message: &str) {
+        self.warnings.push(format!("{}: {}", pointer, message));
+    }
+}
+
+pub(crate) fn error(pointer: &str, message: impl Into<String>) -> JsonSchemaError {
+    JsonSchemaError {
+        pointer: pointer.to_owned(),
+        message: message.into(),
+    }
+}
+
+pub(crate) fn child_pointer(pointer: &str, segment: &str) -> String {
+    format!(
+        "{}/{}",
+        pointer,
+        segment.replace('~', "~0").replace('/', "~1")
+    )
+}
+
+/// The bounds of a range, which are moved without going beyond the values of their type.
+pub(crate) trait Bound: PartialOrd + Copy {
+    fn saturating_add(self, other: Self) -> Self;
+    fn saturating_sub(self, other: Self) -> Self;
+}
+
+impl Bound for usize {
+    fn saturating_add(self, other: Self) -> Self {
+        usize::saturating_add(self, other)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        usize::saturating_sub(self, other)
+    }
+}
+
+impl Bound for i128 {
+    fn saturating_add(self, other: Self) -> Self {
+        i128::saturating_add(self, other)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        i128::saturating_sub(self, other)
+    }
+}
+
+impl Bound for f64 {
+    fn saturating_add(self, other: Self) -> Self {
+        (self + other).clamp(f64::MIN, f64::MAX)
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        (self - other).clamp(f64::MIN, f64::MAX)
+    }
+}
+
+/// Resolves the bounds of a range from an optional lower and upper bound, falling back to the
+/// default range for the missing bounds. A single bound that falls outside of the default
+/// range moves the range along with it, as far as the values of its type go.
+pub(crate) fn bounds<T: Bound>(
+    min: Option<T>,
+    max: Option<T>,
+    default: &RangeInclusive<T>,
+) -> (T, T) {
+    let (default_min, default_max) = (*default.start(), *default.end());
+    let span = default_max.saturating_sub(default_min);
+    match (min, max) {
+        (Some(min), Some(max)) => (min, max),
+        (Some(min), None) if min <= default_max => (min, default_max),
+        (Some(min), None) => (min, min.saturating_add(span)),
+        (None, Some(max)) if max >= default_min => (default_min, max),
+        (None, Some(max)) => (max.saturating_sub(span), max),
+        (None, None) => (default_min, default_max),
+    }
+}
+
+fn as_usize(node: &serde_json::Map<String, serde_json::Value>, keyword: &str) -> Option<usize> {
+    node.get(keyword)
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize)
+}
+
+/// The most branches that the `dependentRequired` of an object is expanded into, beyond which
+/// the dependencies of every property are required instead.
+const MAX_DEPENDENT_BRANCHES: usize = 64;
+
+/// The major versions of semantic versions in a JSON Schema that does not give them.
+const DEFAULT_SEMVER_MAJORS: (u64, u64) = (0, 5);
+
+/// The fewest and most words of natural language in a JSON Schema that does not give them.
+const DEFAULT_TEXT_WORDS: (usize, usize) = (3, 12);
+
+/// The fewest and most directories of file paths in a JSON Schema that does not give them.
+const DEFAULT_PATH_DEPTHS: (usize, usize) = (1, 3);
+
+/// The format of phone numbers in a JSON Schema that does not give one: E.164.
+const DEFAULT_PHONE_FORMAT: &str = "+###########";
+
+fn parse_string(
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> SchemaState {
+    if let Some(name) = node.get("x-drivel-type").and_then(|name| name.as_str()) {
+        match name {
+            "ulid" => return SchemaState::String(StringType::Ulid),
+            "ksuid" => return SchemaState::String(StringType::Ksuid),
+            "objectid" => return SchemaState::String(StringType::ObjectId),
+            "date-mdy" | "date-dmy" | "date-compact" => {
+                let format = match name {
+                    "date-mdy" => DateFormat::MonthDayYear,
+                    "date-dmy" => DateFormat::DayMonthYear,
+                    _ => DateFormat::Compact,
+                };
+                return SchemaState::String(StringType::FormattedDate {
+                    format,
+                    range: None,
+                });
+            }
+            "epoch" => return SchemaState::String(StringType::EpochSeconds { range: None }),
+            "text" => {
+                let words = node.get("x-drivel-words");
+                let count = |keyword: &str| {
+                    words
+                        .and_then(|words| words.get(keyword)?.as_u64())
+                        .map(|count| count as usize)
+                };
+                let (min_words, max_words) = DEFAULT_TEXT_WORDS;
+                let min_words = count("minimum").unwrap_or(min_words);
+                return SchemaState::String(StringType::Text {
+                    min_words,
+                    max_words: count("maximum").unwrap_or(max_words).max(min_words),
+                });
+            }
+            "path" => {
+                let counts = |keyword: &str, default: &str| {
+                    let mut counts = node
+                        .get(keyword)
+                        .and_then(|names| names.as_array())
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|name| Some((name.as_str()?.to_owned(), 1)))
+                        .collect::<HashMap<_, _>>();
+                    if counts.is_empty() {
+                        counts.insert(default.to_owned(), 1);
+                    }
+                    counts
+                };
+                let roots = counts("x-drivel-roots", "/");
+                let depths = node.get("x-drivel-depth");
+                let depth = |keyword: &str| depths.and_then(|depths| depths.get(keyword)?.as_u64());
+                let (min_depth, max_depth) = DEFAULT_PATH_DEPTHS;
+                let min_depth = depth("minimum").map_or(min_depth, |depth| depth as usize);
+                return SchemaState::String(StringType::FilePath {
+                    kind: PathKind::of_root(roots.keys().next().expect("there is a root")),
+                    roots,
+                    min_depth,
+                    max_depth: depth("maximum")
+                        .map_or(max_depth, |depth| depth as usize)
+                        .max(min_depth),
+                    extensions: counts("x-drivel-extensions", ""),
+                });
+            }
+            "semver" => {
+                let majors = node.get("x-drivel-major");
+                let major = |keyword: &str| majors.and_then(|majors| majors.get(keyword)?.as_u64());
+                let (min_major, max_major) = DEFAULT_SEMVER_MAJORS;
+                let min_major = major("minimum").unwrap_or(min_major);
+                return SchemaState::String(StringType::Semver {
+                    min_major,
+                    max_major: major("maximum").unwrap_or(max_major).max(min_major),
+                });
+            }
+            "phone" => {
+                let format = node
+                    .get("x-drivel-format")
+                    .and_then(|format| format.as_str())
+                    .unwrap_or(DEFAULT_PHONE_FORMAT);
+                return SchemaState::String(StringType::PhoneNumber {
+                    formats: HashMap::from_iter([(format.to_owned(), 1)]),
+                });
+            }
+            _ => ctx.warn(
+                pointer,
+                &format!(
+                    "x-drivel-type {:?} is not supported and will be ignored",
+                    name
+                ),
+            ),
+        }
+    }
+
+    if node.get("contentEncoding") == Some(&json!("base64")) {
+        let (min_length, max_length) = bounds(
+            as_usize(node, "minLength"),
+            as_usize(node, "maxLength"),
+            &ctx.defaults.string_lengths,
+        );
+        return SchemaState::String(StringType::Base64 {
+            min_length,
+            max_length,
+        });
+    }
+
+    if let Some(pattern) = node.get("pattern") {
+        let pattern = pattern.as_str().unwrap_or_default();
+        match crate::pattern::check(pattern) {
+            Ok(()) => {
+                for keyword in ["format", "minLength", "maxLength"] {
+                    if node.contains_key(keyword) {
+                        ctx.warn(
+                            pointer,
+                            &format!("{} is ignored in favour of pattern", keyword),
+                        );
+                    }
+                }
+                return SchemaState::String(StringType::Pattern(pattern.to_owned()));
+            }
+            Err(err) => ctx.warn(
+                pointer,
+                &format!(
+                    "pattern {:?} is not supported and will be ignored: {}",
+                    pattern, err
+                ),
+            ),
+        }
+    }
+
+    if let Some(format) = node.get("format").and_then(|f| f.as_str()) {
+        let string_type = match format {
+            "date" => Some(StringType::IsoDate { range: None }),
+            "date-time" => Some(StringType::DateTimeISO8601 { range: None }),
+            "time" => Some(StringType::Time {
+                range: None,
+                seconds: true,
+            }),
+            "duration" => Some(StringType::Duration { range: None }),
+            "uuid" => Some(StringType::UUID),
+            "email" => Some(StringType::Email),
+            "uri" | "url" => Some(StringType::Url),
+            "hostname" => Some(StringType::Hostname),
+            _ => {
+                ctx.warn(
+                    pointer,
+                    &format!("format {:?} is not supported and will be ignored", format),
+                );
+                None
+            }
+        };
+        if let Some(string_type) = string_type {
+            return SchemaState::String(string_type);
+        }
+    }
+
+    let (min_length, max_length) = bounds(
+        as_usize(node, "minLength"),
+        as_usize(node, "maxLength"),
+        &ctx.defaults.string_lengths,
+    );
+    SchemaState::String(StringType::Unknown {
+        strings_seen: vec![],
+        chars_seen: HashMap::new(),
+        n_strings_seen: 0,
+        distinct: DistinctCount::new(),
+        n_chars_seen: 0,
+        min_length: Some(min_length),
+        max_length: Some(max_length),
+        prefix: String::new(),
+        suffix: String::new(),
+        structure: None,
+    })
+}
+
+/// Parses a JWT, whose claims are described by the schema under `x-drivel-claims`. Without
+/// one, the claims are an object of any shape.
+fn parse_jwt(
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let claims = match node.get("x-drivel-claims") {
+        Some(claims) => parse_node(claims, &format!("{}/x-drivel-claims", pointer), ctx)?,
+        None => SchemaState::UnconstrainedObject,
+    };
+    Ok(SchemaState::String(StringType::Jwt {
+        claims: Box::new(claims),
+    }))
+}
+
+/// Parses a string of JSON, whose schema is given by `contentSchema`. Without one, the
+/// document is an object of any shape.
+fn parse_embedded_json(
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let schema = match node.get("contentSchema") {
+        Some(schema) => parse_node(schema, &child_pointer(pointer, "contentSchema"), ctx)?,
+        None => SchemaState::UnconstrainedObject,
+    };
+    Ok(SchemaState::String(StringType::EmbeddedJson(Box::new(
+        schema,
+    ))))
+}
+
+/// Returns a bound of numbers, and whether it is exclusive: the stricter of the `inclusive` and
+/// `exclusive` keywords, if both are set. `lower` tells whether the bound is a minimum.
+///
+/// A boolean `exclusive` keyword, as in Draft-04, makes the `inclusive` keyword exclusive.
+fn number_bound(
+    node: &serde_json::Map<String, serde_json::Value>,
+    inclusive: &str,
+    exclusive: &str,
+    lower: bool,
+) -> Option<(f64, bool)> {
+    let inclusive = node.get(inclusive).and_then(|v| v.as_f64());
+    if let Some(exclusive) = node.get(exclusive).and_then(|v| v.as_bool()) {
+        return inclusive.map(|inclusive| (inclusive, exclusive));
+    }
+    let exclusive = node.get(exclusive).and_then(|v| v.as_f64());
+    match (inclusive, exclusive) {
+        (Some(inclusive), Some(exclusive)) => {
+            let stricter = if lower {
+                exclusive >= inclusive
+            } else {
+                exclusive <= inclusive
+            };
+            Some(if stricter {
+                (exclusive, true)
+            } else {
+                (inclusive, false)
+            })
+        }
+        (Some(inclusive), None) => Some((inclusive, false)),
+        (None, Some(exclusive)) => Some((exclusive, true)),
+        (None, None) => None,
+    }
+}
+
+/// The largest number of decimal places that a `multipleOf` can give numbers.
+const MAX_DECIMAL_SCALE: u32 = 6;
+
+fn parse_number(
+    node: &serde_json::Map<String, serde_json::Value>,
+    integer: bool,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    // a multipleOf of 0.01 (or another power of ten) gives numbers a fixed number of decimals
+    let scale = node
+        .get("multipleOf")
+        .and_then(|multiple| multiple.as_f64())
+        .and_then(|multiple| {
+            (1..=MAX_DECIMAL_SCALE).find(|scale| multiple == 10f64.powi(-(*scale as i32)))
+        });
+    if node.contains_key("multipleOf") && (integer || scale.is_none()) {
+        ctx.warn(pointer, "multipleOf is not supported and will be ignored");
+    }
+
+    let min = number_bound(node, "minimum", "exclusiveMinimum", true);
+    let max = number_bound(node, "maximum", "exclusiveMaximum", false);
+
+    if integer {
+        // integer bounds are read exactly where they can be, as floats cannot hold the bounds
+        // of unsigned 64-bit integers, and exclusive bounds are the integers next to them
+        let exact = |inclusive: &str, exclusive: &str, bound: Option<(f64, bool)>| {
+            let (bound, is_exclusive) = bound?;
+            let keyword = match node.get(exclusive) {
+                Some(serde_json::Value::Number(_)) if is_exclusive => exclusive,
+                _ => inclusive,
+            };
+            let exact = node
+                .get(keyword)
+                .and_then(|bound| bound.as_number())
+                .and_then(integer_value);
+            Some((exact, bound, is_exclusive))
+        };
+        // bounds beyond the range of i128 saturate, as do the integers next to them
+        let min = exact("minimum", "exclusiveMinimum", min).map(|bound| match bound {
+            (Some(exact), _, exclusive) => exact.saturating_add(i128::from(exclusive)),
+            (None, bound, true) => (bound.floor() as i128).saturating_add(1),
+            (None, bound, false) => bound.ceil() as i128,
+        });
+        let max = exact("maximum", "exclusiveMaximum", max).map(|bound| match bound {
+            (Some(exact), _, exclusive) => exact.saturating_sub(i128::from(exclusive)),
+            (None, bound, true) => (bound.ceil() as i128).saturating_sub(1),
+            (None, bound, false) => bound.floor() as i128,
+        });
+        let defaults = &ctx.defaults.integers;
+        let (min, max) = bounds(
+            min,
+            max,
+            &(i128::from(*defaults.start())..=i128::from(*defaults.end())),
+        );
+        if min > max {
+            return Err(error(pointer, "no integer lies within the bounds"));
+        }
+        Ok(SchemaState::Number(NumberType::integers(min, max)))
+    } else if let Some(scale) = scale {
+        // exclusive bounds of decimals are the multiples next to them
+        let unit = 10f64.powi(scale as i32);
+        let min = min.map(|(min, exclusive)| {
+            if exclusive {
+                ((min * unit).floor() + 1.0) / unit
+            } else {
+                min
+            }
+        });
+        let max = max.map(|(max, exclusive)| {
+            if exclusive {
+                ((max * unit).ceil() - 1.0) / unit
+            } else {
+                max
+            }
+        });
+        let (min, max) = bounds(min, max, &ctx.defaults.floats);
+        Ok(SchemaState::Number(NumberType::Decimal { scale, min, max }))
+    } else {
+        let exclusive_min = min.is_some_and(|(_, exclusive)| exclusive);
+        let exclusive_max = max.is_some_and(|(_, exclusive)| exclusive);
+        let (min, max) = bounds(
+            min.map(|(min, _)| min),
+            max.map(|(max, _)| max),
+            &ctx.defaults.floats,
+        );
+        Ok(SchemaState::Number(NumberType::Float {
+            min,
+            max,
+            non_finite: false,
+            exclusive_min,
+            exclusive_max,
+        }))
+    }
+}
+
+fn parse_array(
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let unique = node.get("uniqueItems") == Some(&json!(true));
+
+    // before Draft 2020-12, an array of items gave the elements of a tuple
+    let (prefix_keyword, rest_keyword) = match node.get("items") {
+        Some(serde_json::Value::Array(_)) => ("items", "additionalItems"),
+        _ => ("prefixItems", "items"),
+    };
+    if let Some(prefix_items) = node.get(prefix_keyword) {
+        if unique {
+            ctx.warn(
+                pointer,
+                "uniqueItems of prefixItems is not supported and will be ignored",
+            );
+        }
+        let prefix_items = prefix_items
+            .as_array()
+            .ok_or_else(|| error(pointer, "prefixItems must be an array"))?;
+        if node.get(rest_keyword).is_some_and(|items| items != false) {
+            ctx.warn(
+                pointer,
+                &format!(
+                    "{} after {} are not supported and will be ignored",
+                    rest_keyword, prefix_keyword
+                ),
+            );
+        }
+        let prefix_pointer = child_pointer(pointer, prefix_keyword);
+        let elements = prefix_items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| {
+                parse_node(item, &child_pointer(&prefix_pointer, &idx.to_string()), ctx)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(SchemaState::Tuple(elements));
+    }
+
+    let schema = match node.get("items") {
+        Some(items) => parse_node(items, &child_pointer(pointer, "items"), ctx)?,
+        None => SchemaState::Indefinite,
+    };
+    let (min_length, max_length) = bounds(
+        as_usize(node, "minItems"),
+        as_usize(node, "maxItems"),
+        &ctx.defaults.array_lengths,
+    );
+    Ok(SchemaState::Array {
+        min_length,
+        max_length,
+        unique,
+        schema: Box::new(schema),
+    })
+}
+
+/// Parses the `patternProperties` of an object without `properties` as a map whose keys match
+/// the pattern, or a union of maps if there are several patterns, each object holding keys of
+/// one pattern.
+fn parse_pattern_properties(
+    patterns: &serde_json::Value,
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let patterns = patterns
+        .as_object()
+        .ok_or_else(|| error(pointer, "patternProperties must be an object"))?;
+    let (min_length, max_length) = bounds(
+        as_usize(node, "minProperties"),
+        as_usize(node, "maxProperties"),
+        &ctx.defaults.map_lengths,
+    );
+    let patterns_pointer = child_pointer(pointer, "patternProperties");
+    let mut maps = vec![];
+    for (pattern, values) in patterns {
+        let values_pointer = child_pointer(&patterns_pointer, pattern);
+        if let Err(err) = crate::pattern::check(pattern) {
+            ctx.warn(
+                &values_pointer,
+                &format!(
+                    "pattern {:?} is not supported and will be ignored: {}",
+                    pattern, err
+                ),
+            );
+            continue;
+        }
+        maps.push(SchemaState::Map {
+            pattern: pattern.clone(),
+            min_length,
+            max_length,
+            schema: Box::new(parse_node(values, &values_pointer, ctx)?),
+        });
+    }
+    Ok(match maps.len() {
+        0 => SchemaState::UnconstrainedObject,
+        _ => union_of(maps),
+    })
+}
+
+fn parse_object(
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    if let Some(patterns) = node.get("patternProperties") {
+        if node.contains_key("properties") {
+            ctx.warn(
+                pointer,
+                "patternProperties alongside properties is not supported and will be ignored",
+            );
+        } else {
+            return parse_pattern_properties(patterns, node, pointer, ctx);
+        }
+    }
+
+    let required_keys: HashSet<&str> = node
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|r| r.iter().filter_map(|k| k.as_str()).collect())
+        .unwrap_or_default();
+
+    let mut required = IndexMap::new();
+    let mut optional = IndexMap::new();
+    if let Some(properties) = node.get("properties") {
+        let properties = properties
+            .as_object()
+            .ok_or_else(|| error(pointer, "properties must be an object"))?;
+        let properties_pointer = child_pointer(pointer, "properties");
+        for (k, v) in properties {
+            // properties with a false schema must be absent, so are never produced
+            if v == &json!(false) && !required_keys.contains(k.as_str()) {
+                continue;
+            }
+            let schema = parse_node(v, &child_pointer(&properties_pointer, k), ctx)?;
+            if required_keys.contains(k.as_str()) {
+                required.insert(k.clone(), schema);
+            } else {
+                let default = v.get("default").filter(|_| ctx.defaults.use_defaults);
+                let schema = default.map_or(schema, |default| SchemaState::Const(default.clone()));
+                optional.insert(k.clone(), schema);
+            }
+        }
+    }
+
+    Ok(SchemaState::Object { required, optional })
+}
+
+fn parse_typed(
+    node: &serde_json::Map<String, serde_json::Value>,
+    type_name: &str,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    match type_name {
+        "null" => Ok(SchemaState::Null),
+        "boolean" => Ok(SchemaState::Boolean),
+        "integer" => parse_number(node, true, pointer, ctx),
+        "number" => parse_number(node, false, pointer, ctx),
+        "string" if node.get("x-drivel-type") == Some(&json!("jwt")) => {
+            parse_jwt(node, pointer, ctx)
+        }
+        "string" if node.get("contentMediaType") == Some(&json!("application/json")) => {
+            parse_embedded_json(node, pointer, ctx)
+        }
+        "string" => Ok(parse_string(node, pointer, ctx)),
+        "array" => parse_array(node, pointer, ctx),
+        "object" => parse_object(node, pointer, ctx),
+        other => Err(error(pointer, format!("unknown type {:?}", other))),
+    }
+}
+
+/// Keywords whose values are data rather than subschemas, which are not searched for
+/// identified subschemas.
+const DATA_KEYWORDS: [&str; 5] = ["const", "default", "enum", "example", "examples"];
+
+/// Returns the subschema of `root` that `reference` identifies by its `$id` (`id` in Draft-04)
+/// or `$anchor`. Base URIs are not resolved, so an identifier matches a reference to the same
+/// URI, or to the end of its path, e.g. `address.json` matches
+/// `https://example.com/schemas/address.json`.
+fn find_identified<'a>(
+    root: &'a serde_json::Value,
+    reference: &str,
+    draft: SchemaDraft,
+) -> Option<&'a serde_json::Value> {
+    let reference = reference.trim_end_matches('#');
+    let mut pending = vec![root];
+    while let Some(value) = pending.pop() {
+        match value {
+            serde_json::Value::Object(node) => {
+                let id = node
+                    .get(draft.id_keyword())
+                    .and_then(|id| id.as_str())
+                    .map(|id| id.trim_end_matches('#'));
+                let anchor = node.get("$anchor").and_then(|anchor| anchor.as_str());
+                if id.is_some_and(|id| id == reference || id.ends_with(&format!("/{}", reference)))
+                    || anchor.is_some_and(|anchor| reference.strip_prefix('#') == Some(anchor))
+                {
+                    return Some(value);
+                }
+                pending.extend(
+                    node.iter()
+                        .filter(|(k, _)| !DATA_KEYWORDS.contains(&k.as_str()))
+                        .map(|(_, v)| v),
+                );
+            }
+            serde_json::Value::Array(items) => pending.extend(items),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the subschema of `root` that a local `reference` refers to: a JSON pointer into the
+/// document, like `#/definitions/Address`, or an identified subschema.
+fn resolve_local<'a>(
+    reference: &str,
+    root: &'a serde_json::Value,
+    draft: SchemaDraft,
+) -> Option<&'a serde_json::Value> {
+    match reference.strip_prefix('#') {
+        Some(target_pointer) if target_pointer.is_empty() || target_pointer.starts_with('/') => {
+            root.pointer(target_pointer)
+        }
+        _ => find_identified(root, reference, draft),
+    }
+}
+
+/// Parses the subschema that `reference` refers to: a subschema of the document, or, if the
+/// document was read from a file, a subschema of another file, like
+/// `./common/address.json#/definitions/Address`. `siblings` are the other keywords of the
+/// subschema with the `$ref`, which apply along with it from Draft 2019-09.
+fn parse_ref(
+    reference: &str,
+    siblings: Option<&serde_json::Map<String, serde_json::Value>>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    match resolve_local(reference, ctx.root, ctx.draft) {
+        Some(target) => parse_ref_target(reference, reference, target, siblings, pointer, ctx),
+        None if reference.starts_with('#') => Err(error(
+            pointer,
+            format!("unable to resolve $ref {:?}", reference),
+        )),
+        None => match ctx.file {
+            Some(file) if !reference.contains("://") => {
+                parse_external_ref(reference, file, siblings, pointer, ctx)
+            }
+            _ => Err(error(
+                pointer,
+                format!("only local $refs are supported, found {:?}", reference),
+            )),
+        },
+    }
+}
+
+/// Parses `target`, the subschema that `reference` refers to within the current document, as
+/// the subschema at `label`.
+fn parse_ref_target(
+    reference: &str,
+    label: &str,
+    target: &serde_json::Value,
+    siblings: Option<&serde_json::Map<String, serde_json::Value>>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    // references are told apart by the file they are resolved in, as the same pointer may refer
+    // to different subschemas of different files
+    let key = match ctx.file {
+        Some(file) => format!("{}{}", file.display(), reference),
+        None => reference.to_owned(),
+    };
+    if ctx.ref_stack.contains(&key) {
+        return Err(error(
+            pointer,
+            format!("recursive $ref {:?} is not supported", label),
+        ));
+    }
+
+    ctx.ref_stack.push(key);
+    let parsed = match (siblings, target) {
+        (Some(siblings), serde_json::Value::Object(target)) => parse_node(
+            &serde_json::Value::Object(merge_nodes(target, siblings)),
+            label,
+            ctx,
+        ),
+        _ => parse_node(target, label, ctx),
+    };
+    ctx.ref_stack.pop();
+    parsed
+}
+
+/// Parses the subschema of another file that `reference` refers to, read relative to `file`,
+/// the file of the current document. Its own `$ref`s are resolved against that other file.
+fn parse_external_ref(
+    reference: &str,
+    file: &std::path::Path,
+    siblings: Option<&serde_json::Map<String, serde_json::Value>>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let (path, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+    // subschemas of the file are pointed to past its path, e.g. `address.json#/properties/zip`
+    let label = format!("{}#{}", path, fragment);
+    let path = file.parent().unwrap_or(std::path::Path::new("")).join(path);
+    let path = path.canonicalize().unwrap_or(path);
+    let document = match ctx.documents.get(&path) {
+        Some(document) => document.clone(),
+        None => {
+            let document =
+                std::rc::Rc::new(read_document(&path).map_err(|err| {
+                    error(pointer, format!("unable to read {:?}: {}", path, err))
+                })?);
+            ctx.documents.insert(path.clone(), document.clone());
+            document
+        }
+    };
+    let fragment = format!("#{}", fragment);
+    let draft = SchemaDraft::detect(&document).unwrap_or(ctx.draft);
+    let target = resolve_local(&fragment, &document, draft)
+        .ok_or_else(|| error(pointer, format!("unable to resolve $ref {:?}", reference)))?;
+
+    let mut external = ParseContext {
+        root: &document,
+        defaults: ctx.defaults,
+        warnings: std::mem::take(&mut ctx.warnings),
+        ref_stack: std::mem::take(&mut ctx.ref_stack),
+        openapi: ctx.openapi,
+        draft,
+        file: Some(&path),
+        documents: std::mem::take(&mut ctx.documents),
+    };
+    let parsed = parse_ref_target(&fragment, &label, target, siblings, pointer, &mut external);
+    ctx.warnings = external.warnings;
+    ctx.ref_stack = external.ref_stack;
+    ctx.documents = external.documents;
+    parsed
+}
+
+/// Reads the JSON Schema document in the file at `path`, as YAML if its extension says so.
+fn read_document(path: &std::path::Path) -> Result<serde_json::Value, String> {
+    let document = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml" | "yml") => serde_yaml::from_str(&document).map_err(|err| err.to_string()),
+        _ => serde_json::from_str(&document).map_err(|err| err.to_string()),
+    }
+}
+
+fn parse_enum(variants: &serde_json::Value, pointer: &str) -> Result<SchemaState, JsonSchemaError> {
+    let variants = variants
+        .as_array()
+        .ok_or_else(|| error(pointer, "enum must be an array"))?;
+
+    let mut strings = HashSet::new();
+    let mut integers = HashSet::new();
+    let mut alternatives = vec![];
+    for variant in variants {
+        match variant {
+            serde_json::Value::Null => alternatives.push(SchemaState::Null),
+            serde_json::Value::String(s) => {
+                strings.insert(s.clone());
+            }
+            serde_json::Value::Number(n) if n.is_i64() => {
+                integers.extend(n.as_i64());
+            }
+            _ => {
+                return Err(error(
+                    pointer,
+                    "only enums of strings and integers are supported",
+                ))
+            }
+        }
+    }
+
+    // enums that mix strings and integers are a union of an enum of each
+    if !strings.is_empty() || integers.is_empty() {
+        alternatives.push(SchemaState::String(StringType::Enum { variants: strings }));
+    }
+    if !integers.is_empty() {
+        alternatives.push(SchemaState::Number(NumberType::Enum {
+            variants: integers,
+            n_seen: 0,
+        }));
+    }
+    Ok(union_of(alternatives))
+}
+
+/// Combines alternative schemas into a single schema. Null alternatives make the result
+/// nullable rather than becoming a branch of their own, and nested unions are flattened.
+pub(crate) fn union_of(alternatives: Vec<SchemaState>) -> SchemaState {
+    let mut nullable = false;
+    let mut branches = vec![];
+    let mut pending = alternatives;
+    while let Some(alternative) = pending.pop() {
+        match alternative {
+            SchemaState::Null => nullable = true,
+            SchemaState::Nullable(inner) => {
+                nullable = true;
+                pending.push(*inner);
+            }
+            SchemaState::Union(inner) => pending.extend(inner),
+            other => branches.push(other),
+        }
+    }
+    branches.reverse();
+
+    let schema = match branches.len() {
+        0 => return SchemaState::Null,
+        1 => branches.pop().unwrap(),
+        _ => SchemaState::Union(branches),
+    };
+    if nullable {
+        SchemaState::Nullable(Box::new(schema))
+    } else {
+        schema
+    }
+}
+
+fn parse_union(
+    keyword: &str,
+    branches: &serde_json::Value,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let branches = branches
+        .as_array()
+        .filter(|branches| !branches.is_empty())
+        .ok_or_else(|| error(pointer, format!("{} must be a non-empty array", keyword)))?;
+    // oneOf is treated like anyOf: a produced value matches the branch it was produced from,
+    // but is not checked against the other branches
+    let keyword_pointer = child_pointer(pointer, keyword);
+    let alternatives = branches
+        .iter()
+        .enumerate()
+        .map(|(idx, branch)| {
+            parse_node(
+                branch,
+                &child_pointer(&keyword_pointer, &idx.to_string()),
+                ctx,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(union_of(alternatives))
+}
+
+/// Merges the keywords of `other` into the schema `base`, as if both applied: properties are
+/// merged keyword by keyword, `required` keys are combined, and other keywords of `other`
+/// replace those of `base`.
+fn merge_nodes(
+    base: &serde_json::Map<String, serde_json::Value>,
+    other: &serde_json::Map<String, serde_json::Value>,
+) -> serde_json::Map<String, serde_json::Value> {
+    let mut merged = base.clone();
+    for (keyword, value) in other {
+        match (keyword.as_str(), merged.get_mut(keyword), value) {
+            (
+                "properties",
+                Some(serde_json::Value::Object(properties)),
+                serde_json::Value::Object(others),
+            ) => {
+                for (k, v) in others {
+                    match (properties.get(k), v) {
+                        (
+                            Some(serde_json::Value::Object(property)),
+                            serde_json::Value::Object(v),
+                        ) => {
+                            let property = merge_nodes(property, v);
+                            properties.insert(k.clone(), serde_json::Value::Object(property));
+                        }
+                        _ => {
+                            properties.insert(k.clone(), v.clone());
+                        }
+                    }
+                }
+            }
+            (
+                "required",
+                Some(serde_json::Value::Array(required)),
+                serde_json::Value::Array(others),
+            ) => {
+                for k in others {
+                    if !required.contains(k) {
+                        required.push(k.clone());
+                    }
+                }
+            }
+            _ => {
+                merged.insert(keyword.clone(), value.clone());
+            }
+        }
+    }
+    merged
+}
+
+/// Returns `base` narrowed so that its values do not match `condition`, if a property that the
+/// condition sets to a `const` or `enum` can be given other values: those of the `enum` of the
+/// property in `base` that are left, or the other boolean. The property becomes required, as an
+/// absent property would match the condition.
+fn negated(
+    base: &serde_json::Map<String, serde_json::Value>,
+    condition: &serde_json::Map<String, serde_json::Value>,
+) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let conditions = condition.get("properties")?.as_object()?;
+    let properties = base.get("properties")?.as_object()?;
+    conditions.iter().find_map(|(k, condition)| {
+        let matching = match (condition.get("const"), condition.get("enum")) {
+            (Some(value), _) => vec![value.clone()],
+            (None, Some(serde_json::Value::Array(variants))) => variants.clone(),
+            _ => return None,
+        };
+        let property = properties.get(k)?;
+        let remaining = match (property.get("enum"), property.get("type")) {
+            (Some(serde_json::Value::Array(variants)), _) => variants
+                .iter()
+                .filter(|variant| !matching.contains(variant))
+                .cloned()
+                .collect(),
+            (None, Some(serde_json::Value::String(type_name))) if type_name == "boolean" => {
+                [json!(true), json!(false)]
+                    .into_iter()
+                    .filter(|variant| !matching.contains(variant))
+                    .collect()
+            }
+            _ => vec![],
+        };
+        if remaining.is_empty() {
+            return None;
+        }
+        let narrowing = json!({ "properties": { k: { "enum": remaining } }, "required": [k] });
+        Some(merge_nodes(base, narrowing.as_object()?))
+    })
+}
+
+/// Parses a schema with `if`, `then` and `else` as a union of the schema with both `if` and
+/// `then` applied, and the schema with `else` applied to values that do not match `if`.
+fn parse_conditional(
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let mut base = node.clone();
+    let mut subschema = |keyword: &str| match base.remove(keyword) {
+        Some(serde_json::Value::Object(subschema)) => Ok(subschema),
+        Some(serde_json::Value::Bool(true)) | None => Ok(serde_json::Map::new()),
+        Some(_) => Err(error(
+            &child_pointer(pointer, keyword),
+            format!("{} must be an object", keyword),
+        )),
+    };
+    let (condition, then, otherwise) = (subschema("if")?, subschema("then")?, subschema("else")?);
+
+    let then = merge_nodes(&merge_nodes(&base, &condition), &then);
+    let mut alternatives = vec![parse_node(&serde_json::Value::Object(then), pointer, ctx)?];
+    match negated(&base, &condition) {
+        Some(negated) => {
+            let otherwise = merge_nodes(&negated, &otherwise);
+            alternatives.push(parse_node(
+                &serde_json::Value::Object(otherwise),
+                pointer,
+                ctx,
+            )?);
+        }
+        None => ctx.warn(
+            pointer,
+            "only values matching if are produced, as its condition cannot be negated",
+        ),
+    }
+    Ok(union_of(alternatives))
+}
+
+/// Parses an object with `dependentRequired` as a union of objects that either leave out a
+/// property with dependencies, or have it along with the properties it depends on.
+///
+/// The `dependencies` of drafts before 2019-09 are read as `dependentRequired` where they list
+/// properties.
+fn parse_dependent_required(
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let mut base = node.clone();
+    let mut dependencies = serde_json::Map::new();
+    for keyword in ["dependentRequired", "dependencies"] {
+        match base.remove(keyword) {
+            Some(serde_json::Value::Object(entries)) => {
+                for (k, dependents) in entries {
+                    if dependents.is_array() {
+                        dependencies.insert(k, dependents);
+                    } else if keyword == "dependencies" {
+                        ctx.warn(
+                            &child_pointer(&child_pointer(pointer, keyword), &k),
+                            "dependencies on schemas are not supported and will be ignored",
+                        );
+                    } else {
+                        return Err(error(
+                            &child_pointer(&child_pointer(pointer, keyword), &k),
+                            "the dependencies of dependentRequired must be an array",
+                        ));
+                    }
+                }
+            }
+            Some(_) => {
+                return Err(error(
+                    &child_pointer(pointer, keyword),
+                    format!("{} must be an object", keyword),
+                ))
+            }
+            None => {}
+        }
+    }
+    let is_required = |k: &str| {
+        base.get("required")
+            .and_then(|required| required.as_array())
+            .is_some_and(|required| required.iter().any(|r| r == k))
+    };
+
+    // properties that are required anyway always have their dependencies
+    let (required, optional): (Vec<_>, Vec<_>) =
+        dependencies.into_iter().partition(|(k, _)| is_required(k));
+    for (_, dependents) in required {
+        base = merge_nodes(
+            &base,
+            json!({ "required": dependents }).as_object().unwrap(),
+        );
+    }
+    if optional.is_empty() {
+        return parse_node(&serde_json::Value::Object(base), pointer, ctx);
+    }
+    if 2usize.saturating_pow(optional.len() as u32) > MAX_DEPENDENT_BRANCHES {
+        ctx.warn(
+            pointer,
+            "the dependencies of dependentRequired are always required, as there are too many to combine",
+        );
+        for (_, dependents) in optional {
+            base = merge_nodes(
+                &base,
+                json!({ "required": dependents }).as_object().unwrap(),
+            );
+        }
+        return parse_node(&serde_json::Value::Object(base), pointer, ctx);
+    }
+
+    let mut branches = vec![base];
+    for (k, dependents) in optional {
+        branches = branches
+            .into_iter()
+            .flat_map(|branch| {
+                let mut without = branch.clone();
+                if let Some(serde_json::Value::Object(properties)) = without.get_mut("properties") {
+                    properties.remove(&k);
+                }
+                let mut required = vec![json!(k)];
+                required.extend(dependents.as_array().into_iter().flatten().cloned());
+                let with = merge_nodes(
+                    &branch,
+                    json!({ "required": required }).as_object().unwrap(),
+                );
+                [without, with]
+            })
+            .collect();
+    }
+    let alternatives = branches
+        .into_iter()
+        .map(|branch| parse_node(&serde_json::Value::Object(branch), pointer, ctx))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(union_of(alternatives))
+}
+
+fn parse_node(
+    node: &serde_json::Value,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let node = match node {
+        serde_json::Value::Bool(true) => return Ok(SchemaState::Indefinite),
+        serde_json::Value::Bool(false) => {
+            return Err(error(pointer, "a false schema does not allow any value"))
+        }
+        serde_json::Value::Object(node) => node,
+        _ => return Err(error(pointer, "a schema must be an object or a boolean")),
+    };
+
+    if ctx.openapi && node.get("nullable") == Some(&json!(true)) {
+        let mut node = node.clone();
+        node.remove("nullable");
+        let schema = parse_node(&serde_json::Value::Object(node), pointer, ctx)?;
+        return Ok(union_of(vec![schema, SchemaState::Null]));
+    }
+
+    if let Some(reference) = node.get("$ref") {
+        let reference = reference
+            .as_str()
+            .ok_or_else(|| error(pointer, "$ref must be a string"))?;
+        // until Draft 2019-09, the rest of a subschema with a $ref is ignored
+        let siblings = node
+            .iter()
+            .filter(|(k, _)| *k != "$ref" && !ANNOTATIONS.contains(&k.as_str()))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<serde_json::Map<_, _>>();
+        let siblings =
+            (ctx.draft >= SchemaDraft::Draft2019_09 && !siblings.is_empty()).then_some(siblings);
+        return parse_ref(reference, siblings.as_ref(), pointer, ctx);
+    }
+    if node.contains_key("if") {
+        return parse_conditional(node, pointer, ctx);
+    }
+    if node.contains_key("dependentRequired") || node.contains_key("dependencies") {
+        return parse_dependent_required(node, pointer, ctx);
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(branches) = node.get(keyword) {
+            return parse_union(keyword, branches, pointer, ctx);
+        }
+    }
+    if node.contains_key("allOf") {
+        return Err(error(pointer, "allOf is not supported"));
+    }
+    if let Some(value) = node.get("const") {
+        return Ok(SchemaState::Const(value.clone()));
+    }
+    if let Some(variants) = node.get("enum") {
+        return match parse_enum(variants, pointer)? {
+            SchemaState::String(StringType::Enum { variants })
+                if node.get("x-drivel-type") == Some(&json!("currency")) =>
+            {
+                Ok(SchemaState::String(StringType::CurrencyCode {
+                    codes: variants,
+                }))
+            }
+            schema => Ok(schema),
+        };
+    }
+
+    match node.get("type") {
+        Some(serde_json::Value::String(type_name)) => parse_typed(node, type_name, pointer, ctx),
+        Some(serde_json::Value::Array(type_names)) => {
+            let type_names = type_names
+                .iter()
+                .map(|t| t.as_str())
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| error(pointer, "type must be a string or an array of strings"))?;
+            let alternatives = type_names
+                .iter()
+                .map(|type_name| parse_typed(node, type_name, pointer, ctx))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(union_of(alternatives))
+        }
+        Some(_) => Err(error(
+            pointer,
+            "type must be a string or an array of strings",
+        )),
+        None if node.contains_key("properties") => parse_object(node, pointer, ctx),
+        None if node.contains_key("items") || node.contains_key("prefixItems") => {
+            parse_array(node, pointer, ctx)
+        }
+        None => Ok(SchemaState::Indefinite),
+    }
+}
+
+/// Parses a JSON Schema document into a SchemaState, so that data can be produced from it.
+///
+/// Values that the schema does not constrain (e.g. an integer without `minimum` and `maximum`)
+/// are bounded by `defaults`. Local `$ref`s (e.g. `#/$defs/Address`) are resolved. Keywords
+/// that drivel cannot represent are either reported as warnings or, if ignoring them would
+/// produce wrong data, as an error.
+///
+/// The schema is read as the draft its `$schema` names, or as Draft 2020-12 if it names none;
+/// see [`parse_json_schema_with_draft`] to choose the draft.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{parse_json_schema, NumberType, SchemaDefaults, SchemaState};
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "type": "object",
+///     "properties": {
+///         "age": { "type": "integer", "minimum": 18 }
+///     },
+///     "required": ["age"]
+/// });
+///
+/// let parsed = parse_json_schema(&schema, &SchemaDefaults::default()).unwrap();
+///
+/// let SchemaState::Object { required, .. } = parsed.schema else { panic!() };
+/// assert_eq!(
+///     required["age"],
+///     SchemaState::Number(NumberType::Integer { min: 18, max: 1000 })
+/// );
+/// ```
+pub fn parse_json_schema(
+    schema: &serde_json::Value,
+    defaults: &SchemaDefaults,
+) -> Result<ParsedSchema, DrivelError> {
+    parse_json_schema_with_draft(schema, defaults, None)
+}
+
+/// Parses a JSON Schema document like [`parse_json_schema`], reading it as `draft` rather than
+/// the draft its `$schema` names. `None` reads it as the draft its `$schema` names.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{parse_json_schema_with_draft, SchemaDefaults, SchemaDraft, SchemaState};
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "definitions": { "name": { "type": "string" } },
+///     "$ref": "#/definitions/name",
+///     "type": "integer"
+/// });
+///
+/// // until Draft 2019-09, the keywords alongside a $ref are ignored
+/// let parsed =
+///     parse_json_schema_with_draft(&schema, &SchemaDefaults::default(), Some(SchemaDraft::Draft7))
+///         .unwrap();
+/// assert!(matches!(parsed.schema, SchemaState::String(_)));
+/// ```
+pub fn parse_json_schema_with_draft(
+    schema: &serde_json::Value,
+    defaults: &SchemaDefaults,
+    draft: Option<SchemaDraft>,
+) -> Result<ParsedSchema, DrivelError> {
+    let draft = draft
+        .or_else(|| SchemaDraft::detect(schema))
+        .unwrap_or(SchemaDraft::Draft2020_12);
+    parse_subschema(schema, "#", defaults, false, draft, None)
+}
+
+/// Parses a JSON Schema document like [`parse_json_schema_with_draft`], following `$ref`s to
+/// subschemas of other files, like `./common/address.json#/definitions/Address`. `path` is the
+/// file the document was read from, which the files it refers to are relative to, as are the
+/// files that those refer to. Files with a `.yaml` or `.yml` extension are read as YAML.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{parse_json_schema_with_external_refs, SchemaDefaults, SchemaState};
+/// use serde_json::json;
+///
+/// let dir = std::env::temp_dir().join("drivel-external-refs-example");
+/// std::fs::create_dir_all(dir.join("common")).unwrap();
+/// std::fs::write(
+///     dir.join("common/name.json"),
+///     r#"{ "definitions": { "Name": { "type": "string" } } }"#,
+/// )
+/// .unwrap();
+///
+/// let schema = json!({ "$ref": "./common/name.json#/definitions/Name" });
+/// let parsed = parse_json_schema_with_external_refs(
+///     &schema,
+///     &dir.join("schema.json"),
+///     &SchemaDefaults::default(),
+///     None,
+/// )
+/// .unwrap();
+/// assert!(matches!(parsed.schema, SchemaState::String(_)));
+/// ```
+pub fn parse_json_schema_with_external_refs(
+    schema: &serde_json::Value,
+    path: &std::path::Path,
+    defaults: &SchemaDefaults,
+    draft: Option<SchemaDraft>,
+) -> Result<ParsedSchema, DrivelError> {
+    let draft = draft
+        .or_else(|| SchemaDraft::detect(schema))
+        .unwrap_or(SchemaDraft::Draft2020_12);
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+    parse_subschema(schema, "#", defaults, false, draft, Some(&path))
+}
+
+/// Parses the subschema of `document` at `pointer` (a JSON pointer as a URI fragment), resolving
+/// `$ref`s against the whole document, and against other files if `file` is the file it was
+/// read from. `openapi` reads the `nullable` keyword of OpenAPI 3.0.
+pub(crate) fn parse_subschema(
+    document: &serde_json::Value,
+    pointer: &str,
+    defaults: &SchemaDefaults,
+    openapi: bool,
+    draft: SchemaDraft,
+    file: Option<&std::path::Path>,
+) -> Result<ParsedSchema, DrivelError> {
+    let node = pointer
+        .strip_prefix('#')
+        .and_then(|target| document.pointer(target))
+        .ok_or_else(|| error(pointer, "no schema found"))?;
+    let mut ctx = ParseContext {
+        root: document,
+        defaults,
+        warnings: vec![],
+        ref_stack: vec![],
+        openapi,
+        draft,
+        file,
+        documents: HashMap::new(),
+    };
+    let schema = parse_node(node, pointer, &mut ctx)?;
+    Ok(ParsedSchema {
+        schema,
+        warnings: ctx.warnings,
+    })
+}
+
+fn string_to_json_schema(string_type: &StringType) -> serde_json::Value {
+    let format = match string_type {
+        StringType::Unknown {
+            min_length,
+            max_length,
+            ..
+        } => {
+            // the lengths of strings that share a structure follow from their pattern
+            if let Some(structure) = string_type.structure_pattern() {
+                return json!({ "type": "string", "pattern": format!("^{}$", structure) });
+            }
+            let mut schema = json!({ "type": "string" });
+            if let Some(min_length) = min_length {
+                schema["minLength"] = json!(min_length);
+            }
+            if let Some(max_length) = max_length {
+                schema["maxLength"] = json!(max_length);
+            }
+            return schema;
+        }
+        StringType::Pattern(pattern) => return json!({ "type": "string", "pattern": pattern }),
+        StringType::Base64 {
+            min_length,
+            max_length,
+        } => {
+            return json!({
+                "type": "string",
+                "contentEncoding": "base64",
+                "minLength": min_length,
+                "maxLength": max_length
+            });
+        }
+        StringType::CurrencyCode { codes } => {
+            let mut codes = codes.iter().collect::<Vec<_>>();
+            codes.sort();
+            return json!({ "type": "string", "enum": codes, "x-drivel-type": "currency" });
+        }
+        // JSON Schema has no formats for these identifiers, so they are described by their
+        // pattern, and named by an extension keyword that drivel reads back
+        StringType::PhoneNumber { .. } => {
+            let format = string_type.phone_format().unwrap_or(DEFAULT_PHONE_FORMAT);
+            let pattern = format
+                .chars()
+                .map(|c| match c {
+                    '#' => r"\d".to_owned(),
+                    c => regex::escape(&c.to_string()),
+                })
+                .collect::<String>();
+            return json!({
+                "type": "string",
+                "pattern": format!("^{}$", pattern),
+                "x-drivel-type": "phone",
+                "x-drivel-format": format
+            });
+        }
+        StringType::Semver {
+            min_major,
+            max_major,
+        } => {
+            return json!({
+                "type": "string",
+                "pattern": SEMVER_PATTERN,
+                "x-drivel-type": "semver",
+                "x-drivel-major": { "minimum": min_major, "maximum": max_major }
+            });
+        }
+        StringType::EmbeddedJson(schema) => {
+            return json!({
+                "type": "string",
+                "contentMediaType": "application/json",
+                "contentSchema": to_json_schema_inner(schema)
+            });
+        }
+        StringType::Jwt { claims } => {
+            return json!({
+                "type": "string",
+                "pattern": JWT_PATTERN,
+                "x-drivel-type": "jwt",
+                "x-drivel-claims": to_json_schema_inner(claims)
+            });
+        }
+        StringType::Ulid | StringType::Ksuid | StringType::ObjectId => {
+            let (pattern, name) = match string_type {
+                StringType::Ulid => (ULID_PATTERN, "ulid"),
+                StringType::Ksuid => (KSUID_PATTERN, "ksuid"),
+                _ => (OBJECT_ID_PATTERN, "objectid"),
+            };
+            return json!({ "type": "string", "pattern": pattern, "x-drivel-type": name });
+        }
+        StringType::FormattedDate { format, .. } => {
+            return json!({
+                "type": "string",
+                "pattern": format.pattern(),
+                "x-drivel-type": format.name()
+            });
+        }
+        StringType::FilePath {
+            kind,
+            roots,
+            min_depth,
+            max_depth,
+            extensions,
+        } => {
+            let sorted = |counts: &HashMap<String, usize>| {
+                let mut keys = counts.keys().cloned().collect::<Vec<_>>();
+                keys.sort();
+                keys
+            };
+            return json!({
+                "type": "string",
+                "pattern": kind.pattern(),
+                "x-drivel-type": "path",
+                "x-drivel-roots": sorted(roots),
+                "x-drivel-depth": { "minimum": min_depth, "maximum": max_depth },
+                "x-drivel-extensions": sorted(extensions)
+            });
+        }
+        StringType::Text {
+            min_words,
+            max_words,
+        } => {
+            return json!({
+                "type": "string",
+                "x-drivel-type": "text",
+                "x-drivel-words": { "minimum": min_words, "maximum": max_words }
+            });
+        }
+        StringType::EpochSeconds { .. } => {
+            return json!({ "type": "string", "pattern": "^[0-9]{10}$", "x-drivel-type": "epoch" });
+        }
+        StringType::Enum { variants } => {
+            let mut variants = variants.iter().collect::<Vec<_>>();
+            variants.sort();
+            return json!({ "type": "string", "enum": variants });
+        }
+        StringType::NonFiniteFloat => {
+            return json!({ "type": "string", "enum": NON_FINITE_FLOATS });
+        }
+        StringType::Tally { format, .. } => return string_to_json_schema(format),
+        // JSON Schema has no format for RFC 2822 dates
+        StringType::DateTimeRFC2822 { .. } => return json!({ "type": "string" }),
+        StringType::IsoDate { .. } => "date",
+        StringType::DateTimeISO8601 { .. } => "date-time",
+        StringType::Time { .. } => "time",
+        StringType::Duration { .. } => "duration",
+        StringType::UUID => "uuid",
+        StringType::Email => "email",
+        StringType::Url => "uri",
+        StringType::Hostname => "hostname",
+    };
+    json!({ "type": "string", "format": format })
+}
+
+pub(crate) fn to_json_schema_inner(schema: &SchemaState) -> serde_json::Value {
+    match schema {
+        SchemaState::Initial | SchemaState::Indefinite => json!({}),
+        SchemaState::Null => json!({ "type": "null" }),
+        SchemaState::Nullable(inner) => {
+            let mut inner = to_json_schema_inner(inner);
+            if let Some(serde_json::Value::Array(branches)) = inner.get_mut("anyOf") {
+                branches.push(json!({ "type": "null" }));
+                return inner;
+            }
+            match inner.get("type").and_then(|t| t.as_str()) {
+                Some(type_name) => {
+                    inner["type"] = json!([type_name, "null"]);
+                    if let Some(serde_json::Value::Array(variants)) = inner.get_mut("enum") {
+                        variants.push(serde_json::Value::Null);
+                    }
+                    inner
+                }
+                None => json!({ "anyOf": [inner, { "type": "null" }] }),
+            }
+        }
+        SchemaState::String(string_type) => string_to_json_schema(string_type),
+        SchemaState::Number(NumberType::Integer { min, max }) => {
+            json!({ "type": "integer", "minimum": min, "maximum": max })
+        }
+        SchemaState::Number(NumberType::BigInteger { min, max }) => json!({
+            "type": "integer",
+            "minimum": integer_number(*min),
+            "maximum": integer_number(*max)
+        }),
+        SchemaState::Number(NumberType::Enum { variants, .. }) => {
+            let mut variants = variants.iter().collect::<Vec<_>>();
+            variants.sort();
+            json!({ "type": "integer", "enum": variants })
+        }
+        SchemaState::Number(NumberType::Decimal { scale, min, max }) => json!({
+            "type": "number",
+            "minimum": min,
+            "maximum": max,
+            "multipleOf": 10f64.powi(-(*scale as i32))
+        }),
+        SchemaState::Number(NumberType::Float {
+            min,
+            max,
+            non_finite,
+            exclusive_min,
+            exclusive_max,
+        }) => {
+            let mut number = serde_json::Map::new();
+            number.insert("type".to_owned(), json!("number"));
+            let min_keyword = if *exclusive_min {
+                "exclusiveMinimum"
+            } else {
+                "minimum"
+            };
+            number.insert(min_keyword.to_owned(), json!(min));
+            let max_keyword = if *exclusive_max {
+                "exclusiveMaximum"
+            } else {
+                "maximum"
+            };
+            number.insert(max_keyword.to_owned(), json!(max));
+            let number = serde_json::Value::Object(number);
+            if *non_finite {
+                json!({
+                    "anyOf": [
+                        number,
+                        string_to_json_schema(&StringType::NonFiniteFloat)
+                    ]
+                })
+            } else {
+                number
+            }
+        }
+        SchemaState::Boolean => json!({ "type": "boolean" }),
+        SchemaState::UnconstrainedArray => json!({ "type": "array" }),
+        SchemaState::UnconstrainedObject => json!({ "type": "object" }),
+        SchemaState::Const(value) => json!({ "const": value }),
+        SchemaState::Array {
+            min_length,
+            max_length,
+            unique,
+            schema,
+        } => {
+            let mut array = json!({
+                "type": "array",
+                "minItems": min_length,
+                "maxItems": max_length
+            });
+            if !matches!(
+                schema.as_ref(),
+                SchemaState::Initial | SchemaState::Indefinite
+            ) {
+                array["items"] = to_json_schema_inner(schema);
+            }
+            if *unique {
+                array["uniqueItems"] = json!(true);
+            }
+            array
+        }
+        SchemaState::Tuple(elements) => json!({
+            "type": "array",
+            "prefixItems": elements.iter().map(to_json_schema_inner).collect::<Vec<_>>(),
+            "items": false,
+            "minItems": elements.len(),
+            "maxItems": elements.len()
+        }),
+        SchemaState::Union(branches) => json!({
+            "anyOf": branches.iter().map(to_json_schema_inner).collect::<Vec<_>>()
+        }),
+        SchemaState::Map {
+            pattern,
+            min_length,
+            max_length,
+            schema,
+        } => json!({
+            "type": "object",
+            "patternProperties": { pattern.as_str(): to_json_schema_inner(schema) },
+            "additionalProperties": false,
+            "minProperties": min_length,
+            "maxProperties": max_length
+        }),
+        SchemaState::Object { required, optional } => {
+            let properties = required
+                .iter()
+                .chain(optional)
+                .map(|(k, v)| (k.clone(), to_json_schema_inner(v)))
+                .collect::<serde_json::Map<_, _>>();
+            let required = required.keys().collect::<Vec<_>>();
+            json!({
+                "type": "object",
+                "properties": properties,
+                "required": required
+            })
+        }
+    }
+}
+
+/// Counts the object schemas (with properties) within `schema`, by their JSON, along with the
+/// name of the property that each was first found under. `schema` itself is not counted if it
+/// is the `root` of the document.
+fn count_objects(
+    schema: &serde_json::Value,
+    name: &str,
+    root: bool,
+    counts: &mut IndexMap<String, (usize, String)>,
+) {
+    match schema {
+        serde_json::Value::Object(node) => {
+            let has_properties = node
+                .get("properties")
+                .and_then(|properties| properties.as_object())
+                .is_some_and(|properties| !properties.is_empty());
+            if !root && has_properties {
+                counts
+                    .entry(schema.to_string())
+                    .or_insert_with(|| (0, name.to_owned()))
+                    .0 += 1;
+            }
+            for (k, v) in node {
+                match (k.as_str(), v) {
+                    (k, _) if DATA_KEYWORDS.contains(&k) => {}
+                    ("properties" | "patternProperties", serde_json::Value::Object(properties)) => {
+                        for (property, subschema) in properties {
+                            count_objects(subschema, property, false, counts);
+                        }
+                    }
+                    _ => count_objects(v, name, false, counts),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                count_objects(item, name, false, counts);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces `schema`, or the subschemas within it, that are `definition` with `reference`.
+fn replace_objects(
+    schema: &mut serde_json::Value,
+    definition: &serde_json::Value,
+    reference: &serde_json::Value,
+) {
+    if schema == definition {
+        *schema = reference.clone();
+        return;
+    }
+    match schema {
+        serde_json::Value::Object(node) => {
+            for (k, v) in node.iter_mut() {
+                match (k.as_str(), v) {
+                    (k, _) if DATA_KEYWORDS.contains(&k) => {}
+                    ("properties" | "patternProperties", serde_json::Value::Object(properties)) => {
+                        for subschema in properties.values_mut() {
+                            replace_objects(subschema, definition, reference);
+                        }
+                    }
+                    (_, v) => replace_objects(v, definition, reference),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                replace_objects(item, definition, reference);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Moves the object schemas that appear more than once in `document` to its `$defs`, named
+/// after the property they were first found under, and refers to them with `$ref`s. The largest
+/// are moved first, so that the objects repeated within them are only counted once.
+fn define_repeated_objects(document: &mut serde_json::Value) {
+    let mut definitions = serde_json::Map::new();
+    loop {
+        let mut counts = IndexMap::new();
+        count_objects(document, "", true, &mut counts);
+        for (name, definition) in &definitions {
+            count_objects(definition, name, true, &mut counts);
+        }
+        // the first of the largest, so that names follow the order of the document
+        let Some((json, (_, name))) = counts
+            .into_iter()
+            .rev()
+            .filter(|(_, (count, _))| *count > 1)
+            .max_by_key(|(json, _)| json.len())
+        else {
+            break;
+        };
+
+        let name = match crate::codegen::pascal_case(&name)
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+        {
+            name if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) => {
+                format!("Object{}", name)
+            }
+            name => name,
+        };
+        let name = (1..)
+            .map(|n| match n {
+                1 => name.clone(),
+                n => format!("{}{}", name, n),
+            })
+            .find(|name| !definitions.contains_key(name))
+            .unwrap();
+        let reference = json!({ "$ref": child_pointer("#/$defs", &name) });
+        let definition = serde_json::from_str(&json).unwrap();
+        replace_objects(document, &definition, &reference);
+        for other in definitions.values_mut() {
+            replace_objects(other, &definition, &reference);
+        }
+        definitions.insert(name, definition);
+    }
+    if !definitions.is_empty() {
+        document["$defs"] = serde_json::Value::Object(definitions);
+    }
+}
+
+/// Exports a schema as a JSON Schema (draft 2020-12) document.
+///
+/// The inferred ranges of numbers and of string and array lengths become `minimum`/`maximum`,
+/// `minLength`/`maxLength` and `minItems`/`maxItems`, so that producing data from the exported
+/// document with `parse_json_schema` stays within the same bounds. Objects of the same shape
+/// that appear in several places are written once, under `$defs`, and referred to with `$ref`.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{to_json_schema, SchemaState, StringType};
+/// use serde_json::json;
+///
+/// let schema = SchemaState::Nullable(Box::new(SchemaState::String(StringType::UUID)));
+///
+/// assert_eq!(
+///     to_json_schema(&schema),
+///     json!({
+///         "$schema": "https://json-schema.org/draft/2020-12/schema",
+///         "type": ["string", "null"],
+///         "format": "uuid"
+///     })
+/// );
+/// ```
+pub fn to_json_schema(schema: &SchemaState) -> serde_json::Value {
+    to_json_schema_with_examples(schema, &[])
+}
+
+/// Exports a schema as a JSON Schema (draft 2020-12) document, like `to_json_schema`, with the
+/// values found at each path (see `field_examples`) as the `examples` of its subschema. The
+/// paths are relative to the root of `schema`.
+pub fn to_json_schema_with_examples(
+    schema: &SchemaState,
+    examples: &[FieldExamples],
+) -> serde_json::Value {
+    let mut document = to_json_schema_inner(schema);
+    add_examples(&mut document, examples);
+    define_repeated_objects(&mut document);
+    match document {
+        // the dialect comes first, as keywords are written in the order they are inserted
+        serde_json::Value::Object(map) => {
+            let mut document = serde_json::Map::new();
+            document.insert(
+                "$schema".to_owned(),
+                json!("https://json-schema.org/draft/2020-12/schema"),
+            );
+            document.extend(map);
+            serde_json::Value::Object(document)
+        }
+        document => document,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(schema: serde_json::Value) -> Result<ParsedSchema, DrivelError> {
+        parse_json_schema(&schema, &SchemaDefaults::default())
+    }
+
+    #[test]
+    fn parses_object_with_required_and_optional_fields() {
+        let parsed = parse(json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "format": "uuid" },
+                "name": { "type": ["string", "null"], "minLength": 1, "maxLength": 10 },
+                "tags": { "type": "array", "items": { "enum": ["a", "b"] }, "maxItems": 3 }
+            },
+            "required": ["id"]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            parsed.schema,
+            SchemaState::Object {
+                required: IndexMap::from_iter([(
+                    "id".to_owned(),
+                    SchemaState::String(StringType::UUID)
+                )]),
+                optional: IndexMap::from_iter([
+                    (
+                        "name".to_owned(),
+                        SchemaState::Nullable(Box::new(SchemaState::String(StringType::Unknown {
+                            strings_seen: vec![],
+                            chars_seen: HashMap::new(),
+                            n_strings_seen: 0,
+                            distinct: DistinctCount::new(),
+                            n_chars_seen: 0,
+                            min_length: Some(1),
+                            max_length: Some(10),
+                            prefix: String::new(),
+                            suffix: String::new(),
+                            structure: None,
+                        })))
+                    ),
+                    (
+                        "tags".to_owned(),
+                        SchemaState::Array {
+                            min_length: 0,
+                            max_length: 3,
+                            unique: false,
+                            schema: Box::new(SchemaState::String(StringType::Enum {
+                                variants: HashSet::from_iter(["a".to_owned(), "b".to_owned()])
+                            }))
+                        }
+                    )
+                ])
+            }
+        );
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn parses_constants_and_optionally_uses_defaults() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "version": { "const": 2 },
+                "active": { "const": true },
+                "theme": { "type": "string", "enum": ["dark", "light"], "default": "light" }
+            },
+            "required": ["version", "active"]
+        });
+
+        let SchemaState::Object { required, optional } = parse(schema.clone()).unwrap().schema
+        else {
+            panic!("expected an object");
+        };
+        assert_eq!(required["version"], SchemaState::Const(json!(2)));
+        assert_eq!(required["active"], SchemaState::Const(json!(true)));
+        assert_eq!(
+            to_json_schema_inner(&required["active"]),
+            json!({ "const": true })
+        );
+        assert!(matches!(
+            optional["theme"],
+            SchemaState::String(StringType::Enum { .. })
+        ));
+
+        let defaults = SchemaDefaults {
+            use_defaults: true,
+            ..Default::default()
+        };
+        let SchemaState::Object { optional, .. } =
+            parse_json_schema(&schema, &defaults).unwrap().schema
+        else {
+            panic!("expected an object");
+        };
+        assert_eq!(optional["theme"], SchemaState::Const(json!("light")));
+    }
+
+    #[test]
+    fn moves_configured_defaults_without_overflowing() {
+        let defaults = SchemaDefaults {
+            string_lengths: 5..=32,
+            array_lengths: 5..=32,
+            ..Default::default()
+        };
+        let parse = |schema| parse_json_schema(&schema, &defaults).unwrap().schema;
+
+        assert!(matches!(
+            parse(json!({ "type": "string", "maxLength": 3 })),
+            SchemaState::String(StringType::Unknown {
+                min_length: Some(0),
+                max_length: Some(3),
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse(json!({ "type": "array", "items": { "type": "boolean" }, "minItems": u64::MAX })),
+            SchemaState::Array { min_length, max_length, .. }
+                if min_length == u64::MAX as usize && max_length == usize::MAX
+        ));
+        assert!(matches!(
+            parse(json!({ "type": "number", "minimum": f64::MAX })),
+            SchemaState::Number(NumberType::Float { min, max, .. }) if min == f64::MAX && max == f64::MAX
+        ));
+    }
+
+    #[test]
+    fn applies_configured_defaults() {
+        let defaults = SchemaDefaults {
+            integers: -5..=5,
+            floats: 0.0..=1.0,
+            string_lengths: 2..=4,
+            array_lengths: 1..=2,
+            ..Default::default()
+        };
+        let parsed = parse_json_schema(
+            &json!({ "type": "array", "items": { "type": "integer", "minimum": 10 } }),
+            &defaults,
+        )
+        .unwrap();
+
+        assert_eq!(
+            parsed.schema,
+            SchemaState::Array {
+                min_length: 1,
+                max_length: 2,
+                unique: false,
+                schema: Box::new(SchemaState::Number(NumberType::Integer {
+                    min: 10,
+                    max: 20
+                }))
+            }
+        );
+    }
+
+    #[test]
+    fn resolves_local_refs_and_rejects_cycles() {
+        let parsed = parse(json!({
+            "$defs": { "flag": { "type": "boolean" } },
+            "anyOf": [{ "$ref": "#/$defs/flag" }, { "type": "null" }]
+        }))
+        .unwrap();
+        assert_eq!(
+            parsed.schema,
+            SchemaState::Nullable(Box::new(SchemaState::Boolean))
+        );
+
+        let err = parse(json!({
+            "$defs": { "node": { "type": "array", "items": { "$ref": "#/$defs/node" } } },
+            "$ref": "#/$defs/node"
+        }))
+        .unwrap_err();
+        let DrivelError::JsonSchema(err) = err else {
+            panic!("expected a JSON Schema error");
+        };
+        assert_eq!(err.pointer, "#/$defs/node/items");
+    }
+
+    #[test]
+    fn exports_and_parses_tuples_with_prefix_items() {
+        let tuple = SchemaState::Tuple(vec![
+            SchemaState::String(StringType::Email),
+            SchemaState::Number(NumberType::Integer { min: 1, max: 5 }),
+            SchemaState::Boolean,
+        ]);
+
+        let exported = to_json_schema(&tuple);
+        assert_eq!(
+            exported["prefixItems"][1],
+            json!({ "type": "integer", "minimum": 1, "maximum": 5 })
+        );
+        assert_eq!(exported["items"], json!(false));
+
+        let parsed = parse(exported).unwrap();
+        assert_eq!(parsed.schema, tuple);
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn exports_and_parses_identifiers_with_an_extension_keyword() {
+        let identifiers = SchemaState::Tuple(vec![
+            SchemaState::String(StringType::Ulid),
+            SchemaState::String(StringType::Ksuid),
+            SchemaState::Nullable(Box::new(SchemaState::String(StringType::ObjectId))),
+        ]);
+
+        let exported = to_json_schema(&identifiers);
+        assert_eq!(
+            exported["prefixItems"][2],
+            json!({
+                "type": ["string", "null"],
+                "pattern": OBJECT_ID_PATTERN,
+                "x-drivel-type": "objectid"
+            })
+        );
+
+        let parsed = parse(exported).unwrap();
+        assert_eq!(parsed.schema, identifiers);
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn exports_and_parses_monetary_amounts() {
+        let price = SchemaState::Tuple(vec![
+            SchemaState::Number(NumberType::Decimal {
+                scale: 2,
+                min: 0.5,
+                max: 99.99,
+            }),
+            SchemaState::String(StringType::CurrencyCode {
+                codes: HashSet::from_iter(["USD".to_owned(), "EUR".to_owned()]),
+            }),
+        ]);
+
+        let exported = to_json_schema(&price);
+        assert_eq!(exported["prefixItems"][0]["multipleOf"], json!(0.01));
+        assert_eq!(exported["prefixItems"][1]["enum"], json!(["EUR", "USD"]));
+
+        let parsed = parse(exported).unwrap();
+        assert_eq!(parsed.schema, price);
+        assert!(parsed.warnings.is_empty());
+
+        // other multiples cannot be produced, and are ignored
+        let parsed = parse(json!({ "type": "number", "multipleOf": 0.5 })).unwrap();
+        assert!(matches!(
+            parsed.schema,
+            SchemaState::Number(NumberType::Float { .. })
+        ));
+        assert_eq!(parsed.warnings.len(), 1);
+    }
+
+    #[test]
+    fn exports_and_parses_the_bounds_of_unsigned_64_bit_integers_exactly() {
+        let ids = SchemaState::Number(NumberType::BigInteger {
+            min: 0,
+            max: u64::MAX.into(),
+        });
+
+        let exported = to_json_schema(&ids);
+        assert_eq!(exported["maximum"], json!(u64::MAX));
+        assert_eq!(parse(exported).unwrap().schema, ids);
+    }
+
+    #[test]
+    fn exports_and_parses_integer_and_mixed_enums() {
+        let priority = SchemaState::Nullable(Box::new(SchemaState::Number(NumberType::Enum {
+            variants: HashSet::from([1, 2, 3]),
+            n_seen: 0,
+        })));
+
+        let exported = to_json_schema(&priority);
+        assert_eq!(
+            exported,
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "type": ["integer", "null"],
+                "enum": [1, 2, 3, null]
+            })
+        );
+        assert_eq!(parse(exported).unwrap().schema, priority);
+
+        let parsed = parse(json!({ "enum": ["auto", 0, 1] })).unwrap();
+        assert_eq!(
+            parsed.schema,
+            SchemaState::Union(vec![
+                SchemaState::String(StringType::Enum {
+                    variants: HashSet::from(["auto".to_owned()])
+                }),
+                SchemaState::Number(NumberType::Enum {
+                    variants: HashSet::from([0, 1]),
+                    n_seen: 0
+                }),
+            ])
+        );
+        assert!(parse(json!({ "enum": [0.5] })).is_err());
+    }
+
+    #[test]
+    fn parses_unions_of_any_number_of_branches() {
+        let parsed = parse(json!({
+            "oneOf": [
+                { "type": "boolean" },
+                { "type": ["integer", "null"], "minimum": 1, "maximum": 2 },
+                { "anyOf": [{ "type": "string", "format": "email" }] }
+            ]
+        }))
+        .unwrap();
+
+        let union = SchemaState::Union(vec![
+            SchemaState::Boolean,
+            SchemaState::Number(NumberType::Integer { min: 1, max: 2 }),
+            SchemaState::String(StringType::Email),
+        ]);
+        assert_eq!(parsed.schema, SchemaState::Nullable(Box::new(union)));
+        assert_eq!(
+            parse(to_json_schema(&parsed.schema)).unwrap().schema,
+            parsed.schema
+        );
+    }
+
+    #[test]
+    fn parses_patterns() {
+        let parsed =
+            parse(json!({ "type": "string", "pattern": "^ORD-[0-9]{5}$", "maxLength": 9 }))
+                .unwrap();
+        assert_eq!(
+            parsed.schema,
+            SchemaState::String(StringType::Pattern("^ORD-[0-9]{5}$".to_owned()))
+        );
+        assert_eq!(
+            parsed.warnings,
+            vec!["#: maxLength is ignored in favour of pattern"]
+        );
+
+        let parsed = parse(json!({ "type": "string", "pattern": "(unclosed" })).unwrap();
+        assert!(matches!(
+            parsed.schema,
+            SchemaState::String(StringType::Unknown { .. })
+        ));
+        assert_eq!(parsed.warnings.len(), 1);
+    }
+
+    #[test]
+    fn parses_exports_and_produces_pattern_properties() {
+        let schema = json!({
+            "type": "object",
+            "patternProperties": {
+                "^sku-[0-9]{4}$": { "type": "integer", "minimum": 1, "maximum": 99 }
+            },
+            "minProperties": 2,
+            "maxProperties": 3
+        });
+        let parsed = parse(schema.clone()).unwrap();
+        assert!(parsed.warnings.is_empty());
+        assert!(matches!(
+            &parsed.schema,
+            SchemaState::Map { pattern, min_length: 2, max_length: 3, .. } if pattern == "^sku-[0-9]{4}$"
+        ));
+        assert_eq!(
+            to_json_schema(&parsed.schema)["patternProperties"],
+            schema["patternProperties"]
+        );
+
+        for seed in 0..10 {
+            let options = crate::ProduceOptions::new().seed(seed);
+            let value = crate::produce_with_options(&parsed.schema, &options).unwrap();
+            assert!((2..=3).contains(&value.as_object().unwrap().len()));
+            assert!(crate::validate(&value, &parsed.schema).is_empty());
+        }
+
+        let parsed = parse(json!({
+            "type": "object",
+            "properties": { "id": { "type": "integer" } },
+            "patternProperties": { "^x-": {} }
+        }))
+        .unwrap();
+        assert_eq!(
+            parsed.warnings,
+            vec!["#: patternProperties alongside properties is not supported and will be ignored"]
+        );
+    }
+
+    #[test]
+    fn produces_both_branches_of_conditionals() {
+        let parsed = parse(json!({
+            "type": "object",
+            "properties": {
+                "type": { "type": "string", "enum": ["card", "bank", "cash"] },
+                "card_number": { "type": "string", "pattern": "^[0-9]{16}$" },
+                "iban": { "type": "string" }
+            },
+            "required": ["type"],
+            "if": { "properties": { "type": { "const": "card" } } },
+            "then": { "required": ["card_number"] },
+            "else": { "properties": { "card_number": false } }
+        }))
+        .unwrap();
+        assert!(parsed.warnings.is_empty());
+
+        let mut kinds = HashSet::new();
+        for seed in 0..50 {
+            let options = crate::ProduceOptions::new().seed(seed);
+            let value = crate::produce_with_options(&parsed.schema, &options).unwrap();
+            let kind = value["type"].as_str().unwrap().to_owned();
+            assert_eq!(
+                kind == "card",
+                value.get("card_number").is_some(),
+                "{}",
+                value
+            );
+            kinds.insert(kind);
+        }
+        assert_eq!(kinds.len(), 3);
+
+        let parsed = parse(json!({
+            "type": "integer",
+            "if": { "minimum": 10 },
+            "then": { "maximum": 20 }
+        }))
+        .unwrap();
+        assert_eq!(
+            parsed.warnings,
+            vec!["#: only values matching if are produced, as its condition cannot be negated"]
+        );
+    }
+
+    #[test]
+    fn requires_the_dependencies_of_present_properties() {
+        let parsed = parse(json!({
+            "type": "object",
+            "properties": {
+                "credit_card": { "type": "string" },
+                "billing_address": { "type": "string" },
+                "name": { "type": "string" }
+            },
+            "dependentRequired": { "credit_card": ["billing_address"] }
+        }))
+        .unwrap();
+
+        let SchemaState::Union(branches) = parsed.schema else {
+            panic!("expected a union, got {:?}", parsed.schema)
+        };
+        assert_eq!(branches.len(), 2);
+        let SchemaState::Object { required, optional } = &branches[1] else {
+            panic!("expected an object, got {:?}", branches[1])
+        };
+        assert_eq!(
+            required.keys().collect::<Vec<_>>(),
+            vec!["credit_card", "billing_address"]
+        );
+        assert_eq!(optional.keys().collect::<Vec<_>>(), vec!["name"]);
+    }
+
+    #[test]
+    fn warns_about_ignored_keywords() {
+        let parsed = parse(json!({
+            "type": "object",
+            "properties": { "n": { "type": "integer", "multipleOf": 5 } }
+        }))
+        .unwrap();
+        assert_eq!(
+            parsed.warnings,
+            vec!["#/properties/n: multipleOf is not supported and will be ignored"]
+        );
+    }
+
+    #[test]
+    fn produces_unique_items() {
+        let schema = json!({
+            "type": "array",
+            "items": { "type": "integer", "minimum": 1, "maximum": 6 },
+            "minItems": 6,
+            "maxItems": 6,
+            "uniqueItems": true
+        });
+        let parsed = parse(schema.clone()).unwrap();
+        assert!(parsed.warnings.is_empty());
+        assert_eq!(to_json_schema_inner(&parsed.schema), schema);
+
+        let element = SchemaState::Array {
+            min_length: 0,
+            max_length: 0,
+            unique: false,
+            schema: Box::new(parsed.schema.clone()),
+        };
+        let produced =
+            crate::produce_with_options(&element, &crate::ProduceOptions::new().count(20).seed(1))
+                .unwrap();
+        for value in produced.as_array().unwrap() {
+            let mut items = value
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|n| n.as_i64().unwrap())
+                .collect::<Vec<_>>();
+            items.sort();
+            assert_eq!(items, vec![1, 2, 3, 4, 5, 6]);
+        }
+        assert_eq!(
+            crate::validate(&json!([1, 2, 2, 3, 4, 5]), &parsed.schema).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn honours_and_exports_exclusive_bounds() {
+        let schema = json!({ "type": "number", "exclusiveMinimum": 0, "exclusiveMaximum": 1 });
+        let parsed = parse(schema.clone()).unwrap();
+        assert!(parsed.warnings.is_empty());
+        assert_eq!(
+            to_json_schema(&parsed.schema)["exclusiveMinimum"],
+            json!(0.0)
+        );
+        assert_eq!(
+            to_json_schema(&parsed.schema)["exclusiveMaximum"],
+            json!(1.0)
+        );
+
+        let options = crate::ProduceOptions::new().edge_cases(true);
+        let values = crate::produce_with_options(
+            &SchemaState::Array {
+                min_length: 0,
+                max_length: 0,
+                unique: false,
+                schema: Box::new(parsed.schema.clone()),
+            },
+            &options.count(4),
+        )
+        .unwrap();
+        for value in values.as_array().unwrap() {
+            let n = value.as_f64().unwrap();
+            assert!(n > 0.0 && n < 1.0, "{}", n);
+        }
+        assert!(!crate::validate(&json!(0), &parsed.schema).is_empty());
+
+        let parsed = parse(json!({ "type": "integer", "exclusiveMinimum": 0, "maximum": 10 }));
+        assert_eq!(
+            parsed.unwrap().schema,
+            SchemaState::Number(NumberType::Integer { min: 1, max: 10 })
+        );
+        let parsed = parse(json!({ "type": "number", "multipleOf": 0.01, "exclusiveMaximum": 5 }));
+        assert!(matches!(
+            parsed.unwrap().schema,
+            SchemaState::Number(NumberType::Decimal { max, .. }) if max == 4.99
+        ));
+    }
+
+    #[test]
+    fn saturates_exclusive_integer_bounds_and_rejects_those_without_integers() {
+        assert_eq!(
+            parse(json!({ "type": "integer", "exclusiveMinimum": 1e40 }))
+                .unwrap()
+                .schema,
+            SchemaState::Number(NumberType::BigInteger {
+                min: i128::MAX,
+                max: i128::MAX
+            })
+        );
+        assert_eq!(
+            parse(json!({ "type": "integer", "exclusiveMaximum": -1e40 }))
+                .unwrap()
+                .schema,
+            SchemaState::Number(NumberType::BigInteger {
+                min: i128::MIN,
+                max: i128::MIN
+            })
+        );
+
+        for schema in [
+            json!({ "type": "integer", "exclusiveMinimum": 5, "exclusiveMaximum": 6 }),
+            json!({ "type": "integer", "minimum": 5.2, "maximum": 5.5 }),
+            json!({ "type": "integer", "exclusiveMinimum": 5.2, "exclusiveMaximum": 5.5 }),
+        ] {
+            assert!(
+                matches!(
+                    parse(schema.clone()),
+                    Err(DrivelError::JsonSchema(JsonSchemaError { ref message, .. }))
+                        if message == "no integer lies within the bounds"
+                ),
+                "{}",
+                schema
+            );
+        }
+    }
+
+    #[test]
+    fn reads_older_drafts() {
+        let parsed = parse(json!({
+            "$schema": "http://json-schema.org/draft-04/schema#",
+            "definitions": {
+                "point": {
+                    "id": "#point",
+                    "type": "array",
+                    "items": [{ "type": "integer" }, { "type": "integer" }],
+                    "additionalItems": false
+                }
+            },
+            "type": "object",
+            "properties": {
+                "age": { "type": "integer", "minimum": 0, "exclusiveMinimum": true, "maximum": 10 },
+                "point": { "$ref": "#point", "description": "where it is" },
+                "email": { "type": "string" },
+                "name": { "type": "string" }
+            },
+            "required": ["age", "point"],
+            "dependencies": { "email": ["name"] }
+        }))
+        .unwrap();
+        assert!(parsed.warnings.is_empty(), "{:?}", parsed.warnings);
+
+        let SchemaState::Union(branches) = parsed.schema else {
+            panic!("expected a union, got {:?}", parsed.schema)
+        };
+        let SchemaState::Object { required, .. } = &branches[1] else {
+            panic!("expected an object, got {:?}", branches[1])
+        };
+        assert_eq!(
+            required["age"],
+            SchemaState::Number(NumberType::Integer { min: 1, max: 10 })
+        );
+        assert!(matches!(&required["point"], SchemaState::Tuple(elements) if elements.len() == 2));
+        assert!(required.contains_key("name"));
+    }
+
+    #[test]
+    fn applies_keywords_alongside_refs_from_draft_2019_09() {
+        let schema = |draft: &str| {
+            json!({
+                "$schema": format!("https://json-schema.org/draft/{}/schema", draft),
+                "$defs": {
+                    "count": { "$anchor": "count", "type": "integer", "minimum": 0, "maximum": 100 }
+                },
+                "$ref": "#count",
+                "maximum": 5
+            })
+        };
+        let parsed = parse(schema("2019-09")).unwrap();
+        assert_eq!(
+            parsed.schema,
+            SchemaState::Number(NumberType::Integer { min: 0, max: 5 })
+        );
+
+        let parsed = parse_json_schema_with_draft(
+            &schema("2019-09"),
+            &SchemaDefaults::default(),
+            Some(SchemaDraft::Draft7),
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.schema,
+            SchemaState::Number(NumberType::Integer { min: 0, max: 100 })
+        );
+    }
+
+    #[test]
+    fn follows_external_refs_relative_to_their_file() {
+        let dir = std::env::temp_dir().join("drivel-follows-external-refs");
+        std::fs::create_dir_all(dir.join("common")).unwrap();
+        std::fs::write(
+            dir.join("common/address.json"),
+            json!({
+                "definitions": {
+                    "Address": {
+                        "type": "object",
+                        "properties": { "zip": { "$ref": "#/definitions/Zip" } },
+                        "required": ["zip"]
+                    },
+                    "Zip": { "type": "integer", "minimum": 10000, "maximum": 99999 }
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("common/node.yaml"),
+            "type: object\nproperties:\n  parent: { $ref: '../schema.json' }\n",
+        )
+        .unwrap();
+        let parse_file = |schema: serde_json::Value| {
+            parse_json_schema_with_external_refs(
+                &schema,
+                &dir.join("schema.json"),
+                &SchemaDefaults::default(),
+                None,
+            )
+        };
+
+        let schema = json!({ "$ref": "./common/address.json#/definitions/Address" });
+        let SchemaState::Object { required, .. } = parse_file(schema.clone()).unwrap().schema
+        else {
+            panic!("expected an object")
+        };
+        assert_eq!(
+            required["zip"],
+            SchemaState::Number(NumberType::Integer {
+                min: 10000,
+                max: 99999
+            })
+        );
+        assert_eq!(
+            parse(schema).unwrap_err().to_string(),
+            "#: only local $refs are supported, found \"./common/address.json#/definitions/Address\""
+        );
+
+        let schema = json!({
+            "type": "object",
+            "properties": { "node": { "$ref": "common/node.yaml" } }
+        });
+        std::fs::write(dir.join("schema.json"), schema.to_string()).unwrap();
+        let err = parse_file(schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "../schema.json#/properties/node: recursive $ref \"common/node.yaml#\" is not supported"
+        );
+    }
+
+    #[test]
+    fn defines_repeated_objects_once() {
+        let point = json!({
+            "type": "object",
+            "properties": { "x": { "type": "integer", "minimum": 0, "maximum": 9 } },
+            "required": ["x"]
+        });
+        let line = json!({
+            "type": "object",
+            "properties": { "from": point, "to": point },
+            "required": ["from", "to"]
+        });
+        let schema = parse(json!({
+            "type": "object",
+            "properties": {
+                "edge": line,
+                "path": { "type": "array", "items": line, "minItems": 0, "maxItems": 3 },
+                "origin": point
+            },
+            "required": ["edge", "path", "origin"]
+        }))
+        .unwrap()
+        .schema;
+
+        let exported = to_json_schema(&schema);
+        assert_eq!(
+            exported["$defs"],
+            json!({
+                "Edge": {
+                    "type": "object",
+                    "properties": {
+                        "from": { "$ref": "#/$defs/Origin" },
+                        "to": { "$ref": "#/$defs/Origin" }
+                    },
+                    "required": ["from", "to"]
+                },
+                "Origin": point
+            })
+        );
+        assert_eq!(
+            exported["properties"]["path"]["items"],
+            json!({ "$ref": "#/$defs/Edge" })
+        );
+        assert_eq!(
+            exported["properties"]["origin"],
+            json!({ "$ref": "#/$defs/Origin" })
+        );
+        assert_eq!(parse(exported).unwrap().schema, schema);
+    }
+}
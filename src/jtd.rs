@@ -0,0 +1,594 @@
+//! Exporting schemas as JSON Type Definition (RFC 8927) documents, and parsing them.
+
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexMap;
+use serde_json::json;
+
+use crate::json_schema::{bounds, child_pointer, error};
+use crate::{
+    infer::integer_value, pattern, DistinctCount, DrivelError, JsonSchemaError, NumberType,
+    ParsedSchema, SchemaDefaults, SchemaState, StringType,
+};
+
+/// Returns the narrowest JTD integer type that holds every integer in `min..=max`, preferring
+/// 32-bit types since the range seen rarely covers every value a field can take. JTD has no
+/// 64-bit integers, so wider ranges become `float64`.
+fn integer_type(min: i128, max: i128) -> &'static str {
+    let fits = |lower: i128, upper: i128| min >= lower && max <= upper;
+    if fits(i32::MIN.into(), i32::MAX.into()) {
+        "int32"
+    } else if fits(0, u32::MAX.into()) {
+        "uint32"
+    } else {
+        "float64"
+    }
+}
+
+fn string_to_jtd(string_type: &StringType) -> serde_json::Value {
+    match string_type {
+        StringType::DateTimeISO8601 { .. } => json!({ "type": "timestamp" }),
+        StringType::Enum { variants } => {
+            let mut variants = variants.iter().collect::<Vec<_>>();
+            variants.sort();
+            json!({ "enum": variants })
+        }
+        _ => json!({ "type": "string" }),
+    }
+}
+
+/// Returns the key that tells the branches of a union apart, if they are all objects that
+/// hold a different string in it, like `{ "type": "click", ... }` and `{ "type": "scroll", ... }`.
+fn discriminator(branches: &[SchemaState]) -> Option<&str> {
+    let SchemaState::Object { required, .. } = branches.first()? else {
+        return None;
+    };
+    required.keys().map(String::as_str).find(|key| {
+        let mut values = HashSet::new();
+        branches.iter().all(|branch| match branch {
+            SchemaState::Object { required, .. } => matches!(
+                required.get(*key),
+                Some(SchemaState::Const(serde_json::Value::String(value))) if values.insert(value)
+            ),
+            _ => false,
+        })
+    })
+}
+
+fn object_to_jtd<'a>(
+    required: impl Iterator<Item = (&'a String, &'a SchemaState)>,
+    optional: &IndexMap<String, SchemaState>,
+) -> serde_json::Value {
+    let mut object = json!({
+        "properties": required
+            .map(|(k, v)| (k.clone(), to_jtd_inner(v)))
+            .collect::<serde_json::Map<_, _>>()
+    });
+    if !optional.is_empty() {
+        object["optionalProperties"] = optional
+            .iter()
+            .map(|(k, v)| (k.clone(), to_jtd_inner(v)))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    object
+}
+
+fn to_jtd_inner(schema: &SchemaState) -> serde_json::Value {
+    match schema {
+        SchemaState::Union(branches) => match discriminator(branches) {
+            Some(tag) => {
+                // each branch is described by its fields other than the tag
+                let mapping = branches
+                    .iter()
+                    .filter_map(|branch| match branch {
+                        SchemaState::Object { required, optional } => match required.get(tag) {
+                            Some(SchemaState::Const(serde_json::Value::String(value))) => {
+                                let required = required.iter().filter(|(k, _)| *k != tag);
+                                Some((value.clone(), object_to_jtd(required, optional)))
+                            }
+                            _ => None,
+                        },
+                        _ => None,
+                    })
+                    .collect::<serde_json::Map<_, _>>();
+                json!({ "discriminator": tag, "mapping": mapping })
+            }
+            None => json!({}),
+        },
+        // JTD has no other unions, nor tuples; the empty form accepts any value
+        SchemaState::Initial
+        | SchemaState::Indefinite
+        | SchemaState::Number(NumberType::Float {
+            non_finite: true, ..
+        }) => json!({}),
+        SchemaState::Null => json!({ "nullable": true }),
+        SchemaState::Nullable(inner) => {
+            let mut inner = to_jtd_inner(inner);
+            inner["nullable"] = json!(true);
+            inner
+        }
+        SchemaState::String(string_type) => string_to_jtd(string_type),
+        SchemaState::Number(
+            number_type @ (NumberType::Integer { .. }
+            | NumberType::Enum { .. }
+            | NumberType::BigInteger { .. }),
+        ) => {
+            // JTD enums can only hold strings
+            let (min, max) = number_type.integer_range().unwrap_or_default();
+            json!({ "type": integer_type(min, max) })
+        }
+        SchemaState::Number(NumberType::Float { .. } | NumberType::Decimal { .. }) => {
+            json!({ "type": "float64" })
+        }
+        SchemaState::Boolean => json!({ "type": "boolean" }),
+        SchemaState::Array { schema, .. } => json!({ "elements": to_jtd_inner(schema) }),
+        SchemaState::Tuple(_) | SchemaState::UnconstrainedArray => json!({ "elements": {} }),
+        SchemaState::Object { required, optional } => object_to_jtd(required.iter(), optional),
+        SchemaState::UnconstrainedObject => {
+            json!({ "properties": {}, "additionalProperties": true })
+        }
+        // JTD cannot constrain the keys of `values`
+        SchemaState::Map { schema, .. } => json!({ "values": to_jtd_inner(schema) }),
+        // JTD has no constants, so they are described by their type, or an enum for strings
+        SchemaState::Const(value) => match value {
+            serde_json::Value::Null => json!({ "nullable": true }),
+            serde_json::Value::Bool(_) => json!({ "type": "boolean" }),
+            serde_json::Value::Number(n) => match integer_value(n) {
+                Some(n) => json!({ "type": integer_type(n, n) }),
+                None => json!({ "type": "float64" }),
+            },
+            serde_json::Value::String(s) => json!({ "enum": [s] }),
+            serde_json::Value::Array(_) | serde_json::Value::Object(_) => json!({}),
+        },
+    }
+}
+
+/// Exports a schema as a JSON Type Definition (RFC 8927) document.
+///
+/// JTD describes the shape of data rather than its ranges, so lengths and ranges are left out.
+/// Integers become `int32`, or `uint32` and `float64` when their range needs it; ISO 8601
+/// datetimes become timestamps. Unions of objects that are told apart by a string field
+/// become discriminators; values that JTD cannot describe, like tuples and other unions,
+/// accept any value.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_jtd, InferenceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!({ "id": 1, "email": null, "tags": ["a"] }),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(
+///     to_jtd(&schema),
+///     json!({
+///         "properties": {
+///             "id": { "type": "int32" },
+///             "email": { "nullable": true },
+///             "tags": { "elements": { "type": "string" } }
+///         }
+///     })
+/// );
+/// ```
+pub fn to_jtd(schema: &SchemaState) -> serde_json::Value {
+    to_jtd_inner(schema)
+}
+
+struct ParseContext<'a> {
+    definitions: Option<&'a serde_json::Map<String, serde_json::Value>>,
+    defaults: &'a SchemaDefaults,
+    warnings: Vec<String>,
+    /// The definitions currently being resolved, used to detect cycles.
+    ref_stack: Vec<String>,
+}
+
+/// Returns the range of integers of a JTD integer type.
+fn integer_range(name: &str) -> Option<(i64, i64)> {
+    match name {
+        "int8" => Some((i8::MIN.into(), i8::MAX.into())),
+        "uint8" => Some((u8::MIN.into(), u8::MAX.into())),
+        "int16" => Some((i16::MIN.into(), i16::MAX.into())),
+        "uint16" => Some((u16::MIN.into(), u16::MAX.into())),
+        "int32" => Some((i32::MIN.into(), i32::MAX.into())),
+        "uint32" => Some((u32::MIN.into(), u32::MAX.into())),
+        _ => None,
+    }
+}
+
+fn parse_type(
+    name: &str,
+    pointer: &str,
+    ctx: &ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    if let Some((lower, upper)) = integer_range(name) {
+        // the default range, within the range of the type
+        let (min, max) = (
+            (*ctx.defaults.integers.start()).max(lower),
+            (*ctx.defaults.integers.end()).min(upper),
+        );
+        let (min, max) = if min <= max {
+            (min, max)
+        } else {
+            (lower, upper)
+        };
+        return Ok(SchemaState::Number(NumberType::Integer { min, max }));
+    }
+    match name {
+        "boolean" => Ok(SchemaState::Boolean),
+        "float32" | "float64" => Ok(SchemaState::Number(NumberType::Float {
+            min: *ctx.defaults.floats.start(),
+            max: *ctx.defaults.floats.end(),
+            non_finite: false,
+            exclusive_min: false,
+            exclusive_max: false,
+        })),
+        "string" => {
+            let (min_length, max_length) = bounds(None, None, &ctx.defaults.string_lengths);
+            Ok(SchemaState::String(StringType::Unknown {
+                strings_seen: vec![],
+                chars_seen: HashMap::new(),
+                n_strings_seen: 0,
+                distinct: DistinctCount::new(),
+                n_chars_seen: 0,
+                min_length: Some(min_length),
+                max_length: Some(max_length),
+                prefix: String::new(),
+                suffix: String::new(),
+                structure: None,
+            }))
+        }
+        "timestamp" => Ok(SchemaState::String(StringType::DateTimeISO8601 {
+            range: None,
+        })),
+        name => Err(error(pointer, format!("unknown type {:?}", name))),
+    }
+}
+
+/// The fields of an object, by their keys.
+type Fields = IndexMap<String, SchemaState>;
+
+/// Parses the required and optional fields of the properties form.
+fn parse_properties(
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<(Fields, Fields), JsonSchemaError> {
+    let mut fields = [IndexMap::new(), IndexMap::new()];
+    for (keyword, fields) in ["properties", "optionalProperties"].iter().zip(&mut fields) {
+        let Some(properties) = node.get(*keyword) else {
+            continue;
+        };
+        let properties_pointer = child_pointer(pointer, keyword);
+        let properties = properties
+            .as_object()
+            .ok_or_else(|| error(pointer, format!("{} must be an object", keyword)))?;
+        for (key, property) in properties {
+            let property = parse_node(property, &child_pointer(&properties_pointer, key), ctx)?;
+            fields.insert(key.clone(), property);
+        }
+    }
+    let [required, optional] = fields;
+    Ok((required, optional))
+}
+
+/// Parses the discriminator form: a union of objects that are told apart by the string held
+/// in the tag property.
+fn parse_discriminator(
+    tag: &serde_json::Value,
+    node: &serde_json::Map<String, serde_json::Value>,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let tag = tag
+        .as_str()
+        .ok_or_else(|| error(pointer, "discriminator must be a string"))?;
+    let mapping = node
+        .get("mapping")
+        .and_then(|mapping| mapping.as_object())
+        .ok_or_else(|| error(pointer, "a discriminator needs a mapping object"))?;
+    let mapping_pointer = child_pointer(pointer, "mapping");
+    let branches = mapping
+        .iter()
+        .map(|(value, branch)| {
+            let branch_pointer = child_pointer(&mapping_pointer, value);
+            let branch = branch.as_object().ok_or_else(|| {
+                error(
+                    &branch_pointer,
+                    "a mapping must hold schemas of the properties form",
+                )
+            })?;
+            let (required, optional) = parse_properties(branch, &branch_pointer, ctx)?;
+            let required = [(tag.to_owned(), SchemaState::Const(json!(value)))]
+                .into_iter()
+                .chain(required)
+                .collect();
+            Ok(SchemaState::Object { required, optional })
+        })
+        .collect::<Result<Vec<_>, JsonSchemaError>>()?;
+    Ok(SchemaState::Union(branches))
+}
+
+fn parse_node(
+    node: &serde_json::Value,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    let node = node
+        .as_object()
+        .ok_or_else(|| error(pointer, "a schema must be an object"))?;
+
+    let schema = if let Some(reference) = node.get("ref") {
+        let reference = reference
+            .as_str()
+            .ok_or_else(|| error(pointer, "ref must be a string"))?;
+        if ctx.ref_stack.iter().any(|r| r == reference) {
+            return Err(error(
+                pointer,
+                format!("recursive ref {:?} is not supported", reference),
+            ));
+        }
+        let definition = ctx
+            .definitions
+            .and_then(|definitions| definitions.get(reference))
+            .ok_or_else(|| error(pointer, format!("unable to resolve ref {:?}", reference)))?;
+        ctx.ref_stack.push(reference.to_owned());
+        let parsed = parse_node(
+            definition,
+            &child_pointer(&child_pointer("#", "definitions"), reference),
+            ctx,
+        );
+        ctx.ref_stack.pop();
+        parsed?
+    } else if let Some(name) = node.get("type") {
+        let name = name
+            .as_str()
+            .ok_or_else(|| error(pointer, "type must be a string"))?;
+        parse_type(name, pointer, ctx)?
+    } else if let Some(variants) = node.get("enum") {
+        let variants = variants
+            .as_array()
+            .and_then(|variants| {
+                variants
+                    .iter()
+                    .map(|variant| variant.as_str().map(str::to_owned))
+                    .collect::<Option<HashSet<_>>>()
+            })
+            .ok_or_else(|| error(pointer, "enum must be an array of strings"))?;
+        SchemaState::String(StringType::Enum { variants })
+    } else if let Some(elements) = node.get("elements") {
+        let schema = parse_node(elements, &child_pointer(pointer, "elements"), ctx)?;
+        let (min_length, max_length) = bounds(None, None, &ctx.defaults.array_lengths);
+        SchemaState::Array {
+            min_length,
+            max_length,
+            unique: false,
+            schema: Box::new(schema),
+        }
+    } else if node.contains_key("properties") || node.contains_key("optionalProperties") {
+        let (required, optional) = parse_properties(node, pointer, ctx)?;
+        SchemaState::Object { required, optional }
+    } else if let Some(values) = node.get("values") {
+        let schema = parse_node(values, &child_pointer(pointer, "values"), ctx)?;
+        let (min_length, max_length) = bounds(None, None, &ctx.defaults.map_lengths);
+        SchemaState::Map {
+            pattern: pattern::ANY_KEY.to_owned(),
+            min_length,
+            max_length,
+            schema: Box::new(schema),
+        }
+    } else if let Some(tag) = node.get("discriminator") {
+        parse_discriminator(tag, node, pointer, ctx)?
+    } else {
+        SchemaState::Indefinite
+    };
+
+    if node.get("nullable") == Some(&json!(true)) {
+        Ok(match schema {
+            SchemaState::Indefinite => schema,
+            schema => SchemaState::Nullable(Box::new(schema)),
+        })
+    } else {
+        Ok(schema)
+    }
+}
+
+/// Parses a JSON Type Definition (RFC 8927) into a SchemaState, so that data can be produced
+/// from it.
+///
+/// JTD describes the shape of data rather than its ranges, so numbers, strings and arrays are
+/// bounded by `defaults`, and integers by the range of their type too. Discriminators become
+/// unions of objects that hold their tag, and `ref`s are resolved against the `definitions`.
+/// Objects of `values` are maps with `defaults.map_lengths` keys.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{parse_jtd, NumberType, SchemaDefaults, SchemaState};
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "properties": { "age": { "type": "uint8" } },
+///     "optionalProperties": { "nickname": { "type": "string", "nullable": true } }
+/// });
+///
+/// let parsed = parse_jtd(&schema, &SchemaDefaults::default()).unwrap();
+///
+/// let SchemaState::Object { required, optional } = parsed.schema else { panic!() };
+/// assert_eq!(
+///     required["age"],
+///     SchemaState::Number(NumberType::Integer { min: 0, max: 255 })
+/// );
+/// assert!(matches!(optional["nickname"], SchemaState::Nullable(_)));
+/// ```
+pub fn parse_jtd(
+    schema: &serde_json::Value,
+    defaults: &SchemaDefaults,
+) -> Result<ParsedSchema, DrivelError> {
+    let mut ctx = ParseContext {
+        definitions: schema
+            .get("definitions")
+            .and_then(|definitions| definitions.as_object()),
+        defaults,
+        warnings: vec![],
+        ref_stack: vec![],
+    };
+    let schema = parse_node(schema, "#", &mut ctx)?;
+    Ok(ParsedSchema {
+        schema,
+        warnings: ctx.warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_optional_nullable_and_enum_fields() {
+        let schema = SchemaState::Object {
+            required: IndexMap::from_iter([
+                (
+                    "status".to_owned(),
+                    SchemaState::String(StringType::Enum {
+                        variants: HashSet::from_iter(["open".to_owned(), "closed".to_owned()]),
+                    }),
+                ),
+                (
+                    "at".to_owned(),
+                    SchemaState::String(StringType::DateTimeISO8601 { range: None }),
+                ),
+            ]),
+            optional: IndexMap::from_iter([(
+                "count".to_owned(),
+                SchemaState::Nullable(Box::new(SchemaState::Number(NumberType::Integer {
+                    min: 0,
+                    max: 5_000_000_000,
+                }))),
+            )]),
+        };
+
+        assert_eq!(
+            to_jtd(&schema),
+            json!({
+                "properties": {
+                    "status": { "enum": ["closed", "open"] },
+                    "at": { "type": "timestamp" }
+                },
+                "optionalProperties": {
+                    "count": { "type": "float64", "nullable": true }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn parses_discriminators_refs_and_integer_types() {
+        let parsed = parse_jtd(
+            &json!({
+                "definitions": { "at": { "type": "timestamp" } },
+                "discriminator": "kind",
+                "mapping": {
+                    "click": {
+                        "properties": {
+                            "x": { "type": "int8" },
+                            "at": { "ref": "at", "nullable": true }
+                        }
+                    },
+                    "scroll": { "optionalProperties": { "by": { "type": "uint32" } } }
+                }
+            }),
+            &SchemaDefaults {
+                integers: -1000..=1000,
+                ..SchemaDefaults::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            parsed.schema,
+            SchemaState::Union(vec![
+                SchemaState::Object {
+                    required: IndexMap::from_iter([
+                        ("kind".to_owned(), SchemaState::Const(json!("click"))),
+                        (
+                            "x".to_owned(),
+                            SchemaState::Number(NumberType::Integer {
+                                min: -128,
+                                max: 127
+                            })
+                        ),
+                        (
+                            "at".to_owned(),
+                            SchemaState::Nullable(Box::new(SchemaState::String(
+                                StringType::DateTimeISO8601 { range: None }
+                            )))
+                        ),
+                    ]),
+                    optional: IndexMap::new(),
+                },
+                SchemaState::Object {
+                    required: IndexMap::from_iter([(
+                        "kind".to_owned(),
+                        SchemaState::Const(json!("scroll"))
+                    )]),
+                    optional: IndexMap::from_iter([(
+                        "by".to_owned(),
+                        SchemaState::Number(NumberType::Integer { min: 0, max: 1000 })
+                    )]),
+                },
+            ])
+        );
+        assert!(parsed.warnings.is_empty());
+    }
+
+    #[test]
+    fn exports_and_parses_discriminated_unions() {
+        let document = json!({
+            "discriminator": "kind",
+            "mapping": {
+                "click": { "properties": { "x": { "type": "int32" } } },
+                "scroll": { "properties": {}, "optionalProperties": { "by": { "type": "boolean" } } }
+            }
+        });
+
+        let parsed = parse_jtd(&document, &SchemaDefaults::default()).unwrap();
+
+        assert_eq!(to_jtd(&parsed.schema), document);
+    }
+
+    #[test]
+    fn parses_and_exports_values_as_maps() {
+        let document = json!({ "values": { "type": "boolean" } });
+
+        let parsed = parse_jtd(&document, &SchemaDefaults::default()).unwrap();
+
+        assert_eq!(
+            parsed.schema,
+            SchemaState::Map {
+                pattern: pattern::ANY_KEY.to_owned(),
+                min_length: 1,
+                max_length: 4,
+                schema: Box::new(SchemaState::Boolean),
+            }
+        );
+        assert!(parsed.warnings.is_empty());
+        assert_eq!(to_jtd(&parsed.schema), document);
+    }
+
+    #[test]
+    fn rejects_recursive_refs() {
+        let parsed = parse_jtd(
+            &json!({
+                "definitions": { "node": { "properties": { "next": { "ref": "node" } } } },
+                "ref": "node"
+            }),
+            &SchemaDefaults::default(),
+        );
+
+        assert!(parsed.is_err());
+    }
+}
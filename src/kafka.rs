@@ -0,0 +1,170 @@
+//! Publishing produced records straight to a Kafka topic, to seed event streams without
+//! writing them out first.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rdkafka::{
+    config::ClientConfig,
+    error::{KafkaError, RDKafkaErrorCode},
+    producer::{BaseRecord, DeliveryResult, Producer, ProducerContext, ThreadedProducer},
+    ClientContext,
+};
+
+use crate::{
+    path::{from_record, parse_steps, visit_mut},
+    stream::stream_records,
+    DrivelError, ProduceOptions, SchemaState, StreamOptions,
+};
+
+/// The time given to records still queued to be delivered once all have been produced.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where and how records are published by `produce_to_kafka`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use drivel::{produce_to_kafka, KafkaOptions, ProduceOptions, SchemaState, StreamOptions};
+///
+/// let kafka = KafkaOptions::new("localhost:9092", "events")
+///     .key("user.id")
+///     .batch_size(500);
+/// let stream = StreamOptions::new().rate(100.0).limit(1000);
+/// produce_to_kafka(&SchemaState::Boolean, &ProduceOptions::new(), &stream, &kafka).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct KafkaOptions {
+    topic: String,
+    /// The path of the value that records are keyed by, if any.
+    key: Option<String>,
+    config: ClientConfig,
+}
+
+impl KafkaOptions {
+    /// Publishes records to `topic` through the comma-separated `brokers`, unkeyed.
+    pub fn new(brokers: &str, topic: &str) -> Self {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", brokers);
+        Self {
+            topic: topic.to_owned(),
+            key: None,
+            config,
+        }
+    }
+
+    /// Keys each record by the value at `path` within it, e.g. `user.id`: strings as they are,
+    /// and other values as JSON. Records without a value there, or with null, are unkeyed.
+    /// Paths use the same notation as overrides.
+    pub fn key(mut self, path: &str) -> Self {
+        self.key = Some(path.to_owned());
+        self
+    }
+
+    /// The most records sent to a broker at once. Default = librdkafka's default, 10000.
+    pub fn batch_size(self, batch_size: usize) -> Self {
+        self.set("batch.num.messages", &batch_size.to_string())
+    }
+
+    /// The time records wait for a batch to fill up before it is sent. Default =
+    /// librdkafka's default, 5ms.
+    pub fn linger(self, linger: Duration) -> Self {
+        self.set("linger.ms", &linger.as_millis().to_string())
+    }
+
+    /// Sets any librdkafka configuration property, like `compression.type` or
+    /// `security.protocol`.
+    pub fn set(mut self, property: &str, value: &str) -> Self {
+        self.config.set(property, value);
+        self
+    }
+}
+
+/// Remembers the first record that could not be delivered, to fail with.
+#[derive(Default)]
+struct DeliveryErrors {
+    first: Mutex<Option<KafkaError>>,
+}
+
+impl ClientContext for DeliveryErrors {}
+
+impl ProducerContext for DeliveryErrors {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: Self::DeliveryOpaque) {
+        if let Err((err, _)) = result {
+            self.first
+                .lock()
+                .unwrap()
+                .get_or_insert_with(|| err.clone());
+        }
+    }
+}
+
+impl From<KafkaError> for DrivelError {
+    fn from(err: KafkaError) -> Self {
+        DrivelError::Kafka(err.to_string())
+    }
+}
+
+/// Publishes records of `schema`, the elements of an array at the root or otherwise values of
+/// the schema itself, as JSON messages to the topic of `kafka`, at the rate and until the
+/// duration or limit of `stream`, like `produce_stream` writes them. Returns the number of
+/// records published, once all of them are delivered.
+///
+/// Fails with `DrivelError::Kafka` when the producer cannot be created or a record cannot be
+/// delivered.
+pub fn produce_to_kafka(
+    schema: &SchemaState,
+    options: &ProduceOptions,
+    stream: &StreamOptions,
+    kafka: &KafkaOptions,
+) -> Result<usize, DrivelError> {
+    let producer: ThreadedProducer<DeliveryErrors> = kafka
+        .config
+        .create_with_context(DeliveryErrors::default())?;
+    let key = kafka
+        .key
+        .as_deref()
+        .map(|path| from_record(parse_steps(path)));
+
+    let published = stream_records(schema, options, stream, |mut record| {
+        if let Some(err) = producer.context().first.lock().unwrap().take() {
+            return Err(err.into());
+        }
+        let mut key_bytes = None;
+        if let Some(steps) = &key {
+            visit_mut(&mut record, steps, &mut |value| {
+                if key_bytes.is_none() {
+                    key_bytes = match value {
+                        serde_json::Value::Null => None,
+                        serde_json::Value::String(s) => Some(s.as_bytes().to_vec()),
+                        value => Some(value.to_string().into_bytes()),
+                    };
+                }
+            });
+        }
+        let payload = serde_json::to_vec(&record).map_err(std::io::Error::from)?;
+        let mut message = BaseRecord::to(&kafka.topic).payload(&payload);
+        if let Some(key) = &key_bytes {
+            message = message.key(key);
+        }
+        // records wait for room in the queue of the producer, rather than being dropped
+        loop {
+            match producer.send(message) {
+                Ok(()) => return Ok(true),
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), returned)) => {
+                    message = returned;
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err((err, _)) => return Err(err.into()),
+            }
+        }
+    })?;
+
+    producer.flush(FLUSH_TIMEOUT)?;
+    if let Some(err) = producer.context().first.lock().unwrap().take() {
+        return Err(err.into());
+    }
+    Ok(published)
+}
@@ -1,11 +1,128 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod annotate;
+mod avro;
+mod bigquery;
+mod binary;
+mod cancel;
+mod codegen;
+mod derive;
+mod detect;
+mod diff;
+mod distinct;
+mod elasticsearch;
+mod error;
+mod evidence;
+mod examples;
+mod fetch;
+mod field_counts;
+mod graphql;
 mod infer;
 mod infer_string;
+mod input;
+mod json_schema;
+mod jtd;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod locale;
+#[cfg(feature = "mongo")]
+mod mongo;
+mod mutate;
+mod nulls;
+mod openapi;
+mod overrides;
+mod path;
+mod pattern;
+#[cfg(feature = "postgres")]
+mod pg;
+mod pins;
+mod presence;
 mod produce;
+mod profile;
+mod proto;
+#[cfg(feature = "python")]
+mod python;
+mod python_classes;
+mod relations;
+#[cfg(feature = "tui")]
+mod review;
+mod roundtrip;
+mod sample;
 mod schema;
+mod series;
+mod session;
+mod spark;
+mod sql;
+mod stats;
+mod stream;
+mod transform;
+mod unique;
+mod validate;
+mod widen;
+mod xml;
+mod zod;
 
+pub use annotate::*;
+pub use avro::{parse_avro_schema, to_avro_schema, write_avro};
+pub use bigquery::to_bigquery;
+pub use binary::{write_cbor, write_msgpack};
+pub use cancel::{Cancellation, Cancelled};
+pub use derive::Derivations;
+pub use detect::{Detectors, StringDetector};
+pub use diff::{check_compatibility, diff_schemas, diff_schemas_in_detail, Change, ChangeKind};
+pub use distinct::DistinctCount;
+pub use elasticsearch::to_es_mapping;
+pub use error::DrivelError;
+pub use evidence::{field_evidence, FieldEvidence};
+pub use examples::{field_examples, FieldExamples};
+pub use fetch::{fetch_input, FetchOptions, DEFAULT_MAX_PAGES};
+pub use field_counts::FieldCounts;
+pub use graphql::to_graphql;
 pub use infer::*;
+pub use input::Input;
+pub use json_schema::{
+    parse_json_schema, parse_json_schema_with_draft, parse_json_schema_with_external_refs,
+    to_json_schema, to_json_schema_with_examples, JsonSchemaError, ParsedSchema, SchemaDefaults,
+    SchemaDraft,
+};
+pub use jtd::{parse_jtd, to_jtd};
+#[cfg(feature = "kafka")]
+pub use kafka::{produce_to_kafka, KafkaOptions};
+pub use locale::Locale;
+#[cfg(feature = "mongo")]
+pub use mongo::{sample_mongo, DEFAULT_MONGO_SAMPLE_SIZE};
+pub use mutate::{inject_violations, Mutation, MutationKind, DEFAULT_MUTATION_RATE};
+pub use nulls::{field_nulls, FieldNulls};
+pub use openapi::{parse_openapi, to_openapi, OpenApiSchema};
+pub use overrides::{Override, Overrides, DEFAULT_POOL_SIZE, FAKER_GENERATORS};
+#[cfg(feature = "postgres")]
+pub use pg::query_postgres;
+pub use pins::{PinnedType, TypePins};
+pub use presence::{field_presence, FieldPresence};
+// the deprecated function remains exported until the next breaking release
+#[allow(deprecated)]
 pub use produce::produce;
+pub use produce::{
+    produce_to_serializer, produce_to_writer_with_options, produce_with_options, ProduceOptions,
+    DEFAULT_CHUNK_SIZE, DEFAULT_NULL_PROBABILITY, DEFAULT_OPTIONAL_PROBABILITY,
+};
+pub use profile::{DetectorProfile, Profile};
+pub use proto::{parse_proto_descriptor, to_proto};
+pub use python_classes::{to_python_classes, PythonStyle};
+pub use relations::Relations;
+#[cfg(feature = "tui")]
+pub use review::{review_schema, ReviewOutcome};
+pub use roundtrip::verify_roundtrip;
 pub use schema::*;
+pub use series::TimeSeries;
+pub use session::{InferenceSession, DEFAULT_COMPACTION_INTERVAL};
+pub use spark::to_spark;
+pub use sql::{to_sql_inserts, SqlDialect, DEFAULT_BATCH_SIZE};
+pub use stats::{field_stats, Distribution, FieldStats, ValueCount};
+pub use stream::{produce_stream, StreamOptions};
+pub use transform::{Transform, Transforms};
+pub use validate::{validate, validate_with_overrides, Violation};
+pub use widen::singleton_paths;
+pub use xml::{to_xml, XmlOptions, TEXT_KEY};
+pub use zod::to_zod;
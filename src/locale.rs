@@ -0,0 +1,43 @@
+//! The locales that fake values, like names and addresses, are produced in.
+
+/// The locale that fake values, like names, addresses, words and phone numbers, are produced
+/// in. Values that a locale has no data for are produced in English.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    /// English.
+    #[default]
+    En,
+    /// French, as spoken in France.
+    FrFr,
+    /// Portuguese, as spoken in Brazil.
+    PtBr,
+    /// Simplified Chinese, as written in China.
+    ZhCn,
+    /// Traditional Chinese, as written in Taiwan.
+    ZhTw,
+    /// Japanese.
+    JaJp,
+    /// Arabic, as spoken in Saudi Arabia.
+    ArSa,
+}
+
+/// Produces a fake value with one of the "raw" fakers of the `fake` crate, which take the
+/// locale as their first argument, in a `Locale`, e.g.
+/// `fake_in!(locale, rng, fake::faker::name::raw::Name)`, with any arguments
+/// of the faker after it.
+macro_rules! fake_in {
+    ($locale:expr, $rng:expr, $faker:path $(, $arg:expr)* $(,)?) => {{
+        use fake::locales::{AR_SA, EN, FR_FR, JA_JP, PT_BR, ZH_CN, ZH_TW};
+        use fake::Fake;
+        match $locale {
+            $crate::Locale::En => $faker(EN $(, $arg)*).fake_with_rng($rng),
+            $crate::Locale::FrFr => $faker(FR_FR $(, $arg)*).fake_with_rng($rng),
+            $crate::Locale::PtBr => $faker(PT_BR $(, $arg)*).fake_with_rng($rng),
+            $crate::Locale::ZhCn => $faker(ZH_CN $(, $arg)*).fake_with_rng($rng),
+            $crate::Locale::ZhTw => $faker(ZH_TW $(, $arg)*).fake_with_rng($rng),
+            $crate::Locale::JaJp => $faker(JA_JP $(, $arg)*).fake_with_rng($rng),
+            $crate::Locale::ArSa => $faker(AR_SA $(, $arg)*).fake_with_rng($rng),
+        }
+    }};
+}
+pub(crate) use fake_in;
@@ -0,0 +1,124 @@
+//! Sampling the documents of a MongoDB collection as the records that schemas are inferred
+//! from.
+
+use mongodb::bson::{doc, spec::BinarySubtype, Bson, Document};
+
+use crate::{
+    binary::{bytes, float},
+    produce::integer_number,
+    DrivelError, Input,
+};
+
+/// The number of documents sampled unless another number is given.
+pub const DEFAULT_MONGO_SAMPLE_SIZE: usize = 1000;
+
+/// Converts a BSON value to JSON, mapping the types of extended JSON onto the strings that
+/// drivel infers the same types from: ObjectIds as their hex, dates as ISO 8601 datetimes,
+/// UUIDs as UUIDs and other binary data as base64.
+fn to_json(value: Bson) -> serde_json::Value {
+    use serde_json::Value;
+
+    match value {
+        Bson::Null | Bson::Undefined | Bson::MinKey | Bson::MaxKey | Bson::DbPointer(_) => {
+            Value::Null
+        }
+        Bson::Boolean(b) => Value::Bool(b),
+        Bson::Int32(n) => Value::Number(n.into()),
+        Bson::Int64(n) => Value::Number(n.into()),
+        Bson::Double(n) => float(n),
+        Bson::Decimal128(n) => {
+            let s = n.to_string();
+            match s.parse::<i128>() {
+                Ok(n) => Value::Number(integer_number(n)),
+                Err(_) => serde_json::from_str(&s).unwrap_or(Value::String(s)),
+            }
+        }
+        Bson::String(s) | Bson::Symbol(s) | Bson::JavaScriptCode(s) => Value::String(s),
+        Bson::JavaScriptCodeWithScope(code) => Value::String(code.code),
+        Bson::RegularExpression(regex) => Value::String(regex.pattern),
+        Bson::ObjectId(id) => Value::String(id.to_hex()),
+        Bson::DateTime(date_time) => match date_time.try_to_rfc3339_string() {
+            Ok(s) => Value::String(s),
+            Err(_) => Value::Number(date_time.timestamp_millis().into()),
+        },
+        Bson::Timestamp(timestamp) => Value::Number(timestamp.time.into()),
+        Bson::Binary(binary) => match binary.to_uuid() {
+            Ok(uuid) if binary.subtype == BinarySubtype::Uuid => Value::String(uuid.to_string()),
+            _ => bytes(&binary.bytes),
+        },
+        Bson::Array(items) => Value::Array(items.into_iter().map(to_json).collect()),
+        Bson::Document(document) => document_to_json(document),
+    }
+}
+
+fn document_to_json(document: Document) -> serde_json::Value {
+    serde_json::Value::Object(
+        document
+            .into_iter()
+            .map(|(key, value)| (key, to_json(value)))
+            .collect(),
+    )
+}
+
+/// Samples `size` documents at random from `collection` of the database named by the MongoDB
+/// connection string `uri`, like `mongodb://localhost:27017/shop`, with `$sample`, and returns
+/// them as records.
+///
+/// The types of extended JSON are read as the strings that drivel infers the same types from:
+/// ObjectIds as ObjectIds, dates as ISO 8601 datetimes, UUIDs as UUIDs, decimals as numbers and
+/// other binary data as base64.
+pub fn sample_mongo(uri: &str, collection: &str, size: usize) -> Result<Input, DrivelError> {
+    let client = mongodb::sync::Client::with_uri_str(uri)?;
+    let database = client.default_database().ok_or_else(|| {
+        DrivelError::Mongo("the connection string does not name a database".to_owned())
+    })?;
+    let documents = database
+        .collection::<Document>(collection)
+        .aggregate([doc! { "$sample": { "size": size as i64 } }])
+        .run()?
+        .map(|document| Ok(document_to_json(document?)))
+        .collect::<Result<Vec<_>, DrivelError>>()?;
+    Ok(Input::Lines(documents))
+}
+
+impl From<mongodb::error::Error> for DrivelError {
+    fn from(err: mongodb::error::Error) -> Self {
+        DrivelError::Mongo(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::{oid::ObjectId, Binary, DateTime, Decimal128};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn maps_extended_json_types_onto_strings_drivel_infers_types_from() {
+        let id = ObjectId::parse_str("65f1c0ffee0123456789abcd").unwrap();
+        let uuid = mongodb::bson::Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        let document = doc! {
+            "_id": id,
+            "created_at": DateTime::from_millis(1_700_000_000_000),
+            "token": Binary { subtype: BinarySubtype::Uuid, bytes: uuid.bytes().to_vec() },
+            "blob": Binary { subtype: BinarySubtype::Generic, bytes: vec![1, 2, 3] },
+            "price": "12.50".parse::<Decimal128>().unwrap(),
+            "ratio": f64::NAN,
+            "tags": ["a", Bson::Null],
+        };
+
+        assert_eq!(
+            document_to_json(document),
+            json!({
+                "_id": "65f1c0ffee0123456789abcd",
+                "created_at": "2023-11-14T22:13:20Z",
+                "token": "67e55044-10b1-426f-9247-bb680e5fe0c8",
+                "blob": "AQID",
+                "price": 12.5,
+                "ratio": "NaN",
+                "tags": ["a", null],
+            })
+        );
+    }
+}
@@ -0,0 +1,457 @@
+//! Injecting violations of a schema into data that conforms to it, to test that consumers of
+//! the data reject invalid input.
+
+use std::fmt::Display;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde_json::Number;
+
+use crate::{path, produce::integer_number, validate, NumberType, SchemaState, StringType};
+
+/// The default probability that a value is made to violate its schema.
+pub const DEFAULT_MUTATION_RATE: f64 = 0.1;
+
+/// The ways in which a value is made to violate its schema.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MutationKind {
+    /// The value is replaced by a value of another type, e.g. a number by a string.
+    WrongType,
+    /// A number is replaced by one outside of its range, a string by one of a length outside
+    /// of its range, or an enum by a value that is not one of its variants.
+    OutOfRange,
+    /// A required field is removed from an object.
+    MissingField,
+    /// A string of a format, like a UUID or a date, is replaced by one that is malformed.
+    MalformedFormat,
+}
+
+impl Display for MutationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            MutationKind::WrongType => "wrong type",
+            MutationKind::OutOfRange => "out of range",
+            MutationKind::MissingField => "missing field",
+            MutationKind::MalformedFormat => "malformed format",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// A violation of the schema injected into a value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mutation {
+    /// The path of the mutated value, with the positions of array elements, e.g.
+    /// `[3].address.city`.
+    pub path: String,
+    pub kind: MutationKind,
+    /// A description of the change, e.g. `replaced 42 by "42"`.
+    pub message: String,
+}
+
+impl Display for Mutation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} ({})",
+            path::display(&self.path),
+            self.message,
+            self.kind
+        )
+    }
+}
+
+/// A value that a value could be replaced by to violate its schema.
+struct Candidate {
+    kind: MutationKind,
+    value: serde_json::Value,
+}
+
+fn candidate(kind: MutationKind, value: serde_json::Value) -> Candidate {
+    Candidate { kind, value }
+}
+
+fn float(n: f64) -> Option<serde_json::Value> {
+    Number::from_f64(n).map(serde_json::Value::Number)
+}
+
+/// Returns values of the wrong type, out of range and malformed that could replace `value`.
+/// Not all of them need to violate `schema`; those that do not are discarded later.
+fn replacements(value: &serde_json::Value, schema: &SchemaState) -> Vec<Candidate> {
+    let mut candidates = [
+        serde_json::json!(true),
+        serde_json::json!(0),
+        serde_json::json!("invalid"),
+        serde_json::json!([]),
+        serde_json::json!({}),
+    ]
+    .into_iter()
+    .map(|value| candidate(MutationKind::WrongType, value))
+    .collect::<Vec<_>>();
+
+    let schema = match schema {
+        SchemaState::Nullable(inner) => inner.as_ref(),
+        schema => schema,
+    };
+    match schema {
+        SchemaState::Number(number_type) => {
+            if let serde_json::Value::Number(n) = value {
+                // a number written as a string is a common mistake
+                candidates.push(candidate(
+                    MutationKind::WrongType,
+                    serde_json::Value::String(n.to_string()),
+                ));
+            }
+            let out_of_range = match *number_type {
+                NumberType::Float { min, max, .. } | NumberType::Decimal { min, max, .. } => {
+                    let width = (max - min).abs().max(1.0);
+                    let mut out_of_range = vec![float(min - width), float(max + width)];
+                    // exclusive bounds are the values most likely to slip through
+                    if let NumberType::Float {
+                        exclusive_min,
+                        exclusive_max,
+                        ..
+                    } = *number_type
+                    {
+                        out_of_range.extend([
+                            float(min).filter(|_| exclusive_min),
+                            float(max).filter(|_| exclusive_max),
+                        ]);
+                    }
+                    out_of_range
+                }
+                _ => {
+                    let (min, max) = number_type.integer_range().unwrap_or_default();
+                    vec![
+                        min.checked_sub(1)
+                            .map(|n| serde_json::Value::Number(integer_number(n))),
+                        max.checked_add(1)
+                            .map(|n| serde_json::Value::Number(integer_number(n))),
+                    ]
+                }
+            };
+            candidates.extend(
+                out_of_range
+                    .into_iter()
+                    .flatten()
+                    .map(|value| candidate(MutationKind::OutOfRange, value)),
+            );
+            if let NumberType::Decimal { scale, min, .. } = *number_type {
+                // one decimal place too many
+                let extra = 10f64.powi(-(scale as i32 + 1));
+                candidates.extend(
+                    float(min + extra).map(|value| candidate(MutationKind::OutOfRange, value)),
+                );
+            }
+        }
+        SchemaState::String(StringType::Unknown {
+            min_length,
+            max_length,
+            ..
+        }) => {
+            let lengths = [
+                min_length.and_then(|min| min.checked_sub(1)),
+                max_length.map(|max| max + 1),
+            ];
+            candidates.extend(
+                lengths
+                    .into_iter()
+                    .flatten()
+                    .map(|length| candidate(MutationKind::OutOfRange, "x".repeat(length).into())),
+            );
+        }
+        SchemaState::String(StringType::Enum { variants }) => {
+            let mut variant = variants.iter().max().cloned().unwrap_or_default();
+            variant.push_str("_invalid");
+            candidates.push(candidate(MutationKind::OutOfRange, variant.into()));
+        }
+        SchemaState::String(_) => {
+            if let serde_json::Value::String(s) = value {
+                // the value with a character in the middle replaced, and cut off halfway
+                let chars = s.chars().collect::<Vec<_>>();
+                let mid = chars.len() / 2;
+                let mut replaced = chars.clone();
+                if let Some(c) = replaced.get_mut(mid) {
+                    *c = if *c == '!' { '?' } else { '!' };
+                }
+                for malformed in [
+                    replaced.into_iter().collect::<String>(),
+                    chars[..mid].iter().collect::<String>(),
+                ] {
+                    candidates.push(candidate(MutationKind::MalformedFormat, malformed.into()));
+                }
+            }
+        }
+        _ => {}
+    }
+    candidates
+}
+
+/// Injects violations of `schema` into `value`, which conforms to it.
+struct Mutator<'a> {
+    rate: f64,
+    rng: &'a mut StdRng,
+    mutations: Vec<Mutation>,
+}
+
+impl Mutator<'_> {
+    /// Replaces `value` by one of the candidates that violate `schema`, picking the kind of
+    /// violation first so that each is as likely as the others. Returns whether it did.
+    fn replace(
+        &mut self,
+        value: &mut serde_json::Value,
+        schema: &SchemaState,
+        candidates: Vec<Candidate>,
+        current_path: &str,
+    ) -> bool {
+        let violating = candidates
+            .into_iter()
+            .filter(|candidate| !validate(&candidate.value, schema).is_empty())
+            .collect::<Vec<_>>();
+        let mut kinds = vec![];
+        for candidate in &violating {
+            if !kinds.contains(&candidate.kind) {
+                kinds.push(candidate.kind);
+            }
+        }
+        let Some(kind) = kinds.choose(self.rng).copied() else {
+            return false;
+        };
+        let of_kind = violating
+            .into_iter()
+            .filter(|candidate| candidate.kind == kind)
+            .collect::<Vec<_>>();
+        let Some(chosen) = of_kind.choose(self.rng) else {
+            return false;
+        };
+        self.mutations.push(Mutation {
+            path: current_path.to_owned(),
+            kind,
+            message: format!("replaced {} by {}", value, chosen.value),
+        });
+        *value = chosen.value.clone();
+        true
+    }
+
+    fn mutate(
+        &mut self,
+        value: &mut serde_json::Value,
+        schema: &SchemaState,
+        current_path: &str,
+        current_depth: usize,
+    ) {
+        // records, the elements of an array at the root or the root itself, can have their
+        // fields removed, but are never replaced as a whole
+        let is_record = current_depth == 1;
+        if current_depth > 0 && self.rng.gen_bool(self.rate) {
+            let required = match (schema, &*value) {
+                (SchemaState::Object { required, .. }, serde_json::Value::Object(map)) => required
+                    .keys()
+                    .filter(|k| map.contains_key(*k))
+                    .collect::<Vec<_>>(),
+                _ => vec![],
+            };
+            // removing a required field is as likely as replacing the object
+            if !required.is_empty() && (is_record || self.rng.gen_bool(0.5)) {
+                let k = required[self.rng.gen_range(0..required.len())].clone();
+                if let serde_json::Value::Object(map) = value {
+                    map.shift_remove(&k);
+                }
+                self.mutations.push(Mutation {
+                    path: path::key(current_path, &k),
+                    kind: MutationKind::MissingField,
+                    message: format!("removed required field {:?}", k),
+                });
+                return;
+            }
+            if !is_record {
+                let candidates = replacements(value, schema);
+                if self.replace(value, schema, candidates, current_path) {
+                    return;
+                }
+            }
+        }
+
+        let schema = match schema {
+            SchemaState::Nullable(inner) if !value.is_null() => inner.as_ref(),
+            schema => schema,
+        };
+        match (schema, value) {
+            (SchemaState::Array { schema, .. }, serde_json::Value::Array(items)) => {
+                for (idx, item) in items.iter_mut().enumerate() {
+                    self.mutate(
+                        item,
+                        schema,
+                        &path::index(current_path, idx),
+                        current_depth + 1,
+                    );
+                }
+            }
+            (SchemaState::Tuple(elements), serde_json::Value::Array(items)) => {
+                for (idx, (item, element)) in items.iter_mut().zip(elements).enumerate() {
+                    self.mutate(
+                        item,
+                        element,
+                        &path::index(current_path, idx),
+                        current_depth + 1,
+                    );
+                }
+            }
+            (SchemaState::Object { required, optional }, serde_json::Value::Object(map)) => {
+                for (k, v) in map.iter_mut() {
+                    if let Some(field_schema) = required.get(k).or_else(|| optional.get(k)) {
+                        self.mutate(
+                            v,
+                            field_schema,
+                            &path::key(current_path, k),
+                            current_depth + 1,
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Injects violations of `schema` into `value`, which conforms to it, returning the violations
+/// injected.
+///
+/// Every value, like a field of a record, is replaced with probability `rate` by a value of
+/// the wrong type, a number or string outside of its range, or a malformed string of a format,
+/// or has one of its required fields removed if it is an object. Records, the elements of an
+/// array at the root or the root itself, only ever have fields removed, rather than being
+/// replaced as a whole. The same `seed` injects the same violations into the same value.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{inject_violations, validate, NumberType, SchemaState};
+/// use indexmap::IndexMap;
+/// use serde_json::json;
+///
+/// let schema = SchemaState::Array {
+///     min_length: 1,
+///     max_length: 1,
+///     unique: false,
+///     schema: Box::new(SchemaState::Object {
+///         required: IndexMap::from_iter([(
+///             "age".to_string(),
+///             SchemaState::Number(NumberType::Integer { min: 0, max: 100 }),
+///         )]),
+///         optional: IndexMap::new(),
+///     }),
+/// };
+///
+/// let mut data = json!([{ "age": 42 }, { "age": 7 }]);
+/// let mutations = inject_violations(&mut data, &schema, 1.0, Some(1));
+/// assert_eq!(mutations.len(), 2);
+/// assert_eq!(validate(&data, &schema).len(), 2);
+/// ```
+pub fn inject_violations(
+    value: &mut serde_json::Value,
+    schema: &SchemaState,
+    rate: f64,
+    seed: Option<u64>,
+) -> Vec<Mutation> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut mutator = Mutator {
+        rate: rate.clamp(0.0, 1.0),
+        rng: &mut rng,
+        mutations: vec![],
+    };
+    let depth = usize::from(!matches!(schema, SchemaState::Array { .. }));
+    mutator.mutate(value, schema, "", depth);
+    mutator.mutations
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+    use crate::{infer_schema, produce_with_options, InferenceOptions, ProduceOptions};
+
+    #[test]
+    fn every_injected_mutation_violates_the_schema() {
+        let input = serde_json::json!([
+            {
+                "id": "0b7e2f4c-8e8f-4a38-9a7c-9b1d2f3e4a5b",
+                "created": "2024-01-02T03:04:05Z",
+                "price": 12.5,
+                "status": "active",
+                "tags": ["a", "b"],
+                "owner": { "name": "alice", "age": 30 },
+                "deleted": null,
+            },
+            {
+                "id": "1b7e2f4c-8e8f-4a38-9a7c-9b1d2f3e4a5b",
+                "created": "2024-02-02T03:04:05Z",
+                "price": 99.0,
+                "status": "banned",
+                "tags": [],
+                "owner": { "name": "bob", "age": 41 },
+                "deleted": true,
+            },
+        ]);
+        let options = InferenceOptions {
+            enum_inference: Some(crate::EnumInference {
+                max_unique_ratio: 1.0,
+                min_sample_size: 1,
+            }),
+            ..InferenceOptions::default()
+        };
+        let schema = infer_schema(input, &options);
+        let SchemaState::Array {
+            schema: record_schema,
+            ..
+        } = &schema
+        else {
+            panic!("expected an array schema");
+        };
+
+        for seed in 0..50 {
+            let produce_options = ProduceOptions::new().count(1).seed(seed);
+            let mut data = produce_with_options(&schema, &produce_options).unwrap();
+            let mutations = inject_violations(&mut data, &schema, 0.3, Some(seed));
+            let record = &data[0];
+            // each mutated value on its own violates the schema at its path
+            let violations = validate(record, record_schema);
+            assert_eq!(violations.is_empty(), mutations.is_empty(), "{}", record);
+            for mutation in &mutations {
+                assert!(mutation.path.starts_with("[0]"));
+            }
+        }
+    }
+
+    #[test]
+    fn removes_required_fields() {
+        let schema = SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: false,
+            schema: Box::new(SchemaState::Object {
+                required: IndexMap::from_iter([(
+                    "owner".to_owned(),
+                    SchemaState::Object {
+                        required: IndexMap::from_iter([("id".to_owned(), SchemaState::Boolean)]),
+                        optional: IndexMap::new(),
+                    },
+                )]),
+                optional: IndexMap::new(),
+            }),
+        };
+        let kinds = (0..50)
+            .flat_map(|seed| {
+                let mut data = serde_json::json!([{ "owner": { "id": true } }]);
+                inject_violations(&mut data, &schema, 0.5, Some(seed))
+            })
+            .map(|mutation| mutation.kind)
+            .collect::<std::collections::HashSet<_>>();
+        // records only ever have fields removed; the objects within them are replaced too
+        assert!(kinds.contains(&MutationKind::MissingField));
+        assert!(kinds.contains(&MutationKind::WrongType));
+    }
+}
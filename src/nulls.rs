@@ -0,0 +1,125 @@
+//! Counting how often the values found at each path of the input records were null, so that
+//! data produced from their schema is null as often.
+
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{
+    evidence::{group_digits, percentage},
+    path, FieldCounts,
+};
+
+/// How many of the values found at one path of the input records were null.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct FieldNulls {
+    /// The path of the field, e.g. `users[].address.city`.
+    pub path: String,
+    /// The number of values found at the path, including nulls. Fields left out of an object
+    /// are not counted.
+    pub samples: usize,
+    /// The number of samples that were null.
+    pub nulls: usize,
+}
+
+impl FieldNulls {
+    /// Returns the share of samples that were null.
+    pub fn null_ratio(&self) -> f64 {
+        self.nulls as f64 / self.samples as f64
+    }
+}
+
+impl Display for FieldNulls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percentage = percentage(self.nulls, self.samples);
+        write!(
+            f,
+            "{}: {}% null ({} of {} samples)",
+            path::display(&self.path),
+            percentage,
+            group_digits(self.nulls),
+            group_digits(self.samples)
+        )
+    }
+}
+
+/// Counts the values found at each path of `records` and how many of them were null. Only
+/// paths where both nulls and other values were found are reported, as those are the paths
+/// of nullable values; they are sorted by path.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::field_nulls;
+/// use serde_json::json;
+///
+/// let records = vec![
+///     json!({ "name": "Ann", "nickname": null }),
+///     json!({ "name": "Bob", "nickname": "bobby" }),
+///     json!({ "name": "Cat", "nickname": null }),
+///     json!({ "name": "Dan" }),
+/// ];
+///
+/// let nulls = field_nulls(&records);
+///
+/// assert_eq!(nulls.len(), 1);
+/// assert_eq!(nulls[0].path, "nickname");
+/// assert_eq!(nulls[0].null_ratio(), 2.0 / 3.0);
+/// assert_eq!(nulls[0].to_string(), "nickname: 67% null (2 of 3 samples)");
+/// ```
+pub fn field_nulls(records: &[serde_json::Value]) -> Vec<FieldNulls> {
+    let counts = FieldCounts::new();
+    counts.count(records);
+    counts.nulls()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema, produce_with_options, InferenceOptions, ProduceOptions};
+
+    #[test]
+    fn produces_nulls_as_often_as_they_were_found() {
+        let records = (0..1000)
+            .map(|n| {
+                json!({
+                    "deleted_at": if n % 10 == 0 { json!("2024-01-01") } else { json!(null) },
+                    "tags": [if n == 0 { json!(null) } else { json!("a") }],
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let nulls = field_nulls(&records);
+        assert_eq!(
+            nulls
+                .iter()
+                .map(|field| field.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "deleted_at: 90% null (900 of 1,000 samples)",
+                "tags[]: <1% null (1 of 1,000 samples)",
+            ]
+        );
+
+        // paths are relative to the elements of an array at the root
+        let schema = infer_schema(json!(records), &InferenceOptions::default());
+        let mut options = ProduceOptions::new().count(1000).seed(7);
+        for field in &nulls {
+            options = options.null_probability_at(&field.path, field.null_ratio());
+        }
+        let produced = produce_with_options(&schema, &options).unwrap();
+        let produced = produced.as_array().unwrap();
+        let produced_nulls = produced
+            .iter()
+            .filter(|record| record["deleted_at"].is_null())
+            .count();
+        assert!((850..=950).contains(&produced_nulls));
+        let produced_nulls = produced
+            .iter()
+            .filter(|record| record["tags"][0].is_null())
+            .count();
+        assert!(produced_nulls < 10);
+    }
+}
@@ -0,0 +1,377 @@
+//! Exporting schemas as the components of OpenAPI 3.1 documents, and reading the schemas of
+//! components and responses back from OpenAPI documents.
+
+use std::collections::HashSet;
+
+use serde_json::json;
+
+use crate::{
+    codegen::unique_name,
+    json_schema::{child_pointer, error, parse_subschema, to_json_schema_inner, SchemaDraft},
+    DrivelError, ParsedSchema, SchemaDefaults, SchemaState,
+};
+
+/// The version of OpenAPI that documents are written in.
+const OPENAPI_VERSION: &str = "3.1.0";
+
+/// The methods of the operations of a path, in the order their responses are looked up in.
+const METHODS: [&str; 8] = [
+    "get", "post", "put", "patch", "delete", "head", "options", "trace",
+];
+
+/// The schema of an OpenAPI document that data is produced from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OpenApiSchema {
+    /// The schema of a component under `components.schemas`, by name.
+    Component(String),
+    /// The schema of the JSON content of a response to an operation on a path, like `/users`,
+    /// by status code, like `200` or `default`. The operations are looked up in the order
+    /// `get`, `post`, `put`, `patch`, `delete`, `head`, `options`, `trace`.
+    Response { path: String, status: String },
+}
+
+/// Follows the `$ref` of `node`, if it has one, to the object it refers to within `document`,
+/// returning the object and its pointer.
+fn follow_ref<'a>(
+    document: &'a serde_json::Value,
+    node: &'a serde_json::Value,
+    pointer: String,
+) -> Result<(&'a serde_json::Value, String), DrivelError> {
+    let (mut node, mut pointer) = (node, pointer);
+    let mut seen = vec![];
+    while let Some(reference) = node.get("$ref").and_then(|r| r.as_str()) {
+        if seen.iter().any(|r| r == reference) {
+            return Err(error(&pointer, format!("recursive $ref {:?}", reference)).into());
+        }
+        node = reference
+            .strip_prefix('#')
+            .and_then(|target| document.pointer(target))
+            .ok_or_else(|| error(&pointer, format!("unable to resolve $ref {:?}", reference)))?;
+        pointer = reference.to_owned();
+        seen.push(reference.to_owned());
+    }
+    Ok((node, pointer))
+}
+
+/// Returns the pointer to the schema of the JSON content of the response with `status` to an
+/// operation on `path`. Content of `application/json` is preferred over other JSON media
+/// types, like `application/problem+json`.
+fn response_pointer(
+    document: &serde_json::Value,
+    path: &str,
+    status: &str,
+) -> Result<String, DrivelError> {
+    let path_pointer = child_pointer("#/paths", path);
+    let item = document
+        .get("paths")
+        .and_then(|paths| paths.get(path))
+        .ok_or_else(|| error("#/paths", format!("no path {:?}", path)))?;
+    let (item, path_pointer) = follow_ref(document, item, path_pointer)?;
+    let (response, response_pointer) = METHODS
+        .iter()
+        .find_map(|method| {
+            let response = item.get(method)?.get("responses")?.get(status)?;
+            let pointer = child_pointer(&child_pointer(&path_pointer, method), "responses");
+            Some((response, child_pointer(&pointer, status)))
+        })
+        .ok_or_else(|| {
+            error(
+                &path_pointer,
+                format!("no operation has a response with status {:?}", status),
+            )
+        })?;
+    let (response, response_pointer) = follow_ref(document, response, response_pointer)?;
+
+    let content = response
+        .get("content")
+        .and_then(|content| content.as_object())
+        .into_iter()
+        .flatten();
+    let is_json = |media_type: &str| {
+        let media_type = media_type.split(';').next().unwrap_or_default().trim();
+        media_type == "application/json" || media_type.ends_with("+json")
+    };
+    let (media_type, _) = content
+        .filter(|(media_type, content)| is_json(media_type) && content.get("schema").is_some())
+        .min_by_key(|(media_type, _)| !media_type.starts_with("application/json"))
+        .ok_or_else(|| {
+            error(
+                &response_pointer,
+                "the response has no JSON content with a schema",
+            )
+        })?;
+    let content_pointer = child_pointer(&response_pointer, "content");
+    Ok(child_pointer(
+        &child_pointer(&content_pointer, media_type),
+        "schema",
+    ))
+}
+
+/// Parses a schema of an OpenAPI 3.0 or 3.1 document into a SchemaState, so that data can be
+/// produced from it: a component, or the JSON content of a response.
+///
+/// The schema is parsed like `parse_json_schema` parses a JSON Schema, with `$ref`s resolved
+/// against the whole document, so that components can refer to each other. Schemas marked
+/// `nullable`, as OpenAPI 3.0 does, produce nulls too.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{parse_openapi, OpenApiSchema, SchemaDefaults, SchemaState, StringType};
+/// use serde_json::json;
+///
+/// let document = json!({
+///     "openapi": "3.0.3",
+///     "paths": {
+///         "/users": {
+///             "get": {
+///                 "responses": {
+///                     "200": {
+///                         "content": {
+///                             "application/json": {
+///                                 "schema": {
+///                                     "type": "array",
+///                                     "items": { "$ref": "#/components/schemas/User" }
+///                                 }
+///                             }
+///                         }
+///                     }
+///                 }
+///             }
+///         }
+///     },
+///     "components": {
+///         "schemas": {
+///             "User": {
+///                 "type": "object",
+///                 "properties": { "email": { "type": "string", "format": "email" } },
+///                 "required": ["email"]
+///             }
+///         }
+///     }
+/// });
+///
+/// let user = parse_openapi(
+///     &document,
+///     &OpenApiSchema::Component("User".to_owned()),
+///     &SchemaDefaults::default(),
+/// )
+/// .unwrap();
+/// let SchemaState::Object { required, .. } = &user.schema else { panic!() };
+/// assert_eq!(required["email"], SchemaState::String(StringType::Email));
+///
+/// let users = parse_openapi(
+///     &document,
+///     &OpenApiSchema::Response { path: "/users".to_owned(), status: "200".to_owned() },
+///     &SchemaDefaults::default(),
+/// )
+/// .unwrap();
+/// assert!(matches!(users.schema, SchemaState::Array { .. }));
+/// ```
+pub fn parse_openapi(
+    document: &serde_json::Value,
+    schema: &OpenApiSchema,
+    defaults: &SchemaDefaults,
+) -> Result<ParsedSchema, DrivelError> {
+    let pointer = match schema {
+        OpenApiSchema::Component(name) => {
+            let pointer = child_pointer("#/components/schemas", name);
+            if document.pointer(&pointer[1..]).is_none() {
+                return Err(error(
+                    "#/components/schemas",
+                    format!("no component named {:?}", name),
+                )
+                .into());
+            }
+            pointer
+        }
+        OpenApiSchema::Response { path, status } => response_pointer(document, path, status)?,
+    };
+    // the schemas of OpenAPI 3.0 are an extended subset of an early draft, those of 3.1 are
+    // Draft 2020-12
+    let draft = match document.get("openapi").and_then(|v| v.as_str()) {
+        Some(version) if version.starts_with("3.0") => SchemaDraft::Draft7,
+        _ => SchemaDraft::Draft2020_12,
+    };
+    parse_subschema(document, &pointer, defaults, true, draft, None)
+}
+
+/// Turns a name into the name of a component, which may only hold letters, digits, `.`, `-`
+/// and `_`.
+fn component_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Exports named schemas as an OpenAPI 3.1 document, with the records of each schema as a
+/// component under `components.schemas`: the elements of an array at the root, or the root
+/// itself.
+///
+/// OpenAPI 3.1 describes data with JSON Schema (draft 2020-12), so each component is written
+/// like `to_json_schema` writes a document, without `$schema`. The document has no paths, so
+/// that the components can be referred to from the paths of an API, as
+/// `#/components/schemas/<name>`. Characters that component names cannot hold become `_`, and
+/// names that are taken already are followed by a number, like `user_events2`.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_openapi, InferenceOptions};
+/// use serde_json::json;
+///
+/// let users = infer_schema(json!([{ "id": 1 }]), &InferenceOptions::default());
+/// let orders = infer_schema(json!([{ "total": 9.5 }]), &InferenceOptions::default());
+///
+/// let document = to_openapi([("User", &users), ("Order", &orders)]);
+///
+/// assert_eq!(document["openapi"], "3.1.0");
+/// assert_eq!(
+///     document["components"]["schemas"]["User"],
+///     json!({
+///         "type": "object",
+///         "properties": { "id": { "type": "integer", "minimum": 1, "maximum": 1 } },
+///         "required": ["id"]
+///     })
+/// );
+/// assert_eq!(document["components"]["schemas"]["Order"]["required"], json!(["total"]));
+/// ```
+pub fn to_openapi<'a>(
+    schemas: impl IntoIterator<Item = (&'a str, &'a SchemaState)>,
+) -> serde_json::Value {
+    let mut names = HashSet::new();
+    let components = schemas
+        .into_iter()
+        .map(|(name, schema)| {
+            let record = match schema {
+                SchemaState::Array { schema, .. } => schema,
+                schema => schema,
+            };
+            (
+                unique_name(component_name(name), &mut names),
+                to_json_schema_inner(record),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>();
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "Inferred schemas",
+            "version": "1.0.0"
+        },
+        "components": {
+            "schemas": components
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+    use crate::StringType;
+
+    #[test]
+    fn writes_a_component_for_each_named_schema() {
+        let user = SchemaState::Object {
+            required: IndexMap::from_iter([(
+                "email".to_owned(),
+                SchemaState::String(StringType::Email),
+            )]),
+            optional: IndexMap::new(),
+        };
+
+        assert_eq!(
+            to_openapi([
+                ("User", &user),
+                ("user events", &SchemaState::Boolean),
+                ("user/events", &SchemaState::Null),
+            ]),
+            json!({
+                "openapi": "3.1.0",
+                "info": { "title": "Inferred schemas", "version": "1.0.0" },
+                "components": {
+                    "schemas": {
+                        "User": {
+                            "type": "object",
+                            "properties": { "email": { "type": "string", "format": "email" } },
+                            "required": ["email"]
+                        },
+                        "user_events": { "type": "boolean" },
+                        "user_events2": { "type": "null" }
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn reads_responses_by_ref_and_nullable_schemas() {
+        let document = json!({
+            "openapi": "3.0.3",
+            "paths": {
+                "/orders/{id}": {
+                    "post": {
+                        "responses": {
+                            "404": { "$ref": "#/components/responses/NotFound" }
+                        }
+                    }
+                }
+            },
+            "components": {
+                "responses": {
+                    "NotFound": {
+                        "content": {
+                            "text/plain": { "schema": { "type": "string" } },
+                            "application/problem+json": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "detail": { "type": "string", "nullable": true, "maxLength": 4 }
+                                    },
+                                    "required": ["detail"]
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        let parsed = parse_openapi(
+            &document,
+            &OpenApiSchema::Response {
+                path: "/orders/{id}".to_owned(),
+                status: "404".to_owned(),
+            },
+            &SchemaDefaults::default(),
+        )
+        .unwrap();
+        let SchemaState::Object { required, .. } = parsed.schema else {
+            panic!()
+        };
+        assert!(
+            matches!(&required["detail"], SchemaState::Nullable(inner) if matches!(**inner, SchemaState::String(_)))
+        );
+
+        let missing = parse_openapi(
+            &document,
+            &OpenApiSchema::Response {
+                path: "/orders/{id}".to_owned(),
+                status: "200".to_owned(),
+            },
+            &SchemaDefaults::default(),
+        );
+        assert_eq!(
+            missing.unwrap_err().to_string(),
+            "#/paths/~1orders~1{id}: no operation has a response with status \"200\""
+        );
+    }
+}
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+
+use fake::faker::{
+    address::raw::{CityName, CountryName, StreetName, ZipCode},
+    company::raw::CompanyName,
+    internet::raw::{FreeEmail, IPv4, Username},
+    lorem::raw::{Sentence, Word},
+    name::raw::{FirstName, LastName, Name},
+    phone_number::raw::PhoneNumber,
+};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{locale::fake_in, DrivelError, Locale};
+
+/// The faker generators that can be used in an override.
+pub const FAKER_GENERATORS: [&str; 14] = [
+    "name",
+    "first_name",
+    "last_name",
+    "email",
+    "username",
+    "phone",
+    "city",
+    "country",
+    "street",
+    "zip",
+    "company",
+    "word",
+    "sentence",
+    "ipv4",
+];
+
+/// A user-specified replacement for the values produced at a given path.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Override {
+    /// Always produce this value.
+    Fixed(serde_json::Value),
+    /// Produce one of these values, picked at random.
+    OneOf(Vec<serde_json::Value>),
+    /// Produce a value using one of the named faker generators (see `FAKER_GENERATORS`).
+    Faker(String),
+    /// Produce an integer in this (inclusive) range.
+    IntRange { min: i64, max: i64 },
+    /// Produce a float in this (inclusive) range.
+    FloatRange { min: f64, max: f64 },
+    /// Produce one of the values in the pool with this name, which every path drawing from the
+    /// pool shares, so that the same values recur across them. The pool holds the largest `size`
+    /// given for it, or `DEFAULT_POOL_SIZE` values, produced from the schema of the first path
+    /// (in path order) that draws from it.
+    Pool { name: String, size: Option<usize> },
+}
+
+/// The number of values in a pool whose size is not given.
+pub const DEFAULT_POOL_SIZE: usize = 100;
+
+impl Override {
+    /// Produces a value for this override, drawing from `pools` for pool overrides, and
+    /// producing fake values in `locale`.
+    pub(crate) fn produce(
+        &self,
+        pools: &HashMap<String, Vec<serde_json::Value>>,
+        locale: Locale,
+        rng: &mut impl Rng,
+    ) -> serde_json::Value {
+        match self {
+            Override::Fixed(value) => value.clone(),
+            Override::OneOf(values) => values[rng.gen_range(0..values.len())].clone(),
+            Override::Faker(generator) => {
+                let value: String = match generator.as_str() {
+                    "name" => fake_in!(locale, rng, Name),
+                    "first_name" => fake_in!(locale, rng, FirstName),
+                    "last_name" => fake_in!(locale, rng, LastName),
+                    "email" => fake_in!(locale, rng, FreeEmail),
+                    "username" => fake_in!(locale, rng, Username),
+                    "phone" => fake_in!(locale, rng, PhoneNumber),
+                    "city" => fake_in!(locale, rng, CityName),
+                    "country" => fake_in!(locale, rng, CountryName),
+                    "street" => fake_in!(locale, rng, StreetName),
+                    "zip" => fake_in!(locale, rng, ZipCode),
+                    "company" => fake_in!(locale, rng, CompanyName),
+                    "word" => fake_in!(locale, rng, Word),
+                    "sentence" => fake_in!(locale, rng, Sentence, 3..10),
+                    "ipv4" => fake_in!(locale, rng, IPv4),
+                    _ => unreachable!("faker generators are validated when parsing"),
+                };
+                serde_json::Value::String(value)
+            }
+            Override::IntRange { min, max } => {
+                serde_json::Value::Number(rng.gen_range(*min..=*max).into())
+            }
+            Override::FloatRange { min, max } => {
+                let number = crate::produce::random_float(*min, *max, rng);
+                serde_json::Value::Number(serde_json::Number::from_f64(number).unwrap())
+            }
+            // a pool is empty when its paths are not part of the schema
+            Override::Pool { name, .. } => pools
+                .get(name)
+                .and_then(|values| values.choose(rng))
+                .cloned()
+                .unwrap_or(serde_json::Value::Null),
+        }
+    }
+
+    fn from_json(value: serde_json::Value) -> Result<Self, String> {
+        if let Some(range) = value.as_str().and_then(Override::from_range) {
+            return range;
+        }
+        let serde_json::Value::Object(mut table) = value else {
+            return Ok(Override::Fixed(value));
+        };
+
+        if let Some(value) = table.remove("value") {
+            return Ok(Override::Fixed(value));
+        }
+        if let Some(values) = table.remove("one_of") {
+            return match values {
+                serde_json::Value::Array(values) if !values.is_empty() => {
+                    Ok(Override::OneOf(values))
+                }
+                _ => Err("`one_of` must be a non-empty list of values".to_owned()),
+            };
+        }
+        if let Some(generator) = table.remove("faker") {
+            return match generator.as_str() {
+                Some(name) if FAKER_GENERATORS.contains(&name) => {
+                    Ok(Override::Faker(name.to_owned()))
+                }
+                _ => Err(format!(
+                    "`faker` must be one of: {}",
+                    FAKER_GENERATORS.join(", ")
+                )),
+            };
+        }
+        if let Some(name) = table.remove("pool") {
+            let size = match table.remove("size") {
+                None => None,
+                Some(size) => match size.as_u64() {
+                    Some(size) if size > 0 => Some(size as usize),
+                    _ => return Err("`size` must be a positive integer".to_owned()),
+                },
+            };
+            return match name {
+                serde_json::Value::String(name) => Ok(Override::Pool { name, size }),
+                _ => Err("`pool` must be the name of a pool".to_owned()),
+            };
+        }
+        if let (Some(min), Some(max)) = (table.get("min"), table.get("max")) {
+            if let (Some(min), Some(max)) = (min.as_i64(), max.as_i64()) {
+                if min <= max {
+                    return Ok(Override::IntRange { min, max });
+                }
+            } else if let (Some(min), Some(max)) = (min.as_f64(), max.as_f64()) {
+                if min <= max {
+                    return Ok(Override::FloatRange { min, max });
+                }
+            }
+            return Err("`min` and `max` must be numbers, with `min` <= `max`".to_owned());
+        }
+
+        Err(
+            "expected a plain value or a table with `value`, `one_of`, `faker`, `pool`, or `min` and `max`"
+                .to_owned(),
+        )
+    }
+
+    /// Parses a range written as `min..max`, like `18..65` or `0.5..2.5`, if both of its ends
+    /// are numbers; other strings are plain values.
+    fn from_range(s: &str) -> Option<Result<Self, String>> {
+        let (min, max) = s.split_once("..")?;
+        let (min, max) = (min.trim(), max.trim());
+        let range = if let (Ok(min), Ok(max)) = (min.parse::<i64>(), max.parse::<i64>()) {
+            (min <= max).then_some(Override::IntRange { min, max })
+        } else {
+            let (min, max) = (min.parse::<f64>().ok()?, max.parse::<f64>().ok()?);
+            (min.is_finite() && max.is_finite() && min <= max)
+                .then_some(Override::FloatRange { min, max })
+        };
+        Some(range.ok_or_else(|| format!("the range {:?} must have `min` <= `max`", s)))
+    }
+}
+
+/// A node in the tree of overrides, mirroring the structure of the schema they apply to.
+#[derive(Default, Debug, Clone)]
+pub(crate) struct OverrideNode {
+    pub(crate) value: Option<Override>,
+    pub(crate) fields: HashMap<String, OverrideNode>,
+    pub(crate) elements: Option<Box<OverrideNode>>,
+    /// The probability that a nullable value here is produced as null, instead of
+    /// `ProduceOptions::null_probability`.
+    pub(crate) null_probability: Option<f64>,
+    /// The probability that the field here, if optional, is produced, instead of
+    /// `ProduceOptions::optional_probability`.
+    pub(crate) optional_probability: Option<f64>,
+}
+
+impl OverrideNode {
+    /// Returns true if an override pins the values produced here or within.
+    pub(crate) fn pins_values(&self) -> bool {
+        self.value.is_some()
+            || self.fields.values().any(OverrideNode::pins_values)
+            || self
+                .elements
+                .as_deref()
+                .is_some_and(OverrideNode::pins_values)
+    }
+}
+
+/// A set of overrides that pin the values produced at specific paths.
+///
+/// Paths use the same notation as elsewhere in drivel: object fields are separated by `.`,
+/// and `[]` refers to the elements of an array (e.g. `users[].address.city`). When the schema
+/// describes an array at its root, paths are relative to the elements of that array.
+#[derive(Default, Debug, Clone)]
+pub struct Overrides {
+    pub(crate) root: OverrideNode,
+}
+
+impl Overrides {
+    /// Returns an empty set of overrides.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if no overrides have been added.
+    pub fn is_empty(&self) -> bool {
+        self.root.value.is_none()
+            && self.root.fields.is_empty()
+            && self.root.elements.is_none()
+            && self.root.null_probability.is_none()
+            && self.root.optional_probability.is_none()
+    }
+
+    /// Pins the values produced at `path` to the given override.
+    pub fn insert(&mut self, path: &str, value: Override) {
+        self.node_mut(path).value = Some(value);
+    }
+
+    /// Sets the probability that a nullable value at `path` is produced as null.
+    pub(crate) fn insert_null_probability(&mut self, path: &str, probability: f64) {
+        self.node_mut(path).null_probability = Some(probability);
+    }
+
+    /// Sets the probability that an optional field at `path` is produced.
+    pub(crate) fn insert_optional_probability(&mut self, path: &str, probability: f64) {
+        self.node_mut(path).optional_probability = Some(probability);
+    }
+
+    /// Returns the node at `path`, adding it and the nodes leading to it if need be.
+    fn node_mut(&mut self, path: &str) -> &mut OverrideNode {
+        let mut node = &mut self.root;
+        for segment in path.split('.').filter(|s| !s.is_empty()) {
+            let key = segment.trim_end_matches("[]");
+            if !key.is_empty() {
+                node = node.fields.entry(key.to_owned()).or_default();
+            }
+            for _ in 0..(segment.len() - key.len()) / 2 {
+                node = node.elements.get_or_insert_with(Box::default);
+            }
+        }
+        node
+    }
+
+    /// Parses a set of overrides from a TOML document that maps paths to overrides.
+    ///
+    /// Plain values (strings, numbers, booleans and lists) are produced as they are, except for
+    /// strings holding a range of numbers like `"18..65"`, which produce a number in that range
+    /// (use `{ value = "18..65" }` for the string itself); tables select a different kind of
+    /// override.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use drivel::Overrides;
+    ///
+    /// let overrides = Overrides::from_toml(r#"
+    ///     "users[].status" = "active"
+    ///     "users[].age" = { min = 18, max = 65 }
+    ///     "users[].score" = "0.5..2.5"
+    ///     "users[].role" = { one_of = ["admin", "member"] }
+    ///     "users[].name" = { faker = "name" }
+    ///     "users[].settings" = { value = { theme = "dark" } }
+    ///     "users[].created_by" = { pool = "admins", size = 5 }
+    ///     "users[].updated_by" = { pool = "admins" }
+    /// "#).unwrap();
+    ///
+    /// assert!(!overrides.is_empty());
+    /// ```
+    pub fn from_toml(document: &str) -> Result<Self, DrivelError> {
+        let table: serde_json::Map<String, serde_json::Value> = toml::from_str(document)?;
+
+        let mut overrides = Self::new();
+        for (path, value) in table {
+            let value =
+                Override::from_json(value).map_err(|message| DrivelError::InvalidOverride {
+                    path: path.clone(),
+                    message,
+                })?;
+            overrides.insert(&path, value);
+        }
+        Ok(overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_toml_overrides_into_tree() {
+        let overrides = Overrides::from_toml(
+            r#"
+            "users[].age" = { min = 18, max = 65 }
+            "tags[]" = { one_of = ["a", "b"] }
+            "#,
+        )
+        .unwrap();
+
+        let age = overrides.root.fields["users"]
+            .elements
+            .as_ref()
+            .unwrap()
+            .fields["age"]
+            .value
+            .as_ref();
+        assert_eq!(age, Some(&Override::IntRange { min: 18, max: 65 }));
+
+        let tags = overrides.root.fields["tags"].elements.as_ref().unwrap();
+        assert_eq!(
+            tags.value,
+            Some(Override::OneOf(vec![json!("a"), json!("b")]))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_faker() {
+        let result = Overrides::from_toml(r#"name = { faker = "unicorn" }"#);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("invalid override for name: `faker` must be one of"));
+    }
+
+    #[test]
+    fn parses_ranges_written_as_strings() {
+        let overrides = Overrides::from_toml(
+            r#"
+            age = "18..65"
+            score = "0.5..2.5"
+            version = "v1..v2"
+            label = { value = "1..2" }
+            "#,
+        )
+        .unwrap();
+
+        let value = |field: &str| overrides.root.fields[field].value.clone();
+        assert_eq!(value("age"), Some(Override::IntRange { min: 18, max: 65 }));
+        assert_eq!(
+            value("score"),
+            Some(Override::FloatRange { min: 0.5, max: 2.5 })
+        );
+        assert_eq!(value("version"), Some(Override::Fixed(json!("v1..v2"))));
+        assert_eq!(value("label"), Some(Override::Fixed(json!("1..2"))));
+
+        let result = Overrides::from_toml(r#"age = "65..18""#);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid override for age: the range \"65..18\" must have `min` <= `max`"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Helpers for building the human-readable paths used to refer to fields within a schema,
+//! e.g. `users[].address.city`.
+
+/// Returns the path of the field `key` within the object found at `parent`.
+pub(crate) fn key(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Returns the path of the elements of the array found at `parent`.
+pub(crate) fn elements(parent: &str) -> String {
+    format!("{}[]", parent)
+}
+
+/// Returns the path of the element at position `index` of the tuple found at `parent`.
+pub(crate) fn index(parent: &str, index: usize) -> String {
+    format!("{}[{}]", parent, index)
+}
+
+/// Formats a path for display, making the (empty) root path visible.
+pub(crate) fn display(path: &str) -> &str {
+    if path.is_empty() {
+        "<root>"
+    } else {
+        path
+    }
+}
+
+/// A step along a path: into a field of an object, or into every element of an array.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Step {
+    Key(String),
+    Elements,
+}
+
+/// Returns the steps of a path, like `users[].address.city`.
+pub(crate) fn parse_steps(path: &str) -> Vec<Step> {
+    let mut steps = vec![];
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        let key = segment.trim_end_matches("[]");
+        if !key.is_empty() {
+            steps.push(Step::Key(key.to_owned()));
+        }
+        for _ in 0..(segment.len() - key.len()) / 2 {
+            steps.push(Step::Elements);
+        }
+    }
+    steps
+}
+
+/// Calls `f` with every value found at `steps` within `value`.
+pub(crate) fn visit_mut(
+    value: &mut serde_json::Value,
+    steps: &[Step],
+    f: &mut impl FnMut(&mut serde_json::Value),
+) {
+    match (steps.split_first(), value) {
+        (None, value) => f(value),
+        (Some((Step::Key(key), rest)), serde_json::Value::Object(map)) => {
+            if let Some(value) = map.get_mut(key) {
+                visit_mut(value, rest, f);
+            }
+        }
+        (Some((Step::Elements, rest)), serde_json::Value::Array(items)) => {
+            for item in items {
+                visit_mut(item, rest, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the steps of a path given relative to the elements of an array at the root, as
+/// paths given by users are, as steps from the root.
+pub(crate) fn from_root(steps: Vec<Step>, root_is_array: bool) -> Vec<Step> {
+    match steps.first() {
+        Some(Step::Elements) => steps,
+        _ if root_is_array => [vec![Step::Elements], steps].concat(),
+        _ => steps,
+    }
+}
+
+/// Returns the steps of a path given relative to the elements of an array at the root as steps
+/// from one of those elements.
+pub(crate) fn from_record(mut steps: Vec<Step>) -> Vec<Step> {
+    if steps.first() == Some(&Step::Elements) {
+        steps.remove(0);
+    }
+    steps
+}
@@ -0,0 +1,99 @@
+//! Generating and matching strings for the `pattern` string type.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use rand::Rng;
+
+/// The maximum number of repetitions generated for unbounded repetitions like `*` and `+`.
+const MAX_REPEAT: u32 = 16;
+
+/// The pattern of the keys of maps whose keys can be any string, like Avro maps.
+pub(crate) const ANY_KEY: &str = "^[a-z][a-z0-9_]{0,15}$";
+
+lazy_static! {
+    static ref GENERATORS: Mutex<HashMap<String, Arc<rand_regex::Regex>>> =
+        Mutex::new(HashMap::new());
+    static ref MATCHERS: Mutex<HashMap<String, Arc<regex::Regex>>> = Mutex::new(HashMap::new());
+}
+
+/// Removes the anchors at the start and end of a pattern: the strings generated from a
+/// pattern match it in its entirety anyway, and anchors cannot be generated from.
+fn strip_anchors(pattern: &str) -> &str {
+    let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+    match pattern.strip_suffix('$') {
+        Some(stripped) if !stripped.ends_with('\\') => stripped,
+        _ => pattern,
+    }
+}
+
+fn compile_generator(pattern: &str) -> Result<rand_regex::Regex, String> {
+    let pattern = strip_anchors(pattern);
+    // classes like `\d` and `\w` are generated from ASCII where possible, which is what the
+    // authors of most patterns have in mind
+    let hir = regex_syntax::ParserBuilder::new()
+        .unicode(false)
+        .build()
+        .parse(pattern)
+        .or_else(|_| {
+            regex_syntax::Parser::new()
+                .parse(pattern)
+                .map_err(|err| err.to_string())
+        })?;
+    rand_regex::Regex::with_hir(hir, MAX_REPEAT).map_err(|err| err.to_string())
+}
+
+/// Checks that strings can be generated from, and matched against, a pattern.
+pub(crate) fn check(pattern: &str) -> Result<(), String> {
+    regex::Regex::new(pattern).map_err(|err| err.to_string())?;
+    compile_generator(pattern).map(|_| ())
+}
+
+/// Generates a random string that matches `pattern`, which must have passed `check`.
+pub(crate) fn generate(pattern: &str, rng: &mut impl Rng) -> String {
+    let generator = {
+        let mut generators = GENERATORS.lock().unwrap();
+        generators
+            .entry(pattern.to_owned())
+            .or_insert_with(|| {
+                Arc::new(compile_generator(pattern).expect("patterns are checked when parsing"))
+            })
+            .clone()
+    };
+    rng.sample::<String, _>(generator.as_ref())
+}
+
+/// Returns true if `s` matches `pattern`. Invalid patterns match nothing.
+pub(crate) fn is_match(pattern: &str, s: &str) -> bool {
+    let matcher = {
+        let mut matchers = MATCHERS.lock().unwrap();
+        match matchers.get(pattern) {
+            Some(matcher) => matcher.clone(),
+            None => match regex::Regex::new(pattern) {
+                Ok(matcher) => {
+                    let matcher = Arc::new(matcher);
+                    matchers.insert(pattern.to_owned(), matcher.clone());
+                    matcher
+                }
+                Err(_) => return false,
+            },
+        }
+    };
+    matcher.is_match(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_strings_matching_anchored_patterns() {
+        for pattern in [r"^cus_\d{4}$", r"[A-Z]{3}-\d+", r"^(red|green)\.v[0-9]$"] {
+            check(pattern).unwrap();
+            for _ in 0..20 {
+                let s = generate(pattern, &mut rand::thread_rng());
+                assert!(is_match(pattern, &s), "{:?} does not match {}", s, pattern);
+            }
+        }
+    }
+}
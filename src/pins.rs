@@ -0,0 +1,490 @@
+//! Pinning the types of fields by path, so that odd values cannot downgrade them.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+
+use crate::{
+    infer::integer_value,
+    infer_string::{dates, email, non_finite_float, url_host, uuid},
+    path, DistinctCount, DrivelError, NumberType, SchemaDefaults, SchemaState, StringType,
+    Violation,
+};
+
+/// A type that the values at a path are pinned to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinnedType {
+    String,
+    Uuid,
+    Email,
+    Url,
+    Hostname,
+    /// An ISO 8601 date, e.g. `2024-01-31`.
+    Date,
+    /// An RFC 3339 datetime, e.g. `2024-01-31T12:00:00Z`.
+    DateTime,
+    Integer,
+    /// Any number, or one of the strings used for NaN and infinite floats.
+    Float,
+    Boolean,
+}
+
+const PINNED_TYPES: [(&str, PinnedType); 10] = [
+    ("string", PinnedType::String),
+    ("uuid", PinnedType::Uuid),
+    ("email", PinnedType::Email),
+    ("url", PinnedType::Url),
+    ("hostname", PinnedType::Hostname),
+    ("date", PinnedType::Date),
+    ("datetime", PinnedType::DateTime),
+    ("integer", PinnedType::Integer),
+    ("float", PinnedType::Float),
+    ("boolean", PinnedType::Boolean),
+];
+
+impl FromStr for PinnedType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PINNED_TYPES
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, pinned)| *pinned)
+            .ok_or_else(|| {
+                format!(
+                    "unknown type {:?}, expected one of {}",
+                    s,
+                    PINNED_TYPES
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+impl Display for PinnedType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, _) = PINNED_TYPES
+            .iter()
+            .find(|(_, pinned)| pinned == self)
+            .expect("every type has a name");
+        write!(f, "{}", name)
+    }
+}
+
+impl PinnedType {
+    fn conforms(self, value: &serde_json::Value) -> bool {
+        match (self, value) {
+            (PinnedType::String, serde_json::Value::String(_)) => true,
+            (PinnedType::Uuid, serde_json::Value::String(s)) => uuid(s).is_some(),
+            (PinnedType::Email, serde_json::Value::String(s)) => email(s).is_some(),
+            (PinnedType::Url, serde_json::Value::String(s)) => url_host(s) == Some(StringType::Url),
+            (PinnedType::Hostname, serde_json::Value::String(s)) => {
+                url_host(s) == Some(StringType::Hostname)
+            }
+            (PinnedType::Date, serde_json::Value::String(s)) => {
+                matches!(dates(s), Some(StringType::IsoDate { .. }))
+            }
+            (PinnedType::DateTime, serde_json::Value::String(s)) => {
+                matches!(dates(s), Some(StringType::DateTimeISO8601 { .. }))
+            }
+            (PinnedType::Integer, serde_json::Value::Number(n)) => integer_value(n).is_some(),
+            (PinnedType::Float, serde_json::Value::Number(_)) => true,
+            (PinnedType::Float, serde_json::Value::String(s)) => non_finite_float(s).is_some(),
+            (PinnedType::Boolean, serde_json::Value::Bool(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Narrows the schema inferred from the conforming values to this type, falling back to the
+    /// type bounded by `defaults` when no values conformed.
+    pub(crate) fn narrow(self, inferred: SchemaState, defaults: &SchemaDefaults) -> SchemaState {
+        match (self, inferred) {
+            (PinnedType::String, inferred @ SchemaState::String(_))
+            | (PinnedType::Uuid, inferred @ SchemaState::String(StringType::UUID))
+            | (PinnedType::Email, inferred @ SchemaState::String(StringType::Email))
+            | (PinnedType::Url, inferred @ SchemaState::String(StringType::Url))
+            | (PinnedType::Hostname, inferred @ SchemaState::String(StringType::Hostname))
+            | (PinnedType::Date, inferred @ SchemaState::String(StringType::IsoDate { .. }))
+            | (
+                PinnedType::DateTime,
+                inferred @ SchemaState::String(StringType::DateTimeISO8601 { .. }),
+            )
+            | (PinnedType::Integer, inferred @ SchemaState::Number(NumberType::Integer { .. }))
+            | (PinnedType::Integer, inferred @ SchemaState::Number(NumberType::Enum { .. }))
+            | (
+                PinnedType::Integer,
+                inferred @ SchemaState::Number(NumberType::BigInteger { .. }),
+            )
+            | (PinnedType::Float, inferred @ SchemaState::Number(NumberType::Float { .. }))
+            | (PinnedType::Float, inferred @ SchemaState::Number(NumberType::Decimal { .. }))
+            | (PinnedType::Boolean, inferred @ SchemaState::Boolean) => inferred,
+            (
+                PinnedType::Float,
+                SchemaState::Number(
+                    number_type @ (NumberType::Integer { .. }
+                    | NumberType::Enum { .. }
+                    | NumberType::BigInteger { .. }),
+                ),
+            ) => {
+                let (min, max) = number_type.integer_range().unwrap_or_default();
+                SchemaState::Number(NumberType::Float {
+                    min: min as f64,
+                    max: max as f64,
+                    non_finite: false,
+                    exclusive_min: false,
+                    exclusive_max: false,
+                })
+            }
+            (PinnedType::String, _) => SchemaState::String(StringType::Unknown {
+                strings_seen: vec![],
+                chars_seen: HashMap::new(),
+                n_strings_seen: 0,
+                distinct: DistinctCount::new(),
+                n_chars_seen: 0,
+                min_length: Some(*defaults.string_lengths.start()),
+                max_length: Some(*defaults.string_lengths.end()),
+                prefix: String::new(),
+                suffix: String::new(),
+                structure: None,
+            }),
+            (PinnedType::Uuid, _) => SchemaState::String(StringType::UUID),
+            (PinnedType::Email, _) => SchemaState::String(StringType::Email),
+            (PinnedType::Url, _) => SchemaState::String(StringType::Url),
+            (PinnedType::Hostname, _) => SchemaState::String(StringType::Hostname),
+            (PinnedType::Date, _) => SchemaState::String(StringType::IsoDate { range: None }),
+            (PinnedType::DateTime, _) => {
+                SchemaState::String(StringType::DateTimeISO8601 { range: None })
+            }
+            (PinnedType::Integer, _) => SchemaState::Number(NumberType::Integer {
+                min: *defaults.integers.start(),
+                max: *defaults.integers.end(),
+            }),
+            (PinnedType::Float, _) => SchemaState::Number(NumberType::Float {
+                min: *defaults.floats.start(),
+                max: *defaults.floats.end(),
+                non_finite: false,
+                exclusive_min: false,
+                exclusive_max: false,
+            }),
+            (PinnedType::Boolean, _) => SchemaState::Boolean,
+        }
+    }
+}
+
+/// The types pinned at specific paths. Values at these paths that do not have the pinned type
+/// are left out of inference, so that they cannot widen the type to an unknown string or a
+/// union; `TypePins::violations` reports them instead.
+///
+/// Paths separate object fields with `.` and refer to array elements with `[]`, relative to the
+/// records being inferred.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypePins {
+    paths: HashMap<String, PinnedType>,
+    defaults: SchemaDefaults,
+}
+
+impl TypePins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Pins the type of the values at `path`.
+    pub fn pin(&mut self, path: &str, pinned: PinnedType) {
+        self.paths.insert(path.to_owned(), pinned);
+    }
+
+    /// Bounds the numbers and strings of pinned paths where no values of the pinned type were
+    /// found by `defaults`. Default = `SchemaDefaults::default()`.
+    pub fn set_defaults(&mut self, defaults: SchemaDefaults) {
+        self.defaults = defaults;
+    }
+
+    /// Parses type pins from a TOML document with a `paths` table, mapping paths to the names
+    /// of their types: `string`, `uuid`, `email`, `url`, `hostname`, `date`, `datetime`,
+    /// `integer`, `float` or `boolean`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{PinnedType, TypePins};
+    ///
+    /// let pins = TypePins::from_toml(r#"
+    ///     [paths]
+    ///     "user.id" = "uuid"
+    ///     "orders[].total" = "float"
+    /// "#).unwrap();
+    ///
+    /// let mut expected = TypePins::new();
+    /// expected.pin("user.id", PinnedType::Uuid);
+    /// expected.pin("orders[].total", PinnedType::Float);
+    /// assert_eq!(pins, expected);
+    /// ```
+    pub fn from_toml(document: &str) -> Result<Self, DrivelError> {
+        let table: serde_json::Map<String, serde_json::Value> = toml::from_str(document)?;
+
+        let mut pins = Self::new();
+        for (key, value) in table {
+            if key != "paths" {
+                return Err(DrivelError::InvalidTypePin {
+                    path: key,
+                    message: "unknown key, expected a \"paths\" table".to_owned(),
+                });
+            }
+            let serde_json::Value::Object(paths) = value else {
+                return Err(DrivelError::InvalidTypePin {
+                    path: key,
+                    message: "expected a table mapping paths to types".to_owned(),
+                });
+            };
+            for (path, pinned) in paths {
+                let pinned = match pinned.as_str().map(str::parse) {
+                    Some(Ok(pinned)) => pinned,
+                    Some(Err(message)) => {
+                        return Err(DrivelError::InvalidTypePin { path, message })
+                    }
+                    None => {
+                        return Err(DrivelError::InvalidTypePin {
+                            path,
+                            message: "expected the name of a type".to_owned(),
+                        })
+                    }
+                };
+                pins.pin(&path, pinned);
+            }
+        }
+        Ok(pins)
+    }
+
+    fn visit<'a>(
+        &self,
+        value: &'a serde_json::Value,
+        current_path: &str,
+        f: &mut impl FnMut(&str, PinnedType, &'a serde_json::Value),
+    ) {
+        if let Some(pinned) = self.paths.get(current_path) {
+            f(current_path, *pinned, value);
+        }
+        match value {
+            serde_json::Value::Array(items) => {
+                let elements_path = path::elements(current_path);
+                for item in items {
+                    self.visit(item, &elements_path, f);
+                }
+            }
+            serde_json::Value::Object(object) => {
+                for (k, v) in object {
+                    self.visit(v, &path::key(current_path, k), f);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns the values at the pinned paths of `records` that have their pinned type, by path.
+    pub(crate) fn conforming_values(
+        &self,
+        records: &[serde_json::Value],
+    ) -> HashMap<String, Vec<serde_json::Value>> {
+        let mut values = HashMap::<String, Vec<serde_json::Value>>::new();
+        for record in records {
+            self.visit(record, "", &mut |path, pinned, value| {
+                let values = values.entry(path.to_owned()).or_default();
+                if pinned.conforms(value) {
+                    values.push(value.clone());
+                }
+            });
+        }
+        values
+    }
+
+    /// Returns the values at the pinned paths of `records` that do not have their pinned type,
+    /// along with the index of the record they were found in. Nulls are not reported, as they
+    /// make a pinned field nullable rather than change its type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{PinnedType, TypePins};
+    /// use serde_json::json;
+    ///
+    /// let mut pins = TypePins::new();
+    /// pins.pin("id", PinnedType::Uuid);
+    /// let records = vec![
+    ///     json!({ "id": "988c2c6d-df1b-4bb9-b837-6ba706c0b4ad" }),
+    ///     json!({ "id": "n/a" }),
+    ///     json!({ "id": null }),
+    /// ];
+    ///
+    /// let violations = pins.violations(&records);
+    /// assert_eq!(violations.len(), 1);
+    /// assert_eq!(violations[0].0, 1);
+    /// assert_eq!(violations[0].1.message, "\"n/a\" is not a valid uuid");
+    /// ```
+    pub fn violations(&self, records: &[serde_json::Value]) -> Vec<(usize, Violation)> {
+        let mut violations = vec![];
+        for (idx, record) in records.iter().enumerate() {
+            self.visit(record, "", &mut |path, pinned, value| {
+                if !value.is_null() && !pinned.conforms(value) {
+                    violations.push((
+                        idx,
+                        Violation {
+                            path: path.to_owned(),
+                            message: format!("{} is not a valid {}", value, pinned),
+                        },
+                    ));
+                }
+            });
+        }
+        violations
+    }
+
+    fn apply_inner(
+        &self,
+        schema: SchemaState,
+        pinned: &mut HashMap<String, SchemaState>,
+        current_path: &str,
+    ) -> SchemaState {
+        if let Some(pinned_type) = self.paths.get(current_path) {
+            let narrowed = pinned_type.narrow(
+                pinned.remove(current_path).unwrap_or(SchemaState::Initial),
+                &self.defaults,
+            );
+            return match schema {
+                SchemaState::Null | SchemaState::Nullable(_) => {
+                    SchemaState::Nullable(Box::new(narrowed))
+                }
+                _ => narrowed,
+            };
+        }
+        match schema {
+            SchemaState::Array {
+                min_length,
+                max_length,
+                unique,
+                schema,
+            } => SchemaState::Array {
+                min_length,
+                max_length,
+                unique,
+                schema: Box::new(self.apply_inner(*schema, pinned, &path::elements(current_path))),
+            },
+            SchemaState::Object { required, optional } => {
+                let mut apply_fields = |fields: IndexMap<String, SchemaState>| {
+                    fields
+                        .into_iter()
+                        .map(|(k, v)| {
+                            let v = self.apply_inner(v, pinned, &path::key(current_path, &k));
+                            (k, v)
+                        })
+                        .collect()
+                };
+                SchemaState::Object {
+                    required: apply_fields(required),
+                    optional: apply_fields(optional),
+                }
+            }
+            SchemaState::Nullable(inner) => {
+                SchemaState::Nullable(Box::new(self.apply_inner(*inner, pinned, current_path)))
+            }
+            schema => schema,
+        }
+    }
+
+    /// Replaces the schemas at the pinned paths with the schemas inferred from their conforming
+    /// values, narrowed to the pinned types.
+    pub(crate) fn apply(
+        &self,
+        schema: SchemaState,
+        mut pinned: HashMap<String, SchemaState>,
+    ) -> SchemaState {
+        self.apply_inner(schema, &mut pinned, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema_from_iter, InferenceOptions};
+
+    #[test]
+    fn keeps_pinned_types_despite_conflicting_values() {
+        let mut pins = TypePins::new();
+        pins.pin("id", PinnedType::Uuid);
+        pins.pin("items[].price", PinnedType::Float);
+        pins.pin("age", PinnedType::Integer);
+        let records = vec![
+            json!({"id": "988c2c6d-df1b-4bb9-b837-6ba706c0b4ad", "age": 30, "items": [{"price": 2}]}),
+            json!({"id": "unknown", "age": "thirty", "items": [{"price": 2.5}]}),
+            json!({"id": null, "age": 40, "items": [{"price": "free"}]}),
+        ];
+        let options = InferenceOptions {
+            type_pins: pins.clone(),
+            ..Default::default()
+        };
+
+        let SchemaState::Object { required, .. } =
+            infer_schema_from_iter(records.clone(), &options)
+        else {
+            panic!("expected object schema");
+        };
+        assert_eq!(
+            required["id"],
+            SchemaState::Nullable(Box::new(SchemaState::String(StringType::UUID)))
+        );
+        assert_eq!(
+            required["age"],
+            SchemaState::Number(NumberType::Integer { min: 30, max: 40 })
+        );
+        assert_eq!(
+            required["items"].to_string_pretty(),
+            "[\n  {\n    \"price\": float (2-2.5)\n  }\n] (1)"
+        );
+
+        assert_eq!(
+            pins.violations(&records)
+                .into_iter()
+                .map(|(idx, violation)| format!("{}: {}", idx, violation))
+                .collect::<Vec<_>>(),
+            vec![
+                "1: id: \"unknown\" is not a valid uuid",
+                "1: age: \"thirty\" is not a valid integer",
+                "2: items[].price: \"free\" is not a valid float",
+            ]
+        );
+    }
+
+    #[test]
+    fn bounds_pinned_types_without_conforming_values_by_the_defaults() {
+        let mut pins = TypePins::new();
+        pins.pin("age", PinnedType::Integer);
+        pins.set_defaults(SchemaDefaults {
+            integers: 5..=9,
+            ..SchemaDefaults::default()
+        });
+        let options = InferenceOptions {
+            type_pins: pins,
+            ..Default::default()
+        };
+
+        let SchemaState::Object { required, .. } =
+            infer_schema_from_iter(vec![json!({"age": "thirty"})], &options)
+        else {
+            panic!("expected object schema");
+        };
+        assert_eq!(
+            required["age"],
+            SchemaState::Number(NumberType::Integer { min: 5, max: 9 })
+        );
+    }
+}
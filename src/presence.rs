@@ -0,0 +1,119 @@
+//! Counting how often the fields found at each path of the input records were present in the
+//! objects holding them, so that data produced from their schema leaves them out as often.
+
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{
+    evidence::{group_digits, percentage},
+    FieldCounts,
+};
+
+/// How many of the objects found at the parent of one path of the input records held the
+/// field at that path.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct FieldPresence {
+    /// The path of the field, e.g. `users[].address.city`.
+    pub path: String,
+    /// The number of objects found at the parent of the path.
+    pub objects: usize,
+    /// The number of those objects that held the field.
+    pub present: usize,
+}
+
+impl FieldPresence {
+    /// Returns the share of objects that held the field.
+    pub fn presence_ratio(&self) -> f64 {
+        self.present as f64 / self.objects as f64
+    }
+}
+
+impl Display for FieldPresence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percentage = percentage(self.present, self.objects);
+        write!(
+            f,
+            "{}: {}% present ({} of {} objects)",
+            self.path,
+            percentage,
+            group_digits(self.present),
+            group_digits(self.objects)
+        )
+    }
+}
+
+/// Counts the objects found at each path of `records` and how many of them held each of their
+/// fields. Only fields left out of some of the objects are reported, as those are the optional
+/// fields; they are sorted by path.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::field_presence;
+/// use serde_json::json;
+///
+/// let records = vec![
+///     json!({ "name": "Ann", "nickname": "annie" }),
+///     json!({ "name": "Bob", "nickname": null }),
+///     json!({ "name": "Cat", "nickname": "kitty" }),
+///     json!({ "name": "Dan" }),
+/// ];
+///
+/// let presence = field_presence(&records);
+///
+/// assert_eq!(presence.len(), 1);
+/// assert_eq!(presence[0].path, "nickname");
+/// assert_eq!(presence[0].presence_ratio(), 0.75);
+/// assert_eq!(presence[0].to_string(), "nickname: 75% present (3 of 4 objects)");
+/// ```
+pub fn field_presence(records: &[serde_json::Value]) -> Vec<FieldPresence> {
+    let counts = FieldCounts::new();
+    counts.count(records);
+    counts.presence()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema, produce_with_options, InferenceOptions, ProduceOptions};
+
+    #[test]
+    fn produces_optional_fields_as_often_as_they_were_present() {
+        let records = (0..1000)
+            .map(|n| match n % 100 {
+                0 => json!({ "id": n, "address": { "city": "Oslo" } }),
+                1..=9 => json!({ "id": n, "email": "ann@example.com", "address": {} }),
+                _ => json!({ "id": n, "email": "ann@example.com", "address": { "city": "Rome" } }),
+            })
+            .collect::<Vec<_>>();
+
+        let presence = field_presence(&records);
+        assert_eq!(
+            presence
+                .iter()
+                .map(|field| field.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "address.city: 91% present (910 of 1,000 objects)",
+                "email: 99% present (990 of 1,000 objects)",
+            ]
+        );
+
+        // paths are relative to the elements of an array at the root
+        let schema = infer_schema(json!(records), &InferenceOptions::default());
+        let mut options = ProduceOptions::new().count(1000).seed(7);
+        for field in &presence {
+            options = options.optional_probability_at(&field.path, field.presence_ratio());
+        }
+        let produced = produce_with_options(&schema, &options).unwrap();
+        let produced = produced.as_array().unwrap();
+        let missing = |present: fn(&serde_json::Value) -> bool| {
+            produced.iter().filter(|record| !present(record)).count()
+        };
+        assert!(missing(|record| record.get("email").is_some()) < 30);
+        assert!((50..=130).contains(&missing(|record| record["address"].get("city").is_some())));
+    }
+}
@@ -1,197 +1,2419 @@
-use chrono::{DateTime, NaiveDate, SubsecRound, Utc};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, SubsecRound, TimeDelta, Timelike, Utc};
 use fake::{
     faker::{
         company::en::Buzzword,
         internet::en::{DomainSuffix, FreeEmail},
-        lorem::en::Word,
+        lorem::en::{Word, Words},
+        phone_number::raw::PhoneNumber,
     },
     Fake, Faker,
 };
-use rand::{random, thread_rng, Rng};
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
 use rayon::prelude::*;
+use serde::ser::{Error as _, SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
 use serde_json::Number;
 
-use crate::{NumberType, SchemaState, StringType};
+use crate::{
+    cancel,
+    derive::Derivations,
+    infer_string::NON_FINITE_FLOATS,
+    locale::fake_in,
+    overrides::OverrideNode,
+    path, pattern,
+    relations::Relations,
+    schema::{format_duration, format_time},
+    series::{SeriesState, TimeSeries},
+    unique::UniqueValues,
+    Cancellation, Cancelled, DrivelError, Locale, NumberType, Override, Overrides, PathKind,
+    SchemaDefaults, SchemaState, StringType, DEFAULT_MAX_DEPTH, DEFAULT_POOL_SIZE,
+};
+
+/// The share of values produced as NaN or infinity for floats that were seen to be non-finite.
+const NON_FINITE_RATIO: f64 = 0.05;
+
+/// The default value of `ProduceOptions::chunk_size`.
+pub const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// The number of keys of a map, or elements of an array of unique elements, produced per one
+/// wanted, before giving up on values that the schema may not have left to produce.
+const MAX_UNIQUE_ATTEMPTS: usize = 10;
+
+/// The default value of `ProduceOptions::null_probability`.
+pub const DEFAULT_NULL_PROBABILITY: f64 = 0.5;
+
+/// The default value of `ProduceOptions::optional_probability`.
+pub const DEFAULT_OPTIONAL_PROBABILITY: f64 = 0.5;
+
+/// How data is produced from a schema: how many records, how they are randomised, and which
+/// values they hold.
+///
+/// Options are set with a builder, starting from the defaults of `ProduceOptions::new`, so
+/// that options can be added without breaking the code that sets the others.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{produce_with_options, Locale, NumberType, ProduceOptions, SchemaState};
+///
+/// let schema = SchemaState::Array {
+///     min_length: 1,
+///     max_length: 1,
+///     unique: false,
+///     schema: Box::new(SchemaState::Nullable(Box::new(SchemaState::Number(
+///         NumberType::Integer { min: 0, max: 100 },
+///     )))),
+/// };
+///
+/// let options = ProduceOptions::new()
+///     .count(3)
+///     .seed(42)
+///     .null_probability(0.1)
+///     .locale(Locale::FrFr);
+/// let produced = produce_with_options(&schema, &options).unwrap();
+/// assert_eq!(produced.as_array().unwrap().len(), 3);
+///
+/// // the same seed produces the same data
+/// assert_eq!(produce_with_options(&schema, &options).unwrap(), produced);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ProduceOptions {
+    count: usize,
+    seed: Option<u64>,
+    null_probability: f64,
+    optional_probability: f64,
+    max_depth: usize,
+    locale: Locale,
+    overrides: Overrides,
+    null_probabilities: Vec<(String, f64)>,
+    optional_probabilities: Vec<(String, f64)>,
+    cancellation: Option<Cancellation>,
+    edge_cases: bool,
+    unique: Vec<String>,
+    unique_ids: bool,
+    relations: Relations,
+    derivations: Derivations,
+    time_series: Option<TimeSeries>,
+    chunk_size: usize,
+    defaults: SchemaDefaults,
+}
+
+impl Default for ProduceOptions {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            seed: None,
+            null_probability: DEFAULT_NULL_PROBABILITY,
+            optional_probability: DEFAULT_OPTIONAL_PROBABILITY,
+            max_depth: DEFAULT_MAX_DEPTH,
+            locale: Locale::default(),
+            overrides: Overrides::new(),
+            null_probabilities: vec![],
+            optional_probabilities: vec![],
+            cancellation: None,
+            edge_cases: false,
+            unique: vec![],
+            unique_ids: false,
+            relations: Relations::new(),
+            derivations: Derivations::new(),
+            time_series: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            defaults: SchemaDefaults::default(),
+        }
+    }
+}
+
+impl ProduceOptions {
+    /// Returns the default options: a single value, randomised differently every time, with
+    /// even odds of nullable values being null and of optional fields being present.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Produces `count` elements for an array at the root of the schema. Default = 1.
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Seeds the random number generator, so that the same schema and options produce the same
+    /// data every time. Only the timestamps that ULIDs, KSUIDs and ObjectIds start with still
+    /// vary, as they hold the time the data is produced. Default = a random seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// The probability, between 0 and 1, that a nullable value is produced as null.
+    /// Default = `DEFAULT_NULL_PROBABILITY`.
+    pub fn null_probability(mut self, probability: f64) -> Self {
+        self.null_probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The probability, between 0 and 1, that a nullable value at `path` is produced as null,
+    /// instead of `null_probability`, like the share of nulls found there (see `field_nulls`).
+    /// Paths use the notation of overrides, e.g. `users[].deleted_at`, and can be added
+    /// repeatedly. Default = none.
+    pub fn null_probability_at(mut self, path: &str, probability: f64) -> Self {
+        let probability = probability.clamp(0.0, 1.0);
+        self.overrides.insert_null_probability(path, probability);
+        self.null_probabilities.push((path.to_owned(), probability));
+        self
+    }
+
+    /// The probability, between 0 and 1, that an optional field of an object is produced.
+    /// Default = `DEFAULT_OPTIONAL_PROBABILITY`.
+    pub fn optional_probability(mut self, probability: f64) -> Self {
+        self.optional_probability = probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// The probability, between 0 and 1, that an optional field at `path` is produced, instead
+    /// of `optional_probability`, like the share of objects holding it (see `field_presence`).
+    /// Paths use the notation of overrides, e.g. `users[].nickname`, and can be added
+    /// repeatedly. Default = none.
+    pub fn optional_probability_at(mut self, path: &str, probability: f64) -> Self {
+        let probability = probability.clamp(0.0, 1.0);
+        self.overrides
+            .insert_optional_probability(path, probability);
+        self.optional_probabilities
+            .push((path.to_owned(), probability));
+        self
+    }
+
+    /// The number of arrays and objects nested within each other beyond which arrays and
+    /// objects are produced empty, rather than recursing into them, so that schemas nested
+    /// deeper than the stack allows cannot overflow it. Default = `DEFAULT_MAX_DEPTH`.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The locale that fake values pinned by overrides, like names and cities, and phone
+    /// numbers are produced in. Emails, hostnames and URLs are always produced in English, as
+    /// they are limited to ASCII. Default = `Locale::En`.
+    pub fn locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// Replaces the values produced at the paths pinned by `overrides`. Default = none.
+    pub fn overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        for (path, probability) in &self.null_probabilities {
+            self.overrides.insert_null_probability(path, *probability);
+        }
+        for (path, probability) in &self.optional_probabilities {
+            self.overrides
+                .insert_optional_probability(path, *probability);
+        }
+        self
+    }
+
+    /// Gives up producing as soon as `cancellation` is cancelled or its deadline passes.
+    /// Default = never.
+    pub fn cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Deliberately produces boundary values rather than uniformly random ones: the minimum and
+    /// maximum of numbers, dates and lengths, every variant of an enum, empty optional fields
+    /// and nulls. The `n`th element of an array at the root is the `n`th edge case, so that
+    /// e.g. the first holds every minimum and null, and a count at least as large as the
+    /// largest enum makes sure every variant is produced. Default = false.
+    pub fn edge_cases(mut self, edge_cases: bool) -> Self {
+        self.edge_cases = edge_cases;
+        self
+    }
+
+    /// Makes sure that no value is produced twice at `path`, across all of the data produced,
+    /// like for the primary keys of records. Paths use the notation of overrides, e.g.
+    /// `users[].id`, and can be added repeatedly. Values produced before are replaced by
+    /// values produced at random, or, for integers in a range, by the lowest integer in the
+    /// range not produced yet; production fails with `DrivelError::NotUnique` when no such
+    /// value can be found. Nulls are never duplicates. Default = none.
+    pub fn unique(mut self, path: &str) -> Self {
+        self.unique.push(path.to_owned());
+        self
+    }
+
+    /// Makes sure that no identifier is produced twice, like `unique` does for paths:
+    /// numbers and strings in fields named `id` or `_id`, and strings formatted like UUIDs,
+    /// ULIDs, KSUIDs and ObjectIds. Default = false.
+    pub fn unique_ids(mut self, unique_ids: bool) -> Self {
+        self.unique_ids = unique_ids;
+        self
+    }
+
+    /// Draws the values produced at some paths from the values produced at others, so that
+    /// the entities of the data refer to one another, e.g. `orders[].user_id` to `users[].id`.
+    /// Data with relations is produced whole before it is written, rather than as it goes.
+    /// Default = none.
+    pub fn relations(mut self, relations: Relations) -> Self {
+        self.relations = relations;
+        self
+    }
+
+    /// Derives the values produced at some paths from the other fields of the same object,
+    /// after everything else is produced, so that e.g. `users[].email_domain` is the domain of
+    /// `users[].email`. Derivations run after values are kept unique and related, and may undo
+    /// either. Default = none.
+    pub fn derivations(mut self, derivations: Derivations) -> Self {
+        self.derivations = derivations;
+        self
+    }
+
+    /// Produces the elements of an array at the root as a time series, one following on from
+    /// the other: with a timestamp that increases from one to the next, and numbers that
+    /// follow a random walk. Derivations run after it, and see the timestamps of the series.
+    /// Default = none.
+    pub fn time_series(mut self, time_series: TimeSeries) -> Self {
+        self.time_series = Some(time_series);
+        self
+    }
+
+    /// The number of elements of an array at the root that `produce_to_writer_with_options`
+    /// produces at a time, spread across threads, and writes out before producing the next,
+    /// so that the memory it uses grows with the chunk size rather than with the count. The
+    /// data written is the same whatever the chunk size. Default = `DEFAULT_CHUNK_SIZE`.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// The defaults that values the schema does not constrain are produced with, like those a
+    /// schema was parsed with: unconstrained arrays whose elements are filled in by overrides
+    /// get `defaults.array_lengths` elements. Default = `SchemaDefaults::default()`.
+    pub fn defaults(mut self, defaults: SchemaDefaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Returns the random number generator that the steps of a time series are taken with,
+    /// drawn from `rng` only when producing one, so that other data produced from a seed does
+    /// not change.
+    fn series_rng(&self, rng: &mut StdRng) -> Option<StdRng> {
+        self.time_series
+            .as_ref()
+            .map(|_| StdRng::seed_from_u64(rng.gen()))
+    }
+
+    /// Returns the edge case that the value at the root is, when producing edge cases.
+    fn root_case(&self) -> Option<usize> {
+        self.edge_cases.then_some(0)
+    }
+
+    fn override_root(&self) -> Option<&OverrideNode> {
+        (!self.overrides.is_empty()).then_some(&self.overrides.root)
+    }
+
+    /// Returns the random number generator that production starts from.
+    fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+/// The settings that apply to an entire call to produce.
+struct ProduceContext<'a> {
+    options: &'a ProduceOptions,
+    /// The values of the pools that overrides draw from, by name.
+    pools: HashMap<String, Vec<serde_json::Value>>,
+}
+
+/// Returns the schema to produce an override from, for overrides that target values within
+/// an unconstrained container: the structure of the overrides is all there is to go by.
+fn unconstrained_schema(node: &OverrideNode) -> SchemaState {
+    if node.elements.is_some() {
+        SchemaState::UnconstrainedArray
+    } else if node.fields.is_empty() {
+        SchemaState::Indefinite
+    } else {
+        SchemaState::UnconstrainedObject
+    }
+}
+
+/// Collects the largest size requested for each of the pools that the overrides within `node`
+/// draw from, if any size was requested.
+fn pool_sizes(node: &OverrideNode, sizes: &mut HashMap<String, Option<usize>>) {
+    if let Some(Override::Pool { name, size }) = &node.value {
+        let largest = sizes.entry(name.clone()).or_default();
+        *largest = (*largest).max(*size);
+    }
+    for field_node in node.fields.values() {
+        pool_sizes(field_node, sizes);
+    }
+    if let Some(elements) = node.elements.as_deref() {
+        pool_sizes(elements, sizes);
+    }
+}
+
+/// Fills each pool that the overrides within `node` draw from with values of the schema of the
+/// first path (in path order) that draws from it. Follows the schema the way `produce_inner`
+/// does.
+fn fill_pools(
+    schema: &SchemaState,
+    node: &OverrideNode,
+    current_depth: usize,
+    sizes: &HashMap<String, Option<usize>>,
+    ctx: &mut ProduceContext,
+    rng: &mut StdRng,
+) -> Result<(), Cancelled> {
+    if let Some(Override::Pool { name, .. }) = &node.value {
+        if !ctx.pools.contains_key(name) {
+            // the values of a pool are never null, as overrides take the place of nullable values
+            let schema = match schema {
+                SchemaState::Nullable(inner) => inner.as_ref(),
+                schema => schema,
+            };
+            let size = sizes[name].unwrap_or(DEFAULT_POOL_SIZE);
+            let case = ctx.options.root_case();
+            let values = produce_elements(schema, ctx, 1, None, case, size, rng)?;
+            ctx.pools.insert(name.clone(), values);
+        }
+        return Ok(());
+    }
 
-fn produce_inner(schema: &SchemaState, repeat_n: usize, current_depth: usize) -> serde_json::Value {
     match schema {
-        SchemaState::Initial | SchemaState::Null => serde_json::Value::Null,
-        SchemaState::Nullable(inner) => {
-            let should_return_null: bool = random();
-            if should_return_null {
-                serde_json::Value::Null
+        SchemaState::Nullable(inner) => fill_pools(inner, node, current_depth, sizes, ctx, rng)?,
+        SchemaState::Union(branches) => {
+            for branch in branches {
+                fill_pools(branch, node, current_depth, sizes, ctx, rng)?;
+            }
+        }
+        SchemaState::Array { schema, .. } => {
+            let elements = if current_depth == 0 {
+                node.elements.as_deref().or(Some(node))
             } else {
-                produce_inner(inner, repeat_n, current_depth + 1)
+                node.elements.as_deref()
+            };
+            if let Some(elements) = elements {
+                fill_pools(schema, elements, current_depth + 1, sizes, ctx, rng)?;
             }
         }
-        SchemaState::String(string_type) => {
-            let value = match string_type {
-                StringType::IsoDate => {
-                    let date: NaiveDate = Faker.fake();
-                    date.to_string()
-                }
-                StringType::DateTimeISO8601 => {
-                    let date_time: DateTime<Utc> = Faker.fake();
-                    let date_time = date_time.round_subsecs(3);
-                    date_time.to_rfc3339()
-                }
-                StringType::DateTimeRFC2822 => {
-                    let date_time: DateTime<Utc> = Faker.fake();
-                    let date_time = date_time.round_subsecs(3);
-                    date_time.to_rfc2822()
-                }
-                StringType::UUID => {
-                    let uuid = uuid::Uuid::new_v4();
-                    uuid.to_string()
-                }
-                StringType::Email => FreeEmail().fake(),
-                StringType::Hostname => {
-                    let name: String = Buzzword().fake();
-                    let suffix: String = DomainSuffix().fake();
-                    format!("{}.{}", name.to_lowercase(), suffix)
-                }
-                StringType::Url => {
-                    let host: String = Buzzword().fake();
-                    let suffix: String = DomainSuffix().fake();
-                    let path: String = Word().fake();
-                    format!(
-                        "https://{}.{}/{}",
-                        host.to_lowercase(),
-                        suffix,
-                        path.to_lowercase()
-                    )
+        SchemaState::Tuple(elements) => {
+            if let Some(element_node) = node.elements.as_deref() {
+                for element in elements {
+                    fill_pools(element, element_node, current_depth + 1, sizes, ctx, rng)?;
                 }
-                StringType::Unknown {
-                    chars_seen,
-                    min_length,
-                    max_length,
-                    ..
-                } => {
-                    let min = min_length.unwrap_or(0);
-                    let max = max_length.unwrap_or(32);
-                    let take_n = if min != max {
-                        thread_rng().gen_range(min..=max)
-                    } else {
-                        min
-                    };
+            }
+        }
+        SchemaState::UnconstrainedArray => {
+            if let Some(elements) = node.elements.as_deref() {
+                fill_pools(
+                    &unconstrained_schema(elements),
+                    elements,
+                    current_depth + 1,
+                    sizes,
+                    ctx,
+                    rng,
+                )?;
+            }
+        }
+        SchemaState::Object { required, optional } => {
+            let mut fields = node.fields.iter().collect::<Vec<_>>();
+            fields.sort_by_key(|(k, _)| *k);
+            for (k, field_node) in fields {
+                if let Some(field_schema) = required.get(k).or_else(|| optional.get(k)) {
+                    fill_pools(field_schema, field_node, current_depth + 1, sizes, ctx, rng)?;
+                }
+            }
+        }
+        SchemaState::UnconstrainedObject => {
+            let mut fields = node.fields.iter().collect::<Vec<_>>();
+            fields.sort_by_key(|(k, _)| *k);
+            for (_, field_node) in fields {
+                let field_schema = unconstrained_schema(field_node);
+                fill_pools(
+                    &field_schema,
+                    field_node,
+                    current_depth + 1,
+                    sizes,
+                    ctx,
+                    rng,
+                )?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
-                    if chars_seen.is_empty() {
-                        // we have no data at all to go by; generate a totally random string
-                        take_n.fake()
-                    } else {
-                        // otherwise we use the fact that we have collected all characters seen
-                        // to generate a random string with a similar character distribution to the
-                        // input data.
-                        let mut s = String::with_capacity(take_n);
-                        for _ in 0..take_n {
-                            let idx = thread_rng().gen_range(0..chars_seen.len());
-                            s.push(chars_seen[idx]);
-                        }
-                        s
-                    }
+/// Returns the smallest unit of decimals of `scale`, e.g. a hundredth, along with the lowest and
+/// the highest whole number of units within `min..=max`. The lowest exceeds the highest when no
+/// decimal of the scale lies within the range, which `check_decimals` rejects.
+fn decimal_units(scale: u32, min: f64, max: f64) -> (f64, f64, f64) {
+    let unit = 10f64.powi(scale as i32);
+    (unit, (min * unit).ceil(), (max * unit).floor())
+}
+
+/// Fails when a decimal within `schema` has a range in which no decimal of its scale lies, as
+/// no value of it can be produced.
+fn check_decimals(schema: &SchemaState, current_path: &str) -> Result<(), DrivelError> {
+    match schema {
+        SchemaState::Number(NumberType::Decimal { scale, min, max }) => {
+            let (_, lowest, highest) = decimal_units(*scale, *min, *max);
+            if lowest > highest {
+                return Err(DrivelError::InvalidDecimal {
+                    path: path::display(current_path).to_owned(),
+                    message: format!(
+                        "no decimal with {} decimal places lies within {}-{}",
+                        scale, min, max
+                    ),
+                });
+            }
+        }
+        SchemaState::Nullable(inner) => check_decimals(inner, current_path)?,
+        SchemaState::Union(branches) => {
+            for branch in branches {
+                check_decimals(branch, current_path)?;
+            }
+        }
+        SchemaState::Array { schema, .. } => {
+            check_decimals(schema, &path::elements(current_path))?;
+        }
+        SchemaState::Tuple(elements) => {
+            for (i, element) in elements.iter().enumerate() {
+                check_decimals(element, &path::index(current_path, i))?;
+            }
+        }
+        SchemaState::Object { required, optional } => {
+            for (k, field_schema) in required.iter().chain(optional) {
+                check_decimals(field_schema, &path::key(current_path, k))?;
+            }
+        }
+        // the values of a map have no path of their own
+        SchemaState::Map { schema, .. } => check_decimals(schema, current_path)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Returns the context of producing `schema` with `options`, with the values of the pools that
+/// the overrides draw from.
+fn produce_context<'a>(
+    schema: &SchemaState,
+    options: &'a ProduceOptions,
+    rng: &mut StdRng,
+) -> Result<ProduceContext<'a>, DrivelError> {
+    check_decimals(schema, "")?;
+    let mut ctx = ProduceContext {
+        options,
+        pools: HashMap::new(),
+    };
+    if let Some(node) = options.override_root() {
+        let mut sizes = HashMap::new();
+        pool_sizes(node, &mut sizes);
+        fill_pools(schema, node, 0, &sizes, &mut ctx, rng)?;
+    }
+    Ok(ctx)
+}
+
+/// Returns a random float in the inclusive range `min..=max`. Unlike `gen_range`, this does not
+/// panic on ranges that are too wide for their width to be represented, like `f64::MIN..=f64::MAX`.
+pub(crate) fn random_float(min: f64, max: f64, rng: &mut impl Rng) -> f64 {
+    if min == max {
+        min
+    } else if (max - min).is_finite() {
+        rng.gen_range(min..=max)
+    } else {
+        let (mid, half_width) = (min / 2.0 + max / 2.0, max / 2.0 - min / 2.0);
+        (mid + half_width * rng.gen_range(-1.0..=1.0)).clamp(min, max)
+    }
+}
+
+/// Returns a random date within `range`, or any date if the range is not known.
+fn random_date(range: Option<&RangeInclusive<NaiveDate>>, rng: &mut impl Rng) -> NaiveDate {
+    match range {
+        Some(range) => {
+            let days = range.end().signed_duration_since(*range.start()).num_days();
+            *range.start() + TimeDelta::days(rng.gen_range(0..=days))
+        }
+        None => Faker.fake_with_rng(rng),
+    }
+}
+
+/// Returns a random moment, with millisecond precision, within `range`, or any moment if the
+/// range is not known.
+fn random_date_time(
+    range: Option<&RangeInclusive<DateTime<Utc>>>,
+    rng: &mut impl Rng,
+) -> DateTime<Utc> {
+    let Some(range) = range else {
+        let date_time: DateTime<Utc> = Faker.fake_with_rng(rng);
+        return date_time.round_subsecs(3);
+    };
+    // the earliest whole millisecond that is not before the start of the range
+    let start = range.start().timestamp_millis()
+        + i64::from(range.start().timestamp_subsec_nanos() % 1_000_000 != 0);
+    let end = range.end().timestamp_millis();
+    if start > end {
+        return *range.start();
+    }
+    DateTime::from_timestamp_millis(rng.gen_range(start..=end))
+        .expect("the range consists of valid moments")
+}
+
+/// Returns a random time of day, in whole seconds, within `range`, or any time if the range is
+/// not known.
+fn random_time(range: Option<&RangeInclusive<NaiveTime>>, rng: &mut impl Rng) -> NaiveTime {
+    let (start, end) = range.map_or((0, 86_399), |range| {
+        (
+            range.start().num_seconds_from_midnight(),
+            range.end().num_seconds_from_midnight(),
+        )
+    });
+    NaiveTime::from_num_seconds_from_midnight_opt(rng.gen_range(start..=end), 0)
+        .expect("the range consists of valid times")
+}
+
+/// Chooses one of the keys of `counts`, each as often as it was counted.
+fn choose_weighted<'a>(counts: &'a HashMap<String, usize>, rng: &mut impl Rng) -> &'a str {
+    let mut counted = counts.iter().collect::<Vec<_>>();
+    // sorted, so that the same seed chooses the same key
+    counted.sort();
+    let total = counted.iter().map(|(_, count)| **count).sum::<usize>();
+    let mut choice = rng.gen_range(0..total.max(1));
+    for (key, count) in counted {
+        if choice < *count {
+            return key;
+        }
+        choice -= count;
+    }
+    ""
+}
+
+/// Produces a path of `kind`, starting with one of `roots`, through random directories to a
+/// file with one of `extensions`, each chosen as often as it was seen.
+fn file_path(
+    kind: PathKind,
+    roots: &HashMap<String, usize>,
+    depth: RangeInclusive<usize>,
+    extensions: &HashMap<String, usize>,
+    rng: &mut impl Rng,
+) -> String {
+    let mut path = choose_weighted(roots, rng).to_owned();
+    for _ in 0..rng.gen_range(depth) {
+        let directory: String = Word().fake_with_rng(rng);
+        path.push_str(&directory.to_lowercase());
+        path.push(kind.separator());
+    }
+    let name: String = Word().fake_with_rng(rng);
+    path.push_str(&name.to_lowercase());
+    match choose_weighted(extensions, rng) {
+        "" => path,
+        extension => format!("{}.{}", path, extension),
+    }
+}
+
+/// Produces a sentence of lorem ipsum of `words` words, e.g. `Voluptas sit aut dolores.`
+fn sentence(words: usize, rng: &mut impl Rng) -> String {
+    let words: Vec<String> = Words(words..words + 1).fake_with_rng(rng);
+    let mut sentence = words.join(" ");
+    if let Some(first) = sentence.get(..1) {
+        sentence.replace_range(..1, &first.to_uppercase());
+    }
+    sentence.push('.');
+    sentence
+}
+
+/// Produces one of the strings that encode NaN and infinite floats in JSON.
+fn non_finite_float(rng: &mut impl Rng) -> serde_json::Value {
+    let idx = rng.gen_range(0..NON_FINITE_FLOATS.len());
+    serde_json::Value::String(NON_FINITE_FLOATS[idx].to_owned())
+}
+
+fn unix_time() -> std::time::Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Produces a ULID for the current time, so that ULIDs sort by when they were produced.
+fn ulid(rng: &mut impl Rng) -> String {
+    const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    let timestamp = unix_time().as_millis() & ((1 << 48) - 1);
+    let value = timestamp << 80 | (rng.gen::<u128>() & ((1 << 80) - 1));
+    (0..26)
+        .rev()
+        .map(|idx| CROCKFORD[((value >> (idx * 5)) & 31) as usize] as char)
+        .collect()
+}
+
+/// Produces a KSUID for the current time: its timestamp counts seconds since 2014-05-13.
+fn ksuid(rng: &mut impl Rng) -> String {
+    const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    const KSUID_EPOCH: u64 = 1_400_000_000;
+    let mut bytes = [0u8; 20];
+    let timestamp = (unix_time().as_secs() - KSUID_EPOCH) as u32;
+    bytes[..4].copy_from_slice(&timestamp.to_be_bytes());
+    rng.fill(&mut bytes[4..]);
+
+    // base62 encodes the bytes as one big-endian number, by repeated long division
+    let mut digits = Vec::with_capacity(27);
+    while bytes.iter().any(|&byte| byte != 0) {
+        let mut remainder = 0u32;
+        for byte in bytes.iter_mut() {
+            let accumulated = remainder << 8 | *byte as u32;
+            *byte = (accumulated / 62) as u8;
+            remainder = accumulated % 62;
+        }
+        digits.push(BASE62[remainder as usize] as char);
+    }
+    digits.resize(27, '0');
+    digits.into_iter().rev().collect()
+}
+
+/// Produces an ObjectId for the current time.
+fn object_id(rng: &mut impl Rng) -> String {
+    let mut bytes = [0u8; 12];
+    bytes[..4].copy_from_slice(&(unix_time().as_secs() as u32).to_be_bytes());
+    rng.fill(&mut bytes[4..]);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Produces a phone number in `format`, filling its digits with those of a fake number, and
+/// prefixing them with random digits when the format has more, e.g. for a country code.
+fn phone_number(format: &str, locale: Locale, rng: &mut impl Rng) -> String {
+    let fake: String = fake_in!(locale, rng, PhoneNumber);
+    let mut digits = fake
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect::<Vec<_>>();
+    let n_digits = format.chars().filter(|c| *c == '#').count();
+    while digits.len() < n_digits {
+        digits.insert(0, char::from(b'0' + rng.gen_range(1..10)));
+    }
+    let start = digits.len() - n_digits;
+    let digits = &mut digits[start..];
+    // country codes do not start with a zero
+    if format.starts_with("+#") && digits.first() == Some(&'0') {
+        digits[0] = '1';
+    }
+    let mut digits = digits.iter();
+    format
+        .chars()
+        .map(|c| match c {
+            '#' => *digits.next().expect("there is a digit for every #"),
+            c => c,
+        })
+        .collect()
+}
+
+/// Produces a JWT carrying `claims`, with a random HS256 signature.
+fn jwt(claims: &serde_json::Value, rng: &mut impl Rng) -> String {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    let header = serde_json::json!({ "alg": "HS256", "typ": "JWT" });
+    let signature = rng.gen::<[u8; 32]>();
+    format!(
+        "{}.{}.{}",
+        URL_SAFE_NO_PAD.encode(header.to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string()),
+        URL_SAFE_NO_PAD.encode(signature)
+    )
+}
+
+/// The longest duration, in seconds, produced for a schema that does not give their range: a day.
+const DEFAULT_MAX_DURATION: u64 = 86_400;
+
+/// The highest minor and patch numbers of the semantic versions produced.
+const MAX_SEMVER_MINOR: u64 = 20;
+const MAX_SEMVER_PATCH: u64 = 30;
+
+/// Returns an integer as a JSON number. Integers beyond the range of `u64` and `i64` can only
+/// be held exactly when serde_json parses numbers with arbitrary precision, and are written
+/// as floats otherwise.
+pub(crate) fn integer_number(n: i128) -> Number {
+    if let Ok(n) = i64::try_from(n) {
+        Number::from(n)
+    } else if let Ok(n) = u64::try_from(n) {
+        Number::from(n)
+    } else {
+        serde_json::from_str(&n.to_string()).unwrap_or_else(|_| Number::from_f64(n as f64).unwrap())
+    }
+}
+
+/// Produces padded base64 of random bytes, with a length in the given range where possible.
+fn base64(min_length: usize, max_length: usize, rng: &mut impl Rng) -> String {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    // every 3 bytes are encoded as 4 characters, padding the last ones
+    let min_groups = min_length.div_ceil(4).max(1);
+    let groups = rng.gen_range(min_groups..=(max_length / 4).max(min_groups));
+    let n_bytes = groups * 3 - rng.gen_range(0..=2);
+    let bytes = (0..n_bytes).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+    STANDARD.encode(bytes)
+}
+
+/// Returns the boundary value of the edge case `case` out of `candidates`, cycling through them.
+fn edge<T: Clone>(case: usize, candidates: &[T]) -> T {
+    candidates[case % candidates.len()].clone()
+}
+
+/// Returns the values of a set in order, as the order in which a set iterates over them varies
+/// from run to run, which would make seeded output vary too.
+fn sorted<T: Ord>(set: &std::collections::HashSet<T>) -> Vec<&T> {
+    let mut values = set.iter().collect::<Vec<_>>();
+    values.sort_unstable();
+    values
+}
+
+/// Picks one of the values of a set at random.
+fn choose_from_set<T: Ord + Clone>(
+    set: &std::collections::HashSet<T>,
+    rng: &mut impl Rng,
+) -> Option<T> {
+    sorted(set).into_iter().choose(rng).cloned()
+}
+
+/// Returns the edge case `case` of a scalar schema whose boundary values are known, like the
+/// minimum or maximum of a number or the variants of an enum, or `None` for other schemas.
+///
+/// This is kept apart from `produce_inner`, as the stack frame of a function it recurses
+/// through limits how deeply nested the schemas it produces can be.
+fn edge_case_value(schema: &SchemaState, case: usize) -> Option<serde_json::Value> {
+    let string = |value: String| Some(serde_json::Value::String(value));
+    let float = |number: f64| Number::from_f64(number).map(serde_json::Value::Number);
+    match schema {
+        SchemaState::String(string_type) => match string_type {
+            StringType::IsoDate { range: Some(range) } => {
+                string(edge(case, &[range.start(), range.end()]).to_string())
+            }
+            StringType::DateTimeISO8601 { range: Some(range) } => {
+                string(edge(case, &[range.start(), range.end()]).to_rfc3339())
+            }
+            StringType::DateTimeRFC2822 { range: Some(range) } => {
+                string(edge(case, &[range.start(), range.end()]).to_rfc2822())
+            }
+            StringType::FormattedDate {
+                format,
+                range: Some(range),
+            } => string(format.format(*edge(case, &[range.start(), range.end()]))),
+            StringType::EpochSeconds { range: Some(range) } => string(
+                edge(case, &[range.start(), range.end()])
+                    .timestamp()
+                    .to_string(),
+            ),
+            StringType::CurrencyCode { codes } if !codes.is_empty() => {
+                string(edge(case, &sorted(codes)).clone())
+            }
+            StringType::Enum { variants } if !variants.is_empty() => {
+                string(edge(case, &sorted(variants)).clone())
+            }
+            StringType::Semver {
+                min_major,
+                max_major,
+            } => string(edge(
+                case,
+                &[
+                    format!("{}.0.0", min_major),
+                    format!("{}.{}.{}", max_major, MAX_SEMVER_MINOR, MAX_SEMVER_PATCH),
+                ],
+            )),
+            StringType::NonFiniteFloat => string(edge(case, &NON_FINITE_FLOATS).to_owned()),
+            _ => None,
+        },
+        SchemaState::Number(number_type) => match *number_type {
+            NumberType::Enum { ref variants, .. } if !variants.is_empty() => Some(
+                serde_json::Value::Number(Number::from(*edge(case, &sorted(variants)))),
+            ),
+            // zero is a boundary of its own when the range spans it
+            NumberType::Integer { min, max } if min < 0 && max > 0 => Some(
+                serde_json::Value::Number(Number::from(edge(case, &[min, max, 0]))),
+            ),
+            NumberType::Integer { min, max } => Some(serde_json::Value::Number(Number::from(
+                edge(case, &[min, max]),
+            ))),
+            NumberType::BigInteger { min, max } => Some(serde_json::Value::Number(integer_number(
+                edge(case, &[min, max]),
+            ))),
+            NumberType::Float { non_finite, .. } => {
+                // exclusive bounds are approached as closely as floats allow
+                let (min, max) = number_type.float_range().unwrap_or_default();
+                let mut candidates = vec![float(min), float(max)];
+                if min < 0.0 && max > 0.0 {
+                    candidates.push(float(0.0));
+                }
+                // the non-finite floats seen follow the finite boundaries
+                if non_finite {
+                    candidates.extend(NON_FINITE_FLOATS.map(|s| string(s.to_owned())));
+                }
+                edge(case, &candidates)
+            }
+            NumberType::Decimal { scale, min, max } => {
+                let (unit, lowest, highest) = decimal_units(scale, min, max);
+                float(edge(case, &[lowest, highest.max(lowest)]) / unit)
+            }
+            _ => None,
+        },
+        SchemaState::Boolean => Some(serde_json::Value::Bool(edge(case, &[false, true]))),
+        _ => None,
+    }
+}
+
+/// Produces `n` values of `schema` in parallel. Each value gets its own random number generator,
+/// seeded from `rng` up front, so that seeded output does not depend on how the values are
+/// spread across threads. When producing edge cases, the values are the consecutive edge cases
+/// from `case` onwards.
+fn produce_elements(
+    schema: &SchemaState,
+    ctx: &ProduceContext,
+    current_depth: usize,
+    overrides: Option<&OverrideNode>,
+    case: Option<usize>,
+    n: usize,
+    rng: &mut StdRng,
+) -> Result<Vec<serde_json::Value>, Cancelled> {
+    let seeds = (0..n)
+        .map(|idx| (case.map(|case| case + idx), rng.gen::<u64>()))
+        .collect::<Vec<_>>();
+    seeds
+        .into_par_iter()
+        .map(|(case, seed)| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            produce_inner(schema, ctx, current_depth, overrides, case, &mut rng)
+        })
+        .collect()
+}
+
+/// Produces a string of `string_type`. Kept out of `produce_inner`, so that its stack frame
+/// stays small for deeply nested schemas.
+fn produce_string(
+    string_type: &StringType,
+    ctx: &ProduceContext,
+    current_depth: usize,
+    case: Option<usize>,
+    rng: &mut StdRng,
+) -> Result<serde_json::Value, Cancelled> {
+    let value = match string_type {
+        StringType::IsoDate { range } => random_date(range.as_ref(), rng).to_string(),
+        StringType::DateTimeISO8601 { range } => random_date_time(range.as_ref(), rng).to_rfc3339(),
+        StringType::DateTimeRFC2822 { range } => random_date_time(range.as_ref(), rng).to_rfc2822(),
+        StringType::FormattedDate { format, range } => {
+            format.format(random_date(range.as_ref(), rng))
+        }
+        StringType::EpochSeconds { range } => random_date_time(range.as_ref(), rng)
+            .timestamp()
+            .to_string(),
+        StringType::Time { range, seconds } => {
+            format_time(random_time(range.as_ref(), rng), *seconds)
+        }
+        StringType::Text {
+            min_words,
+            max_words,
+        } => {
+            let words = match case {
+                Some(case) => *edge(case, &[min_words, max_words]),
+                None => rng.gen_range(*min_words..=*max_words),
+            };
+            sentence(words, rng)
+        }
+        StringType::FilePath {
+            kind,
+            roots,
+            min_depth,
+            max_depth,
+            extensions,
+        } => file_path(*kind, roots, *min_depth..=*max_depth, extensions, rng),
+        StringType::Duration { range } => {
+            format_duration(rng.gen_range(range.clone().unwrap_or(0..=DEFAULT_MAX_DURATION)))
+        }
+        StringType::UUID => {
+            let uuid = uuid::Builder::from_random_bytes(rng.gen()).into_uuid();
+            uuid.to_string()
+        }
+        StringType::Ulid => ulid(rng),
+        StringType::Ksuid => ksuid(rng),
+        StringType::ObjectId => object_id(rng),
+        StringType::CurrencyCode { codes } => choose_from_set(codes, rng).unwrap_or_default(),
+        StringType::Base64 {
+            min_length,
+            max_length,
+        } => {
+            let (min, max) = match case {
+                Some(case) => edge(
+                    case,
+                    &[(*min_length, *min_length), (*max_length, *max_length)],
+                ),
+                None => (*min_length, *max_length),
+            };
+            base64(min, max, rng)
+        }
+        StringType::Semver {
+            min_major,
+            max_major,
+        } => {
+            format!(
+                "{}.{}.{}",
+                rng.gen_range(*min_major..=*max_major),
+                rng.gen_range(0..=MAX_SEMVER_MINOR),
+                rng.gen_range(0..=MAX_SEMVER_PATCH)
+            )
+        }
+        StringType::PhoneNumber { .. } => phone_number(
+            string_type.phone_format().unwrap_or_default(),
+            ctx.options.locale,
+            rng,
+        ),
+        StringType::EmbeddedJson(schema) => {
+            produce_inner(schema, ctx, current_depth + 1, None, case, rng)?.to_string()
+        }
+        StringType::Jwt { claims } => {
+            let claims = produce_inner(claims, ctx, current_depth + 1, None, case, rng)?;
+            jwt(&claims, rng)
+        }
+        StringType::Email => FreeEmail().fake_with_rng(rng),
+        StringType::Hostname => {
+            let name: String = Buzzword().fake_with_rng(rng);
+            let suffix: String = DomainSuffix().fake_with_rng(rng);
+            format!("{}.{}", name.to_lowercase().replace(' ', "-"), suffix)
+        }
+        StringType::Url => {
+            let host: String = Buzzword().fake_with_rng(rng);
+            let suffix: String = DomainSuffix().fake_with_rng(rng);
+            let path: String = Word().fake_with_rng(rng);
+            format!(
+                "https://{}.{}/{}",
+                host.to_lowercase().replace(' ', "-"),
+                suffix,
+                path.to_lowercase()
+            )
+        }
+        StringType::Unknown {
+            strings_seen,
+            chars_seen,
+            min_length,
+            max_length,
+            ..
+        } => {
+            // strings that share a structure are generated from it, which keeps runs of
+            // digits and letters in place
+            if let Some(pattern) = string_type.structure_pattern() {
+                return Ok(serde_json::Value::String(pattern::generate(&pattern, rng)));
+            }
+            let min = min_length.unwrap_or(0);
+            let max = max_length.unwrap_or(32);
+            let take_n = if let Some(case) = case {
+                edge(case, &[min, max])
+            } else if min != max {
+                rng.gen_range(min..=max)
+            } else {
+                min
+            };
+
+            // a shared prefix and suffix are kept as they are; only the part in
+            // between is randomised
+            let affixes = string_type.affixes();
+            let (prefix, suffix) = affixes.unwrap_or(("", ""));
+            let take_n = take_n.saturating_sub(prefix.len() + suffix.len());
+
+            let middle: String = if chars_seen.values().all(|count| *count == 0) {
+                // we have no data at all to go by; generate a totally random string
+                take_n.fake_with_rng(rng)
+            } else if affixes.is_some() && !strings_seen.is_empty() {
+                // the characters of the prefix and suffix would skew the distribution
+                // of the characters seen, so we pick characters from the variable
+                // parts of the strings seen instead
+                let mut s = String::with_capacity(take_n);
+                for _ in 0..take_n {
+                    // every string seen is longer than its prefix and suffix combined,
+                    // so the variable part is never empty
+                    let seen = &strings_seen[rng.gen_range(0..strings_seen.len())];
+                    let variable = &seen[prefix.len()..seen.len() - suffix.len()];
+                    let n_chars = variable.chars().count();
+                    s.extend(variable.chars().nth(rng.gen_range(0..n_chars)));
                 }
-                StringType::Enum { variants } => {
-                    let variants_vec = variants.iter().cloned().collect::<Vec<_>>();
-                    let idx = thread_rng().gen_range(0..variants_vec.len());
-                    variants_vec[idx].clone()
+                s
+            } else {
+                // otherwise we draw characters by how often they were seen, to generate a
+                // random string with a similar character distribution to the input data
+                let mut counted = chars_seen.iter().collect::<Vec<_>>();
+                // sorted, so that the same seed draws the same characters
+                counted.sort_unstable();
+                let cumulative = counted
+                    .iter()
+                    .scan(0, |total, (_, count)| {
+                        *total += **count;
+                        Some(*total)
+                    })
+                    .collect::<Vec<_>>();
+                let total = cumulative.last().copied().unwrap_or_default();
+                let mut s = String::with_capacity(take_n);
+                for _ in 0..take_n {
+                    let choice = rng.gen_range(0..total);
+                    let idx = cumulative.partition_point(|&upto| upto <= choice);
+                    s.push(*counted[idx].0);
                 }
+                s
             };
-            serde_json::Value::String(value)
+            format!("{}{}{}", prefix, middle, suffix)
+        }
+        StringType::Pattern(pattern) => pattern::generate(pattern, rng),
+        StringType::NonFiniteFloat => return Ok(non_finite_float(rng)),
+        StringType::Tally { format, .. } => {
+            return produce_string(format, ctx, current_depth, case, rng)
+        }
+        StringType::Enum { variants } => choose_from_set(variants, rng).unwrap_or_default(),
+    };
+    Ok(serde_json::Value::String(value))
+}
+
+/// Produces a value of `schema`. With a `case`, the value is that edge case of the schema, as
+/// described by `ProduceOptions::edge_cases`.
+fn produce_inner(
+    schema: &SchemaState,
+    ctx: &ProduceContext,
+    current_depth: usize,
+    overrides: Option<&OverrideNode>,
+    case: Option<usize>,
+    rng: &mut StdRng,
+) -> Result<serde_json::Value, Cancelled> {
+    cancel::check(ctx.options.cancellation.as_ref())?;
+
+    if let Some(value) = overrides.and_then(|node| node.value.as_ref()) {
+        return Ok(value.produce(&ctx.pools, ctx.options.locale, rng));
+    }
+
+    // arrays and objects nested too deeply are left empty, so that (hand-written) schemas that
+    // are deeper than the stack allows cannot overflow it
+    if current_depth > ctx.options.max_depth {
+        match schema {
+            SchemaState::Array { .. } | SchemaState::Tuple(_) | SchemaState::UnconstrainedArray => {
+                return Ok(serde_json::Value::Array(vec![]))
+            }
+            SchemaState::Object { .. }
+            | SchemaState::Map { .. }
+            | SchemaState::UnconstrainedObject => {
+                return Ok(serde_json::Value::Object(serde_json::Map::new()))
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(value) = case.and_then(|case| edge_case_value(schema, case)) {
+        return Ok(value);
+    }
+
+    let value = match schema {
+        SchemaState::Initial | SchemaState::Null => serde_json::Value::Null,
+        SchemaState::Nullable(inner) => {
+            // every other edge case is null
+            let is_null = match case {
+                Some(case) => case.is_multiple_of(2),
+                None => rng.gen_bool(
+                    overrides
+                        .and_then(|node| node.null_probability)
+                        .unwrap_or(ctx.options.null_probability),
+                ),
+            };
+            if is_null {
+                serde_json::Value::Null
+            } else {
+                let case = case.map(|case| case / 2);
+                produce_inner(inner, ctx, current_depth, overrides, case, rng)?
+            }
+        }
+        SchemaState::String(string_type) => {
+            produce_string(string_type, ctx, current_depth, case, rng)?
         }
         SchemaState::Number(number_type) => match *number_type {
+            NumberType::Enum { ref variants, .. } => choose_from_set(variants, rng)
+                .map_or(serde_json::Value::Null, |variant| {
+                    serde_json::Value::Number(Number::from(variant))
+                }),
             NumberType::Integer { min, max } => {
                 let number = if min != max {
-                    thread_rng().gen_range(min..=max)
+                    rng.gen_range(min..=max)
                 } else {
                     min
                 };
                 serde_json::Value::Number(Number::from(number))
             }
-            NumberType::Float { min, max } => {
-                let number = if min != max {
-                    thread_rng().gen_range(min..=max)
+            NumberType::BigInteger { min, max } => {
+                serde_json::Value::Number(integer_number(rng.gen_range(min..=max)))
+            }
+            NumberType::Float { non_finite, .. } => {
+                if non_finite && rng.gen_bool(NON_FINITE_RATIO) {
+                    return Ok(non_finite_float(rng));
+                }
+                let (min, max) = number_type.float_range().unwrap_or_default();
+                let number = random_float(min, max.max(min), rng);
+                serde_json::Value::Number(Number::from_f64(number).unwrap())
+            }
+            NumberType::Decimal { scale, min, max } => {
+                // decimals are produced as a whole number of their smallest unit, e.g. cents
+                let (unit, lowest, highest) = decimal_units(scale, min, max);
+                let number = if lowest < highest {
+                    rng.gen_range(lowest as i64..=highest as i64) as f64 / unit
                 } else {
-                    min
+                    lowest / unit
                 };
                 serde_json::Value::Number(Number::from_f64(number).unwrap())
             }
         },
-        SchemaState::Boolean => serde_json::Value::Bool(random()),
+        SchemaState::Boolean => serde_json::Value::Bool(rng.gen()),
+        SchemaState::UnconstrainedArray => {
+            // an unconstrained array stays empty, unless overrides fill in its elements
+            let Some(element_overrides) = overrides
+                .and_then(|node| node.elements.as_deref())
+                .filter(|node| node.pins_values())
+            else {
+                return Ok(serde_json::Value::Array(vec![]));
+            };
+            let lengths = ctx.options.defaults.array_lengths.clone();
+            let n_elements = match case {
+                Some(case) => edge(case, &[*lengths.start(), *lengths.end()]),
+                None => rng.gen_range(lengths),
+            };
+            let element_schema = unconstrained_schema(element_overrides);
+            let data = (0..n_elements)
+                .map(|idx| {
+                    produce_inner(
+                        &element_schema,
+                        ctx,
+                        current_depth + 1,
+                        Some(element_overrides),
+                        case.map(|case| case + idx),
+                        rng,
+                    )
+                })
+                .collect::<Result<Vec<_>, Cancelled>>()?;
+            serde_json::Value::Array(data)
+        }
+        SchemaState::Const(value) => value.clone(),
+        SchemaState::UnconstrainedObject => {
+            // an unconstrained object stays empty, unless overrides fill in its fields
+            let mut map = serde_json::Map::new();
+            if let Some(node) = overrides {
+                for (k, field_overrides) in &node.fields {
+                    if !field_overrides.pins_values() {
+                        continue;
+                    }
+                    let field_schema = unconstrained_schema(field_overrides);
+                    let value = produce_inner(
+                        &field_schema,
+                        ctx,
+                        current_depth + 1,
+                        Some(field_overrides),
+                        case,
+                        rng,
+                    )?;
+                    map.insert(k.clone(), value);
+                }
+            }
+            serde_json::Value::Object(map)
+        }
         SchemaState::Array {
             min_length,
             max_length,
+            unique,
             schema,
         } => {
             if schema.as_ref() == &SchemaState::Indefinite
                 || schema.as_ref() == &SchemaState::Initial
             {
-                return serde_json::Value::Array(vec![]);
+                return Ok(serde_json::Value::Array(vec![]));
             }
 
+            let element_overrides = overrides.and_then(|node| {
+                if current_depth == 0 {
+                    // paths are relative to the elements of an array at the root
+                    node.elements.as_deref().or(Some(node))
+                } else {
+                    node.elements.as_deref()
+                }
+            });
+
             let n_elements = if current_depth == 0 {
                 // if we are dealing with an array at the root, we produce the requested `n` elements
-                repeat_n
+                ctx.options.count
+            } else if let Some(case) = case {
+                edge(case, &[*min_length, *max_length])
             } else if min_length != max_length {
-                thread_rng().gen_range(*min_length..=*max_length)
+                rng.gen_range(*min_length..=*max_length)
             } else {
                 *min_length
             };
 
-            let data: Vec<_> = (0..n_elements)
-                .into_par_iter()
-                .map(|_| produce_inner(schema, repeat_n, current_depth + 1))
-                .collect();
+            let mut data = produce_elements(
+                schema,
+                ctx,
+                current_depth + 1,
+                element_overrides,
+                case,
+                n_elements,
+                rng,
+            )?;
+            if *unique {
+                deduplicate_elements(
+                    &mut data,
+                    schema,
+                    ctx,
+                    current_depth + 1,
+                    element_overrides,
+                    rng,
+                )?;
+            }
             serde_json::Value::Array(data)
         }
+        SchemaState::Tuple(elements) => {
+            // overrides for `[]` apply to every position of a tuple
+            let element_overrides = overrides.and_then(|node| node.elements.as_deref());
+            let data = elements
+                .iter()
+                .map(|element| {
+                    produce_inner(
+                        element,
+                        ctx,
+                        current_depth + 1,
+                        element_overrides,
+                        case,
+                        rng,
+                    )
+                })
+                .collect::<Result<Vec<_>, Cancelled>>()?;
+            serde_json::Value::Array(data)
+        }
+        SchemaState::Union(branches) => {
+            // edge cases cycle through the branches, and through the edge cases of each in turn
+            let (branch, case) = match case {
+                Some(case) => (
+                    &branches[case % branches.len()],
+                    Some(case / branches.len()),
+                ),
+                None => (&branches[rng.gen_range(0..branches.len())], None),
+            };
+            produce_inner(branch, ctx, current_depth, overrides, case, rng)?
+        }
         SchemaState::Object { required, optional } => {
             let mut map = serde_json::Map::new();
+            let field_overrides = |k: &String| overrides.and_then(|node| node.fields.get(k));
             for (k, v) in required.iter() {
-                let value = produce_inner(v, repeat_n, current_depth + 1);
+                let value =
+                    produce_inner(v, ctx, current_depth + 1, field_overrides(k), case, rng)?;
                 map.insert(k.clone(), value);
             }
+            // every other edge case leaves out all optional fields
+            let (include_optional, case) = match case {
+                Some(case) => (!case.is_multiple_of(2), Some(case / 2)),
+                None => (false, None),
+            };
             for (k, v) in optional.iter() {
-                let should_include: bool = random();
-                if should_include {
-                    let value = produce_inner(v, repeat_n, current_depth + 1);
+                let probability = field_overrides(k)
+                    .and_then(|node| node.optional_probability)
+                    .unwrap_or(ctx.options.optional_probability);
+                if include_optional || (case.is_none() && rng.gen_bool(probability)) {
+                    let value =
+                        produce_inner(v, ctx, current_depth + 1, field_overrides(k), case, rng)?;
                     map.insert(k.clone(), value);
                 }
             }
             serde_json::Value::Object(map)
         }
+        SchemaState::Map { .. } => produce_map(schema, ctx, current_depth, case, rng)?,
         SchemaState::Indefinite => serde_json::Value::Null,
+    };
+    Ok(value)
+}
+
+/// Replaces repeated elements of an array by new ones, for as long as new ones turn up: schemas
+/// with few values may not have enough distinct ones, so that the array comes out shorter.
+fn deduplicate_elements(
+    elements: &mut Vec<serde_json::Value>,
+    schema: &SchemaState,
+    ctx: &ProduceContext,
+    current_depth: usize,
+    overrides: Option<&OverrideNode>,
+    rng: &mut StdRng,
+) -> Result<(), Cancelled> {
+    let wanted = elements.len();
+    let mut unique: Vec<serde_json::Value> = Vec::with_capacity(wanted);
+    for element in elements.drain(..) {
+        if !unique.contains(&element) {
+            unique.push(element);
+        }
+    }
+    for _ in 0..wanted * MAX_UNIQUE_ATTEMPTS {
+        if unique.len() == wanted {
+            break;
+        }
+        let element = produce_inner(schema, ctx, current_depth, overrides, None, rng)?;
+        if !unique.contains(&element) {
+            unique.push(element);
+        }
+    }
+    *elements = unique;
+    Ok(())
+}
+
+/// Produces an object of keys generated from the pattern of a map schema, each with a value of
+/// its schema. Patterns may match fewer keys than wanted, so only so many are tried.
+fn produce_map(
+    schema: &SchemaState,
+    ctx: &ProduceContext,
+    current_depth: usize,
+    case: Option<usize>,
+    rng: &mut StdRng,
+) -> Result<serde_json::Value, Cancelled> {
+    let SchemaState::Map {
+        pattern,
+        min_length,
+        max_length,
+        schema,
+    } = schema
+    else {
+        unreachable!("only maps are produced as maps");
+    };
+    let n_keys = if let Some(case) = case {
+        edge(case, &[*min_length, *max_length])
+    } else if min_length != max_length {
+        rng.gen_range(*min_length..=*max_length)
+    } else {
+        *min_length
+    };
+    let mut map = serde_json::Map::new();
+    for _ in 0..n_keys * MAX_UNIQUE_ATTEMPTS {
+        if map.len() == n_keys {
+            break;
+        }
+        let key = pattern::generate(pattern, rng);
+        if !map.contains_key(&key) {
+            let value = produce_inner(schema, ctx, current_depth + 1, None, case, rng)?;
+            map.insert(key, value);
+        }
+    }
+    Ok(serde_json::Value::Object(map))
+}
+
+/// A value of a schema that is produced as it is serialized, so that no `serde_json::Value` is
+/// built of the objects and arrays within it. It draws from `rng` in the same order as
+/// `produce_inner` does, so that it serializes to the value `produce_inner` would produce.
+struct Producing<'a> {
+    schema: &'a SchemaState,
+    ctx: &'a ProduceContext<'a>,
+    current_depth: usize,
+    overrides: Option<&'a OverrideNode>,
+    case: Option<usize>,
+    rng: &'a RefCell<StdRng>,
+}
+
+impl<'a> Producing<'a> {
+    fn nested(
+        &self,
+        schema: &'a SchemaState,
+        current_depth: usize,
+        overrides: Option<&'a OverrideNode>,
+        case: Option<usize>,
+    ) -> Self {
+        Self {
+            schema,
+            current_depth,
+            overrides,
+            case,
+            ..*self
+        }
+    }
+
+    /// Returns whether the value is an object or array that is serialized as it is produced;
+    /// anything else is produced by `produce_inner` as a whole.
+    fn is_streamed(&self) -> bool {
+        if self.overrides.is_some_and(|node| node.value.is_some()) {
+            return false;
+        }
+        match self.schema {
+            SchemaState::Nullable(_) | SchemaState::Union(_) => true,
+            // arrays of unique elements are compared with each other as values
+            SchemaState::Array { unique, schema, .. } => {
+                !unique
+                    && !matches!(
+                        schema.as_ref(),
+                        SchemaState::Indefinite | SchemaState::Initial
+                    )
+                    && self.current_depth <= self.ctx.options.max_depth
+            }
+            SchemaState::Tuple(_) | SchemaState::Object { .. } => {
+                self.current_depth <= self.ctx.options.max_depth
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Serialize for Producing<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Producing {
+            schema,
+            ctx,
+            current_depth,
+            overrides,
+            case,
+            rng,
+        } = *self;
+        if !self.is_streamed() {
+            let value = produce_inner(
+                schema,
+                ctx,
+                current_depth,
+                overrides,
+                case,
+                &mut rng.borrow_mut(),
+            )
+            .map_err(S::Error::custom)?;
+            return value.serialize(serializer);
+        }
+        cancel::check(ctx.options.cancellation.as_ref()).map_err(S::Error::custom)?;
+
+        match schema {
+            SchemaState::Nullable(inner) => {
+                let is_null = match case {
+                    Some(case) => case.is_multiple_of(2),
+                    None => rng.borrow_mut().gen_bool(
+                        overrides
+                            .and_then(|node| node.null_probability)
+                            .unwrap_or(ctx.options.null_probability),
+                    ),
+                };
+                if is_null {
+                    serializer.serialize_unit()
+                } else {
+                    let case = case.map(|case| case / 2);
+                    self.nested(inner, current_depth, overrides, case)
+                        .serialize(serializer)
+                }
+            }
+            SchemaState::Union(branches) => {
+                let (branch, case) = match case {
+                    Some(case) => (
+                        &branches[case % branches.len()],
+                        Some(case / branches.len()),
+                    ),
+                    None => (
+                        &branches[rng.borrow_mut().gen_range(0..branches.len())],
+                        None,
+                    ),
+                };
+                self.nested(branch, current_depth, overrides, case)
+                    .serialize(serializer)
+            }
+            SchemaState::Array {
+                min_length,
+                max_length,
+                schema,
+                ..
+            } => {
+                let element_overrides = overrides.and_then(|node| {
+                    if current_depth == 0 {
+                        node.elements.as_deref().or(Some(node))
+                    } else {
+                        node.elements.as_deref()
+                    }
+                });
+                let n_elements = if current_depth == 0 {
+                    ctx.options.count
+                } else if let Some(case) = case {
+                    edge(case, &[*min_length, *max_length])
+                } else if min_length != max_length {
+                    rng.borrow_mut().gen_range(*min_length..=*max_length)
+                } else {
+                    *min_length
+                };
+                // the seeds of the elements are drawn up front, as `produce_elements` draws them
+                let seeds = (0..n_elements)
+                    .map(|idx| (case.map(|case| case + idx), rng.borrow_mut().gen::<u64>()))
+                    .collect::<Vec<_>>();
+                let mut seq = serializer.serialize_seq(Some(n_elements))?;
+                for (case, seed) in seeds {
+                    let rng = RefCell::new(StdRng::seed_from_u64(seed));
+                    seq.serialize_element(&Producing {
+                        rng: &rng,
+                        ..self.nested(schema, current_depth + 1, element_overrides, case)
+                    })?;
+                }
+                seq.end()
+            }
+            SchemaState::Tuple(elements) => {
+                let element_overrides = overrides.and_then(|node| node.elements.as_deref());
+                let mut seq = serializer.serialize_seq(Some(elements.len()))?;
+                for element in elements {
+                    seq.serialize_element(&self.nested(
+                        element,
+                        current_depth + 1,
+                        element_overrides,
+                        case,
+                    ))?;
+                }
+                seq.end()
+            }
+            SchemaState::Object { required, optional } => {
+                let field_overrides = |k: &String| overrides.and_then(|node| node.fields.get(k));
+                let mut map = serializer.serialize_map(None)?;
+                for (k, v) in required.iter() {
+                    map.serialize_entry(
+                        k,
+                        &self.nested(v, current_depth + 1, field_overrides(k), case),
+                    )?;
+                }
+                let (include_optional, case) = match case {
+                    Some(case) => (!case.is_multiple_of(2), Some(case / 2)),
+                    None => (false, None),
+                };
+                for (k, v) in optional.iter() {
+                    let probability = field_overrides(k)
+                        .and_then(|node| node.optional_probability)
+                        .unwrap_or(ctx.options.optional_probability);
+                    if include_optional
+                        || (case.is_none() && rng.borrow_mut().gen_bool(probability))
+                    {
+                        map.serialize_entry(
+                            k,
+                            &self.nested(v, current_depth + 1, field_overrides(k), case),
+                        )?;
+                    }
+                }
+                map.end()
+            }
+            _ => unreachable!("only objects and arrays are streamed"),
+        }
     }
 }
 
-/// Produces a JSON value based on the given schema.
+/// Produces `n` independent values of a single record's schema. Unlike `produce_with_options`,
+/// an array schema is treated like any other value rather than as the root to repeat.
+pub(crate) fn produce_records(record_schema: &SchemaState, n: usize) -> Vec<serde_json::Value> {
+    let options = ProduceOptions::new();
+    let ctx = ProduceContext {
+        options: &options,
+        pools: HashMap::new(),
+    };
+    produce_elements(record_schema, &ctx, 1, None, None, n, &mut options.rng())
+        .expect("production without cancellation cannot fail")
+}
+
+/// Produces a JSON value based on the given schema, as set out by `options`.
 ///
-/// This function generates a JSON value based on the provided schema state.
+/// An array at the root of the schema is produced with `options`' count of elements; anything
+/// else is produced once. This only fails when production is cancelled.
+///
+/// Overrides only apply to fields that are part of the schema, or that fall within an
+/// unconstrained (only ever seen empty) array or object; optional fields are still omitted at
+/// random.
+///
+/// # Examples
 ///
-/// # Arguments
+/// ```
+/// use drivel::{produce_with_options, NumberType, Override, Overrides, ProduceOptions, SchemaState};
+/// use indexmap::IndexMap;
 ///
-/// * `schema` - The schema state to produce JSON values for.
-/// * `repeat_n` - The number of times to repeat generation (used for arrays at the JSON root).
+/// let schema = SchemaState::Array {
+///     min_length: 1,
+///     max_length: 1,
+///     unique: false,
+///     schema: Box::new(SchemaState::Object {
+///         required: IndexMap::from_iter([(
+///             "age".to_string(),
+///             SchemaState::Number(NumberType::Integer { min: 0, max: 100 }),
+///         )]),
+///         optional: IndexMap::new(),
+///     }),
+/// };
 ///
-/// # Returns
+/// let mut overrides = Overrides::new();
+/// overrides.insert("age", Override::IntRange { min: 18, max: 65 });
+///
+/// let options = ProduceOptions::new().count(3).overrides(overrides);
+/// let json_data = produce_with_options(&schema, &options).unwrap();
+/// for record in json_data.as_array().unwrap() {
+///     let age = record["age"].as_i64().unwrap();
+///     assert!((18..=65).contains(&age));
+/// }
+/// ```
+pub fn produce_with_options(
+    schema: &SchemaState,
+    options: &ProduceOptions,
+) -> Result<serde_json::Value, DrivelError> {
+    let mut rng = options.rng();
+    let ctx = produce_context(schema, options, &mut rng)?;
+    // values replaced after they are produced, to keep them unique or to relate them, are
+    // produced with a random number generator of their own
+    let mut finishing_rng = StdRng::seed_from_u64(rng.gen());
+    let series_rng = options.series_rng(&mut rng);
+    let mut produced = produce_inner(
+        schema,
+        &ctx,
+        0,
+        options.override_root(),
+        options.root_case(),
+        &mut rng,
+    )?;
+
+    let mut unique = UniqueValues::new(&options.unique, options.unique_ids);
+    if unique.is_enabled() {
+        let mut regenerate = |schema: &SchemaState, overrides: Option<&OverrideNode>| {
+            produce_inner(schema, &ctx, 1, overrides, None, &mut finishing_rng)
+        };
+        match (schema, &mut produced) {
+            (SchemaState::Array { schema, .. }, serde_json::Value::Array(records)) => {
+                // paths are relative to the elements of an array at the root
+                let record_overrides = options
+                    .override_root()
+                    .map(|node| node.elements.as_deref().unwrap_or(node));
+                for record in records {
+                    unique.enforce(record, schema, record_overrides, &mut regenerate)?;
+                }
+            }
+            (schema, produced) => {
+                unique.enforce(produced, schema, options.override_root(), &mut regenerate)?
+            }
+        }
+    }
+    let root_is_array = matches!(schema, SchemaState::Array { .. });
+    options
+        .relations
+        .apply(&mut produced, root_is_array, &mut finishing_rng)?;
+    if let (Some(series), Some(mut series_rng)) = (&options.time_series, series_rng) {
+        match (schema, &mut produced) {
+            (SchemaState::Array { schema, .. }, serde_json::Value::Array(records)) => {
+                let mut state = SeriesState::new(series, schema)?;
+                for record in records {
+                    state.advance(record, &mut series_rng);
+                }
+            }
+            (schema, produced) => {
+                SeriesState::new(series, schema)?.advance(produced, &mut series_rng)
+            }
+        }
+    }
+    options.derivations.apply(&mut produced, root_is_array)?;
+    Ok(produced)
+}
+
+/// Produces a JSON value based on the given schema, repeating an array at the root `repeat_n`
+/// times.
+#[deprecated(note = "use `produce_with_options` with `ProduceOptions::count`")]
+pub fn produce(schema: &SchemaState, repeat_n: usize) -> serde_json::Value {
+    produce_with_options(schema, &ProduceOptions::new().count(repeat_n))
+        .expect("production without cancellation cannot fail")
+}
+
+/// Returns whether producing with `options` changes values after they are produced, so that
+/// they have to be produced as values rather than serialized as they are produced.
+fn finishes_values(options: &ProduceOptions) -> bool {
+    !options.unique.is_empty()
+        || options.unique_ids
+        || options.time_series.is_some()
+        || !options.relations.is_empty()
+        || !options.derivations.is_empty()
+}
+
+/// Produces data like `produce_with_options` does, serializing it with `serializer` as it is
+/// produced rather than building a `serde_json::Value` of it first, which takes far less memory
+/// for large outputs. With a seed, the data serialized is the same as `produce_with_options`
+/// returns.
+///
+/// Data whose values are kept unique, related, derived or advanced as a time series is
+/// produced whole before it is serialized, as are arrays of unique elements. Cancellation is
+/// returned as an error of the serializer.
 ///
 /// # Examples
 ///
 /// ```
-/// use drivel::{SchemaState, NumberType, produce};
+/// use drivel::{produce_to_serializer, produce_with_options, NumberType, ProduceOptions, SchemaState};
 ///
-/// // The inferred schema consists of an array with length = 1
 /// let schema = SchemaState::Array {
 ///     min_length: 1,
 ///     max_length: 1,
+///     unique: false,
 ///     schema: Box::new(SchemaState::Number(NumberType::Integer { min: 0, max: 100 })),
 /// };
+/// let options = ProduceOptions::new().count(1_000).seed(7);
 ///
-/// // Generate three values based on the schema
-/// let json_data = produce(&schema, 3);
+/// let mut output = vec![];
+/// produce_to_serializer(&schema, &options, &mut serde_json::Serializer::new(&mut output))
+///     .unwrap();
 ///
-/// // Do something with the generated JSON data
-/// println!("{}", json_data);
-/// // Output: [23, 58, 12]
+/// let produced: serde_json::Value = serde_json::from_slice(&output).unwrap();
+/// assert_eq!(produced, produce_with_options(&schema, &options).unwrap());
 /// ```
-pub fn produce(schema: &SchemaState, repeat_n: usize) -> serde_json::Value {
-    produce_inner(schema, repeat_n, 0)
+pub fn produce_to_serializer<S: Serializer>(
+    schema: &SchemaState,
+    options: &ProduceOptions,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if finishes_values(options) {
+        return produce_with_options(schema, options)
+            .map_err(S::Error::custom)?
+            .serialize(serializer);
+    }
+    let mut rng = options.rng();
+    let ctx = produce_context(schema, options, &mut rng).map_err(S::Error::custom)?;
+    // the random number generators of finishing values are drawn all the same, as
+    // `produce_with_options` draws them
+    let _finishing_seed: u64 = rng.gen();
+    let rng = RefCell::new(rng);
+    Producing {
+        schema,
+        ctx: &ctx,
+        current_depth: 0,
+        overrides: options.override_root(),
+        case: options.root_case(),
+        rng: &rng,
+    }
+    .serialize(serializer)
+}
+
+/// Serializes a record as an element of a pretty-printed array, indented by one level. Records
+/// that are produced as they are serialized fail only when production is cancelled.
+fn serialize_element(record: &impl Serialize) -> Result<Vec<u8>, Cancelled> {
+    let pretty = serde_json::to_vec_pretty(record).map_err(|_| Cancelled)?;
+    let mut element = Vec::with_capacity(pretty.len() + 64);
+    for (idx, line) in pretty.split(|byte| *byte == b'\n').enumerate() {
+        if idx > 0 {
+            element.push(b'\n');
+        }
+        element.extend_from_slice(b"  ");
+        element.extend_from_slice(line);
+    }
+    Ok(element)
+}
+
+/// Produces data like `produce_with_options` does, writing it to `writer` as pretty-printed
+/// JSON as it goes.
+///
+/// The elements of an array at the root are produced and serialized in parallel, a chunk of
+/// `ProduceOptions::chunk_size` at a time, and written out in order, so that memory use does
+/// not grow with the count. With a seed, the data written is the same as
+/// `produce_with_options` returns. Unique paths of integers whose range holds fewer integers
+/// than the records need fail before anything is written; when production fails midway
+/// otherwise, e.g. as the values of an optional unique field run out, the data written so far
+/// is left incomplete.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{produce_to_writer_with_options, NumberType, ProduceOptions, SchemaState};
+///
+/// let schema = SchemaState::Array {
+///     min_length: 1,
+///     max_length: 1,
+///     unique: false,
+///     schema: Box::new(SchemaState::Number(NumberType::Integer { min: 0, max: 100 })),
+/// };
+///
+/// let mut output = vec![];
+/// let options = ProduceOptions::new().count(10_000);
+/// produce_to_writer_with_options(&schema, &options, &mut output).unwrap();
+///
+/// let produced: serde_json::Value = serde_json::from_slice(&output).unwrap();
+/// assert_eq!(produced.as_array().unwrap().len(), 10_000);
+/// ```
+pub fn produce_to_writer_with_options(
+    schema: &SchemaState,
+    options: &ProduceOptions,
+    writer: &mut impl std::io::Write,
+) -> Result<(), DrivelError> {
+    let overrides = options.override_root();
+    // only the elements of an array at the root are streamed, unless they are kept distinct
+    // from one another; anything else is produced whole
+    let record_schema = match schema {
+        SchemaState::Array {
+            schema,
+            unique: false,
+            ..
+        } if options.count > 0
+            && !matches!(
+                schema.as_ref(),
+                SchemaState::Initial | SchemaState::Indefinite
+            )
+            && overrides.is_none_or(|node| node.value.is_none())
+            && options.relations.is_empty() =>
+        {
+            schema.as_ref()
+        }
+        _ => {
+            let value = produce_with_options(schema, options)?;
+            serde_json::to_writer_pretty(&mut *writer, &value).map_err(std::io::Error::from)?;
+            return Ok(());
+        }
+    };
+
+    // paths are relative to the elements of an array at the root
+    let record_overrides = overrides.map(|node| node.elements.as_deref().unwrap_or(node));
+    let mut rng = options.rng();
+    let ctx = produce_context(schema, options, &mut rng)?;
+    // values replaced to keep them unique are produced in the same order as
+    // `produce_with_options` produces them
+    let mut finishing_rng = StdRng::seed_from_u64(rng.gen());
+    let mut series = options
+        .series_rng(&mut rng)
+        .zip(options.time_series.as_ref())
+        .map(|(rng, series)| Ok::<_, DrivelError>((SeriesState::new(series, record_schema)?, rng)))
+        .transpose()?;
+    let mut unique = UniqueValues::new(&options.unique, options.unique_ids);
+    unique.check_ranges(record_schema, record_overrides, options.count)?;
+
+    writer.write_all(b"[")?;
+    let mut remaining = options.count;
+    let mut first = true;
+    while remaining > 0 {
+        let chunk_size = remaining.min(options.chunk_size);
+        let chunk_start = options.count - remaining;
+        remaining -= chunk_size;
+        // the seeds are drawn in the same order as `produce_elements` draws them
+        let case = options.root_case().map(|case| case + chunk_start);
+        let seeds = (0..chunk_size)
+            .map(|idx| (case.map(|case| case + idx), rng.gen::<u64>()))
+            .collect::<Vec<_>>();
+        let elements = if unique.is_enabled() || series.is_some() {
+            // duplicates are replaced, and time series advanced, one record at a time, in
+            // order, so that the values produced before are known
+            let mut records = seeds
+                .into_par_iter()
+                .map(|(case, seed)| {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    produce_inner(record_schema, &ctx, 1, record_overrides, case, &mut rng)
+                })
+                .collect::<Result<Vec<_>, Cancelled>>()?;
+            let mut regenerate = |schema: &SchemaState, overrides: Option<&OverrideNode>| {
+                produce_inner(schema, &ctx, 1, overrides, None, &mut finishing_rng)
+            };
+            for record in &mut records {
+                unique.enforce(record, record_schema, record_overrides, &mut regenerate)?;
+                if let Some((state, rng)) = &mut series {
+                    state.advance(record, rng);
+                }
+                options.derivations.apply_to_record(record)?;
+            }
+            records
+                .par_iter()
+                .map(serialize_element)
+                .collect::<Result<Vec<_>, Cancelled>>()?
+        } else if !options.derivations.is_empty() {
+            seeds
+                .into_par_iter()
+                .map(|(case, seed)| {
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let mut record =
+                        produce_inner(record_schema, &ctx, 1, record_overrides, case, &mut rng)?;
+                    options.derivations.apply_to_record(&mut record)?;
+                    Ok(serialize_element(&record)?)
+                })
+                .collect::<Result<Vec<_>, DrivelError>>()?
+        } else {
+            // records that are not changed once produced are serialized as they are produced
+            seeds
+                .into_par_iter()
+                .map(|(case, seed)| {
+                    let rng = RefCell::new(StdRng::seed_from_u64(seed));
+                    serialize_element(&Producing {
+                        schema: record_schema,
+                        ctx: &ctx,
+                        current_depth: 1,
+                        overrides: record_overrides,
+                        case,
+                        rng: &rng,
+                    })
+                })
+                .collect::<Result<Vec<_>, Cancelled>>()?
+        };
+        for element in elements {
+            writer.write_all(if first { b"\n" } else { b",\n" })?;
+            writer.write_all(&element)?;
+            first = false;
+        }
+    }
+    writer.write_all(b"\n]")?;
+    Ok(())
+}
+
+/// Produces the records of a schema one at a time, for as long as they are asked for: the
+/// elements of an array at the root, or otherwise values of the schema itself. Records are
+/// kept unique, advanced as a time series and derived like `produce_to_writer_with_options`
+/// does; relations are not applied, as they need the data whole, and nor are the records of an
+/// array of unique elements at the root kept distinct from one another.
+pub(crate) struct Records<'a> {
+    options: &'a ProduceOptions,
+    ctx: ProduceContext<'a>,
+    record_schema: &'a SchemaState,
+    record_overrides: Option<&'a OverrideNode>,
+    rng: StdRng,
+    finishing_rng: StdRng,
+    series: Option<(SeriesState<'a>, StdRng)>,
+    unique: UniqueValues<'a>,
+    /// The edge case of the next record, when producing edge cases.
+    case: Option<usize>,
+}
+
+impl<'a> Records<'a> {
+    pub(crate) fn new(
+        schema: &'a SchemaState,
+        options: &'a ProduceOptions,
+    ) -> Result<Self, DrivelError> {
+        let (record_schema, record_overrides) = match schema {
+            // paths are relative to the elements of an array at the root
+            SchemaState::Array { schema, .. } => (
+                schema.as_ref(),
+                options
+                    .override_root()
+                    .map(|node| node.elements.as_deref().unwrap_or(node)),
+            ),
+            schema => (schema, options.override_root()),
+        };
+        // the random number generators are drawn in the same order as
+        // `produce_to_writer_with_options` draws them, so that seeded records are the same
+        let mut rng = options.rng();
+        let ctx = produce_context(schema, options, &mut rng)?;
+        let finishing_rng = StdRng::seed_from_u64(rng.gen());
+        let series = options
+            .series_rng(&mut rng)
+            .zip(options.time_series.as_ref())
+            .map(|(rng, series)| {
+                Ok::<_, DrivelError>((SeriesState::new(series, record_schema)?, rng))
+            })
+            .transpose()?;
+        Ok(Self {
+            options,
+            ctx,
+            record_schema,
+            record_overrides,
+            rng,
+            finishing_rng,
+            series,
+            unique: UniqueValues::new(&options.unique, options.unique_ids),
+            case: options.root_case(),
+        })
+    }
+
+    /// Produces the next record.
+    pub(crate) fn next_record(&mut self) -> Result<serde_json::Value, DrivelError> {
+        let case = self.case;
+        self.case = case.map(|case| case + 1);
+        let mut rng = StdRng::seed_from_u64(self.rng.gen());
+        let mut record = produce_inner(
+            self.record_schema,
+            &self.ctx,
+            1,
+            self.record_overrides,
+            case,
+            &mut rng,
+        )?;
+        if self.unique.is_enabled() {
+            let (ctx, finishing_rng) = (&self.ctx, &mut self.finishing_rng);
+            let mut regenerate = |schema: &SchemaState, overrides: Option<&OverrideNode>| {
+                produce_inner(schema, ctx, 1, overrides, None, finishing_rng)
+            };
+            self.unique.enforce(
+                &mut record,
+                self.record_schema,
+                self.record_overrides,
+                &mut regenerate,
+            )?;
+        }
+        if let Some((state, rng)) = &mut self.series {
+            state.advance(&mut record, rng);
+        }
+        self.options.derivations.apply_to_record(&mut record)?;
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+    use crate::DistinctCount;
+
+    #[test]
+    fn produces_floats_in_ranges_too_wide_to_represent() {
+        for _ in 0..100 {
+            let number = random_float(f64::MIN, f64::MAX, &mut rand::thread_rng());
+            assert!(number.is_finite());
+        }
+    }
+
+    #[test]
+    fn writes_the_same_json_as_produce() {
+        let schema = SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: false,
+            schema: Box::new(SchemaState::Object {
+                required: IndexMap::from_iter([
+                    (
+                        "id".to_owned(),
+                        SchemaState::Number(NumberType::Integer { min: 0, max: 100 }),
+                    ),
+                    (
+                        "tags".to_owned(),
+                        SchemaState::Array {
+                            min_length: 2,
+                            max_length: 2,
+                            unique: false,
+                            schema: Box::new(SchemaState::Boolean),
+                        },
+                    ),
+                ]),
+                optional: IndexMap::new(),
+            }),
+        };
+        let mut overrides = Overrides::new();
+        overrides.insert("tags[]", crate::Override::Fixed(serde_json::json!(true)));
+
+        for n in [0, 1, DEFAULT_CHUNK_SIZE + 1] {
+            let options = ProduceOptions::new()
+                .count(n)
+                .seed(7)
+                .overrides(overrides.clone());
+            let expected =
+                serde_json::to_string_pretty(&produce_with_options(&schema, &options).unwrap())
+                    .unwrap();
+            for chunk_size in [1, 100, DEFAULT_CHUNK_SIZE] {
+                let mut written = vec![];
+                let options = options.clone().chunk_size(chunk_size);
+                produce_to_writer_with_options(&schema, &options, &mut written).unwrap();
+                assert_eq!(String::from_utf8(written).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn writes_distinct_elements_of_an_array_of_unique_elements_at_the_root() {
+        let schema = SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: true,
+            schema: Box::new(SchemaState::Number(NumberType::Integer { min: 0, max: 20 })),
+        };
+        let options = ProduceOptions::new().count(15).seed(3).chunk_size(4);
+
+        let mut written = vec![];
+        produce_to_writer_with_options(&schema, &options, &mut written).unwrap();
+
+        let produced: serde_json::Value = serde_json::from_slice(&written).unwrap();
+        assert_eq!(produced, produce_with_options(&schema, &options).unwrap());
+        let mut elements = produced
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|element| element.as_i64().unwrap())
+            .collect::<Vec<_>>();
+        elements.sort();
+        elements.dedup();
+        assert_eq!(elements.len(), 15);
+    }
+
+    #[test]
+    fn fills_unconstrained_arrays_with_the_default_array_length() {
+        let schema = SchemaState::Object {
+            required: IndexMap::from_iter([("tags".to_owned(), SchemaState::UnconstrainedArray)]),
+            optional: IndexMap::new(),
+        };
+        let mut overrides = Overrides::new();
+        overrides.insert("tags[]", crate::Override::Fixed(serde_json::json!("a")));
+        let options = ProduceOptions::new()
+            .overrides(overrides)
+            .defaults(SchemaDefaults {
+                array_lengths: 3..=3,
+                ..SchemaDefaults::default()
+            });
+
+        let produced = produce_with_options(&schema, &options).unwrap();
+
+        assert_eq!(produced, serde_json::json!({ "tags": ["a", "a", "a"] }));
+    }
+
+    #[test]
+    fn produces_decimals_of_their_scale_or_fails_when_none_lies_within_the_range() {
+        let decimal = |min, max| SchemaState::Object {
+            required: IndexMap::from_iter([(
+                "price".to_owned(),
+                SchemaState::Number(NumberType::Decimal { scale: 2, min, max }),
+            )]),
+            optional: IndexMap::new(),
+        };
+
+        let produced = produce_with_options(&decimal(0.005, 0.011), &ProduceOptions::new());
+        assert_eq!(produced.unwrap(), serde_json::json!({ "price": 0.01 }));
+
+        let produced = produce_with_options(&decimal(0.001, 0.004), &ProduceOptions::new());
+        assert_eq!(
+            produced.unwrap_err().to_string(),
+            "invalid decimal for price: no decimal with 2 decimal places lies within 0.001-0.004"
+        );
+    }
+
+    #[test]
+    fn serializes_the_same_data_as_produce() {
+        let schema = crate::infer_schema(
+            serde_json::json!([
+                { "id": 1, "name": "Alice", "tags": ["a", "b"], "point": [1.5, "x"], "note": null },
+                { "id": 2, "tags": [], "point": [2.5, "y"], "note": "hi", "extra": { "n": true } },
+                { "id": "3", "name": "Carol", "tags": ["c"], "point": [0.5, "z"] }
+            ]),
+            &crate::InferenceOptions::default(),
+        );
+        let mut overrides = Overrides::new();
+        overrides.insert("name", crate::Override::Fixed(serde_json::json!("fixed")));
+
+        for options in [
+            ProduceOptions::new().count(50).seed(3),
+            ProduceOptions::new().count(50).seed(3).edge_cases(true),
+            ProduceOptions::new().count(50).seed(3).overrides(overrides),
+        ] {
+            let mut serialized = vec![];
+            produce_to_serializer(
+                &schema,
+                &options,
+                &mut serde_json::Serializer::new(&mut serialized),
+            )
+            .unwrap();
+            let expected =
+                serde_json::to_vec(&produce_with_options(&schema, &options).unwrap()).unwrap();
+            assert_eq!(
+                String::from_utf8(serialized).unwrap(),
+                String::from_utf8(expected).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn draws_pooled_values_from_one_shared_pool() {
+        let schema = SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: false,
+            schema: Box::new(SchemaState::Object {
+                required: IndexMap::from_iter([
+                    (
+                        "created_by".to_owned(),
+                        SchemaState::String(StringType::UUID),
+                    ),
+                    (
+                        "updated_by".to_owned(),
+                        SchemaState::Nullable(Box::new(SchemaState::String(StringType::UUID))),
+                    ),
+                ]),
+                optional: IndexMap::new(),
+            }),
+        };
+        let overrides = Overrides::from_toml(
+            r#"
+            created_by = { pool = "users", size = 3 }
+            updated_by = { pool = "users" }
+            "#,
+        )
+        .unwrap();
+
+        let options = ProduceOptions::new().count(200).overrides(overrides);
+        let produced = produce_with_options(&schema, &options).unwrap();
+        let users = produced
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|record| [&record["created_by"], &record["updated_by"]])
+            .map(|user| user.as_str().unwrap().to_owned())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(users.len(), 3);
+    }
+
+    #[test]
+    fn leaves_containers_nested_deeper_than_the_max_depth_empty() {
+        let mut schema = SchemaState::Number(NumberType::Integer { min: 1, max: 1 });
+        for _ in 0..DEFAULT_MAX_DEPTH + 10 {
+            schema = SchemaState::Array {
+                min_length: 1,
+                max_length: 1,
+                unique: false,
+                schema: Box::new(schema),
+            };
+        }
+
+        let produced = produce_with_options(&schema, &ProduceOptions::new()).unwrap();
+        let mut produced = &produced;
+        let mut depth = 0;
+        while let Some([element]) = produced.as_array().map(Vec::as_slice) {
+            produced = element;
+            depth += 1;
+        }
+        assert_eq!(depth, DEFAULT_MAX_DEPTH + 1);
+        assert_eq!(produced, &serde_json::Value::Array(vec![]));
+    }
+
+    #[test]
+    fn produces_the_same_data_from_the_same_seed() {
+        let schema = SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: false,
+            schema: Box::new(SchemaState::Object {
+                required: IndexMap::from_iter([
+                    ("id".to_owned(), SchemaState::String(StringType::UUID)),
+                    (
+                        "phone".to_owned(),
+                        SchemaState::String(StringType::PhoneNumber {
+                            formats: [("+## ## ## ## ##".to_owned(), 1)].into(),
+                        }),
+                    ),
+                    (
+                        "status".to_owned(),
+                        SchemaState::String(StringType::Enum {
+                            variants: ["active", "banned", "pending"].map(str::to_owned).into(),
+                        }),
+                    ),
+                ]),
+                optional: IndexMap::from_iter([(
+                    "score".to_owned(),
+                    SchemaState::Nullable(Box::new(SchemaState::Number(NumberType::Float {
+                        min: 0.0,
+                        max: 1.0,
+                        non_finite: false,
+                        exclusive_min: false,
+                        exclusive_max: false,
+                    }))),
+                )]),
+            }),
+        };
+
+        let options = ProduceOptions::new()
+            .count(100)
+            .seed(42)
+            .locale(Locale::FrFr);
+        let produced = produce_with_options(&schema, &options).unwrap();
+        assert_eq!(produce_with_options(&schema, &options).unwrap(), produced);
+        assert_ne!(
+            produce_with_options(&schema, &options.clone().seed(43)).unwrap(),
+            produced
+        );
+    }
+
+    #[test]
+    fn draws_characters_by_how_often_they_were_seen() {
+        let schema = SchemaState::String(StringType::Unknown {
+            strings_seen: vec![],
+            chars_seen: HashMap::from([('a', 9), ('b', 1)]),
+            n_strings_seen: 0,
+            distinct: DistinctCount::new(),
+            n_chars_seen: 10,
+            min_length: Some(10_000),
+            max_length: Some(10_000),
+            prefix: String::new(),
+            suffix: String::new(),
+            structure: None,
+        });
+
+        let options = ProduceOptions::new().seed(11);
+        let produced = produce_with_options(&schema, &options).unwrap();
+        assert_eq!(produce_with_options(&schema, &options).unwrap(), produced);
+        let produced = produced.as_str().unwrap();
+        assert!(produced.chars().all(|c| c == 'a' || c == 'b'));
+        let n_b = produced.chars().filter(|c| *c == 'b').count();
+        assert!((700..1300).contains(&n_b), "{}", n_b);
+    }
+
+    #[test]
+    fn produces_nulls_and_optional_fields_with_the_given_probabilities() {
+        let schema = SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: false,
+            schema: Box::new(SchemaState::Object {
+                required: IndexMap::from_iter([(
+                    "nullable".to_owned(),
+                    SchemaState::Nullable(Box::new(SchemaState::Boolean)),
+                )]),
+                optional: IndexMap::from_iter([("optional".to_owned(), SchemaState::Boolean)]),
+            }),
+        };
+
+        let options = ProduceOptions::new()
+            .count(50)
+            .null_probability(1.0)
+            .optional_probability(0.0);
+        for record in produce_with_options(&schema, &options)
+            .unwrap()
+            .as_array()
+            .unwrap()
+        {
+            assert_eq!(record, &serde_json::json!({ "nullable": null }));
+        }
+    }
+
+    #[test]
+    fn produces_boundary_values_as_edge_cases() {
+        let schema = SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: false,
+            schema: Box::new(SchemaState::Object {
+                required: IndexMap::from_iter([
+                    (
+                        "age".to_owned(),
+                        SchemaState::Number(NumberType::Integer { min: 18, max: 65 }),
+                    ),
+                    (
+                        "status".to_owned(),
+                        SchemaState::String(StringType::Enum {
+                            variants: ["active", "banned", "pending"].map(str::to_owned).into(),
+                        }),
+                    ),
+                    (
+                        "tags".to_owned(),
+                        SchemaState::Array {
+                            min_length: 0,
+                            max_length: 3,
+                            unique: false,
+                            schema: Box::new(SchemaState::Boolean),
+                        },
+                    ),
+                ]),
+                optional: IndexMap::from_iter([(
+                    "nickname".to_owned(),
+                    SchemaState::Nullable(Box::new(SchemaState::String(StringType::Unknown {
+                        strings_seen: vec![],
+                        chars_seen: HashMap::from([('a', 1)]),
+                        n_strings_seen: 0,
+                        distinct: DistinctCount::new(),
+                        n_chars_seen: 1,
+                        min_length: Some(1),
+                        max_length: Some(4),
+                        prefix: String::new(),
+                        suffix: String::new(),
+                        structure: None,
+                    }))),
+                )]),
+            }),
+        };
+
+        let options = ProduceOptions::new().count(8).edge_cases(true);
+        let produced = produce_with_options(&schema, &options).unwrap();
+        assert_eq!(crate::validate(&produced, &schema), vec![]);
+        let records = produced.as_array().unwrap();
+        assert_eq!(
+            records[0],
+            serde_json::json!({ "age": 18, "status": "active", "tags": [] })
+        );
+        assert_eq!(
+            records[1],
+            serde_json::json!({
+                "age": 65,
+                "status": "banned",
+                "tags": [true, false, true],
+                "nickname": null,
+            })
+        );
+        assert_eq!(records[3]["nickname"], "a");
+        assert_eq!(records[7]["nickname"], "aaaa");
+        let statuses = records
+            .iter()
+            .map(|record| record["status"].as_str().unwrap())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(statuses.len(), 3);
+    }
 }
@@ -0,0 +1,283 @@
+//! Profiling where inference spends its time, and how often each format is tried against the
+//! strings of the input.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::{evidence::group_digits, Detectors};
+
+/// A stage of inference whose time is profiled.
+#[derive(Clone, Copy)]
+pub(crate) enum Stage {
+    Parsing,
+    Detection,
+    Merging,
+    Enums,
+}
+
+/// The times a detector was tried, and the time spent trying it.
+#[derive(Default)]
+pub(crate) struct DetectorStats {
+    name: String,
+    tried: AtomicU64,
+    matched: AtomicU64,
+    nanos: AtomicU64,
+}
+
+impl DetectorStats {
+    /// Tries a detector, counting whether it detected its format.
+    pub(crate) fn tried<T>(&self, detect: impl FnOnce() -> Option<T>) -> Option<T> {
+        let started = Instant::now();
+        let detected = detect();
+        add(&self.nanos, started.elapsed());
+        self.tried.fetch_add(1, Ordering::Relaxed);
+        if detected.is_some() {
+            self.matched.fetch_add(1, Ordering::Relaxed);
+        }
+        detected
+    }
+}
+
+/// How often a detector was tried while inferring, and the time spent trying it. See `Profile`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct DetectorProfile {
+    /// The name of the format, e.g. `email`.
+    pub name: String,
+    /// The number of strings the detector was tried against.
+    pub tried: u64,
+    /// The number of strings detected as its format.
+    pub matched: u64,
+    /// The time spent trying the detector.
+    pub time: Duration,
+}
+
+/// The time spent in each stage of inferring a schema: parsing the input, detecting the formats
+/// of strings, merging the schemas of values, and applying enums, along with how often each
+/// detector was tried. The times of stages that run on several threads at once are summed
+/// across them, so they add up to more than the time that passed.
+///
+/// A profile records inference when it is given as `InferenceOptions::profile`, and can be
+/// shared between several inferences using the same detectors, adding up their times.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use drivel::{infer_schema, InferenceOptions, Profile};
+/// use serde_json::json;
+///
+/// let profile = Arc::new(Profile::new());
+/// let options = InferenceOptions {
+///     profile: Some(profile.clone()),
+///     ..InferenceOptions::default()
+/// };
+/// infer_schema(json!([{ "email": "ada@example.com" }, { "email": "alan@example.com" }]), &options);
+///
+/// let email = profile.detectors().into_iter().find(|d| d.name == "email").unwrap();
+/// assert_eq!((email.tried, email.matched), (2, 2));
+/// ```
+#[derive(Default)]
+pub struct Profile {
+    stages: [AtomicU64; 4],
+    detectors: OnceLock<Vec<DetectorStats>>,
+}
+
+fn add(nanos: &AtomicU64, elapsed: Duration) {
+    nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+}
+
+impl Profile {
+    /// Returns a profile in which no time was spent.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds time spent parsing the input, which happens before inference and is profiled by
+    /// whoever parses it.
+    pub fn add_parsing(&self, elapsed: Duration) {
+        add(&self.stages[Stage::Parsing as usize], elapsed);
+    }
+
+    /// Returns the time spent parsing the input.
+    pub fn parsing(&self) -> Duration {
+        self.stage(Stage::Parsing)
+    }
+
+    /// Returns the time spent detecting the formats of strings.
+    pub fn detection(&self) -> Duration {
+        self.stage(Stage::Detection)
+    }
+
+    /// Returns the time spent merging the schemas of values.
+    pub fn merging(&self) -> Duration {
+        self.stage(Stage::Merging)
+    }
+
+    /// Returns the time spent telling which strings and integers are enums, and settling the
+    /// other types that can only be told once all values were seen.
+    pub fn enums(&self) -> Duration {
+        self.stage(Stage::Enums)
+    }
+
+    /// Returns how often each detector was tried, in the order in which they are tried.
+    pub fn detectors(&self) -> Vec<DetectorProfile> {
+        self.detectors
+            .get()
+            .into_iter()
+            .flatten()
+            .map(|stats| DetectorProfile {
+                name: stats.name.clone(),
+                tried: stats.tried.load(Ordering::Relaxed),
+                matched: stats.matched.load(Ordering::Relaxed),
+                time: Duration::from_nanos(stats.nanos.load(Ordering::Relaxed)),
+            })
+            .collect()
+    }
+
+    fn stage(&self, stage: Stage) -> Duration {
+        Duration::from_nanos(self.stages[stage as usize].load(Ordering::Relaxed))
+    }
+
+    /// Returns the statistics of `detectors`, in the order in which they are tried.
+    pub(crate) fn detector_stats(&self, detectors: &Detectors) -> &[DetectorStats] {
+        self.detectors.get_or_init(|| {
+            detectors
+                .names()
+                .map(|name| DetectorStats {
+                    name: name.to_owned(),
+                    ..DetectorStats::default()
+                })
+                .collect()
+        })
+    }
+}
+
+/// Runs `f`, adding the time it takes to `stage` of the profile, if any.
+pub(crate) fn timed<T>(profile: Option<&Profile>, stage: Stage, f: impl FnOnce() -> T) -> T {
+    let Some(profile) = profile else {
+        return f();
+    };
+    let started = Instant::now();
+    let result = f();
+    add(&profile.stages[stage as usize], started.elapsed());
+    result
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Time spent, summed across threads:")?;
+        for (name, time) in [
+            ("parsing", self.parsing()),
+            ("detection", self.detection()),
+            ("merging", self.merging()),
+            ("enums", self.enums()),
+        ] {
+            writeln!(f, "  {:<10} {:>10}", name, format!("{:.1?}", time))?;
+        }
+
+        let detectors = self.detectors();
+        if detectors.is_empty() {
+            return Ok(());
+        }
+        let width = detectors
+            .iter()
+            .map(|detector| detector.name.len())
+            .max()
+            .unwrap_or_default()
+            .max("format".len());
+        writeln!(f)?;
+        writeln!(f, "Formats tried against strings:")?;
+        writeln!(
+            f,
+            "  {:<width$} {:>12} {:>12} {:>10}",
+            "format", "tried", "matched", "time"
+        )?;
+        for detector in detectors {
+            writeln!(
+                f,
+                "  {:<width$} {:>12} {:>12} {:>10}",
+                detector.name,
+                group_digits(detector.tried as usize),
+                group_digits(detector.matched as usize),
+                format!("{:.1?}", detector.time)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema, infer_schema_from_iter, EnumInference, InferenceOptions};
+
+    #[test]
+    fn counts_the_detectors_tried_until_one_matches() {
+        let profile = Arc::new(Profile::new());
+        let options = InferenceOptions {
+            profile: Some(profile.clone()),
+            enum_inference: Some(EnumInference {
+                max_unique_ratio: 0.1,
+                min_sample_size: 1,
+            }),
+            ..InferenceOptions::default()
+        };
+        let values = (0..10)
+            .map(|n| json!({ "id": n, "contact": format!("user{}@example.com", n) }))
+            .chain([json!({ "id": 10, "contact": "not an address" })])
+            .collect();
+        infer_schema_from_iter(values, &options);
+
+        let detectors = profile.detectors();
+        let names = detectors
+            .iter()
+            .map(|detector| detector.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, Detectors::new().names().collect::<Vec<_>>());
+        let email = names.iter().position(|name| *name == "email").unwrap();
+        for (i, detector) in detectors.iter().enumerate() {
+            let (tried, matched) = match i.cmp(&email) {
+                std::cmp::Ordering::Less => (11, 0),
+                std::cmp::Ordering::Equal => (11, 10),
+                std::cmp::Ordering::Greater => (1, 0),
+            };
+            assert_eq!(
+                (detector.tried, detector.matched),
+                (tried, matched),
+                "{}",
+                detector.name
+            );
+        }
+    }
+
+    #[test]
+    fn reports_the_time_of_each_stage() {
+        let profile = Arc::new(Profile::new());
+        let options = InferenceOptions {
+            profile: Some(profile.clone()),
+            ..InferenceOptions::default()
+        };
+        profile.add_parsing(Duration::from_millis(12));
+        infer_schema(json!(["a", "b"]), &options);
+
+        assert_eq!(profile.parsing(), Duration::from_millis(12));
+        let report = profile.to_string();
+        assert!(report.starts_with("Time spent, summed across threads:\n  parsing        12.0ms\n"));
+        assert!(report.contains("\n  detection  "));
+        assert!(report.contains("\n  uuid                    2            0 "));
+    }
+
+    #[test]
+    fn reports_no_detectors_until_strings_are_inferred() {
+        let profile = Profile::new();
+        assert!(profile.detectors().is_empty());
+        assert!(!profile.to_string().contains("Formats tried"));
+    }
+}
@@ -0,0 +1,946 @@
+//! Exporting schemas as Protocol Buffers (proto3) message definitions, and reading the
+//! messages of compiled descriptor sets.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use indexmap::IndexMap;
+use prost::Message;
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FileDescriptorSet,
+};
+
+use crate::codegen::{self, field_name, pascal_case, screaming_snake_case, unique_name, Field};
+use crate::json_schema::{bounds, error};
+use crate::{
+    infer::integer_value, pattern, DistinctCount, DrivelError, JsonSchemaError, NumberType,
+    ParsedSchema, SchemaDefaults, SchemaState, StringType,
+};
+
+/// The name of the message describing each record.
+const ROOT_MESSAGE: &str = "Record";
+
+/// The field numbers that protobuf reserves for its own use.
+const RESERVED_FIELD_NUMBERS: std::ops::Range<usize> = 19000..20000;
+
+const STRUCT_PROTO: &str = "google/protobuf/struct.proto";
+const TIMESTAMP_PROTO: &str = "google/protobuf/timestamp.proto";
+
+/// The messages and enums defined within a message, and the names they have taken.
+#[derive(Default)]
+struct Scope {
+    definitions: Vec<Vec<String>>,
+    names: HashSet<String>,
+}
+
+impl Scope {
+    fn define(&mut self, lines: Vec<String>) {
+        self.definitions.push(lines);
+    }
+}
+
+/// Returns the narrowest protobuf integer type that holds every integer in `min..=max`.
+/// Integers beyond the range of every integer type become `double`, which is how they are
+/// written in JSON.
+fn integer_type(min: i128, max: i128) -> &'static str {
+    let fits = |lower: i128, upper: i128| min >= lower && max <= upper;
+    if fits(i32::MIN.into(), i32::MAX.into()) {
+        "int32"
+    } else if fits(i64::MIN.into(), i64::MAX.into()) {
+        "int64"
+    } else if fits(0, u64::MAX.into()) {
+        "uint64"
+    } else {
+        "double"
+    }
+}
+
+/// The type of a field, and whether it is repeated, a map or can be null.
+struct FieldType {
+    name: String,
+    repeated: bool,
+    map: bool,
+    nullable: bool,
+}
+
+impl FieldType {
+    fn new(name: impl Into<String>) -> Self {
+        FieldType {
+            name: name.into(),
+            repeated: false,
+            map: false,
+            nullable: false,
+        }
+    }
+}
+
+fn enum_definition(name: &str, variants: &HashSet<String>) -> Vec<String> {
+    let prefix = screaming_snake_case(name);
+    let mut variants = variants.iter().collect::<Vec<_>>();
+    variants.sort();
+    let mut taken = HashSet::new();
+    let mut lines = vec![format!("enum {} {{", name)];
+    lines.push(format!("  {}_UNSPECIFIED = 0;", prefix));
+    taken.insert(format!("{}_UNSPECIFIED", prefix));
+    for (number, variant) in variants.into_iter().enumerate() {
+        let value = unique_name(
+            format!("{}_{}", prefix, screaming_snake_case(variant)),
+            &mut taken,
+        );
+        lines.push(format!("  {} = {};", value, number + 1));
+    }
+    lines.push("}".to_owned());
+    lines
+}
+
+fn field_type(
+    schema: &SchemaState,
+    type_name: &str,
+    scope: &mut Scope,
+    imports: &mut BTreeSet<&'static str>,
+) -> FieldType {
+    match schema {
+        // protobuf has no unions or tuples; a `Value` holds any JSON value
+        SchemaState::Initial
+        | SchemaState::Indefinite
+        | SchemaState::Null
+        | SchemaState::Union(_)
+        | SchemaState::Tuple(_)
+        | SchemaState::Const(serde_json::Value::Null) => {
+            imports.insert(STRUCT_PROTO);
+            FieldType::new("google.protobuf.Value")
+        }
+        SchemaState::Nullable(inner) => FieldType {
+            nullable: true,
+            ..field_type(inner, type_name, scope, imports)
+        },
+        SchemaState::String(StringType::Enum { variants }) => {
+            let name = unique_name(pascal_case(type_name), &mut scope.names);
+            scope.define(enum_definition(&name, variants));
+            FieldType::new(name)
+        }
+        SchemaState::String(StringType::DateTimeISO8601 { .. }) => {
+            imports.insert(TIMESTAMP_PROTO);
+            FieldType::new("google.protobuf.Timestamp")
+        }
+        // protobuf writes bytes as base64 in JSON
+        SchemaState::String(StringType::Base64 { .. }) => FieldType::new("bytes"),
+        SchemaState::String(_) | SchemaState::Const(serde_json::Value::String(_)) => {
+            FieldType::new("string")
+        }
+        SchemaState::Number(
+            number_type @ (NumberType::Integer { .. }
+            | NumberType::Enum { .. }
+            | NumberType::BigInteger { .. }),
+        ) => {
+            let (min, max) = number_type.integer_range().unwrap_or_default();
+            FieldType::new(integer_type(min, max))
+        }
+        SchemaState::Number(NumberType::Float { .. } | NumberType::Decimal { .. }) => {
+            FieldType::new("double")
+        }
+        SchemaState::Const(serde_json::Value::Number(n)) => match integer_value(n) {
+            Some(n) => FieldType::new(integer_type(n, n)),
+            None => FieldType::new("double"),
+        },
+        SchemaState::Boolean | SchemaState::Const(serde_json::Value::Bool(_)) => {
+            FieldType::new("bool")
+        }
+        SchemaState::Array { schema, .. } => {
+            // repeated fields cannot hold nulls, nor be repeated or maps themselves, so arrays
+            // of arrays or maps are wrapped in a message of their own
+            let element = field_type(schema, type_name, scope, imports);
+            FieldType {
+                name: wrap_collection(element, "List", type_name, scope),
+                repeated: true,
+                map: false,
+                nullable: false,
+            }
+        }
+        SchemaState::Map { schema, .. } => {
+            let value = field_type(schema, type_name, scope, imports);
+            FieldType {
+                name: format!(
+                    "map<string, {}>",
+                    wrap_collection(value, "Values", type_name, scope)
+                ),
+                repeated: false,
+                map: true,
+                nullable: false,
+            }
+        }
+        SchemaState::UnconstrainedArray | SchemaState::Const(serde_json::Value::Array(_)) => {
+            imports.insert(STRUCT_PROTO);
+            FieldType::new("google.protobuf.ListValue")
+        }
+        SchemaState::Object { required, optional } => {
+            let name = unique_name(pascal_case(type_name), &mut scope.names);
+            scope.define(message(&name, codegen::fields(required, optional), imports));
+            FieldType::new(name)
+        }
+        SchemaState::UnconstrainedObject | SchemaState::Const(serde_json::Value::Object(_)) => {
+            imports.insert(STRUCT_PROTO);
+            FieldType::new("google.protobuf.Struct")
+        }
+    }
+}
+
+/// Returns the name of the type of the elements of an array or the values of a map, which are
+/// wrapped in a message of their own, named after the field with `suffix`, when they are
+/// arrays or maps themselves.
+fn wrap_collection(element: FieldType, suffix: &str, type_name: &str, scope: &mut Scope) -> String {
+    if !element.repeated && !element.map {
+        return element.name;
+    }
+    let name = unique_name(
+        format!("{}{}", pascal_case(type_name), suffix),
+        &mut scope.names,
+    );
+    let label = if element.repeated { "repeated " } else { "" };
+    scope.define(vec![
+        format!("message {} {{", name),
+        format!("  {}{} values = 1;", label, element.name),
+        "}".to_owned(),
+    ]);
+    name
+}
+
+/// Returns the lines defining a message with `fields`, numbered in the order they are given in.
+fn message(name: &str, fields: Vec<Field>, imports: &mut BTreeSet<&'static str>) -> Vec<String> {
+    let mut scope = Scope::default();
+    let mut field_names = HashSet::new();
+    let mut lines = vec![format!("message {} {{", name)];
+    let numbers = (1..).filter(|number| !RESERVED_FIELD_NUMBERS.contains(number));
+    for (field, number) in fields.into_iter().zip(numbers) {
+        let name = unique_name(field_name(field.key), &mut field_names);
+        let field_type = field_type(field.schema, &name, &mut scope, imports);
+        // maps cannot be optional, but are empty when missing
+        let label = if field_type.repeated {
+            "repeated "
+        } else if field_type.map {
+            ""
+        } else if field.optional || field_type.nullable {
+            "optional "
+        } else {
+            ""
+        };
+        // keys that are not valid field names keep their name in JSON
+        let options = if name != field.key {
+            format!(" [json_name = {}]", serde_json::Value::from(field.key))
+        } else {
+            String::new()
+        };
+        lines.push(format!(
+            "  {}{} {} = {}{};",
+            label, field_type.name, name, number, options
+        ));
+    }
+    for definition in scope.definitions {
+        lines.push(String::new());
+        lines.extend(definition.into_iter().map(|line| format!("  {}", line)));
+    }
+    lines.push("}".to_owned());
+    lines
+}
+
+/// Exports a schema as a Protocol Buffers (proto3) file, with a `Record` message describing
+/// each record: the elements of an array at the root, or the root itself.
+///
+/// Objects become nested messages and arrays `repeated` fields; optional and nullable fields
+/// are `optional`, and string enums become enums. Values protobuf cannot describe, like
+/// unions and tuples, become `google.protobuf.Value`s, and ISO 8601 datetimes
+/// `google.protobuf.Timestamp`s. Records that are not objects are held in the `value` field of
+/// the `Record` message.
+///
+/// Fields are numbered in the order they were first seen in, required fields before optional
+/// ones, so the same input always gets the same numbers. The numbers are not stable as the data
+/// changes though: a field that is added, removed or made optional renumbers the fields after
+/// it. Fix the numbers of a message by hand before relying on them to stay wire compatible.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_proto, InferenceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!({ "id": 1, "email": null, "tags": ["a"] }),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(
+///     to_proto(&schema),
+///     r#"syntax = "proto3";
+///
+/// import "google/protobuf/struct.proto";
+///
+/// message Record {
+///   int32 id = 1;
+///   google.protobuf.Value email = 2;
+///   repeated string tags = 3;
+/// }
+/// "#
+/// );
+/// ```
+pub fn to_proto(schema: &SchemaState) -> String {
+    let mut imports = BTreeSet::new();
+    let lines = message(ROOT_MESSAGE, codegen::record_fields(schema), &mut imports);
+
+    let mut proto = "syntax = \"proto3\";\n\n".to_owned();
+    for import in &imports {
+        proto.push_str(&format!("import \"{}\";\n", import));
+    }
+    if !imports.is_empty() {
+        proto.push('\n');
+    }
+    for line in lines {
+        proto.push_str(&line);
+        proto.push('\n');
+    }
+    proto
+}
+
+/// The most combinations of the members of the oneofs of a message that are produced as the
+/// branches of a union, one branch per combination.
+const MAX_ONEOF_BRANCHES: usize = 64;
+
+/// The JSON form of a `google.protobuf.Duration`: seconds, with up to nine decimals.
+const DURATION_PATTERN: &str = r"^(0|[1-9][0-9]{0,4})(\.[0-9]{1,9})?s$";
+
+/// A message or enum of a descriptor set.
+enum Definition<'a> {
+    Message(&'a DescriptorProto),
+    Enum(&'a EnumDescriptorProto),
+}
+
+struct ParseContext<'a> {
+    /// The messages and enums of the descriptor set, by their full names, like `.my.pkg.Event`.
+    definitions: HashMap<String, Definition<'a>>,
+    defaults: &'a SchemaDefaults,
+    warnings: Vec<String>,
+    /// The messages currently being parsed, used to detect recursive messages.
+    message_stack: Vec<String>,
+}
+
+fn collect_definitions<'a>(
+    scope: &str,
+    messages: &'a [DescriptorProto],
+    enums: &'a [EnumDescriptorProto],
+    definitions: &mut HashMap<String, Definition<'a>>,
+) {
+    for enumeration in enums {
+        definitions.insert(
+            format!("{}.{}", scope, enumeration.name()),
+            Definition::Enum(enumeration),
+        );
+    }
+    for message in messages {
+        let name = format!("{}.{}", scope, message.name());
+        collect_definitions(&name, &message.nested_type, &message.enum_type, definitions);
+        definitions.insert(name, Definition::Message(message));
+    }
+}
+
+/// Returns the integers of a protobuf type within `lower..=upper`, in the default range where
+/// it fits within the type.
+fn parse_integer(lower: i64, upper: i64, ctx: &ParseContext) -> SchemaState {
+    let (min, max) = (
+        (*ctx.defaults.integers.start()).max(lower),
+        (*ctx.defaults.integers.end()).min(upper),
+    );
+    let (min, max) = if min <= max {
+        (min, max)
+    } else {
+        (lower, upper)
+    };
+    SchemaState::Number(NumberType::Integer { min, max })
+}
+
+fn parse_string(ctx: &ParseContext) -> SchemaState {
+    let (min_length, max_length) = bounds(None, None, &ctx.defaults.string_lengths);
+    SchemaState::String(StringType::Unknown {
+        strings_seen: vec![],
+        chars_seen: HashMap::new(),
+        n_strings_seen: 0,
+        distinct: DistinctCount::new(),
+        n_chars_seen: 0,
+        min_length: Some(min_length),
+        max_length: Some(max_length),
+        prefix: String::new(),
+        suffix: String::new(),
+        structure: None,
+    })
+}
+
+/// Returns the schema of the JSON form of a well-known type, which differs from the form of
+/// its fields.
+fn parse_well_known(name: &str, ctx: &ParseContext) -> Option<SchemaState> {
+    let wrapped = match name {
+        ".google.protobuf.Timestamp" => {
+            return Some(SchemaState::String(StringType::DateTimeISO8601 {
+                range: None,
+            }))
+        }
+        ".google.protobuf.Duration" => {
+            return Some(SchemaState::String(StringType::Pattern(
+                DURATION_PATTERN.to_owned(),
+            )))
+        }
+        ".google.protobuf.FieldMask" => return Some(parse_string(ctx)),
+        ".google.protobuf.Struct" | ".google.protobuf.Any" => {
+            return Some(SchemaState::UnconstrainedObject)
+        }
+        ".google.protobuf.ListValue" => return Some(SchemaState::UnconstrainedArray),
+        ".google.protobuf.Value" => return Some(SchemaState::Indefinite),
+        ".google.protobuf.Empty" => {
+            return Some(SchemaState::Object {
+                required: IndexMap::new(),
+                optional: IndexMap::new(),
+            })
+        }
+        ".google.protobuf.DoubleValue" => Type::Double,
+        ".google.protobuf.FloatValue" => Type::Float,
+        ".google.protobuf.Int64Value" => Type::Int64,
+        ".google.protobuf.UInt64Value" => Type::Uint64,
+        ".google.protobuf.Int32Value" => Type::Int32,
+        ".google.protobuf.UInt32Value" => Type::Uint32,
+        ".google.protobuf.BoolValue" => Type::Bool,
+        ".google.protobuf.StringValue" => Type::String,
+        ".google.protobuf.BytesValue" => Type::Bytes,
+        _ => return None,
+    };
+    // wrappers are written as the scalars they wrap, or null
+    Some(SchemaState::Nullable(Box::new(parse_scalar(wrapped, ctx)?)))
+}
+
+/// Parses the type of a scalar field, i.e. of any type but a message or an enum.
+fn parse_scalar(field_type: Type, ctx: &ParseContext) -> Option<SchemaState> {
+    // 64-bit integers are written as strings in JSON, but read from numbers too
+    let schema = match field_type {
+        Type::Double | Type::Float => SchemaState::Number(NumberType::Float {
+            min: *ctx.defaults.floats.start(),
+            max: *ctx.defaults.floats.end(),
+            non_finite: false,
+            exclusive_min: false,
+            exclusive_max: false,
+        }),
+        Type::Int32 | Type::Sint32 | Type::Sfixed32 => {
+            parse_integer(i32::MIN.into(), i32::MAX.into(), ctx)
+        }
+        Type::Uint32 | Type::Fixed32 => parse_integer(0, u32::MAX.into(), ctx),
+        Type::Int64 | Type::Sint64 | Type::Sfixed64 => parse_integer(i64::MIN, i64::MAX, ctx),
+        Type::Uint64 | Type::Fixed64 => parse_integer(0, i64::MAX, ctx),
+        Type::Bool => SchemaState::Boolean,
+        Type::String => parse_string(ctx),
+        Type::Bytes => {
+            let (min_length, max_length) = bounds(None, None, &ctx.defaults.string_lengths);
+            SchemaState::String(StringType::Base64 {
+                min_length,
+                max_length,
+            })
+        }
+        Type::Enum | Type::Message | Type::Group => return None,
+    };
+    Some(schema)
+}
+
+/// Returns true if `name` is the entry message of a map field, which protoc generates for
+/// each field like `map<string, int32> counts = 1;`.
+fn is_map_entry(name: &str, ctx: &ParseContext) -> bool {
+    matches!(
+        ctx.definitions.get(name),
+        Some(Definition::Message(message))
+            if message.options.as_ref().is_some_and(|options| options.map_entry())
+    )
+}
+
+/// Returns the pattern of the keys of a map whose keys are of `key_type`, as the JSON form
+/// writes them: as strings, whatever their type.
+fn map_key_pattern(key_type: Type) -> &'static str {
+    match key_type {
+        Type::Bool => "^(true|false)$",
+        Type::Uint32 | Type::Uint64 | Type::Fixed32 | Type::Fixed64 => "^[0-9]{1,9}$",
+        Type::Int32
+        | Type::Int64
+        | Type::Sint32
+        | Type::Sint64
+        | Type::Sfixed32
+        | Type::Sfixed64 => "^-?[0-9]{1,9}$",
+        _ => pattern::ANY_KEY,
+    }
+}
+
+/// Parses the type of a single value of a field, ignoring whether it is repeated.
+fn parse_field_type(
+    field: &FieldDescriptorProto,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    if let Some(schema) = parse_scalar(field.r#type(), ctx) {
+        return Ok(schema);
+    }
+    let schema = match field.r#type() {
+        Type::Enum => match ctx.definitions.get(field.type_name()) {
+            Some(Definition::Enum(enumeration)) => SchemaState::String(StringType::Enum {
+                variants: enumeration
+                    .value
+                    .iter()
+                    .map(|value| value.name().to_owned())
+                    .collect(),
+            }),
+            _ => {
+                return Err(error(
+                    pointer,
+                    format!("unknown enum {:?}", field.type_name()),
+                ))
+            }
+        },
+        _ => parse_message(field.type_name(), pointer, ctx)?,
+    };
+    Ok(schema)
+}
+
+fn parse_message(
+    name: &str,
+    pointer: &str,
+    ctx: &mut ParseContext,
+) -> Result<SchemaState, JsonSchemaError> {
+    if let Some(schema) = parse_well_known(name, ctx) {
+        return Ok(schema);
+    }
+    let message = match ctx.definitions.get(name) {
+        Some(Definition::Message(message)) => *message,
+        _ => return Err(error(pointer, format!("unknown message {:?}", name))),
+    };
+    if is_map_entry(name, ctx) {
+        let field = |number| {
+            message
+                .field
+                .iter()
+                .find(|field| field.number() == number)
+                .ok_or_else(|| {
+                    error(
+                        pointer,
+                        format!("map entry {:?} lacks a key or value", name),
+                    )
+                })
+        };
+        let (key, value) = (field(1)?, field(2)?);
+        let schema = parse_field_type(value, pointer, ctx)?;
+        let (min_length, max_length) = bounds(None, None, &ctx.defaults.map_lengths);
+        return Ok(SchemaState::Map {
+            pattern: map_key_pattern(key.r#type()).to_owned(),
+            min_length,
+            max_length,
+            schema: Box::new(schema),
+        });
+    }
+    if ctx.message_stack.iter().any(|m| m == name) {
+        return Err(error(
+            pointer,
+            format!("recursive message {:?} is not supported", name),
+        ));
+    }
+
+    ctx.message_stack.push(name.to_owned());
+    let mut required = IndexMap::new();
+    let mut optional = IndexMap::new();
+    let mut oneofs = vec![vec![]; message.oneof_decl.len()];
+    for field in &message.field {
+        let key = match &field.json_name {
+            Some(json_name) => json_name.clone(),
+            None => field.name().to_owned(),
+        };
+        let field_pointer = format!("{}.{}", &name[1..], field.name());
+        let mut schema = parse_field_type(field, &field_pointer, ctx)?;
+        if field.label() == Label::Repeated && !is_map_entry(field.type_name(), ctx) {
+            let (min_length, max_length) = bounds(None, None, &ctx.defaults.array_lengths);
+            schema = SchemaState::Array {
+                min_length,
+                max_length,
+                unique: false,
+                schema: Box::new(schema),
+            };
+        }
+        match field.oneof_index {
+            // the oneof of an `optional` field is synthetic, and holds only that field
+            Some(_) if field.proto3_optional() => {
+                optional.insert(key, schema);
+            }
+            Some(idx) => match oneofs.get_mut(idx as usize) {
+                Some(members) => members.push((key, schema)),
+                None => {
+                    ctx.message_stack.pop();
+                    return Err(error(&field_pointer, "unknown oneof"));
+                }
+            },
+            None => {
+                required.insert(key, schema);
+            }
+        }
+    }
+    ctx.message_stack.pop();
+
+    // each combination of the members of the oneofs, one of each, is a branch of a union
+    let oneofs = oneofs
+        .into_iter()
+        .filter(|members| !members.is_empty())
+        .collect::<Vec<_>>();
+    let combinations = oneofs
+        .iter()
+        .map(|members| members.len())
+        .try_fold(1usize, |n, members| n.checked_mul(members))
+        .unwrap_or(usize::MAX);
+    if combinations > MAX_ONEOF_BRANCHES {
+        ctx.warnings.push(format!(
+            "{}: the oneofs have too many combinations of members to produce one member of each, so their members are produced as optional fields",
+            &name[1..]
+        ));
+        optional.extend(oneofs.into_iter().flatten());
+        return Ok(SchemaState::Object { required, optional });
+    }
+    let mut branches = vec![required];
+    for members in oneofs {
+        branches = branches
+            .into_iter()
+            .flat_map(|branch| {
+                members.iter().map(move |(key, schema)| {
+                    let mut branch = branch.clone();
+                    branch.insert(key.clone(), schema.clone());
+                    branch
+                })
+            })
+            .collect();
+    }
+    let mut branches = branches
+        .into_iter()
+        .map(|required| SchemaState::Object {
+            required,
+            optional: optional.clone(),
+        })
+        .collect::<Vec<_>>();
+    Ok(match branches.len() {
+        1 => branches.pop().unwrap(),
+        _ => SchemaState::Union(branches),
+    })
+}
+
+/// Parses a message of a compiled descriptor set, as written by
+/// `protoc --include_imports --descriptor_set_out`, into a SchemaState of its JSON form, so
+/// that data can be produced from it.
+///
+/// `message` is the full name of the message, like `my.pkg.Event`. Its fields are keyed by
+/// their JSON names, as the proto3 JSON mapping (and gRPC-JSON transcoding) writes them:
+/// enums as the names of their values, bytes as base64, `repeated` fields as arrays, and
+/// well-known types like `google.protobuf.Timestamp` in their JSON form. Fields marked
+/// `optional` are optional, and each combination of the members of the oneofs, one member of
+/// each, is a branch of a union. Numbers, strings and arrays are bounded by `defaults`, and
+/// integers by the range of their type too; 64-bit integers are produced as numbers, which
+/// parsers accept as well as strings. Maps have `defaults.map_lengths` keys, written as strings
+/// whatever their type.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{parse_proto_descriptor, SchemaDefaults, SchemaState, StringType};
+/// use prost::Message;
+/// use prost_types::{
+///     field_descriptor_proto::Type, DescriptorProto, FieldDescriptorProto,
+///     FileDescriptorProto, FileDescriptorSet,
+/// };
+///
+/// let field = FieldDescriptorProto {
+///     name: Some("user_name".to_owned()),
+///     json_name: Some("userName".to_owned()),
+///     number: Some(1),
+///     r#type: Some(Type::String as i32),
+///     ..Default::default()
+/// };
+/// let descriptors = FileDescriptorSet {
+///     file: vec![FileDescriptorProto {
+///         package: Some("my.pkg".to_owned()),
+///         message_type: vec![DescriptorProto {
+///             name: Some("Event".to_owned()),
+///             field: vec![field],
+///             ..Default::default()
+///         }],
+///         ..Default::default()
+///     }],
+/// };
+///
+/// let parsed = parse_proto_descriptor(
+///     &descriptors.encode_to_vec(),
+///     "my.pkg.Event",
+///     &SchemaDefaults::default(),
+/// )
+/// .unwrap();
+///
+/// let SchemaState::Object { required, .. } = parsed.schema else { panic!() };
+/// assert!(matches!(required["userName"], SchemaState::String(StringType::Unknown { .. })));
+/// ```
+pub fn parse_proto_descriptor(
+    descriptor_set: &[u8],
+    message: &str,
+    defaults: &SchemaDefaults,
+) -> Result<ParsedSchema, DrivelError> {
+    let descriptor_set = FileDescriptorSet::decode(descriptor_set)?;
+    let mut ctx = ParseContext {
+        definitions: HashMap::new(),
+        defaults,
+        warnings: vec![],
+        message_stack: vec![],
+    };
+    for file in &descriptor_set.file {
+        let scope = match file.package() {
+            "" => String::new(),
+            package => format!(".{}", package),
+        };
+        collect_definitions(
+            &scope,
+            &file.message_type,
+            &file.enum_type,
+            &mut ctx.definitions,
+        );
+    }
+    let name = format!(".{}", message.trim_start_matches('.'));
+    let schema = parse_message(&name, &name[1..], &mut ctx)?;
+    Ok(ParsedSchema {
+        schema,
+        warnings: ctx.warnings,
+    })
+}
+
+impl From<prost::DecodeError> for DrivelError {
+    fn from(err: prost::DecodeError) -> Self {
+        DrivelError::InvalidDescriptor(err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_nested_messages_enums_and_optional_fields() {
+        let address = SchemaState::Object {
+            required: IndexMap::from_iter([(
+                "city".to_owned(),
+                SchemaState::String(StringType::Email),
+            )]),
+            optional: IndexMap::new(),
+        };
+        let schema = SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: false,
+            schema: Box::new(SchemaState::Object {
+                required: IndexMap::from_iter([
+                    (
+                        "status".to_owned(),
+                        SchemaState::String(StringType::Enum {
+                            variants: HashSet::from_iter([
+                                "open".to_owned(),
+                                "inReview".to_owned(),
+                            ]),
+                        }),
+                    ),
+                    (
+                        "shipping-address".to_owned(),
+                        SchemaState::Nullable(Box::new(address)),
+                    ),
+                    (
+                        "at".to_owned(),
+                        SchemaState::String(StringType::DateTimeISO8601 { range: None }),
+                    ),
+                    (
+                        "matrix".to_owned(),
+                        SchemaState::Array {
+                            min_length: 1,
+                            max_length: 1,
+                            unique: false,
+                            schema: Box::new(SchemaState::Array {
+                                min_length: 1,
+                                max_length: 1,
+                                unique: false,
+                                schema: Box::new(SchemaState::Number(NumberType::Float {
+                                    min: 0.0,
+                                    max: 1.0,
+                                    non_finite: false,
+                                    exclusive_min: false,
+                                    exclusive_max: false,
+                                })),
+                            }),
+                        },
+                    ),
+                ]),
+                optional: IndexMap::from_iter([(
+                    "count".to_owned(),
+                    SchemaState::Number(NumberType::Integer {
+                        min: 0,
+                        max: 5_000_000_000,
+                    }),
+                )]),
+            }),
+        };
+
+        assert_eq!(
+            to_proto(&schema),
+            r#"syntax = "proto3";
+
+import "google/protobuf/timestamp.proto";
+
+message Record {
+  Status status = 1;
+  optional ShippingAddress shipping_address = 2 [json_name = "shipping-address"];
+  google.protobuf.Timestamp at = 3;
+  repeated MatrixList matrix = 4;
+  optional int64 count = 5;
+
+  enum Status {
+    STATUS_UNSPECIFIED = 0;
+    STATUS_IN_REVIEW = 1;
+    STATUS_OPEN = 2;
+  }
+
+  message ShippingAddress {
+    string city = 1;
+  }
+
+  message MatrixList {
+    repeated double values = 1;
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn numbers_fields_in_the_order_they_were_first_seen() {
+        let schema = crate::infer_schema(
+            serde_json::json!([{ "name": "a", "age": 1 }, { "name": "b", "age": 2, "email": "c" }]),
+            &crate::InferenceOptions::default(),
+        );
+        let proto = to_proto(&schema);
+        assert!(
+            proto.contains("  string name = 1;\n  int32 age = 2;\n  optional string email = 3;\n")
+        );
+    }
+
+    fn field(name: &str, number: i32, field_type: Type) -> FieldDescriptorProto {
+        // protoc names fields in JSON in lower camel case
+        let pascal_case = pascal_case(name);
+        let json_name = pascal_case[..1].to_lowercase() + &pascal_case[1..];
+        FieldDescriptorProto {
+            name: Some(name.to_owned()),
+            json_name: Some(json_name),
+            number: Some(number),
+            r#type: Some(field_type as i32),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_the_json_form_of_messages() {
+        let typed = |mut field: FieldDescriptorProto, type_name: &str| {
+            field.type_name = Some(type_name.to_owned());
+            field
+        };
+        let in_oneof = |mut field: FieldDescriptorProto, idx: i32| {
+            field.oneof_index = Some(idx);
+            field
+        };
+        let mut tags = field("tags", 3, Type::String);
+        tags.label = Some(Label::Repeated as i32);
+        let mut note = in_oneof(field("note", 6, Type::String), 1);
+        note.proto3_optional = Some(true);
+        let mut labels = typed(field("labels", 7, Type::Message), ".shop.Order.LabelsEntry");
+        labels.label = Some(Label::Repeated as i32);
+        let order = DescriptorProto {
+            name: Some("Order".to_owned()),
+            field: vec![
+                typed(field("status", 1, Type::Enum), ".shop.Status"),
+                typed(
+                    field("placed_at", 2, Type::Message),
+                    ".google.protobuf.Timestamp",
+                ),
+                tags,
+                in_oneof(field("card_number", 4, Type::String), 0),
+                in_oneof(field("iban", 5, Type::String), 0),
+                note,
+                labels,
+            ],
+            nested_type: vec![DescriptorProto {
+                name: Some("LabelsEntry".to_owned()),
+                field: vec![
+                    field("key", 1, Type::String),
+                    field("value", 2, Type::String),
+                ],
+                options: Some(prost_types::MessageOptions {
+                    map_entry: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            oneof_decl: vec![Default::default(), Default::default()],
+            ..Default::default()
+        };
+        let descriptors = FileDescriptorSet {
+            file: vec![prost_types::FileDescriptorProto {
+                package: Some("shop".to_owned()),
+                message_type: vec![order],
+                enum_type: vec![EnumDescriptorProto {
+                    name: Some("Status".to_owned()),
+                    value: ["OPEN", "PAID"]
+                        .iter()
+                        .map(|name| prost_types::EnumValueDescriptorProto {
+                            name: Some(name.to_string()),
+                            ..Default::default()
+                        })
+                        .collect(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+
+        let parsed = parse_proto_descriptor(
+            &descriptors.encode_to_vec(),
+            "shop.Order",
+            &SchemaDefaults::default(),
+        )
+        .unwrap();
+
+        let SchemaState::Union(branches) = parsed.schema else {
+            panic!()
+        };
+        assert_eq!(branches.len(), 2);
+        for (branch, member) in branches.iter().zip(["cardNumber", "iban"]) {
+            let SchemaState::Object { required, optional } = branch else {
+                panic!()
+            };
+            assert_eq!(
+                required.keys().collect::<Vec<_>>(),
+                ["status", "placedAt", "tags", "labels", member]
+            );
+            assert_eq!(optional.keys().collect::<Vec<_>>(), ["note"]);
+            assert_eq!(
+                required["status"],
+                SchemaState::String(StringType::Enum {
+                    variants: HashSet::from_iter(["OPEN".to_owned(), "PAID".to_owned()]),
+                })
+            );
+            assert_eq!(
+                required["placedAt"],
+                SchemaState::String(StringType::DateTimeISO8601 { range: None })
+            );
+            assert!(matches!(required["tags"], SchemaState::Array { .. }));
+            assert!(matches!(
+                &required["labels"],
+                SchemaState::Map { pattern, min_length: 1, max_length: 4, schema }
+                    if pattern == crate::pattern::ANY_KEY
+                        && matches!(**schema, SchemaState::String(StringType::Unknown { .. }))
+            ));
+        }
+        assert!(parsed.warnings.is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+//! Python bindings, built with the `python` feature as the `drivel` extension module.
+//!
+//! Values cross the boundary as JSON: Python objects are serialized with the `json` module, and
+//! produced data and exported schemas are returned as the dicts and lists it loads them into.
+
+use std::ffi::CString;
+
+use pyo3::exceptions::{PyUserWarning, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyString;
+
+use crate::{
+    produce_with_options, DrivelError, EnumInference, InferenceOptions, Input, ProduceOptions,
+    SchemaDefaults, SchemaState, DEFAULT_MAX_DEPTH, DEFAULT_MAX_SAMPLES,
+};
+
+fn value_error(err: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+impl From<DrivelError> for PyErr {
+    fn from(err: DrivelError) -> Self {
+        value_error(err)
+    }
+}
+
+/// Converts a Python object, or a string holding JSON, into a JSON value.
+fn to_json(data: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    let json = if data.is_instance_of::<PyString>() {
+        data.extract::<String>()?
+    } else {
+        data.py()
+            .import("json")?
+            .call_method1("dumps", (data,))?
+            .extract::<String>()?
+    };
+    serde_json::from_str(&json).map_err(value_error)
+}
+
+fn to_python<'py>(py: Python<'py>, value: &serde_json::Value) -> PyResult<Bound<'py, PyAny>> {
+    py.import("json")?
+        .call_method1("loads", (value.to_string(),))
+}
+
+/// A schema, inferred from data or parsed from a JSON Schema.
+#[pyclass(name = "Schema", module = "drivel", frozen)]
+struct PySchema {
+    schema: SchemaState,
+}
+
+#[pymethods]
+impl PySchema {
+    /// Exports the schema as a JSON Schema document.
+    fn to_json_schema<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_python(py, &crate::to_json_schema(&self.schema))
+    }
+
+    /// Exports the schema as a JSON Type Definition (RFC 8927) document.
+    fn to_jtd<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_python(py, &crate::to_jtd(&self.schema))
+    }
+
+    /// Exports the schema as a Protocol Buffers (proto3) file.
+    fn to_proto(&self) -> String {
+        crate::to_proto(&self.schema)
+    }
+
+    /// Exports the schema as a BigQuery table schema.
+    fn to_bigquery<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_python(py, &crate::to_bigquery(&self.schema))
+    }
+
+    /// Exports the schema as an Avro schema.
+    fn to_avro_schema<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_python(py, &crate::to_avro_schema(&self.schema))
+    }
+
+    /// Exports the schema as a Spark SQL schema in DDL.
+    fn to_spark(&self) -> String {
+        crate::to_spark(&self.schema)
+    }
+
+    /// Generates Python classes describing the schema, as `"pydantic"` models or as
+    /// `"dataclass"`es.
+    #[pyo3(signature = (style = "pydantic"))]
+    fn to_python_classes(&self, style: &str) -> PyResult<String> {
+        let style = match style {
+            "pydantic" => crate::PythonStyle::Pydantic,
+            "dataclass" => crate::PythonStyle::Dataclass,
+            style => {
+                return Err(value_error(format!(
+                    "unknown style of Python classes: {}, expected \"pydantic\" or \"dataclass\"",
+                    style
+                )))
+            }
+        };
+        Ok(crate::to_python_classes(&self.schema, style))
+    }
+
+    /// Generates a TypeScript file declaring a Zod validator of each record.
+    fn to_zod(&self) -> String {
+        crate::to_zod(&self.schema)
+    }
+
+    /// Exports the schema as GraphQL type definitions.
+    fn to_graphql(&self) -> String {
+        crate::to_graphql(&self.schema)
+    }
+
+    /// Exports the schema as the mappings of an Elasticsearch (or OpenSearch) index.
+    fn to_es_mapping<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_python(py, &crate::to_es_mapping(&self.schema))
+    }
+
+    /// Exports the schema of the records as a component of an OpenAPI 3.1 document.
+    #[pyo3(signature = (name = "Record"))]
+    fn to_openapi<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyAny>> {
+        to_python(py, &crate::to_openapi([(name, &self.schema)]))
+    }
+
+    fn __str__(&self) -> String {
+        self.schema.to_string_pretty()
+    }
+}
+
+/// Infers a schema from a Python object, or from a string holding a JSON document or JSON
+/// lines. The elements of a list (or of an array at the root) are inferred as records.
+#[pyfunction(name = "infer_schema")]
+#[pyo3(signature = (
+    data,
+    *,
+    infer_enum = false,
+    enum_max_uniq = 0.1,
+    enum_min_n = 1,
+    max_samples = DEFAULT_MAX_SAMPLES,
+    unconstrained_empty = false,
+    widen_singletons = false,
+    embedded_json = false,
+    max_depth = DEFAULT_MAX_DEPTH,
+    format_tolerance = None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn py_infer_schema(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    infer_enum: bool,
+    enum_max_uniq: f64,
+    enum_min_n: usize,
+    max_samples: usize,
+    unconstrained_empty: bool,
+    widen_singletons: bool,
+    embedded_json: bool,
+    max_depth: usize,
+    format_tolerance: Option<f64>,
+) -> PyResult<PySchema> {
+    let input = if data.is_instance_of::<PyString>() {
+        Input::parse(&data.extract::<String>()?)?
+    } else {
+        Input::Document(to_json(data)?)
+    };
+    let options = InferenceOptions {
+        enum_inference: infer_enum.then_some(EnumInference {
+            max_unique_ratio: enum_max_uniq,
+            min_sample_size: enum_min_n,
+        }),
+        max_samples,
+        unconstrained_empty,
+        widen_singletons,
+        embedded_json,
+        max_depth,
+        format_tolerance,
+        ..Default::default()
+    };
+    let schema = py.detach(|| input.infer_schema(&options));
+    Ok(PySchema { schema })
+}
+
+/// Parses a JSON Schema, given as a dict or as a string holding JSON. Keywords that cannot be
+/// honoured are reported as `UserWarning`s.
+#[pyfunction(name = "parse_json_schema")]
+fn py_parse_json_schema(py: Python<'_>, schema: &Bound<'_, PyAny>) -> PyResult<PySchema> {
+    let parsed = crate::parse_json_schema(&to_json(schema)?, &SchemaDefaults::default())?;
+    for warning in &parsed.warnings {
+        let message = CString::new(warning.to_string()).map_err(value_error)?;
+        PyErr::warn(py, &py.get_type::<PyUserWarning>(), &message, 1)?;
+    }
+    Ok(PySchema {
+        schema: parsed.schema,
+    })
+}
+
+/// Parses a JSON Type Definition (RFC 8927), given as a dict or as a string holding JSON.
+/// Forms that cannot be honoured are reported as `UserWarning`s.
+#[pyfunction(name = "parse_jtd")]
+fn py_parse_jtd(py: Python<'_>, schema: &Bound<'_, PyAny>) -> PyResult<PySchema> {
+    let parsed = crate::parse_jtd(&to_json(schema)?, &SchemaDefaults::default())?;
+    for warning in &parsed.warnings {
+        let message = CString::new(warning.to_string()).map_err(value_error)?;
+        PyErr::warn(py, &py.get_type::<PyUserWarning>(), &message, 1)?;
+    }
+    Ok(PySchema {
+        schema: parsed.schema,
+    })
+}
+
+/// Produces data adhering to a schema: a list of `n` records if the schema describes an array
+/// of records or `n` is more than 1, and a single value otherwise. A `seed` makes the data the
+/// same every time.
+#[pyfunction(name = "produce")]
+#[pyo3(signature = (schema, n = 1, seed = None))]
+fn py_produce<'py>(
+    py: Python<'py>,
+    schema: &Bound<'py, PySchema>,
+    n: usize,
+    seed: Option<u64>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let schema = &schema.get().schema;
+    let mut options = ProduceOptions::new().count(n);
+    if let Some(seed) = seed {
+        options = options.seed(seed);
+    }
+    let produced = py.detach(|| match schema {
+        SchemaState::Array { .. } => produce_with_options(schema, &options),
+        _ if n > 1 => produce_with_options(
+            &SchemaState::Array {
+                min_length: 1,
+                max_length: 1,
+                unique: false,
+                schema: Box::new(schema.clone()),
+            },
+            &options,
+        ),
+        _ => produce_with_options(schema, &options),
+    });
+    to_python(py, &produced.map_err(value_error)?)
+}
+
+#[pymodule]
+#[pyo3(name = "drivel")]
+fn drivel_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PySchema>()?;
+    module.add_function(wrap_pyfunction!(py_infer_schema, module)?)?;
+    module.add_function(wrap_pyfunction!(py_parse_json_schema, module)?)?;
+    module.add_function(wrap_pyfunction!(py_parse_jtd, module)?)?;
+    module.add_function(wrap_pyfunction!(py_produce, module)?)?;
+    Ok(())
+}
@@ -0,0 +1,420 @@
+//! Generating Python classes, as pydantic models or dataclasses, from schemas.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::codegen::{self, field_name, pascal_case, unique_name, Field};
+use crate::{NumberType, SchemaState, StringType};
+
+/// The name of the class describing each record.
+const ROOT_CLASS: &str = "Record";
+
+/// The words Python reserves, which cannot name fields.
+const KEYWORDS: [&str; 35] = [
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+/// The names imported from other modules, which classes cannot take.
+const IMPORTED_NAMES: [&str; 11] = [
+    "Any",
+    "BaseModel",
+    "Decimal",
+    "Dict",
+    "EmailStr",
+    "Field",
+    "List",
+    "Literal",
+    "Optional",
+    "Tuple",
+    "Union",
+];
+
+/// The kind of Python classes to generate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PythonStyle {
+    /// pydantic models, which validate and parse the data they are given.
+    Pydantic,
+    /// Standard library dataclasses, which declare the shape of the data only.
+    Dataclass,
+}
+
+/// The classes generated so far, and the names they have taken.
+struct Module {
+    style: PythonStyle,
+    /// The names imported from each module, like `List` from `typing`.
+    imports: BTreeMap<&'static str, BTreeSet<&'static str>>,
+    classes: Vec<String>,
+    names: HashSet<String>,
+}
+
+impl Module {
+    fn import(&mut self, module: &'static str, name: &'static str) -> String {
+        self.imports.entry(module).or_default().insert(name);
+        name.to_owned()
+    }
+}
+
+/// Turns a key into the name of an attribute, which cannot be a keyword, nor start with an
+/// underscore, as pydantic keeps those private.
+fn attribute_name(key: &str) -> String {
+    let name = field_name(key);
+    if name.starts_with('_') {
+        format!("field{}", name)
+    } else if KEYWORDS.contains(&name.as_str()) {
+        format!("{}_", name)
+    } else {
+        name
+    }
+}
+
+/// Returns a `Literal` of values, written as Python.
+fn literal(values: Vec<String>, module: &mut Module) -> String {
+    format!(
+        "{}[{}]",
+        module.import("typing", "Literal"),
+        values.join(", ")
+    )
+}
+
+fn python_literal(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::Null => Some("None".to_owned()),
+        serde_json::Value::Bool(true) => Some("True".to_owned()),
+        serde_json::Value::Bool(false) => Some("False".to_owned()),
+        // JSON strings are valid Python strings
+        serde_json::Value::Number(_) | serde_json::Value::String(_) => Some(value.to_string()),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => None,
+    }
+}
+
+fn type_hint(schema: &SchemaState, class_name: &str, module: &mut Module) -> String {
+    match schema {
+        SchemaState::Initial | SchemaState::Indefinite => module.import("typing", "Any"),
+        SchemaState::Null => "None".to_owned(),
+        SchemaState::Nullable(inner) => match type_hint(inner, class_name, module).as_str() {
+            hint @ ("Any" | "None") => hint.to_owned(),
+            hint => format!("{}[{}]", module.import("typing", "Optional"), hint),
+        },
+        SchemaState::String(StringType::Enum { variants }) => {
+            let mut variants = variants.iter().collect::<Vec<_>>();
+            variants.sort();
+            let variants = variants
+                .into_iter()
+                .map(|variant| serde_json::Value::from(variant.as_str()).to_string())
+                .collect();
+            literal(variants, module)
+        }
+        SchemaState::String(StringType::DateTimeISO8601 { .. }) => {
+            module.import("datetime", "datetime")
+        }
+        SchemaState::String(StringType::IsoDate { .. }) => module.import("datetime", "date"),
+        SchemaState::String(StringType::Time { .. }) => module.import("datetime", "time"),
+        // pydantic parses ISO 8601 durations, dataclasses keep them as strings
+        SchemaState::String(StringType::Duration { .. })
+            if module.style == PythonStyle::Pydantic =>
+        {
+            module.import("datetime", "timedelta")
+        }
+        SchemaState::String(StringType::UUID) => module.import("uuid", "UUID"),
+        SchemaState::String(StringType::Email) if module.style == PythonStyle::Pydantic => {
+            module.import("pydantic", "EmailStr")
+        }
+        SchemaState::String(_) => "str".to_owned(),
+        SchemaState::Number(NumberType::Enum { variants, .. }) => {
+            let mut variants = variants.iter().collect::<Vec<_>>();
+            variants.sort();
+            literal(variants.iter().map(|n| n.to_string()).collect(), module)
+        }
+        SchemaState::Number(NumberType::Integer { .. } | NumberType::BigInteger { .. }) => {
+            "int".to_owned()
+        }
+        SchemaState::Number(NumberType::Decimal { .. }) => module.import("decimal", "Decimal"),
+        SchemaState::Number(NumberType::Float { .. }) => "float".to_owned(),
+        SchemaState::Boolean => "bool".to_owned(),
+        SchemaState::Array { schema, .. } => {
+            let element = type_hint(schema, class_name, module);
+            format!("{}[{}]", module.import("typing", "List"), element)
+        }
+        SchemaState::Tuple(elements) => {
+            let elements = elements
+                .iter()
+                .map(|element| type_hint(element, class_name, module))
+                .collect::<Vec<_>>();
+            format!(
+                "{}[{}]",
+                module.import("typing", "Tuple"),
+                elements.join(", ")
+            )
+        }
+        SchemaState::Union(variants) => {
+            let variants = variants
+                .iter()
+                .map(|variant| type_hint(variant, class_name, module))
+                .collect::<Vec<_>>();
+            format!(
+                "{}[{}]",
+                module.import("typing", "Union"),
+                variants.join(", ")
+            )
+        }
+        SchemaState::UnconstrainedArray => format!(
+            "{}[{}]",
+            module.import("typing", "List"),
+            module.import("typing", "Any")
+        ),
+        SchemaState::Object { required, optional } => {
+            let name = unique_name(pascal_case(class_name), &mut module.names);
+            class(&name, codegen::fields(required, optional), module);
+            name
+        }
+        SchemaState::UnconstrainedObject => format!(
+            "{}[str, {}]",
+            module.import("typing", "Dict"),
+            module.import("typing", "Any")
+        ),
+        SchemaState::Map { schema, .. } => {
+            let value = type_hint(schema, class_name, module);
+            format!("{}[str, {}]", module.import("typing", "Dict"), value)
+        }
+        SchemaState::Const(value) => match python_literal(value) {
+            Some(value) if value == "None" => value,
+            Some(value) => literal(vec![value], module),
+            None => module.import("typing", "Any"),
+        },
+    }
+}
+
+/// Generates a class named `name` with `fields`, after the classes of its fields, so that
+/// every class is defined before it is used.
+fn class(name: &str, fields: Vec<Field>, module: &mut Module) {
+    let mut attribute_names = HashSet::new();
+    let mut attributes = vec![];
+    for field in fields {
+        let attribute = unique_name(attribute_name(field.key), &mut attribute_names);
+        let mut hint = type_hint(field.schema, &attribute, module);
+        // absent fields are None, so that they can be left out when creating the class
+        if field.optional && hint != "Any" && hint != "None" && !hint.starts_with("Optional[") {
+            hint = format!("{}[{}]", module.import("typing", "Optional"), hint);
+        }
+        let default = field.optional.then_some("None");
+        let line = match module.style {
+            PythonStyle::Pydantic if attribute != field.key => {
+                let alias = serde_json::Value::from(field.key);
+                let field = module.import("pydantic", "Field");
+                match default {
+                    Some(default) => format!(
+                        "{}: {} = {}(default={}, alias={})",
+                        attribute, hint, field, default, alias
+                    ),
+                    None => format!("{}: {} = {}(alias={})", attribute, hint, field, alias),
+                }
+            }
+            PythonStyle::Dataclass if attribute != field.key => {
+                let alias = serde_json::Value::from(field.key);
+                match default {
+                    Some(default) => format!("{}: {} = {}  # {}", attribute, hint, default, alias),
+                    None => format!("{}: {}  # {}", attribute, hint, alias),
+                }
+            }
+            _ => match default {
+                Some(default) => format!("{}: {} = {}", attribute, hint, default),
+                None => format!("{}: {}", attribute, hint),
+            },
+        };
+        attributes.push(line);
+    }
+    if attributes.is_empty() {
+        attributes.push("pass".to_owned());
+    }
+
+    let header = match module.style {
+        PythonStyle::Pydantic => {
+            format!(
+                "class {}({}):",
+                name,
+                module.import("pydantic", "BaseModel")
+            )
+        }
+        PythonStyle::Dataclass => {
+            format!(
+                "@{}\nclass {}:",
+                module.import("dataclasses", "dataclass"),
+                name
+            )
+        }
+    };
+    let body = attributes
+        .iter()
+        .map(|attribute| format!("    {}", attribute))
+        .collect::<Vec<_>>()
+        .join("\n");
+    module.classes.push(format!("{}\n{}\n", header, body));
+}
+
+/// Generates Python classes describing a schema, with a `Record` class describing each
+/// record: the elements of an array at the root, or the root itself. The classes are
+/// pydantic models or dataclasses, depending on `style`.
+///
+/// Objects become classes of their own, named after their fields, and arrays `List`s.
+/// Nullable fields are `Optional`, and optional fields default to `None`. String and integer
+/// enums become `Literal`s, and ISO 8601 datetimes and dates, UUIDs and decimals become
+/// `datetime`s, `date`s, `UUID`s and `Decimal`s; emails are pydantic's `EmailStr`. Keys that
+/// are not valid attribute names are renamed, with the key as the alias of the pydantic
+/// field. Records that are not objects are held in the `value` attribute of `Record`.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_python_classes, InferenceOptions, PythonStyle};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!({ "id": 1, "address": { "city": "Utrecht" }, "tags": ["a"] }),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(
+///     to_python_classes(&schema, PythonStyle::Pydantic),
+///     r#"from typing import List
+///
+/// from pydantic import BaseModel
+///
+///
+/// class Address(BaseModel):
+///     city: str
+///
+///
+/// class Record(BaseModel):
+///     id: int
+///     address: Address
+///     tags: List[str]
+/// "#
+/// );
+/// ```
+pub fn to_python_classes(schema: &SchemaState, style: PythonStyle) -> String {
+    let mut module = Module {
+        style,
+        imports: BTreeMap::new(),
+        classes: vec![],
+        names: HashSet::from_iter(
+            IMPORTED_NAMES
+                .into_iter()
+                .chain([ROOT_CLASS, "UUID"])
+                .map(str::to_owned),
+        ),
+    };
+    class(ROOT_CLASS, codegen::record_fields(schema), &mut module);
+
+    let import_lines = |modules: Vec<(&&str, &BTreeSet<&str>)>| {
+        modules
+            .into_iter()
+            .map(|(module, names)| {
+                let names = names.iter().copied().collect::<Vec<_>>().join(", ");
+                format!("from {} import {}\n", module, names)
+            })
+            .collect::<String>()
+    };
+    // the standard library first, then pydantic
+    let (third_party, standard) = module
+        .imports
+        .iter()
+        .partition::<Vec<_>, _>(|(module, _)| **module == "pydantic");
+    let imports = [import_lines(standard), import_lines(third_party)]
+        .into_iter()
+        .filter(|lines| !lines.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n\n{}", imports, module.classes.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+
+    fn schema() -> SchemaState {
+        SchemaState::Object {
+            required: IndexMap::from_iter([
+                ("id".to_owned(), SchemaState::String(StringType::UUID)),
+                (
+                    "status".to_owned(),
+                    SchemaState::String(StringType::Enum {
+                        variants: HashSet::from_iter(["open".to_owned(), "closed".to_owned()]),
+                    }),
+                ),
+                (
+                    "created-at".to_owned(),
+                    SchemaState::String(StringType::DateTimeISO8601 { range: None }),
+                ),
+                (
+                    "owner".to_owned(),
+                    SchemaState::Nullable(Box::new(SchemaState::Object {
+                        required: IndexMap::from_iter([(
+                            "email".to_owned(),
+                            SchemaState::String(StringType::Email),
+                        )]),
+                        optional: IndexMap::new(),
+                    })),
+                ),
+            ]),
+            optional: IndexMap::from_iter([(
+                "class".to_owned(),
+                SchemaState::Number(NumberType::Integer { min: 1, max: 3 }),
+            )]),
+        }
+    }
+
+    #[test]
+    fn generates_pydantic_models() {
+        assert_eq!(
+            to_python_classes(&schema(), PythonStyle::Pydantic),
+            r#"from datetime import datetime
+from typing import Literal, Optional
+from uuid import UUID
+
+from pydantic import BaseModel, EmailStr, Field
+
+
+class Owner(BaseModel):
+    email: EmailStr
+
+
+class Record(BaseModel):
+    id: UUID
+    status: Literal["closed", "open"]
+    created_at: datetime = Field(alias="created-at")
+    owner: Optional[Owner]
+    class_: Optional[int] = Field(default=None, alias="class")
+"#
+        );
+    }
+
+    #[test]
+    fn generates_dataclasses() {
+        assert_eq!(
+            to_python_classes(&schema(), PythonStyle::Dataclass),
+            r#"from dataclasses import dataclass
+from datetime import datetime
+from typing import Literal, Optional
+from uuid import UUID
+
+
+@dataclass
+class Owner:
+    email: str
+
+
+@dataclass
+class Record:
+    id: UUID
+    status: Literal["closed", "open"]
+    created_at: datetime  # "created-at"
+    owner: Optional[Owner]
+    class_: Optional[int] = None  # "class"
+"#
+        );
+    }
+}
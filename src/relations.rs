@@ -0,0 +1,195 @@
+//! Relationships between the values produced at different paths, so that the entities of a
+//! dataset refer to one another, like orders to the users who placed them.
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    path::{self, parse_steps, visit_mut, Step},
+    DrivelError,
+};
+
+/// A relation with its paths parsed into steps.
+struct Relation<'a> {
+    path: Vec<Step>,
+    target: Vec<Step>,
+    /// The path as it was given, to report errors with.
+    name: &'a str,
+}
+
+/// A set of relationships that draw the values produced at some paths from the values produced
+/// at others, like `orders[].user_id` from `users[].id`, so that the data joins.
+///
+/// Paths use the same notation as overrides, and are relative to the elements of an array at
+/// the root.
+#[derive(Clone, Debug, Default)]
+pub struct Relations {
+    /// The paths drawing their values, and the paths they draw them from.
+    relations: Vec<(String, String)>,
+}
+
+impl Relations {
+    /// Returns an empty set of relations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if no relations have been added.
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+
+    /// Draws the values produced at `path` from the values produced at `target`.
+    pub fn insert(&mut self, path: &str, target: &str) {
+        self.relations.push((path.to_owned(), target.to_owned()));
+    }
+
+    /// Parses relations from a TOML document mapping paths to the paths they draw their values
+    /// from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::Relations;
+    ///
+    /// let relations = Relations::from_toml(r#"
+    ///     "orders[].user_id" = "users[].id"
+    ///     "orders[].items[].product_id" = "products[].id"
+    /// "#).unwrap();
+    ///
+    /// assert!(!relations.is_empty());
+    /// ```
+    pub fn from_toml(document: &str) -> Result<Self, DrivelError> {
+        let table: serde_json::Map<String, serde_json::Value> = toml::from_str(document)?;
+
+        let mut relations = Self::new();
+        for (path, target) in table {
+            match target {
+                serde_json::Value::String(target) => relations.insert(&path, &target),
+                target => {
+                    return Err(DrivelError::InvalidRelation {
+                        path,
+                        message: format!("expected the path to draw from, found {}", target),
+                    })
+                }
+            }
+        }
+        Ok(relations)
+    }
+
+    /// Returns the relations in the order in which their values are drawn: a relation drawing
+    /// from a path that draws from another one comes after that one.
+    fn ordered(&self) -> Result<Vec<Relation<'_>>, DrivelError> {
+        let mut pending = self
+            .relations
+            .iter()
+            .map(|(path, target)| Relation {
+                path: parse_steps(path),
+                target: parse_steps(target),
+                name: path,
+            })
+            .collect::<Vec<_>>();
+        let mut ordered = vec![];
+        while !pending.is_empty() {
+            let ready = pending.iter().position(|relation| {
+                !pending.iter().any(|other| {
+                    relation.target.starts_with(&other.path)
+                        || other.path.starts_with(&relation.target)
+                })
+            });
+            match ready {
+                Some(idx) => ordered.push(pending.remove(idx)),
+                None => {
+                    return Err(DrivelError::InvalidRelation {
+                        path: pending[0].name.to_owned(),
+                        message: "the relations draw from one another in a cycle".to_owned(),
+                    })
+                }
+            }
+        }
+        Ok(ordered)
+    }
+
+    /// Replaces the values produced at the paths of the relations by values picked at random
+    /// from those produced at the paths they draw from. Nulls are kept, as are missing fields.
+    pub(crate) fn apply(
+        &self,
+        value: &mut serde_json::Value,
+        root_is_array: bool,
+        rng: &mut impl Rng,
+    ) -> Result<(), DrivelError> {
+        for Relation { path, target, name } in self.ordered()? {
+            let (path, target) = (
+                path::from_root(path, root_is_array),
+                path::from_root(target, root_is_array),
+            );
+
+            let mut pool = vec![];
+            visit_mut(value, &target, &mut |value| {
+                if !value.is_null() {
+                    pool.push(value.clone());
+                }
+            });
+            if pool.is_empty() {
+                return Err(DrivelError::InvalidRelation {
+                    path: name.to_owned(),
+                    message: "no values were produced at the path it draws from".to_owned(),
+                });
+            }
+            visit_mut(value, &path, &mut |value| {
+                if !value.is_null() {
+                    *value = pool.choose(rng).cloned().unwrap_or_default();
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn draws_values_from_the_paths_related_to_in_order() {
+        let relations = Relations::from_toml(
+            r#"
+            "orders[].user_id" = "users[].id"
+            "users[].id" = "accounts[]"
+            "#,
+        )
+        .unwrap();
+        let mut value = json!({
+            "accounts": [10, 11],
+            "users": [{ "id": 1 }, { "id": 2 }, { "id": 3 }],
+            "orders": [{ "user_id": 5 }, { "user_id": null }, {}],
+        });
+
+        relations
+            .apply(&mut value, false, &mut StdRng::seed_from_u64(1))
+            .unwrap();
+        for user in value["users"].as_array().unwrap() {
+            assert!([json!(10), json!(11)].contains(&user["id"]));
+        }
+        assert!([json!(10), json!(11)].contains(&value["orders"][0]["user_id"]));
+        assert_eq!(value["orders"][1], json!({ "user_id": null }));
+        assert_eq!(value["orders"][2], json!({}));
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        let mut relations = Relations::new();
+        relations.insert("a", "b");
+        relations.insert("b", "a");
+        assert!(matches!(
+            relations.apply(
+                &mut json!({ "a": 1, "b": 2 }),
+                false,
+                &mut rand::thread_rng()
+            ),
+            Err(DrivelError::InvalidRelation { .. })
+        ));
+    }
+}
@@ -0,0 +1,399 @@
+//! A terminal UI for reviewing and correcting a schema before it is described or produced from.
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    Terminal,
+};
+
+use crate::{path, to_json_schema, DrivelError, SchemaState, StringType, Transform, Transforms};
+
+/// What to do with a schema once it has been reviewed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReviewOutcome {
+    /// Describe the corrected schema.
+    Describe(SchemaState),
+    /// Produce data from the corrected schema.
+    Produce(SchemaState),
+    /// Neither describe the schema nor produce data from it.
+    Cancelled,
+}
+
+const HELP: &str = "↑↓ move  o optional  n nullable  e enum/string  r rename  g range  t type  s save  p produce  q done  esc cancel";
+
+/// A line of the schema tree: the records, a field, or the elements of an array.
+struct Row {
+    /// The path of the values, as transforms refer to them.
+    path: String,
+    depth: usize,
+    label: String,
+    optional: bool,
+    nullable: bool,
+    enum_of_strings: bool,
+    description: String,
+}
+
+/// Describes a schema on a single line, leaving what is within objects and arrays to the rows
+/// below it.
+fn describe(schema: &SchemaState) -> String {
+    match schema {
+        SchemaState::Object { .. } => "object".to_owned(),
+        SchemaState::Array {
+            min_length,
+            max_length,
+            unique,
+            ..
+        } => {
+            let unique = if *unique { ", unique" } else { "" };
+            if min_length == max_length {
+                format!("array ({}{})", min_length, unique)
+            } else {
+                format!("array ({}-{}{})", min_length, max_length, unique)
+            }
+        }
+        SchemaState::Map { pattern, .. } => format!("map (keys: /{}/)", pattern),
+        SchemaState::Tuple(elements) => format!("tuple of {}", elements.len()),
+        SchemaState::Union(branches) => format!("one of {} types", branches.len()),
+        schema => schema.to_string_pretty(),
+    }
+}
+
+fn collect_rows(
+    schema: &SchemaState,
+    path: &str,
+    label: &str,
+    depth: usize,
+    optional: bool,
+    rows: &mut Vec<Row>,
+) {
+    let (schema, nullable) = match schema {
+        SchemaState::Nullable(inner) => (inner.as_ref(), true),
+        schema => (schema, false),
+    };
+    rows.push(Row {
+        path: path.to_owned(),
+        depth,
+        label: label.to_owned(),
+        optional,
+        nullable,
+        enum_of_strings: matches!(schema, SchemaState::String(StringType::Enum { .. })),
+        description: describe(schema),
+    });
+    match schema {
+        SchemaState::Object { required, optional } => {
+            let fields = required
+                .iter()
+                .map(|field| (field, false))
+                .chain(optional.iter().map(|field| (field, true)));
+            for ((k, v), optional) in fields {
+                collect_rows(v, &path::key(path, k), k, depth + 1, optional, rows);
+            }
+        }
+        SchemaState::Array { schema, .. } => {
+            collect_rows(schema, &path::elements(path), "[]", depth + 1, false, rows)
+        }
+        _ => {}
+    }
+}
+
+/// The rows of a schema, starting from its records if it describes an array at its root, as
+/// the paths of transforms do.
+fn rows(schema: &SchemaState) -> Vec<Row> {
+    let mut rows = vec![];
+    match schema {
+        SchemaState::Array { schema, .. } => {
+            collect_rows(schema, "", "records", 0, false, &mut rows)
+        }
+        schema => collect_rows(schema, "", "<root>", 0, false, &mut rows),
+    }
+    rows
+}
+
+/// What the text being typed at the prompt is for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Prompt {
+    Rename,
+    Range,
+    Type,
+    Save,
+}
+
+impl Prompt {
+    fn question(self) -> &'static str {
+        match self {
+            Prompt::Rename => "rename to",
+            Prompt::Range => "range (min..max)",
+            Prompt::Type => "type (e.g. integer 0..120)",
+            Prompt::Save => "save the JSON Schema to",
+        }
+    }
+}
+
+/// The state of a review, changed by the keys pressed.
+struct Review {
+    schema: SchemaState,
+    rows: Vec<Row>,
+    selected: usize,
+    prompt: Option<(Prompt, String)>,
+    status: String,
+}
+
+impl Review {
+    fn new(schema: SchemaState) -> Self {
+        Self {
+            rows: rows(&schema),
+            schema,
+            selected: 0,
+            prompt: None,
+            status: String::new(),
+        }
+    }
+
+    fn row(&self) -> &Row {
+        &self.rows[self.selected]
+    }
+
+    /// Applies `transform` to the values of the selected row, leaving the schema as it was if
+    /// it cannot be applied.
+    fn transform(&mut self, transform: Transform) {
+        let path = self.row().path.clone();
+        let mut transforms = Transforms::new();
+        transforms.push(&path, transform.clone());
+        let mut schema = self.schema.clone();
+        match transforms.apply(&mut schema) {
+            Ok(()) => {
+                self.schema = schema;
+                self.rows = rows(&self.schema);
+                // the selection follows a field that moved among the optional fields
+                self.selected = self
+                    .rows
+                    .iter()
+                    .position(|row| row.path == path)
+                    .unwrap_or(self.selected.min(self.rows.len() - 1));
+                self.status = format!("{}: {}", path::display(&path), transform);
+            }
+            Err(err) => self.status = err.to_string(),
+        }
+    }
+
+    fn submit(&mut self, prompt: Prompt, input: &str) {
+        let input = input.trim();
+        match prompt {
+            Prompt::Rename => self.transform(Transform::Rename(input.to_owned())),
+            Prompt::Range => match format!("range {}", input).parse() {
+                Ok(transform) => self.transform(transform),
+                Err(err) => self.status = err,
+            },
+            Prompt::Type => match input.parse() {
+                Ok(transform @ Transform::Type { .. }) => self.transform(transform),
+                Ok(_) => self.status = format!("{:?} is not a type", input),
+                Err(err) => self.status = err,
+            },
+            Prompt::Save => {
+                let document = serde_json::to_string_pretty(&to_json_schema(&self.schema))
+                    .expect("schemas serialize to JSON");
+                self.status = match std::fs::write(input, document + "\n") {
+                    Ok(()) => format!("Saved the JSON Schema to {}", input),
+                    Err(err) => format!("Unable to save to {}: {}", input, err),
+                };
+            }
+        }
+    }
+
+    /// Handles a key press, returning what to do with the schema once the review is over.
+    fn handle_key(&mut self, key: KeyCode) -> Option<ReviewOutcome> {
+        if let Some((prompt, mut input)) = self.prompt.take() {
+            match key {
+                KeyCode::Enter => self.submit(prompt, &input),
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    input.pop();
+                    self.prompt = Some((prompt, input));
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    self.prompt = Some((prompt, input));
+                }
+                _ => self.prompt = Some((prompt, input)),
+            }
+            return None;
+        }
+
+        self.status.clear();
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.rows.len() - 1)
+            }
+            KeyCode::Char('o') => {
+                let transform = if self.row().optional {
+                    Transform::Required
+                } else {
+                    Transform::Optional
+                };
+                self.transform(transform);
+            }
+            KeyCode::Char('n') => self.transform(Transform::Nullable(!self.row().nullable)),
+            KeyCode::Char('e') => {
+                let transform = if self.row().enum_of_strings {
+                    Transform::FreeString
+                } else {
+                    Transform::Enum
+                };
+                self.transform(transform);
+            }
+            KeyCode::Char('r') => self.prompt = Some((Prompt::Rename, self.row().label.clone())),
+            KeyCode::Char('g') => self.prompt = Some((Prompt::Range, String::new())),
+            KeyCode::Char('t') => self.prompt = Some((Prompt::Type, String::new())),
+            KeyCode::Char('s') => self.prompt = Some((Prompt::Save, "schema.json".to_owned())),
+            KeyCode::Char('p') => return Some(ReviewOutcome::Produce(self.schema.clone())),
+            KeyCode::Char('q') => return Some(ReviewOutcome::Describe(self.schema.clone())),
+            KeyCode::Esc => return Some(ReviewOutcome::Cancelled),
+            _ => {}
+        }
+        None
+    }
+
+    fn draw(&self, frame: &mut ratatui::Frame) {
+        let [tree, status, help] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let items = self
+            .rows
+            .iter()
+            .map(|row| {
+                ListItem::new(format!(
+                    "{}{}: {}{}{}",
+                    "  ".repeat(row.depth),
+                    row.label,
+                    if row.optional { "optional " } else { "" },
+                    if row.nullable { "nullable " } else { "" },
+                    row.description
+                ))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(" schema "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, tree, &mut state);
+
+        let status_line = match &self.prompt {
+            Some((prompt, input)) => format!("{}: {}▏", prompt.question(), input),
+            None => self.status.clone(),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+        frame.render_widget(
+            Paragraph::new(HELP).style(Style::default().add_modifier(Modifier::DIM)),
+            help,
+        );
+    }
+}
+
+/// Restores the terminal when the review is over, however it ends.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(std::io::stderr(), LeaveAlternateScreen);
+    }
+}
+
+/// Lets the user review `schema` in a terminal UI and correct it: make fields optional or
+/// nullable, rename them, turn strings into enums and back, and set types and ranges, as
+/// [`Transforms`] do. The corrected schema can be saved as a JSON Schema along the way.
+///
+/// The UI is drawn on stderr, and keys are read from the terminal even if stdin is redirected,
+/// so that the schema can be inferred from stdin and described on stdout. Returns what the user
+/// chose to do with the corrected schema.
+pub fn review_schema(schema: SchemaState) -> Result<ReviewOutcome, DrivelError> {
+    enable_raw_mode()?;
+    let _guard = TerminalGuard;
+    execute!(std::io::stderr(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stderr()))?;
+
+    let mut review = Review::new(schema);
+    loop {
+        terminal.draw(|frame| review.draw(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(ReviewOutcome::Cancelled);
+        }
+        if let Some(outcome) = review.handle_key(key.code) {
+            return Ok(outcome);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema, InferenceOptions};
+
+    fn press(review: &mut Review, keys: &str) -> Option<ReviewOutcome> {
+        keys.chars()
+            .map(|c| match c {
+                '\n' => KeyCode::Enter,
+                '\u{8}' => KeyCode::Backspace,
+                c => KeyCode::Char(c),
+            })
+            .fold(None, |outcome, key| outcome.or(review.handle_key(key)))
+    }
+
+    #[test]
+    fn corrects_the_selected_field_with_keys() {
+        let schema = infer_schema(
+            json!([{ "name": "Ann", "status": "open", "age": 31 }]),
+            &InferenceOptions::default(),
+        );
+        let mut review = Review::new(schema);
+        assert_eq!(
+            review
+                .rows
+                .iter()
+                .map(|row| row.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["", "name", "status", "age"]
+        );
+
+        // rename `name`, replacing the label it is prefilled with
+        press(&mut review, "jr\u{8}\u{8}\u{8}\u{8}full_name\n");
+        assert_eq!(review.status, "name: rename full_name");
+        // make `status` an optional enum, and `age` a nullable integer within 0..120
+        press(&mut review, "joe");
+        assert_eq!(review.rows[3].path, "status");
+        assert!(review.rows[3].optional && review.rows[3].enum_of_strings);
+        press(&mut review, "kng0..120\n");
+        assert_eq!(review.rows[2].description, "int (0-120)");
+        // a type that does not exist leaves the schema as it was
+        press(&mut review, "tbool\n");
+        assert!(review.status.starts_with("unknown type \"bool\""));
+
+        let Some(ReviewOutcome::Describe(schema)) = press(&mut review, "q") else {
+            panic!("expected the review to end");
+        };
+        assert_eq!(
+            schema.to_string_pretty(),
+            "[\n  {\n    \"full_name\": string (3),\n    \"age\": nullable int (0-120),\n    \"status\": optional string (enum: open)\n  }\n] (1)"
+        );
+    }
+}
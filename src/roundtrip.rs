@@ -0,0 +1,90 @@
+use crate::{diff_schemas, infer_schema_from_iter, produce::produce_records, Change};
+use crate::{InferenceOptions, SchemaState};
+
+/// The number of records produced to re-infer a schema from. Large enough that optional and
+/// nullable fields are all but certain to be both present and absent, and null and non-null.
+const ROUNDTRIP_RECORDS: usize = 1000;
+
+/// Checks that data produced from a schema can be inferred back into the same schema.
+///
+/// Produces records from `schema`, infers a schema from those records, and returns the
+/// structural differences between the two (see `diff_schemas`). An empty result means that
+/// drivel can fully express the schema. When `schema` describes an array at its root, its
+/// elements are treated as the records.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, verify_roundtrip, InferenceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!([{ "id": "988c2c6d-df1b-4bb9-b837-6ba706c0b4ad", "score": 1.5, "tags": ["a"] }]),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(verify_roundtrip(&schema), vec![]);
+/// ```
+pub fn verify_roundtrip(schema: &SchemaState) -> Vec<Change> {
+    let record_schema = match schema {
+        SchemaState::Array { schema, .. } => schema.as_ref(),
+        other => other,
+    };
+    let records = produce_records(record_schema, ROUNDTRIP_RECORDS);
+    let reinferred = infer_schema_from_iter(records, &InferenceOptions::default());
+    diff_schemas(record_schema, &reinferred)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema, ChangeKind, StringType};
+
+    #[test]
+    fn inferred_schemas_roundtrip() {
+        let input = json!([
+            {
+                "id": "988c2c6d-df1b-4bb9-b837-6ba706c0b4ad",
+                "email": "test@example.com",
+                "date": "2013-01-12",
+                "created": "2013-01-12T00:00:00.000Z",
+                "point": [1.5, "N"],
+                "profile": {"name": "Alice", "age": 30},
+                "parent": null,
+                "tags": ["a", "b"]
+            },
+            {
+                "id": "1d83a00a-5c47-4e7a-a4e4-58cd5d8cd6cd",
+                "email": "other@example.com",
+                "date": "2020-03-04",
+                "created": "2020-03-04T10:00:00.000Z",
+                "point": [-3.25, "S"],
+                "profile": {"name": "Bob"},
+                "parent": {"active": true},
+                "tags": []
+            }
+        ]);
+        let schema = infer_schema(input, &InferenceOptions::default());
+
+        assert_eq!(verify_roundtrip(&schema), vec![]);
+    }
+
+    #[test]
+    fn reports_formats_that_do_not_roundtrip() {
+        // RFC 2822 dates are inferred as ISO 8601 date-times
+        let schema = SchemaState::String(StringType::DateTimeRFC2822 { range: None });
+
+        assert_eq!(
+            verify_roundtrip(&schema),
+            vec![Change {
+                path: "".to_owned(),
+                kind: ChangeKind::TypeChanged {
+                    from: "string (datetime - RFC 2822)".to_owned(),
+                    to: "string (datetime - ISO 8601)".to_owned()
+                }
+            }]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Bounded uniform random sampling (reservoir sampling) of the values seen during inference,
+//! and bounded counts of the characters seen.
+
+use std::collections::HashMap;
+
+use rand::{seq::SliceRandom, thread_rng, Rng};
+
+/// Adds the items from `items` to `sample`, a uniform random sample of `n_seen` items, while
+/// keeping it a uniform sample of at most `max_samples` items.
+///
+/// Returns the total number of items seen.
+pub(crate) fn sample_into<T>(
+    sample: &mut Vec<T>,
+    mut n_seen: usize,
+    items: impl IntoIterator<Item = T>,
+    max_samples: usize,
+) -> usize {
+    let mut rng = thread_rng();
+    for item in items {
+        n_seen += 1;
+        if sample.len() < max_samples {
+            sample.push(item);
+        } else {
+            let idx = rng.gen_range(0..n_seen);
+            if idx < max_samples {
+                sample[idx] = item;
+            }
+        }
+    }
+    n_seen
+}
+
+/// Merges two uniform random samples, of `n_first` and `n_second` items respectively, into a
+/// uniform random sample of at most `max_samples` items of the combined population.
+///
+/// As long as the merged sample fits, the samples are simply concatenated, preserving the
+/// order of their items.
+pub(crate) fn merge_samples<T>(
+    mut first: Vec<T>,
+    n_first: usize,
+    mut second: Vec<T>,
+    n_second: usize,
+    max_samples: usize,
+) -> Vec<T> {
+    if first.len() + second.len() <= max_samples {
+        first.extend(second);
+        return first;
+    }
+
+    // a sample that holds its entire population can be streamed into the other sample
+    if second.len() == n_second {
+        sample_into(&mut first, n_first, second, max_samples);
+        return first;
+    }
+    if first.len() == n_first {
+        sample_into(&mut second, n_second, first, max_samples);
+        return second;
+    }
+
+    // otherwise draw each item of the merged sample from either sample, weighted by the size
+    // of the population it represents
+    let mut rng = thread_rng();
+    let p_first = n_first as f64 / (n_first + n_second) as f64;
+    let from_first = (0..max_samples).filter(|_| rng.gen_bool(p_first)).count();
+    let from_first = from_first
+        .max(max_samples.saturating_sub(second.len()))
+        .min(first.len());
+    let from_second = max_samples - from_first;
+
+    // partial_shuffle moves the randomly chosen items to the end of the slice
+    first.partial_shuffle(&mut rng, from_first);
+    second.partial_shuffle(&mut rng, from_second);
+    let mut merged = first.split_off(first.len() - from_first);
+    merged.extend(second.split_off(second.len() - from_second));
+    merged
+}
+
+/// Adds the counts of characters in `chars` to `counts`, which counts at most `max_distinct`
+/// different characters: characters that are new once it is full are left out.
+///
+/// Returns the number of characters added, including those left out.
+pub(crate) fn count_into(
+    counts: &mut HashMap<char, usize>,
+    chars: impl IntoIterator<Item = (char, usize)>,
+    max_distinct: usize,
+) -> usize {
+    let mut n_added = 0;
+    for (c, count) in chars {
+        n_added += count;
+        if let Some(counted) = counts.get_mut(&c) {
+            *counted += count;
+        } else if counts.len() < max_distinct {
+            counts.insert(c, count);
+        }
+    }
+    n_added
+}
+
+/// Merges two counts of characters of at most `max_distinct` different characters each,
+/// adding the smaller to the larger.
+pub(crate) fn merge_counts(
+    mut first: HashMap<char, usize>,
+    mut second: HashMap<char, usize>,
+    max_distinct: usize,
+) -> HashMap<char, usize> {
+    if first.len() < second.len() {
+        std::mem::swap(&mut first, &mut second);
+    }
+    count_into(&mut first, second, max_distinct);
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_is_bounded() {
+        let mut sample = vec![];
+        let n_seen = sample_into(&mut sample, 0, 0..1000, 10);
+        assert_eq!(n_seen, 1000);
+        assert_eq!(sample.len(), 10);
+    }
+
+    #[test]
+    fn merges_small_samples_by_concatenation() {
+        let merged = merge_samples(vec![1, 2], 2, vec![3], 1, 10);
+        assert_eq!(merged, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn merged_sample_is_weighted_by_population() {
+        let mut from_large = 0;
+        for _ in 0..100 {
+            let large = vec![0; 100];
+            let small = vec![1; 100];
+            let merged = merge_samples(large, 1_000_000, small, 1_000, 100);
+            assert_eq!(merged.len(), 100);
+            from_large += merged.iter().filter(|i| **i == 0).count();
+        }
+        // ~99.9% of the items should come from the larger population
+        assert!(from_large > 9_900);
+    }
+
+    #[test]
+    fn counts_are_bounded_by_the_number_of_distinct_characters() {
+        let mut counts = HashMap::new();
+        let n_added = count_into(&mut counts, "abcabcdef".chars().map(|c| (c, 1)), 3);
+        assert_eq!(n_added, 9);
+        assert_eq!(counts, HashMap::from([('a', 2), ('b', 2), ('c', 2)]));
+
+        let merged = merge_counts(counts, HashMap::from([('c', 5), ('z', 1)]), 3);
+        assert_eq!(merged, HashMap::from([('a', 2), ('b', 2), ('c', 7)]));
+    }
+}
@@ -1,33 +1,449 @@
 use std::fmt::Display;
+use std::ops::RangeInclusive;
 
-#[derive(PartialEq, Debug)]
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+use crate::DistinctCount;
+
+/// The class of characters that a [`Segment`] of a string consists of.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CharClass {
+    /// ASCII digits.
+    Digit,
+    /// Uppercase ASCII letters.
+    Uppercase,
+    /// Lowercase ASCII letters.
+    Lowercase,
+    /// ASCII letters of either case.
+    Letter,
+    /// A single character of any other kind, like a separator.
+    Other,
+}
+
+/// A run of characters of the same class, at the same position in every string seen.
+#[derive(PartialEq, Clone, Debug)]
+pub struct Segment {
+    pub class: CharClass,
+    /// The text of the run, if it is the same in every string seen.
+    pub literal: Option<String>,
+    pub min_length: usize,
+    pub max_length: usize,
+}
+
+impl Segment {
+    fn to_regex(&self) -> String {
+        if let Some(literal) = &self.literal {
+            // only the characters that are special outside of a character class are escaped,
+            // to keep the pattern readable
+            return literal
+                .chars()
+                .flat_map(|c| {
+                    let escape = r"\.+*?()|[]{}^$".contains(c);
+                    escape.then_some('\\').into_iter().chain([c])
+                })
+                .collect();
+        }
+        let class = match self.class {
+            CharClass::Digit => "[0-9]",
+            CharClass::Uppercase => "[A-Z]",
+            CharClass::Lowercase => "[a-z]",
+            CharClass::Letter => "[A-Za-z]",
+            CharClass::Other => unreachable!("other characters are always literal"),
+        };
+        match (self.min_length, self.max_length) {
+            (1, 1) => class.to_owned(),
+            (min, max) if min == max => format!("{}{{{}}}", class, min),
+            (min, max) => format!("{}{{{},{}}}", class, min, max),
+        }
+    }
+}
+
+/// A way of writing dates other than ISO 8601, e.g. `03/14/2024`.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DateFormat {
+    /// `MM/DD/YYYY`, e.g. `03/14/2024`.
+    MonthDayYear,
+    /// `DD-MM-YYYY`, e.g. `14-03-2024`.
+    DayMonthYear,
+    /// `YYYYMMDD`, e.g. `20240314`.
+    Compact,
+}
+
+impl DateFormat {
+    /// Returns the name of the detector of dates in this format, e.g. `date-mdy`.
+    pub fn name(self) -> &'static str {
+        match self {
+            DateFormat::MonthDayYear => "date-mdy",
+            DateFormat::DayMonthYear => "date-dmy",
+            DateFormat::Compact => "date-compact",
+        }
+    }
+
+    /// Returns the `chrono` format string with which dates are parsed and written.
+    pub fn chrono_format(self) -> &'static str {
+        match self {
+            DateFormat::MonthDayYear => "%m/%d/%Y",
+            DateFormat::DayMonthYear => "%d-%m-%Y",
+            DateFormat::Compact => "%Y%m%d",
+        }
+    }
+
+    /// Returns a regular expression matching dates written in this format.
+    pub fn pattern(self) -> &'static str {
+        match self {
+            DateFormat::MonthDayYear => "^[0-9]{2}/[0-9]{2}/[0-9]{4}$",
+            DateFormat::DayMonthYear => "^[0-9]{2}-[0-9]{2}-[0-9]{4}$",
+            DateFormat::Compact => "^[0-9]{8}$",
+        }
+    }
+
+    /// Writes `date` in this format.
+    pub fn format(self, date: NaiveDate) -> String {
+        date.format(self.chrono_format()).to_string()
+    }
+}
+
+impl Display for DateFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            DateFormat::MonthDayYear => "MM/DD/YYYY",
+            DateFormat::DayMonthYear => "DD-MM-YYYY",
+            DateFormat::Compact => "YYYYMMDD",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// The kind of a file path, or of a URI of an object in cloud storage.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PathKind {
+    /// A POSIX path, e.g. `/var/log/app.log` or `./data/users.csv`.
+    Posix,
+    /// A Windows path, e.g. `C:\Users\ann\report.pdf`.
+    Windows,
+    /// An Amazon S3 URI, e.g. `s3://logs/2024/app.log`.
+    S3,
+    /// A Google Cloud Storage URI, e.g. `gs://logs/2024/app.log`.
+    Gcs,
+}
+
+impl PathKind {
+    /// Returns the kind of the paths that start with `root`, e.g. `C:\` or `s3://logs/`.
+    pub fn of_root(root: &str) -> Self {
+        if root.starts_with("s3://") {
+            PathKind::S3
+        } else if root.starts_with("gs://") {
+            PathKind::Gcs
+        } else if root.ends_with(":\\") {
+            PathKind::Windows
+        } else {
+            PathKind::Posix
+        }
+    }
+
+    /// Returns the character between the directories and the file name of a path.
+    pub fn separator(self) -> char {
+        match self {
+            PathKind::Windows => '\\',
+            _ => '/',
+        }
+    }
+
+    /// Returns a regular expression matching the start of paths of this kind.
+    pub fn pattern(self) -> &'static str {
+        match self {
+            PathKind::Posix => r"^(?:/|\./|\.\./|~/)",
+            PathKind::Windows => r"^[A-Za-z]:\\",
+            PathKind::S3 => "^s3://",
+            PathKind::Gcs => "^gs://",
+        }
+    }
+}
+
+impl Display for PathKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            PathKind::Posix => "POSIX",
+            PathKind::Windows => "Windows",
+            PathKind::S3 => "S3",
+            PathKind::Gcs => "GCS",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+fn sorted_keys(counts: &std::collections::HashMap<String, usize>) -> Vec<&str> {
+    let mut keys = counts.keys().map(String::as_str).collect::<Vec<_>>();
+    keys.sort();
+    keys
+}
+
+/// Writes a time of day as `HH:MM`, or `HH:MM:SS` with `seconds`.
+pub(crate) fn format_time(time: NaiveTime, seconds: bool) -> String {
+    let format = if seconds { "%H:%M:%S" } else { "%H:%M" };
+    time.format(format).to_string()
+}
+
+/// Writes a duration of `seconds` in ISO 8601, e.g. `P1DT2H30M`, or `PT0S` if it is zero.
+pub(crate) fn format_duration(seconds: u64) -> String {
+    if seconds == 0 {
+        return "PT0S".to_owned();
+    }
+    let (days, rest) = (seconds / 86_400, seconds % 86_400);
+    let parts = [
+        (rest / 3600, 'H'),
+        (rest % 3600 / 60, 'M'),
+        (rest % 60, 'S'),
+    ];
+    let mut duration = "P".to_owned();
+    if days > 0 {
+        duration.push_str(&format!("{}D", days));
+    }
+    if rest > 0 {
+        duration.push('T');
+        for (amount, unit) in parts {
+            if amount > 0 {
+                duration.push_str(&format!("{}{}", amount, unit));
+            }
+        }
+    }
+    duration
+}
+
+#[derive(PartialEq, Clone, Debug)]
 pub enum StringType {
     Unknown {
+        /// A uniform random sample of the strings seen, bounded by `InferenceOptions::max_samples`.
         strings_seen: Vec<String>,
-        chars_seen: Vec<char>,
+        /// How often each character was seen, counting up to `InferenceOptions::max_samples`
+        /// different characters.
+        chars_seen: std::collections::HashMap<char, usize>,
+        /// The total number of strings seen, of which `strings_seen` is a sample.
+        n_strings_seen: usize,
+        /// The number of distinct strings seen, estimated in constant memory.
+        distinct: DistinctCount,
+        /// The total number of characters seen, of which `chars_seen` is a sample.
+        n_chars_seen: usize,
         min_length: Option<usize>,
         max_length: Option<usize>,
+        /// The longest prefix shared by all strings seen.
+        prefix: String,
+        /// The longest suffix shared by all strings seen.
+        suffix: String,
+        /// The runs of digits, letters and other characters shared by all strings seen, or
+        /// `None` if the strings do not line up.
+        structure: Option<Vec<Segment>>,
+    },
+    IsoDate {
+        /// The earliest and latest dates seen, if known.
+        range: Option<RangeInclusive<NaiveDate>>,
+    },
+    DateTimeRFC2822 {
+        /// The earliest and latest moments seen, if known.
+        range: Option<RangeInclusive<DateTime<Utc>>>,
+    },
+    DateTimeISO8601 {
+        /// The earliest and latest moments seen, if known.
+        range: Option<RangeInclusive<DateTime<Utc>>>,
+    },
+    /// A date written in a format other than ISO 8601, e.g. `03/14/2024`, which values are
+    /// produced in as well.
+    FormattedDate {
+        format: DateFormat,
+        /// The earliest and latest dates seen, if known.
+        range: Option<RangeInclusive<NaiveDate>>,
+    },
+    /// A moment written as the number of seconds since the Unix epoch, e.g. `"1710428400"`.
+    EpochSeconds {
+        /// The earliest and latest moments seen, if known.
+        range: Option<RangeInclusive<DateTime<Utc>>>,
+    },
+    /// A time of day, e.g. `09:30` or `17:45:00`.
+    Time {
+        /// The earliest and latest times seen, if known.
+        range: Option<RangeInclusive<NaiveTime>>,
+        /// Whether times are written with seconds.
+        seconds: bool,
+    },
+    /// A file path, e.g. `/var/log/app.log`, or a URI of an object in cloud storage, e.g.
+    /// `s3://logs/2024/app.log`.
+    FilePath {
+        kind: PathKind,
+        /// What the paths seen start with, e.g. `/`, `C:\` or `s3://logs/`, and how often each
+        /// was seen.
+        roots: std::collections::HashMap<String, usize>,
+        /// The fewest and most directories seen between the root and the file name.
+        min_depth: usize,
+        max_depth: usize,
+        /// The extensions of the file names seen, e.g. `csv`, or an empty string for names
+        /// without one, and how often each was seen.
+        extensions: std::collections::HashMap<String, usize>,
+    },
+    /// Natural language, e.g. `Arrived on time, well packaged.`, of a number of words.
+    Text {
+        min_words: usize,
+        max_words: usize,
+    },
+    /// An ISO 8601 duration, e.g. `PT1H30M`.
+    Duration {
+        /// The shortest and longest durations seen, in whole seconds, if known. Months count as
+        /// 30 days and years as 365.
+        range: Option<RangeInclusive<u64>>,
     },
-    IsoDate,
-    DateTimeRFC2822,
-    DateTimeISO8601,
     UUID,
+    /// A ULID, e.g. `01ARZ3NDEKTSV4RRFFQ69G5FAV`.
+    Ulid,
+    /// A KSUID, e.g. `0ujtsYcgvSTl8PAuAdqWYSMnLOv`.
+    Ksuid,
+    /// A MongoDB ObjectId, e.g. `507f1f77bcf86cd799439011`.
+    ObjectId,
+    /// An ISO 4217 currency code, e.g. `EUR`.
+    CurrencyCode {
+        /// The codes seen.
+        codes: std::collections::HashSet<String>,
+    },
+    /// A phone number, in one of the formats seen.
+    PhoneNumber {
+        /// The formats of the numbers seen, with each digit replaced by `#`, e.g.
+        /// `(###) ###-####`, and how often each was seen.
+        formats: std::collections::HashMap<String, usize>,
+    },
+    /// A semantic version, e.g. `2.0.0-rc.1+build5`.
+    Semver {
+        /// The lowest and highest major versions seen.
+        min_major: u64,
+        max_major: u64,
+    },
+    /// Binary data encoded as padded base64, e.g. `aGVsbG8sIHdvcmxkIQ==`.
+    Base64 {
+        min_length: usize,
+        max_length: usize,
+    },
+    /// A JSON document embedded in a string, e.g. `"{\"a\":1}"`, whose schema is inferred
+    /// like any other JSON. See `InferenceOptions::embedded_json`.
+    EmbeddedJson(Box<SchemaState>),
+    /// A JSON Web Token, whose claims are described by a schema of their own.
+    Jwt {
+        claims: Box<SchemaState>,
+    },
     Email,
     Url,
     Hostname,
     Enum {
         variants: std::collections::HashSet<String>,
     },
+    /// A string matching a regular expression, e.g. from a JSON Schema `pattern`.
+    Pattern(String),
+    /// One of the strings `"NaN"`, `"Infinity"` and `"-Infinity"`, with which some producers
+    /// encode floats that JSON cannot represent. Merged with numbers, these become floats.
+    NonFiniteFloat,
+    /// Strings counted while inferring with `InferenceOptions::format_tolerance`: `matches` of
+    /// them were detected as `format`, and `outliers` were not, described by `unmatched`. Once
+    /// all strings were seen, these become `format` if few enough of them were outliers, and
+    /// strings of unknown format otherwise.
+    Tally {
+        format: Box<StringType>,
+        matches: usize,
+        outliers: usize,
+        unmatched: Option<Box<StringType>>,
+    },
+}
+
+impl StringType {
+    /// Returns the format in which most of the phone numbers seen were written, e.g.
+    /// `+## ## #### ####`.
+    pub fn phone_format(&self) -> Option<&str> {
+        match self {
+            StringType::PhoneNumber { formats } => formats
+                .iter()
+                .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+                .map(|(format, _)| format.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Returns the prefix and suffix that all strings seen share, if the strings follow such a
+    /// convention (e.g. identifiers like `cus_1234`): at least two strings must have been seen,
+    /// with a variable part remaining between the prefix and the suffix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema_from_iter, InferenceOptions, SchemaState};
+    /// use serde_json::json;
+    ///
+    /// let schema = infer_schema_from_iter(
+    ///     vec![json!("cus_81ab_v2"), json!("cus_9f_v2"), json!("cus_c0de_v2")],
+    ///     &InferenceOptions::default(),
+    /// );
+    ///
+    /// let SchemaState::String(string_type) = schema else { panic!() };
+    /// assert_eq!(string_type.affixes(), Some(("cus_", "_v2")));
+    /// ```
+    pub fn affixes(&self) -> Option<(&str, &str)> {
+        match self {
+            StringType::Unknown {
+                n_strings_seen,
+                min_length: Some(min_length),
+                prefix,
+                suffix,
+                ..
+            } if *n_strings_seen > 1
+                && !(prefix.is_empty() && suffix.is_empty())
+                && prefix.len() + suffix.len() < *min_length =>
+            {
+                Some((prefix, suffix))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a regular expression describing the structure shared by all strings seen, if
+    /// they follow one (e.g. order numbers like `ORD-2024-00123`): at least two strings must
+    /// have been seen, made up of the same runs of digits, letters and separators, of which
+    /// some are fixed and some vary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema_from_iter, InferenceOptions, SchemaState};
+    /// use serde_json::json;
+    ///
+    /// let schema = infer_schema_from_iter(
+    ///     vec![json!("ORD-2024-00123"), json!("ORD-2023-04711"), json!("ORD-2024-10001")],
+    ///     &InferenceOptions::default(),
+    /// );
+    ///
+    /// let SchemaState::String(string_type) = schema else { panic!() };
+    /// assert_eq!(
+    ///     string_type.structure_pattern().as_deref(),
+    ///     Some("ORD-[0-9]{4}-[0-9]{5}")
+    /// );
+    /// ```
+    pub fn structure_pattern(&self) -> Option<String> {
+        match self {
+            StringType::Unknown {
+                n_strings_seen,
+                structure: Some(segments),
+                ..
+            } if *n_strings_seen > 1
+                && segments.iter().any(|segment| segment.literal.is_some())
+                && segments.iter().any(|segment| segment.literal.is_none()) =>
+            {
+                Some(segments.iter().map(Segment::to_regex).collect())
+            }
+            _ => None,
+        }
+    }
 }
 
 impl Display for StringType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let text = match self {
             StringType::Unknown {
-                strings_seen: _,
-                chars_seen: _,
                 min_length,
                 max_length,
+                ..
             } => {
                 let length = match (min_length, max_length) {
                     (Some(min), Some(max)) => {
@@ -41,46 +457,312 @@ impl Display for StringType {
                     (None, Some(max)) => format!("(?-{})", max),
                     (None, None) => "(length unknown)".to_string(),
                 };
-                format!("string {}", length)
+                if let Some(pattern) = self.structure_pattern() {
+                    return write!(f, "string {} pattern /{}/", length, pattern);
+                }
+                match self.affixes() {
+                    Some((prefix, "")) => format!("string {} prefix {:?}", length, prefix),
+                    Some(("", suffix)) => format!("string {} suffix {:?}", length, suffix),
+                    Some((prefix, suffix)) => {
+                        format!("string {} prefix {:?} suffix {:?}", length, prefix, suffix)
+                    }
+                    None => format!("string {}", length),
+                }
+            }
+            StringType::IsoDate { range: Some(range) } => format!(
+                "string (date - ISO 8601, {} to {})",
+                range.start(),
+                range.end()
+            ),
+            StringType::DateTimeRFC2822 { range: Some(range) } => format!(
+                "string (datetime - RFC 2822, {} to {})",
+                range.start().to_rfc2822(),
+                range.end().to_rfc2822()
+            ),
+            StringType::DateTimeISO8601 { range: Some(range) } => format!(
+                "string (datetime - ISO 8601, {} to {})",
+                range.start().to_rfc3339(),
+                range.end().to_rfc3339()
+            ),
+            StringType::IsoDate { range: None } => "string (date - ISO 8601)".to_owned(),
+            StringType::DateTimeRFC2822 { range: None } => {
+                "string (datetime - RFC 2822)".to_owned()
+            }
+            StringType::DateTimeISO8601 { range: None } => {
+                "string (datetime - ISO 8601)".to_owned()
+            }
+            StringType::FormattedDate {
+                format,
+                range: Some(range),
+            } => format!(
+                "string (date - {}, {} to {})",
+                format,
+                format.format(*range.start()),
+                format.format(*range.end())
+            ),
+            StringType::FormattedDate {
+                format,
+                range: None,
+            } => format!("string (date - {})", format),
+            StringType::EpochSeconds { range: Some(range) } => format!(
+                "string (epoch seconds, {} to {})",
+                range.start().to_rfc3339(),
+                range.end().to_rfc3339()
+            ),
+            StringType::EpochSeconds { range: None } => "string (epoch seconds)".to_owned(),
+            StringType::Time {
+                range: Some(range),
+                seconds,
+            } => format!(
+                "string (time, {} to {})",
+                format_time(*range.start(), *seconds),
+                format_time(*range.end(), *seconds)
+            ),
+            StringType::Time { range: None, .. } => "string (time)".to_owned(),
+            StringType::Duration { range: Some(range) } => format!(
+                "string (duration, {} to {})",
+                format_duration(*range.start()),
+                format_duration(*range.end())
+            ),
+            StringType::Duration { range: None } => "string (duration)".to_owned(),
+            StringType::Text {
+                min_words,
+                max_words,
+            } => {
+                if min_words == max_words {
+                    format!("string (text, {} words)", min_words)
+                } else {
+                    format!("string (text, {}-{} words)", min_words, max_words)
+                }
+            }
+            StringType::FilePath {
+                kind,
+                roots,
+                min_depth,
+                max_depth,
+                extensions,
+            } => {
+                let depth = if min_depth == max_depth {
+                    min_depth.to_string()
+                } else {
+                    format!("{}-{}", min_depth, max_depth)
+                };
+                let extensions = sorted_keys(extensions)
+                    .into_iter()
+                    .map(|extension| match extension {
+                        "" => "none",
+                        extension => extension,
+                    })
+                    .collect::<Vec<_>>();
+                format!(
+                    "string ({} path under {}, {} directories, extensions: {})",
+                    kind,
+                    sorted_keys(roots).join(" "),
+                    depth,
+                    extensions.join(", ")
+                )
             }
-            StringType::IsoDate => "string (date - ISO 8601)".to_owned(),
-            StringType::DateTimeRFC2822 => "string (datetime - RFC 2822)".to_owned(),
-            StringType::DateTimeISO8601 => "string (datetime - ISO 8601)".to_owned(),
             StringType::UUID => "string (uuid)".to_owned(),
+            StringType::Ulid => "string (ulid)".to_owned(),
+            StringType::Ksuid => "string (ksuid)".to_owned(),
+            StringType::ObjectId => "string (ObjectId)".to_owned(),
+            StringType::CurrencyCode { codes } => {
+                let mut codes = codes.iter().map(String::as_str).collect::<Vec<_>>();
+                codes.sort();
+                format!("string (currency: {})", codes.join(", "))
+            }
+            StringType::PhoneNumber { .. } => format!(
+                "string (phone: {})",
+                self.phone_format().unwrap_or_default()
+            ),
+            StringType::Semver {
+                min_major,
+                max_major,
+            } => {
+                if min_major == max_major {
+                    format!("string (semver, major {})", min_major)
+                } else {
+                    format!("string (semver, major {}-{})", min_major, max_major)
+                }
+            }
+            StringType::Base64 {
+                min_length,
+                max_length,
+            } => {
+                if min_length == max_length {
+                    format!("string (base64, {})", min_length)
+                } else {
+                    format!("string (base64, {}-{})", min_length, max_length)
+                }
+            }
+            StringType::EmbeddedJson(_) => "string (json)".to_owned(),
+            StringType::Jwt { claims } => match claims.as_ref() {
+                SchemaState::Object { required, optional } => {
+                    let mut names = required.keys().chain(optional.keys()).collect::<Vec<_>>();
+                    names.sort();
+                    let names = names
+                        .into_iter()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!("string (jwt: {})", names)
+                }
+                _ => "string (jwt)".to_owned(),
+            },
             StringType::Email => "string (email)".to_owned(),
             StringType::Hostname => "string (hostname)".to_owned(),
             StringType::Url => "string (url)".to_owned(),
             StringType::Enum { variants } => {
-                let variants_vec = variants.iter().cloned().collect::<Vec<_>>();
-                let formatted = variants_vec.join(", ");
-                format!("string (enum: {})", formatted)
+                let mut variants = variants.iter().map(String::as_str).collect::<Vec<_>>();
+                variants.sort();
+                format!("string (enum: {})", variants.join(", "))
             }
+            StringType::Pattern(pattern) => format!("string (pattern: {})", pattern),
+            StringType::NonFiniteFloat => "string (NaN/Infinity)".to_owned(),
+            StringType::Tally { format, .. } => format.to_string(),
         };
         write!(f, "{}", text)
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub enum NumberType {
-    Integer { min: i64, max: i64 },
-    Float { min: f64, max: f64 },
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    Float {
+        min: f64,
+        max: f64,
+        /// Whether some values were NaN or infinite, which JSON can only encode as the strings
+        /// `"NaN"`, `"Infinity"` and `"-Infinity"`.
+        non_finite: bool,
+        /// Whether `min` itself is left out of the range, like the `exclusiveMinimum` of a JSON
+        /// Schema.
+        exclusive_min: bool,
+        /// Whether `max` itself is left out of the range, like the `exclusiveMaximum` of a JSON
+        /// Schema.
+        exclusive_max: bool,
+    },
+    /// Numbers with a fixed number of decimal places, like monetary amounts.
+    Decimal {
+        /// The number of decimal places.
+        scale: u32,
+        min: f64,
+        max: f64,
+    },
+    /// Integers that take one of a few values, like HTTP status codes or 0/1 flags.
+    Enum {
+        variants: std::collections::HashSet<i64>,
+        /// The number of integers seen, of which `variants` are the distinct values.
+        n_seen: usize,
+    },
+    /// Integers of which some are beyond the range of `i64`, like unsigned 64-bit IDs.
+    BigInteger {
+        min: i128,
+        max: i128,
+    },
+}
+
+impl NumberType {
+    /// Returns the integers from `min` to `max`, which are a `BigInteger` only when they do not
+    /// fit in an `i64`.
+    pub fn integers(min: i128, max: i128) -> Self {
+        match (i64::try_from(min), i64::try_from(max)) {
+            (Ok(min), Ok(max)) => NumberType::Integer { min, max },
+            _ => NumberType::BigInteger { min, max },
+        }
+    }
+
+    /// Returns the lowest and highest float that values of floats may take: the bounds
+    /// themselves, or the floats just within them when they are exclusive.
+    pub fn float_range(&self) -> Option<(f64, f64)> {
+        match *self {
+            NumberType::Float {
+                min,
+                max,
+                exclusive_min,
+                exclusive_max,
+                ..
+            } => Some((
+                if exclusive_min { min.next_up() } else { min },
+                if exclusive_max { max.next_down() } else { max },
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns the lowest and highest value of integers, including those of an enum.
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        match self {
+            NumberType::Integer { min, max } => Some(((*min).into(), (*max).into())),
+            NumberType::BigInteger { min, max } => Some((*min, *max)),
+            NumberType::Enum { variants, .. } => Some((
+                variants.iter().copied().min().unwrap_or_default().into(),
+                variants.iter().copied().max().unwrap_or_default().into(),
+            )),
+            NumberType::Float { .. } | NumberType::Decimal { .. } => None,
+        }
+    }
 }
 
 impl Display for NumberType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let text = match self {
-            NumberType::Integer { min, max } => {
+            NumberType::Integer { .. } | NumberType::BigInteger { .. } => {
+                let (min, max) = self.integer_range().unwrap_or_default();
                 if min != max {
                     format!("int ({}-{})", min, max)
                 } else {
                     format!("int ({})", min)
                 }
             }
-            NumberType::Float { min, max } => {
-                if min != max {
+            NumberType::Float {
+                min,
+                max,
+                non_finite,
+                exclusive_min,
+                exclusive_max,
+            } => {
+                let excluded = [(*exclusive_min, min), (*exclusive_max, max)]
+                    .into_iter()
+                    .filter(|(exclusive, _)| *exclusive)
+                    .map(|(_, bound)| bound.to_string())
+                    .collect::<Vec<_>>();
+                let range = if !excluded.is_empty() {
+                    format!(
+                        "float ({}-{}, excluding {})",
+                        min,
+                        max,
+                        excluded.join(" and ")
+                    )
+                } else if min != max {
                     format!("float ({}-{})", min, max)
                 } else {
                     format!("float ({})", min)
+                };
+                if *non_finite {
+                    format!("{} or NaN/Infinity", range)
+                } else {
+                    range
+                }
+            }
+            NumberType::Enum { variants, .. } => {
+                let mut variants = variants.iter().collect::<Vec<_>>();
+                variants.sort();
+                let variants = variants
+                    .into_iter()
+                    .map(i64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("int (enum: {})", variants)
+            }
+            NumberType::Decimal { scale, min, max } => {
+                let scale = *scale as usize;
+                if min != max {
+                    format!("decimal ({:.*}-{:.*})", scale, min, scale, max)
+                } else {
+                    format!("decimal ({:.*})", scale, min)
                 }
             }
         };
@@ -95,7 +777,7 @@ impl Display for NumberType {
 ///   schema inference process that have no equivalents in the JSON specification.
 /// - The String and Number types have an inner type that specialises the more generic types. This is to
 ///   add some further semantics to the data type, provided `drivel` is able to infer these semantics.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub enum SchemaState {
     /// Initial state.
     Initial,
@@ -115,16 +797,44 @@ pub enum SchemaState {
         min_length: usize,
         /// Maximum length of the array.
         max_length: usize,
+        /// Whether the elements of the array are all distinct, like those of a JSON Schema with
+        /// `uniqueItems`.
+        unique: bool,
         /// Schema for the elements of the array.
         schema: Box<SchemaState>,
     },
+    /// Represents a fixed-length array with a schema for each position, e.g. `[lat, long]`.
+    Tuple(Vec<SchemaState>),
+    /// Represents a value that matches any one of several schemas, e.g. a JSON Schema `anyOf`.
+    Union(Vec<SchemaState>),
     /// Represents an object with required and optional fields and their corresponding schemas.
+    /// Fields are kept in the order they were first seen, and described and produced in that
+    /// order: the required fields first, then the optional ones.
     Object {
         /// Required fields and their schemas.
-        required: std::collections::HashMap<String, SchemaState>,
+        required: indexmap::IndexMap<String, SchemaState>,
         /// Optional fields and their schemas.
-        optional: std::collections::HashMap<String, SchemaState>,
+        optional: indexmap::IndexMap<String, SchemaState>,
+    },
+    /// Represents an object whose keys are not known up front but match a pattern, with a
+    /// schema for their values, like a map from IDs to records, e.g. from a JSON Schema
+    /// `patternProperties`.
+    Map {
+        /// The regular expression that the keys match.
+        pattern: String,
+        /// Minimum number of keys.
+        min_length: usize,
+        /// Maximum number of keys.
+        max_length: usize,
+        /// Schema for the values.
+        schema: Box<SchemaState>,
     },
+    /// Represents an array that was only ever seen empty, and whose elements are unconstrained.
+    UnconstrainedArray,
+    /// Represents an object that was only ever seen empty, and whose fields are unconstrained.
+    UnconstrainedObject,
+    /// Represents a single fixed value, e.g. from a JSON Schema `const`.
+    Const(serde_json::Value),
     /// Represents an indefinite state.
     Indefinite,
 }
@@ -136,10 +846,40 @@ fn to_string_pretty_inner(schema_state: &SchemaState, depth: usize) -> String {
         SchemaState::Nullable(state) => {
             format!("nullable {}", to_string_pretty_inner(state, depth))
         }
+        SchemaState::String(StringType::EmbeddedJson(inner)) => {
+            format!("string (json) {}", to_string_pretty_inner(inner, depth))
+        }
         SchemaState::String(string_type) => format!("{}", string_type),
         SchemaState::Number(number_type) => format!("{}", number_type),
         SchemaState::Boolean => "boolean".to_string(),
+        SchemaState::UnconstrainedArray => "[ unconstrained ]".to_string(),
+        SchemaState::UnconstrainedObject => "{ unconstrained }".to_string(),
+        SchemaState::Const(value) => format!("const {}", value),
         SchemaState::Array {
+            min_length,
+            max_length,
+            unique,
+            schema,
+        } => {
+            let indent = 2 + 2 * depth;
+            let indent_str = " ".repeat(indent);
+            let indent_str_close = " ".repeat(indent - 2);
+            let length = match (min_length != max_length, *unique) {
+                (true, false) => format!("({}-{})", min_length, max_length),
+                (true, true) => format!("({}-{}, unique)", min_length, max_length),
+                (false, false) => format!("({})", min_length),
+                (false, true) => format!("({}, unique)", min_length),
+            };
+            format!(
+                "[\n{}{}\n{}] {}",
+                indent_str,
+                to_string_pretty_inner(schema, depth + 1),
+                indent_str_close,
+                length
+            )
+        }
+        SchemaState::Map {
+            pattern,
             min_length,
             max_length,
             schema,
@@ -153,13 +893,48 @@ fn to_string_pretty_inner(schema_state: &SchemaState, depth: usize) -> String {
                 format!("({})", min_length)
             };
             format!(
-                "[\n{}{}\n{}] {}",
+                "{{\n{}/{}/: {}\n{}}} {}",
                 indent_str,
+                pattern,
                 to_string_pretty_inner(schema, depth + 1),
                 indent_str_close,
                 length
             )
         }
+        SchemaState::Tuple(elements) => {
+            let indent = 2 + 2 * depth;
+            let indent_str = " ".repeat(indent);
+            let indent_str_close = " ".repeat(indent - 2);
+            let elements = elements
+                .iter()
+                .map(|element| {
+                    format!(
+                        "{}{}",
+                        indent_str,
+                        to_string_pretty_inner(element, depth + 1)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",\n");
+            format!("tuple [\n{}\n{}]", elements, indent_str_close)
+        }
+        SchemaState::Union(branches) => {
+            let indent = 2 + 2 * depth;
+            let indent_str = " ".repeat(indent);
+            let indent_str_close = " ".repeat(indent - 2);
+            let branches = branches
+                .iter()
+                .map(|branch| {
+                    format!(
+                        "{}{}",
+                        indent_str,
+                        to_string_pretty_inner(branch, depth + 1)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",\n");
+            format!("one of [\n{}\n{}]", branches, indent_str_close)
+        }
         SchemaState::Object { required, optional } => {
             let indent = 2 + 2 * depth;
             let indent_str = " ".repeat(indent);
@@ -196,7 +971,358 @@ fn to_string_pretty_inner(schema_state: &SchemaState, depth: usize) -> String {
     }
 }
 
+/// Summary statistics about a schema, shown in the header of the pretty output.
+#[derive(PartialEq, Debug, Default)]
+pub struct SchemaSummary {
+    /// The total number of object fields, at any depth.
+    pub total_fields: usize,
+    /// The maximum nesting depth of objects and arrays; a scalar at the root has depth 0.
+    pub max_depth: usize,
+    /// The number of string values for which a specialised format (e.g. a date or UUID) was detected.
+    pub detected_formats: usize,
+}
+
+fn summarise(schema_state: &SchemaState, depth: usize, summary: &mut SchemaSummary) {
+    summary.max_depth = summary.max_depth.max(depth);
+    match schema_state {
+        SchemaState::Nullable(inner) => summarise(inner, depth, summary),
+        SchemaState::String(StringType::Unknown { .. }) => {}
+        SchemaState::String(_) => summary.detected_formats += 1,
+        SchemaState::Array { schema, .. } | SchemaState::Map { schema, .. } => {
+            summarise(schema, depth + 1, summary)
+        }
+        SchemaState::Tuple(elements) => {
+            for element in elements {
+                summarise(element, depth + 1, summary);
+            }
+        }
+        SchemaState::Union(branches) => {
+            for branch in branches {
+                summarise(branch, depth, summary);
+            }
+        }
+        SchemaState::Object { required, optional } => {
+            summary.max_depth = summary.max_depth.max(depth + 1);
+            for v in required.values().chain(optional.values()) {
+                summary.total_fields += 1;
+                summarise(v, depth + 1, summary);
+            }
+        }
+        _ => {}
+    }
+}
+
+const LEGEND: &str = "Legend:
+  optional     the field is missing from some records
+  nullable     the value is null in some records
+  (min-max)    the range of lengths (strings, arrays) or values (numbers) seen
+  (n)          a single length or value was seen
+  prefix \"x\"   all strings start (or, for a suffix, end) with x
+  pattern /x/  all strings match the regular expression x
+  [ ... ] (n)  an array, with the schema of its elements and its length
+  (n, unique)  an array whose elements are all distinct
+  tuple [ ... ]  a fixed-length array, with the schema of each position
+  { /x/: ... } (n)  an object whose keys match x, with the schema of its values and its number of keys
+  one of [ ... ] a value matching any of the listed schemas
+  unconstrained  an array or object that was only seen empty
+  const x      always the value x
+  unknown      no type could be inferred";
+
+fn collect_empty_paths(schema_state: &SchemaState, current_path: &str, paths: &mut Vec<String>) {
+    match schema_state {
+        SchemaState::UnconstrainedArray | SchemaState::UnconstrainedObject => {
+            paths.push(current_path.to_owned())
+        }
+        SchemaState::Array { schema, .. } => match schema.as_ref() {
+            SchemaState::Initial => paths.push(current_path.to_owned()),
+            schema => collect_empty_paths(schema, &crate::path::elements(current_path), paths),
+        },
+        SchemaState::Object { required, optional } => {
+            if required.is_empty() && optional.is_empty() {
+                paths.push(current_path.to_owned());
+            }
+            for (k, v) in required.iter().chain(optional) {
+                collect_empty_paths(v, &crate::path::key(current_path, k), paths);
+            }
+        }
+        SchemaState::Tuple(elements) => {
+            for (idx, element) in elements.iter().enumerate() {
+                collect_empty_paths(element, &crate::path::index(current_path, idx), paths);
+            }
+        }
+        SchemaState::Nullable(inner) => collect_empty_paths(inner, current_path, paths),
+        SchemaState::Union(branches) => {
+            for branch in branches {
+                collect_empty_paths(branch, current_path, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn override_date_range_inner(schema_state: &mut SchemaState, range: &RangeInclusive<NaiveDate>) {
+    let start = range.start().and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end = range
+        .end()
+        .and_hms_milli_opt(23, 59, 59, 999)
+        .unwrap()
+        .and_utc();
+    match schema_state {
+        SchemaState::String(
+            StringType::IsoDate { range: date_range }
+            | StringType::FormattedDate {
+                range: date_range, ..
+            },
+        ) => *date_range = Some(range.clone()),
+        SchemaState::String(
+            StringType::DateTimeISO8601 { range: date_range }
+            | StringType::DateTimeRFC2822 { range: date_range }
+            | StringType::EpochSeconds { range: date_range },
+        ) => *date_range = Some(start..=end),
+        SchemaState::Nullable(inner) => override_date_range_inner(inner, range),
+        SchemaState::Array { schema, .. } | SchemaState::Map { schema, .. } => {
+            override_date_range_inner(schema, range)
+        }
+        SchemaState::Tuple(schemas) | SchemaState::Union(schemas) => {
+            for schema in schemas {
+                override_date_range_inner(schema, range);
+            }
+        }
+        SchemaState::Object { required, optional } => {
+            for schema in required.values_mut().chain(optional.values_mut()) {
+                override_date_range_inner(schema, range);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn sort_fields_inner(schema_state: &mut SchemaState) {
+    match schema_state {
+        SchemaState::String(StringType::EmbeddedJson(inner))
+        | SchemaState::String(StringType::Jwt { claims: inner })
+        | SchemaState::Nullable(inner)
+        | SchemaState::Array { schema: inner, .. }
+        | SchemaState::Map { schema: inner, .. } => sort_fields_inner(inner),
+        SchemaState::Tuple(schemas) | SchemaState::Union(schemas) => {
+            for schema in schemas {
+                sort_fields_inner(schema);
+            }
+        }
+        SchemaState::Object { required, optional } => {
+            required.sort_keys();
+            optional.sort_keys();
+            for schema in required.values_mut().chain(optional.values_mut()) {
+                sort_fields_inner(schema);
+            }
+        }
+        _ => {}
+    }
+}
+
 impl SchemaState {
+    /// Merges two schemas into one that describes the values of both, as inference does for
+    /// the records of an input, keeping up to `DEFAULT_MAX_SAMPLES` strings and characters as a
+    /// sample for each string field. See `merge_with_max_samples`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema, InferenceOptions, NumberType, SchemaState};
+    /// use serde_json::json;
+    ///
+    /// let options = InferenceOptions::default();
+    /// let schema = infer_schema(json!(1), &options).merge(infer_schema(json!(null), &options));
+    ///
+    /// assert_eq!(
+    ///     schema,
+    ///     SchemaState::Nullable(Box::new(SchemaState::Number(NumberType::Integer {
+    ///         min: 1,
+    ///         max: 1
+    ///     })))
+    /// );
+    /// ```
+    pub fn merge(self, other: SchemaState) -> SchemaState {
+        self.merge_with_max_samples(other, crate::DEFAULT_MAX_SAMPLES)
+    }
+
+    /// Merges two schemas into one that describes the values of both, keeping up to
+    /// `max_samples` strings and characters as a sample for each string field.
+    ///
+    /// Merging lets the schemas of parts of an input be inferred separately, e.g. on different
+    /// machines or as events arrive, and combined afterwards. `Initial` is its identity, and it
+    /// is commutative and associative: whatever order schemas are merged in, the merged schema
+    /// describes the same values. Only the order of the fields of objects, of the branches of
+    /// unions and of the samples kept follows the order of merging, as does the choice of
+    /// samples kept once there are more than `max_samples`.
+    ///
+    /// Schemas are refined once all records are inferred, with what is observed across all of
+    /// them: enums with `InferenceOptions::enum_inference`, singletons with
+    /// `InferenceOptions::widen_singletons`, and pinned types. Merging schemas that were
+    /// refined is not refined again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema_from_iter, InferenceOptions, SchemaState};
+    /// use serde_json::json;
+    ///
+    /// let options = InferenceOptions::default();
+    /// let first = infer_schema_from_iter(vec![json!({ "id": 1 })], &options);
+    /// let second = infer_schema_from_iter(vec![json!({ "id": 2, "tag": "a" })], &options);
+    ///
+    /// let schema = SchemaState::Initial
+    ///     .merge_with_max_samples(first, 100)
+    ///     .merge_with_max_samples(second, 100);
+    /// assert_eq!(
+    ///     schema.to_string_pretty(),
+    ///     "{\n  \"id\": int (1-2),\n  \"tag\": optional string (1)\n}"
+    /// );
+    /// ```
+    pub fn merge_with_max_samples(self, other: SchemaState, max_samples: usize) -> SchemaState {
+        crate::infer::merge(self, other, max_samples)
+    }
+
+    /// Merges `other` into this schema in place, like `merge` does, keeping up to
+    /// `DEFAULT_MAX_SAMPLES` strings and characters as a sample for each string field.
+    ///
+    /// Merging in place reuses the fields, elements and samples of this schema, so it is the
+    /// cheaper way to accumulate the schemas of many values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema, InferenceOptions, SchemaState};
+    /// use serde_json::json;
+    ///
+    /// let options = InferenceOptions::default();
+    /// let mut schema = SchemaState::Initial;
+    /// for value in [json!({ "id": 1 }), json!({ "id": 2, "tag": "a" })] {
+    ///     schema.merge_into(infer_schema(value, &options));
+    /// }
+    /// assert_eq!(
+    ///     schema.to_string_pretty(),
+    ///     "{\n  \"id\": int (1-2),\n  \"tag\": optional string (1)\n}"
+    /// );
+    /// ```
+    pub fn merge_into(&mut self, other: SchemaState) {
+        self.merge_into_with_max_samples(other, crate::DEFAULT_MAX_SAMPLES)
+    }
+
+    /// Merges `other` into this schema in place, like `merge_with_max_samples` does, keeping up
+    /// to `max_samples` strings and characters as a sample for each string field.
+    pub fn merge_into_with_max_samples(&mut self, other: SchemaState, max_samples: usize) {
+        crate::infer::merge_into(self, other, max_samples)
+    }
+
+    /// Sorts the fields of every object in the schema by name, so that they are described and
+    /// produced in alphabetical order rather than in the order they were first seen.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema, InferenceOptions};
+    /// use serde_json::json;
+    ///
+    /// let mut schema = infer_schema(json!({ "b": 1, "a": true }), &InferenceOptions::default());
+    /// assert_eq!(schema.to_string_pretty(), "{\n  \"b\": int (1),\n  \"a\": boolean\n}");
+    ///
+    /// schema.sort_fields();
+    /// assert_eq!(schema.to_string_pretty(), "{\n  \"a\": boolean,\n  \"b\": int (1)\n}");
+    /// ```
+    pub fn sort_fields(&mut self) {
+        sort_fields_inner(self);
+    }
+
+    /// Replaces the range of every date and datetime in the schema with `range`, so that they
+    /// are produced within it rather than within the range seen.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use drivel::{infer_schema, InferenceOptions};
+    /// use serde_json::json;
+    ///
+    /// let mut schema = infer_schema(json!({ "born": "1990-04-01" }), &InferenceOptions::default());
+    /// let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+    /// let end = NaiveDate::from_ymd_opt(2020, 12, 31).unwrap();
+    /// schema.override_date_range(start..=end);
+    ///
+    /// assert!(schema.to_string_pretty().contains("2020-01-01 to 2020-12-31"));
+    /// ```
+    pub fn override_date_range(&mut self, range: RangeInclusive<NaiveDate>) {
+        override_date_range_inner(self, &range);
+    }
+
+    /// Returns the paths of the arrays and objects that were only ever seen empty, sorted by
+    /// path. When the schema describes an array at its root, paths are relative to its elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema_from_iter, InferenceOptions};
+    /// use serde_json::json;
+    ///
+    /// let schema = infer_schema_from_iter(
+    ///     vec![json!({ "tags": [], "meta": {} }), json!({ "tags": [], "meta": { "a": 1 } })],
+    ///     &InferenceOptions::default(),
+    /// );
+    ///
+    /// assert_eq!(schema.empty_container_paths(), vec!["tags"]);
+    /// ```
+    pub fn empty_container_paths(&self) -> Vec<String> {
+        let mut paths = vec![];
+        match self {
+            SchemaState::Array { schema, .. } => collect_empty_paths(schema, "", &mut paths),
+            _ => collect_empty_paths(self, "", &mut paths),
+        }
+        paths.sort();
+        paths
+    }
+
+    /// Computes summary statistics about the schema: the number of fields, the maximum
+    /// nesting depth, and the number of detected string formats.
+    pub fn summary(&self) -> SchemaSummary {
+        let mut summary = SchemaSummary::default();
+        summarise(self, 0, &mut summary);
+        summary
+    }
+
+    /// Returns the same representation as `to_string_pretty`, preceded by a summary header and
+    /// a legend explaining the notation.
+    ///
+    /// `record_count` is the number of records the schema was inferred from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{SchemaState, StringType};
+    /// use indexmap::IndexMap;
+    ///
+    /// let schema = SchemaState::Object {
+    ///     required: IndexMap::from_iter([
+    ///         ("id".to_string(), SchemaState::String(StringType::UUID)),
+    ///     ]),
+    ///     optional: IndexMap::new(),
+    /// };
+    ///
+    /// let output = schema.to_string_pretty_with_summary(10);
+    /// assert!(output.starts_with("Records: 10\nFields: 1 (max depth: 1)\nDetected formats: 1\n"));
+    /// ```
+    pub fn to_string_pretty_with_summary(&self, record_count: usize) -> String {
+        let summary = self.summary();
+        format!(
+            "Records: {}\nFields: {} (max depth: {})\nDetected formats: {}\n\n{}\n\n{}",
+            record_count,
+            summary.total_fields,
+            summary.max_depth,
+            summary.detected_formats,
+            LEGEND,
+            self.to_string_pretty()
+        )
+    }
+
     /// Returns a formatted string representation of the schema state with indentation for readability.
     ///
     /// This method recursively traverses the schema state and constructs a formatted string representation
@@ -205,20 +1331,27 @@ impl SchemaState {
     /// # Examples
     ///
     /// ```
-    /// use drivel::{SchemaState, StringType, NumberType};
-    /// use std::collections::{HashMap, HashSet};
-    /// use std::iter::FromIterator;
+    /// use std::collections::HashMap;
+    ///
+    /// use drivel::{DistinctCount, SchemaState, StringType, NumberType};
+    /// use indexmap::IndexMap;
     ///
-    /// let required = HashMap::from_iter(vec![
+    /// let required = IndexMap::from_iter(vec![
     ///     ("name".to_string(), SchemaState::String(StringType::Unknown {
     ///         strings_seen: vec!["abc".to_string()],
-    ///         chars_seen: vec!['a', 'b', 'c'],
+    ///         chars_seen: HashMap::from([('a', 1), ('b', 1), ('c', 1)]),
+    ///         n_strings_seen: 1,
+    ///         distinct: DistinctCount::from_iter(["abc"]),
+    ///         n_chars_seen: 3,
     ///         min_length: Some(1),
     ///         max_length: Some(10),
+    ///         prefix: "abc".to_owned(),
+    ///         suffix: "abc".to_owned(),
+    ///         structure: None,
     ///     }))
     /// ]);
     ///
-    /// let optional = HashMap::from_iter(vec![
+    /// let optional = IndexMap::from_iter(vec![
     ///     ("age".to_string(), SchemaState::Number(NumberType::Integer { min: 0, max: 120 }))
     /// ]);
     ///
@@ -0,0 +1,427 @@
+//! Producing records as a time series: with timestamps that increase from one record to the
+//! next, and numbers that wander from their value in the record before rather than being drawn
+//! independently, like the events of a stream.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use rand::Rng;
+use serde_json::Value;
+
+use crate::{
+    path::{self, parse_steps, visit_mut, Step},
+    produce::integer_number,
+    DrivelError, NumberType, SchemaState,
+};
+
+/// The share of the range of a number that a random walk steps by at most, when no step is
+/// given.
+const DEFAULT_STEP_RATIO: f64 = 0.01;
+
+/// How the records of a time series are produced: the path of the timestamp that increases
+/// from one record to the next, and the paths of the numbers that follow a random walk.
+///
+/// Paths use the same notation as overrides, and are relative to the elements of an array at
+/// the root.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::TimeSeries;
+///
+/// let series = TimeSeries::new("timestamp")
+///     .interval(chrono::TimeDelta::minutes(5))
+///     .jitter(chrono::TimeDelta::seconds(30))
+///     .walk("temperature", Some(0.5));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TimeSeries {
+    timestamp: String,
+    start: Option<DateTime<Utc>>,
+    interval: TimeDelta,
+    jitter: TimeDelta,
+    /// The paths of the numbers that follow a random walk, and the most they step by.
+    walks: Vec<(String, Option<f64>)>,
+}
+
+impl TimeSeries {
+    /// Returns a time series whose timestamp, at `timestamp`, starts at the timestamp produced
+    /// for the first record and increases by a second from one record to the next.
+    pub fn new(timestamp: &str) -> Self {
+        Self {
+            timestamp: timestamp.to_owned(),
+            start: None,
+            interval: TimeDelta::seconds(1),
+            jitter: TimeDelta::zero(),
+            walks: vec![],
+        }
+    }
+
+    /// The timestamp of the first record. Default = the timestamp produced for it.
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// The time between the timestamps of consecutive records. Default = one second.
+    pub fn interval(mut self, interval: TimeDelta) -> Self {
+        self.interval = interval.abs();
+        self
+    }
+
+    /// The most that the time between consecutive records differs from the interval, at
+    /// random. Timestamps never decrease, however large the jitter. Default = none.
+    pub fn jitter(mut self, jitter: TimeDelta) -> Self {
+        self.jitter = jitter.abs();
+        self
+    }
+
+    /// Makes the numbers produced at `path` follow a random walk: each is the number of the
+    /// record before plus a step of at most `step` either way, kept within the range of the
+    /// schema. The first is produced as usual. Default step = a hundredth of the range, and
+    /// steps larger than the range are capped at it.
+    pub fn walk(mut self, path: &str, step: Option<f64>) -> Self {
+        self.walks.push((path.to_owned(), step));
+        self
+    }
+}
+
+/// Returns the schema of the values found at `steps` within values of `schema`.
+fn schema_at<'a>(schema: &'a SchemaState, steps: &[Step]) -> Option<&'a SchemaState> {
+    match (steps.split_first(), schema) {
+        (_, SchemaState::Nullable(inner)) => schema_at(inner, steps),
+        (_, SchemaState::Union(branches)) => {
+            branches.iter().find_map(|branch| schema_at(branch, steps))
+        }
+        (None, schema) => Some(schema),
+        (Some((Step::Key(key), rest)), SchemaState::Object { required, optional }) => {
+            schema_at(required.get(key).or_else(|| optional.get(key))?, rest)
+        }
+        (Some((Step::Elements, rest)), SchemaState::Array { schema, .. }) => {
+            schema_at(schema, rest)
+        }
+        (Some((Step::Elements, rest)), SchemaState::Tuple(elements)) => {
+            elements.iter().find_map(|element| schema_at(element, rest))
+        }
+        _ => None,
+    }
+}
+
+/// A number that follows a random walk.
+struct Walk {
+    steps: Vec<Step>,
+    min: f64,
+    max: f64,
+    step: f64,
+    /// The number of decimal places numbers are rounded to, if any.
+    scale: Option<u32>,
+    last: Option<f64>,
+}
+
+/// The state of a time series as its records are produced, one after the other.
+pub(crate) struct SeriesState<'a> {
+    series: &'a TimeSeries,
+    timestamp: Vec<Step>,
+    /// The timestamp of the record before.
+    last: Option<DateTime<Utc>>,
+    /// Whether timestamps that are numbers count milliseconds since the epoch rather than
+    /// seconds, as decided by the first one.
+    epoch_millis: Option<bool>,
+    walks: Vec<Walk>,
+}
+
+impl<'a> SeriesState<'a> {
+    /// Starts the time series for records of `record_schema`, or returns an error if no values
+    /// are produced at the path of its timestamp or of one of its walks.
+    pub(crate) fn new(
+        series: &'a TimeSeries,
+        record_schema: &SchemaState,
+    ) -> Result<Self, DrivelError> {
+        let steps_at = |path: &str| {
+            let steps = path::from_record(parse_steps(path));
+            match schema_at(record_schema, &steps) {
+                Some(schema) => Ok((steps, schema)),
+                None => Err(DrivelError::InvalidTimeSeries {
+                    path: path.to_owned(),
+                    message: "no values found at this path".to_owned(),
+                }),
+            }
+        };
+        let (timestamp, _) = steps_at(&series.timestamp)?;
+        let walks = series
+            .walks
+            .iter()
+            .map(|(path, step)| {
+                let (steps, schema) = steps_at(path)?;
+                let (min, max, scale) = match schema {
+                    SchemaState::Number(NumberType::Float { min, max, .. }) => (*min, *max, None),
+                    SchemaState::Number(NumberType::Decimal { min, max, scale }) => {
+                        (*min, *max, Some(*scale))
+                    }
+                    SchemaState::Number(number_type) => {
+                        let (min, max) = number_type.integer_range().unwrap_or_default();
+                        (min as f64, max as f64, Some(0))
+                    }
+                    _ => (f64::MIN, f64::MAX, None),
+                };
+                let step = step.unwrap_or_else(|| {
+                    let step = (max - min) * DEFAULT_STEP_RATIO;
+                    if step.is_finite() {
+                        step
+                    } else {
+                        1.0
+                    }
+                });
+                Ok(Walk {
+                    steps,
+                    min,
+                    max,
+                    step: step.abs().min(max - min),
+                    scale,
+                    last: None,
+                })
+            })
+            .collect::<Result<_, DrivelError>>()?;
+        Ok(Self {
+            series,
+            timestamp,
+            last: None,
+            epoch_millis: None,
+            walks,
+        })
+    }
+
+    /// Returns the timestamp of the next record, given the one produced for it.
+    fn next_timestamp(&mut self, produced: DateTime<Utc>, rng: &mut impl Rng) -> DateTime<Utc> {
+        let next = match self.last {
+            None => self.series.start.unwrap_or(produced),
+            Some(last) => {
+                let jitter = self.series.jitter.num_milliseconds();
+                let jitter = if jitter > 0 {
+                    rng.gen_range(-jitter..=jitter)
+                } else {
+                    0
+                };
+                let step = (self.series.interval.num_milliseconds() + jitter).max(0);
+                last + TimeDelta::milliseconds(step)
+            }
+        };
+        self.last = Some(next);
+        next
+    }
+
+    /// Replaces the timestamp of `record` by the next one of the series, and moves the numbers
+    /// that follow a random walk on from those of the record before. Nulls are kept, as are
+    /// missing fields.
+    pub(crate) fn advance(&mut self, record: &mut Value, rng: &mut impl Rng) {
+        let timestamp = self.timestamp.clone();
+        visit_mut(record, &timestamp, &mut |value| match value {
+            Value::String(s) => {
+                if let Ok(produced) = DateTime::parse_from_rfc3339(s) {
+                    *s = self.next_timestamp(produced.to_utc(), rng).to_rfc3339();
+                } else if let Ok(produced) = DateTime::parse_from_rfc2822(s) {
+                    *s = self.next_timestamp(produced.to_utc(), rng).to_rfc2822();
+                } else if let Ok(produced) = s.parse::<chrono::NaiveDate>() {
+                    let produced = produced.and_time(chrono::NaiveTime::MIN).and_utc();
+                    *s = self.next_timestamp(produced, rng).date_naive().to_string();
+                }
+            }
+            Value::Number(n) => {
+                let Some(n) = n.as_i64() else { return };
+                // integers this large are milliseconds since the epoch, rather than seconds
+                let millis = *self.epoch_millis.get_or_insert(n.abs() >= 100_000_000_000);
+                let produced = if millis {
+                    DateTime::from_timestamp_millis(n)
+                } else {
+                    DateTime::from_timestamp(n, 0)
+                };
+                let Some(produced) = produced else { return };
+                let next = self.next_timestamp(produced, rng);
+                let next = if millis {
+                    next.timestamp_millis()
+                } else {
+                    next.timestamp()
+                };
+                *value = Value::Number(next.into());
+            }
+            _ => {}
+        });
+
+        for walk in &mut self.walks {
+            visit_mut(record, &walk.steps, &mut |value| {
+                let Some(produced) = value.as_f64() else {
+                    return;
+                };
+                let next = match walk.last {
+                    None => produced,
+                    Some(last) => {
+                        // scaled rather than drawn from `-step..=step`, whose width may not be
+                        // representable
+                        let step = walk.step * rng.gen_range(-1.0..=1.0);
+                        (last + step).clamp(walk.min, walk.max)
+                    }
+                };
+                let next = match walk.scale {
+                    Some(scale) => {
+                        let factor = 10f64.powi(scale as i32);
+                        (next * factor).round() / factor
+                    }
+                    None => next,
+                };
+                walk.last = Some(next);
+                *value = match walk.scale {
+                    Some(0) => Value::Number(integer_number(next as i128)),
+                    _ => serde_json::Number::from_f64(next)
+                        .map(Value::Number)
+                        .unwrap_or_default(),
+                };
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn increases_timestamps_and_walks_numbers_from_one_record_to_the_next() {
+        let schema = SchemaState::Object {
+            required: [
+                (
+                    "at".to_owned(),
+                    SchemaState::String(crate::StringType::DateTimeISO8601 { range: None }),
+                ),
+                (
+                    "level".to_owned(),
+                    SchemaState::Number(NumberType::Integer { min: 0, max: 100 }),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            optional: Default::default(),
+        };
+        let series = TimeSeries::new("at")
+            .start("2024-01-01T00:00:00Z".parse().unwrap())
+            .interval(TimeDelta::minutes(1))
+            .jitter(TimeDelta::seconds(10))
+            .walk("level", Some(3.0));
+        let mut state = SeriesState::new(&series, &schema).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut records = (0..50)
+            .map(|idx| json!({ "at": "2030-05-05T00:00:00Z", "level": (idx * 37) % 100 }))
+            .collect::<Vec<_>>();
+        for record in &mut records {
+            state.advance(record, &mut rng);
+        }
+
+        assert_eq!(records[0]["at"], json!("2024-01-01T00:00:00+00:00"));
+        for pair in records.windows(2) {
+            let moment = |record: &Value| {
+                DateTime::parse_from_rfc3339(record["at"].as_str().unwrap()).unwrap()
+            };
+            let elapsed = moment(&pair[1]) - moment(&pair[0]);
+            assert!(elapsed >= TimeDelta::seconds(50) && elapsed <= TimeDelta::seconds(70));
+
+            let level = |record: &Value| record["level"].as_i64().unwrap();
+            assert!((level(&pair[1]) - level(&pair[0])).abs() <= 3);
+            assert!((0..=100).contains(&level(&pair[1])));
+        }
+    }
+
+    #[test]
+    fn increases_epoch_timestamps_in_their_unit() {
+        let series = TimeSeries::new("ts").interval(TimeDelta::seconds(2));
+        let schema = SchemaState::Object {
+            required: [(
+                "ts".to_owned(),
+                SchemaState::Number(NumberType::Integer {
+                    min: 5,
+                    max: 1_700_000_000_000,
+                }),
+            )]
+            .into_iter()
+            .collect(),
+            optional: Default::default(),
+        };
+        let mut state = SeriesState::new(&series, &schema).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut records = vec![json!({ "ts": 1_700_000_000 }), json!({ "ts": 5 })];
+        for record in &mut records {
+            state.advance(record, &mut rng);
+        }
+        assert_eq!(records[1]["ts"], json!(1_700_000_002));
+
+        let mut state = SeriesState::new(&series, &schema).unwrap();
+        let mut records = vec![json!({ "ts": 1_700_000_000_000u64 }), json!({ "ts": 5 })];
+        for record in &mut records {
+            state.advance(record, &mut rng);
+        }
+        assert_eq!(records[1]["ts"], json!(1_700_000_002_000u64));
+    }
+
+    #[test]
+    fn caps_steps_at_the_range_of_the_walk() {
+        let schema = SchemaState::Object {
+            required: [
+                (
+                    "at".to_owned(),
+                    SchemaState::String(crate::StringType::DateTimeISO8601 { range: None }),
+                ),
+                (
+                    "price".to_owned(),
+                    SchemaState::Number(NumberType::Float {
+                        min: f64::MIN,
+                        max: f64::MAX,
+                        non_finite: false,
+                        exclusive_min: false,
+                        exclusive_max: false,
+                    }),
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            optional: Default::default(),
+        };
+        let series = TimeSeries::new("at").walk("price", Some(f64::MAX));
+        let mut state = SeriesState::new(&series, &schema).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        for _ in 0..10 {
+            let mut record = json!({ "at": "2024-01-01T00:00:00Z", "price": 1.5 });
+            state.advance(&mut record, &mut rng);
+            assert!(record["price"].as_f64().unwrap().is_finite());
+        }
+    }
+
+    #[test]
+    fn rejects_paths_without_values() {
+        let schema = SchemaState::Object {
+            required: [(
+                "at".to_owned(),
+                SchemaState::String(crate::StringType::DateTimeISO8601 { range: None }),
+            )]
+            .into_iter()
+            .collect(),
+            optional: Default::default(),
+        };
+        for (series, path) in [
+            (TimeSeries::new("nope"), "nope"),
+            (TimeSeries::new("at").walk("price", None), "price"),
+        ] {
+            assert_eq!(
+                SeriesState::new(&series, &schema)
+                    .err()
+                    .unwrap()
+                    .to_string(),
+                format!(
+                    "invalid time series for {}: no values found at this path",
+                    path
+                )
+            );
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Inferring a schema incrementally, one value at a time, for services that keep refining a
+//! schema as values arrive.
+
+use std::cell::OnceCell;
+
+use crate::infer::{infer_record, merge_into, settle};
+use crate::profile::{timed, Stage};
+use crate::{InferenceOptions, SchemaState, StringType};
+
+/// The number of values observed between compactions of the samples of a session, unless
+/// another interval is given.
+pub const DEFAULT_COMPACTION_INTERVAL: usize = 10_000;
+
+/// A schema that is inferred incrementally, by observing values one at a time, e.g. as events
+/// are read from a queue, without collecting them first.
+///
+/// The schema of the values observed is the one `infer_schema_from_iter` infers from them,
+/// except for what can only be observed across all of them at once: singletons are not
+/// widened, and types are not pinned. The samples of strings kept are bounded by
+/// `InferenceOptions::max_samples`, and are compacted every so often to release the memory
+/// they no longer use.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{InferenceOptions, InferenceSession};
+/// use serde_json::json;
+///
+/// let mut session = InferenceSession::new(InferenceOptions::default());
+/// session.observe(&json!({ "id": 1, "level": "info" }));
+/// session.observe(&json!({ "id": 2, "level": "warn", "trace": null }));
+///
+/// assert_eq!(session.n_observed(), 2);
+/// assert_eq!(
+///     session.schema().to_string_pretty(),
+///     "{\n  \"id\": int (1-2),\n  \"level\": string (4),\n  \"trace\": optional null\n}"
+/// );
+/// ```
+pub struct InferenceSession {
+    options: InferenceOptions,
+    /// The schema of the values observed, before it is settled.
+    merged: SchemaState,
+    /// The settled schema, from when it was last asked for.
+    schema: OnceCell<SchemaState>,
+    n_observed: usize,
+    compaction_interval: usize,
+}
+
+impl InferenceSession {
+    /// Returns a session that has not observed any values, whose samples are compacted every
+    /// `DEFAULT_COMPACTION_INTERVAL` values.
+    pub fn new(options: InferenceOptions) -> Self {
+        Self::with_compaction_interval(options, DEFAULT_COMPACTION_INTERVAL)
+    }
+
+    /// Returns a session that has not observed any values, whose samples are compacted every
+    /// `compaction_interval` values.
+    pub fn with_compaction_interval(options: InferenceOptions, compaction_interval: usize) -> Self {
+        Self {
+            options,
+            merged: SchemaState::Initial,
+            schema: OnceCell::new(),
+            n_observed: 0,
+            compaction_interval: compaction_interval.max(1),
+        }
+    }
+
+    /// Refines the schema with a value, like a line of JSON lines.
+    pub fn observe(&mut self, value: &serde_json::Value) {
+        let inferred = infer_record(value.clone(), &self.options);
+        timed(self.options.profile.as_deref(), Stage::Merging, || {
+            merge_into(&mut self.merged, inferred, self.options.max_samples)
+        });
+        self.schema = OnceCell::new();
+        self.n_observed += 1;
+        if self.n_observed.is_multiple_of(self.compaction_interval) {
+            self.compact();
+        }
+    }
+
+    /// Returns the schema of the values observed so far, which is `SchemaState::Initial` until
+    /// a value is observed.
+    pub fn schema(&self) -> &SchemaState {
+        self.schema
+            .get_or_init(|| settle(self.merged.clone(), &self.options))
+    }
+
+    /// Returns the schema of the values observed, ending the session.
+    pub fn into_schema(self) -> SchemaState {
+        match self.schema.into_inner() {
+            Some(schema) => schema,
+            None => settle(self.merged, &self.options),
+        }
+    }
+
+    /// Returns the number of values observed so far.
+    pub fn n_observed(&self) -> usize {
+        self.n_observed
+    }
+
+    /// Releases the memory that the samples of the schema no longer use, as merging samples
+    /// leaves room for more than are kept. This happens every so often while observing values,
+    /// but can be done at any time, e.g. when the session is about to be idle.
+    pub fn compact(&mut self) {
+        compact(&mut self.merged);
+    }
+}
+
+fn compact(schema: &mut SchemaState) {
+    match schema {
+        SchemaState::String(string_type) => match string_type {
+            StringType::Unknown {
+                strings_seen,
+                chars_seen,
+                ..
+            } => {
+                strings_seen.shrink_to_fit();
+                chars_seen.shrink_to_fit();
+            }
+            StringType::CurrencyCode { codes } => codes.shrink_to_fit(),
+            StringType::PhoneNumber { formats } => formats.shrink_to_fit(),
+            StringType::Enum { variants } => variants.shrink_to_fit(),
+            StringType::EmbeddedJson(inner) | StringType::Jwt { claims: inner } => compact(inner),
+            _ => {}
+        },
+        SchemaState::Number(crate::NumberType::Enum { variants, .. }) => variants.shrink_to_fit(),
+        SchemaState::Nullable(inner)
+        | SchemaState::Array { schema: inner, .. }
+        | SchemaState::Map { schema: inner, .. } => compact(inner),
+        SchemaState::Tuple(schemas) | SchemaState::Union(schemas) => {
+            schemas.iter_mut().for_each(compact)
+        }
+        SchemaState::Object { required, optional } => required
+            .values_mut()
+            .chain(optional.values_mut())
+            .for_each(compact),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema_from_iter, EnumInference};
+
+    #[test]
+    fn infers_the_schema_of_the_values_observed() {
+        let values = (0..50)
+            .map(|n| json!({ "id": n % 3, "price": 1.25 * n as f64, "tag": format!("t{}", n) }))
+            .collect::<Vec<_>>();
+        let options = || InferenceOptions {
+            enum_inference: Some(EnumInference {
+                max_unique_ratio: 0.1,
+                min_sample_size: 10,
+            }),
+            ..InferenceOptions::default()
+        };
+
+        let mut session = InferenceSession::with_compaction_interval(options(), 7);
+        for value in &values {
+            session.observe(value);
+        }
+        assert_eq!(
+            session.schema(),
+            &infer_schema_from_iter(values, &options())
+        );
+        assert_eq!(session.n_observed(), 50);
+    }
+}
@@ -0,0 +1,191 @@
+//! Exporting schemas as Spark SQL schemas, written in DDL.
+
+use crate::codegen::{self, Field};
+use crate::{infer::integer_value, NumberType, SchemaState, StringType};
+
+/// The largest precision of a Spark decimal.
+const MAX_DECIMAL_PRECISION: u32 = 38;
+
+/// Returns the narrowest Spark type that holds every integer in `min..=max`.
+fn integer_type(min: i128, max: i128) -> String {
+    let fits = |lower: i128, upper: i128| min >= lower && max <= upper;
+    let digits = min.unsigned_abs().max(max.unsigned_abs()).checked_ilog10();
+    if fits(i32::MIN.into(), i32::MAX.into()) {
+        "INT".to_owned()
+    } else if fits(i64::MIN.into(), i64::MAX.into()) {
+        "BIGINT".to_owned()
+    } else if digits.is_some_and(|digits| digits < MAX_DECIMAL_PRECISION) {
+        format!("DECIMAL({},0)", MAX_DECIMAL_PRECISION)
+    } else {
+        "DOUBLE".to_owned()
+    }
+}
+
+/// Quotes a key as a Spark identifier, e.g. `first-name` as `` `first-name` ``.
+fn identifier(key: &str) -> String {
+    format!("`{}`", key.replace('`', "``"))
+}
+
+fn data_type(schema: &SchemaState) -> String {
+    match schema {
+        // Spark has no unions or tuples, and reads values that do not fit a string column as
+        // their JSON
+        SchemaState::Initial
+        | SchemaState::Indefinite
+        | SchemaState::Null
+        | SchemaState::Union(_)
+        | SchemaState::Tuple(_)
+        | SchemaState::UnconstrainedArray
+        | SchemaState::UnconstrainedObject
+        | SchemaState::Const(
+            serde_json::Value::Null | serde_json::Value::Array(_) | serde_json::Value::Object(_),
+        ) => "STRING".to_owned(),
+        SchemaState::Nullable(inner) => data_type(inner),
+        SchemaState::String(StringType::DateTimeISO8601 { .. }) => "TIMESTAMP".to_owned(),
+        SchemaState::String(StringType::IsoDate { .. }) => "DATE".to_owned(),
+        // Spark reads binary values from base64 in JSON
+        SchemaState::String(StringType::Base64 { .. }) => "BINARY".to_owned(),
+        SchemaState::String(_) | SchemaState::Const(serde_json::Value::String(_)) => {
+            "STRING".to_owned()
+        }
+        SchemaState::Number(
+            number_type @ (NumberType::Integer { .. }
+            | NumberType::Enum { .. }
+            | NumberType::BigInteger { .. }),
+        ) => {
+            let (min, max) = number_type.integer_range().unwrap_or_default();
+            integer_type(min, max)
+        }
+        SchemaState::Number(NumberType::Decimal { scale, .. })
+            if *scale <= MAX_DECIMAL_PRECISION =>
+        {
+            format!("DECIMAL({},{})", MAX_DECIMAL_PRECISION, scale)
+        }
+        SchemaState::Number(NumberType::Float { .. } | NumberType::Decimal { .. }) => {
+            "DOUBLE".to_owned()
+        }
+        SchemaState::Const(serde_json::Value::Number(n)) => match integer_value(n) {
+            Some(n) => integer_type(n, n),
+            None => "DOUBLE".to_owned(),
+        },
+        SchemaState::Boolean | SchemaState::Const(serde_json::Value::Bool(_)) => {
+            "BOOLEAN".to_owned()
+        }
+        SchemaState::Array { schema, .. } => format!("ARRAY<{}>", data_type(schema)),
+        SchemaState::Map { schema, .. } => format!("MAP<STRING, {}>", data_type(schema)),
+        SchemaState::Object { required, optional } => {
+            let fields = codegen::fields(required, optional)
+                .into_iter()
+                .map(|field| format!("{}: {}", identifier(field.key), column_type(&field)))
+                .collect::<Vec<_>>();
+            format!("STRUCT<{}>", fields.join(", "))
+        }
+    }
+}
+
+/// Returns the type of a field, which is `NOT NULL` when it is required and never null.
+fn column_type(field: &Field) -> String {
+    let nullable = field.optional
+        || matches!(
+            field.schema,
+            SchemaState::Nullable(_)
+                | SchemaState::Null
+                | SchemaState::Const(serde_json::Value::Null)
+        );
+    if nullable {
+        data_type(field.schema)
+    } else {
+        format!("{} NOT NULL", data_type(field.schema))
+    }
+}
+
+/// Exports a schema as a Spark SQL schema in DDL, as read by `StructType.fromDDL` and
+/// `spark.read.schema`, with a column for each field of the records: the elements of an array
+/// at the root, or the root itself.
+///
+/// Objects become `STRUCT`s and arrays `ARRAY`s, and required fields that are never null are
+/// `NOT NULL`. ISO 8601 datetimes and dates become `TIMESTAMP`s and `DATE`s. Values Spark
+/// cannot describe, like unions and tuples, become strings, which Spark reads as their JSON.
+/// Records that are not objects are held in a `value` column.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_spark, InferenceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!({ "id": 1, "email": null, "tags": ["a"] }),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(
+///     to_spark(&schema),
+///     "`id` INT NOT NULL,`email` STRING,`tags` ARRAY<STRING> NOT NULL"
+/// );
+/// ```
+pub fn to_spark(schema: &SchemaState) -> String {
+    codegen::record_fields(schema)
+        .iter()
+        .map(|field| format!("{} {}", identifier(field.key), column_type(field)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+
+    #[test]
+    fn maps_structs_arrays_and_nullable_columns() {
+        let schema = SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: false,
+            schema: Box::new(SchemaState::Object {
+                required: IndexMap::from_iter([
+                    (
+                        "line-items".to_owned(),
+                        SchemaState::Array {
+                            min_length: 1,
+                            max_length: 3,
+                            unique: false,
+                            schema: Box::new(SchemaState::Object {
+                                required: IndexMap::from_iter([(
+                                    "price".to_owned(),
+                                    SchemaState::Number(NumberType::Decimal {
+                                        scale: 2,
+                                        min: 0.5,
+                                        max: 9.99,
+                                    }),
+                                )]),
+                                optional: IndexMap::from_iter([(
+                                    "quantity".to_owned(),
+                                    SchemaState::Number(NumberType::Integer {
+                                        min: 1,
+                                        max: 5_000_000_000,
+                                    }),
+                                )]),
+                            }),
+                        },
+                    ),
+                    (
+                        "at".to_owned(),
+                        SchemaState::Nullable(Box::new(SchemaState::String(
+                            StringType::DateTimeISO8601 { range: None },
+                        ))),
+                    ),
+                ]),
+                optional: IndexMap::new(),
+            }),
+        };
+
+        assert_eq!(
+            to_spark(&schema),
+            "`line-items` ARRAY<STRUCT<`price`: DECIMAL(38,2) NOT NULL, `quantity`: BIGINT>> NOT NULL,\
+             `at` TIMESTAMP"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Writing produced records as SQL `INSERT` statements, to load them into a database.
+
+use std::collections::{HashMap, HashSet};
+
+use indexmap::IndexSet;
+
+use crate::codegen::unique_name;
+
+/// The number of rows inserted by each statement unless another batch size is given.
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// The SQL dialect that statements are written in, which determines how identifiers and
+/// values are quoted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SqlDialect {
+    /// PostgreSQL, which quotes identifiers with `"`.
+    Postgres,
+    /// MySQL (and MariaDB), which quotes identifiers with backticks, and escapes backslashes
+    /// in strings.
+    Mysql,
+    /// SQLite, which quotes identifiers with `"`, and stores booleans as 1 and 0.
+    Sqlite,
+}
+
+impl SqlDialect {
+    fn identifier(self, name: &str) -> String {
+        match self {
+            SqlDialect::Postgres | SqlDialect::Sqlite => {
+                format!("\"{}\"", name.replace('"', "\"\""))
+            }
+            SqlDialect::Mysql => format!("`{}`", name.replace('`', "``")),
+        }
+    }
+
+    fn string(self, s: &str) -> String {
+        let s = s.replace('\'', "''");
+        match self {
+            SqlDialect::Postgres | SqlDialect::Sqlite => format!("'{}'", s),
+            SqlDialect::Mysql => format!("'{}'", s.replace('\\', "\\\\")),
+        }
+    }
+
+    fn value(self, value: Option<&serde_json::Value>) -> String {
+        match value {
+            None | Some(serde_json::Value::Null) => "NULL".to_owned(),
+            Some(serde_json::Value::Bool(b)) => match self {
+                SqlDialect::Postgres | SqlDialect::Mysql => b.to_string().to_uppercase(),
+                SqlDialect::Sqlite => u8::from(*b).to_string(),
+            },
+            Some(serde_json::Value::Number(n)) => n.to_string(),
+            Some(serde_json::Value::String(s)) => self.string(s),
+            // arrays (and objects without fields) are inserted as their JSON, for JSON columns
+            Some(value) => self.string(&value.to_string()),
+        }
+    }
+
+    /// Returns the statement inserting a row of defaults, for records without any columns.
+    fn default_row(self, table: &str) -> String {
+        match self {
+            SqlDialect::Postgres | SqlDialect::Sqlite => {
+                format!("INSERT INTO {} DEFAULT VALUES;\n", table)
+            }
+            SqlDialect::Mysql => format!("INSERT INTO {} () VALUES ();\n", table),
+        }
+    }
+}
+
+/// Collects the values of an object into `columns`, by the path of keys leading to them, with
+/// the fields of nested objects flattened into columns of their own.
+fn flatten<'a>(
+    value: &'a serde_json::Value,
+    path: &mut Vec<&'a str>,
+    columns: &mut Vec<(Vec<&'a str>, &'a serde_json::Value)>,
+) {
+    match value {
+        serde_json::Value::Object(fields) if !fields.is_empty() => {
+            for (key, value) in fields {
+                path.push(key);
+                flatten(value, path, columns);
+                path.pop();
+            }
+        }
+        value => columns.push((path.clone(), value)),
+    }
+}
+
+fn record_columns(record: &serde_json::Value) -> Vec<(Vec<&str>, &serde_json::Value)> {
+    let mut columns = vec![];
+    match record {
+        serde_json::Value::Object(fields) if fields.is_empty() => {}
+        serde_json::Value::Object(_) => flatten(record, &mut vec![], &mut columns),
+        record => columns.push((vec!["value"], record)),
+    }
+    columns
+}
+
+/// Writes records as `INSERT INTO <table>` statements in a SQL dialect, each inserting up to
+/// `batch_size` rows: the elements of an array, or the value itself.
+///
+/// The fields of nested objects are flattened into columns named after the path of keys
+/// leading to them, like `address_city`, and arrays are inserted as their JSON. Every statement
+/// lists the columns of all records, in the order they were first seen, inserting `NULL` where
+/// a record lacks a field. Records that are not objects are held in a `value` column. The
+/// table may be qualified by a schema, as `schema.table`.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{to_sql_inserts, SqlDialect};
+/// use serde_json::json;
+///
+/// let records = json!([
+///     { "id": 1, "name": "O'Brien", "address": { "city": "Cork" } },
+///     { "id": 2, "name": "Smith", "address": null, "tags": ["a"] },
+/// ]);
+///
+/// assert_eq!(
+///     to_sql_inserts(&records, "events", SqlDialect::Postgres, 1000),
+///     r#"INSERT INTO "events" ("id", "name", "address_city", "tags") VALUES
+///   (1, 'O''Brien', 'Cork', NULL),
+///   (2, 'Smith', NULL, '["a"]');
+/// "#
+/// );
+/// ```
+pub fn to_sql_inserts(
+    records: &serde_json::Value,
+    table: &str,
+    dialect: SqlDialect,
+    batch_size: usize,
+) -> String {
+    let records = match records {
+        serde_json::Value::Array(records) => records.iter().collect::<Vec<_>>(),
+        record => vec![record],
+    };
+    let rows = records
+        .iter()
+        .map(|record| record_columns(record))
+        .collect::<Vec<_>>();
+
+    let mut paths = IndexSet::new();
+    for (path, _) in rows.iter().flatten() {
+        paths.insert(path.clone());
+    }
+    // an object that is null in some records is described by the columns of its fields, which
+    // are null too
+    let prefixes = paths
+        .iter()
+        .flat_map(|path| (0..path.len()).map(|n| &path[..n]))
+        .collect::<HashSet<_>>();
+    let paths = paths
+        .iter()
+        .filter(|path| !prefixes.contains(&path[..]))
+        .collect::<Vec<_>>();
+
+    let table = table
+        .split('.')
+        .map(|part| dialect.identifier(part))
+        .collect::<Vec<_>>()
+        .join(".");
+    if paths.is_empty() {
+        return records
+            .iter()
+            .map(|_| dialect.default_row(&table))
+            .collect();
+    }
+    let mut taken = HashSet::new();
+    let columns = paths
+        .iter()
+        .map(|path| dialect.identifier(&unique_name(path.join("_"), &mut taken)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    rows.chunks(batch_size.max(1))
+        .map(|batch| {
+            let values = batch
+                .iter()
+                .map(|row| {
+                    let row = row.iter().cloned().collect::<HashMap<_, _>>();
+                    let values = paths
+                        .iter()
+                        .map(|path| dialect.value(row.get(*path).copied()))
+                        .collect::<Vec<_>>();
+                    format!("  ({})", values.join(", "))
+                })
+                .collect::<Vec<_>>();
+            format!(
+                "INSERT INTO {} ({}) VALUES\n{};\n",
+                table,
+                columns,
+                values.join(",\n")
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn quotes_and_escapes_per_dialect() {
+        let records = json!([
+            { "id": 1, "active": true, "note": "it's C:\\tmp", "a`b": "x\"y" },
+            { "id": 2, "active": false, "note": null, "a`b": "z" },
+            { "id": 3, "active": true, "note": "ok", "a`b": "w" },
+        ]);
+
+        assert_eq!(
+            to_sql_inserts(&records, "app.events", SqlDialect::Mysql, 2),
+            r#"INSERT INTO `app`.`events` (`id`, `active`, `note`, `a``b`) VALUES
+  (1, TRUE, 'it''s C:\\tmp', 'x"y'),
+  (2, FALSE, NULL, 'z');
+INSERT INTO `app`.`events` (`id`, `active`, `note`, `a``b`) VALUES
+  (3, TRUE, 'ok', 'w');
+"#
+        );
+        assert_eq!(
+            to_sql_inserts(&records, "events", SqlDialect::Sqlite, 1000),
+            r#"INSERT INTO "events" ("id", "active", "note", "a`b") VALUES
+  (1, 1, 'it''s C:\tmp', 'x"y'),
+  (2, 0, NULL, 'z'),
+  (3, 1, 'ok', 'w');
+"#
+        );
+    }
+
+    #[test]
+    fn inserts_values_and_empty_records() {
+        assert_eq!(
+            to_sql_inserts(&json!([1, "a"]), "t", SqlDialect::Postgres, 1000),
+            "INSERT INTO \"t\" (\"value\") VALUES\n  (1),\n  ('a');\n"
+        );
+        assert_eq!(
+            to_sql_inserts(&json!([{}, {}]), "t", SqlDialect::Postgres, 1000),
+            "INSERT INTO \"t\" DEFAULT VALUES;\nINSERT INTO \"t\" DEFAULT VALUES;\n"
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! Profiling the values found at each path of the input records.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use crate::{path, DistinctCount};
+
+/// The number of most common values reported for each field.
+const TOP_VALUES: usize = 5;
+
+/// The number of distinct values counted at each path to find the most common ones, beyond
+/// which values not found before are no longer counted, so that memory use is bounded.
+const MAX_COUNTED_VALUES: usize = 10_000;
+
+/// The distribution of a set of numbers.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct Distribution {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+}
+
+impl Distribution {
+    fn from_values(mut values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        // nearest-rank percentiles, so that every percentile is a value that was seen
+        let percentile = |p: f64| {
+            let rank = (p * values.len() as f64).ceil() as usize;
+            values[rank.clamp(1, values.len()) - 1]
+        };
+        Some(Distribution {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            p50: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+        })
+    }
+}
+
+impl Display for Distribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "min {}, mean {:.2}, p50 {}, p90 {}, p99 {}, max {}",
+            self.min, self.mean, self.p50, self.p90, self.p99, self.max
+        )
+    }
+}
+
+/// A value, written as JSON, and the number of times it occurs.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct ValueCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Statistics about the values found at one path of the input records.
+#[derive(PartialEq, Debug, Serialize)]
+pub struct FieldStats {
+    /// The path of the field, e.g. `users[].address.city`.
+    pub path: String,
+    /// The number of times the field occurs, including as null.
+    pub count: usize,
+    /// The number of times the field is null.
+    pub nulls: usize,
+    /// The number of distinct strings, numbers and booleans found at the path, estimated
+    /// beyond a few hundred of them (see `DistinctCount`).
+    pub distinct: usize,
+    /// The distribution of the numbers found at the path, if any.
+    pub numbers: Option<Distribution>,
+    /// The distribution of the lengths (in characters) of the strings found at the path, if any.
+    pub string_lengths: Option<Distribution>,
+    /// The most common strings, numbers and booleans found at the path.
+    pub top_values: Vec<ValueCount>,
+}
+
+impl FieldStats {
+    /// Returns the share of occurrences of the field that are null.
+    pub fn null_ratio(&self) -> f64 {
+        self.nulls as f64 / self.count as f64
+    }
+}
+
+impl Display for FieldStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", path::display(&self.path))?;
+        write!(
+            f,
+            "  count: {} (null: {:.1}%)",
+            self.count,
+            self.null_ratio() * 100.0
+        )?;
+        if self.distinct > 0 {
+            write!(f, "\n  distinct: {}", self.distinct)?;
+        }
+        if let Some(numbers) = &self.numbers {
+            write!(f, "\n  numbers: {}", numbers)?;
+        }
+        if let Some(lengths) = &self.string_lengths {
+            write!(f, "\n  string lengths: {}", lengths)?;
+        }
+        if !self.top_values.is_empty() {
+            let top = self
+                .top_values
+                .iter()
+                .map(|top| format!("{} ({})", top.value, top.count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            write!(f, "\n  top values: {}", top)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Accumulator {
+    count: usize,
+    nulls: usize,
+    numbers: Vec<f64>,
+    string_lengths: Vec<f64>,
+    distinct: DistinctCount,
+    values: HashMap<String, usize>,
+}
+
+impl Accumulator {
+    fn count_value(&mut self, value: &serde_json::Value) {
+        let value = value.to_string();
+        self.distinct.insert(&value);
+        if let Some(count) = self.values.get_mut(&value) {
+            *count += 1;
+        } else if self.values.len() < MAX_COUNTED_VALUES {
+            self.values.insert(value, 1);
+        }
+    }
+}
+
+fn accumulate(
+    value: &serde_json::Value,
+    current_path: &str,
+    fields: &mut BTreeMap<String, Accumulator>,
+) {
+    let field = fields.entry(current_path.to_owned()).or_default();
+    field.count += 1;
+    match value {
+        serde_json::Value::Null => field.nulls += 1,
+        serde_json::Value::Bool(_) => field.count_value(value),
+        serde_json::Value::Number(n) => {
+            field.numbers.extend(n.as_f64());
+            field.count_value(value);
+        }
+        serde_json::Value::String(s) => {
+            field.string_lengths.push(s.chars().count() as f64);
+            field.count_value(value);
+        }
+        serde_json::Value::Array(items) => {
+            let elements_path = path::elements(current_path);
+            for item in items {
+                accumulate(item, &elements_path, fields);
+            }
+        }
+        serde_json::Value::Object(object) => {
+            for (k, v) in object {
+                accumulate(v, &path::key(current_path, k), fields);
+            }
+        }
+    }
+}
+
+/// Profiles the values found at each path of `records`: how often they occur and are null,
+/// how many distinct values there are, the distributions of numbers and of string lengths,
+/// and the most common values. The statistics are sorted by path. They are exact, except for
+/// the number of distinct values beyond a few hundred of them, which is estimated, and the most
+/// common values of paths with more than 10,000 distinct values, which are counted among the
+/// first 10,000 found.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::field_stats;
+/// use serde_json::json;
+///
+/// let records = vec![
+///     json!({ "age": 30, "name": "Alice" }),
+///     json!({ "age": 40, "name": null }),
+///     json!({ "age": 30, "name": "Bob" }),
+/// ];
+///
+/// let stats = field_stats(&records);
+///
+/// assert_eq!(stats[0].path, "age");
+/// assert_eq!(stats[0].distinct, 2);
+/// assert_eq!(stats[0].numbers.as_ref().unwrap().p50, 30.0);
+/// assert_eq!(stats[0].top_values[0].value, "30");
+/// assert_eq!(stats[0].top_values[0].count, 2);
+/// assert_eq!(stats[1].nulls, 1);
+/// ```
+pub fn field_stats(records: &[serde_json::Value]) -> Vec<FieldStats> {
+    let mut fields = BTreeMap::new();
+    for record in records {
+        accumulate(record, "", &mut fields);
+    }
+    fields
+        .into_iter()
+        // the root of the records is only a field of its own when it holds values
+        .filter(|(path, field)| !path.is_empty() || field.nulls > 0 || !field.values.is_empty())
+        .map(|(path, field)| {
+            let distinct = field.distinct.estimate();
+            let mut top_values = field.values.into_iter().collect::<Vec<_>>();
+            top_values.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+            top_values.truncate(TOP_VALUES);
+            let top_values = top_values
+                .into_iter()
+                .map(|(value, count)| ValueCount { value, count })
+                .collect();
+            FieldStats {
+                path,
+                count: field.count,
+                nulls: field.nulls,
+                distinct,
+                numbers: Distribution::from_values(field.numbers),
+                string_lengths: Distribution::from_values(field.string_lengths),
+                top_values,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn profiles_nested_fields_and_percentiles() {
+        let records = (1..=100)
+            .map(|n| json!({ "n": n, "tags": ["a", if n % 2 == 0 { "even" } else { "odd" }] }))
+            .collect::<Vec<_>>();
+
+        let stats = field_stats(&records);
+
+        assert_eq!(
+            stats
+                .iter()
+                .map(|field| field.path.as_str())
+                .collect::<Vec<_>>(),
+            vec!["n", "tags", "tags[]"]
+        );
+        assert_eq!(
+            stats[0].numbers,
+            Some(Distribution {
+                min: 1.0,
+                max: 100.0,
+                mean: 50.5,
+                p50: 50.0,
+                p90: 90.0,
+                p99: 99.0
+            })
+        );
+        assert_eq!(stats[1].count, 100);
+        assert_eq!(stats[1].distinct, 0);
+        assert_eq!(stats[2].count, 200);
+        assert_eq!(
+            stats[2].top_values,
+            vec![
+                ValueCount {
+                    value: "\"a\"".to_owned(),
+                    count: 100
+                },
+                ValueCount {
+                    value: "\"even\"".to_owned(),
+                    count: 50
+                },
+                ValueCount {
+                    value: "\"odd\"".to_owned(),
+                    count: 50
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Streaming produced records out as JSON lines at a steady rate, for as long as they are
+//! wanted, like a load generator would.
+
+use std::time::{Duration, Instant};
+
+use crate::{produce::Records, DrivelError, ProduceOptions, SchemaState};
+
+/// How records are streamed by `produce_stream`: how fast, and for how long.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{produce_stream, ProduceOptions, SchemaState, StreamOptions};
+///
+/// let stream = StreamOptions::new().rate(1000.0).limit(5);
+/// let mut output = vec![];
+/// let written = produce_stream(&SchemaState::Boolean, &ProduceOptions::new(), &stream, &mut output)
+///     .unwrap();
+///
+/// assert_eq!(written, 5);
+/// assert_eq!(String::from_utf8(output).unwrap().lines().count(), 5);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct StreamOptions {
+    rate: Option<f64>,
+    duration: Option<Duration>,
+    limit: Option<usize>,
+}
+
+impl StreamOptions {
+    /// Returns the default options: records streamed as fast as they are produced, forever.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of records written per second. Default = as many as can be produced.
+    pub fn rate(mut self, records_per_second: f64) -> Self {
+        self.rate = (records_per_second > 0.0).then_some(records_per_second);
+        self
+    }
+
+    /// Stops streaming once `duration` has passed. Default = never.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Stops streaming once `limit` records are written. Default = no limit.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+/// Produces records of `schema`, the elements of an array at the root or otherwise values of
+/// the schema itself, at the rate and until the duration or limit of `stream`, handing each to
+/// `emit`, which returns false to stop early. Returns the number of records emitted.
+pub(crate) fn stream_records(
+    schema: &SchemaState,
+    options: &ProduceOptions,
+    stream: &StreamOptions,
+    mut emit: impl FnMut(serde_json::Value) -> Result<bool, DrivelError>,
+) -> Result<usize, DrivelError> {
+    let mut records = Records::new(schema, options)?;
+    let started = Instant::now();
+    let mut emitted = 0;
+    while stream.limit.is_none_or(|limit| emitted < limit) {
+        if let Some(rate) = stream.rate {
+            // records are emitted on a schedule, so that slow records do not lower the rate
+            let due = started + Duration::from_secs_f64(emitted as f64 / rate);
+            if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                if stream
+                    .duration
+                    .is_some_and(|duration| due.duration_since(started) >= duration)
+                {
+                    break;
+                }
+                std::thread::sleep(wait);
+            }
+        }
+        if stream
+            .duration
+            .is_some_and(|duration| started.elapsed() >= duration)
+        {
+            break;
+        }
+
+        if !emit(records.next_record()?)? {
+            break;
+        }
+        emitted += 1;
+    }
+    Ok(emitted)
+}
+
+/// Writes records of `schema`, the elements of an array at the root or otherwise values of the
+/// schema itself, to `writer` as JSON lines, flushing it after every record, at the rate and
+/// until the duration or limit of `stream`. Returns the number of records written.
+///
+/// Records are produced with `options`, apart from its count, and relations, which need the
+/// data whole. Streaming stops early, without an error, when the reader at the other end of
+/// `writer` goes away, e.g. when it is piped into `head`.
+pub fn produce_stream(
+    schema: &SchemaState,
+    options: &ProduceOptions,
+    stream: &StreamOptions,
+    writer: &mut impl std::io::Write,
+) -> Result<usize, DrivelError> {
+    stream_records(schema, options, stream, |record| {
+        let mut line = serde_json::to_vec(&record).map_err(std::io::Error::from)?;
+        line.push(b'\n');
+        match writer.write_all(&line).and_then(|_| writer.flush()) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_records_at_the_rate_until_the_duration_has_passed() {
+        let stream = StreamOptions::new()
+            .rate(100.0)
+            .duration(Duration::from_millis(200));
+        let mut output = vec![];
+        let started = Instant::now();
+        let written = produce_stream(
+            &SchemaState::Boolean,
+            &ProduceOptions::new(),
+            &stream,
+            &mut output,
+        )
+        .unwrap();
+
+        assert!(started.elapsed() >= Duration::from_millis(190));
+        assert!((15..=20).contains(&written), "{} records written", written);
+        assert_eq!(String::from_utf8(output).unwrap().lines().count(), written);
+    }
+}
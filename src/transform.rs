@@ -0,0 +1,638 @@
+//! Corrections made to a schema by path after it was inferred or read, like renaming fields or
+//! forcing their types.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+
+use crate::{
+    infer::merge,
+    infer_string::infer_string_type,
+    path::{self, Step},
+    Detectors, DrivelError, NumberType, PinnedType, SchemaDefaults, SchemaState, StringType,
+    DEFAULT_MAX_SAMPLES,
+};
+
+/// A correction made to the schema at a path.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    /// Renames the field, keeping its place among the other fields.
+    Rename(String),
+    /// Removes the field.
+    Drop,
+    /// Makes the field required.
+    Required,
+    /// Makes the field optional.
+    Optional,
+    /// Forces the type of the values, keeping what was inferred of them if they already have
+    /// it, and optionally sets their range as `Range` does.
+    Type {
+        pinned: PinnedType,
+        range: Option<(f64, f64)>,
+    },
+    /// Sets the range of numbers, or of the lengths of strings, arrays and maps.
+    Range(f64, f64),
+    /// Makes the values nullable, or no longer nullable.
+    Nullable(bool),
+    /// Makes strings an enum of the strings seen.
+    Enum,
+    /// Makes an enum of strings strings of any value, like its variants.
+    FreeString,
+}
+
+impl Transform {
+    /// Whether the transform applies to a field of an object, rather than to any value.
+    fn applies_to_field(&self) -> bool {
+        matches!(
+            self,
+            Transform::Rename(_) | Transform::Drop | Transform::Required | Transform::Optional
+        )
+    }
+}
+
+/// Parses a range written as `min..max`.
+fn parse_range(s: &str) -> Result<(f64, f64), String> {
+    let invalid = || format!("expected a range like `0..120`, found {:?}", s);
+    let (min, max) = s.split_once("..").ok_or_else(invalid)?;
+    let min: f64 = min.trim().parse().map_err(|_| invalid())?;
+    let max: f64 = max.trim().parse().map_err(|_| invalid())?;
+    if min > max {
+        return Err(format!("the range {:?} is empty", s));
+    }
+    Ok((min, max))
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    /// Parses a transform written as `rename <name>`, `drop`, `required`, `optional`,
+    /// `nullable`, `non-nullable`, `enum`, `free-string`, `range <min>..<max>`, or the name of a
+    /// type optionally followed by a range, e.g. `integer 0..120`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let operation = words.next().ok_or("expected a transform")?;
+        let argument = words.next();
+        if words.next().is_some() {
+            return Err(format!("unexpected words after {:?}", operation));
+        }
+        match (operation, argument) {
+            ("rename", Some(name)) => Ok(Transform::Rename(name.to_owned())),
+            ("rename", None) => Err("expected the new name of the field".to_owned()),
+            ("drop", None) => Ok(Transform::Drop),
+            ("required", None) => Ok(Transform::Required),
+            ("optional", None) => Ok(Transform::Optional),
+            ("nullable", None) => Ok(Transform::Nullable(true)),
+            ("non-nullable", None) => Ok(Transform::Nullable(false)),
+            ("enum", None) => Ok(Transform::Enum),
+            ("free-string", None) => Ok(Transform::FreeString),
+            ("range", Some(range)) => {
+                let (min, max) = parse_range(range)?;
+                Ok(Transform::Range(min, max))
+            }
+            ("range", None) => Err("expected a range like `0..120`".to_owned()),
+            (
+                "drop" | "required" | "optional" | "nullable" | "non-nullable" | "enum"
+                | "free-string",
+                Some(_),
+            ) => {
+                Err(format!("{} takes no arguments", operation))
+            }
+            (pinned, range) => Ok(Transform::Type {
+                pinned: pinned.parse().map_err(|err| {
+                    format!(
+                        "{}, or one of rename, drop, required, optional, nullable, non-nullable, enum, free-string or range",
+                        err
+                    )
+                })?,
+                range: range.map(parse_range).transpose()?,
+            }),
+        }
+    }
+}
+
+impl Display for Transform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transform::Rename(name) => write!(f, "rename {}", name),
+            Transform::Drop => write!(f, "drop"),
+            Transform::Required => write!(f, "required"),
+            Transform::Optional => write!(f, "optional"),
+            Transform::Type { pinned, range } => match range {
+                Some((min, max)) => write!(f, "{} {}..{}", pinned, min, max),
+                None => write!(f, "{}", pinned),
+            },
+            Transform::Range(min, max) => write!(f, "range {}..{}", min, max),
+            Transform::Nullable(true) => write!(f, "nullable"),
+            Transform::Nullable(false) => write!(f, "non-nullable"),
+            Transform::Enum => write!(f, "enum"),
+            Transform::FreeString => write!(f, "free-string"),
+        }
+    }
+}
+
+/// Corrections made to a schema by path, in order, such as renaming fields, dropping them,
+/// forcing their types and setting their ranges. Each is written as `path: transform`, e.g.
+/// `user.age: integer 0..120`; see [`Transform`] for the transforms.
+///
+/// Paths separate object fields with `.` and refer to array elements with `[]`. When the schema
+/// describes an array at its root, paths are relative to its elements, as they are when pinning
+/// types.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transforms {
+    transforms: Vec<(String, Transform)>,
+    defaults: SchemaDefaults,
+}
+
+impl Transforms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Bounds the numbers and strings that a change of type without a range makes of values of
+    /// another type by `defaults`. Default = `SchemaDefaults::default()`.
+    pub fn set_defaults(&mut self, defaults: SchemaDefaults) {
+        self.defaults = defaults;
+    }
+
+    /// Adds a transform of the schema at `path`, made after those added before it.
+    pub fn push(&mut self, path: &str, transform: Transform) {
+        self.transforms.push((path.to_owned(), transform));
+    }
+
+    /// Adds a transform written as `path: transform`, e.g. `user.name: rename full_name`.
+    pub fn add(&mut self, transform: &str) -> Result<(), DrivelError> {
+        let (path, transform) =
+            transform
+                .split_once(':')
+                .ok_or_else(|| DrivelError::InvalidTransform {
+                    path: transform.trim().to_owned(),
+                    message: "expected a transform like `user.age: integer 0..120`".to_owned(),
+                })?;
+        let path = path.trim();
+        let transform = transform
+            .parse()
+            .map_err(|message| DrivelError::InvalidTransform {
+                path: path.to_owned(),
+                message,
+            })?;
+        self.push(path, transform);
+        Ok(())
+    }
+
+    /// Parses transforms written one per line as `path: transform`. Empty lines and lines
+    /// starting with `#` are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{PinnedType, Transform, Transforms};
+    ///
+    /// let transforms = Transforms::from_lines(
+    ///     "# corrections of the users API
+    ///      user.age: integer 0..120
+    ///      user.name: rename full_name",
+    /// )
+    /// .unwrap();
+    ///
+    /// let mut expected = Transforms::new();
+    /// expected.push(
+    ///     "user.age",
+    ///     Transform::Type { pinned: PinnedType::Integer, range: Some((0.0, 120.0)) },
+    /// );
+    /// expected.push("user.name", Transform::Rename("full_name".to_owned()));
+    /// assert_eq!(transforms, expected);
+    /// ```
+    pub fn from_lines(document: &str) -> Result<Self, DrivelError> {
+        let mut transforms = Self::new();
+        for line in document.lines().map(str::trim) {
+            if !line.is_empty() && !line.starts_with('#') {
+                transforms.add(line)?;
+            }
+        }
+        Ok(transforms)
+    }
+
+    /// Applies the transforms to `schema` in order. A transform of a path that the schema has no
+    /// values at is an error, as is one that does not apply to the values there.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use drivel::{infer_schema, InferenceOptions, Transforms};
+    /// use serde_json::json;
+    ///
+    /// let mut schema = infer_schema(
+    ///     json!({ "user": { "age": 31, "tmp": true } }),
+    ///     &InferenceOptions::default(),
+    /// );
+    /// let transforms =
+    ///     Transforms::from_lines("user.age: range 18..99\nuser.tmp: drop").unwrap();
+    /// transforms.apply(&mut schema).unwrap();
+    ///
+    /// assert_eq!(
+    ///     schema.to_string_pretty(),
+    ///     "{\n  \"user\": {\n    \"age\": int (18-99)\n  }\n}"
+    /// );
+    /// ```
+    pub fn apply(&self, schema: &mut SchemaState) -> Result<(), DrivelError> {
+        let root_is_array = matches!(schema, SchemaState::Array { .. });
+        for (path, transform) in &self.transforms {
+            let steps = path::from_root(path::parse_steps(path), root_is_array);
+            let invalid = |message: String| DrivelError::InvalidTransform {
+                path: path.to_owned(),
+                message,
+            };
+            let mut result = Ok(());
+            let found = match (transform.applies_to_field(), steps.split_last()) {
+                (true, Some((Step::Key(key), parent))) => {
+                    visit_schemas(schema, parent, transform, &mut |schema| match schema {
+                        SchemaState::Object { required, optional }
+                            if required.contains_key(key) || optional.contains_key(key) =>
+                        {
+                            result = result
+                                .clone()
+                                .and_then(|_| transform_field(required, optional, key, transform));
+                            true
+                        }
+                        _ => false,
+                    })
+                }
+                (true, _) => return Err(invalid(format!("{} only applies to fields", transform))),
+                (false, _) => visit_schemas(schema, &steps, transform, &mut |schema| {
+                    result = result
+                        .clone()
+                        .and_then(|_| transform_value(schema, transform, &self.defaults));
+                    true
+                }),
+            };
+            if found == 0 {
+                return Err(invalid("no values found at this path".to_owned()));
+            }
+            result.map_err(invalid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Calls `f` with every schema at `steps` within `schema`, looking into the branches of unions
+/// and past nullability (unless `transform` changes it), and returns the number of schemas for
+/// which `f` returned true.
+fn visit_schemas(
+    schema: &mut SchemaState,
+    steps: &[Step],
+    transform: &Transform,
+    f: &mut impl FnMut(&mut SchemaState) -> bool,
+) -> usize {
+    match (steps.split_first(), schema) {
+        (None, schema @ SchemaState::Nullable(_))
+            if matches!(transform, Transform::Nullable(_)) =>
+        {
+            usize::from(f(schema))
+        }
+        (_, SchemaState::Nullable(inner)) => visit_schemas(inner, steps, transform, f),
+        (_, SchemaState::Union(branches)) => branches
+            .iter_mut()
+            .map(|branch| visit_schemas(branch, steps, transform, f))
+            .sum(),
+        (None, schema) => usize::from(f(schema)),
+        (Some((Step::Key(key), rest)), SchemaState::Object { required, optional }) => {
+            match required.get_mut(key).or(optional.get_mut(key)) {
+                Some(schema) => visit_schemas(schema, rest, transform, f),
+                None => 0,
+            }
+        }
+        (Some((Step::Elements, rest)), SchemaState::Array { schema, .. }) => {
+            visit_schemas(schema, rest, transform, f)
+        }
+        (Some((Step::Elements, rest)), SchemaState::Tuple(elements)) => elements
+            .iter_mut()
+            .map(|element| visit_schemas(element, rest, transform, f))
+            .sum(),
+        _ => 0,
+    }
+}
+
+fn transform_field(
+    required: &mut IndexMap<String, SchemaState>,
+    optional: &mut IndexMap<String, SchemaState>,
+    key: &str,
+    transform: &Transform,
+) -> Result<(), String> {
+    match transform {
+        Transform::Rename(name) => {
+            if name != key && (required.contains_key(name) || optional.contains_key(name)) {
+                return Err(format!("a field named {:?} already exists", name));
+            }
+            for fields in [required, optional] {
+                if let Some(idx) = fields.get_index_of(key) {
+                    let (_, schema) = fields.shift_remove_index(idx).unwrap();
+                    fields.shift_insert(idx, name.clone(), schema);
+                }
+            }
+        }
+        Transform::Drop => {
+            required.shift_remove(key);
+            optional.shift_remove(key);
+        }
+        Transform::Required => {
+            if let Some(schema) = optional.shift_remove(key) {
+                required.insert(key.to_owned(), schema);
+            }
+        }
+        Transform::Optional => {
+            if let Some(schema) = required.shift_remove(key) {
+                optional.insert(key.to_owned(), schema);
+            }
+        }
+        _ => unreachable!("only transforms of fields are applied to fields"),
+    }
+    Ok(())
+}
+
+/// Returns the integer bounds of a range, which must be whole numbers.
+fn integer_range(min: f64, max: f64) -> Result<(i128, i128), String> {
+    if min.fract() != 0.0 || max.fract() != 0.0 {
+        return Err(format!(
+            "the range of integers must be whole numbers, found {}..{}",
+            min, max
+        ));
+    }
+    Ok((min as i128, max as i128))
+}
+
+/// Returns the bounds of a range of lengths, which must be whole and not negative.
+fn length_range(min: f64, max: f64) -> Result<(usize, usize), String> {
+    let (min, max) = integer_range(min, max)?;
+    if min < 0 {
+        return Err("lengths cannot be negative".to_owned());
+    }
+    Ok((min as usize, max as usize))
+}
+
+fn transform_value(
+    schema: &mut SchemaState,
+    transform: &Transform,
+    defaults: &SchemaDefaults,
+) -> Result<(), String> {
+    match transform {
+        Transform::Type { pinned, range } => {
+            let current = std::mem::replace(schema, SchemaState::Initial);
+            *schema = match current {
+                // a field that was only seen null keeps producing nulls along with the new type
+                SchemaState::Null => {
+                    SchemaState::Nullable(Box::new(pinned.narrow(SchemaState::Null, defaults)))
+                }
+                current => pinned.narrow(current, defaults),
+            };
+            match range {
+                Some((min, max)) => match schema {
+                    SchemaState::Nullable(inner) => set_range(inner, *min, *max),
+                    schema => set_range(schema, *min, *max),
+                },
+                None => Ok(()),
+            }
+        }
+        Transform::Range(min, max) => set_range(schema, *min, *max),
+        Transform::Nullable(true) => {
+            if !matches!(schema, SchemaState::Null | SchemaState::Nullable(_)) {
+                let inner = std::mem::replace(schema, SchemaState::Initial);
+                *schema = SchemaState::Nullable(Box::new(inner));
+            }
+            Ok(())
+        }
+        Transform::Nullable(false) => match std::mem::replace(schema, SchemaState::Initial) {
+            SchemaState::Nullable(inner) => {
+                *schema = *inner;
+                Ok(())
+            }
+            SchemaState::Null => {
+                *schema = SchemaState::Null;
+                Err("only nulls were seen here; give it a type instead".to_owned())
+            }
+            other => {
+                *schema = other;
+                Ok(())
+            }
+        },
+        Transform::Enum => match schema {
+            SchemaState::String(StringType::Enum { .. }) => Ok(()),
+            SchemaState::String(StringType::Unknown { strings_seen, .. }) => {
+                if strings_seen.is_empty() {
+                    return Err("no strings were seen to make an enum of".to_owned());
+                }
+                let variants = strings_seen.iter().cloned().collect();
+                *schema = SchemaState::String(StringType::Enum { variants });
+                Ok(())
+            }
+            _ => Err("only strings of no particular format can be made an enum".to_owned()),
+        },
+        Transform::FreeString => match schema {
+            SchemaState::String(StringType::Enum { variants }) => {
+                let mut variants = variants.iter().collect::<Vec<_>>();
+                variants.sort();
+                *schema = variants
+                    .into_iter()
+                    .map(|variant| {
+                        SchemaState::String(infer_string_type(
+                            variant,
+                            &Detectors::new(),
+                            DEFAULT_MAX_SAMPLES,
+                            None,
+                        ))
+                    })
+                    .fold(SchemaState::Initial, |merged, variant| {
+                        merge(merged, variant, DEFAULT_MAX_SAMPLES)
+                    });
+                Ok(())
+            }
+            _ => Err("only an enum of strings can be made free strings".to_owned()),
+        },
+        _ => unreachable!("transforms of fields are not applied to values"),
+    }
+}
+
+fn set_range(schema: &mut SchemaState, min: f64, max: f64) -> Result<(), String> {
+    match schema {
+        SchemaState::Number(number_type) => match number_type {
+            NumberType::Integer { .. }
+            | NumberType::BigInteger { .. }
+            | NumberType::Enum { .. } => {
+                let (min, max) = integer_range(min, max)?;
+                *number_type = NumberType::integers(min, max);
+            }
+            NumberType::Decimal {
+                min: decimal_min,
+                max: decimal_max,
+                ..
+            } => {
+                *decimal_min = min;
+                *decimal_max = max;
+            }
+            NumberType::Float {
+                min: float_min,
+                max: float_max,
+                exclusive_min,
+                exclusive_max,
+                ..
+            } => {
+                *float_min = min;
+                *float_max = max;
+                *exclusive_min = false;
+                *exclusive_max = false;
+            }
+        },
+        SchemaState::String(StringType::Unknown {
+            min_length,
+            max_length,
+            structure,
+            ..
+        }) => {
+            let (min, max) = length_range(min, max)?;
+            *min_length = Some(min);
+            *max_length = Some(max);
+            // a structure fixes the length of strings, so it no longer describes them
+            *structure = None;
+        }
+        SchemaState::Array {
+            min_length,
+            max_length,
+            ..
+        }
+        | SchemaState::Map {
+            min_length,
+            max_length,
+            ..
+        } => {
+            let (min, max) = length_range(min, max)?;
+            *min_length = min;
+            *max_length = max;
+        }
+        _ => return Err("a range only applies to numbers, strings, arrays and maps".to_owned()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema, infer_schema_from_iter, InferenceOptions};
+
+    #[test]
+    fn renames_drops_and_retypes_fields_in_order() {
+        // paths are relative to the records of an array at the root
+        let mut schema = infer_schema(
+            json!([
+                { "id": "a1", "user": { "age": "31", "name": "Ann", "tmp": 1 }, "tags": [1] },
+                { "id": "b2", "user": { "age": null, "name": "Bob" }, "tags": [] }
+            ]),
+            &InferenceOptions::default(),
+        );
+        let transforms = Transforms::from_lines(
+            "user.name: rename full_name
+             user.full_name: optional
+             user.tmp: drop
+             user.age: integer 0..120
+             tags[]: range 5..9
+             tags: range 0..3",
+        )
+        .unwrap();
+        transforms.apply(&mut schema).unwrap();
+
+        assert_eq!(
+            schema.to_string_pretty(),
+            "[\n  {\n    \"id\": string (2),\n    \"user\": {\n      \"age\": nullable int (0-120),\n      \"full_name\": optional string (3)\n    },\n    \"tags\": [\n      int (5-9)\n    ] (0-3)\n  }\n] (2)"
+        );
+    }
+
+    #[test]
+    fn reports_transforms_that_cannot_be_applied() {
+        let schema = infer_schema_from_iter(
+            vec![json!({ "id": "a1", "active": true })],
+            &InferenceOptions::default(),
+        );
+        let error_in = |mut schema: SchemaState, transform: &str| {
+            let mut transforms = Transforms::new();
+            transforms
+                .add(transform)
+                .and_then(|_| transforms.apply(&mut schema))
+                .unwrap_err()
+                .to_string()
+        };
+        let error = |transform: &str| error_in(schema.clone(), transform);
+
+        assert_eq!(
+            error("missing: drop"),
+            "invalid transform for missing: no values found at this path"
+        );
+        assert_eq!(
+            error("active: range 0..1"),
+            "invalid transform for active: a range only applies to numbers, strings, arrays and maps"
+        );
+        assert_eq!(
+            error("id: rename active"),
+            "invalid transform for id: a field named \"active\" already exists"
+        );
+        assert_eq!(
+            error("id: uuid4"),
+            "invalid transform for id: unknown type \"uuid4\", expected one of string, uuid, email, url, hostname, date, datetime, integer, float, boolean, or one of rename, drop, required, optional, nullable, non-nullable, enum, free-string or range"
+        );
+        assert_eq!(
+            error("id drop"),
+            "invalid transform for id drop: expected a transform like `user.age: integer 0..120`"
+        );
+        assert_eq!(
+            error_in(SchemaState::Null, "id: drop"),
+            "invalid transform for id: no values found at this path"
+        );
+    }
+
+    #[test]
+    fn toggles_nullability_and_enums() {
+        let mut schema = infer_schema_from_iter(
+            vec![
+                json!({ "status": "open", "note": null, "id": 1 }),
+                json!({ "status": "closed", "note": "late", "id": 2 }),
+            ],
+            &InferenceOptions::default(),
+        );
+        let transforms = Transforms::from_lines(
+            "status: enum
+             note: non-nullable
+             id: nullable",
+        )
+        .unwrap();
+        transforms.apply(&mut schema).unwrap();
+        let SchemaState::Object { required, .. } = &schema else {
+            panic!("expected an object, got {:?}", schema)
+        };
+        assert_eq!(
+            required["status"],
+            SchemaState::String(StringType::Enum {
+                variants: ["open", "closed"].map(str::to_owned).into()
+            })
+        );
+        assert!(matches!(required["note"], SchemaState::String(_)));
+        assert_eq!(
+            required["id"],
+            SchemaState::Nullable(Box::new(SchemaState::Number(NumberType::Integer {
+                min: 1,
+                max: 2
+            })))
+        );
+
+        let mut transforms = Transforms::new();
+        transforms.push("status", Transform::FreeString);
+        transforms.apply(&mut schema).unwrap();
+        let SchemaState::Object { required, .. } = &schema else {
+            panic!("expected an object, got {:?}", schema)
+        };
+        assert_eq!(required["status"].to_string_pretty(), "string (4-6)");
+    }
+}
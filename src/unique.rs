@@ -0,0 +1,397 @@
+//! Keeping the values produced at some paths unique across all of the data produced, like the
+//! primary keys of records.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    overrides::OverrideNode, path, Cancelled, DrivelError, NumberType, Override, SchemaState,
+    StringType,
+};
+
+/// The number of values produced at random for a unique path before giving up, or, for
+/// integers, before falling back to the lowest integer in the range not produced yet.
+const MAX_RANDOM_ATTEMPTS: usize = 100;
+
+/// Returns whether `key` names the identifier of a record, like `id` or `_id`.
+fn is_id_key(key: &str) -> bool {
+    key.eq_ignore_ascii_case("id") || key == "_id"
+}
+
+/// Returns whether values of `schema` are identifiers by their format, like UUIDs.
+fn is_identifier(schema: &SchemaState) -> bool {
+    matches!(
+        schema,
+        SchemaState::String(
+            StringType::UUID | StringType::Ulid | StringType::Ksuid | StringType::ObjectId
+        )
+    )
+}
+
+fn is_scalar(schema: &SchemaState) -> bool {
+    matches!(schema, SchemaState::String(_) | SchemaState::Number(_))
+}
+
+/// Returns the range of the integers produced at a path, if they are integers from a range.
+fn integer_range(schema: &SchemaState, overrides: Option<&OverrideNode>) -> Option<(i128, i128)> {
+    match (overrides.and_then(|node| node.value.as_ref()), schema) {
+        (Some(Override::IntRange { min, max }), _) => Some(((*min).into(), (*max).into())),
+        (Some(_), _) => None,
+        (
+            None,
+            SchemaState::Number(
+                number_type @ (NumberType::Integer { .. } | NumberType::BigInteger { .. }),
+            ),
+        ) => number_type.integer_range(),
+        (None, _) => None,
+    }
+}
+
+/// The values produced so far at the paths whose values must be unique.
+pub(crate) struct UniqueValues<'a> {
+    /// The paths whose values must be unique, relative to the elements of an array at the root.
+    paths: HashSet<&'a str>,
+    /// Whether identifiers, fields named like `id` and values formatted like UUIDs, must be
+    /// unique too.
+    ids: bool,
+    /// The values produced at each unique path, serialized.
+    seen: HashMap<String, HashSet<String>>,
+    /// For each unique path of integers, the integer below which all integers in the range
+    /// were produced already.
+    next_integer: HashMap<String, i128>,
+}
+
+impl<'a> UniqueValues<'a> {
+    pub(crate) fn new(paths: &'a [String], ids: bool) -> Self {
+        Self {
+            paths: paths
+                .iter()
+                .map(|path| path.trim_start_matches("[]").trim_start_matches('.'))
+                .collect(),
+            ids,
+            seen: HashMap::new(),
+            next_integer: HashMap::new(),
+        }
+    }
+
+    /// Returns whether any values must be unique.
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.paths.is_empty() || self.ids
+    }
+
+    /// Replaces the values of a record at unique paths that were produced before, by values
+    /// produced with `regenerate` from the schema and overrides of the path.
+    pub(crate) fn enforce(
+        &mut self,
+        value: &mut serde_json::Value,
+        schema: &SchemaState,
+        overrides: Option<&OverrideNode>,
+        regenerate: &mut impl FnMut(
+            &SchemaState,
+            Option<&OverrideNode>,
+        ) -> Result<serde_json::Value, Cancelled>,
+    ) -> Result<(), DrivelError> {
+        self.enforce_inner(value, schema, overrides, "", false, regenerate)
+    }
+
+    fn enforce_inner(
+        &mut self,
+        value: &mut serde_json::Value,
+        schema: &SchemaState,
+        overrides: Option<&OverrideNode>,
+        current_path: &str,
+        is_id: bool,
+        regenerate: &mut impl FnMut(
+            &SchemaState,
+            Option<&OverrideNode>,
+        ) -> Result<serde_json::Value, Cancelled>,
+    ) -> Result<(), DrivelError> {
+        // nulls are never duplicates, like in the unique columns of a database
+        if value.is_null() {
+            return Ok(());
+        }
+        let schema = match schema {
+            SchemaState::Nullable(inner) => inner.as_ref(),
+            schema => schema,
+        };
+
+        let is_unique = self.paths.contains(current_path)
+            || (self.ids && ((is_id && is_scalar(schema)) || is_identifier(schema)));
+        if is_unique {
+            return self.make_unique(value, schema, overrides, current_path, regenerate);
+        }
+        if overrides.is_some_and(|node| node.value.is_some()) {
+            return Ok(());
+        }
+
+        match (schema, value) {
+            (SchemaState::Array { schema, .. }, serde_json::Value::Array(items)) => {
+                let elements_path = path::elements(current_path);
+                let element_overrides = overrides.and_then(|node| node.elements.as_deref());
+                for item in items {
+                    self.enforce_inner(
+                        item,
+                        schema,
+                        element_overrides,
+                        &elements_path,
+                        false,
+                        regenerate,
+                    )?;
+                }
+            }
+            (SchemaState::Tuple(elements), serde_json::Value::Array(items)) => {
+                let elements_path = path::elements(current_path);
+                let element_overrides = overrides.and_then(|node| node.elements.as_deref());
+                for (item, element) in items.iter_mut().zip(elements) {
+                    self.enforce_inner(
+                        item,
+                        element,
+                        element_overrides,
+                        &elements_path,
+                        false,
+                        regenerate,
+                    )?;
+                }
+            }
+            (SchemaState::Object { required, optional }, serde_json::Value::Object(map)) => {
+                for (k, v) in map.iter_mut() {
+                    if let Some(field_schema) = required.get(k).or_else(|| optional.get(k)) {
+                        self.enforce_inner(
+                            v,
+                            field_schema,
+                            overrides.and_then(|node| node.fields.get(k)),
+                            &path::key(current_path, k),
+                            is_id_key(k),
+                            regenerate,
+                        )?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Fails when a unique path of integers from a range holds fewer integers than the least
+    /// number of values that producing `count` records of `schema` produces there, so that
+    /// production can fail before any of it is written rather than once the range runs out.
+    pub(crate) fn check_ranges(
+        &self,
+        schema: &SchemaState,
+        overrides: Option<&OverrideNode>,
+        count: usize,
+    ) -> Result<(), DrivelError> {
+        self.check_ranges_inner(schema, overrides, "", false, count as u128)
+    }
+
+    fn check_ranges_inner(
+        &self,
+        schema: &SchemaState,
+        overrides: Option<&OverrideNode>,
+        current_path: &str,
+        is_id: bool,
+        produced: u128,
+    ) -> Result<(), DrivelError> {
+        let is_unique = self.paths.contains(current_path)
+            || (self.ids && ((is_id && is_scalar(schema)) || is_identifier(schema)));
+        if is_unique {
+            let Some((min, max)) = integer_range(schema, overrides) else {
+                return Ok(());
+            };
+            let available = max.abs_diff(min).saturating_add(1);
+            if produced > available {
+                return Err(DrivelError::NotUnique {
+                    path: path::display(current_path).to_owned(),
+                    message: format!(
+                        "the range {}-{} holds {} integers, fewer than the {} values to produce",
+                        min, max, available, produced
+                    ),
+                });
+            }
+            return Ok(());
+        }
+        if overrides.is_some_and(|node| node.value.is_some()) {
+            return Ok(());
+        }
+
+        // nullable values and optional fields may not be produced at all, so they are left out
+        // of the least number of values produced
+        match schema {
+            SchemaState::Array {
+                min_length, schema, ..
+            } => self.check_ranges_inner(
+                schema,
+                overrides.and_then(|node| node.elements.as_deref()),
+                &path::elements(current_path),
+                false,
+                produced.saturating_mul(*min_length as u128),
+            ),
+            SchemaState::Tuple(elements) => elements.iter().try_for_each(|element| {
+                self.check_ranges_inner(
+                    element,
+                    overrides.and_then(|node| node.elements.as_deref()),
+                    &path::elements(current_path),
+                    false,
+                    produced,
+                )
+            }),
+            SchemaState::Object { required, .. } => {
+                required.iter().try_for_each(|(k, field_schema)| {
+                    self.check_ranges_inner(
+                        field_schema,
+                        overrides.and_then(|node| node.fields.get(k)),
+                        &path::key(current_path, k),
+                        is_id_key(k),
+                        produced,
+                    )
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Replaces `value` if it was produced at `current_path` before: by values produced at
+    /// random, or, for integers in a range, by the lowest integer not produced yet.
+    fn make_unique(
+        &mut self,
+        value: &mut serde_json::Value,
+        schema: &SchemaState,
+        overrides: Option<&OverrideNode>,
+        current_path: &str,
+        regenerate: &mut impl FnMut(
+            &SchemaState,
+            Option<&OverrideNode>,
+        ) -> Result<serde_json::Value, Cancelled>,
+    ) -> Result<(), DrivelError> {
+        let seen = self.seen.entry(current_path.to_owned()).or_default();
+        if seen.insert(value.to_string()) {
+            return Ok(());
+        }
+        for _ in 0..MAX_RANDOM_ATTEMPTS {
+            let candidate = regenerate(schema, overrides)?;
+            if !candidate.is_null() && seen.insert(candidate.to_string()) {
+                *value = candidate;
+                return Ok(());
+            }
+        }
+
+        let not_unique = |message: String| DrivelError::NotUnique {
+            path: path::display(current_path).to_owned(),
+            message,
+        };
+        let Some((min, max)) = integer_range(schema, overrides) else {
+            return Err(not_unique(format!(
+                "no value that was not produced before was found in {} attempts",
+                MAX_RANDOM_ATTEMPTS
+            )));
+        };
+        let next = self
+            .next_integer
+            .entry(current_path.to_owned())
+            .or_insert(min);
+        while *next <= max {
+            let candidate = serde_json::Value::Number(crate::produce::integer_number(*next));
+            *next += 1;
+            if seen.insert(candidate.to_string()) {
+                *value = candidate;
+                return Ok(());
+            }
+        }
+        Err(not_unique(format!(
+            "every integer in the range {}-{} was produced already",
+            min, max
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::IndexMap;
+
+    use super::*;
+    use crate::{produce_with_options, ProduceOptions};
+
+    fn records_with_ids(ids: SchemaState) -> SchemaState {
+        SchemaState::Array {
+            min_length: 1,
+            max_length: 1,
+            unique: false,
+            schema: Box::new(SchemaState::Object {
+                required: IndexMap::from_iter([
+                    ("id".to_owned(), ids),
+                    (
+                        "tags".to_owned(),
+                        SchemaState::Array {
+                            min_length: 2,
+                            max_length: 2,
+                            unique: false,
+                            schema: Box::new(SchemaState::Number(NumberType::Integer {
+                                min: 0,
+                                max: 1000,
+                            })),
+                        },
+                    ),
+                ]),
+                optional: IndexMap::new(),
+            }),
+        }
+    }
+
+    fn distinct(values: impl Iterator<Item = serde_json::Value>) -> usize {
+        values
+            .map(|value| value.to_string())
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    #[test]
+    fn produces_every_integer_of_a_range_just_large_enough() {
+        let schema = records_with_ids(SchemaState::Number(NumberType::Integer {
+            min: 1,
+            max: 500,
+        }));
+        let options = ProduceOptions::new().count(500).seed(1).unique_ids(true);
+        let produced = produce_with_options(&schema, &options).unwrap();
+        let records = produced.as_array().unwrap();
+        assert_eq!(
+            distinct(records.iter().map(|record| record["id"].clone())),
+            500
+        );
+
+        let options = options.count(501);
+        assert!(matches!(
+            produce_with_options(&schema, &options),
+            Err(DrivelError::NotUnique { .. })
+        ));
+    }
+
+    #[test]
+    fn fails_before_writing_when_a_range_holds_too_few_integers() {
+        let schema = records_with_ids(SchemaState::Number(NumberType::Integer { min: 1, max: 3 }));
+        let options = ProduceOptions::new().count(4).unique_ids(true);
+        let mut output = vec![];
+
+        let result = crate::produce_to_writer_with_options(&schema, &options, &mut output);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "unable to produce unique values for id: the range 1-3 holds 3 integers, fewer than \
+             the 4 values to produce"
+        );
+        assert!(output.is_empty());
+        let options = options.count(3);
+        assert!(crate::produce_to_writer_with_options(&schema, &options, &mut output).is_ok());
+    }
+
+    #[test]
+    fn keeps_the_values_at_the_given_paths_unique() {
+        let schema = records_with_ids(SchemaState::Number(NumberType::Integer { min: 0, max: 1 }));
+        let options = ProduceOptions::new().count(400).seed(2).unique("[].tags[]");
+        let produced = produce_with_options(&schema, &options).unwrap();
+        let tags = produced
+            .as_array()
+            .unwrap()
+            .iter()
+            .flat_map(|record| record["tags"].as_array().unwrap().clone());
+        assert_eq!(distinct(tags), 800);
+    }
+}
@@ -0,0 +1,581 @@
+use std::fmt::Display;
+
+use crate::{
+    infer::{decimal_places, integer_value},
+    infer_string::{
+        dates, duration, email, epoch_seconds, extension, formatted_date, identifier, jwt_claims,
+        non_finite_float, phone_format, semver, split_path, text, time, url_host, uuid,
+    },
+    overrides::OverrideNode,
+    path, pattern, NumberType, Overrides, SchemaState, StringType,
+};
+
+/// A value that does not conform to the schema it was checked against.
+#[derive(PartialEq, Debug)]
+pub struct Violation {
+    /// The path of the offending value, e.g. `users[].address.city`.
+    pub path: String,
+    /// A description of how the value violates the schema.
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", path::display(&self.path), self.message)
+    }
+}
+
+fn in_range<T: PartialOrd>(value: T, min: Option<T>, max: Option<T>) -> bool {
+    min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+}
+
+fn check_string(s: &str, string_type: &StringType) -> Option<String> {
+    let conforms =
+        match string_type {
+            StringType::Unknown {
+                min_length,
+                max_length,
+                ..
+            } => {
+                let length = s.chars().count();
+                if !in_range(length, *min_length, *max_length) {
+                    return Some(format!(
+                        "string of length {} is outside of the expected length range",
+                        length
+                    ));
+                }
+                match (string_type.structure_pattern(), string_type.affixes()) {
+                    (Some(structure), _) => pattern::is_match(&format!("^(?:{})$", structure), s),
+                    (None, Some((prefix, suffix))) => s.starts_with(prefix) && s.ends_with(suffix),
+                    (None, None) => true,
+                }
+            }
+            StringType::IsoDate { range } => match dates(s) {
+                Some(StringType::IsoDate { range: Some(seen) }) => range
+                    .as_ref()
+                    .is_none_or(|range| range.contains(seen.start())),
+                _ => false,
+            },
+            StringType::DateTimeISO8601 { range } => chrono::DateTime::parse_from_rfc3339(s)
+                .is_ok_and(|date_time| {
+                    range
+                        .as_ref()
+                        .is_none_or(|range| range.contains(&date_time.to_utc()))
+                }),
+            StringType::DateTimeRFC2822 { range } => chrono::DateTime::parse_from_rfc2822(s)
+                .is_ok_and(|date_time| {
+                    range
+                        .as_ref()
+                        .is_none_or(|range| range.contains(&date_time.to_utc()))
+                }),
+            StringType::FormattedDate { format, range } => match formatted_date(s, *format) {
+                Some(StringType::FormattedDate {
+                    range: Some(seen), ..
+                }) => range
+                    .as_ref()
+                    .is_none_or(|range| range.contains(seen.start())),
+                _ => false,
+            },
+            StringType::EpochSeconds { range } => match epoch_seconds(s) {
+                Some(StringType::EpochSeconds { range: Some(seen) }) => range
+                    .as_ref()
+                    .is_none_or(|range| range.contains(seen.start())),
+                _ => false,
+            },
+            StringType::Time { range, .. } => match time(s) {
+                Some(StringType::Time {
+                    range: Some(seen), ..
+                }) => range
+                    .as_ref()
+                    .is_none_or(|range| range.contains(seen.start())),
+                _ => false,
+            },
+            StringType::Duration { range } => match duration(s) {
+                Some(StringType::Duration { range: Some(seen) }) => range
+                    .as_ref()
+                    .is_none_or(|range| range.contains(seen.start())),
+                _ => false,
+            },
+            StringType::FilePath {
+                kind,
+                roots,
+                min_depth,
+                max_depth,
+                extensions,
+            } => split_path(s).is_some_and(|(seen, root, names)| {
+                seen == *kind
+                    && roots.contains_key(root)
+                    && in_range(names.len() - 1, Some(*min_depth), Some(*max_depth))
+                    && extensions.contains_key(extension(names.last().unwrap()))
+            }),
+            StringType::Text {
+                min_words,
+                max_words,
+            } => match text(s) {
+                Some(StringType::Text {
+                    min_words: words, ..
+                }) => in_range(words, Some(*min_words), Some(*max_words)),
+                _ => false,
+            },
+            StringType::UUID => uuid(s).is_some(),
+            StringType::Ulid | StringType::Ksuid | StringType::ObjectId => {
+                identifier(s).as_ref() == Some(string_type)
+            }
+            StringType::EmbeddedJson(schema) => match serde_json::from_str(s) {
+                Ok(document) => {
+                    if let Some(violation) = validate(&document, schema).first() {
+                        return Some(format!("embedded JSON does not conform: {}", violation));
+                    }
+                    true
+                }
+                Err(_) => false,
+            },
+            StringType::Jwt { claims } => match jwt_claims(s) {
+                Some(payload) => {
+                    if let Some(violation) = validate(&payload, claims).first() {
+                        return Some(format!("JWT claims do not conform: {}", violation));
+                    }
+                    true
+                }
+                None => false,
+            },
+            StringType::PhoneNumber { formats } => {
+                phone_format(s).is_some_and(|format| formats.contains_key(&format))
+            }
+            StringType::Semver {
+                min_major,
+                max_major,
+            } => match semver(s) {
+                Some(StringType::Semver {
+                    min_major: major, ..
+                }) => in_range(major, Some(*min_major), Some(*max_major)),
+                _ => false,
+            },
+            StringType::Base64 {
+                min_length,
+                max_length,
+            } => {
+                use base64::Engine;
+                base64::engine::general_purpose::STANDARD.decode(s).is_ok()
+                    && in_range(s.len(), Some(*min_length), Some(*max_length))
+            }
+            StringType::Email => email(s).is_some(),
+            StringType::Url => url_host(s) == Some(StringType::Url),
+            StringType::Hostname => url_host(s) == Some(StringType::Hostname),
+            StringType::Enum { variants } => variants.contains(s),
+            StringType::CurrencyCode { codes } => codes.contains(s),
+            StringType::Pattern(pattern) => pattern::is_match(pattern, s),
+            StringType::NonFiniteFloat => non_finite_float(s).is_some(),
+            StringType::Tally { format, .. } => return check_string(s, format),
+        };
+
+    if conforms {
+        None
+    } else {
+        Some(format!("{:?} is not a valid {}", s, string_type))
+    }
+}
+
+fn json_type(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    }
+}
+
+fn validate_inner(
+    value: &serde_json::Value,
+    schema: &SchemaState,
+    current_path: &str,
+    current_depth: usize,
+    overrides: Option<&OverrideNode>,
+    violations: &mut Vec<Violation>,
+) {
+    if overrides.is_some_and(|node| node.value.is_some()) {
+        // overridden values are pinned by the user and do not have to conform to the schema
+        return;
+    }
+
+    let mut violation = |message: String| {
+        violations.push(Violation {
+            path: current_path.to_owned(),
+            message,
+        })
+    };
+
+    match (schema, value) {
+        (SchemaState::Initial | SchemaState::Indefinite, _) => {}
+        (SchemaState::Null, serde_json::Value::Null) => {}
+        (SchemaState::Nullable(_), serde_json::Value::Null) => {}
+        (SchemaState::Nullable(inner), _) => validate_inner(
+            value,
+            inner,
+            current_path,
+            current_depth,
+            overrides,
+            violations,
+        ),
+        (SchemaState::String(string_type), serde_json::Value::String(s)) => {
+            if let Some(message) = check_string(s, string_type) {
+                violation(message);
+            }
+        }
+        (
+            SchemaState::Number(
+                number_type @ (NumberType::Integer { .. } | NumberType::BigInteger { .. }),
+            ),
+            serde_json::Value::Number(n),
+        ) => {
+            let (min, max) = number_type.integer_range().unwrap_or_default();
+            match integer_value(n) {
+                Some(n) if in_range(n, Some(min), Some(max)) => {}
+                Some(n) => violation(format!("{} is outside of the range {}-{}", n, min, max)),
+                None => violation(format!("{} is not an integer", n)),
+            }
+        }
+        (
+            SchemaState::Number(NumberType::Float {
+                non_finite: true, ..
+            }),
+            serde_json::Value::String(s),
+        ) if non_finite_float(s).is_some() => {}
+        (
+            SchemaState::Number(
+                number_type @ NumberType::Float {
+                    min,
+                    max,
+                    exclusive_min,
+                    exclusive_max,
+                    ..
+                },
+            ),
+            serde_json::Value::Number(n),
+        ) => {
+            let n = n.as_f64().unwrap_or(f64::NAN);
+            let (lowest, highest) = number_type.float_range().unwrap_or_default();
+            if !in_range(n, Some(*min), Some(*max)) {
+                violation(format!("{} is outside of the range {}-{}", n, min, max));
+            } else if *exclusive_min && n < lowest {
+                violation(format!("{} is not above the exclusive minimum {}", n, min));
+            } else if *exclusive_max && n > highest {
+                violation(format!("{} is not below the exclusive maximum {}", n, max));
+            }
+        }
+        (SchemaState::Number(NumberType::Enum { variants, .. }), serde_json::Value::Number(n)) => {
+            if !n.as_i64().is_some_and(|n| variants.contains(&n)) {
+                violation(format!("{} is not one of the variants of the enum", n));
+            }
+        }
+        (
+            SchemaState::Number(NumberType::Decimal { scale, min, max }),
+            serde_json::Value::Number(n),
+        ) => {
+            if decimal_places(n).is_none_or(|places| places > *scale) {
+                violation(format!("{} does not have at most {} decimals", n, scale));
+            }
+            let n = n.as_f64().unwrap_or(f64::NAN);
+            if !in_range(n, Some(*min), Some(*max)) {
+                violation(format!("{} is outside of the range {}-{}", n, min, max));
+            }
+        }
+        (SchemaState::Boolean, serde_json::Value::Bool(_)) => {}
+        (SchemaState::UnconstrainedArray, serde_json::Value::Array(_)) => {}
+        (SchemaState::UnconstrainedObject, serde_json::Value::Object(_)) => {}
+        (SchemaState::Const(expected), _) => {
+            if value != expected {
+                violation(format!("{} is not the constant {}", value, expected));
+            }
+        }
+        (
+            SchemaState::Array {
+                min_length,
+                max_length,
+                unique,
+                schema,
+            },
+            serde_json::Value::Array(items),
+        ) => {
+            // the length of an array at the root is determined by the requested number of
+            // elements, and arrays of unknown elements are always produced empty
+            let has_known_elements = !matches!(
+                schema.as_ref(),
+                SchemaState::Initial | SchemaState::Indefinite
+            );
+            if *unique
+                && items
+                    .iter()
+                    .enumerate()
+                    .any(|(idx, item)| items[..idx].contains(item))
+            {
+                violation("array has repeated elements".to_owned());
+            }
+            if current_depth > 0
+                && has_known_elements
+                && !in_range(items.len(), Some(*min_length), Some(*max_length))
+            {
+                violation(format!(
+                    "array of length {} is outside of the expected length range {}-{}",
+                    items.len(),
+                    min_length,
+                    max_length
+                ));
+            }
+
+            let element_overrides = overrides.and_then(|node| {
+                if current_depth == 0 {
+                    node.elements.as_deref().or(Some(node))
+                } else {
+                    node.elements.as_deref()
+                }
+            });
+            let elements_path = path::elements(current_path);
+            for item in items {
+                validate_inner(
+                    item,
+                    schema,
+                    &elements_path,
+                    current_depth + 1,
+                    element_overrides,
+                    violations,
+                );
+            }
+        }
+        (SchemaState::Tuple(elements), serde_json::Value::Array(items)) => {
+            if items.len() != elements.len() {
+                violation(format!(
+                    "tuple of length {} does not have the expected length {}",
+                    items.len(),
+                    elements.len()
+                ));
+            }
+            let element_overrides = overrides.and_then(|node| node.elements.as_deref());
+            for (idx, (item, element)) in items.iter().zip(elements).enumerate() {
+                validate_inner(
+                    item,
+                    element,
+                    &path::index(current_path, idx),
+                    current_depth + 1,
+                    element_overrides,
+                    violations,
+                );
+            }
+        }
+        (SchemaState::Union(branches), _) => {
+            let matches_any = branches.iter().any(|branch| {
+                let mut branch_violations = vec![];
+                validate_inner(
+                    value,
+                    branch,
+                    current_path,
+                    current_depth,
+                    overrides,
+                    &mut branch_violations,
+                );
+                branch_violations.is_empty()
+            });
+            if !matches_any {
+                violation(format!(
+                    "{} does not match any of the {} alternatives",
+                    json_type(value),
+                    branches.len()
+                ));
+            }
+        }
+        (SchemaState::Object { required, optional }, serde_json::Value::Object(map)) => {
+            for k in required.keys() {
+                if !map.contains_key(k) {
+                    violation(format!("required field {:?} is missing", k));
+                }
+            }
+            for (k, v) in map {
+                let field_path = path::key(current_path, k);
+                let field_overrides = overrides.and_then(|node| node.fields.get(k));
+                match required.get(k).or_else(|| optional.get(k)) {
+                    Some(field_schema) => validate_inner(
+                        v,
+                        field_schema,
+                        &field_path,
+                        current_depth + 1,
+                        field_overrides,
+                        violations,
+                    ),
+                    None => violations.push(Violation {
+                        path: field_path,
+                        message: "field is not part of the schema".to_owned(),
+                    }),
+                }
+            }
+        }
+        (
+            SchemaState::Map {
+                pattern,
+                min_length,
+                max_length,
+                schema,
+            },
+            serde_json::Value::Object(map),
+        ) => {
+            if !in_range(map.len(), Some(*min_length), Some(*max_length)) {
+                violation(format!(
+                    "object of {} keys is outside of the expected range {}-{}",
+                    map.len(),
+                    min_length,
+                    max_length
+                ));
+            }
+            for (k, v) in map {
+                let field_path = path::key(current_path, k);
+                if !pattern::is_match(pattern, k) {
+                    violations.push(Violation {
+                        path: field_path.clone(),
+                        message: format!("key does not match the pattern {:?}", pattern),
+                    });
+                }
+                let field_overrides = overrides.and_then(|node| node.fields.get(k));
+                validate_inner(
+                    v,
+                    schema,
+                    &field_path,
+                    current_depth + 1,
+                    field_overrides,
+                    violations,
+                );
+            }
+        }
+        _ => violation(format!("unexpected {}", json_type(value))),
+    }
+}
+
+/// Checks a JSON value against a schema, returning every way in which the value violates it.
+///
+/// The checks mirror what `produce` generates: an array at the root may have any length,
+/// as `produce` repeats its elements the requested number of times.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{validate, SchemaState, NumberType};
+/// use serde_json::json;
+///
+/// let schema = SchemaState::Number(NumberType::Integer { min: 0, max: 10 });
+///
+/// assert!(validate(&json!(5), &schema).is_empty());
+/// assert_eq!(validate(&json!(11), &schema)[0].message, "11 is outside of the range 0-10");
+/// ```
+pub fn validate(value: &serde_json::Value, schema: &SchemaState) -> Vec<Violation> {
+    let mut violations = vec![];
+    validate_inner(value, schema, "", 0, None, &mut violations);
+    violations
+}
+
+/// Checks a JSON value against a schema like `validate` does, skipping the values at the paths
+/// pinned by `overrides`.
+pub fn validate_with_overrides(
+    value: &serde_json::Value,
+    schema: &SchemaState,
+    overrides: &Overrides,
+) -> Vec<Violation> {
+    let mut violations = vec![];
+    validate_inner(value, schema, "", 0, Some(&overrides.root), &mut violations);
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema, produce_with_options, InferenceOptions, ProduceOptions};
+
+    #[test]
+    fn produced_data_conforms_to_inferred_schema() {
+        let input = json!([
+            {
+                "id": "988c2c6d-df1b-4bb9-b837-6ba706c0b4ad",
+                "email": "test@example.com",
+                "host": "example.com",
+                "url": "https://example.com/page",
+                "date": "2013-01-12",
+                "created": "2013-01-12T00:00:00.000Z",
+                "name": "Alice",
+                "code": "cus_123",
+                "score": 1.5,
+                "ratio": 0.25,
+                "tags": ["a", "b"],
+                "parent": null
+            },
+            {
+                "id": "1d83a00a-5c47-4e7a-a4e4-58cd5d8cd6cd",
+                "email": "other@example.com",
+                "host": "example.org",
+                "url": "https://example.org/other",
+                "date": "2020-03-04",
+                "created": "2020-03-04T10:00:00.000Z",
+                "code": "cus_4",
+                "score": 3,
+                "ratio": "NaN",
+                "tags": [],
+                "parent": {"name": "Bob"}
+            }
+        ]);
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema(input, &options);
+
+        for _ in 0..20 {
+            let produced = produce_with_options(&schema, &ProduceOptions::new().count(10)).unwrap();
+            assert_eq!(validate(&produced, &schema), vec![]);
+        }
+    }
+
+    #[test]
+    fn reports_violations_with_paths() {
+        let options = InferenceOptions {
+            enum_inference: None,
+            ..Default::default()
+        };
+        let schema = infer_schema(json!({"user": {"age": 30, "tags": ["a"]}}), &options);
+
+        assert_eq!(
+            validate(
+                &json!({"user": {"age": "thirty", "tags": ["a", 1], "extra": true}}),
+                &schema
+            ),
+            vec![
+                Violation {
+                    path: "user.age".to_owned(),
+                    message: "unexpected string".to_owned()
+                },
+                Violation {
+                    path: "user.tags".to_owned(),
+                    message: "array of length 2 is outside of the expected length range 1-1"
+                        .to_owned()
+                },
+                Violation {
+                    path: "user.tags[]".to_owned(),
+                    message: "unexpected number".to_owned()
+                },
+                Violation {
+                    path: "user.extra".to_owned(),
+                    message: "field is not part of the schema".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn does_not_bound_the_length_of_a_nullable_array_at_the_root() {
+        let schema = SchemaState::Nullable(Box::new(infer_schema(
+            json!([1, 2]),
+            &InferenceOptions::default(),
+        )));
+
+        assert_eq!(validate(&json!([1, 2, 1, 2, 1]), &schema), vec![]);
+    }
+}
@@ -0,0 +1,291 @@
+//! Relaxing the constraints inferred from values that were observed only once.
+
+use std::collections::HashSet;
+
+use crate::{infer::tuple_to_array, path, NumberType, SchemaState, StringType};
+
+/// The share by which the length of a string or array observed once is widened, either way.
+const LENGTH_WIDENING: f64 = 0.5;
+/// The share by which a number observed once is widened, either way.
+const RANGE_WIDENING: f64 = 0.2;
+
+fn count_observations(
+    value: &serde_json::Value,
+    current_path: &str,
+    counts: &mut std::collections::HashMap<String, usize>,
+) {
+    match value {
+        serde_json::Value::Null | serde_json::Value::Bool(_) => {}
+        serde_json::Value::String(_) | serde_json::Value::Number(_) => {
+            *counts.entry(current_path.to_owned()).or_default() += 1;
+        }
+        serde_json::Value::Array(items) => {
+            *counts.entry(current_path.to_owned()).or_default() += 1;
+            let elements_path = path::elements(current_path);
+            for item in items {
+                count_observations(item, &elements_path, counts);
+            }
+        }
+        serde_json::Value::Object(object) => {
+            for (k, v) in object {
+                count_observations(v, &path::key(current_path, k), counts);
+            }
+        }
+    }
+}
+
+pub(crate) fn singletons(records: &[serde_json::Value]) -> HashSet<String> {
+    let mut counts = std::collections::HashMap::new();
+    for record in records {
+        count_observations(record, "", &mut counts);
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count == 1)
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// Returns the paths of the strings, numbers and arrays that were observed exactly once across
+/// `records`, sorted by path. These are the values whose constraints are widened by
+/// `InferenceOptions::widen_singletons`.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::singleton_paths;
+/// use serde_json::json;
+///
+/// let records = vec![json!({ "id": 1, "note": "rare" }), json!({ "id": 2 })];
+///
+/// assert_eq!(singleton_paths(&records), vec!["note"]);
+/// ```
+pub fn singleton_paths(records: &[serde_json::Value]) -> Vec<String> {
+    let mut paths = singletons(records).into_iter().collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+fn widen_length(length: usize) -> (usize, usize) {
+    let delta = (length as f64 * LENGTH_WIDENING).ceil() as usize;
+    (length.saturating_sub(delta), length.saturating_add(delta))
+}
+
+/// Widens a number observed once by its share of `RANGE_WIDENING`, staying within finite values.
+fn widen_range(value: f64) -> (f64, f64) {
+    let delta = value.abs() * RANGE_WIDENING;
+    (
+        (value - delta).clamp(f64::MIN, f64::MAX),
+        (value + delta).clamp(f64::MIN, f64::MAX),
+    )
+}
+
+fn widen_inner(
+    schema: SchemaState,
+    singletons: &HashSet<String>,
+    current_path: &str,
+    max_samples: usize,
+) -> SchemaState {
+    let observed_once = singletons.contains(current_path);
+    match schema {
+        SchemaState::String(StringType::Unknown {
+            strings_seen,
+            chars_seen,
+            n_strings_seen,
+            distinct,
+            n_chars_seen,
+            min_length: Some(length),
+            max_length: Some(max_length),
+            prefix,
+            suffix,
+            structure,
+        }) if observed_once && length == max_length => {
+            let (min_length, max_length) = widen_length(length);
+            SchemaState::String(StringType::Unknown {
+                strings_seen,
+                chars_seen,
+                n_strings_seen,
+                distinct,
+                n_chars_seen,
+                min_length: Some(min_length),
+                max_length: Some(max_length),
+                prefix,
+                suffix,
+                structure,
+            })
+        }
+        SchemaState::Number(NumberType::Integer { min, max }) if observed_once && min == max => {
+            let delta = (min.unsigned_abs() as f64 * RANGE_WIDENING).ceil() as i64;
+            SchemaState::Number(NumberType::Integer {
+                min: min.saturating_sub(delta),
+                max: max.saturating_add(delta),
+            })
+        }
+        SchemaState::Number(NumberType::Float {
+            min,
+            max,
+            non_finite,
+            ..
+        }) if observed_once && min == max => {
+            let (min, max) = widen_range(min);
+            SchemaState::Number(NumberType::Float {
+                min,
+                max,
+                non_finite,
+                exclusive_min: false,
+                exclusive_max: false,
+            })
+        }
+        SchemaState::Number(NumberType::Decimal { scale, min, max })
+            if observed_once && min == max =>
+        {
+            let (min, max) = widen_range(min);
+            SchemaState::Number(NumberType::Decimal { scale, min, max })
+        }
+        SchemaState::Array {
+            min_length,
+            max_length,
+            unique,
+            schema,
+        } => {
+            let (min_length, max_length) = if observed_once && min_length == max_length {
+                widen_length(min_length)
+            } else {
+                (min_length, max_length)
+            };
+            SchemaState::Array {
+                min_length,
+                max_length,
+                unique,
+                schema: Box::new(widen_inner(
+                    *schema,
+                    singletons,
+                    &path::elements(current_path),
+                    max_samples,
+                )),
+            }
+        }
+        SchemaState::Object { required, optional } => {
+            let widen_fields = |fields: indexmap::IndexMap<String, SchemaState>| {
+                fields
+                    .into_iter()
+                    .map(|(k, v)| {
+                        let v =
+                            widen_inner(v, singletons, &path::key(current_path, &k), max_samples);
+                        (k, v)
+                    })
+                    .collect()
+            };
+            SchemaState::Object {
+                required: widen_fields(required),
+                optional: widen_fields(optional),
+            }
+        }
+        // a tuple observed once never showed that its length is fixed, so it is widened as an array
+        SchemaState::Tuple(elements) if observed_once => widen_inner(
+            tuple_to_array(elements, max_samples),
+            singletons,
+            current_path,
+            max_samples,
+        ),
+        SchemaState::Nullable(inner) => SchemaState::Nullable(Box::new(widen_inner(
+            *inner,
+            singletons,
+            current_path,
+            max_samples,
+        ))),
+        schema => schema,
+    }
+}
+
+/// Widens the constraints of the values at the given paths, which were observed only once:
+/// string and array lengths by 50%, and numbers by 20% of their value, either way.
+pub(crate) fn widen_singletons(
+    schema: SchemaState,
+    singletons: &HashSet<String>,
+    max_samples: usize,
+) -> SchemaState {
+    widen_inner(schema, singletons, "", max_samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{infer_schema_from_iter, InferenceOptions};
+
+    #[test]
+    fn widens_values_observed_once() {
+        let options = InferenceOptions {
+            widen_singletons: true,
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(
+            vec![
+                json!({"id": 1, "age": 30, "score": 2.5, "name": "abcd", "tags": ["x", "y"]}),
+                json!({"id": 2}),
+            ],
+            &options,
+        );
+        let SchemaState::Object { required, optional } = schema else {
+            panic!("expected object schema");
+        };
+
+        assert_eq!(
+            required["id"],
+            SchemaState::Number(NumberType::Integer { min: 1, max: 2 })
+        );
+        assert_eq!(
+            optional["age"],
+            SchemaState::Number(NumberType::Integer { min: 24, max: 36 })
+        );
+        assert_eq!(
+            optional["score"],
+            SchemaState::Number(NumberType::Float {
+                min: 2.0,
+                max: 3.0,
+                non_finite: false,
+                exclusive_min: false,
+                exclusive_max: false,
+            })
+        );
+        assert_eq!(optional["name"].to_string_pretty(), "string (2-6)");
+        assert!(matches!(
+            optional["tags"],
+            SchemaState::Array {
+                min_length: 1,
+                max_length: 3,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn keeps_widened_numbers_finite() {
+        let options = InferenceOptions {
+            widen_singletons: true,
+            ..Default::default()
+        };
+        let schema = infer_schema_from_iter(
+            vec![json!({"x": f64::MAX, "y": -f64::MAX}), json!({"z": 1})],
+            &options,
+        );
+        let SchemaState::Object { optional, .. } = schema else {
+            panic!("expected object schema");
+        };
+
+        for (field, expected) in [("x", f64::MAX), ("y", -f64::MAX)] {
+            let SchemaState::Number(NumberType::Float { min, max, .. }) = optional[field] else {
+                panic!("expected float schema");
+            };
+            assert!(min.is_finite() && max.is_finite(), "{}", field);
+            assert!(min <= expected && expected <= max, "{}", field);
+        }
+        let schema = SchemaState::Object {
+            required: Default::default(),
+            optional,
+        };
+        assert!(crate::produce_with_options(&schema, &crate::ProduceOptions::new()).is_ok());
+    }
+}
@@ -0,0 +1,449 @@
+//! Reading XML documents into JSON values that schemas are inferred from, and writing produced
+//! values as XML.
+
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+use quick_xml::events::Event;
+
+use crate::DrivelError;
+
+/// The key holding the text of elements that also have attributes or child elements.
+pub const TEXT_KEY: &str = "#text";
+
+/// The name of the element wrapping the records produced, when there are many of them.
+const RECORDS_ELEMENT: &str = "records";
+
+/// The name of the element holding a record that does not name its own element.
+const RECORD_ELEMENT: &str = "record";
+
+/// Options for how XML maps to JSON values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct XmlOptions {
+    /// The prefix of the keys holding the attributes of elements, e.g. `@` for `@id`.
+    pub attribute_prefix: String,
+}
+
+impl Default for XmlOptions {
+    fn default() -> Self {
+        Self {
+            attribute_prefix: "@".to_owned(),
+        }
+    }
+}
+
+/// An element of an XML document, as it was read.
+#[derive(Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+fn xml_error(err: impl std::fmt::Display) -> DrivelError {
+    DrivelError::InvalidXml(err.to_string())
+}
+
+fn start_element(start: &quick_xml::events::BytesStart) -> Result<Element, DrivelError> {
+    let attributes = start
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute.map_err(xml_error)?;
+            let name = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+            let value = attribute.unescape_value().map_err(xml_error)?.into_owned();
+            Ok((name, value))
+        })
+        .collect::<Result<_, DrivelError>>()?;
+    Ok(Element {
+        name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+        attributes,
+        ..Default::default()
+    })
+}
+
+/// Reads the root element of an XML document, along with everything within it.
+fn read_root(input: &str) -> Result<Element, DrivelError> {
+    let mut reader = quick_xml::Reader::from_str(input);
+    let mut open = Vec::<Element>::new();
+    let mut root = None;
+    let mut close = |element: Element, open: &mut Vec<Element>| match open.last_mut() {
+        Some(parent) => {
+            parent.children.push(element);
+            Ok(())
+        }
+        None if root.is_some() => Err(xml_error("the document has more than one root element")),
+        None => {
+            root = Some(element);
+            Ok(())
+        }
+    };
+    loop {
+        match reader.read_event().map_err(xml_error)? {
+            Event::Start(start) => open.push(start_element(&start)?),
+            Event::Empty(start) => close(start_element(&start)?, &mut open)?,
+            Event::End(_) => {
+                let element = open.pop().expect("quick-xml checks that end tags match");
+                close(element, &mut open)?;
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(xml_error)?;
+                match open.last_mut() {
+                    Some(element) => element.text.push_str(&text),
+                    None if text.trim().is_empty() => {}
+                    None => return Err(xml_error("text outside of the root element")),
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = open.last_mut() {
+                    element
+                        .text
+                        .push_str(&String::from_utf8_lossy(&data.into_inner()));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if let Some(element) = open.last() {
+        return Err(xml_error(format!("<{}> is not closed", element.name)));
+    }
+    root.ok_or_else(|| xml_error("the document has no root element"))
+}
+
+/// Collects the paths of the elements that are repeated within any of their parents.
+fn repeated_paths(element: &Element, path: &str, repeated: &mut HashSet<String>) {
+    let mut seen = HashSet::new();
+    for child in &element.children {
+        let child_path = format!("{}/{}", path, child.name);
+        if !seen.insert(&child.name) {
+            repeated.insert(child_path.clone());
+        }
+        repeated_paths(child, &child_path, repeated);
+    }
+}
+
+/// Returns text as the JSON value it reads as: a boolean, a number, or else a string.
+fn scalar(text: &str) -> serde_json::Value {
+    match text {
+        "true" => serde_json::Value::Bool(true),
+        "false" => serde_json::Value::Bool(false),
+        // JSON numbers have no leading zeros, so codes like `007` are kept as strings
+        text => serde_json::from_str::<serde_json::Number>(text)
+            .map(serde_json::Value::Number)
+            .unwrap_or_else(|_| serde_json::Value::String(text.to_owned())),
+    }
+}
+
+fn element_value(
+    element: Element,
+    path: &str,
+    repeated: &HashSet<String>,
+    options: &XmlOptions,
+) -> serde_json::Value {
+    let text = element.text.trim();
+    if element.attributes.is_empty() && element.children.is_empty() {
+        return if text.is_empty() {
+            serde_json::Value::Null
+        } else {
+            scalar(text)
+        };
+    }
+
+    let mut object = serde_json::Map::new();
+    for (name, value) in &element.attributes {
+        object.insert(
+            format!("{}{}", options.attribute_prefix, name),
+            scalar(value),
+        );
+    }
+    if !text.is_empty() {
+        object.insert(TEXT_KEY.to_owned(), scalar(text));
+    }
+    let mut children = IndexMap::<String, Vec<Element>>::new();
+    for child in element.children {
+        children.entry(child.name.clone()).or_default().push(child);
+    }
+    for (name, elements) in children {
+        let child_path = format!("{}/{}", path, name);
+        let mut values = elements
+            .into_iter()
+            .map(|child| element_value(child, &child_path, repeated, options))
+            .collect::<Vec<_>>();
+        // elements repeated anywhere are arrays everywhere, so that records agree on their type
+        let value = if repeated.contains(&child_path) {
+            serde_json::Value::Array(values)
+        } else {
+            values.remove(0)
+        };
+        object.insert(name, value);
+    }
+    serde_json::Value::Object(object)
+}
+
+/// Parses an XML document into a JSON object holding its root element, like
+/// `{ "orders": { "order": [...] } }`.
+///
+/// Attributes become fields named with `options.attribute_prefix`, and elements become fields
+/// holding their text, or objects when they have attributes or child elements, with their text
+/// under `#text`. Elements that are repeated anywhere in the document become arrays wherever
+/// they occur. Text that reads as a boolean or a number becomes one, and empty elements are
+/// null.
+pub(crate) fn parse_xml(
+    input: &str,
+    options: &XmlOptions,
+) -> Result<serde_json::Value, DrivelError> {
+    let root = read_root(input)?;
+    let mut repeated = HashSet::new();
+    repeated_paths(&root, &root.name, &mut repeated);
+    let name = root.name.clone();
+    let path = name.clone();
+    let mut document = serde_json::Map::new();
+    document.insert(name, element_value(root, &path, &repeated, options));
+    Ok(serde_json::Value::Object(document))
+}
+
+/// Turns a key into the name of an element or attribute, which may only hold letters, digits
+/// and `_-.:`, and must start with a letter or `_`.
+fn xml_name(key: &str) -> String {
+    let name = key
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        name
+    } else {
+        format!("_{}", name)
+    }
+}
+
+fn text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+fn write_element(
+    name: &str,
+    value: &serde_json::Value,
+    depth: usize,
+    options: &XmlOptions,
+    out: &mut String,
+) {
+    let indent = "  ".repeat(depth);
+    let name = xml_name(name);
+    match value {
+        serde_json::Value::Null => out.push_str(&format!("{}<{}/>\n", indent, name)),
+        // arrays are written as repeated elements
+        serde_json::Value::Array(values) => {
+            for value in values {
+                write_element(&name, value, depth, options, out);
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            let mut attributes = String::new();
+            let mut text = None;
+            let mut children = vec![];
+            for (key, value) in fields {
+                let attribute = (!options.attribute_prefix.is_empty())
+                    .then(|| key.strip_prefix(&options.attribute_prefix))
+                    .flatten();
+                match attribute {
+                    Some(_) if value.is_null() => {}
+                    Some(attribute) => attributes.push_str(&format!(
+                        " {}=\"{}\"",
+                        xml_name(attribute),
+                        quick_xml::escape::escape(self::text(value))
+                    )),
+                    None if key == TEXT_KEY => text = Some(self::text(value)),
+                    None => children.push((key, value)),
+                }
+            }
+            let text = text.map(|text| quick_xml::escape::escape(text).into_owned());
+            match (text, children.is_empty()) {
+                (None, true) => out.push_str(&format!("{}<{}{}/>\n", indent, name, attributes)),
+                (Some(text), true) => out.push_str(&format!(
+                    "{}<{}{}>{}</{}>\n",
+                    indent, name, attributes, text, name
+                )),
+                (text, false) => {
+                    out.push_str(&format!("{}<{}{}>\n", indent, name, attributes));
+                    if let Some(text) = text {
+                        out.push_str(&format!("{}  {}\n", indent, text));
+                    }
+                    for (key, value) in children {
+                        write_element(key, value, depth + 1, options, out);
+                    }
+                    out.push_str(&format!("{}</{}>\n", indent, name));
+                }
+            }
+        }
+        value => out.push_str(&format!(
+            "{}<{}>{}</{}>\n",
+            indent,
+            name,
+            quick_xml::escape::escape(text(value)),
+            name
+        )),
+    }
+}
+
+/// Returns the name of the element that a record is written as, and the value of that element:
+/// a record holding a single object, as parsed from XML, is written as that element.
+fn record_element(record: &serde_json::Value) -> (&str, &serde_json::Value) {
+    match record {
+        serde_json::Value::Object(fields) if fields.len() == 1 => {
+            let (key, value) = fields.iter().next().unwrap();
+            if value.is_object() {
+                (key, value)
+            } else {
+                (RECORD_ELEMENT, record)
+            }
+        }
+        record => (RECORD_ELEMENT, record),
+    }
+}
+
+/// Writes a value as an XML document, the other way around from how XML is read: fields
+/// prefixed with `options.attribute_prefix` become attributes, `#text` the text of an element,
+/// and arrays repeated elements.
+///
+/// An object with a single field holding an object, as parsed from XML, is written as that
+/// element; other values
+/// are written as a `record` element. An array of records is written as a `records` element
+/// holding each of them.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{to_xml, XmlOptions};
+/// use serde_json::json;
+///
+/// let value = json!({ "order": { "@id": 7, "item": ["a", "b"], "note": null } });
+///
+/// assert_eq!(
+///     to_xml(&value, &XmlOptions::default()),
+///     r#"<?xml version="1.0" encoding="UTF-8"?>
+/// <order id="7">
+///   <item>a</item>
+///   <item>b</item>
+///   <note/>
+/// </order>
+/// "#
+/// );
+/// ```
+pub fn to_xml(value: &serde_json::Value, options: &XmlOptions) -> String {
+    let mut out = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_owned();
+    match value {
+        serde_json::Value::Array(records) => {
+            out.push_str(&format!("<{}>\n", RECORDS_ELEMENT));
+            for record in records {
+                let (name, value) = record_element(record);
+                write_element(name, value, 1, options, &mut out);
+            }
+            out.push_str(&format!("</{}>\n", RECORDS_ELEMENT));
+        }
+        record => {
+            let (name, value) = record_element(record);
+            write_element(name, value, 0, options, &mut out);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const ORDERS: &str = r#"<?xml version="1.0"?>
+<orders>
+  <order id="1" status="open">
+    <item sku="A-1">Widget &amp; co</item>
+    <item sku="B-2">Gadget</item>
+    <zip>01234</zip>
+    <total>12.50</total>
+    <gift>false</gift>
+    <note/>
+  </order>
+  <order id="2">
+    <item sku="C-3"><![CDATA[<Gizmo>]]></item>
+    <zip>98765</zip>
+    <total>3</total>
+  </order>
+</orders>
+"#;
+
+    #[test]
+    fn parses_attributes_text_and_repeated_elements() {
+        assert_eq!(
+            parse_xml(ORDERS, &XmlOptions::default()).unwrap(),
+            json!({
+                "orders": {
+                    "order": [
+                        {
+                            "@id": 1,
+                            "@status": "open",
+                            "item": [
+                                { "@sku": "A-1", "#text": "Widget & co" },
+                                { "@sku": "B-2", "#text": "Gadget" }
+                            ],
+                            "zip": "01234",
+                            "total": 12.50,
+                            "gift": false,
+                            "note": null
+                        },
+                        {
+                            "@id": 2,
+                            // repeated in the other order, so an array here too
+                            "item": [{ "@sku": "C-3", "#text": "<Gizmo>" }],
+                            "zip": 98765,
+                            "total": 3
+                        }
+                    ]
+                }
+            })
+        );
+
+        let options = XmlOptions {
+            attribute_prefix: "_".to_owned(),
+        };
+        let document = parse_xml("<a b=\"c\"/>", &options).unwrap();
+        assert_eq!(document, json!({ "a": { "_b": "c" } }));
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        for input in ["<a>", "<a></b>", "<a/><b/>", "", "text"] {
+            assert!(
+                parse_xml(input, &XmlOptions::default()).is_err(),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let options = XmlOptions::default();
+        let document = parse_xml(ORDERS, &options).unwrap();
+        assert_eq!(
+            parse_xml(&to_xml(&document, &options), &options).unwrap(),
+            document
+        );
+
+        assert_eq!(
+            to_xml(&json!([{ "id": 1 }, { "id": 2 }]), &options),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<records>\n  <record>\n    <id>1</id>\n  </record>\n  <record>\n    <id>2</id>\n  </record>\n</records>\n"
+        );
+    }
+}
@@ -0,0 +1,370 @@
+//! Generating Zod validators, written in TypeScript, from schemas.
+
+use crate::{infer::integer_value, NumberType, SchemaState, StringType};
+
+/// The name of the validator of each record.
+const ROOT_VALIDATOR: &str = "Record";
+
+/// Returns a string as a TypeScript string literal.
+fn string_literal(s: &str) -> String {
+    // JSON strings are valid TypeScript strings
+    serde_json::Value::from(s).to_string()
+}
+
+/// Returns a key as a TypeScript property name, quoted unless it is a valid identifier.
+fn property_name(key: &str) -> String {
+    let is_identifier = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if is_identifier {
+        key.to_owned()
+    } else {
+        string_literal(key)
+    }
+}
+
+/// Returns the checks of a length, like `.min(1).max(3)`.
+fn length_checks(min: Option<usize>, max: Option<usize>) -> String {
+    let mut checks = String::new();
+    if let Some(min) = min {
+        checks.push_str(&format!(".min({})", min));
+    }
+    if let Some(max) = max {
+        checks.push_str(&format!(".max({})", max));
+    }
+    checks
+}
+
+/// Returns the checks of a range of numbers, leaving out bounds that are not finite.
+fn range_checks(min: f64, max: f64) -> String {
+    exclusive_range_checks(min, max, false, false)
+}
+
+/// Returns the checks of a range whose bounds may be exclusive, using `gt` and `lt` for them.
+fn exclusive_range_checks(min: f64, max: f64, exclusive_min: bool, exclusive_max: bool) -> String {
+    let mut checks = String::new();
+    if min.is_finite() {
+        let check = if exclusive_min { "gt" } else { "min" };
+        checks.push_str(&format!(".{}({})", check, min));
+    }
+    if max.is_finite() {
+        let check = if exclusive_max { "lt" } else { "max" };
+        checks.push_str(&format!(".{}({})", check, max));
+    }
+    checks
+}
+
+/// Returns `z.enum` of strings, sorted so that the validator is the same across runs.
+fn string_enum<'a>(variants: impl Iterator<Item = &'a String>) -> String {
+    let mut variants = variants
+        .map(|variant| string_literal(variant))
+        .collect::<Vec<_>>();
+    variants.sort();
+    format!("z.enum([{}])", variants.join(", "))
+}
+
+fn union(variants: Vec<String>) -> String {
+    match variants.len() {
+        0 => "z.never()".to_owned(),
+        1 => variants.into_iter().next().unwrap(),
+        _ => format!("z.union([{}])", variants.join(", ")),
+    }
+}
+
+fn string_validator(string_type: &StringType) -> String {
+    match string_type {
+        StringType::Unknown {
+            min_length,
+            max_length,
+            ..
+        } => format!("z.string(){}", length_checks(*min_length, *max_length)),
+        StringType::IsoDate { .. } => "z.string().date()".to_owned(),
+        StringType::DateTimeISO8601 { .. } => "z.string().datetime({ offset: true })".to_owned(),
+        StringType::UUID => "z.string().uuid()".to_owned(),
+        StringType::Ulid => "z.string().ulid()".to_owned(),
+        StringType::Email => "z.string().email()".to_owned(),
+        StringType::Url => "z.string().url()".to_owned(),
+        StringType::Base64 {
+            min_length,
+            max_length,
+        } => format!(
+            "z.string().base64(){}",
+            length_checks(Some(*min_length), Some(*max_length))
+        ),
+        StringType::Enum { variants } => string_enum(variants.iter()),
+        StringType::CurrencyCode { codes } => string_enum(codes.iter()),
+        StringType::Pattern(pattern) => {
+            format!("z.string().regex(new RegExp({}))", string_literal(pattern))
+        }
+        StringType::NonFiniteFloat => "z.enum([\"-Infinity\", \"Infinity\", \"NaN\"])".to_owned(),
+        StringType::Tally { format, .. } => string_validator(format),
+        StringType::FormattedDate { format, .. } => {
+            string_validator(&StringType::Pattern(format.pattern().to_owned()))
+        }
+        StringType::EpochSeconds { .. } => "z.string().regex(/^[0-9]{10}$/)".to_owned(),
+        // zod's times have seconds
+        StringType::Time { seconds: true, .. } => "z.string().time()".to_owned(),
+        StringType::Time { seconds: false, .. } => {
+            "z.string().regex(/^[0-9]{2}:[0-9]{2}$/)".to_owned()
+        }
+        StringType::Duration { .. } => "z.string().duration()".to_owned(),
+        StringType::FilePath { kind, .. } => {
+            string_validator(&StringType::Pattern(kind.pattern().to_owned()))
+        }
+        StringType::DateTimeRFC2822 { .. }
+        | StringType::Ksuid
+        | StringType::ObjectId
+        | StringType::PhoneNumber { .. }
+        | StringType::Semver { .. }
+        | StringType::EmbeddedJson(_)
+        | StringType::Jwt { .. }
+        | StringType::Text { .. }
+        | StringType::Hostname => "z.string()".to_owned(),
+    }
+}
+
+fn number_validator(number_type: &NumberType) -> String {
+    match number_type {
+        NumberType::Integer { min, max } => {
+            format!("z.number().int().min({}).max({})", min, max)
+        }
+        // bounds beyond the range of safe integers would be rounded by TypeScript
+        NumberType::BigInteger { .. } => "z.number().int()".to_owned(),
+        NumberType::Enum { variants, .. } => {
+            let mut variants = variants.iter().collect::<Vec<_>>();
+            variants.sort();
+            union(
+                variants
+                    .into_iter()
+                    .map(|n| format!("z.literal({})", n))
+                    .collect(),
+            )
+        }
+        NumberType::Float {
+            min,
+            max,
+            non_finite,
+            exclusive_min,
+            exclusive_max,
+        } => {
+            let number = format!(
+                "z.number(){}",
+                exclusive_range_checks(*min, *max, *exclusive_min, *exclusive_max)
+            );
+            if *non_finite {
+                union(vec![number, string_validator(&StringType::NonFiniteFloat)])
+            } else {
+                number
+            }
+        }
+        NumberType::Decimal { min, max, .. } => format!("z.number(){}", range_checks(*min, *max)),
+    }
+}
+
+fn validator(schema: &SchemaState, indent: usize) -> String {
+    match schema {
+        SchemaState::Initial | SchemaState::Indefinite => "z.unknown()".to_owned(),
+        SchemaState::Null | SchemaState::Const(serde_json::Value::Null) => "z.null()".to_owned(),
+        SchemaState::Nullable(inner) => format!("{}.nullable()", validator(inner, indent)),
+        SchemaState::String(string_type) => string_validator(string_type),
+        SchemaState::Number(number_type) => number_validator(number_type),
+        SchemaState::Boolean => "z.boolean()".to_owned(),
+        SchemaState::Array {
+            min_length,
+            max_length,
+            unique,
+            schema,
+        } => {
+            let array = format!(
+                "z.array({}){}",
+                validator(schema, indent),
+                length_checks(Some(*min_length), Some(*max_length))
+            );
+            if *unique {
+                // like uniqueItems, although objects and arrays are compared by reference
+                format!(
+                    "{}.refine((items) => new Set(items).size === items.length, {{ message: \"items must be unique\" }})",
+                    array
+                )
+            } else {
+                array
+            }
+        }
+        SchemaState::Tuple(elements) => format!(
+            "z.tuple([{}])",
+            elements
+                .iter()
+                .map(|element| validator(element, indent))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        SchemaState::Union(variants) => union(
+            variants
+                .iter()
+                .map(|variant| validator(variant, indent))
+                .collect(),
+        ),
+        SchemaState::UnconstrainedArray => "z.array(z.unknown())".to_owned(),
+        SchemaState::Object { required, optional }
+            if required.is_empty() && optional.is_empty() =>
+        {
+            "z.object({})".to_owned()
+        }
+        SchemaState::Object { required, optional } => {
+            let padding = "  ".repeat(indent + 1);
+            let properties = required
+                .iter()
+                .map(|(k, v)| (k, validator(v, indent + 1)))
+                .chain(
+                    optional
+                        .iter()
+                        .map(|(k, v)| (k, format!("{}.optional()", validator(v, indent + 1)))),
+                )
+                .map(|(k, v)| format!("{}{}: {},\n", padding, property_name(k), v))
+                .collect::<String>();
+            format!("z.object({{\n{}{}}})", properties, "  ".repeat(indent))
+        }
+        SchemaState::UnconstrainedObject => "z.record(z.unknown())".to_owned(),
+        SchemaState::Map {
+            pattern, schema, ..
+        } => format!(
+            "z.record({}, {})",
+            string_validator(&StringType::Pattern(pattern.clone())),
+            validator(schema, indent)
+        ),
+        SchemaState::Const(value @ serde_json::Value::Number(n)) => match integer_value(n) {
+            // integers beyond the range of safe integers would be rounded by TypeScript
+            Some(n) if n.unsigned_abs() > 1 << 53 => "z.number().int()".to_owned(),
+            _ => format!("z.literal({})", value),
+        },
+        SchemaState::Const(value @ (serde_json::Value::Bool(_) | serde_json::Value::String(_))) => {
+            format!("z.literal({})", value)
+        }
+        SchemaState::Const(serde_json::Value::Array(_) | serde_json::Value::Object(_)) => {
+            "z.unknown()".to_owned()
+        }
+    }
+}
+
+/// Generates a TypeScript file declaring a [Zod](https://zod.dev) validator of each record,
+/// named `Record`, along with its type: the elements of an array at the root, or the root
+/// itself.
+///
+/// Objects become `z.object`s, with `.optional()` fields, and nullable values are
+/// `.nullable()`. String enums become `z.enum`s, and UUIDs, emails, URLs and ISO 8601 dates
+/// and datetimes are checked as such. The lengths of strings and arrays and the ranges of
+/// numbers seen become `.min()` and `.max()` checks.
+///
+/// # Examples
+///
+/// ```
+/// use drivel::{infer_schema, to_zod, InferenceOptions};
+/// use serde_json::json;
+///
+/// let schema = infer_schema(
+///     json!({ "id": 1, "email": "alice@example.com", "tags": ["a"] }),
+///     &InferenceOptions::default(),
+/// );
+///
+/// assert_eq!(
+///     to_zod(&schema),
+///     r#"import { z } from "zod";
+///
+/// export const Record = z.object({
+///   id: z.number().int().min(1).max(1),
+///   email: z.string().email(),
+///   tags: z.array(z.string().min(1).max(1)).min(1).max(1),
+/// });
+///
+/// export type Record = z.infer<typeof Record>;
+/// "#
+/// );
+/// ```
+pub fn to_zod(schema: &SchemaState) -> String {
+    let record = match schema {
+        SchemaState::Array { schema, .. } => schema,
+        schema => schema,
+    };
+    format!(
+        "import {{ z }} from \"zod\";\n\nexport const {name} = {};\n\nexport type {name} = z.infer<typeof {name}>;\n",
+        validator(record, 0),
+        name = ROOT_VALIDATOR
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use indexmap::IndexMap;
+
+    use super::*;
+    use crate::DistinctCount;
+
+    #[test]
+    fn generates_nested_optional_and_nullable_validators() {
+        let schema = SchemaState::Object {
+            required: IndexMap::from_iter([
+                ("id".to_owned(), SchemaState::String(StringType::UUID)),
+                (
+                    "status".to_owned(),
+                    SchemaState::String(StringType::Enum {
+                        variants: HashSet::from_iter(["open".to_owned(), "closed".to_owned()]),
+                    }),
+                ),
+                (
+                    "shipping-address".to_owned(),
+                    SchemaState::Nullable(Box::new(SchemaState::Object {
+                        required: IndexMap::from_iter([(
+                            "city".to_owned(),
+                            SchemaState::String(StringType::Unknown {
+                                strings_seen: vec![],
+                                chars_seen: HashMap::new(),
+                                n_strings_seen: 0,
+                                distinct: DistinctCount::new(),
+                                n_chars_seen: 0,
+                                min_length: Some(3),
+                                max_length: Some(12),
+                                prefix: String::new(),
+                                suffix: String::new(),
+                                structure: None,
+                            }),
+                        )]),
+                        optional: IndexMap::new(),
+                    })),
+                ),
+            ]),
+            optional: IndexMap::from_iter([(
+                "score".to_owned(),
+                SchemaState::Number(NumberType::Float {
+                    min: 0.5,
+                    max: 9.75,
+                    non_finite: false,
+                    exclusive_min: false,
+                    exclusive_max: false,
+                }),
+            )]),
+        };
+
+        assert_eq!(
+            to_zod(&schema),
+            r#"import { z } from "zod";
+
+export const Record = z.object({
+  id: z.string().uuid(),
+  status: z.enum(["closed", "open"]),
+  "shipping-address": z.object({
+    city: z.string().min(3).max(12),
+  }).nullable(),
+  score: z.number().min(0.5).max(9.75).optional(),
+});
+
+export type Record = z.infer<typeof Record>;
+"#
+        );
+    }
+}